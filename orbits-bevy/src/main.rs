@@ -0,0 +1,238 @@
+//! The same piece drawn with bevy instead of nannou, sharing the simulation and the shapes of its
+//! trails through orbits-core, to compare the two renderers and reach the bevy ecosystem.
+
+use std::path::Path;
+
+use bevy::asset::RenderAssetUsages;
+use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use nannou_core::color as nannou;
+use nannou_core::rand::random;
+use orbits_core::orbital_radius;
+use orbits_core::params::Param;
+use orbits_core::plugins;
+use orbits_core::simulation::Simulation;
+use orbits_core::trails::{self, Line};
+
+/// Width and height of the world kept in view, in orbital radii, with a margin around the average
+/// orbit.
+const VIEW_SIZE: f32 = 2.2;
+
+/// Everything being simulated, stepped once a frame.
+#[derive(Resource, Deref, DerefMut)]
+struct Sim(Simulation);
+
+/// The mesh every trail is drawn into, rebuilt each frame.
+#[derive(Component)]
+struct Trails;
+
+/// The average orbit, a circle.
+#[derive(Component)]
+struct Orbit;
+
+/// One of the central bodies, or the halo around it.
+#[derive(Component)]
+struct Body {
+    index: usize,
+    halo: bool,
+}
+
+fn main() {
+    if let Err(err) = plugins::load(Path::new(plugins::PLUGINS_DIR)) {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: "trynannou-bevy".into(),
+                ..default()
+            }),
+            ..default()
+        }))
+        .add_plugins((FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin::default()))
+        .insert_resource(Sim(Simulation::new(random())))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (reseed, step, draw).chain())
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    sim: Res<Sim>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.spawn((
+        Camera2d,
+        OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {
+                min_width: VIEW_SIZE * orbital_radius(),
+                min_height: VIEW_SIZE * orbital_radius(),
+            },
+            ..OrthographicProjection::default_2d()
+        },
+    ));
+
+    let circle = meshes.add(Circle::new(1.0));
+    commands.spawn((
+        Orbit,
+        Mesh2d(circle.clone()),
+        MeshMaterial2d(materials.add(ColorMaterial::default())),
+        Transform::from_scale(Vec3::splat(orbital_radius())),
+    ));
+    commands.spawn((
+        Trails,
+        Mesh2d(meshes.add(trail_mesh(&sim))),
+        MeshMaterial2d(materials.add(ColorMaterial::default())),
+        Transform::from_xyz(0.0, 0.0, 1.0),
+    ));
+    // as in trynannou, a lone central body is left undrawn
+    let bodies = sim.bodies();
+    if bodies.len() >= 2 {
+        for index in 0..bodies.len() {
+            for halo in [true, false] {
+                commands.spawn((
+                    Body { index, halo },
+                    Mesh2d(circle.clone()),
+                    MeshMaterial2d(materials.add(ColorMaterial::default())),
+                ));
+            }
+        }
+    }
+}
+
+/// Start a new generation fading in when R is pressed.
+fn reseed(keys: Res<ButtonInput<KeyCode>>, mut sim: ResMut<Sim>) {
+    if keys.just_pressed(KeyCode::KeyR) {
+        sim.reseed(random());
+    }
+}
+
+/// Advance the simulation by the frame's wall-clock time, slowed by bullet time.
+fn step(time: Res<Time>, mut sim: ResMut<Sim>) {
+    sim.step(time.delta_secs());
+}
+
+/// Bring the meshes and colors in the world up to date with the simulation.
+fn draw(
+    sim: Res<Sim>,
+    mut clear: ResMut<ClearColor>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    trails: Query<&Mesh2d, With<Trails>>,
+    orbit: Query<&MeshMaterial2d<ColorMaterial>, With<Orbit>>,
+    mut bodies: Query<(&Body, &MeshMaterial2d<ColorMaterial>, &mut Transform)>,
+) {
+    let top = sim.current();
+    clear.0 = color(top.background, 1.0);
+    for Mesh2d(mesh) in &trails {
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = trail_mesh(&sim);
+        }
+    }
+    for MeshMaterial2d(material) in &orbit {
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color(top.circle_color, 1.0);
+        }
+    }
+
+    let positions = sim.bodies();
+    let total: f32 = positions.iter().map(|body| body.gm).sum();
+    for (body, MeshMaterial2d(material), mut transform) in &mut bodies {
+        let position = &positions[body.index];
+        let radius = 40.0 * (position.gm / total).sqrt();
+        let (radius, alpha, z) = if body.halo {
+            (radius * 1.4, 0.2, 2.0)
+        } else {
+            (radius, 1.0, 3.0)
+        };
+        *transform =
+            Transform::from_xyz(position.pos.x, position.pos.y, z).with_scale(Vec3::splat(radius));
+        if let Some(material) = materials.get_mut(material) {
+            material.color = color(top.body_color, alpha);
+        }
+    }
+}
+
+/// Every generation's trails as one mesh of colored triangles, the current generation on top.
+fn trail_mesh(sim: &Sim) -> Mesh {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    let highlight = sim.params.get(Param::Highlight);
+    let length = sim.params.get(Param::TrailLength);
+    let current = sim.generations.len() - 1;
+    for (i, generation) in sim.generations.iter().enumerate() {
+        let opacity = if i == current {
+            1.0
+        } else {
+            generation.opacity
+        };
+        let opacity = opacity * sim.params.get(Param::TrailOpacity);
+
+        let ribbons = trails::ribbons(generation, opacity, highlight, 0.0, length);
+        let base = positions.len();
+        for (pos, hsla) in ribbons.vertices {
+            positions.push([pos.x, pos.y, 0.0]);
+            colors.push(linear(hsla));
+        }
+        indices.extend(ribbons.indices.iter().map(|&i| (base + i) as u32));
+
+        for line in trails::highlights(generation, opacity, highlight, 0.0, length) {
+            stroke(&line, &mut positions, &mut colors, &mut indices);
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+/// Append a line as a quad along each of its segments, there being no polylines in bevy's meshes.
+fn stroke(
+    line: &Line,
+    positions: &mut Vec<[f32; 3]>,
+    colors: &mut Vec<[f32; 4]>,
+    indices: &mut Vec<u32>,
+) {
+    for segment in line.points.windows(2) {
+        let [(a, color_a), (b, color_b)] = segment else {
+            unreachable!()
+        };
+        let side = (*b - *a).perp().normalize_or_zero() * line.width / 2.0;
+        let base = positions.len() as u32;
+        for (pos, hsla) in [
+            (*a - side, color_a),
+            (*a + side, color_a),
+            (*b + side, color_b),
+            (*b - side, color_b),
+        ] {
+            positions.push([pos.x, pos.y, 0.0]);
+            colors.push(linear(*hsla));
+        }
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+    }
+}
+
+/// A nannou color as bevy's, which has its own color types.
+fn color(hsl: nannou::Hsl, alpha: f32) -> Color {
+    Color::hsla(
+        hsl.hue.to_positive_degrees(),
+        hsl.saturation,
+        hsl.lightness,
+        alpha,
+    )
+}
+
+/// A nannou color as the linear components bevy expects of vertex colors.
+fn linear(hsla: nannou::Hsla) -> [f32; 4] {
+    color(hsla.color, hsla.alpha).to_linear().to_f32_array()
+}
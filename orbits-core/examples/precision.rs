@@ -0,0 +1,90 @@
+//! Times stepping a swarm in single precision against double, and how far each lets its energy
+//! drift, for picking the `precision` of `[simulation]`. Run it with
+//! `cargo run --release --example precision -- [seconds] [particles]`, for 600 simulated seconds
+//! of 64 particles by default.
+
+use std::time::Instant;
+
+use nannou_core::prelude::*;
+use nannou_core::rand::rngs::StdRng;
+use nannou_core::rand::SeedableRng;
+use orbits_core::generation::TICK_SECONDS;
+use orbits_core::init::Preset;
+use orbits_core::physics::{self, Body, GravityLaw, Particle};
+use orbits_core::precise::{self, Precise};
+use orbits_core::settings::{self, Settings};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut arg = |default: f32| args.next().map_or(default, |arg| arg.parse().unwrap());
+    let seconds = arg(600.0);
+    let count = arg(64.0) as usize;
+    settings::set(Settings {
+        gravity_law: GravityLaw::Newtonian,
+        ..Settings::default()
+    })
+    .unwrap();
+    let scheme = settings::get().integrator;
+    let gm = physics::gm(0.0);
+    let bodies = [Body {
+        pos: Point2::ZERO,
+        gm,
+        law: GravityLaw::Newtonian,
+    }];
+    let start = Preset::ResonantChain.generate(count, gm, 0.0, &mut StdRng::seed_from_u64(0));
+    let steps = (seconds / TICK_SECONDS) as usize;
+    let energy = |pos: DVec2, vel: DVec2| vel.length_squared() / 2.0 - gm as f64 / pos.length();
+    println!("{count} particles, {seconds} seconds in {steps} steps of {scheme:?}");
+
+    let mut single = start.clone();
+    let timer = Instant::now();
+    for particle in &mut single {
+        for _ in 0..steps {
+            scheme.advance(
+                &mut particle.pos,
+                &mut particle.vel,
+                TICK_SECONDS,
+                &|pos, _| physics::acceleration_at(pos, &bodies),
+            );
+        }
+    }
+    let single_time = timer.elapsed();
+    let single: Vec<Precise> = single.iter().map(Precise::new).collect();
+
+    let mut double: Vec<Precise> = start.iter().map(Precise::new).collect();
+    let timer = Instant::now();
+    for particle in &mut double {
+        for _ in 0..steps {
+            scheme.advance(
+                &mut particle.pos,
+                &mut particle.vel,
+                TICK_SECONDS as f64,
+                &|pos, _| precise::acceleration_at(pos, &bodies),
+            );
+        }
+    }
+    let double_time = timer.elapsed();
+
+    // how far, on average, each particle's energy ended up from where it started, as a share of it
+    let drift = |end: &[Precise]| {
+        let drifts = start.iter().zip(end).map(|(start, end): (&Particle, _)| {
+            let before = energy(start.pos.as_f64(), start.vel.as_f64());
+            ((energy(end.pos, end.vel) - before) / before).abs()
+        });
+        drifts.sum::<f64>() / count as f64
+    };
+    let apart = (single.iter().zip(&double))
+        .map(|(single, double)| single.pos.distance(double.pos))
+        .fold(0.0, f64::max);
+    for (name, time, end) in [
+        ("single", single_time, &single),
+        ("double", double_time, &double),
+    ] {
+        println!(
+            "  {name}  {:>8.1} ms  energy drift {:.2e}",
+            time.as_secs_f64() * 1000.0,
+            drift(end)
+        );
+    }
+    println!("  the two end up at most {apart:.3} world units apart");
+}
@@ -0,0 +1,27 @@
+//! A force plugin swirling particles around the origin, faster the closer they pass. Build it
+//! with `cargo build --example vortex_plugin` and copy the library from `target/debug/examples/`
+//! into `plugins/` to try it.
+
+use orbits_core::plugins::{ForcePlugin, Vector, ABI_VERSION};
+
+/// Tangential acceleration at the average orbit.
+const STRENGTH: f32 = 20.0;
+
+#[no_mangle]
+pub extern "C" fn trynannou_force_plugin() -> ForcePlugin {
+    ForcePlugin {
+        abi_version: ABI_VERSION,
+        name: c"vortex".as_ptr(),
+        acceleration,
+    }
+}
+
+extern "C" fn acceleration(pos: Vector, _vel: Vector) -> Vector {
+    let r = (pos.x * pos.x + pos.y * pos.y).sqrt().max(1.0);
+    // at right angles to the radius, falling off with distance
+    let a = STRENGTH * orbits_core::orbital_radius() / r;
+    Vector {
+        x: -pos.y / r * a,
+        y: pos.x / r * a,
+    }
+}
@@ -0,0 +1,105 @@
+//! The records of very long trails older than those kept whole, with `archive` set in
+//! `[simulation]`: one of every `archive_every` that falls off the end of a trail is kept on in its
+//! archive, packed small, each position as how far it is from the next newer one in steps of a
+//! sixteenth of a world unit, and its color and thickness to a byte a channel. Ribbons are still
+//! built from them, so minutes of a swarm's structure stay drawn for a fraction of the memory.
+
+use std::collections::VecDeque;
+
+use nannou_core::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::generation::Record;
+
+/// World units in each step a position is packed in.
+const STEP: f32 = 1.0 / 16.0;
+/// Steps in a unit of thickness.
+const THICKNESS_STEPS: f32 = 32.0;
+
+/// The archived records of one trail, newest first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Archive {
+    /// Where the newest is, as unpacked, which the rest are reached from.
+    newest: Point2,
+    /// How far from each record the one before it is, in steps, one fewer than there are records.
+    steps: VecDeque<[i16; 2]>,
+    /// Each record's hue, as a fraction of a turn, saturation, lightness and alpha.
+    colors: VecDeque<[u8; 4]>,
+    thickness: VecDeque<u8>,
+}
+
+impl Archive {
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Archive `record`, newer than those already archived.
+    pub fn push(&mut self, record: &Record) {
+        if self.is_empty() {
+            self.newest = record.pos;
+        } else {
+            // from where the newest unpacks to, so rounding doesn't build up along the trail
+            let step = ((record.pos - self.newest) / STEP)
+                .round()
+                .clamp(Vec2::splat(i16::MIN as f32), Vec2::splat(i16::MAX as f32));
+            self.steps.push_front([step.x as i16, step.y as i16]);
+            self.newest += step * STEP;
+        }
+        self.colors.push_front(pack(record.color));
+        let thickness = (record.thickness * THICKNESS_STEPS)
+            .round()
+            .clamp(0.0, 255.0);
+        self.thickness.push_front(thickness as u8);
+    }
+
+    /// Keep only the newest `len` records.
+    pub fn truncate(&mut self, len: usize) {
+        self.colors.truncate(len);
+        self.thickness.truncate(len);
+        self.steps.truncate(len.saturating_sub(1));
+    }
+
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// The records archived, unpacked, newest first.
+    pub fn records(&self) -> impl Iterator<Item = Record> + '_ {
+        let positions = self.steps.iter().scan(self.newest, |pos, &[x, y]| {
+            *pos -= vec2(x as f32, y as f32) * STEP;
+            Some(*pos)
+        });
+        (std::iter::once(self.newest).chain(positions))
+            .zip(self.colors.iter().zip(&self.thickness))
+            .map(|(pos, (&color, &thickness))| Record {
+                pos,
+                // too much to keep for so many, so views of the velocities leave archives out
+                vel: Vec2::ZERO,
+                color: unpack(color),
+                thickness: thickness as f32 / THICKNESS_STEPS,
+            })
+    }
+
+    /// Recolor each record as `recolor` would have its color.
+    pub fn recolor(&mut self, recolor: impl Fn(Hsla) -> Hsla) {
+        for color in &mut self.colors {
+            *color = pack(recolor(unpack(*color)));
+        }
+    }
+}
+
+fn pack(color: Hsla) -> [u8; 4] {
+    let hue = color.hue.to_positive_degrees() / 360.0;
+    [hue, color.saturation, color.lightness, color.alpha]
+        .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+fn unpack([hue, saturation, lightness, alpha]: [u8; 4]) -> Hsla {
+    let [hue, saturation, lightness, alpha] =
+        [hue, saturation, lightness, alpha].map(|channel| channel as f32 / 255.0);
+    hsla(hue, saturation, lightness, alpha)
+}
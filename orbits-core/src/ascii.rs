@@ -0,0 +1,16 @@
+//! Brightness as text: a ramp of glyphs from sparse to dense, shared by the ASCII post filter and
+//! the terminal frontend so both read the same.
+
+/// The glyphs used by default, from darkest to brightest.
+pub const RAMP: &str = " .:-=+*#%@";
+
+/// How bright a color looks, from 0 to 1, given as sRGB channels, by the Rec. 709 weights.
+pub fn luminance(red: f32, green: f32, blue: f32) -> f32 {
+    0.2126 * red + 0.7152 * green + 0.0722 * blue
+}
+
+/// The glyph of `ramp`, darkest first, for `luminance` from 0 to 1. `ramp` can't be empty.
+pub fn glyph(ramp: &[char], luminance: f32) -> char {
+    let last = ramp.len() - 1;
+    ramp[(luminance.clamp(0.0, 1.0) * last as f32).round() as usize]
+}
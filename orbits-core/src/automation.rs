@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::params::Param;
+
+/// Keyframes recorded closer together than this replace each other, so dragging a slider leaves a
+/// curve rather than hundreds of points.
+const MERGE_SECONDS: f32 = 0.1;
+
+/// How the value moves from the previous keyframe to this one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    /// Keep the previous value, then jump.
+    Hold,
+    Linear,
+    /// Ease in and out of the keyframes.
+    Smooth,
+}
+
+impl Easing {
+    pub const ALL: [Easing; 3] = [Easing::Hold, Easing::Linear, Easing::Smooth];
+
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Hold => 0.0,
+            Easing::Linear => t,
+            Easing::Smooth => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Simulated seconds since the start of the run.
+    pub time: f32,
+    pub value: f32,
+    pub easing: Easing,
+}
+
+/// Where the camera is at a keyframe of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraKey {
+    /// Simulated seconds since the start of the run.
+    pub time: f32,
+    /// The point of the world in the middle of the view.
+    pub middle: [f32; 2],
+    /// How far the view is zoomed in on the one showing the whole system, or the viewport.
+    pub zoom: f32,
+    /// With `three_d` on, how far round the system has turned, in turns, and how far down from
+    /// overhead it's seen, in radians, or left out for it to turn as it would.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<[f32; 2]>,
+    pub easing: Easing,
+}
+
+/// Parameter values as editable curves over simulated time, one lane of keyframes per parameter,
+/// and the path the camera moves along.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Automation {
+    /// Keyframes of each lane, sorted by time.
+    pub lanes: BTreeMap<Param, Vec<Keyframe>>,
+    /// Keyframes of the camera's path, sorted by time.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub camera: Vec<CameraKey>,
+    /// Whether changes made by hand are being recorded into the lanes, rather than the lanes
+    /// being played back.
+    #[serde(skip)]
+    pub recording: bool,
+}
+
+impl Automation {
+    /// Load automation from a JSON file, starting with empty lanes if it doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let mut automation: Automation = serde_json::from_str(&text)
+                    .map_err(|err| format!("{}: {err}", path.display()))?;
+                for keys in automation.lanes.values_mut() {
+                    sort(keys);
+                }
+                (automation.camera).sort_by(|a, b| a.time.total_cmp(&b.time));
+                Ok(automation)
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Automation::default()),
+            Err(err) => Err(format!("{}: {err}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// The automated value of `param` at `time`, if its lane has any keyframes.
+    pub fn value(&self, param: Param, time: f32) -> Option<f32> {
+        sample(self.lanes.get(&param)?, time)
+    }
+
+    /// Record a change made by hand, if recording.
+    pub fn record(&mut self, param: Param, time: f32, value: f32) {
+        if !self.recording {
+            return;
+        }
+        let keys = self.lanes.entry(param).or_default();
+        keys.retain(|key| (key.time - time).abs() >= MERGE_SECONDS);
+        keys.push(Keyframe {
+            time,
+            value,
+            easing: Easing::Linear,
+        });
+        sort(keys);
+    }
+
+    /// Where the camera's path has it at `time`, if it has any keyframes, holding the first and
+    /// last beyond them. It zooms evenly, however far in it is.
+    pub fn camera(&self, time: f32) -> Option<CameraKey> {
+        let keys = &self.camera;
+        let next = keys.partition_point(|key| key.time <= time);
+        match (
+            next.checked_sub(1).map(|i| keys[i]),
+            keys.get(next).copied(),
+        ) {
+            (None, None) => None,
+            (Some(key), None) | (None, Some(key)) => Some(key),
+            (Some(prev), Some(next)) => {
+                let t = next
+                    .easing
+                    .apply((time - prev.time) / (next.time - prev.time));
+                let mix = |a: f32, b: f32| a + (b - a) * t;
+                Some(CameraKey {
+                    time,
+                    middle: [0, 1].map(|i| mix(prev.middle[i], next.middle[i])),
+                    zoom: mix(prev.zoom.ln(), next.zoom.ln()).exp(),
+                    orientation: prev
+                        .orientation
+                        .zip(next.orientation)
+                        .map(|(a, b)| [0, 1].map(|i| mix(a[i], b[i]))),
+                    easing: next.easing,
+                })
+            }
+        }
+    }
+
+    /// Key the camera where `key` has it, in place of any keyframe of its path close by.
+    pub fn key_camera(&mut self, key: CameraKey) {
+        let keys = &mut self.camera;
+        keys.retain(|other| (other.time - key.time).abs() >= MERGE_SECONDS);
+        keys.push(key);
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+}
+
+/// The value of a lane of sorted `keys` at `time`, holding the first and last values beyond them.
+pub fn sample(keys: &[Keyframe], time: f32) -> Option<f32> {
+    let next = keys.partition_point(|key| key.time <= time);
+    match (
+        next.checked_sub(1).map(|i| keys[i]),
+        keys.get(next).copied(),
+    ) {
+        (None, None) => None,
+        (Some(key), None) | (None, Some(key)) => Some(key.value),
+        (Some(prev), Some(next)) => {
+            let t = (time - prev.time) / (next.time - prev.time);
+            Some(prev.value + (next.value - prev.value) * next.easing.apply(t))
+        }
+    }
+}
+
+pub fn sort(keys: &mut [Keyframe]) {
+    keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+}
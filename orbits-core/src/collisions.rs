@@ -0,0 +1,100 @@
+//! Particles running into each other, when the settings ask for it: those passing within the
+//! collision radius of each other bounce off elastically or merge into one. Close pairs are found
+//! through a grid of cells as wide as the radius, hashed by where they lie, so only particles in
+//! neighboring cells are ever compared and it takes time in proportion to how many there are.
+
+use std::collections::HashMap;
+
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::physics::Particle;
+
+/// What particles do as they run into each other, as the settings' `collisions` picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Collisions {
+    /// Pass through each other, feeling nothing.
+    #[default]
+    Off,
+    /// Bounce off each other, elastically.
+    Bounce,
+    /// Merge into one, conserving momentum, in the colors of both.
+    Merge,
+}
+
+/// The pairs of `particles` within `radius` of each other and closing in, each with the lower
+/// index first, in order, leaving out any whose place in `skip` is true.
+pub fn closing(particles: &[Particle], skip: &[bool], radius: f32) -> Vec<(usize, usize)> {
+    let cell = |pos: Point2| {
+        (
+            (pos.x / radius).floor() as i32,
+            (pos.y / radius).floor() as i32,
+        )
+    };
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, particle) in particles.iter().enumerate() {
+        if !skip[i] {
+            grid.entry(cell(particle.pos)).or_default().push(i);
+        }
+    }
+    let mut pairs = Vec::new();
+    for (i, a) in particles.iter().enumerate() {
+        if skip[i] {
+            continue;
+        }
+        let (x, y) = cell(a.pos);
+        for neighbor in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (x + dx, y + dy))) {
+            for &j in grid.get(&neighbor).into_iter().flatten() {
+                let b = &particles[j];
+                let apart = b.pos - a.pos;
+                if j > i
+                    && apart.length_squared() < radius * radius
+                    && apart.dot(b.vel - a.vel) < 0.0
+                {
+                    pairs.push((i, j));
+                }
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Bounce `a` of mass `mass_a` and `b` of `mass_b` off each other, elastically, along the line
+/// between them.
+pub fn bounce(a: &mut Particle, mass_a: f32, b: &mut Particle, mass_b: f32) {
+    let normal = (b.pos - a.pos).normalize_or_zero();
+    let closing = (a.vel - b.vel).dot(normal);
+    let total = mass_a + mass_b;
+    a.vel -= normal * closing * 2.0 * mass_b / total;
+    b.vel += normal * closing * 2.0 * mass_a / total;
+}
+
+/// The particle `a` of mass `mass_a` and `b` of `mass_b` merge into, at their center of mass and
+/// with their momentum.
+pub fn merge(a: &Particle, mass_a: f32, b: &Particle, mass_b: f32) -> Particle {
+    let total = mass_a + mass_b;
+    Particle {
+        pos: (a.pos * mass_a + b.pos * mass_b) / total,
+        vel: (a.vel * mass_a + b.vel * mass_b) / total,
+    }
+}
+
+/// The color of particles of `color_a` and `color_b` merged, in proportion to their masses, the
+/// hue taken the short way round the color wheel.
+pub fn mix(color_a: Hsla, mass_a: f32, color_b: Hsla, mass_b: f32) -> Hsla {
+    let share = mass_b / (mass_a + mass_b);
+    let (hue_a, hue_b) = (
+        color_a.hue.to_positive_degrees(),
+        color_b.hue.to_positive_degrees(),
+    );
+    let turn = (hue_b - hue_a + 180.0).rem_euclid(360.0) - 180.0;
+    hsla(
+        (hue_a + turn * share).rem_euclid(360.0) / 360.0,
+        color_a.saturation + (color_b.saturation - color_a.saturation) * share,
+        color_a.lightness + (color_b.lightness - color_a.lightness) * share,
+        color_a.alpha + (color_b.alpha - color_a.alpha) * share,
+    )
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::params::{Param, Params};
+
+/// A look to step to during a performance.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cue {
+    #[serde(default)]
+    pub name: String,
+    /// Parameters to move to, leaving the others as they are.
+    #[serde(default)]
+    pub params: BTreeMap<Param, f32>,
+    /// Crossfade to a new generation seeded with this, bringing its own palette.
+    pub seed: Option<u64>,
+    /// Wall-clock seconds taken to move the parameters to their new values.
+    #[serde(default)]
+    pub transition: f32,
+}
+
+/// A planned sequence of cues, loaded from a JSON array.
+pub struct CueList {
+    pub cues: Vec<Cue>,
+    /// Index of the cue most recently started.
+    pub current: Option<usize>,
+    transition: Option<Transition>,
+}
+
+/// Parameters easing from where they were when a cue started to where the cue puts them.
+pub struct Transition {
+    from: BTreeMap<Param, f32>,
+    to: BTreeMap<Param, f32>,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl Transition {
+    /// Start easing `params` to the values in `to` over `duration` wall-clock seconds.
+    pub fn new(to: &BTreeMap<Param, f32>, params: &Params, duration: f32) -> Self {
+        Transition {
+            from: to.keys().map(|p| (*p, params.get(*p))).collect(),
+            to: to.clone(),
+            elapsed: 0.0,
+            duration,
+        }
+    }
+
+    /// Ease `params` on by `wall_seconds`, returning whether they've got where they're going.
+    pub fn update(&mut self, wall_seconds: f32, params: &mut Params) -> bool {
+        self.elapsed += wall_seconds;
+        let t = if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        };
+        // ease in and out so looks settle smoothly
+        let t = t * t * (3.0 - 2.0 * t);
+        for (param, to) in &self.to {
+            let from = self.from[param];
+            params.set(*param, from + (to - from) * t);
+        }
+        self.elapsed >= self.duration
+    }
+}
+
+impl CueList {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let cues =
+            serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(CueList {
+            cues,
+            current: None,
+            transition: None,
+        })
+    }
+
+    /// Index of the cue after the current one, if there is one.
+    pub fn next(&self) -> Option<usize> {
+        let next = self.current.map_or(0, |i| i + 1);
+        (next < self.cues.len()).then_some(next)
+    }
+
+    /// Start moving the parameters towards those of cue `index`.
+    pub fn start(&mut self, index: usize, params: &Params) {
+        let Some(cue) = self.cues.get(index) else {
+            return;
+        };
+        self.current = Some(index);
+        self.transition = Some(Transition::new(&cue.params, params, cue.transition));
+    }
+
+    /// Continue the transition into the current cue.
+    pub fn update(&mut self, wall_seconds: f32, params: &mut Params) {
+        let Some(transition) = &mut self.transition else {
+            return;
+        };
+        if transition.update(wall_seconds, params) {
+            self.transition = None;
+        }
+    }
+
+    /// How far through the list the show is, e.g. `"2/5 sunrise"`.
+    pub fn describe(&self) -> String {
+        match self.current {
+            None => format!("0/{}", self.cues.len()),
+            Some(i) => format!("{}/{} {}", i + 1, self.cues.len(), self.cues[i].name),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! The forces on the particles besides the central mass, as the `[simulation]` section lists them:
+//! more attractors placed around the scene, and fields filling all of it, slowing the particles,
+//! swirling them or pushing them about.
+
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{gm, Body};
+use crate::{orbital_radius, settings};
+
+/// A mass besides the central one that the particles are attracted to.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Attractor {
+    /// Where it sits, in world units.
+    pub pos: [f32; 2],
+    /// Its mass, as a fraction of the central mass's, growing and shrinking as that does.
+    pub mass: f32,
+    /// Whether it can be dragged about with the mouse.
+    #[serde(default)]
+    pub draggable: bool,
+}
+
+impl Attractor {
+    /// The body it is at time `t`.
+    pub fn body(&self, t: f32) -> Body {
+        Body {
+            pos: self.pos.into(),
+            gm: self.mass * gm(t),
+            law: settings::get().gravity_law,
+        }
+    }
+}
+
+/// A force felt everywhere, depending on where a particle is and how fast it's going.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Field {
+    /// Slows every particle by `rate` of its velocity a second.
+    Drag { rate: f32 },
+    /// Swirls particles around `pos`, the usual way round for a positive `strength`, which is the
+    /// acceleration out to the average orbit's distance from it, falling off with distance beyond.
+    Vortex { pos: [f32; 2], strength: f32 },
+    /// Pushes particles along a flow of up to `strength` that varies smoothly over `scale` world
+    /// units, its pattern picked by `seed`.
+    Noise {
+        strength: f32,
+        scale: f32,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl Field {
+    /// What kind of field it is, as the config names it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Field::Drag { .. } => "drag",
+            Field::Vortex { .. } => "vortex",
+            Field::Noise { .. } => "noise",
+        }
+    }
+
+    /// Acceleration the field exerts on a particle at `pos` going `vel`.
+    pub fn acceleration(&self, pos: Point2, vel: Vec2) -> Vec2 {
+        match *self {
+            Field::Drag { rate } => -vel * rate,
+            Field::Vortex {
+                pos: middle,
+                strength,
+            } => {
+                let offset = pos - Vec2::from(middle);
+                let r = offset.length().max(1.0);
+                // at right angles to the radius, as circular orbits go
+                let tangent = vec2(offset.y, -offset.x) / r;
+                tangent * strength * orbital_radius() / r.max(orbital_radius())
+            }
+            Field::Noise {
+                strength,
+                scale,
+                seed,
+            } => noise(pos / scale, seed) * strength,
+        }
+    }
+
+    /// Why the simulation can't run with the field, if it can't.
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Field::Drag { rate } if rate < 0.0 => {
+                Err(format!("a drag field's rate can't be negative, not {rate}"))
+            }
+            Field::Noise { scale, .. } if scale <= 0.0 => Err(format!(
+                "a noise field's scale has to be positive, not {scale}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Total acceleration the fields in the settings exert on a particle at `pos` going `vel`.
+pub fn acceleration(pos: Point2, vel: Vec2) -> Vec2 {
+    (settings::get().fields.iter())
+        .fold(Vec2::ZERO, |acc, field| acc + field.acceleration(pos, vel))
+}
+
+/// A smoothly varying vector of up to unit length in each component, blended between random ones
+/// at the corners of a unit grid.
+fn noise(pos: Point2, seed: u64) -> Vec2 {
+    let cell = pos.floor();
+    let along = pos - cell;
+    let eased = along * along * (3.0 - 2.0 * along);
+    let (x, y) = (cell.x as i64, cell.y as i64);
+    let corner = |dx, dy| lattice(x + dx, y + dy, seed);
+    let bottom = corner(0, 0).lerp(corner(1, 0), eased.x);
+    let top = corner(0, 1).lerp(corner(1, 1), eased.x);
+    bottom.lerp(top, eased.y)
+}
+
+/// The random vector at a corner of the grid, always the same for the same corner and seed.
+fn lattice(x: i64, y: i64, seed: u64) -> Vec2 {
+    // splitmix64, over the corner and seed mixed together
+    let mut h = (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ seed;
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^= h >> 31;
+    let unit = |bits: u64| (bits & 0xffff) as f32 / 0xffff as f32 * 2.0 - 1.0;
+    vec2(unit(h), unit(h >> 16))
+}
@@ -0,0 +1,1173 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use nannou_core::prelude::*;
+use nannou_core::rand::rngs::StdRng;
+use nannou_core::rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::archive::Archive;
+use crate::collisions::{self, Collisions};
+use crate::init::{self, spawn};
+use crate::nbody::Swarm;
+use crate::palette::Palette;
+use crate::params::{Param, Params};
+use crate::physics::{self, gm, Body, Particle};
+use crate::precise::{Precise, Precision};
+use crate::saliency::Saliency;
+use crate::session::GroupOp;
+use crate::trails::echo_delay;
+use crate::{orbital_radius, plugins, settings};
+/// How long a reseed takes to fade the old generation out and the new one in.
+pub const CROSSFADE_SECONDS: f32 = 2.0;
+/// Wall-clock seconds a particle added as the swarm's resized takes to fade in, and one taken
+/// away to fade out, trail and all.
+const ENVELOPE_SECONDS: f32 = 1.0;
+/// Simulated seconds each step of the physics integrates over, however often frames come, so the
+/// orbits come out the same at any frame rate.
+pub const TICK_SECONDS: f32 = 1.0 / 240.0;
+/// Most ticks one update runs. Any more simulated time is dropped, so a frame that took far too
+/// long, as while the window is dragged, isn't followed by ones stalled catching up.
+const MAX_TICKS: usize = 60;
+/// How quickly particles are eased onto circular orbits at the start of the settling, as the
+/// fraction of the way there a second.
+const SETTLE_RATE: f32 = 2.0;
+/// Records of a broken particle's trail logged when it's respawned.
+const LOGGED_TAIL: usize = 8;
+/// Records in each dash of a dashed trail, and in each gap between.
+const DASH_EPOCHS: usize = 8;
+/// Records over which a particle's color eases from what it was into what it became as it merged
+/// or split, so its trail shows where it came from.
+const LINEAGE_EPOCHS: f32 = 60.0;
+/// How far round the color wheel, in turns, each half of a split particle's color is taken from
+/// the color it had, either way.
+const BRANCH_HUE: f32 = 0.03;
+
+/// Steps a generation's particles through whole ticks somewhere other than the CPU's own loop, as
+/// a GPU's compute pass can.
+pub trait Ticker {
+    /// Step `particles` forward by `ticks` ticks of `TICK_SECONDS` under the pull of `bodies`
+    /// alone, leaving where each was before the last of them in `previous`. Returns whether it
+    /// did, as otherwise they're left for the CPU to step.
+    fn tick(
+        &mut self,
+        particles: &mut [Particle],
+        previous: &mut [Point2],
+        ticks: usize,
+        bodies: &[Body],
+    ) -> bool;
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub pos: Point2,
+    /// The particle's velocity as it left the record, for views plotting more than where it was.
+    #[serde(default)]
+    pub vel: Vec2,
+    pub color: Hsla,
+    /// How thick the trail is drawn here, as a multiple of how thick it's drawn as ever.
+    #[serde(default = "unthickened")]
+    pub thickness: f32,
+}
+
+fn unthickened() -> f32 {
+    1.0
+}
+
+/// How thick each record a particle leaves is drawn, as the `trail_thickness` of `[simulation]`
+/// picks, as a multiple of how thick the trail is drawn as ever.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Thickness {
+    /// As thick all the way along.
+    #[default]
+    Constant,
+    /// As the particle's speed is to that of a circular orbit on the average orbit, kept from
+    /// `min` to `max`, so trails swell where they swoop in close and thin out far away.
+    Speed { min: f32, max: f32 },
+    /// As the square root of how many particles the particle's made of, once any have merged, so
+    /// the width of its trail grows in proportion to it.
+    Mass,
+    /// Swelling and thinning by `depth`, from 0 to 1, once every `period` simulated seconds, the
+    /// pulse running along the swarm from the first particle to the last.
+    Pulse { period: f32, depth: f32 },
+}
+
+impl Thickness {
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Thickness::Speed { min, max } if !(0.0 <= min && min <= max) => Err(format!(
+                "the speed trail thickness goes from a min of 0 or more up to its max, not from \
+                 {min} to {max}"
+            )),
+            Thickness::Pulse { period, .. } if period <= 0.0 => Err(format!(
+                "the pulse trail thickness's period has to be positive, not {period}"
+            )),
+            Thickness::Pulse { depth, .. } if !(0.0..=1.0).contains(&depth) => Err(format!(
+                "the pulse trail thickness's depth goes from 0 to 1, not {depth}"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// How thick the trail of a particle going `vel`, made of `mass` particles, `along` the swarm
+    /// from 0 to 1, is left at `t` simulated seconds, where `circular` is the speed of a circular
+    /// orbit on the average orbit.
+    fn of(self, vel: Vec2, mass: f32, along: f32, t: f32, circular: f32) -> f32 {
+        match self {
+            Thickness::Constant => 1.0,
+            Thickness::Speed { min, max } => (vel.length() / circular).clamp(min, max),
+            Thickness::Mass => mass.sqrt(),
+            Thickness::Pulse { period, depth } => 1.0 + depth * ((t / period - along) * TAU).sin(),
+        }
+    }
+}
+
+/// Where each record a particle leaves is put, as the `trail_source` of `[simulation]` picks, so
+/// the trails can trace things derived from the particles rather than the particles themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TrailSource {
+    /// Where the particle is.
+    #[default]
+    Position,
+    /// Where the particle would be `seconds` on, going as it is, so the trail swings out ahead of
+    /// it where it's fast and hugs it where it's slow.
+    Ahead { seconds: f32 },
+    /// Halfway between the particle and the one `partner` places on along the swarm, going round,
+    /// so the trails trace how the pairs move together.
+    Midpoint { partner: usize },
+    /// The particle turned through half a turn about the middle of the system.
+    Mirrored,
+}
+
+impl TrailSource {
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            TrailSource::Midpoint { partner: 0 } => {
+                Err("the midpoint trail source's partner has to be at least 1".into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Where the record left by particle `i` of those at `positions`, going `vel`, is put.
+    fn of(self, i: usize, positions: &[Point2], vel: Vec2) -> Point2 {
+        let pos = positions[i];
+        match self {
+            TrailSource::Position => pos,
+            TrailSource::Ahead { seconds } => pos + vel * seconds,
+            TrailSource::Midpoint { partner } => {
+                let other = positions[(i + partner) % positions.len()];
+                (pos + other) / 2.0
+            }
+            TrailSource::Mirrored => -pos,
+        }
+    }
+}
+
+/// How the records a particle leaves are drawn apart from the rest's, while its orbit is of a
+/// class styled so. Records already left keep the style they were left in.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassStyle {
+    /// Whether the trail is left in dashes, with gaps as long as the dashes between.
+    pub dashed: bool,
+    /// How saturated the trail is, as a fraction of how saturated it would be.
+    pub saturation: f32,
+    /// Whether the trail is colored from the palette run backwards, in the color of the particle
+    /// as far from the other end of the swarm.
+    pub invert: bool,
+}
+
+/// How far a particle added or taken away as the swarm's resized has faded in or out.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Envelope {
+    /// Multiplier on the alpha of each record the particle leaves, rising from 0 once it's added.
+    arrival: f32,
+    /// Multiplier on the alpha of the particle's whole trail, falling to 0 once it's being taken
+    /// away, when it's gone.
+    presence: f32,
+}
+
+impl Envelope {
+    const SETTLED: Envelope = Envelope {
+        arrival: 1.0,
+        presence: 1.0,
+    };
+}
+
+/// The trail of a particle merged away, left as it was rather than vanishing along with it, to
+/// drain into the trail of the one it merged into, losing its oldest record with every record the
+/// rest leave, until it's gone.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Remnant {
+    /// The particle it merged into.
+    pub into: usize,
+    /// Records that particle has left since, the first of the remnant's as old as its record that
+    /// many back.
+    pub since: usize,
+    pub history: VecDeque<Record>,
+}
+
+/// A particle's color easing into what it became as it merged or split, from what it was.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Inheritance {
+    from: Hsla,
+    /// How far it's eased, from 0 to 1.
+    along: f32,
+}
+
+impl Default for ClassStyle {
+    fn default() -> Self {
+        ClassStyle {
+            dashed: false,
+            saturation: 1.0,
+            invert: false,
+        }
+    }
+}
+
+/// One seeded swarm of particles, along with its colors and the trails it leaves behind.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Generation {
+    /// What the generation was seeded with.
+    pub seed: u64,
+    pub particles: Vec<Particle>,
+    pub colors: Vec<Hsla>,
+    pub background: Hsl,
+    pub circle_color: Hsl,
+    pub body_color: Hsl,
+    /// Hue the background was seeded with, from 0 to 1.
+    background_hue: f32,
+    /// The palette the trails are colored from, by name.
+    pub palette: String,
+    /// Hue the first particle was seeded with, and how far round the color wheel the rest run
+    /// from it, for the palette of the hue run.
+    hue_start: f32,
+    hue_run: f32,
+    /// The records each particle has left behind, newest first. A particle added after the
+    /// generation was seeded has a shorter trail than the rest.
+    pub history: Vec<VecDeque<Record>>,
+    /// The records fallen off the end of each particle's trail that are archived, with `archive`
+    /// set, or nothing with it 0.
+    #[serde(default)]
+    pub archives: Vec<Archive>,
+    /// Records each particle has left since the generation was seeded, however many of them are
+    /// still kept.
+    pub epochs: usize,
+    /// Where the particles were before the last tick, for drawing them between ticks.
+    previous: Vec<Point2>,
+    /// Simulated seconds the particles have yet to be ticked through.
+    unticked: f32,
+    /// Simulated seconds the particles have been ticked through.
+    ticked: f32,
+    /// The trails of particles merged away, draining into those they merged into.
+    #[serde(default)]
+    pub remnants: Vec<Remnant>,
+    /// How far each particle's color has eased into what it became as it last merged or split,
+    /// or nothing for those done easing, those past the end included.
+    #[serde(default)]
+    inheritances: Vec<Option<Inheritance>>,
+    /// Simulated time the generation was seeded at, for those spawned since to be carried along
+    /// with the center as far as it's got.
+    #[serde(default)]
+    born: f32,
+    /// Snapshots of the particle positions every `ghost_spacing` seconds, newest first.
+    pub ghosts: VecDeque<Vec<Point2>>,
+    /// Simulated seconds since the last ghost snapshot.
+    ghost_clock: f32,
+    /// Which trails are interesting enough to highlight.
+    pub saliency: Saliency,
+    /// How visible the generation is, from 0 while fading in or out to 1 once established.
+    pub opacity: f32,
+    /// Wall-clock seconds since the generation was seeded.
+    pub age: f32,
+    /// Particles respawned after their position or velocity stopped being a number.
+    pub respawns: usize,
+    /// Whether each particle is frozen in place.
+    pub frozen: Vec<bool>,
+    /// How far each particle has faded in, if it was added as the swarm was resized, or out, if
+    /// it's being taken away. Any past the end are settled.
+    #[serde(default)]
+    envelopes: Vec<Envelope>,
+    /// Where the particles being taken away start, as the swarm's been resized smaller, those
+    /// from here on fading out until they're gone.
+    #[serde(default)]
+    leaving_from: Option<usize>,
+    /// How each particle's orbital plane is turned from the flat one, with `three_d` on, or
+    /// nothing with it off. Each particle is simulated in its plane, so its position and velocity
+    /// are within it.
+    #[serde(default)]
+    pub planes: Vec<Quat>,
+    /// How many of the particles each particle is made of, once any have merged, or nothing while
+    /// none have and each is one.
+    #[serde(default)]
+    masses: Vec<f32>,
+    /// Each particle in double precision, with `precision = "double"`, or nothing with it single.
+    /// Any found not to round to its particle, as it was moved since, start over from it.
+    #[serde(default)]
+    precise: Vec<Precise>,
+    /// The style each particle's records are left in, as its orbit was last classed, or nothing
+    /// for those left as ever.
+    #[serde(skip)]
+    styles: Vec<Option<ClassStyle>>,
+    /// A random stream for each particle, split from the seed, so the draws a particle makes
+    /// don't depend on the order particles are updated in.
+    streams: Vec<Pcg64>,
+    /// What the streams are split from, for the streams of particles added later.
+    stream_state: u128,
+    /// Most the speed of a particle spawned at random strays from a circular orbit's, as the
+    /// parameter had it when the generation was seeded, for those added and respawned later.
+    #[serde(default = "saved_vary_velocity")]
+    vary_velocity: f32,
+}
+
+/// The speeds of a generation saved before they could be varied by parameter strayed as the
+/// settings had them.
+fn saved_vary_velocity() -> f32 {
+    settings::get().vary_velocity
+}
+
+/// Epochs of history kept of the trail of `particle`, enough to draw the whole of its echo.
+fn stored_epochs(particle: usize) -> usize {
+    settings::get().history + echo_delay(particle)
+}
+
+impl Generation {
+    /// Seed a new generation whose particles start on orbits matching the central mass at time `t`,
+    /// those spawned at random with speeds straying up to `vary_velocity` from a circular orbit's.
+    pub fn new(seed: u64, t: f32, opacity: f32, vary_velocity: f32) -> Self {
+        let settings = settings::get();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let hue_start = rng.gen::<f32>();
+        let [least, most] = settings.hue_run;
+        let hue_run = rng.gen_range(least..=most);
+        let background_hue = rng.gen_range(0.0..1.0);
+        let (background, circle_color, body_color) = scene_colors(background_hue);
+
+        let mut particles =
+            (settings.init).generate(settings.particles, gm(t), vary_velocity, &mut rng);
+        for particle in &mut particles {
+            carry(particle, t);
+        }
+
+        let previous = particles.iter().map(|particle| particle.pos).collect();
+        let linecount = particles.len();
+        // checked along with the rest of the settings
+        let palette = Palette::named(&settings.palette, &settings.palettes).unwrap();
+        let colors = (0..linecount)
+            .map(|i| palette.color(i, linecount, hue_start, hue_run))
+            .collect();
+
+        let stream_state = rng.gen();
+        let streams = (0..linecount)
+            .map(|particle| Pcg64::new(stream_state, particle as u128))
+            .collect();
+        // drawn last, so the flat orbits of a seed are the same with three_d on
+        let planes = match settings.three_d {
+            true => (0..linecount).map(|_| init::plane(&mut rng)).collect(),
+            false => Vec::new(),
+        };
+
+        Generation {
+            seed,
+            particles,
+            colors,
+            background,
+            circle_color,
+            body_color,
+            background_hue,
+            palette: settings.palette.clone(),
+            hue_start,
+            hue_run,
+            history: (0..linecount).map(|_| VecDeque::new()).collect(),
+            archives: Vec::new(),
+            epochs: 0,
+            previous,
+            unticked: 0.0,
+            ticked: 0.0,
+            born: t,
+            remnants: Vec::new(),
+            inheritances: Vec::new(),
+            ghosts: VecDeque::new(),
+            ghost_clock: 0.0,
+            saliency: Saliency::default(),
+            opacity,
+            age: 0.0,
+            respawns: 0,
+            frozen: vec![false; linecount],
+            envelopes: vec![Envelope::SETTLED; linecount],
+            leaving_from: None,
+            planes,
+            masses: Vec::new(),
+            precise: Vec::new(),
+            styles: Vec::new(),
+            streams,
+            stream_state,
+            vary_velocity,
+        }
+    }
+
+    /// Add `particle` to the swarm, in the color of the last particle before it, its trail
+    /// starting from nothing. Any still fading out as the swarm was resized are gone at once.
+    pub fn add(&mut self, particle: Particle) {
+        if let Some(from) = self.leaving_from.take() {
+            while self.particles.len() > from {
+                self.pop();
+            }
+        }
+        let index = self.particles.len();
+        self.previous.push(particle.pos);
+        self.particles.push(particle);
+        self.colors.push(*self.colors.last().unwrap());
+        self.history.push(VecDeque::new());
+        self.frozen.push(false);
+        self.envelopes.resize(index, Envelope::SETTLED);
+        self.envelopes.push(Envelope::SETTLED);
+        self.streams
+            .push(Pcg64::new(self.stream_state, index as u128));
+        if !self.planes.is_empty() {
+            // from a stream apart from the particle's, leaving its draws as they'd be
+            let mut stream = Pcg64::new(!self.stream_state, index as u128);
+            self.planes.push(init::plane(&mut stream));
+        }
+        if !self.masses.is_empty() {
+            self.masses.push(1.0);
+        }
+    }
+
+    /// Add or take away particles at the end of the swarm until there are `count`, those added
+    /// spawned as the generation's first were around a central mass of strength `gm`. Those added
+    /// fade in, and those taken away fade out before they're gone, or back in if they're asked
+    /// for again first.
+    pub fn resize(&mut self, count: usize, gm: f32) {
+        self.leaving_from = (count < self.particles.len()).then_some(count);
+        while self.particles.len() < count {
+            let mut stream = Pcg64::new(self.stream_state, self.particles.len() as u128);
+            let mut particle = spawn(gm, self.vary_velocity, &mut stream);
+            carry(&mut particle, self.born + self.ticked);
+            self.add(particle);
+            self.envelopes.last_mut().unwrap().arrival = 0.0;
+        }
+    }
+
+    /// Fade the particles added and taken away over `wall_seconds` more, dropping those taken away
+    /// that have faded out.
+    pub fn fade_envelopes(&mut self, wall_seconds: f32) {
+        let count = self.particles.len();
+        self.envelopes.resize(count, Envelope::SETTLED);
+        let leaving_from = self.leaving_from.unwrap_or(count);
+        let fade = wall_seconds / ENVELOPE_SECONDS;
+        for (i, envelope) in self.envelopes.iter_mut().enumerate() {
+            envelope.arrival = (envelope.arrival + fade).min(1.0);
+            let fade = if i < leaving_from { fade } else { -fade };
+            envelope.presence = (envelope.presence + fade).clamp(0.0, 1.0);
+        }
+        while self.particles.len() > leaving_from && self.envelopes.last().unwrap().presence <= 0.0
+        {
+            self.pop();
+        }
+        if self.particles.len() <= leaving_from {
+            self.leaving_from = None;
+        }
+    }
+
+    /// Take the last particle out of the swarm, trail and all.
+    fn pop(&mut self) {
+        self.particles.pop();
+        self.previous.pop();
+        self.colors.pop();
+        self.history.pop();
+        self.archives.truncate(self.particles.len());
+        self.frozen.pop();
+        self.envelopes.pop();
+        self.planes.truncate(self.particles.len());
+        self.masses.truncate(self.particles.len());
+        self.styles.truncate(self.particles.len());
+        self.streams.pop();
+        self.inheritances.truncate(self.particles.len());
+        let count = self.particles.len();
+        self.remnants.retain(|remnant| remnant.into < count);
+    }
+
+    /// How many particles the swarm has, leaving out any fading out as it's been resized smaller.
+    pub fn staying(&self) -> usize {
+        self.leaving_from.unwrap_or(self.particles.len())
+    }
+
+    /// Multiplier on the alpha of the whole trail of `particle`, as it fades out if it's being
+    /// taken away.
+    pub fn presence(&self, particle: usize) -> f32 {
+        self.envelopes
+            .get(particle)
+            .map_or(1.0, |envelope| envelope.presence)
+    }
+
+    /// Color the trails from the palette called `name`, among those built in and `custom`, those
+    /// already left as well, each point keeping the alpha it had.
+    pub fn repaint(
+        &mut self,
+        name: &str,
+        custom: &BTreeMap<String, Vec<String>>,
+    ) -> Result<(), String> {
+        let palette = Palette::named(name, custom)?;
+        let count = self.particles.len();
+        for (i, (color, history)) in self.colors.iter_mut().zip(&mut self.history).enumerate() {
+            *color = palette.color(i, count, self.hue_start, self.hue_run);
+            for record in history {
+                record.color = Hsla {
+                    alpha: record.color.alpha,
+                    ..*color
+                };
+            }
+            if let Some(archive) = self.archives.get_mut(i) {
+                archive.recolor(|old| Hsla {
+                    alpha: old.alpha,
+                    ..*color
+                });
+            }
+        }
+        self.palette = name.to_string();
+        Ok(())
+    }
+
+    /// Leave each particle's records from now on in the style of its place in `styles`, those
+    /// past the end of it as ever.
+    pub fn restyle(&mut self, styles: Vec<Option<ClassStyle>>) {
+        self.styles = styles;
+    }
+
+    /// Whether any particle is leaving a dashed trail.
+    pub fn is_dashed(&self) -> bool {
+        self.styles.iter().flatten().any(|style| style.dashed)
+    }
+
+    /// Where `pos`, a point in the orbital plane of `particle`, is in 3D, the flat plane's z being 0.
+    pub fn lift(&self, particle: usize, pos: Point2) -> Point3 {
+        let pos = pos.extend(0.0);
+        self.planes.get(particle).map_or(pos, |&plane| plane * pos)
+    }
+
+    /// Let go of every trail and ghost left so far, the particles going on from where they are.
+    pub fn clear_trails(&mut self) {
+        for trail in &mut self.history {
+            trail.clear();
+        }
+        self.archives.clear();
+        self.ghosts.clear();
+    }
+
+    /// Give the particles within `radius` of `pos` up to `vel` more velocity, those at `pos` all of
+    /// it and those further out less, down to none at `radius`. Frozen particles are left be.
+    pub fn impulse(&mut self, pos: Point2, radius: f32, vel: Vec2) {
+        for (particle, _) in (self.particles.iter_mut().zip(&self.frozen)).filter(|(_, &f)| !f) {
+            let distance = particle.pos.distance(pos);
+            if distance < radius {
+                let falloff = (1.0 - distance / radius).powi(2);
+                particle.vel += vel * falloff;
+            }
+        }
+    }
+
+    /// Do `op` to the particles at `indices`, leaving out any indices past the last particle.
+    /// Deleting them leaves the particles after each one a place further forward.
+    pub fn apply_to(&mut self, indices: &[usize], op: GroupOp) {
+        let mut picked = vec![false; self.particles.len()];
+        for &i in indices {
+            if let Some(picked) = picked.get_mut(i) {
+                *picked = true;
+            }
+        }
+        let picked = |i: &usize| picked[*i];
+        match op {
+            GroupOp::Recolor { hue } => {
+                let hue = hue.rem_euclid(1.0) * 360.0;
+                for i in (0..self.particles.len()).filter(picked) {
+                    self.colors[i].hue = hue.into();
+                    for record in &mut self.history[i] {
+                        record.color.hue = hue.into();
+                    }
+                    if let Some(archive) = self.archives.get_mut(i) {
+                        archive.recolor(|mut color| {
+                            color.hue = hue.into();
+                            color
+                        });
+                    }
+                }
+            }
+            GroupOp::Freeze | GroupOp::Thaw => {
+                for i in (0..self.particles.len()).filter(picked) {
+                    self.frozen[i] = op == GroupOp::Freeze;
+                }
+            }
+            GroupOp::Boost { factor } => {
+                for i in (0..self.particles.len()).filter(picked) {
+                    self.particles[i].vel *= factor;
+                }
+            }
+            GroupOp::Delete => {
+                let keep: Vec<bool> = (0..self.particles.len()).map(|i| !picked(&i)).collect();
+                self.remove(&keep);
+            }
+        }
+    }
+
+    /// A copy of the generation with `particle` the only one left in it, trail and all.
+    pub fn isolated(&self, particle: usize) -> Generation {
+        let mut alone = self.clone();
+        let keep: Vec<bool> = (0..self.particles.len()).map(|i| i == particle).collect();
+        alone.remove(&keep);
+        alone
+    }
+
+    /// Take the particles whose place in `keep` is false out of the swarm, trails and all, those
+    /// after each one going a place further forward.
+    fn remove(&mut self, keep: &[bool]) {
+        retain(&mut self.particles, keep);
+        retain(&mut self.previous, keep);
+        retain(&mut self.colors, keep);
+        retain(&mut self.history, keep);
+        retain(&mut self.archives, keep);
+        retain(&mut self.frozen, keep);
+        retain(&mut self.envelopes, keep);
+        if let Some(from) = self.leaving_from {
+            // the same particles are still leaving, however many before them are gone
+            self.leaving_from = Some((0..from).filter(|&i| *keep.get(i).unwrap_or(&true)).count());
+        }
+        retain(&mut self.planes, keep);
+        retain(&mut self.masses, keep);
+        retain(&mut self.styles, keep);
+        retain(&mut self.streams, keep);
+        retain(&mut self.inheritances, keep);
+        // those draining into a particle taken out go with it, and the rest follow theirs
+        self.remnants
+            .retain(|remnant| *keep.get(remnant.into).unwrap_or(&true));
+        for remnant in &mut self.remnants {
+            remnant.into = (0..remnant.into)
+                .filter(|&i| *keep.get(i).unwrap_or(&true))
+                .count();
+        }
+        for ghost in &mut self.ghosts {
+            retain(ghost, keep);
+        }
+        self.saliency.retain(keep);
+    }
+
+    /// How many of the particles `particle` is made of.
+    fn mass(&self, particle: usize) -> f32 {
+        self.masses.get(particle).copied().unwrap_or(1.0)
+    }
+
+    /// Kick the particles towards the mean energy `stirring` in the settings holds them at, over
+    /// `delta_seconds`, around masses of combined strength `gm`.
+    pub fn stir(&mut self, gm: f32, delta_seconds: f32) {
+        if let Some(stirring) = &settings::get().stirring {
+            stirring.stir(&mut self.particles, &self.frozen, gm, delta_seconds);
+        }
+    }
+
+    /// Bounce or merge the particles running into each other, as the settings ask, returning
+    /// those merged away, by where they were before any were. A merge leaves the particle of the
+    /// pair first in the swarm in place of both, and never leaves fewer than 2. Frozen particles
+    /// are left be.
+    pub fn collide(&mut self) -> Vec<usize> {
+        let settings = settings::get();
+        if settings.collisions == Collisions::Off {
+            return Vec::new();
+        }
+        let pairs = collisions::closing(&self.particles, &self.frozen, settings.collision_radius);
+        if settings.collisions == Collisions::Bounce {
+            for (a, b) in pairs {
+                let (mass_a, mass_b) = (self.mass(a), self.mass(b));
+                let (head, tail) = self.particles.split_at_mut(b);
+                collisions::bounce(&mut head[a], mass_a, &mut tail[0], mass_b);
+            }
+            return Vec::new();
+        }
+        if pairs.is_empty() {
+            return Vec::new();
+        }
+        self.masses.resize(self.particles.len(), 1.0);
+        let mut keep = vec![true; self.particles.len()];
+        // each particle merges once a step, so the rest of the pairs are as close as they were
+        let mut merged = vec![false; self.particles.len()];
+        let mut left = self.particles.len();
+        for (a, b) in pairs {
+            if left <= 2 {
+                break;
+            }
+            if merged[a] || merged[b] {
+                continue;
+            }
+            (merged[a], merged[b]) = (true, true);
+            let (mass_a, mass_b) = (self.masses[a], self.masses[b]);
+            self.particles[a] =
+                collisions::merge(&self.particles[a], mass_a, &self.particles[b], mass_b);
+            self.previous[a] =
+                (self.previous[a] * mass_a + self.previous[b] * mass_b) / (mass_a + mass_b);
+            let from = self.colors[a];
+            self.colors[a] = collisions::mix(from, mass_a, self.colors[b], mass_b);
+            self.inherit(a, from);
+            self.masses[a] = mass_a + mass_b;
+            // what was draining into the one merged away drains into the one it merged into
+            for remnant in &mut self.remnants {
+                if remnant.into == b {
+                    remnant.into = a;
+                }
+            }
+            self.remnants.push(Remnant {
+                into: a,
+                since: 0,
+                history: std::mem::take(&mut self.history[b]),
+            });
+            keep[b] = false;
+            left -= 1;
+        }
+        let gone = (0..keep.len()).filter(|&i| !keep[i]).collect();
+        self.remove(&keep);
+        gone
+    }
+
+    /// Split `particle` in two, the new half going in right after it, the halves kicked `kick`
+    /// apart either way. Both go on from the trail it's left so far, and branch off it in colors
+    /// of their own a little either side of its, once particles have merged each half as heavy.
+    /// Frozen particles are left be.
+    pub fn split(&mut self, particle: usize, kick: Vec2) {
+        if self.frozen[particle] {
+            return;
+        }
+        let at = particle + 1;
+        let mut half = self.particles[particle].clone();
+        self.particles[particle].vel -= kick;
+        half.vel += kick;
+        self.particles.insert(at, half);
+        self.previous.insert(at, self.previous[particle]);
+        let from = self.colors[particle];
+        self.colors[particle] = turned(from, -BRANCH_HUE);
+        self.colors.insert(at, turned(from, BRANCH_HUE));
+        self.history.insert(at, self.history[particle].clone());
+        if particle < self.archives.len() {
+            self.archives.insert(at, self.archives[particle].clone());
+        }
+        self.frozen.insert(at, false);
+        if particle < self.envelopes.len() {
+            self.envelopes.insert(at, self.envelopes[particle]);
+        }
+        if let Some(from) = &mut self.leaving_from {
+            if particle < *from {
+                *from += 1;
+            }
+        }
+        if !self.planes.is_empty() {
+            self.planes.insert(at, self.planes[particle]);
+        }
+        if !self.masses.is_empty() {
+            self.masses[particle] /= 2.0;
+            self.masses.insert(at, self.masses[particle]);
+        }
+        if particle < self.styles.len() {
+            self.styles.insert(at, self.styles[particle]);
+        }
+        // the half's stream is split from the particle's
+        let stream = &mut self.streams[particle];
+        let half = Pcg64::new(stream.gen(), stream.gen());
+        self.streams.insert(at, half);
+        for ghost in &mut self.ghosts {
+            if particle < ghost.len() {
+                ghost.insert(at, ghost[particle]);
+            }
+        }
+        self.saliency.split(particle);
+        for remnant in &mut self.remnants {
+            if remnant.into > particle {
+                remnant.into += 1;
+            }
+        }
+        self.inheritances.resize(at, None);
+        self.inheritances.insert(at, None);
+        self.inherit(particle, from);
+        self.inherit(at, from);
+    }
+
+    /// Have the color of `particle` ease into what it's become from `from`, over the records it
+    /// leaves next.
+    fn inherit(&mut self, particle: usize, from: Hsla) {
+        let count = self.particles.len();
+        self.inheritances.resize(count, None);
+        self.inheritances[particle] = Some(Inheritance { from, along: 0.0 });
+    }
+
+    /// The color the next record of `particle` is left in, eased from what it was as it last
+    /// merged or split into what it's become.
+    fn inherited(&self, particle: usize) -> Hsla {
+        let color = self.colors[particle];
+        match self.inheritances.get(particle).copied().flatten() {
+            Some(Inheritance { from, along }) => collisions::mix(from, 1.0 - along, color, along),
+            None => color,
+        }
+    }
+
+    /// Turn the background, and the colors that go with it, `shift` of the way round the color
+    /// wheel from the hue it was seeded with.
+    pub fn shift_hue(&mut self, shift: f32) {
+        let (background, circle_color, body_color) = scene_colors(self.background_hue + shift);
+        self.background = background;
+        self.circle_color = circle_color;
+        self.body_color = body_color;
+    }
+
+    /// Step the particles forward in ticks of `TICK_SECONDS` and record where they went, as far
+    /// between the last two ticks as `delta_seconds` left over, with colors jittered by `jitter`.
+    /// The ticks are left to `ticker`, if there is one, whenever the particles feel only `bodies`.
+    /// A record is of simulated time passing, so with no `delta_seconds`, as while paused, nothing
+    /// is recorded and the trails hold still.
+    pub fn update(
+        &mut self,
+        delta_seconds: f32,
+        bodies: &[Body],
+        jitter: f32,
+        ticker: Option<&mut dyn Ticker>,
+    ) {
+        self.unticked = (self.unticked + delta_seconds).min(MAX_TICKS as f32 * TICK_SECONDS);
+        let mut ticks = 0;
+        while self.unticked >= TICK_SECONDS {
+            self.unticked -= TICK_SECONDS;
+            ticks += 1;
+        }
+        let settings = settings::get();
+        let (settle_seconds, swarm_mass) = (settings.settle_seconds, settings.swarm_mass);
+        // a ticker steps every particle, frozen or not
+        let bodies_alone = self.ticked >= settle_seconds
+            && !self.frozen.contains(&true)
+            && swarm_mass == 0.0
+            && settings.fields.is_empty()
+            && plugins::names().is_empty();
+        // and only in single precision
+        let double = settings.precision == Precision::Double;
+        let ticked = match ticker {
+            Some(ticker) if ticks > 0 && bodies_alone && !double => {
+                ticker.tick(&mut self.particles, &mut self.previous, ticks, bodies)
+            }
+            _ => false,
+        };
+        if ticked {
+            self.ticked += ticks as f32 * TICK_SECONDS;
+        }
+        let cpu_ticks = if ticked { 0 } else { ticks };
+        if double {
+            self.precise
+                .resize_with(self.particles.len(), Precise::default);
+        }
+        for _ in 0..cpu_ticks {
+            let settle = if settle_seconds > 0.0 {
+                (1.0 - self.ticked / settle_seconds).max(0.0) * SETTLE_RATE * TICK_SECONDS
+            } else {
+                0.0
+            };
+            // the swarm's pull changes little over a tick, so is felt as it was at the start of it
+            let swarm = (swarm_mass > 0.0).then(|| {
+                let gm = swarm_mass * bodies.iter().map(|body| body.gm).sum::<f32>();
+                let count = self.particles.len();
+                let law = bodies
+                    .first()
+                    .map_or(settings::get().gravity_law, |body| body.law);
+                let swarm = Swarm::new(
+                    self.particles.iter().map(|p| p.pos).collect(),
+                    gm / count as f32,
+                    law.exponent(),
+                );
+                (0..count)
+                    .map(|i| swarm.acceleration(i))
+                    .collect::<Vec<_>>()
+            });
+            for (i, (previous, particle)) in self
+                .previous
+                .iter_mut()
+                .zip(&mut self.particles)
+                .enumerate()
+            {
+                *previous = particle.pos;
+                if self.frozen[i] {
+                    continue;
+                }
+                if double {
+                    let precise = &mut self.precise[i];
+                    if !precise.rounds_to(particle) {
+                        *precise = Precise::new(particle);
+                    }
+                    let extra = swarm.as_ref().map_or(Vec2::ZERO, |swarm| swarm[i]);
+                    precise.update(TICK_SECONDS as f64, bodies, extra);
+                    *particle = precise.rounded();
+                } else {
+                    match &swarm {
+                        Some(swarm) => particle.update_with(TICK_SECONDS, &|pos, vel| {
+                            physics::pull(pos, vel, bodies) + swarm[i]
+                        }),
+                        None => particle.update(TICK_SECONDS, bodies),
+                    }
+                }
+                if settle > 0.0 {
+                    particle.settle(bodies, settle);
+                }
+            }
+            self.ticked += TICK_SECONDS;
+        }
+        if cfg!(debug_assertions) || settings::get().validate {
+            self.quarantine(bodies);
+        }
+        if delta_seconds <= 0.0 {
+            return;
+        }
+        let along = self.unticked / TICK_SECONDS;
+
+        let count = self.particles.len();
+        let gap = self.epochs / DASH_EPOCHS % 2 == 1;
+        let thickness = settings.trail_thickness;
+        let gm = bodies.iter().map(|body| body.gm).sum();
+        let circular = physics::circular_speed(orbital_radius(), gm);
+        let thicknesses: Vec<f32> = (0..count)
+            .map(|i| {
+                let along = i as f32 / count as f32;
+                let vel = self.particles[i].vel;
+                thickness.of(vel, self.mass(i), along, self.ticked, circular)
+            })
+            .collect();
+        let source = settings.trail_source;
+        let positions: Vec<Point2> = (self.previous.iter().zip(&self.particles))
+            .map(|(previous, particle)| previous.lerp(particle.pos, along))
+            .collect();
+        let colors: Vec<Hsla> = (0..count).map(|i| self.inherited(i)).collect();
+        let records = self
+            .particles
+            .iter()
+            .zip(&colors)
+            .zip(&mut self.streams)
+            .zip(&mut self.history);
+        for (i, (((particle, color), stream), trail)) in records.enumerate() {
+            let style = self.styles.get(i).copied().flatten();
+            let color = match style {
+                Some(style) if style.invert => &colors[count - 1 - i],
+                _ => color,
+            };
+            let mut color = tweak_color(color, jitter, delta_seconds, stream);
+            color.alpha *= self
+                .envelopes
+                .get(i)
+                .map_or(1.0, |envelope| envelope.arrival);
+            if let Some(style) = style {
+                color.saturation = (color.saturation * style.saturation).min(1.0);
+                if style.dashed && gap {
+                    color.alpha = 0.0;
+                }
+            }
+            trail.push_front(Record {
+                pos: source.of(i, &positions, particle.vel),
+                vel: particle.vel,
+                color,
+                thickness: thicknesses[i],
+            });
+        }
+        self.cut_back();
+        self.epochs += 1;
+        self.age_lineage();
+        self.saliency.update(&self.particles, bodies, delta_seconds);
+
+        let (ghost_count, ghost_spacing) = (settings.ghost_count, settings.ghost_spacing);
+        self.ghost_clock += delta_seconds;
+        if ghost_count > 0 && self.ghost_clock >= ghost_spacing {
+            self.ghost_clock %= ghost_spacing;
+            let positions = self.particles.iter().map(|particle| particle.pos).collect();
+            self.ghosts.push_front(positions);
+            self.ghosts.truncate(ghost_count);
+        }
+    }
+
+    /// Ease each particle's color on a record further into what it became as it last merged or
+    /// split, and drain each remnant a record further into the trail it merged into.
+    fn age_lineage(&mut self) {
+        for inheritance in &mut self.inheritances {
+            if let Some(easing) = inheritance {
+                easing.along += 1.0 / LINEAGE_EPOCHS;
+                if easing.along >= 1.0 {
+                    *inheritance = None;
+                }
+            }
+        }
+        for remnant in &mut self.remnants {
+            remnant.since += 1;
+            let kept = stored_epochs(remnant.into).saturating_sub(remnant.since);
+            remnant.history.truncate(kept);
+        }
+        self.remnants.retain(|remnant| !remnant.history.is_empty());
+    }
+
+    /// Cut each trail back to the epochs of it kept, archiving those cut off that are archived.
+    fn cut_back(&mut self) {
+        let settings = settings::get();
+        let (archived, every) = (settings.archive, settings.archive_every);
+        self.archives.resize_with(
+            if archived > 0 { self.history.len() } else { 0 },
+            Archive::default,
+        );
+        for (i, trail) in self.history.iter_mut().enumerate() {
+            let stored = stored_epochs(i);
+            if trail.len() <= stored {
+                continue;
+            }
+            // the newest of the trail is of this epoch, and the rest of those before
+            let epoch = |back: usize| self.epochs - back;
+            let cut: Vec<(usize, Record)> = trail.drain(stored..).enumerate().collect();
+            if let Some(archive) = self.archives.get_mut(i) {
+                for (k, record) in cut.into_iter().rev() {
+                    if epoch(stored + k) % every == 0 {
+                        archive.push(&record);
+                    }
+                }
+                archive.truncate(archived);
+            }
+        }
+    }
+
+    /// Respawn any particle whose position or velocity has stopped being a number, before it's
+    /// drawn as a ribbon off to nowhere, logging the last of the trail it left, then collapse its
+    /// trail onto where it starts again.
+    fn quarantine(&mut self, bodies: &[Body]) {
+        let gm = bodies.iter().map(|body| body.gm).sum();
+        let t = self.born + self.ticked;
+        for (i, particle) in self.particles.iter_mut().enumerate() {
+            if particle.pos.is_finite() && particle.vel.is_finite() {
+                continue;
+            }
+            let trail = &mut self.history[i];
+            let tail: Vec<Point2> = trail
+                .iter()
+                .take(LOGGED_TAIL)
+                .map(|record| record.pos)
+                .collect();
+            eprintln!(
+                "particle {i} went to {} going {}, so it's respawned; its trail ended {tail:?}",
+                particle.pos, particle.vel
+            );
+            *particle = spawn(gm, self.vary_velocity, &mut self.streams[i]);
+            carry(particle, t);
+            self.respawns += 1;
+            self.previous[i] = particle.pos;
+            for record in trail {
+                record.pos = particle.pos;
+            }
+            if let Some(archive) = self.archives.get_mut(i) {
+                archive.clear();
+            }
+        }
+    }
+}
+
+/// Move `particle`, spawned around the middle of the world, to around where the center has been
+/// carried by time `t`, moving along with it.
+fn carry(particle: &mut Particle, t: f32) {
+    let (pos, vel) = physics::center_motion(t);
+    particle.pos += pos;
+    particle.vel += vel;
+}
+
+/// `color` taken `by` turns round the color wheel.
+fn turned(mut color: Hsla, by: f32) -> Hsla {
+    color.hue = (color.hue.to_positive_degrees() + by * 360.0).into();
+    color
+}
+
+/// Keep the items of `items` whose place in `keep` is true.
+fn retain<T>(items: &mut Vec<T>, keep: &[bool]) {
+    let mut keep = keep.iter();
+    items.retain(|_| *keep.next().unwrap_or(&true));
+}
+
+/// The background, orbit and body colors of a scene whose background has `hue`.
+fn scene_colors(hue: f32) -> (Hsl, Hsl, Hsl) {
+    let settings = settings::get();
+    let hue = hue.rem_euclid(1.0);
+    (
+        hsl(
+            hue,
+            settings.background_saturation,
+            settings.background_lightness,
+        ),
+        hsl(hue, 0.36, 0.33),
+        hsl(hue, 0.3, 0.75),
+    )
+}
+
+/// Step every generation alive, fading the current one, the last, in and the rest out, and dropping
+/// those that have faded away. The current generation is given as many particles as `params`
+/// asks for, and every one feels `bodies` as strong as they ask, ticked by `ticker` where it can.
+/// Returns the particles of the current generation merged away as it was stepped, by where they
+/// were, for the count `params` asks for to be brought down to match.
+pub fn step(
+    generations: &mut Vec<Generation>,
+    wall_seconds: f32,
+    delta_seconds: f32,
+    bodies: &[Body],
+    params: &Params,
+    mut ticker: Option<&mut dyn Ticker>,
+) -> Vec<usize> {
+    let gravity = params.get(Param::Gravity);
+    let exponent = params.get(Param::ForceExponent);
+    let bodies: Vec<Body> = bodies
+        .iter()
+        .map(|body| Body {
+            gm: body.gm * gravity,
+            law: body.law.with_exponent(exponent),
+            ..*body
+        })
+        .collect();
+    let gm = bodies.iter().map(|body| body.gm).sum();
+    let particles = params.get(Param::Particles).round() as usize;
+    generations.last_mut().unwrap().resize(particles, gm);
+
+    let jitter = params.get(Param::ColorJitter);
+    let hue_shift = params.get(Param::BackgroundHue);
+    // fades run on the wall clock so a reseed takes as long in slow motion
+    let fade = wall_seconds / CROSSFADE_SECONDS;
+    let current = generations.len() - 1;
+    for (i, generation) in generations.iter_mut().enumerate() {
+        let fade = if i == current { fade } else { -fade };
+        generation.opacity = (generation.opacity + fade).clamp(0.0, 1.0);
+        generation.age += wall_seconds;
+        generation.fade_envelopes(wall_seconds);
+        generation.shift_hue(hue_shift);
+        let ticker = ticker
+            .as_mut()
+            .map(|ticker| &mut **ticker as &mut dyn Ticker);
+        generation.update(delta_seconds, &bodies, jitter, ticker);
+        generation.stir(gm, delta_seconds);
+    }
+    // nothing runs into anything while time stands still
+    let mut merged = Vec::new();
+    if delta_seconds > 0.0 {
+        for (i, generation) in generations.iter_mut().enumerate() {
+            let gone = generation.collide();
+            if i == current {
+                merged = gone;
+            }
+        }
+    }
+    // the current generation is kept even before it starts fading in
+    let current = generations.pop().unwrap();
+    generations.retain(|generation| generation.opacity > 0.0);
+    generations.push(current);
+    merged
+}
+
+/// Random walk the color of a particle. The jitter grows with the square root of the elapsed time so
+/// the color noise looks the same whatever the frame rate.
+fn tweak_color(c: &Hsla, jitter: f32, delta_seconds: f32, rng: &mut impl Rng) -> Hsla {
+    // `jitter` is the step size at 60 frames per second
+    let mag = jitter * (delta_seconds * 60.0).sqrt();
+    let mut rr = || -> f32 { rng.gen_range(-1.0..1.0) * mag };
+    let hue = (c.hue.to_radians() / TAU + rr()) % 1.0;
+    let sat = (c.saturation + rr()).clamp(0.0, 1.0);
+    let light = (c.lightness + rr()).clamp(0.0, 1.0);
+    hsla(hue, sat, light, c.alpha)
+}
@@ -0,0 +1,337 @@
+//! Shapes made of points: the convex hull around them, that hull inflated into a rounded blob,
+//! and the clusters they fall into, those packed closely enough together picked out from the rest
+//! DBSCAN style, so a frontend can draw the outlines of a swarm and of the clumps within it. And
+//! the closed paths the center can be carried round, a Lissajous figure or a spline through points
+//! of the config's or traced from an SVG, evaluated at any way round them.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::orbital_radius;
+
+/// A closed path, gone round from 0 to 1.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Path {
+    /// Swinging `size` either side of the middle each way, `frequencies` times each way a lap,
+    /// the swing across `phase` turns ahead of the one up and down.
+    Lissajous {
+        size: [f32; 2],
+        frequencies: [u32; 2],
+        #[serde(default)]
+        phase: f32,
+    },
+    /// A Catmull-Rom spline through `points` in turn and back round to the first, as long between
+    /// each and the next. With `svg`, the frontend traces the longest outline in that file into
+    /// the points instead, its longer side `radius` either side of the middle.
+    Spline {
+        #[serde(default)]
+        points: Vec<[f32; 2]>,
+        #[serde(default)]
+        svg: Option<PathBuf>,
+        #[serde(default = "default_spline_radius")]
+        radius: f32,
+    },
+}
+
+fn default_spline_radius() -> f32 {
+    orbital_radius() * 2.0
+}
+
+impl Path {
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Path::Lissajous {
+                size, frequencies, ..
+            } => {
+                if size.iter().any(|&side| side < 0.0) {
+                    return Err(format!(
+                        "a Lissajous path's size can't be negative, not {size:?}"
+                    ));
+                }
+                if frequencies.contains(&0) {
+                    return Err(format!(
+                        "a Lissajous path's frequencies have to be at least 1, not {frequencies:?}"
+                    ));
+                }
+            }
+            Path::Spline {
+                points,
+                svg,
+                radius,
+            } => {
+                if svg.is_none() && points.len() < 3 {
+                    return Err(format!(
+                        "a spline path needs at least 3 points or an svg to trace, not {} points",
+                        points.len()
+                    ));
+                }
+                if *radius <= 0.0 {
+                    return Err(format!(
+                        "a spline path's radius has to be positive, not {radius}"
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the path is `along` the way round it, going round again past 1. A spline with no
+    /// points yet, its SVG still to be traced, stays at the middle.
+    pub fn at(&self, along: f32) -> Point2 {
+        let along = along.rem_euclid(1.0);
+        match self {
+            Path::Lissajous {
+                size,
+                frequencies,
+                phase,
+            } => {
+                let [x, y] = frequencies.map(|frequency| frequency as f32 * along);
+                pt2(
+                    size[0] * ((x + phase) * TAU).sin(),
+                    size[1] * (y * TAU).sin(),
+                )
+            }
+            Path::Spline { points, .. } => {
+                if points.is_empty() {
+                    return Point2::ZERO;
+                }
+                let count = points.len();
+                let spot = along * count as f32;
+                let i = (spot as usize).min(count - 1);
+                let u = spot - i as f32;
+                let [p0, p1, p2, p3] =
+                    [count - 1, 0, 1, 2].map(|k| Vec2::from(points[(i + k) % count]));
+                // uniform Catmull-Rom, through p1 at 0 and p2 at 1
+                0.5 * (2.0 * p1
+                    + (p2 - p0) * u
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u * u
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u * u * u)
+            }
+        }
+    }
+}
+
+/// The corners of the convex hull of `points`, counterclockwise from the lowest leftmost, those
+/// that aren't numbers left out. Fewer than 3 points, or all of them in a line, have a hull of
+/// the ends of them alone.
+pub fn convex_hull(points: &[Point2]) -> Vec<Point2> {
+    let mut sorted: Vec<Point2> = points.iter().copied().filter(|p| p.is_finite()).collect();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    // Andrew's monotone chain, the lower half then the upper, each turning left all the way
+    let turns_left = |o: Point2, a: Point2, b: Point2| (a - o).perp_dot(b - o) > 0.0;
+    let mut hull: Vec<Point2> = Vec::with_capacity(sorted.len() + 1);
+    for pass in [
+        &sorted[..],
+        &sorted.iter().rev().copied().collect::<Vec<_>>()[..],
+    ] {
+        let start = hull.len();
+        for &p in pass {
+            while hull.len() >= start + 2
+                && !turns_left(hull[hull.len() - 2], hull[hull.len() - 1], p)
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // the last of each half is the first of the other
+        hull.pop();
+    }
+    hull
+}
+
+/// `hull`, counterclockwise, grown outwards by `by` all round, its corners rounded off with arcs
+/// of `segments` pieces each, for a soft blob around it rather than a polygon.
+pub fn inflate(hull: &[Point2], by: f32, segments: usize) -> Vec<Point2> {
+    let outward =
+        |from: Point2, to: Point2| -crate::physics::normalize_or((to - from).perp(), Vec2::Y);
+    match hull {
+        [] => Vec::new(),
+        // a dot and a line are circled and capsuled as if they were a hull going out and back
+        [p] => (0..segments * 4)
+            .map(|k| {
+                let angle = k as f32 / (segments * 4) as f32 * TAU;
+                *p + vec2(angle.cos(), angle.sin()) * by
+            })
+            .collect(),
+        _ => {
+            let count = hull.len();
+            let mut blob = Vec::with_capacity(count * (segments + 1));
+            for i in 0..count {
+                let (before, corner, after) = (
+                    hull[(i + count - 1) % count],
+                    hull[i],
+                    hull[(i + 1) % count],
+                );
+                let from = outward(before, corner);
+                let to = outward(corner, after);
+                let start = from.y.atan2(from.x);
+                let mut turn = to.y.atan2(to.x) - start;
+                if turn < 0.0 {
+                    turn += TAU;
+                }
+                for k in 0..=segments {
+                    let angle = start + turn * k as f32 / segments as f32;
+                    blob.push(corner + vec2(angle.cos(), angle.sin()) * by);
+                }
+            }
+            blob
+        }
+    }
+}
+
+/// Which cluster each of `points` is in, numbered from 0, or `None` for those left out as noise:
+/// those with at least `least` others within `radius` of them are at the core of a cluster,
+/// which takes in every point within `radius` of any of its cores. The points are hashed into a
+/// grid of cells `radius` across, so only those in the cells around each are looked at.
+pub fn clusters(points: &[Point2], radius: f32, least: usize) -> Vec<Option<usize>> {
+    let cell = |pos: Point2| {
+        let cell = (pos / radius).floor();
+        (cell.x as i32, cell.y as i32)
+    };
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (i, &pos) in points.iter().enumerate() {
+        if pos.is_finite() {
+            grid.entry(cell(pos)).or_default().push(i);
+        }
+    }
+    let neighbors = |i: usize| -> Vec<usize> {
+        let pos = points[i];
+        let (x, y) = cell(pos);
+        let mut near = Vec::new();
+        for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+            let Some(cell) = grid.get(&(x.saturating_add(dx), y.saturating_add(dy))) else {
+                continue;
+            };
+            near.extend(
+                (cell.iter().copied()).filter(|&j| j != i && pos.distance(points[j]) <= radius),
+            );
+        }
+        near
+    };
+    let mut labels = vec![None; points.len()];
+    let mut visited = vec![false; points.len()];
+    let mut count = 0;
+    for i in 0..points.len() {
+        if visited[i] || !points[i].is_finite() {
+            continue;
+        }
+        visited[i] = true;
+        let near = neighbors(i);
+        if near.len() < least {
+            continue;
+        }
+        labels[i] = Some(count);
+        let mut frontier = near;
+        while let Some(j) = frontier.pop() {
+            if labels[j].is_none() {
+                labels[j] = Some(count);
+            }
+            if visited[j] {
+                continue;
+            }
+            visited[j] = true;
+            let near = neighbors(j);
+            if near.len() >= least {
+                frontier.extend(near);
+            }
+        }
+        count += 1;
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_hull_of_a_square_is_its_corners() {
+        let mut points = vec![
+            pt2(0.0, 0.0),
+            pt2(10.0, 0.0),
+            pt2(10.0, 10.0),
+            pt2(0.0, 10.0),
+        ];
+        // inside it, on an edge of it, and not a number
+        points.extend([pt2(5.0, 5.0), pt2(5.0, 0.0), pt2(f32::NAN, 3.0)]);
+        let hull = convex_hull(&points);
+        assert_eq!(
+            hull,
+            vec![
+                pt2(0.0, 0.0),
+                pt2(10.0, 0.0),
+                pt2(10.0, 10.0),
+                pt2(0.0, 10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn the_hull_of_a_line_is_its_ends() {
+        let points = [pt2(0.0, 0.0), pt2(2.0, 2.0), pt2(1.0, 1.0)];
+        assert_eq!(convex_hull(&points), vec![pt2(0.0, 0.0), pt2(2.0, 2.0)]);
+        assert_eq!(convex_hull(&[]), Vec::<Point2>::new());
+    }
+
+    #[test]
+    fn an_inflated_hull_keeps_its_distance() {
+        let hull = convex_hull(&[pt2(0.0, 0.0), pt2(4.0, 0.0), pt2(0.0, 3.0)]);
+        let blob = inflate(&hull, 2.0, 6);
+        assert_eq!(blob.len(), 3 * 7);
+        for pos in &blob {
+            let nearest = hull.iter().map(|corner| corner.distance(*pos));
+            let nearest = nearest.fold(f32::MAX, f32::min);
+            // as far from the hull as it's grown, and no nearer any corner than that
+            assert!(nearest >= 2.0 - 1e-3, "{pos} is {nearest} from the hull");
+        }
+        let dot = inflate(&[pt2(1.0, 1.0)], 1.0, 4);
+        assert!(dot
+            .iter()
+            .all(|pos| (pos.distance(pt2(1.0, 1.0)) - 1.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn clumps_far_apart_are_clusters_of_their_own() {
+        let clump = |middle: Point2| (0..5).map(move |i| middle + vec2(i as f32, 0.0));
+        let mut points: Vec<Point2> = clump(pt2(0.0, 0.0)).chain(clump(pt2(100.0, 0.0))).collect();
+        points.push(pt2(50.0, 50.0));
+        let labels = clusters(&points, 2.0, 2);
+        assert!(labels[..5].iter().all(|&label| label == labels[0]));
+        assert!(labels[5..10].iter().all(|&label| label == labels[5]));
+        assert!(labels[0].is_some() && labels[5].is_some());
+        assert_ne!(labels[0], labels[5]);
+        assert_eq!(labels[10], None);
+    }
+
+    #[test]
+    fn paths_come_back_round_to_where_they_started() {
+        let points = vec![[0.0, 0.0], [100.0, 0.0], [100.0, 100.0], [0.0, 100.0]];
+        let spline = Path::Spline {
+            points: points.clone(),
+            svg: None,
+            radius: 1.0,
+        };
+        for (i, point) in points.iter().enumerate() {
+            let along = i as f32 / points.len() as f32;
+            assert!(spline.at(along).distance(Vec2::from(*point)) < 1e-3);
+        }
+        let lissajous = Path::Lissajous {
+            size: [300.0, 200.0],
+            frequencies: [3, 2],
+            phase: 0.25,
+        };
+        for path in [spline, lissajous] {
+            assert!(path.at(0.0).distance(path.at(1.0)) < 1e-3);
+            assert!(path.at(0.3).distance(path.at(2.3)) < 1e-2);
+        }
+    }
+}
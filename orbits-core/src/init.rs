@@ -0,0 +1,169 @@
+//! How a generation's particles start out: scattered at random around the average orbit, or on one
+//! of the curated families of orbits built to show something off, as the `init` of `[simulation]`
+//! names them.
+
+use nannou_core::prelude::*;
+use nannou_core::rand::Rng;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::physics::{circular_speed, normalize_or, Particle};
+use crate::{orbital_radius, settings};
+
+/// Periods of the rings of a resonant chain, relative to the shortest.
+const CHAIN_PERIODS: [f32; 3] = [1.0, 2.0, 4.0];
+/// How far apart, as a fraction of the average orbit, the two particles of a horseshoe pair start.
+const HORSESHOE_GAP: f32 = 0.01;
+/// Angle by which the outer particle of a horseshoe pair starts ahead of the inner.
+const HORSESHOE_LEAD: f32 = PI / 3.0;
+/// Shells of a prograde and retrograde mix, spread evenly across `SHELL_SPREAD`.
+const SHELLS: usize = 4;
+/// Innermost and outermost shells of a prograde and retrograde mix, as fractions of the average
+/// orbit.
+const SHELL_SPREAD: [f32; 2] = [0.7, 1.3];
+
+/// The ways there are for a generation's particles to start out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    /// Anywhere on the average orbit, going either way round at about the speed to stay on it.
+    #[default]
+    Random,
+    /// Circular orbits on three rings whose periods go 1:2:4, the middle one the average orbit,
+    /// so the rings line up again and again.
+    ResonantChain,
+    /// Pairs sharing nearly the same orbit, the outer a sixth of the way round ahead. With
+    /// `swarm_mass` set, so they pull on each other, each pair trades places back and forth
+    /// rather than drifting apart.
+    Horseshoe,
+    /// Circular orbits on a few shells, each with as many particles going one way round as the
+    /// other.
+    RetrogradeMix,
+}
+
+impl Preset {
+    /// `count` particles started out this way around a central mass of strength `gm`, those
+    /// spawned at random with speeds straying up to `vary` from a circular orbit's.
+    pub fn generate(self, count: usize, gm: f32, vary: f32, rng: &mut impl Rng) -> Vec<Particle> {
+        match self {
+            Preset::Random => (0..count).map(|_| spawn(gm, vary, rng)).collect(),
+            Preset::ResonantChain => resonant_chain(count, gm, rng),
+            Preset::Horseshoe => horseshoe(count, gm, vary, rng),
+            Preset::RetrogradeMix => retrograde_mix(count, gm, rng),
+        }
+    }
+}
+
+/// A particle somewhere on the average orbit, with about the speed to stay on it around a central
+/// mass of strength `gm`, straying up to `vary` from it.
+pub fn spawn(gm: f32, vary: f32, rng: &mut impl Rng) -> Particle {
+    let pos = point_on_circle(rng) * orbital_radius();
+    // on average velocity will be just enough to keep the particle circular orbit
+    let circular = circular_speed(orbital_radius(), gm);
+    let way = if rng.gen() { 1.0 } else { -1.0 };
+    let settings = settings::get();
+    let speed = way * circular + rng.gen_range(-vary..=vary);
+    let speed = match settings.max_eccentricity {
+        // starting at an apsis, a Kepler orbit of k times the circular speed has e = |k^2 - 1|
+        Some(e) => {
+            let least = circular * (1.0 - e).max(0.0).sqrt();
+            let most = circular * (1.0 + e).sqrt();
+            way * (way * speed).clamp(least, most)
+        }
+        None => speed,
+    };
+    let vel = speed * normalize_or(pt2(pos.y, -pos.x), Vec2::Y);
+    Particle { pos, vel }
+}
+
+/// Particles dealt round the rings of the chain in turn, each ring's spaced evenly along it.
+fn resonant_chain(count: usize, gm: f32, rng: &mut impl Rng) -> Vec<Particle> {
+    let offset = rng.gen_range(0.0..TAU);
+    let rings = CHAIN_PERIODS.len();
+    let exponent = settings::get().gravity_law.exponent();
+    (0..count)
+        .map(|i| {
+            let ring = i % rings;
+            let on_ring = (count + rings - 1 - ring) / rings;
+            // a circular orbit's period goes as r^((n + 1) / 2)
+            let period = CHAIN_PERIODS[ring] / CHAIN_PERIODS[rings / 2];
+            let radius = orbital_radius() * period.powf(2.0 / (exponent + 1.0));
+            let angle = offset + TAU * (i / rings) as f32 / on_ring as f32;
+            circular(radius, angle, 1.0, gm)
+        })
+        .collect()
+}
+
+/// Pairs spaced evenly around the average orbit, a particle left over spawned at random.
+fn horseshoe(count: usize, gm: f32, vary: f32, rng: &mut impl Rng) -> Vec<Particle> {
+    let offset = rng.gen_range(0.0..TAU);
+    let pairs = count / 2;
+    let mut particles = Vec::with_capacity(count);
+    for pair in 0..pairs {
+        let angle = offset + TAU * pair as f32 / pairs as f32;
+        let inner = orbital_radius() * (1.0 - HORSESHOE_GAP / 2.0);
+        let outer = orbital_radius() * (1.0 + HORSESHOE_GAP / 2.0);
+        particles.push(circular(inner, angle, 1.0, gm));
+        particles.push(circular(outer, angle + HORSESHOE_LEAD, 1.0, gm));
+    }
+    if particles.len() < count {
+        particles.push(spawn(gm, vary, rng));
+    }
+    particles
+}
+
+/// Particles dealt round the shells two at a time, one going each way, anywhere along them.
+fn retrograde_mix(count: usize, gm: f32, rng: &mut impl Rng) -> Vec<Particle> {
+    let [innermost, outermost] = SHELL_SPREAD;
+    (0..count)
+        .map(|i| {
+            let shell = (i / 2) % SHELLS;
+            let radius = orbital_radius()
+                * map_range(shell as f32, 0.0, (SHELLS - 1) as f32, innermost, outermost);
+            let way = if i % 2 == 0 { 1.0 } else { -1.0 };
+            circular(radius, rng.gen_range(0.0..TAU), way, gm)
+        })
+        .collect()
+}
+
+/// A particle `angle` round a circular orbit of `radius`, the usual way round if `way` is 1 and
+/// the other if -1.
+fn circular(radius: f32, angle: f32, way: f32, gm: f32) -> Particle {
+    let pos = pt2(angle.cos(), angle.sin()) * radius;
+    let vel = way * circular_speed(radius, gm) * pt2(pos.y, -pos.x) / radius;
+    Particle { pos, vel }
+}
+
+// come up with a random point on a circle
+fn point_on_circle(rng: &mut impl Rng) -> Point2 {
+    loop {
+        let x = rng.gen_range(-1.0..1.0);
+        let y = rng.gen_range(-1.0..1.0);
+        let len = x * x + y * y;
+        if len != 0.0 {
+            return pt2(x, y) / len.sqrt();
+        }
+    }
+}
+
+// come up with a random point on a sphere
+fn point_on_sphere(rng: &mut impl Rng) -> Point3 {
+    loop {
+        let p = pt3(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        );
+        let len = p.length_squared();
+        // within the ball, so every way is as likely as any other
+        if len != 0.0 && len <= 1.0 {
+            return p / len.sqrt();
+        }
+    }
+}
+
+/// A random orientation for an orbital plane, turning the flat plane to face one way as likely as
+/// any other.
+pub fn plane(rng: &mut impl Rng) -> Quat {
+    Quat::from_rotation_arc(Vec3::Z, point_on_sphere(rng))
+}
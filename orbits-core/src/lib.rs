@@ -0,0 +1,40 @@
+//! The simulation behind trynannou, free of any windowing or graphics so other frontends and tools
+//! can drive it: the physics, the integrators stepping it, the attractors, fields and plugins
+//! adding forces to it and the double precision it can be stepped in, generations of particles with
+//! the orbits they start on and the collisions between them, their trails and palettes, a whole run
+//! stepping them, the shapes those trails are drawn as and the archive keeping very long ones
+//! small, the hulls and clusters of the shapes they make, the glyphs their brightness is written
+//! in as text, the settings tuning it all for a run, the parameters, automation, cues and session
+//! logs that steer one, and the saved state it can be resumed from.
+
+pub mod archive;
+pub mod ascii;
+pub mod automation;
+pub mod collisions;
+pub mod cues;
+pub mod forces;
+pub mod generation;
+pub mod geometry;
+pub mod init;
+pub mod integrator;
+pub mod nbody;
+pub mod palette;
+pub mod params;
+pub mod physics;
+pub mod plugins;
+pub mod precise;
+pub mod saliency;
+pub mod session;
+pub mod settings;
+pub mod simulation;
+pub mod state;
+pub mod stirring;
+pub mod thermo;
+pub mod time_scale;
+pub mod trails;
+
+/// The radius of the average orbit, in world units, the `orbital_radius` setting: the distance
+/// the pull of the masses is measured against, and the view framed to.
+pub fn orbital_radius() -> f32 {
+    settings::get().orbital_radius
+}
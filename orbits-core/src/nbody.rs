@@ -0,0 +1,168 @@
+//! The swarm's pull on itself, for the n-body mode where particles attract each other as well as
+//! being attracted to the center: a Barnes-Hut quadtree, summing far off clumps of particles as
+//! one mass at their center, so a tick costs about `n log n` rather than `n^2`.
+
+use nannou_core::prelude::*;
+
+use crate::orbital_radius;
+
+/// How small a cell has to look from a particle, its width over its distance, for the particles
+/// in it to pull as one. Smaller is more accurate and slower.
+const THETA: f32 = 0.7;
+/// Distance, in world units, within which the pull between two particles stops growing, so close
+/// passes don't fling them apart.
+const SOFTENING: f32 = 40.0;
+/// Deepest the tree is split. Particles closer together than its smallest cell share it.
+const MAX_DEPTH: usize = 20;
+
+/// A quadtree over the particles' positions at one moment, each of the same strength.
+pub struct Swarm {
+    nodes: Vec<Node>,
+    positions: Vec<Point2>,
+    gm: f32,
+    exponent: f32,
+}
+
+struct Node {
+    middle: Point2,
+    half: f32,
+    /// Sum of the positions of the particles within.
+    sum: Vec2,
+    count: usize,
+    /// Index among the nodes of the first of the four children, if the node is split.
+    children: Option<usize>,
+    /// The particle alone in the node, if it's a leaf with one.
+    particle: Option<usize>,
+}
+
+impl Node {
+    fn new(middle: Point2, half: f32) -> Self {
+        Node {
+            middle,
+            half,
+            sum: Vec2::ZERO,
+            count: 0,
+            children: None,
+            particle: None,
+        }
+    }
+
+    /// Which child of the node `pos` falls in, from 0 to 3.
+    fn quadrant(&self, pos: Point2) -> usize {
+        (pos.x >= self.middle.x) as usize + 2 * (pos.y >= self.middle.y) as usize
+    }
+}
+
+impl Swarm {
+    /// A tree over particles at `positions`, each pulling the others with strength `gm` by a
+    /// pull falling off as `1 / r^exponent`.
+    pub fn new(positions: Vec<Point2>, gm: f32, exponent: f32) -> Self {
+        let (lo, hi) = positions.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(lo, hi), &p| (lo.min(p), hi.max(p)),
+        );
+        let half = ((hi - lo).max_element() / 2.0).max(1.0);
+        let mut swarm = Swarm {
+            nodes: vec![Node::new((lo + hi) / 2.0, half)],
+            positions,
+            gm,
+            exponent,
+        };
+        for particle in 0..swarm.positions.len() {
+            swarm.insert(particle);
+        }
+        swarm
+    }
+
+    fn insert(&mut self, particle: usize) {
+        let pos = self.positions[particle];
+        let mut node = 0;
+        for depth in 0.. {
+            let current = &mut self.nodes[node];
+            current.sum += pos;
+            current.count += 1;
+            if current.count == 1 {
+                current.particle = Some(particle);
+                return;
+            }
+            if depth == MAX_DEPTH {
+                // particles this close share the leaf
+                current.particle = None;
+                return;
+            }
+            let children = match current.children {
+                Some(children) => children,
+                None => {
+                    let children = self.split(node);
+                    // the particle alone here goes down a level
+                    if let Some(other) = self.nodes[node].particle.take() {
+                        let other_pos = self.positions[other];
+                        let child = children + self.nodes[node].quadrant(other_pos);
+                        let child = &mut self.nodes[child];
+                        child.sum += other_pos;
+                        child.count += 1;
+                        child.particle = Some(other);
+                    }
+                    children
+                }
+            };
+            node = children + self.nodes[node].quadrant(pos);
+        }
+    }
+
+    /// Split `node` into four, returning the index of the first.
+    fn split(&mut self, node: usize) -> usize {
+        let (middle, half) = (self.nodes[node].middle, self.nodes[node].half / 2.0);
+        let children = self.nodes.len();
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            self.nodes
+                .push(Node::new(middle + vec2(dx, dy) * half, half));
+        }
+        self.nodes[node].children = Some(children);
+        children
+    }
+
+    /// Acceleration the rest of the swarm exerts on `particle`.
+    pub fn acceleration(&self, particle: usize) -> Vec2 {
+        let own = self.positions[particle];
+        let mut total = Vec2::ZERO;
+        // each node along with whether the particle is among those within it, as it is the root
+        let mut stack = vec![(0, true)];
+        while let Some((node, holds)) = stack.pop() {
+            let node = &self.nodes[node];
+            // leave the particle itself out of whichever cells hold it
+            let (sum, count) = if holds {
+                (node.sum - own, node.count - 1)
+            } else {
+                (node.sum, node.count)
+            };
+            if count == 0 {
+                continue;
+            }
+            let center = sum / count as f32;
+            let width = node.half * 2.0;
+            let far = width * width < THETA * THETA * own.distance_squared(center);
+            match node.children {
+                Some(children) if !far => {
+                    let own_child = holds.then(|| children + node.quadrant(own));
+                    stack.extend(
+                        (children..children + 4).map(|child| (child, Some(child) == own_child)),
+                    );
+                }
+                _ => total += pull(center - own, self.gm * count as f32, self.exponent),
+            }
+        }
+        total
+    }
+}
+
+/// Softened acceleration towards a mass of strength `gm` at `offset`, falling off as
+/// `1 / r^exponent` as the central mass's pull does, as hard as it on the average orbit.
+fn pull(offset: Vec2, gm: f32, exponent: f32) -> Vec2 {
+    let r2 = offset.length_squared() + SOFTENING * SOFTENING;
+    // the usual inverse square, without the cost of a `powf`, for all a tick's many pulls
+    if exponent == 2.0 {
+        return offset * gm / (r2 * r2.sqrt());
+    }
+    offset * gm * orbital_radius().powf(exponent - 2.0) / r2.powf((exponent + 1.0) / 2.0)
+}
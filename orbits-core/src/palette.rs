@@ -0,0 +1,237 @@
+//! The palettes a generation's trails are colored from, one color per particle in order along
+//! the swarm: the run of hues picked at random for each seed, some of matplotlib's gradients built
+//! in, and any gradients the settings name. Each trail can also fade with age along the part of it
+//! shown, so its oldest points are fainter than where the particle is now and it gives out at the
+//! tail rather than stopping short.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::settings;
+
+/// The palette running round the color wheel from a hue picked at random for each seed, by as far
+/// as the settings' `hue_run` picks.
+pub const HUE_RUN: &str = "hue_run";
+
+/// The gradients built in, as sRGB stops spread evenly from one end to the other.
+const BUILT_IN: [(&str, &[u32]); 5] = [
+    (
+        "viridis",
+        &[
+            0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6ece58,
+            0xb5de2b, 0xfde725,
+        ],
+    ),
+    (
+        "magma",
+        &[
+            0x000004, 0x180f3d, 0x440f76, 0x721f81, 0x9e2f7f, 0xcd4071, 0xf1605d, 0xfd9668,
+            0xfeca8d, 0xfcfdbf,
+        ],
+    ),
+    (
+        "inferno",
+        &[
+            0x000004, 0x1b0c41, 0x4a0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925, 0xfb9b06,
+            0xf7d13d, 0xfcffa4,
+        ],
+    ),
+    (
+        "plasma",
+        &[
+            0x0d0887, 0x47039f, 0x7301a8, 0x9c179e, 0xbd3786, 0xd8576b, 0xed7953, 0xfa9e3b,
+            0xfdc926, 0xf0f921,
+        ],
+    ),
+    (
+        "cividis",
+        &[
+            0x00224e, 0x123570, 0x3b496c, 0x575d6d, 0x707173, 0x8a8779, 0xa69d75, 0xc4b56c,
+            0xe4cf5b, 0xfee838,
+        ],
+    ),
+];
+
+/// Where a palette's colors come from.
+#[derive(Debug, Clone)]
+pub enum Palette {
+    HueRun,
+    /// Stops spread evenly from the first particle's color to the last's, as sRGB from 0 to 1.
+    Gradient(Vec<[f32; 3]>),
+}
+
+impl Palette {
+    /// The palette called `name`, among those built in and `custom`, the gradients the settings
+    /// give as lists of `#rrggbb` stops.
+    pub fn named(name: &str, custom: &BTreeMap<String, Vec<String>>) -> Result<Self, String> {
+        if name == HUE_RUN {
+            return Ok(Palette::HueRun);
+        }
+        if let Some(stops) = custom.get(name) {
+            if stops.is_empty() {
+                return Err(format!("the palette {name:?} has no colors"));
+            }
+            let stops = stops
+                .iter()
+                .map(|stop| parse_hex(stop))
+                .collect::<Result<_, _>>();
+            return stops
+                .map(Palette::Gradient)
+                .map_err(|err| format!("the palette {name:?}: {err}"));
+        }
+        match BUILT_IN.iter().find(|(built_in, _)| *built_in == name) {
+            Some((_, stops)) => Ok(Palette::Gradient(
+                stops.iter().map(|&stop| unpack(stop)).collect(),
+            )),
+            None => Err(format!(
+                "there's no palette called {name:?}, only {}",
+                names(custom).join(", ")
+            )),
+        }
+    }
+
+    /// The color of particle `i` of `count`, in a generation whose hues run `hue_run` round the
+    /// color wheel from `hue_start`, if that's how they're colored.
+    pub fn color(&self, i: usize, count: usize, hue_start: f32, hue_run: f32) -> Hsla {
+        let settings = settings::get();
+        let along = if count > 1 {
+            i as f32 / (count - 1) as f32
+        } else {
+            0.0
+        };
+        match self {
+            Palette::HueRun => hsla(
+                (hue_start + hue_run * along) % 1.0,
+                settings.trail_saturation,
+                settings.trail_lightness,
+                settings.trail_alpha,
+            ),
+            Palette::Gradient(stops) => {
+                let place = along * (stops.len() - 1) as f32;
+                let below = (place.floor() as usize).min(stops.len() - 1);
+                let above = (below + 1).min(stops.len() - 1);
+                let mix = place - below as f32;
+                let [r, g, b] =
+                    [0, 1, 2].map(|c| stops[below][c] * (1.0 - mix) + stops[above][c] * mix);
+                let (hue, saturation, lightness) = to_hsl(r, g, b);
+                hsla(hue, saturation, lightness, settings.trail_alpha)
+            }
+        }
+    }
+}
+
+/// Every palette's name, the hue run's first, then the built-in gradients' and the `custom` ones'.
+pub fn names(custom: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let built_in = BUILT_IN.iter().map(|(name, _)| name.to_string());
+    let custom = custom
+        .keys()
+        .filter(|name| !BUILT_IN.iter().any(|(b, _)| b == name));
+    std::iter::once(HUE_RUN.to_string())
+        .chain(built_in)
+        .chain(custom.cloned())
+        .collect()
+}
+
+/// How quickly trails fade along their length, as the settings' `fade_curve` picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FadeCurve {
+    /// Evenly from the newest point to the oldest.
+    #[default]
+    Linear,
+    /// Quickly behind the particle, then more and more slowly, so most of the trail is faint.
+    Exponential,
+}
+
+/// How sharply an exponential fade falls off, the trail two thirds of the way to its faintest a
+/// quarter of the way along.
+const EXPONENT: f32 = 4.0;
+
+impl FadeCurve {
+    /// How far along towards its faintest a trail is at `age`, from 0 at the newest point to 1 at
+    /// the oldest.
+    pub fn apply(self, age: f32) -> f32 {
+        match self {
+            FadeCurve::Linear => age,
+            FadeCurve::Exponential => (1.0 - (-EXPONENT * age).exp()) / (1.0 - (-EXPONENT).exp()),
+        }
+    }
+
+    /// The curve's number in the GPU's ribbon shader.
+    pub fn index(self) -> u32 {
+        match self {
+            FadeCurve::Linear => 0,
+            FadeCurve::Exponential => 1,
+        }
+    }
+}
+
+/// How far along the `span` epochs of a trail shown the point it left `epoch` records ago is,
+/// from 0 for the newest to 1 for the oldest.
+pub fn age(epoch: usize, span: usize) -> f32 {
+    (epoch as f32 / span.saturating_sub(1).max(1) as f32).min(1.0)
+}
+
+/// Multiplier on the alpha of the point a trail left `epoch` records ago, out of the `span` epochs
+/// of it shown, from 1 for the newest down to the settings' `trail_fade` for the oldest.
+pub fn fade(epoch: usize, span: usize) -> f32 {
+    let settings = settings::get();
+    1.0 - (1.0 - settings.trail_fade) * settings.fade_curve.apply(age(epoch, span))
+}
+
+/// The name and stops of the gradient in the file at `path`, its `#rrggbb` stops in order,
+/// separated by whitespace or commas, named after the file.
+pub fn load(path: &Path) -> Result<(String, Vec<String>), String> {
+    let err = |err: String| format!("{}: {err}", path.display());
+    let text = fs::read_to_string(path).map_err(|e| err(e.to_string()))?;
+    let stops: Vec<String> = (text.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|stop| !stop.is_empty())
+        .map(str::to_string)
+        .collect();
+    if stops.is_empty() {
+        return Err(err("there are no colors in it".into()));
+    }
+    for stop in &stops {
+        parse_hex(stop).map_err(err)?;
+    }
+    let name =
+        (path.file_stem()).map_or("loaded".into(), |stem| stem.to_string_lossy().into_owned());
+    Ok((name, stops))
+}
+
+fn parse_hex(stop: &str) -> Result<[f32; 3], String> {
+    let hex = stop.strip_prefix('#').unwrap_or(stop);
+    match u32::from_str_radix(hex, 16) {
+        Ok(rgb) if hex.len() == 6 => Ok(unpack(rgb)),
+        _ => Err(format!("{stop:?} isn't a color like \"#ff8800\"")),
+    }
+}
+
+fn unpack(rgb: u32) -> [f32; 3] {
+    [16, 8, 0].map(|shift| ((rgb >> shift) & 0xff) as f32 / 255.0)
+}
+
+/// The hue, from 0 to 1, saturation and lightness of the sRGB color `r`, `g`, `b`.
+fn to_hsl(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let most = r.max(g).max(b);
+    let least = r.min(g).min(b);
+    let lightness = (most + least) / 2.0;
+    let chroma = most - least;
+    if chroma <= 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+    let sixths = if most == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if most == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    (sixths / 6.0, saturation, lightness)
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::physics::{GravityLaw, EXPONENTS};
+use crate::settings;
+
+/// Most particles the `Particles` parameter can ask for.
+const MAX_PARTICLES: f32 = 512.0;
+/// Most the `VaryVelocity` parameter can have starting speeds stray, in world units a second.
+const MAX_VARY_VELOCITY: f32 = 1000.0;
+
+/// A continuous value that can be changed while the piece runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Param {
+    /// Speed of simulated time relative to the wall clock.
+    TimeScale,
+    /// Multiplier on the strength of the central mass.
+    Gravity,
+    /// Particles in the current generation, added or taken away from the end of the swarm.
+    Particles,
+    /// Fraction of each trail drawn, newest first.
+    TrailLength,
+    /// Size of the random walk the trail colors take, per 60th of a second.
+    ColorJitter,
+    /// How far round the color wheel the background is turned from the hue it was seeded with.
+    BackgroundHue,
+    /// Multiplier on the alpha of every trail.
+    TrailOpacity,
+    /// How strongly the most interesting trails are picked out from the rest.
+    Highlight,
+    /// How brightly a long exposure is developed.
+    ExposureGain,
+    /// Multiplier on how fast every layer of the scene drifts, spinning and breathing as its
+    /// config has it.
+    LayerDrift,
+    /// Exponent the power gravity law's pull falls off by, leaving any other law as it is.
+    ForceExponent,
+    /// Most a starting speed strays from a circular orbit's, for the particles spawned at random
+    /// from then on, in the generations seeded after and those added to the current one.
+    VaryVelocity,
+}
+
+impl Param {
+    pub const ALL: [Param; 12] = [
+        Param::TimeScale,
+        Param::Gravity,
+        Param::Particles,
+        Param::TrailLength,
+        Param::ColorJitter,
+        Param::BackgroundHue,
+        Param::TrailOpacity,
+        Param::Highlight,
+        Param::ExposureGain,
+        Param::LayerDrift,
+        Param::ForceExponent,
+        Param::VaryVelocity,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Param::TimeScale => "time scale",
+            Param::Gravity => "gravity",
+            Param::Particles => "particles",
+            Param::TrailLength => "trail length",
+            Param::ColorJitter => "color jitter",
+            Param::BackgroundHue => "background hue",
+            Param::TrailOpacity => "trail opacity",
+            Param::Highlight => "highlight",
+            Param::ExposureGain => "exposure gain",
+            Param::LayerDrift => "layer drift",
+            Param::ForceExponent => "force exponent",
+            Param::VaryVelocity => "vary velocity",
+        }
+    }
+
+    pub fn range(self) -> RangeInclusive<f32> {
+        match self {
+            Param::TimeScale => 0.0..=4.0,
+            Param::Gravity => 0.0..=3.0,
+            Param::Particles => 2.0..=MAX_PARTICLES,
+            Param::TrailLength => 0.0..=1.0,
+            Param::ColorJitter => 0.0..=0.05,
+            Param::BackgroundHue => 0.0..=1.0,
+            Param::TrailOpacity => 0.0..=2.0,
+            Param::Highlight => 0.0..=1.0,
+            Param::ExposureGain => 0.0..=32.0,
+            Param::LayerDrift => 0.0..=4.0,
+            Param::ForceExponent => EXPONENTS,
+            Param::VaryVelocity => 0.0..=MAX_VARY_VELOCITY,
+        }
+    }
+
+    fn default_value(self) -> f32 {
+        match self {
+            Param::TimeScale => 1.0,
+            Param::Gravity => 1.0,
+            Param::Particles => (settings::get().particles as f32).min(MAX_PARTICLES),
+            Param::TrailLength => 1.0,
+            Param::ColorJitter => 0.008,
+            Param::BackgroundHue => 0.0,
+            Param::TrailOpacity => 1.0,
+            Param::Highlight => 0.5,
+            Param::ExposureGain => 4.0,
+            Param::LayerDrift => 1.0,
+            Param::ForceExponent => match settings::get().gravity_law {
+                GravityLaw::Power { exponent } => exponent,
+                _ => 2.0,
+            },
+            Param::VaryVelocity => settings::get().vary_velocity.min(MAX_VARY_VELOCITY),
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The current value of every `Param`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Params {
+    /// Those saved before a parameter was added leave it at its default.
+    #[serde(deserialize_with = "saved_values")]
+    values: [f32; Param::ALL.len()],
+}
+
+fn saved_values<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[f32; Param::ALL.len()], D::Error> {
+    let saved = Vec::<f32>::deserialize(deserializer)?;
+    let mut values = Param::ALL.map(Param::default_value);
+    for (value, saved) in values.iter_mut().zip(saved) {
+        *value = saved;
+    }
+    Ok(values)
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            values: Param::ALL.map(Param::default_value),
+        }
+    }
+}
+
+impl Params {
+    pub fn get(&self, param: Param) -> f32 {
+        self.values[param.index()]
+    }
+
+    /// Every parameter along with its value.
+    pub fn iter(&self) -> impl Iterator<Item = (Param, f32)> + '_ {
+        Param::ALL.into_iter().map(|param| (param, self.get(param)))
+    }
+
+    /// Set `param`, clamped to its range.
+    pub fn set(&mut self, param: Param, value: f32) {
+        let range = param.range();
+        self.values[param.index()] = value.clamp(*range.start(), *range.end());
+    }
+}
+
+/// A parameter a signal has moved, and where it was put before it did.
+struct Moved {
+    base: f32,
+    applied: f32,
+}
+
+/// Parameters moved by signals from where they've been put, as sound or a reading of the
+/// simulation moves them.
+#[derive(Default)]
+pub struct Modulation {
+    moved: BTreeMap<Param, Moved>,
+}
+
+impl Modulation {
+    /// Move each parameter of `offsets` that far from where it's been put. Parameters put
+    /// somewhere else since they were last moved, by hand or otherwise, are moved from there.
+    pub fn apply(&mut self, offsets: BTreeMap<Param, f32>, params: &mut Params) {
+        for (param, offset) in offsets {
+            let current = params.get(param);
+            let base = match self.moved.get(&param) {
+                Some(moved) if moved.applied == current => moved.base,
+                _ => current,
+            };
+            params.set(param, base + offset);
+            let applied = params.get(param);
+            self.moved.insert(param, Moved { base, applied });
+        }
+    }
+}
@@ -0,0 +1,587 @@
+use std::ops::RangeInclusive;
+
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::geometry::Path;
+use crate::integrator::Acceleration;
+use crate::{forces, orbital_radius, plugins, settings};
+
+/// Least distance from a mass its pull and potential are reckoned at, so a particle landing right
+/// on one is flung off rather than turning to NaN along with its trail.
+pub const LEAST_DISTANCE: f32 = 1e-3;
+/// Strength of the central mass, as the pull it gives a particle on the average orbit.
+const STRENGTH: f32 = 57.0;
+/// The exponents the power law can fall off by, as the `force_exponent` parameter turns it.
+pub const EXPONENTS: RangeInclusive<f32> = -1.0..=3.0;
+/// The legacy law's pull, a world unit a second more speed every frame at the 60 frames a second
+/// the first sketch ran at.
+const LEGACY_PULL: f32 = 60.0;
+
+/// The law the masses pull the particles by, as the settings' `gravity_law` picks.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum GravityLaw {
+    /// The inverse square law, whose bound orbits are ellipses that stay put.
+    Newtonian,
+    /// `a = gm / r^n`, `n` going from 2 for the inverse square law, through 1 for the
+    /// logarithmic potential of a 2D universe and 0 for a constant pull, to -1 for a harmonic
+    /// trap. It's as hard as the inverse square's on the average orbit whatever `n` is, and the
+    /// `force_exponent` parameter starts out at it, to turn it as the piece runs.
+    Power { exponent: f32 },
+    /// The pull the first sketch gave every particle: as hard however far from a mass it is,
+    /// along with the drift of `1 / 2r` a second it added to both the x and y of its velocity.
+    /// Not a pull any potential has, and it ignores the force exponent.
+    #[default]
+    Legacy,
+    /// The inverse square law smoothed out within about `softening` world units of a mass, so
+    /// those passing close aren't flung off.
+    Softened { softening: f32 },
+    /// The inverse square law with a pull added that grows as `1 / r^4` close in, the form
+    /// relativity's correction takes for an orbit of a given angular momentum, `strength` times
+    /// the inverse square's out on the average orbit. Each ellipse turns round a little every
+    /// orbit, the more so the closer in it dips.
+    PostNewtonian { strength: f32 },
+}
+
+impl GravityLaw {
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            GravityLaw::Softened { softening } if softening <= 0.0 => Err(format!(
+                "the softened gravity law's softening has to be positive, not {softening}"
+            )),
+            GravityLaw::PostNewtonian { strength } if strength < 0.0 => Err(format!(
+                "the post-Newtonian gravity law's strength can't be negative, not {strength}"
+            )),
+            GravityLaw::Power { exponent } if !EXPONENTS.contains(&exponent) => Err(format!(
+                "the power gravity law's exponent goes from {} to {}, not {exponent}",
+                EXPONENTS.start(),
+                EXPONENTS.end()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The exponent `n` the pull falls off as `1 / r^n` by, far from the masses.
+    pub fn exponent(self) -> f32 {
+        match self {
+            GravityLaw::Legacy => 0.0,
+            GravityLaw::Power { exponent } => exponent,
+            _ => 2.0,
+        }
+    }
+
+    /// The law with the power law's exponent turned to `exponent`, and any other as it is.
+    pub fn with_exponent(self, exponent: f32) -> Self {
+        match self {
+            GravityLaw::Power { .. } => GravityLaw::Power { exponent },
+            law => law,
+        }
+    }
+
+    /// How hard a mass of strength `gm` pulls at `r` from it, in single precision or double.
+    pub fn pull<F: Float>(self, r: F, gm: F) -> F {
+        let f = |value: f32| -> F { NumCast::from(value).unwrap() };
+        let r = r.max(f(LEAST_DISTANCE));
+        match self {
+            GravityLaw::Newtonian => gm / (r * r),
+            GravityLaw::Legacy => {
+                gm * f(LEGACY_PULL / STRENGTH / (orbital_radius() * orbital_radius()))
+            }
+            GravityLaw::Power { exponent } => {
+                let radius = f(orbital_radius());
+                gm / (radius * radius) * (radius / r).powf(f(exponent))
+            }
+            GravityLaw::Softened { softening } => {
+                let r2 = r * r + f(softening) * f(softening);
+                gm * r / (r2 * r2.sqrt())
+            }
+            GravityLaw::PostNewtonian { strength } => {
+                let close = f(orbital_radius()) / r;
+                gm / (r * r) * (F::one() + f(strength) * close * close)
+            }
+        }
+    }
+
+    /// The potential energy per unit mass at `r` from a mass of strength `gm`, whose slope is the
+    /// pull there, taken as 0 far away where it can be.
+    pub fn potential(self, r: f32, gm: f32) -> f32 {
+        let r = r.max(LEAST_DISTANCE);
+        match self {
+            GravityLaw::Newtonian => -gm / r,
+            // a pull that doesn't fall off has no bottom to measure from far away, and the drift
+            // alongside it, being the same every way round, none at all
+            GravityLaw::Legacy => {
+                gm * LEGACY_PULL / STRENGTH / (orbital_radius() * orbital_radius()) * r
+            }
+            // a pull falling off no faster than `1 / r` has no bottom to measure from far away
+            GravityLaw::Power { exponent: 1.0 } => gm / orbital_radius() * r.ln(),
+            GravityLaw::Power { exponent } => {
+                let scale = gm * orbital_radius().powf(exponent - 2.0);
+                -scale / ((exponent - 1.0) * r.powf(exponent - 1.0))
+            }
+            GravityLaw::Softened { softening } => -gm / (r * r + softening * softening).sqrt(),
+            GravityLaw::PostNewtonian { strength } => {
+                let close = orbital_radius() / r;
+                -gm / r * (1.0 + strength * close * close / 3.0)
+            }
+        }
+    }
+
+    /// The drift a particle at `r` from a mass picks up alongside its pull, in both x and y.
+    pub fn drift<F: Float>(self, r: F) -> F {
+        match self {
+            GravityLaw::Legacy => {
+                let f = |value: f32| -> F { NumCast::from(value).unwrap() };
+                f(0.5) / r.max(f(LEAST_DISTANCE))
+            }
+            _ => F::zero(),
+        }
+    }
+
+    fn describe(self) -> String {
+        match self {
+            GravityLaw::Newtonian => "1/r^2".into(),
+            GravityLaw::Legacy => "constant, as the first sketch pulled".into(),
+            GravityLaw::Power { exponent } => format!("1/r^{exponent}"),
+            GravityLaw::Softened { softening } => format!("1/r^2, softened within {softening}"),
+            GravityLaw::PostNewtonian { strength } => format!("1/r^2 + {strength} r0^2/r^4"),
+        }
+    }
+}
+
+/// How the central mass evolves over simulated time, relative to its mass at t=0, as the
+/// settings' `mass_program` picks.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MassProgram {
+    #[default]
+    Constant,
+    /// Grow linearly by `rate` times the initial mass every second, shrinking if negative.
+    Grow { rate: f32 },
+    /// Oscillate around the initial mass by a fraction `depth`, once every `period` seconds.
+    Pulse { depth: f32, period: f32 },
+    /// Lose half of the remaining mass every `half_life` seconds.
+    Decay { half_life: f32 },
+}
+
+impl MassProgram {
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            MassProgram::Pulse { depth, .. } if !(0.0..=1.0).contains(&depth) => Err(format!(
+                "the pulsing central mass's depth goes from 0 to 1, not {depth}"
+            )),
+            MassProgram::Pulse { period, .. } if period <= 0.0 => Err(format!(
+                "the pulsing central mass's period has to be positive, not {period}"
+            )),
+            MassProgram::Decay { half_life } if half_life <= 0.0 => Err(format!(
+                "the decaying central mass's half_life has to be positive, not {half_life}"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn factor(self, t: f32) -> f32 {
+        match self {
+            MassProgram::Constant => 1.0,
+            MassProgram::Grow { rate } => (1.0 + rate * t).max(0.0),
+            MassProgram::Pulse { depth, period } => 1.0 + depth * (t / period * TAU).sin(),
+            MassProgram::Decay { half_life } => 0.5f32.powf(t / half_life),
+        }
+    }
+}
+
+/// Two bodies on circular orbits around their common barycenter at the origin, as the settings'
+/// `binary` splits the central mass into.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Binary {
+    /// Fraction of the central mass held by the secondary, 0.5 for an equal pair.
+    pub secondary_fraction: f32,
+    /// Distance between the two bodies.
+    pub separation: f32,
+}
+
+impl Binary {
+    pub fn check(&self) -> Result<(), String> {
+        if !(0.0..=0.5).contains(&self.secondary_fraction) {
+            return Err(format!(
+                "the binary's secondary_fraction goes from 0 to 0.5, not {}",
+                self.secondary_fraction
+            ));
+        }
+        if self.separation <= 0.0 {
+            return Err(format!(
+                "the binary's separation has to be positive, not {}",
+                self.separation
+            ));
+        }
+        Ok(())
+    }
+
+    /// The pair a central mass of strength `gm` pulling by `law` is split into, turned `phase`
+    /// round their barycenter at the origin.
+    pub fn bodies(&self, gm: f32, law: GravityLaw, phase: f32) -> [Body; 2] {
+        let q = self.secondary_fraction;
+        let axis = vec2(phase.cos(), phase.sin()) * self.separation;
+        [
+            Body {
+                pos: -axis * q,
+                gm: gm * (1.0 - q),
+                law,
+            },
+            Body {
+                pos: axis * (1.0 - q),
+                gm: gm * q,
+                law,
+            },
+        ]
+    }
+}
+
+/// A point mass the particles are attracted to.
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    pub pos: Point2,
+    pub gm: f32,
+    /// The law it pulls by, the settings' with the power law's exponent as the parameter has it.
+    pub law: GravityLaw,
+}
+
+/// Label and value pairs describing the force model, for the help overlay.
+pub fn describe() -> Vec<(String, String)> {
+    let center = match settings::get().binary {
+        None => "single body".into(),
+        Some(binary) => format!(
+            "binary, {:.0}% secondary, {} apart",
+            binary.secondary_fraction * 100.0,
+            binary.separation
+        ),
+    };
+    let mut description = vec![
+        ("force law".into(), settings::get().gravity_law.describe()),
+        (
+            "central mass".into(),
+            format!("{:?}", settings::get().mass_program),
+        ),
+        ("center".into(), center),
+    ];
+    let settings = settings::get();
+    if let Some(center_path) = &settings.center_path {
+        let path = match center_path.path {
+            Path::Lissajous { .. } => "Lissajous",
+            Path::Spline { .. } => "spline",
+        };
+        description.push((
+            "center path".into(),
+            format!("{path}, {}s a lap", center_path.period),
+        ));
+    }
+    if !settings.attractors.is_empty() {
+        let count = settings.attractors.len();
+        description.push(("attractors".into(), count.to_string()));
+    }
+    if !settings.fields.is_empty() {
+        let names: Vec<&str> = settings.fields.iter().map(|field| field.name()).collect();
+        description.push(("fields".into(), names.join(", ")));
+    }
+    let plugins = plugins::names();
+    if !plugins.is_empty() {
+        description.push(("plugins".into(), plugins.join(", ")));
+    }
+    description
+}
+
+/// Strength of the central mass at time `t`, normalized so a particle on the average orbit feels
+/// the same initial pull whatever the force law.
+pub fn gm(t: f32) -> f32 {
+    STRENGTH * orbital_radius() * orbital_radius() * settings::get().mass_program.factor(t)
+}
+
+/// Acceleration felt on the average orbit around the central mass at time `t`.
+pub fn reference_acceleration(t: f32) -> f32 {
+    settings::get().gravity_law.pull(orbital_radius(), gm(t))
+}
+
+/// Total acceleration felt at `pos` due to all the `bodies`, leaving out any from plugins, which
+/// depend on a particle's velocity too.
+pub fn acceleration_at(pos: Point2, bodies: &[Body]) -> Vec2 {
+    bodies.iter().fold(Vec2::ZERO, |acc, body| {
+        acc + acceleration(pos - body.pos, body.gm, body.law)
+    })
+}
+
+/// Acceleration felt by a particle at `pos` going `vel`, from `bodies` and from any fields or
+/// plugins.
+pub fn pull(pos: Point2, vel: Vec2, bodies: &[Body]) -> Vec2 {
+    acceleration_at(pos, bodies) + forces::acceleration(pos, vel) + plugins::acceleration(pos, vel)
+}
+
+/// Acceleration towards a body of strength `gm` at the origin pulling by `law` felt at `pos`,
+/// along the x axis right on it, where every way is as much towards it as any other.
+fn acceleration(pos: Point2, gm: f32, law: GravityLaw) -> Vec2 {
+    let r = pos.length();
+    -normalize_or(pos, Vec2::X) * law.pull(r, gm) + Vec2::splat(law.drift(r))
+}
+
+/// `v` scaled to a length of 1, or `fallback` if it's too short to have a direction.
+pub fn normalize_or(v: Vec2, fallback: Vec2) -> Vec2 {
+    let unit = v.normalize_or_zero();
+    if unit == Vec2::ZERO {
+        fallback
+    } else {
+        unit
+    }
+}
+
+/// Speed needed to stay on a circular orbit of radius `r`, where `v^2 / r` is the pull there.
+pub fn circular_speed(r: f32, gm: f32) -> f32 {
+    (settings::get().gravity_law.pull(r, gm) * r).sqrt()
+}
+
+/// Eccentricity of the Kepler orbit a particle at `pos` going `vel` would follow around a mass of
+/// strength `gm` at the origin, from 0 for a circle to 1 and over for one that escapes.
+pub fn eccentricity(pos: Point2, vel: Vec2, gm: f32) -> f32 {
+    let r = pos.length().max(LEAST_DISTANCE);
+    ((vel.length_squared() - gm / r) * pos - pos.dot(vel) * vel).length() / gm
+}
+
+/// The path the central mass is carried round, as the settings' `center_path` gives it, the whole
+/// system dragged along behind it.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CenterPath {
+    pub path: Path,
+    /// Simulated seconds a lap of the path takes.
+    pub period: f32,
+}
+
+impl CenterPath {
+    pub fn check(&self) -> Result<(), String> {
+        if self.period <= 0.0 {
+            return Err(format!(
+                "the center path's period has to be positive, not {}",
+                self.period
+            ));
+        }
+        self.path.check()
+    }
+
+    /// How far the center has been carried from where it set out by time `t`.
+    pub fn offset(&self, t: f32) -> Vec2 {
+        self.path.at(t / self.period) - self.path.at(0.0)
+    }
+}
+
+/// Where the central mass has been carried to by time `t` along the settings' `center_path`, and
+/// how fast it's going there, or the origin at rest without one.
+pub fn center_motion(t: f32) -> (Point2, Vec2) {
+    let Some(center_path) = &settings::get().center_path else {
+        return (Point2::ZERO, Vec2::ZERO);
+    };
+    let h = center_path.period * 1e-3;
+    let vel = (center_path.offset(t + h) - center_path.offset(t - h)) / (2.0 * h);
+    (center_path.offset(t), vel)
+}
+
+/// The central mass of the system, either a single body or a binary pair.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Center {
+    /// Orbital phase of the binary pair, integrated so a changing mass changes its angular speed
+    /// without making the pair jump.
+    phase: f32,
+}
+
+impl Center {
+    pub fn advance(&mut self, t: f32, delta_seconds: f32) {
+        if let Some(binary) = settings::get().binary {
+            // both bodies feel each other as if the whole mass sat at a distance `separation`
+            let law = settings::get().gravity_law;
+            let omega = (law.pull(binary.separation, gm(t)) / binary.separation).sqrt();
+            self.phase = (self.phase + omega * delta_seconds) % TAU;
+        }
+    }
+
+    /// The bodies the central mass is made of at time `t`, carried along any `center_path`.
+    pub fn bodies(&self, t: f32) -> Vec<Body> {
+        let gm = gm(t);
+        let law = settings::get().gravity_law;
+        let (carried, _) = center_motion(t);
+        let bodies = match settings::get().binary {
+            None => vec![Body {
+                pos: Point2::ZERO,
+                gm,
+                law,
+            }],
+            Some(binary) => binary.bodies(gm, law, self.phase).to_vec(),
+        };
+        (bodies.into_iter())
+            .map(|body| Body {
+                pos: body.pos + carried,
+                ..body
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Particle {
+    pub pos: Point2,
+    pub vel: Vec2,
+}
+
+impl Particle {
+    /// Step forward by `delta_seconds` under the pull of `bodies` and any plugins, with the
+    /// integrator picked in the settings.
+    pub fn update(&mut self, delta_seconds: f32, bodies: &[Body]) {
+        self.update_with(delta_seconds, &|pos, vel| pull(pos, vel, bodies));
+    }
+
+    /// Step forward by `delta_seconds` under `acceleration`, with the integrator picked in the
+    /// settings.
+    pub fn update_with(&mut self, delta_seconds: f32, acceleration: &Acceleration) {
+        settings::get()
+            .integrator
+            .integrator()
+            .step(self, delta_seconds, acceleration);
+    }
+
+    /// Pull the velocity `amount` of the way, from 0 to 1, towards that of the circular orbit
+    /// through where the particle is, around the combined mass of `bodies`, going the same way
+    /// round as it is.
+    pub fn settle(&mut self, bodies: &[Body], amount: f32) {
+        let gm = bodies.iter().map(|body| body.gm).sum();
+        let tangent = vec2(self.pos.y, -self.pos.x).normalize_or_zero();
+        let tangent = if tangent.dot(self.vel) < 0.0 {
+            -tangent
+        } else {
+            tangent
+        };
+        let circular = tangent * circular_speed(self.pos.length().max(1.0), gm);
+        self.vel = self.vel.lerp(circular, amount.clamp(0.0, 1.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrator::Scheme;
+
+    #[test]
+    fn normalizing_nothing_falls_back() {
+        assert_eq!(normalize_or(Vec2::ZERO, Vec2::Y), Vec2::Y);
+        assert_eq!(normalize_or(vec2(0.0, -3.0), Vec2::Y), -Vec2::Y);
+        let tiny = normalize_or(Vec2::splat(f32::MIN_POSITIVE), Vec2::Y);
+        assert!(tiny.is_finite());
+    }
+
+    #[test]
+    fn the_pull_is_finite_right_on_a_mass() {
+        let gm = gm(0.0);
+        let law = settings::get().gravity_law;
+        assert!(acceleration(Point2::ZERO, gm, law).is_finite());
+        assert!(circular_speed(0.0, gm).is_finite());
+        assert!(settings::get().gravity_law.potential(0.0, gm).is_finite());
+        assert!(eccentricity(Point2::ZERO, vec2(1.0, 0.0), gm).is_finite());
+    }
+
+    #[test]
+    fn the_legacy_law_steps_as_the_first_sketch_did() {
+        assert_eq!(GravityLaw::default(), GravityLaw::Legacy);
+        let dt = 1.0 / 60.0;
+        let start = (pt2(1000.0, 0.0), vec2(30.0, -238.0));
+        let (mut pos, mut vel) = start;
+        let mut particle = Particle {
+            pos: start.0,
+            vel: start.1,
+        };
+        let gm = gm(0.0);
+        for _ in 0..600 {
+            // the first sketch's step, word for word
+            pos += vel * dt;
+            let force = 1.0 / (pos.length() * 2.0);
+            let gravity = -pos.normalize() + force * dt;
+            vel += gravity;
+
+            Scheme::SemiImplicitEuler
+                .integrator()
+                .step(&mut particle, dt, &|pos, _| {
+                    acceleration(pos, gm, GravityLaw::Legacy)
+                });
+        }
+        assert!(particle.pos.distance(pos) < 0.5, "{} {pos}", particle.pos);
+        assert!(particle.vel.distance(vel) < 0.05, "{} {vel}", particle.vel);
+    }
+
+    #[test]
+    fn the_power_law_follows_its_exponent() {
+        let gm = gm(0.0);
+        let newtonian = GravityLaw::Newtonian.pull(orbital_radius(), gm);
+        for exponent in [-1.0, 0.0, 1.0, 1.5, 2.0, 3.0] {
+            let law = GravityLaw::Newtonian.with_exponent(exponent);
+            assert_eq!(law, GravityLaw::Newtonian);
+            let law = GravityLaw::Power { exponent: 2.0 }.with_exponent(exponent);
+            assert_eq!(law.exponent(), exponent);
+            let on_orbit = law.pull(orbital_radius(), gm);
+            assert!(
+                (on_orbit - newtonian).abs() < 1e-3 * newtonian,
+                "{exponent}"
+            );
+            let twice = law.pull(2.0 * orbital_radius(), gm);
+            let expected = on_orbit / 2.0f32.powf(exponent);
+            assert!((twice - expected).abs() < 1e-3 * expected, "{exponent}");
+            // the potential's slope is the pull
+            let (r, h) = (orbital_radius(), orbital_radius() / 100.0);
+            let slope = (law.potential(r + h, gm) - law.potential(r - h, gm)) / (2.0 * h);
+            assert!(
+                (slope - on_orbit).abs() < 1e-2 * on_orbit,
+                "{exponent} {slope}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_binary_changes_the_pull() {
+        let gm = gm(0.0);
+        let law = GravityLaw::Newtonian;
+        let single = [Body {
+            pos: Point2::ZERO,
+            gm,
+            law,
+        }];
+        let binary = Binary {
+            secondary_fraction: 0.3,
+            separation: 80.0,
+        };
+        assert!(binary.check().is_ok());
+        let pair = binary.bodies(gm, law, 0.0);
+        assert_eq!(pair[0].gm + pair[1].gm, gm);
+        // the barycenter stays at the origin
+        let barycenter = pair[0].pos * pair[0].gm + pair[1].pos * pair[1].gm;
+        assert!(barycenter.length() < 1e-3 * gm, "{barycenter}");
+        let pos = pt2(60.0, 20.0);
+        let apart = acceleration_at(pos, &single).distance(acceleration_at(pos, &pair));
+        assert!(
+            apart > 0.1 * acceleration_at(pos, &single).length(),
+            "{apart}"
+        );
+        // turned round, the pair pulls differently again
+        let turned = binary.bodies(gm, law, PI / 2.0);
+        assert!(acceleration_at(pos, &pair).distance(acceleration_at(pos, &turned)) > 0.0);
+    }
+
+    #[test]
+    fn a_particle_starting_at_the_origin_stays_finite() {
+        let bodies = Center::default().bodies(0.0);
+        let mut particle = Particle {
+            pos: Point2::ZERO,
+            vel: Vec2::ZERO,
+        };
+        for _ in 0..60 {
+            particle.update(1.0 / 60.0, &bodies);
+            assert!(particle.pos.is_finite(), "{:?}", particle.pos);
+            assert!(particle.vel.is_finite(), "{:?}", particle.vel);
+        }
+    }
+}
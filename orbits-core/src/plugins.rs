@@ -0,0 +1,134 @@
+//! Force modules compiled separately and loaded at startup, so new forces can be tried without
+//! rebuilding a frontend. A plugin is a shared library exporting a function named
+//! `trynannou_force_plugin` that returns a [`ForcePlugin`] describing it, through a C ABI so it can
+//! be written in any language. `examples/vortex_plugin.rs` is one, in Rust.
+
+use std::ffi::{c_char, CStr};
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use libloading::Library;
+use nannou_core::prelude::*;
+
+/// Version of the ABI, which a plugin reports back to show it was built against this one.
+pub const ABI_VERSION: u32 = 1;
+/// Where plugins are looked for, relative to the working directory.
+pub const PLUGINS_DIR: &str = "plugins";
+/// Name of the function every plugin exports.
+const ENTRY_POINT: &[u8] = b"trynannou_force_plugin\0";
+
+/// A 2D vector, crossing the ABI by value.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Vector {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// What a plugin's entry point returns.
+#[repr(C)]
+pub struct ForcePlugin {
+    /// The `ABI_VERSION` the plugin was built against.
+    pub abi_version: u32,
+    /// A name for the plugin, NUL-terminated and valid for as long as the library is loaded.
+    pub name: *const c_char,
+    /// Acceleration felt by a particle at `pos` moving at `vel`, in world units per second per
+    /// second. Called from whichever thread steps the simulation.
+    pub acceleration: extern "C" fn(pos: Vector, vel: Vector) -> Vector,
+}
+
+/// The signature of `trynannou_force_plugin`.
+pub type EntryPoint = unsafe extern "C" fn() -> ForcePlugin;
+
+/// A plugin that has been loaded, along with the library keeping its code mapped in.
+struct Loaded {
+    name: String,
+    acceleration: extern "C" fn(pos: Vector, vel: Vector) -> Vector,
+    _library: Library,
+}
+
+static LOADED: OnceLock<Vec<Loaded>> = OnceLock::new();
+
+/// Load every plugin in `dir`, if there is such a directory, returning their names. Plugins can
+/// only be loaded once a run, before the simulation starts.
+pub fn load(dir: &Path) -> Result<Vec<String>, String> {
+    let err = |err: &dyn std::fmt::Display| format!("{}: {err}", dir.display());
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(err(&e)),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| err(&e))?.path();
+        if path
+            .extension()
+            .is_some_and(|e| e == std::env::consts::DLL_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+    // loaded in order of name, so forces add up the same way every run
+    paths.sort();
+
+    let plugins = paths
+        .iter()
+        .map(|path| load_one(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let names = plugins.iter().map(|plugin| plugin.name.clone()).collect();
+    LOADED
+        .set(plugins)
+        .map_err(|_| "plugins are already loaded".to_string())?;
+    Ok(names)
+}
+
+fn load_one(path: &Path) -> Result<Loaded, String> {
+    let err = |err: &dyn std::fmt::Display| format!("{}: {err}", path.display());
+    // SAFETY: a plugin is trusted like the rest of the program, running its initializers on load
+    // and holding to the ABI above
+    unsafe {
+        // names the file itself
+        let library = Library::new(path).map_err(|e| e.to_string())?;
+        let entry_point = library
+            .get::<EntryPoint>(ENTRY_POINT)
+            .map_err(|e| err(&e))?;
+        let plugin = entry_point();
+        if plugin.abi_version != ABI_VERSION {
+            return Err(err(&format!(
+                "built for plugin ABI {}, not {ABI_VERSION}",
+                plugin.abi_version
+            )));
+        }
+        let name = if plugin.name.is_null() {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into()
+        } else {
+            CStr::from_ptr(plugin.name).to_string_lossy().into()
+        };
+        Ok(Loaded {
+            name,
+            acceleration: plugin.acceleration,
+            _library: library,
+        })
+    }
+}
+
+/// Names of the plugins loaded.
+pub fn names() -> Vec<String> {
+    loaded().iter().map(|plugin| plugin.name.clone()).collect()
+}
+
+/// Total acceleration the plugins exert on a particle at `pos` moving at `vel`.
+pub fn acceleration(pos: Point2, vel: Vec2) -> Vec2 {
+    loaded().iter().fold(Vec2::ZERO, |acc, plugin| {
+        let a = (plugin.acceleration)(Vector { x: pos.x, y: pos.y }, Vector { x: vel.x, y: vel.y });
+        acc + vec2(a.x, a.y)
+    })
+}
+
+fn loaded() -> &'static [Loaded] {
+    LOADED.get().map_or(&[], Vec::as_slice)
+}
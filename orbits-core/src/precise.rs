@@ -0,0 +1,82 @@
+//! Simulating the particles in double precision, with `precision = "double"` in `[simulation]`,
+//! for long runs over which single precision's rounding would build up into orbits drifting
+//! off. Each particle is stepped in double precision alongside the single precision one the rest
+//! of the piece reads, which is rounded from it after every tick, so it's only in single
+//! precision as it's drawn. The masses pull in double precision, and fields, plugins and the
+//! swarm's own pull, each small beside them, are added in single.
+
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{Body, Particle};
+use crate::{forces, plugins, settings};
+
+/// The precision the particles are simulated in, as the `precision` of `[simulation]` names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    /// Single precision, as they're drawn in.
+    #[default]
+    Single,
+    /// Double precision, rounded to single only to be drawn. Slower, and stepped on the CPU even
+    /// with `--gpu`.
+    Double,
+}
+
+/// A particle in double precision.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Precise {
+    pub pos: DVec2,
+    pub vel: DVec2,
+}
+
+impl Precise {
+    pub fn new(particle: &Particle) -> Self {
+        Precise {
+            pos: particle.pos.as_f64(),
+            vel: particle.vel.as_f64(),
+        }
+    }
+
+    /// The particle in single precision.
+    pub fn rounded(&self) -> Particle {
+        Particle {
+            pos: self.pos.as_f32(),
+            vel: self.vel.as_f32(),
+        }
+    }
+
+    /// Whether `particle` is this one rounded, rather than moved since by something else.
+    pub fn rounds_to(&self, particle: &Particle) -> bool {
+        self.pos.as_f32() == particle.pos && self.vel.as_f32() == particle.vel
+    }
+
+    /// Step forward by `delta_seconds` under the pull of `bodies`, any fields and plugins, and
+    /// `extra` besides, with the integrator picked in the settings.
+    pub fn update(&mut self, delta_seconds: f64, bodies: &[Body], extra: Vec2) {
+        let acceleration = |pos: DVec2, vel: DVec2| {
+            let (pos32, vel32) = (pos.as_f32(), vel.as_f32());
+            let rest = forces::acceleration(pos32, vel32) + plugins::acceleration(pos32, vel32);
+            acceleration_at(pos, bodies) + (rest + extra).as_f64()
+        };
+        settings::get().integrator.advance(
+            &mut self.pos,
+            &mut self.vel,
+            delta_seconds,
+            &acceleration,
+        );
+    }
+}
+
+/// Total acceleration felt at `pos` due to all the `bodies`, in double precision.
+pub fn acceleration_at(pos: DVec2, bodies: &[Body]) -> DVec2 {
+    bodies.iter().fold(DVec2::ZERO, |acc, body| {
+        let law = body.law;
+        let offset = pos - body.pos.as_f64();
+        let r = offset.length();
+        let unit = offset.normalize_or_zero();
+        let unit = if unit == DVec2::ZERO { DVec2::X } else { unit };
+        acc - unit * law.pull(r, body.gm as f64) + DVec2::splat(law.drift(r))
+    })
+}
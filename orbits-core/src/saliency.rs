@@ -0,0 +1,138 @@
+//! Picks out the most interesting orbits so they can be drawn more boldly than the rest. Orbits
+//! are sized up as if the central masses were one inverse-square point at the origin, which is
+//! rough but plenty to rank them by.
+
+use nannou_core::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{self, Body, Particle};
+
+/// How many trails are highlighted at once.
+const HIGHLIGHTED: usize = 3;
+/// Seconds taken to ease a trail in or out of the highlight.
+const EASE_SECONDS: f32 = 1.5;
+/// Passing within this distance of a central body counts as a close encounter.
+pub const ENCOUNTER_RADIUS: f32 = 300.0;
+/// Seconds for the memory of a close encounter to fade to a third.
+const ENCOUNTER_MEMORY: f32 = 4.0;
+/// How near a ratio of orbital periods must be to a small fraction to count as a resonance.
+const RESONANCE_TOLERANCE: f32 = 0.02;
+/// Period ratios considered resonant, as numerator and denominator.
+const RESONANCES: [(f32, f32); 4] = [(1.0, 2.0), (2.0, 3.0), (1.0, 3.0), (3.0, 4.0)];
+/// Alpha multiplier on trails once fully out of the highlight.
+const DIMMED_ALPHA: f32 = 0.4;
+/// Alpha multiplier on trails once fully in the highlight.
+const BOOSTED_ALPHA: f32 = 2.0;
+/// Width of the line traced over a fully highlighted trail, in world units.
+const HIGHLIGHT_WIDTH: f32 = 6.0;
+
+/// Scores every particle of a generation and eases the best few into the highlight.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Saliency {
+    /// How recently each particle had a close encounter, from 1 right after down toward 0.
+    encounters: Vec<f32>,
+    /// How highlighted each trail is, from 0 to 1.
+    emphasis: Vec<f32>,
+}
+
+impl Saliency {
+    /// Forget the particles whose place in `keep` is false, as they're taken out of the swarm.
+    pub fn retain(&mut self, keep: &[bool]) {
+        for scores in [&mut self.encounters, &mut self.emphasis] {
+            let mut keep = keep.iter();
+            scores.retain(|_| *keep.next().unwrap_or(&true));
+        }
+    }
+
+    /// Make room for the half of `particle` split off it, right after it, as highlighted as it.
+    pub fn split(&mut self, particle: usize) {
+        for scores in [&mut self.encounters, &mut self.emphasis] {
+            if particle < scores.len() {
+                scores.insert(particle + 1, scores[particle]);
+            }
+        }
+    }
+
+    pub fn update(&mut self, particles: &[Particle], bodies: &[Body], delta_seconds: f32) {
+        self.encounters.resize(particles.len(), 0.0);
+        self.emphasis.resize(particles.len(), 0.0);
+
+        let gm: f32 = bodies.iter().map(|body| body.gm).sum();
+        let fade = (-delta_seconds / ENCOUNTER_MEMORY).exp();
+        for (encounter, particle) in self.encounters.iter_mut().zip(particles) {
+            let near = bodies
+                .iter()
+                .any(|body| particle.pos.distance(body.pos) < ENCOUNTER_RADIUS);
+            *encounter = if near { 1.0 } else { *encounter * fade };
+        }
+
+        let orbits: Vec<Orbit> = particles.iter().map(|p| Orbit::of(p, gm)).collect();
+        let scores: Vec<f32> = orbits
+            .iter()
+            .enumerate()
+            .map(|(i, orbit)| {
+                let resonant = orbits
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| i != j && orbit.resonates_with(other));
+                orbit.eccentricity.min(1.0) + resonant as u8 as f32 * 0.5 + self.encounters[i]
+            })
+            .collect();
+
+        let mut ranked: Vec<usize> = (0..scores.len()).collect();
+        ranked.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+        let step = (delta_seconds / EASE_SECONDS).min(1.0);
+        for (rank, &i) in ranked.iter().enumerate() {
+            let target = if rank < HIGHLIGHTED { 1.0 } else { 0.0 };
+            self.emphasis[i] += (target - self.emphasis[i]) * step;
+        }
+    }
+
+    fn emphasis(&self, particle: usize) -> f32 {
+        self.emphasis.get(particle).copied().unwrap_or(0.0)
+    }
+
+    /// Multiplier on the alpha of the trail of `particle`, with the highlight at `strength`.
+    pub fn alpha(&self, particle: usize, strength: f32) -> f32 {
+        let highlighted = DIMMED_ALPHA + (BOOSTED_ALPHA - DIMMED_ALPHA) * self.emphasis(particle);
+        1.0 + (highlighted - 1.0) * strength
+    }
+
+    /// Width of the line traced over the trail of `particle`, with the highlight at `strength`.
+    pub fn width(&self, particle: usize, strength: f32) -> f32 {
+        HIGHLIGHT_WIDTH * self.emphasis(particle) * strength
+    }
+}
+
+/// The Kepler orbit a particle would follow around the total mass at the origin.
+struct Orbit {
+    eccentricity: f32,
+    /// Orbital period, or `None` for an unbound particle.
+    period: Option<f32>,
+}
+
+impl Orbit {
+    fn of(particle: &Particle, gm: f32) -> Self {
+        let (r, v) = (particle.pos, particle.vel);
+        let eccentricity = physics::eccentricity(r, v, gm);
+        let energy = v.length_squared() / 2.0 - gm / r.length();
+        let period = (energy < 0.0).then(|| {
+            let semi_major = -gm / (2.0 * energy);
+            TAU * (semi_major.powi(3) / gm).sqrt()
+        });
+        Orbit {
+            eccentricity,
+            period,
+        }
+    }
+
+    fn resonates_with(&self, other: &Orbit) -> bool {
+        let (Some(a), Some(b)) = (self.period, other.period) else {
+            return false;
+        };
+        let ratio = a.min(b) / a.max(b);
+        RESONANCES
+            .iter()
+            .any(|(p, q)| (ratio - p / q).abs() < RESONANCE_TOLERANCE)
+    }
+}
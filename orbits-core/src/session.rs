@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::params::Param;
+
+/// A change made to the running piece, which a session log records and replays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    /// The first generation was seeded with `seed`.
+    Start { seed: u64 },
+    /// A new generation was seeded with `seed`.
+    Reseed { seed: u64 },
+    /// A parameter was set by hand.
+    Set { param: Param, value: f32 },
+    /// Cue `index` of the cue list was started.
+    Cue { index: usize },
+    /// A particle was added to the current generation at `pos`, going `vel`.
+    Spawn { pos: [f32; 2], vel: [f32; 2] },
+    /// Attractor `attractor` of those in the settings was dragged to `pos`.
+    Move { attractor: usize, pos: [f32; 2] },
+    /// Attractor `attractor` of those in the settings was given `mass`, as a fraction of the
+    /// central mass's.
+    Weigh { attractor: usize, mass: f32 },
+    /// The particles of the current generation within `radius` of `pos` were given up to `vel`
+    /// more velocity, less the further out they were.
+    Impulse {
+        pos: [f32; 2],
+        radius: f32,
+        vel: [f32; 2],
+    },
+    /// The current generation's trails were baked into a layer under the live ones, and cleared.
+    Bake,
+    /// The layers baked were cleared away.
+    Unbake,
+    /// The current generation's trails were colored from the palette called `name`, as the
+    /// generations after it are too.
+    Palette { name: String },
+    /// A palette called `name` was loaded from a file, as the `#rrggbb` stops of a gradient, and
+    /// the current generation's trails colored from it as with `Palette`.
+    LoadPalette { name: String, stops: Vec<String> },
+    /// `op` was done to the particles of the current generation at the indices in `particles`.
+    Group { particles: Vec<usize>, op: GroupOp },
+}
+
+/// What's done to a group of particles picked out together.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupOp {
+    /// Their trails turned to `hue`, from 0 to 1.
+    Recolor { hue: f32 },
+    /// Held where they are, their trails shrinking away behind them.
+    Freeze,
+    /// Let go again, on the orbits they were frozen on.
+    Thaw,
+    /// Sped up `factor` times, or slowed for a factor below 1.
+    Boost { factor: f32 },
+    /// Taken out of the swarm.
+    Delete,
+}
+
+/// A change along with the simulated time at which it was made.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: f32,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// Appends every change to a file, one JSON object per line.
+pub struct SessionLog {
+    out: BufWriter<File>,
+}
+
+impl SessionLog {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(SessionLog {
+            out: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, time: f32, change: &Change) {
+        let entry = Entry {
+            time,
+            change: change.clone(),
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        // flush every entry so a crash doesn't lose the tail of the session
+        if let Err(err) = writeln!(self.out, "{line}").and_then(|_| self.out.flush()) {
+            eprintln!("failed to write to the session log: {err}");
+        }
+    }
+}
+
+/// Changes read back from a session log, handed out as simulated time reaches them.
+pub struct Replay {
+    start_seed: Option<u64>,
+    entries: VecDeque<Entry>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let err = |err: &dyn std::fmt::Display| format!("{}: {err}", path.display());
+        let file = File::open(path).map_err(|e| err(&e))?;
+        let mut entries: VecDeque<Entry> = BufReader::new(file)
+            .lines()
+            .map(|line| {
+                let line = line.map_err(|e| err(&e))?;
+                serde_json::from_str(&line).map_err(|e| err(&e))
+            })
+            .collect::<Result<_, _>>()?;
+        let start_seed = match entries.front() {
+            Some(Entry {
+                change: Change::Start { seed },
+                ..
+            }) => Some(*seed),
+            _ => None,
+        };
+        if start_seed.is_some() {
+            entries.pop_front();
+        }
+        Ok(Replay {
+            start_seed,
+            entries,
+        })
+    }
+
+    /// The seed the recorded session started with.
+    pub fn start_seed(&self) -> Option<u64> {
+        self.start_seed
+    }
+
+    /// Remove and return the next change made at or before `time`.
+    pub fn next_due(&mut self, time: f32) -> Option<Change> {
+        if self.entries.front()?.time <= time {
+            self.entries.pop_front().map(|entry| entry.change)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! The tunables of the simulation, for trying out without rebuilding: how many particles each
+//! generation has, how long their trails are, how they start out and move and what colors they're
+//! given. A frontend can set them once a run, from its config, before the first generation is
+//! seeded.
+//!
+//! They're held in a global, set once, rather than passed down as a config, because they're read
+//! everywhere from deep in the physics, the trails and the palettes, by every frontend, and
+//! threading a config through all of those, and every frontend's calls into them, would touch
+//! nearly every function for values that never change once the run starts. Anything that changes
+//! while the piece runs is a `Param` instead, passed in with the parameters. The cost is that a
+//! process runs with the one set of settings, so tests setting their own go in a test binary of
+//! their own.
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::collisions::Collisions;
+use crate::forces::{Attractor, Field};
+use crate::generation::{Thickness, TrailSource};
+use crate::init::Preset;
+use crate::integrator::Scheme;
+use crate::palette::{self, FadeCurve, Palette};
+use crate::physics::{Binary, CenterPath, GravityLaw, MassProgram};
+use crate::precise::Precision;
+use crate::stirring::Stirring;
+
+/// The `[simulation]` section of trynannou's config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Particles each generation is seeded with, at least 2.
+    pub particles: usize,
+    /// Epochs of trail each particle leaves behind, at least 2.
+    pub history: usize,
+    /// Records of each trail kept on past `history`, one of every `archive_every` epochs, packed
+    /// small, for trails minutes long. 0 for none.
+    pub archive: usize,
+    /// Epochs older than `history` from each of which a record is archived, at least 1.
+    pub archive_every: usize,
+    /// The radius of the average orbit, in world units, more than 0. The pull of the masses is
+    /// measured against it and the view framed to it, but `vary_velocity` and `collision_radius`
+    /// stay in world units, so a wider orbit, whose speed goes up with the square root of its
+    /// radius, strays less from circular and collides less.
+    pub orbital_radius: f32,
+    /// How each generation's particles start out. Those added later, as the particle count is
+    /// turned up, are spawned at random whichever it is.
+    pub init: Preset,
+    /// Most a particle's starting speed strays from a circular orbit's, in world units a second,
+    /// for those spawned at random.
+    pub vary_velocity: f32,
+    /// Most eccentric a particle's starting orbit can be, its speed kept within what allows and
+    /// going the way round it was picked to, so below 1 none start out escaping or plunging. Any
+    /// orbit can start out if left out.
+    pub max_eccentricity: Option<f32>,
+    /// How far round the color wheel each generation's trails run, picked at random between these,
+    /// from 0 to 1 for all the way round.
+    pub hue_run: [f32; 2],
+    pub trail_saturation: f32,
+    pub trail_lightness: f32,
+    pub trail_alpha: f32,
+    /// The palette each generation's trails start out colored from, `hue_run` for the run of hues
+    /// above, one of the gradients built in, `viridis`, `magma`, `inferno`, `plasma` or
+    /// `cividis`, or one in `palettes`.
+    pub palette: String,
+    /// Gradients of your own, by name, as the `#rrggbb` colors stops spread evenly along them.
+    pub palettes: BTreeMap<String, Vec<String>>,
+    /// How much of its alpha the oldest point of a trail shown keeps, from 1 for trails as opaque
+    /// all the way along down to 0 for them to fade out.
+    pub trail_fade: f32,
+    /// How the trails fade from the newest point to the oldest.
+    pub fade_curve: FadeCurve,
+    /// How much narrower the ribbons get towards the tail, from 0 for as wide all the way along to
+    /// 1 for them to taper to nothing. Each ribbon is then built on its own, pulled in towards the
+    /// middle of the trails it joins, rather than sharing their points with its neighbors.
+    pub trail_taper: f32,
+    /// How thick the trails are drawn along them: `constant`, with the particle's `speed`, with
+    /// its `mass` once particles have merged, or in a `pulse` running along the swarm. Ribbons of
+    /// varying thickness are each built on their own, as tapered ones are.
+    pub trail_thickness: Thickness,
+    /// What the trails trace: each particle's `position`, where it'll be a few seconds `ahead`,
+    /// the `midpoint` between it and a partner, or where it is `mirrored` through the middle.
+    pub trail_source: TrailSource,
+    /// Epochs by which each successive particle's trail lags the one before it, drawing the swarm
+    /// as a chain of echoes of itself. 0 draws every trail up to the present.
+    pub echo_delay: usize,
+    /// Onion-skin frames of the swarm drawn behind it, each fainter than the last. 0 for none.
+    pub ghost_count: usize,
+    /// Simulated seconds between successive ghost frames, more than 0.
+    pub ghost_spacing: f32,
+    /// Dip into slow motion while any particle is pulled much harder than on the average orbit,
+    /// as one swoops in close to a mass.
+    pub bullet_time: bool,
+    pub background_saturation: f32,
+    pub background_lightness: f32,
+    pub integrator: Scheme,
+    /// The precision the particles are simulated in: `single`, or `double` for long runs over
+    /// which single precision's rounding would build up, at some cost in speed.
+    pub precision: Precision,
+    /// The law the central masses and attractors pull the particles by: `legacy`, the first
+    /// sketch's pull as hard however far away, `newtonian`, `power`, whose exponent the
+    /// `force_exponent` parameter turns, `softened` or `post_newtonian`.
+    pub gravity_law: GravityLaw,
+    /// How the central mass changes over simulated time: `constant`, `grow` by `rate` times
+    /// its starting mass a second, `pulse` by a fraction `depth` of it every `period` seconds, or
+    /// `decay` to half of it every `half_life` seconds.
+    pub mass_program: MassProgram,
+    /// Split the central mass into a pair orbiting each other, `separation` world units apart,
+    /// the lighter holding `secondary_fraction` of it, up to 0.5 for an equal pair. None by
+    /// default, for the one body.
+    pub binary: Option<Binary>,
+    /// Simulated seconds over which a new generation's particles are eased onto circular orbits,
+    /// less and less as it goes, so none are flung out from the start. 0 for none.
+    pub settle_seconds: f32,
+    /// Combined mass of a generation's particles, as a fraction of the central mass's, for them to
+    /// attract each other as well with. 0 for them to feel the center alone.
+    pub swarm_mass: f32,
+    /// A path for the central mass to be carried round over time, a `lissajous` figure or a
+    /// `spline`, dragging the whole system through space along with it. The particles are seeded
+    /// around the center wherever it's got to, moving with it, but settling and stirring still
+    /// reckon their orbits from the middle of the world. None by default, the center staying put.
+    pub center_path: Option<CenterPath>,
+    /// Masses besides the central one for the particles to be attracted to.
+    pub attractors: Vec<Attractor>,
+    /// Forces felt everywhere, added to the pull of the masses.
+    pub fields: Vec<Field>,
+    /// Orbit each particle in a plane of its own, turned to face any way at all, for a frontend to
+    /// show in 3D, rather than all in the one flat plane. Each is simulated in its plane, the
+    /// masses at the center lying in every one of them, so it can't be had with particles pulling
+    /// on each other or with attractors.
+    pub three_d: bool,
+    /// What particles do as they pass within `collision_radius` of each other: pass through, as
+    /// they do by default, bounce off or merge. Those merged are gone from the swarm for good,
+    /// the rest moving up to fill their places.
+    pub collisions: Collisions,
+    /// How close particles pass to run into each other, in world units.
+    pub collision_radius: f32,
+    /// Hold each generation's mean distance from the middle steady by speeding its particles up
+    /// or slowing them down, for runs with no end to stay balanced rather than fly apart or fall
+    /// in. None by default.
+    pub stirring: Option<Stirring>,
+    /// Check every particle each update for a position or velocity that has stopped being a
+    /// number, and respawn any that have. Always on in debug builds.
+    pub validate: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            particles: 16,
+            history: 200,
+            archive: 0,
+            archive_every: 8,
+            orbital_radius: 1000.0,
+            init: Preset::default(),
+            vary_velocity: 100.0,
+            max_eccentricity: None,
+            hue_run: [0.2, 0.4],
+            trail_saturation: 0.5,
+            trail_lightness: 0.5,
+            trail_alpha: 0.5,
+            palette: palette::HUE_RUN.to_string(),
+            palettes: BTreeMap::new(),
+            trail_fade: 1.0,
+            fade_curve: FadeCurve::default(),
+            trail_taper: 0.0,
+            trail_thickness: Thickness::default(),
+            trail_source: TrailSource::default(),
+            echo_delay: 0,
+            ghost_count: 0,
+            ghost_spacing: 1.0,
+            bullet_time: true,
+            background_saturation: 0.38,
+            background_lightness: 0.33,
+            integrator: Scheme::default(),
+            precision: Precision::default(),
+            gravity_law: GravityLaw::default(),
+            mass_program: MassProgram::default(),
+            binary: None,
+            settle_seconds: 0.0,
+            swarm_mass: 0.0,
+            center_path: None,
+            attractors: Vec::new(),
+            fields: Vec::new(),
+            three_d: false,
+            collisions: Collisions::default(),
+            collision_radius: 20.0,
+            stirring: None,
+            validate: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Whether the simulation can run with these settings.
+    pub fn check(&self) -> Result<(), String> {
+        if self.particles < 2 {
+            return Err(format!(
+                "a generation needs at least 2 particles, not {}",
+                self.particles
+            ));
+        }
+        if self.history < 2 {
+            return Err(format!(
+                "trails need at least 2 epochs of history, not {}",
+                self.history
+            ));
+        }
+        if self.archive_every == 0 {
+            return Err("archive_every has to be at least 1".into());
+        }
+        if self.orbital_radius <= 0.0 {
+            return Err(format!(
+                "orbital_radius has to be positive, not {}",
+                self.orbital_radius
+            ));
+        }
+        if let Some(eccentricity) = self.max_eccentricity.filter(|&e| e < 0.0) {
+            return Err(format!(
+                "max_eccentricity can't be negative, not {eccentricity}"
+            ));
+        }
+        if self.ghost_spacing <= 0.0 {
+            return Err(format!(
+                "ghost_spacing has to be positive, not {}",
+                self.ghost_spacing
+            ));
+        }
+        if self.settle_seconds < 0.0 {
+            return Err(format!(
+                "settle_seconds can't be negative, not {}",
+                self.settle_seconds
+            ));
+        }
+        if self.swarm_mass < 0.0 {
+            return Err(format!(
+                "swarm_mass can't be negative, not {}",
+                self.swarm_mass
+            ));
+        }
+        self.gravity_law.check()?;
+        self.mass_program.check()?;
+        if let Some(binary) = &self.binary {
+            binary.check()?;
+        }
+        if let Some(center_path) = &self.center_path {
+            center_path.check()?;
+            if self.three_d {
+                return Err(
+                    "with three_d, each particle orbits in a plane of its own through the middle \
+                     of the world, so the center can't be carried round a path"
+                        .into(),
+                );
+            }
+        }
+        for field in &self.fields {
+            field.check()?;
+        }
+        if self.three_d && (self.swarm_mass > 0.0 || !self.attractors.is_empty()) {
+            return Err(
+                "with three_d, each particle orbits in a plane of its own, so neither swarm_mass \
+                 nor attractors can be had"
+                    .into(),
+            );
+        }
+        if self.collisions != Collisions::Off {
+            if self.collision_radius <= 0.0 {
+                return Err(format!(
+                    "collision_radius has to be positive, not {}",
+                    self.collision_radius
+                ));
+            }
+            if self.three_d {
+                return Err(
+                    "with three_d, each particle orbits in a plane of its own, so they can't \
+                     collide"
+                        .into(),
+                );
+            }
+        }
+        if let Some(stirring) = &self.stirring {
+            stirring.check()?;
+        }
+        let [least, most] = self.hue_run;
+        if least > most {
+            return Err(format!("hue_run goes from {least} down to {most}"));
+        }
+        for name in self.palettes.keys() {
+            Palette::named(name, &self.palettes)?;
+        }
+        Palette::named(&self.palette, &self.palettes)?;
+        if !(0.0..=1.0).contains(&self.trail_fade) {
+            return Err(format!(
+                "trail_fade goes from 0 to 1, not {}",
+                self.trail_fade
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.trail_taper) {
+            return Err(format!(
+                "trail_taper goes from 0 to 1, not {}",
+                self.trail_taper
+            ));
+        }
+        self.trail_thickness.check()?;
+        self.trail_source.check()?;
+        if self.three_d && matches!(self.trail_source, TrailSource::Midpoint { .. }) {
+            return Err(
+                "with three_d, each particle orbits in a plane of its own, so there's no midpoint \
+                 between two of them to trace"
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether each ribbon is built of a pair of trails of its own, as tapered ribbons and those
+    /// of varying thickness are, rather than sharing its trails with its neighbors.
+    pub fn paired_ribbons(&self) -> bool {
+        self.trail_taper > 0.0 || self.trail_thickness != Thickness::Constant
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Use `settings` for the rest of the run in place of the defaults. They can only be set once a
+/// run, before the simulation starts.
+pub fn set(settings: Settings) -> Result<(), String> {
+    settings.check()?;
+    SETTINGS
+        .set(settings)
+        .map_err(|_| "the simulation settings are already set".to_string())
+}
+
+/// The settings of this run.
+pub fn get() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
@@ -0,0 +1,84 @@
+//! A run of the piece as a whole, as the frontends without nannou's sync, replays or controls step
+//! it: generations crossfading on reseed around the central bodies, in bullet time.
+
+use crate::generation::{self, Generation};
+use crate::params::{Param, Params};
+use crate::physics::{Body, Center};
+use crate::settings;
+use crate::time_scale::{self, TimeScale};
+
+/// Everything being simulated, stepped once a frame.
+pub struct Simulation {
+    /// Generations in the order they were seeded. The last one is current, any others are fading
+    /// out after a reseed.
+    pub generations: Vec<Generation>,
+    pub center: Center,
+    /// Simulated seconds since the start.
+    pub time: f32,
+    pub time_scale: TimeScale,
+    pub params: Params,
+}
+
+impl Simulation {
+    pub fn new(seed: u64) -> Self {
+        let params = Params::default();
+        Simulation {
+            generations: vec![Generation::new(
+                seed,
+                0.0,
+                1.0,
+                params.get(Param::VaryVelocity),
+            )],
+            center: Center::default(),
+            time: 0.0,
+            time_scale: TimeScale::default(),
+            params,
+        }
+    }
+
+    pub fn current(&self) -> &Generation {
+        self.generations.last().unwrap()
+    }
+
+    /// The central bodies and any other attractors, as they are now.
+    pub fn bodies(&self) -> Vec<Body> {
+        let attractors = settings::get().attractors.iter();
+        let mut bodies = self.center.bodies(self.time);
+        bodies.extend(attractors.map(|attractor| attractor.body(self.time)));
+        bodies
+    }
+
+    /// Start a new generation fading in.
+    pub fn reseed(&mut self, seed: u64) {
+        let vary_velocity = self.params.get(Param::VaryVelocity);
+        let generation = Generation::new(seed, self.time, 0.0, vary_velocity);
+        self.generations.push(generation);
+    }
+
+    /// Advance by `wall_seconds` of wall-clock time, slowed by bullet time.
+    pub fn step(&mut self, wall_seconds: f32) {
+        let peak_acceleration =
+            time_scale::peak_acceleration(&self.current().particles, &self.bodies(), self.time);
+        self.time_scale.update(wall_seconds, peak_acceleration);
+        let delta_seconds = self
+            .time_scale
+            .scale(wall_seconds, self.params.get(Param::TimeScale));
+
+        self.center.advance(self.time, delta_seconds);
+        self.time += delta_seconds;
+        let bodies = self.bodies();
+        let merged = generation::step(
+            &mut self.generations,
+            wall_seconds,
+            delta_seconds,
+            &bodies,
+            &self.params,
+            None,
+        );
+        if !merged.is_empty() {
+            // or the swarm would be grown back to the count asked for before
+            let count = self.current().staying() as f32;
+            self.params.set(Param::Particles, count);
+        }
+    }
+}
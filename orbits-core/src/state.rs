@@ -0,0 +1,48 @@
+//! The whole state of a run, saved to a file so it can be resumed later exactly where it left
+//! off: every generation with its particles, colors, trails and random streams, the background,
+//! the central bodies and attractors, and the clock and parameters they're stepped by.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::forces::Attractor;
+use crate::generation::Generation;
+use crate::params::Params;
+use crate::physics::Center;
+use crate::time_scale::TimeScale;
+
+/// A run as it was at one moment.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Simulated seconds since the start of the run.
+    pub time: f32,
+    /// Generations in the order they were seeded, the current one last.
+    pub generations: Vec<Generation>,
+    /// How many generations had been seeded.
+    pub seeded: usize,
+    /// What the seeds of the generations after these are picked from.
+    pub reseeds: u64,
+    pub center: Center,
+    pub attractors: Vec<Attractor>,
+    pub time_scale: TimeScale,
+    pub params: Params,
+}
+
+impl Snapshot {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let snapshot: Snapshot =
+            serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?;
+        if snapshot.generations.is_empty() {
+            return Err(format!("{}: no generations to resume", path.display()));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string(self).unwrap();
+        fs::write(path, text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+}
@@ -0,0 +1,77 @@
+//! Holding a swarm in balance over a run with no end, when the settings ask for it. Left alone, a
+//! long run drifts towards its particles flying apart or falling in together, so each step every
+//! particle's energy is measured against that of a circular orbit at the distance from the middle
+//! the swarm's to be held at, and its velocity scaled by a little: slowed, sinking to a lower
+//! orbit, while it has more energy than that, and sped up, rising, while it has less. Each is held
+//! rather than only their mean, as a swarm whose mean is held can still fly apart, a few particles
+//! escaping with the energy the rest gave up. The energy answers to the kicks at once, where the
+//! orbit's size would only follow over a turn, so it settles back gently rather than overshooting.
+//! The masses are taken as one at the middle, as the legend takes them.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::physics::{self, Particle};
+use crate::{orbital_radius, settings};
+
+/// The settings' `stirring`.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Stirring {
+    /// The radius of the circular orbit whose energy each generation's particles are held at, as
+    /// a multiple of the average orbit's.
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// How hard they're held there: the share of the way to it each one's energy is brought over
+    /// a simulated second, and the most its speed is changed by over one. The harder, the sooner
+    /// the orbits all come to the same size.
+    #[serde(default = "default_rate")]
+    pub rate: f32,
+}
+
+fn default_radius() -> f32 {
+    1.0
+}
+
+fn default_rate() -> f32 {
+    0.02
+}
+
+impl Stirring {
+    pub fn check(&self) -> Result<(), String> {
+        if self.radius <= 0.0 {
+            return Err(format!(
+                "stirring's radius has to be positive, not {}",
+                self.radius
+            ));
+        }
+        if self.rate < 0.0 {
+            return Err(format!(
+                "stirring's rate can't be negative, not {}",
+                self.rate
+            ));
+        }
+        Ok(())
+    }
+
+    /// Kick each of the `particles` whose place in `frozen` is false on towards the energy held
+    /// over `delta_seconds`, around masses of combined strength `gm`.
+    pub fn stir(&self, particles: &mut [Particle], frozen: &[bool], gm: f32, delta_seconds: f32) {
+        let law = settings::get().gravity_law;
+        let radius = self.radius * orbital_radius();
+        let held = physics::circular_speed(radius, gm).powi(2) / 2.0 + law.potential(radius, gm);
+        let share = (self.rate * delta_seconds).min(1.0);
+        for (particle, _) in (particles.iter_mut().zip(frozen)).filter(|(_, &frozen)| !frozen) {
+            let kinetic = particle.vel.length_squared() / 2.0;
+            let potential = law.potential(particle.pos.length().max(1.0), gm);
+            // the share of the gap closed this step, made up in kinetic energy alone, but never
+            // changing the speed by more than that share, or a particle slowed at the far end of
+            // its orbit would stop dead and fall straight in
+            let gained = (held - kinetic - potential) * share;
+            let scale = ((kinetic + gained).max(0.0) / kinetic).sqrt();
+            if scale.is_finite() {
+                particle.vel *= scale.clamp(1.0 - share, 1.0 + share);
+            }
+        }
+    }
+}
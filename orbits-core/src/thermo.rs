@@ -0,0 +1,56 @@
+//! Readouts of the state of a swarm as a whole, as a physicist would sum up a gas: its temperature,
+//! how widely its particles' velocities are spread about their mean, and the entropy of where they
+//! are, how evenly they're spread over a grid across the system, from 0 with all of them in one
+//! cell to 1 with as many in each as can be. Both are for watching a run by, and for mapping to
+//! the look of the piece.
+
+use nannou_core::prelude::*;
+
+use crate::orbital_radius;
+use crate::physics::Particle;
+
+/// Cells across and down the grid the entropy is counted on.
+const CELLS: usize = 16;
+/// How far from the middle the grid reaches, as a multiple of the average orbit's radius, those
+/// further out counted in the cells at its edge.
+const EXTENT: f32 = 2.0;
+
+/// The velocity dispersion of `particles`: the root mean square of how far each one's velocity
+/// is from their mean, in world units a second.
+pub fn temperature(particles: &[Particle]) -> f32 {
+    if particles.is_empty() {
+        return 0.0;
+    }
+    let count = particles.len() as f32;
+    let mean = particles.iter().map(|p| &p.vel).sum::<Vec2>() / count;
+    let spread = (particles.iter())
+        .map(|p| p.vel.distance_squared(mean))
+        .sum::<f32>();
+    (spread / count).sqrt()
+}
+
+/// The entropy of where `particles` are over the grid, as a share of the most they could have,
+/// with one to a cell or the same number in every cell.
+pub fn entropy(particles: &[Particle]) -> f32 {
+    let mut counts = [0usize; CELLS * CELLS];
+    let cell = |along: f32| {
+        let along = (along / (EXTENT * orbital_radius()) + 1.0) / 2.0 * CELLS as f32;
+        (along.max(0.0) as usize).min(CELLS - 1)
+    };
+    for particle in particles {
+        counts[cell(particle.pos.y) * CELLS + cell(particle.pos.x)] += 1;
+    }
+    let most = (particles.len().min(CELLS * CELLS) as f32).ln();
+    if most <= 0.0 {
+        return 0.0;
+    }
+    let count = particles.len() as f32;
+    let entropy: f32 = (counts.iter())
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let share = n as f32 / count;
+            -share * share.ln()
+        })
+        .sum();
+    entropy / most
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{acceleration_at, reference_acceleration, Body, Particle};
+use crate::settings;
+
+/// Acceleration, relative to that felt on the average orbit, above which bullet time kicks in.
+const BULLET_TIME_THRESHOLD: f32 = 8.0;
+/// Time scale at the bottom of the dip.
+const BULLET_TIME_SCALE: f32 = 0.25;
+/// Wall-clock seconds to ease into the dip, kept short so the approach itself is slowed.
+const ATTACK_SECONDS: f32 = 0.15;
+/// Wall-clock seconds to recover full speed once the approach is over.
+const RELEASE_SECONDS: f32 = 1.5;
+
+/// Automatic adjustments to how fast simulated time runs, on top of the speed chosen by the user.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TimeScale {
+    /// Smoothed bullet time factor, 1 at full speed.
+    envelope: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale { envelope: 1.0 }
+    }
+}
+
+impl TimeScale {
+    /// Move the bullet time envelope towards its target. `peak_acceleration` is the strongest pull
+    /// felt by any particle, relative to that felt on the average orbit.
+    pub fn update(&mut self, wall_seconds: f32, peak_acceleration: f32) {
+        let target = if settings::get().bullet_time && peak_acceleration > BULLET_TIME_THRESHOLD {
+            BULLET_TIME_SCALE
+        } else {
+            1.0
+        };
+        let seconds = if target < self.envelope {
+            ATTACK_SECONDS
+        } else {
+            RELEASE_SECONDS
+        };
+        let blend = 1.0 - (-wall_seconds / seconds).exp();
+        self.envelope += (target - self.envelope) * blend;
+    }
+
+    /// Convert elapsed wall-clock seconds into simulated seconds, given the user's `multiplier`.
+    pub fn scale(&self, wall_seconds: f32, multiplier: f32) -> f32 {
+        wall_seconds * multiplier * self.envelope
+    }
+}
+
+/// The strongest pull felt by any of `particles` at time `t`, relative to that felt on the average
+/// orbit, as `TimeScale::update` takes it.
+pub fn peak_acceleration(particles: &[Particle], bodies: &[Body], t: f32) -> f32 {
+    let peak = particles
+        .iter()
+        .map(|particle| acceleration_at(particle.pos, bodies).length())
+        .fold(0.0, f32::max);
+    peak / reference_acceleration(t)
+}
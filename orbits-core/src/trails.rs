@@ -0,0 +1,640 @@
+//! The shapes trails are drawn as, worked out once for every renderer: ribbons stretched between
+//! neighboring particles' trails, and lines traced over the trails of the most interesting orbits.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use nannou_core::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::generation::{Generation, Record};
+use crate::{palette, settings};
+
+/// Epochs by which the trail of `particle` lags the present, as the settings' `echo_delay` has it.
+pub fn echo_delay(particle: usize) -> usize {
+    particle * settings::get().echo_delay
+}
+
+/// An indexed triangle list of colored vertices. Each vertex's z is its epoch, newest first, until
+/// the mesh is layered.
+#[derive(Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<(Point3, Hsla)>,
+    pub indices: Vec<usize>,
+}
+
+/// How ribbons are thinned out where they're dense, so overlapping trails aren't drawn over each
+/// other many times over. Trynannou's `[thinning]` config section.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thinning {
+    /// Pieces of ribbon in a cell of the grid past which more are thinned out, or 0 for no
+    /// thinning.
+    pub density: usize,
+    /// Points across each cell of the grid, as shown, so it thins the same at any scale factor.
+    pub cell: f32,
+}
+
+impl Default for Thinning {
+    fn default() -> Self {
+        Thinning {
+            density: 0,
+            cell: 16.0,
+        }
+    }
+}
+
+impl Thinning {
+    /// Drop pieces of `ribbons`, shown at `pixels_per_unit`, from wherever there are already
+    /// `density` or more in a cell of the grid, each further one being kept with less and less
+    /// chance, so a cell of `n` keeps about `density * (1 + ln(n / density))`. Which pieces go is
+    /// decided by where they lie, so it holds still from one frame to the next.
+    pub fn thin(&self, ribbons: &mut Mesh, pixels_per_unit: f32) {
+        if self.density == 0 {
+            return;
+        }
+        let cell = self.cell / pixels_per_unit;
+        let mut seen: HashMap<(i32, i32), usize> = HashMap::new();
+        let mut kept = Vec::with_capacity(ribbons.indices.len());
+        // each piece is a quad of two triangles
+        for piece in ribbons.indices.chunks(6) {
+            let corner = ribbons.vertices[piece[0]].0;
+            let key = (
+                (corner.x / cell).floor() as i32,
+                (corner.y / cell).floor() as i32,
+            );
+            let count = seen.entry(key).or_default();
+            *count += 1;
+            if *count <= self.density || chance(corner) < self.density as f32 / *count as f32 {
+                kept.extend_from_slice(piece);
+            }
+        }
+        ribbons.indices = kept;
+    }
+}
+
+/// How overlapping pieces of ribbon are stacked, as trynannou's `layering` option picks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Layering {
+    /// Newer pieces over older ones, whichever trails they're of.
+    #[default]
+    NewestOnTop,
+    /// Each ribbon over those of the particles before it, newer pieces over older ones within it.
+    ByParticle,
+    /// Older pieces sunk further back, hidden wherever a newer one is in front of them rather
+    /// than showing through it, and standing over what's drawn after the trails.
+    Depth,
+}
+
+impl Layering {
+    /// Order the pieces of `ribbons`, and set their vertices' depths, to stack them this way. The
+    /// first thing done to the ribbons after they're thinned, as it does away with the epochs
+    /// their vertices' z held.
+    pub fn layer(self, ribbons: &mut Mesh) {
+        let epoch = |vertex: usize| ribbons.vertices[vertex].0.z as usize;
+        let mut pieces: Vec<&[usize]> = ribbons.indices.chunks(6).collect();
+        match self {
+            Layering::NewestOnTop => {
+                pieces.sort_by_key(|piece| std::cmp::Reverse(epoch(piece[0])));
+            }
+            Layering::ByParticle => {
+                // a piece's first corner is on the first trail of its ribbon, whose vertices
+                // start that far before it
+                let ribbon = |piece: &[usize]| piece[0] - epoch(piece[0]);
+                pieces.sort_by_key(|piece| (ribbon(piece), std::cmp::Reverse(epoch(piece[0]))));
+            }
+            Layering::Depth => {}
+        }
+        ribbons.indices = pieces.concat();
+        let epochs = settings::get().history as f32;
+        for (pos, _) in &mut ribbons.vertices {
+            pos.z = match self {
+                // the drawing's depth test lets through whatever's no further back than what's
+                // already there, so those left flat stack in the order they're drawn
+                Layering::NewestOnTop | Layering::ByParticle => 0.0,
+                Layering::Depth => (1.0 - pos.z / epochs).max(0.0),
+            };
+        }
+    }
+}
+
+/// Fade out the edges of `ribbons` over `width` world units outside them, for them to look smooth
+/// without heavy multisampling: along each side of each piece runs a strip out to a transparent
+/// edge, a piece of its own following the one it fades out. Done once they're thinned, so the
+/// strips go with their pieces, and before they're layered, so they're stacked along with them.
+pub fn feather(ribbons: &mut Mesh, width: f32) {
+    let Mesh { vertices, indices } = ribbons;
+    let mut feathered = Vec::with_capacity(indices.len() * 3);
+    for piece in indices.chunks_exact(6) {
+        feathered.extend_from_slice(piece);
+        // older and newer on the first trail, then older and newer on the second
+        let [older_a, newer_a, newer_b, older_b] = [piece[0], piece[1], piece[2], piece[5]];
+        for (older, newer, across) in [(older_a, newer_a, older_b), (older_b, newer_b, older_a)] {
+            let at = |vertex: usize| vertices[vertex].0.truncate();
+            let normal = (at(newer) - at(older)).perp().normalize_or_zero();
+            let out = if normal.dot(at(across) - at(older)) > 0.0 {
+                -normal
+            } else {
+                normal
+            };
+            let start = vertices.len();
+            for vertex in [older, newer] {
+                let (pos, mut color) = vertices[vertex];
+                color.alpha = 0.0;
+                vertices.push((pos + (out * width).extend(0.0), color));
+            }
+            feathered.extend_from_slice(&[older, newer, start + 1, older, start + 1, start]);
+        }
+    }
+    *indices = feathered;
+}
+
+/// A number from 0 to 1 hashed from `point`, the same every time.
+fn chance(point: Point3) -> f32 {
+    // SplitMix64's finalizer
+    let mut z = (point.x.to_bits() as u64) << 32 | point.y.to_bits() as u64;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// A coarse grid over the view counting how many times over ribbons cover each of its cells, for
+/// estimating how much drawing them overdraws.
+pub struct Occupancy {
+    view: Rect,
+    cell: f32,
+    columns: usize,
+    layers: Vec<u32>,
+    triangles: usize,
+}
+
+/// How many times over the ribbons counted by an `Occupancy` draw the view.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Overdraw {
+    pub triangles: usize,
+    /// Fraction of the view covered at least once.
+    pub coverage: f32,
+    /// Times over the covered part is drawn, on average.
+    pub mean: f32,
+    /// Times over its most covered cell is drawn.
+    pub peak: u32,
+}
+
+impl Occupancy {
+    /// An empty grid over `view`, in world coordinates, of cells `cell` across.
+    pub fn new(view: Rect, cell: f32) -> Self {
+        let columns = (view.w() / cell).ceil().max(1.0) as usize;
+        let rows = (view.h() / cell).ceil().max(1.0) as usize;
+        Occupancy {
+            view,
+            cell,
+            columns,
+            layers: vec![0; columns * rows],
+            triangles: 0,
+        }
+    }
+
+    /// Add a layer to each cell whose middle a triangle of `mesh` covers, or, for a triangle too
+    /// small to cover any, to the cell it lies in.
+    pub fn add(&mut self, mesh: &Mesh) {
+        let rows = self.layers.len() / self.columns;
+        let max = [self.columns - 1, rows - 1];
+        for triangle in mesh.indices.chunks_exact(3) {
+            self.triangles += 1;
+            let corners = [0, 1, 2].map(|i| {
+                let corner = mesh.vertices[triangle[i]].0;
+                (vec2(corner.x, corner.y) - self.view.bottom_left()) / self.cell - 0.5
+            });
+            let (lo, hi) = corners[1..]
+                .iter()
+                .fold((corners[0], corners[0]), |(lo, hi), &c| {
+                    (lo.min(c), hi.max(c))
+                });
+            if hi.x < 0.0 || hi.y < 0.0 || lo.x > max[0] as f32 || lo.y > max[1] as f32 {
+                continue;
+            }
+            let clamp = |v: f32, max: usize| (v.max(0.0) as usize).min(max);
+            let mut covered = false;
+            for y in clamp(lo.y.ceil(), max[1])..=clamp(hi.y.floor(), max[1]) {
+                for x in clamp(lo.x.ceil(), max[0])..=clamp(hi.x.floor(), max[0]) {
+                    if contains(corners, vec2(x as f32, y as f32)) {
+                        self.layers[y * self.columns + x] += 1;
+                        covered = true;
+                    }
+                }
+            }
+            if !covered {
+                let middle = (corners[0] + corners[1] + corners[2]) / 3.0;
+                let (x, y) = (
+                    clamp(middle.x.round(), max[0]),
+                    clamp(middle.y.round(), max[1]),
+                );
+                self.layers[y * self.columns + x] += 1;
+            }
+        }
+    }
+
+    pub fn overdraw(&self) -> Overdraw {
+        let covered = self.layers.iter().filter(|&&n| n > 0).count();
+        let total: u32 = self.layers.iter().sum();
+        Overdraw {
+            triangles: self.triangles,
+            coverage: covered as f32 / self.layers.len() as f32,
+            mean: if covered == 0 {
+                0.0
+            } else {
+                total as f32 / covered as f32
+            },
+            peak: self.layers.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+/// Whether `point` lies within the triangle of `corners`, wound either way.
+fn contains(corners: [Vec2; 3], point: Vec2) -> bool {
+    let side = |a: Vec2, b: Vec2| (b - a).perp_dot(point - a);
+    let sides = [
+        side(corners[0], corners[1]),
+        side(corners[1], corners[2]),
+        side(corners[2], corners[0]),
+    ];
+    sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0)
+}
+
+/// A polyline of colored points.
+pub struct Line {
+    /// The particle whose trail it's along.
+    pub particle: usize,
+    pub width: f32,
+    pub points: Vec<(Point2, Hsla)>,
+}
+
+/// The ribbons between neighboring particles' trails, at `opacity`, with the most interesting
+/// picked out by `highlight`. The newest points are pushed `extrapolate` simulated seconds along
+/// the particles' velocities, and only the newest `length` of each trail is drawn, from 0 to 1.
+pub fn ribbons(
+    generation: &Generation,
+    opacity: f32,
+    highlight: f32,
+    extrapolate: f32,
+    length: f32,
+) -> Mesh {
+    let mut ribbons = Ribbons::default();
+    ribbons.update(generation, opacity, highlight, extrapolate, length);
+    ribbons.mesh
+}
+
+/// The ribbons of a generation's trails kept from one frame to the next, for a renderer that
+/// draws them over and over. Their indices depend only on how long each trail is and how much of
+/// it is shown, which stops changing once the trails have grown, so they're built again only when
+/// that changes. The vertices move on an epoch with every record and are rewritten each update,
+/// into the same buffer.
+///
+/// Each trail's vertices follow the last's, newest first, and each ribbon is stretched between
+/// one trail's and the next's, unless the ribbons taper or vary in thickness: then each has a pair
+/// of trails of its own, its first particle's and its second's, pulled in towards each other with
+/// age or pushed apart as thick as their records are.
+#[derive(Default)]
+pub struct Ribbons {
+    /// Each trail's particle, length and the epochs of it shown, as the indices were built for.
+    shape: Vec<(usize, usize, usize)>,
+    indices: Vec<usize>,
+    mesh: Mesh,
+}
+
+impl Ribbons {
+    /// The ribbons of `generation` as `ribbons` would build them, ready to be thinned and layered
+    /// as the last ones were.
+    pub fn update(
+        &mut self,
+        generation: &Generation,
+        opacity: f32,
+        highlight: f32,
+        extrapolate: f32,
+        length: f32,
+    ) -> &mut Mesh {
+        self.update_placed(
+            generation,
+            opacity,
+            highlight,
+            extrapolate,
+            length,
+            |_, pos| pos,
+        )
+    }
+
+    /// The ribbons of `generation` as `update` builds them, with each point of a particle's trail
+    /// put where `place` puts it for that particle before they're tapered, for a renderer seeing
+    /// them other than flat on.
+    pub fn update_placed(
+        &mut self,
+        generation: &Generation,
+        opacity: f32,
+        highlight: f32,
+        extrapolate: f32,
+        length: f32,
+        place: impl Fn(usize, Point2) -> Point2,
+    ) -> &mut Mesh {
+        let settings = settings::get();
+        let (taper, paired) = (settings.trail_taper, settings.paired_ribbons());
+        let trails = layout(generation.history.len(), paired);
+        let shape = trails.iter().map(|&particle| {
+            let len = generation.history[particle].len();
+            (particle, len, visible(generation, particle, length))
+        });
+        if !shape.clone().eq(self.shape.iter().copied()) {
+            self.shape = shape.collect();
+            self.indices = indices(&self.shape, paired);
+        }
+        let span = span(length);
+        let point = |particle: usize, epoch: usize| {
+            place(particle, position(generation, particle, epoch, extrapolate))
+        };
+        let Mesh { vertices, indices } = &mut self.mesh;
+        vertices.clear();
+        for (i, &particle) in trails.iter().enumerate() {
+            // the particle whose trail this one's paired with, and how far they're shown
+            let partner = paired.then(|| {
+                let partner = trails[i ^ 1];
+                let shown =
+                    visible(generation, particle, length).min(visible(generation, partner, length));
+                (partner, shown)
+            });
+            let alpha = opacity
+                * generation.saliency.alpha(particle, highlight)
+                * generation.presence(particle);
+            let delay = echo_delay(particle);
+            let trail = &generation.history[particle];
+            vertices.extend(trail.iter().enumerate().map(|(epoch, record)| {
+                let mut color = record.color;
+                let back = epoch.saturating_sub(delay);
+                color.alpha *= alpha * palette::fade(back, span);
+                let mut pos = point(particle, epoch);
+                if let Some((partner, shown)) = partner.filter(|_| epoch >= delay) {
+                    if back < shown {
+                        let partner_epoch = back + echo_delay(partner);
+                        let middle = (pos + point(partner, partner_epoch)) / 2.0;
+                        let thickness = (record.thickness
+                            + generation.history[partner][partner_epoch].thickness)
+                            / 2.0;
+                        let width = (1.0 - taper * palette::age(back, span)) * thickness;
+                        pos = middle + (pos - middle) * width;
+                    }
+                }
+                (pos.extend(epoch as f32), color)
+            }));
+        }
+        indices.clear();
+        if generation.is_dashed() {
+            // the gaps in dashed trails are left out, rather than drawn unseen
+            indices.extend(
+                (self.indices.chunks(3))
+                    .filter(|triangle| triangle.iter().any(|&v| vertices[v].1.alpha > 0.0))
+                    .flatten(),
+            );
+        } else {
+            indices.extend_from_slice(&self.indices);
+        }
+        &mut self.mesh
+    }
+}
+
+/// The ribbons between neighboring particles' archives, at `opacity`, with the most interesting
+/// picked out by `highlight`, each point put where `place` puts it for its particle, if the newest
+/// `length` of the trails, from 0 to 1, shows them whole. Each runs on from the oldest point of
+/// the trails, fading on from where they leave off to nothing at the oldest archived, and each
+/// shares its archives with its neighbors, even where the trails' own ribbons are paired.
+pub fn archived_ribbons(
+    generation: &Generation,
+    opacity: f32,
+    highlight: f32,
+    length: f32,
+    place: impl Fn(usize, Point2) -> Point2,
+) -> Mesh {
+    let settings = settings::get();
+    let mut mesh = Mesh::default();
+    if generation.archives.is_empty() || span(length) < settings.history {
+        return mesh;
+    }
+    let mut starts = Vec::with_capacity(generation.archives.len());
+    for (particle, archive) in generation.archives.iter().enumerate() {
+        starts.push(mesh.vertices.len());
+        let alpha = opacity
+            * generation.saliency.alpha(particle, highlight)
+            * generation.presence(particle)
+            * settings.trail_fade;
+        let oldest = generation.history[particle].back().cloned();
+        let records: Vec<_> = oldest.into_iter().chain(archive.records()).collect();
+        let count = records.len();
+        mesh.vertices
+            .extend(records.into_iter().enumerate().map(|(i, record)| {
+                let mut color = record.color;
+                color.alpha *= alpha * (1.0 - i as f32 / count as f32);
+                (place(particle, record.pos).extend(0.0), color)
+            }));
+    }
+    starts.push(mesh.vertices.len());
+    for (a, b) in (0..generation.archives.len()).tuple_windows() {
+        let epochs = (starts[a + 1] - starts[a]).min(starts[b + 1] - starts[b]);
+        let (a, b) = (starts[a], starts[b]);
+        for (past, pres) in (0..epochs).tuple_windows() {
+            mesh.indices
+                .extend([a + past, a + pres, b + pres, a + past, b + pres, b + past]);
+        }
+    }
+    mesh
+}
+
+/// The ribbons between the remnants of the trails of particles merged away and the trails of those
+/// they merged into, at `opacity`, with the most interesting picked out by `highlight`, each point
+/// put where `place` puts it for the particle merged into. Each joins a remnant to the trail as
+/// far back as both go within the newest `length` of it, from 0 to 1, narrowing to where they met,
+/// so the two are seen running into one, faded along the way as the trail is.
+pub fn remnant_ribbons(
+    generation: &Generation,
+    opacity: f32,
+    highlight: f32,
+    length: f32,
+    place: impl Fn(usize, Point2) -> Point2,
+) -> Mesh {
+    let span = span(length);
+    let mut mesh = Mesh::default();
+    for remnant in &generation.remnants {
+        let into = remnant.into;
+        let trail = &generation.history[into];
+        // the trail's records as old as the remnant's, past its echo delay
+        let delay = echo_delay(into) + remnant.since;
+        let epochs = (remnant.history.len())
+            .min(trail.len().saturating_sub(delay))
+            .min(span.saturating_sub(remnant.since));
+        if epochs < 2 {
+            continue;
+        }
+        let alpha =
+            opacity * generation.saliency.alpha(into, highlight) * generation.presence(into);
+        let start = mesh.vertices.len();
+        for k in 0..epochs {
+            let back = remnant.since + k;
+            for record in [&remnant.history[k], &trail[delay + k]] {
+                let mut color = record.color;
+                color.alpha *= alpha * palette::fade(back, span);
+                mesh.vertices
+                    .push((place(into, record.pos).extend(back as f32), color));
+            }
+        }
+        // each remnant's vertex is followed by the trail's as old
+        for (past, pres) in (0..epochs).tuple_windows() {
+            let (past, pres) = (start + 2 * past, start + 2 * pres);
+            mesh.indices
+                .extend([past, pres, pres + 1, past, pres + 1, past + 1]);
+        }
+    }
+    mesh
+}
+
+/// The particle of each trail the ribbons between `particles` particles' trails are built of, in
+/// order: each particle once, or each pair of neighbors for ribbons that are `paired`.
+pub fn layout(particles: usize, paired: bool) -> Vec<usize> {
+    if paired {
+        (0..particles)
+            .tuple_windows()
+            .flat_map(|(a, b)| [a, b])
+            .collect()
+    } else {
+        (0..particles).collect()
+    }
+}
+
+/// The indices of the ribbons between trails of the particles, lengths and epochs shown in
+/// `shape`, each trail's vertices following the last's, newest first. Each ribbon joins a trail
+/// to the next, or for ribbons that are `paired`, the two of its own.
+fn indices(shape: &[(usize, usize, usize)], paired: bool) -> Vec<usize> {
+    // where among the vertices each trail starts
+    let starts: Vec<usize> = (shape.iter())
+        .scan(0, |start, &(_, len, _)| {
+            Some(std::mem::replace(start, *start + len))
+        })
+        .collect();
+    let index = |epoch: usize, trail: usize| starts[trail] + epoch + echo_delay(shape[trail].0);
+    let pairs: Vec<(usize, usize)> = if paired {
+        (0..shape.len()).tuples().collect()
+    } else {
+        (0..shape.len()).tuple_windows().collect()
+    };
+    pairs
+        .into_iter()
+        .flat_map(|(trail_a, trail_b)| {
+            // a ribbon runs only as far as the shorter of the trails it joins
+            let epochs = shape[trail_a].2.min(shape[trail_b].2);
+            (0..epochs).tuple_windows().flat_map(move |(past, pres)| {
+                [
+                    index(past, trail_a),
+                    index(pres, trail_a),
+                    index(pres, trail_b),
+                    index(past, trail_a),
+                    index(pres, trail_b),
+                    index(past, trail_b),
+                ]
+            })
+        })
+        .collect()
+}
+
+/// Lines to trace over the highlighted trails, so they stand out by width as well as brightness.
+pub fn highlights(
+    generation: &Generation,
+    opacity: f32,
+    highlight: f32,
+    extrapolate: f32,
+    length: f32,
+) -> Vec<Line> {
+    (0..generation.particles.len())
+        .filter_map(|particle| {
+            let width = generation.saliency.width(particle, highlight);
+            if width < 0.1 {
+                return None;
+            }
+            let points = trail(generation, particle, opacity, extrapolate, length);
+            Some(Line {
+                particle,
+                width,
+                points,
+            })
+        })
+        .collect()
+}
+
+/// The newest `length` of the trail of `particle` at `opacity`, newest point first, for renderers
+/// that draw lines rather than ribbons.
+pub fn trail(
+    generation: &Generation,
+    particle: usize,
+    opacity: f32,
+    extrapolate: f32,
+    length: f32,
+) -> Vec<(Point2, Hsla)> {
+    let delay = echo_delay(particle);
+    projected(generation, particle, opacity, length, |back, _| {
+        position(generation, particle, delay + back, extrapolate)
+    })
+}
+
+/// The newest `length` of the trail of `particle` at `opacity` as `trail` gives it, each point
+/// placed by `project` from how many records back from the newest shown it is and the record,
+/// for views of the swarm other than where it is.
+pub fn projected(
+    generation: &Generation,
+    particle: usize,
+    opacity: f32,
+    length: f32,
+    project: impl Fn(usize, &Record) -> Point2,
+) -> Vec<(Point2, Hsla)> {
+    let delay = echo_delay(particle);
+    let span = span(length);
+    let opacity = opacity * generation.presence(particle);
+    let trail = &generation.history[particle];
+    (0..visible(generation, particle, length))
+        .map(|back| {
+            let record = &trail[delay + back];
+            let mut color = record.color;
+            color.alpha *= opacity * palette::fade(back, span);
+            (project(back, record), color)
+        })
+        .collect()
+}
+
+/// How thick each point of the trail of `particle` that `trail` gives is drawn, newest first.
+pub fn thickness(generation: &Generation, particle: usize, length: f32) -> Vec<f32> {
+    let delay = echo_delay(particle);
+    (delay..delay + visible(generation, particle, length))
+        .map(|epoch| generation.history[particle][epoch].thickness)
+        .collect()
+}
+
+/// How many epochs of the trail of `particle` there are to draw, out of the newest `length` of a
+/// full trail, past its echo delay.
+pub fn visible(generation: &Generation, particle: usize, length: f32) -> usize {
+    generation.history[particle]
+        .len()
+        .saturating_sub(echo_delay(particle))
+        .min(span(length))
+}
+
+/// Epochs in the newest `length` of a full trail, the most of any trail drawn, over which each
+/// fades and tapers. A trail still growing in fades as it will once it's grown.
+pub fn span(length: f32) -> usize {
+    (settings::get().history as f32 * length.clamp(0.0, 1.0)).ceil() as usize
+}
+
+/// Where the record `particle` left `epoch` steps ago is drawn, the newest pushed on along its
+/// velocity.
+fn position(generation: &Generation, particle: usize, epoch: usize, extrapolate: f32) -> Point2 {
+    let pos = generation.history[particle][epoch].pos;
+    match epoch {
+        0 => pos + generation.particles[particle].vel * extrapolate,
+        _ => pos,
+    }
+}
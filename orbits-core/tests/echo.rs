@@ -0,0 +1,37 @@
+//! The trail echo, in a process of its own so the settings can be set for it.
+
+use orbits_core::generation::Generation;
+use orbits_core::physics::Center;
+use orbits_core::settings::{self, Settings};
+use orbits_core::trails;
+
+#[test]
+fn a_delay_echoes_each_trail_behind_the_last() {
+    settings::set(Settings {
+        particles: 4,
+        history: 20,
+        echo_delay: 5,
+        ..Settings::default()
+    })
+    .unwrap();
+    let bodies = Center::default().bodies(0.0);
+    let mut generation = Generation::new(0, 0.0, 1.0, 100.0);
+    for _ in 0..60 {
+        generation.update(1.0 / 60.0, &bodies, 0.0, None);
+    }
+    for particle in 0..4 {
+        let delay = trails::echo_delay(particle);
+        assert_eq!(delay, particle * 5);
+        let history = &generation.history[particle];
+        assert_eq!(history.len(), 20 + delay);
+        let trail = trails::trail(&generation, particle, 1.0, 0.0, 1.0);
+        assert_eq!(trail.len(), 20);
+        assert_eq!(trail[0].0, history[delay].pos);
+        if particle > 0 {
+            assert_ne!(
+                trail[0].0, history[0].pos,
+                "particle {particle} isn't echoed"
+            );
+        }
+    }
+}
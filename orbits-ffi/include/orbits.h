@@ -0,0 +1,46 @@
+/* The piece behind trynannou, embedded through liborbits_ffi. */
+
+#ifndef ORBITS_H
+#define ORBITS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A run of the piece. */
+typedef struct OrbitsSimulation OrbitsSimulation;
+
+/* What each call but `orbits_create` returns. After `ORBITS_PANICKED` the run is only fit to be
+ * destroyed. */
+#define ORBITS_OK 0
+/* An argument was null, out of range, or too small, and nothing was done. */
+#define ORBITS_INVALID_ARGUMENT 1
+/* The piece panicked partway through. */
+#define ORBITS_PANICKED 2
+
+/* Start a run seeded with `seed`, or null if it couldn't be. Free it with `orbits_destroy`. */
+OrbitsSimulation *orbits_create(uint64_t seed);
+
+/* Advance by `dt` seconds of wall-clock time, slowed by bullet time. */
+int32_t orbits_step(OrbitsSimulation *sim, float dt);
+
+/* Start a new generation seeded with `seed`, fading in over the last. */
+int32_t orbits_reseed(OrbitsSimulation *sim, uint64_t seed);
+
+/* Render into `pixels`, `height` rows of `width` opaque 8 bit sRGB RGBA pixels, each row starting
+ * `stride` bytes after the last. Nothing is rendered if an argument is null or too small, or the
+ * buffer too big to address. */
+int32_t orbits_render(const OrbitsSimulation *sim, uint8_t *pixels, uint32_t width,
+                      uint32_t height, size_t stride);
+
+/* Free a run. */
+int32_t orbits_destroy(OrbitsSimulation *sim);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
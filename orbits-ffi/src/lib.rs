@@ -0,0 +1,125 @@
+//! A C API for embedding the piece in other applications, such as a plugin's UI or a game engine:
+//! create a run, step it, render it into a pixel buffer the host uploads as a texture, and destroy
+//! it. `include/orbits.h` declares it for C and C++.
+//!
+//! No panic unwinds into the host: each entry point catches any, returning `ORBITS_PANICKED`, or
+//! null from `orbits_create`, after which the run is only fit to be destroyed.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+use orbits_core::simulation::Simulation;
+
+mod raster;
+
+/// The call did what was asked.
+pub const ORBITS_OK: i32 = 0;
+/// An argument was null, out of range, or too small, and nothing was done.
+pub const ORBITS_INVALID_ARGUMENT: i32 = 1;
+/// The piece panicked partway through, leaving the run to be destroyed.
+pub const ORBITS_PANICKED: i32 = 2;
+
+/// The status `call` returns, or `ORBITS_PANICKED` if it panics.
+fn guard(call: impl FnOnce() -> i32) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or(ORBITS_PANICKED)
+}
+
+/// Start a run seeded with `seed`, or null if it couldn't be. Free it with `orbits_destroy`.
+#[no_mangle]
+pub extern "C" fn orbits_create(seed: u64) -> *mut Simulation {
+    panic::catch_unwind(|| Box::into_raw(Box::new(Simulation::new(seed))))
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Advance `sim` by `dt` seconds of wall-clock time, slowed by bullet time.
+///
+/// # Safety
+///
+/// `sim` must be null or come from `orbits_create`, and not be in use on another thread.
+#[no_mangle]
+pub unsafe extern "C" fn orbits_step(sim: *mut Simulation, dt: f32) -> i32 {
+    guard(|| {
+        let Some(sim) = sim.as_mut() else {
+            return ORBITS_INVALID_ARGUMENT;
+        };
+        if !dt.is_finite() || dt < 0.0 {
+            return ORBITS_INVALID_ARGUMENT;
+        }
+        sim.step(dt);
+        ORBITS_OK
+    })
+}
+
+/// Start a new generation seeded with `seed`, fading in over the last.
+///
+/// # Safety
+///
+/// As for `orbits_step`.
+#[no_mangle]
+pub unsafe extern "C" fn orbits_reseed(sim: *mut Simulation, seed: u64) -> i32 {
+    guard(|| {
+        let Some(sim) = sim.as_mut() else {
+            return ORBITS_INVALID_ARGUMENT;
+        };
+        sim.reseed(seed);
+        ORBITS_OK
+    })
+}
+
+/// Render `sim` into `pixels`, `height` rows of `width` opaque 8 bit sRGB RGBA pixels, each row
+/// starting `stride` bytes after the last. Nothing is rendered if an argument is null or too
+/// small, or the buffer too big to address.
+///
+/// # Safety
+///
+/// `sim` must be null or come from `orbits_create`, and `pixels` must be null or point to
+/// `stride * height` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn orbits_render(
+    sim: *const Simulation,
+    pixels: *mut u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+) -> i32 {
+    guard(|| {
+        let (width, height) = (width as usize, height as usize);
+        let Some(sim) = sim.as_ref() else {
+            return ORBITS_INVALID_ARGUMENT;
+        };
+        let row = width.checked_mul(4);
+        let len = stride.checked_mul(height);
+        let (Some(row), Some(len)) = (row, len) else {
+            return ORBITS_INVALID_ARGUMENT;
+        };
+        if pixels.is_null()
+            || width == 0
+            || height == 0
+            || stride < row
+            || len > isize::MAX as usize
+        {
+            return ORBITS_INVALID_ARGUMENT;
+        }
+        let pixels = slice::from_raw_parts_mut(pixels, len);
+        let mut canvas = raster::Canvas::new(width, height, sim.current().background);
+        raster::draw(sim, &mut canvas);
+        canvas.write(pixels, stride);
+        ORBITS_OK
+    })
+}
+
+/// Free a run made by `orbits_create`.
+///
+/// # Safety
+///
+/// `sim` must be null or come from `orbits_create`, and isn't to be used again.
+#[no_mangle]
+pub unsafe extern "C" fn orbits_destroy(sim: *mut Simulation) -> i32 {
+    guard(|| {
+        if sim.is_null() {
+            return ORBITS_INVALID_ARGUMENT;
+        }
+        drop(Box::from_raw(sim));
+        ORBITS_OK
+    })
+}
@@ -0,0 +1,216 @@
+//! A small software rasterizer for the scene, so a host can show the piece from a plain pixel
+//! buffer, whatever graphics API it uses, or none, without sharing a GPU context with it.
+
+use nannou_core::color::{Hsl, Hsla, Srgb};
+use nannou_core::prelude::*;
+use orbits_core::orbital_radius;
+use orbits_core::params::Param;
+use orbits_core::settings;
+use orbits_core::simulation::Simulation;
+use orbits_core::trails::{self, Line};
+
+/// Linear RGB pixels being drawn into, with world coordinates mapped onto them as trynannou fits
+/// the whole system in its window.
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+    /// Pixels per world unit.
+    scale: f32,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: Hsl) -> Self {
+        let [r, g, b, _] = linear(background.into());
+        Canvas {
+            width,
+            height,
+            pixels: vec![[r, g, b]; width * height],
+            scale: width.min(height) as f32 / 2.0 / orbital_radius() / 1.1,
+        }
+    }
+
+    /// Where `pos` in the world falls on the canvas, in pixels from its top left corner.
+    fn project(&self, pos: Point2) -> Vec2 {
+        vec2(
+            self.width as f32 / 2.0 + pos.x * self.scale,
+            self.height as f32 / 2.0 - pos.y * self.scale,
+        )
+    }
+
+    /// Blend a triangle over the canvas, its colors interpolated between its corners.
+    pub fn triangle(&mut self, corners: [(Point2, Hsla); 3]) {
+        let points = corners.map(|(pos, _)| self.project(pos));
+        let colors = corners.map(|(_, color)| linear(color));
+        let [a, b, c] = points;
+        let area = edge(a, b, c);
+        if area.abs() < 1e-6 {
+            return;
+        }
+        let Some((x0, x1, y0, y1)) = self.bounds(points.iter().copied()) else {
+            return;
+        };
+        for y in y0..y1 {
+            for x in x0..x1 {
+                // sampled at the middle of the pixel
+                let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [edge(b, c, p), edge(c, a, p), edge(a, b, p)].map(|w| w / area);
+                if weights.iter().any(|&w| w < 0.0) {
+                    continue;
+                }
+                let mut color = [0.0; 4];
+                for (corner, weight) in colors.iter().zip(weights) {
+                    for i in 0..4 {
+                        color[i] += corner[i] * weight;
+                    }
+                }
+                self.blend(x, y, color, 1.0);
+            }
+        }
+    }
+
+    /// Blend a disc of `radius` world units around `center` over the canvas, its edge smoothed.
+    pub fn disc(&mut self, center: Point2, radius: f32, color: Hsla) {
+        let middle = self.project(center);
+        let radius = radius * self.scale;
+        let corners = [
+            middle - Vec2::splat(radius + 1.0),
+            middle + Vec2::splat(radius + 1.0),
+        ];
+        let Some((x0, x1, y0, y1)) = self.bounds(corners.into_iter()) else {
+            return;
+        };
+        let color = linear(color);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let distance = vec2(x as f32 + 0.5, y as f32 + 0.5).distance(middle);
+                let coverage = (radius - distance + 0.5).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    /// Blend a polyline over the canvas as a quad along each segment.
+    pub fn line(&mut self, line: &Line) {
+        for segment in line.points.windows(2) {
+            let [(a, color_a), (b, color_b)] = segment else {
+                unreachable!()
+            };
+            let side = (*b - *a).perp().normalize_or_zero() * line.width / 2.0;
+            self.triangle([
+                (*a - side, *color_a),
+                (*a + side, *color_a),
+                (*b + side, *color_b),
+            ]);
+            self.triangle([
+                (*a - side, *color_a),
+                (*b + side, *color_b),
+                (*b - side, *color_b),
+            ]);
+        }
+    }
+
+    /// The pixels covered by the box around `points`, as half-open ranges clipped to the canvas.
+    fn bounds(&self, points: impl Iterator<Item = Vec2>) -> Option<(usize, usize, usize, usize)> {
+        let (min, max) = points.fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), p| (min.min(p), max.max(p)),
+        );
+        let x0 = min.x.floor().max(0.0) as usize;
+        let y0 = min.y.floor().max(0.0) as usize;
+        let x1 = (max.x.ceil().max(0.0) as usize).min(self.width);
+        let y1 = (max.y.ceil().max(0.0) as usize).min(self.height);
+        (x0 < x1 && y0 < y1).then_some((x0, x1, y0, y1))
+    }
+
+    fn blend(&mut self, x: usize, y: usize, [r, g, b, a]: [f32; 4], coverage: f32) {
+        let alpha = (a * coverage).clamp(0.0, 1.0);
+        let pixel = &mut self.pixels[y * self.width + x];
+        for (channel, source) in pixel.iter_mut().zip([r, g, b]) {
+            *channel += (source - *channel) * alpha;
+        }
+    }
+
+    /// Write the canvas as opaque 8 bit sRGB RGBA rows, `stride` bytes apart.
+    pub fn write(&self, rgba: &mut [u8], stride: usize) {
+        for (y, row) in self.pixels.chunks(self.width).enumerate() {
+            let out = &mut rgba[y * stride..y * stride + self.width * 4];
+            for (pixel, out) in row.iter().zip(out.chunks_exact_mut(4)) {
+                let [r, g, b] = pixel.map(encode);
+                out.copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+}
+
+/// Draw the scene as trynannou shows it, without the overlays, every generation at its opacity.
+pub fn draw(sim: &Simulation, canvas: &mut Canvas) {
+    let top = sim.current();
+    canvas.disc(Point2::ZERO, orbital_radius(), top.circle_color.into());
+
+    let highlight = sim.params.get(Param::Highlight);
+    let current = sim.generations.len() - 1;
+    for (i, generation) in sim.generations.iter().enumerate() {
+        let opacity = if i == current {
+            1.0
+        } else {
+            generation.opacity
+        };
+        let opacity = opacity * sim.params.get(Param::TrailOpacity);
+        let ribbons = trails::ribbons(generation, opacity, highlight, 0.0, 1.0);
+        for triangle in ribbons.indices.chunks_exact(3) {
+            canvas.triangle([0, 1, 2].map(|corner| {
+                let (pos, color) = ribbons.vertices[triangle[corner]];
+                (pos.truncate(), color)
+            }));
+        }
+        for line in trails::highlights(generation, opacity, highlight, 0.0, 1.0) {
+            canvas.line(&line);
+        }
+        for (age, positions) in generation.ghosts.iter().enumerate() {
+            let fade = 1.0 - age as f32 / settings::get().ghost_count as f32;
+            for (pos, color) in positions.iter().zip(&generation.colors) {
+                let mut color = *color;
+                color.alpha *= fade * generation.opacity;
+                canvas.disc(*pos, 12.0, color);
+            }
+        }
+    }
+
+    // as in trynannou, a lone central body is left undrawn
+    let bodies = sim.bodies();
+    if bodies.len() >= 2 {
+        let total: f32 = bodies.iter().map(|body| body.gm).sum();
+        for body in &bodies {
+            let radius = 40.0 * (body.gm / total).sqrt();
+            let mut halo: Hsla = top.body_color.into();
+            halo.alpha = 0.2;
+            canvas.disc(body.pos, radius * 1.4, halo);
+            canvas.disc(body.pos, radius, top.body_color.into());
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b - a).perp_dot(p - a)
+}
+
+/// A color as linear RGB with straight alpha.
+fn linear(color: Hsla) -> [f32; 4] {
+    let rgb = Srgb::from(color.color).into_linear();
+    [rgb.red, rgb.green, rgb.blue, color.alpha]
+}
+
+/// Encode a linear channel as 8 bit sRGB.
+fn encode(linear: f32) -> u8 {
+    let linear = linear.clamp(0.0, 1.0);
+    let srgb = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round() as u8
+}
@@ -0,0 +1,27 @@
+//! Stamps the build with the git commit it was built from, for the manifests written alongside
+//! exports, with `-dirty` on the end if the tree had changes. Built outside a checkout, or without
+//! git, it's left unset.
+
+use std::process::Command;
+
+fn main() {
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).output().ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        output.status.success().then_some(text)
+    };
+    println!("cargo:rerun-if-changed=src");
+    if let Some(dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        println!("cargo:rerun-if-changed={dir}/HEAD");
+        println!("cargo:rerun-if-changed={dir}/index");
+        // and the branch HEAD is on, so a commit to it is noticed
+        if let Some(branch) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            println!("cargo:rerun-if-changed={dir}/{branch}");
+        }
+    }
+    if let Some(commit) = git(&["rev-parse", "HEAD"]) {
+        let dirty = git(&["status", "--porcelain"]).is_some_and(|status| !status.is_empty());
+        let suffix = if dirty { "-dirty" } else { "" };
+        println!("cargo:rustc-env=TRYNANNOU_COMMIT={commit}{suffix}");
+    }
+}
@@ -0,0 +1,106 @@
+//! Which of the machine's GPUs the piece is drawn with, for a laptop with both an integrated and a
+//! discrete GPU, or a machine with several. wgpu only picks between them by how much power they
+//! draw, so a GPU asked for by its index is taken as the kind it is: an integrated GPU is asked for
+//! as low powered, a software one as the fallback, and any other as high performance. Whichever is
+//! picked is said on the terminal, along with whether it's the one asked for.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+
+/// The GPU asked for on the command line, if one was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preference {
+    /// The GPU at this index in those listed.
+    Index(usize),
+    /// Whichever draws the least power, an integrated GPU should there be one.
+    LowPower,
+    /// Whichever performs the best, a discrete GPU should there be one.
+    Discrete,
+}
+
+/// How the windows ask wgpu for their GPU.
+#[derive(Debug, Clone)]
+pub struct GpuRequest {
+    pub power_preference: wgpu::PowerPreference,
+    /// Whether the software fallback is asked for.
+    pub fallback: bool,
+    /// The name of the GPU asked for by its index, to be checked against the one picked.
+    wanted: Option<String>,
+}
+
+impl GpuRequest {
+    /// How to ask for the GPU `preference` is for, or why it can't be.
+    pub fn new(app: &App, preference: Option<Preference>) -> Result<Self, String> {
+        let power_preference = match preference {
+            Some(Preference::LowPower) => wgpu::PowerPreference::LowPower,
+            _ => wgpu::PowerPreference::HighPerformance,
+        };
+        let mut request = GpuRequest {
+            power_preference,
+            fallback: false,
+            wanted: None,
+        };
+        let Some(Preference::Index(index)) = preference else {
+            return Ok(request);
+        };
+        let adapters = adapters(app);
+        for (i, info) in adapters.iter().enumerate() {
+            println!("gpu {i}: {}", describe(info));
+        }
+        let info = adapters.get(index).ok_or_else(|| {
+            format!(
+                "there's no gpu {index}, as there are only {}",
+                adapters.len()
+            )
+        })?;
+        match info.device_type {
+            wgpu::DeviceType::IntegratedGpu => {
+                request.power_preference = wgpu::PowerPreference::LowPower
+            }
+            wgpu::DeviceType::Cpu => request.fallback = true,
+            _ => {}
+        }
+        request.wanted = Some(info.name.clone());
+        Ok(request)
+    }
+
+    /// Say which GPU the windows were given, warning if it's not the one asked for.
+    pub fn report(&self, app: &App) {
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: self.power_preference,
+            compatible_surface: None,
+            force_fallback_adapter: self.fallback,
+        };
+        // the window's adapter is kept by its power preference, so this is the same one
+        let Some(adapter) = app.wgpu_adapters().get_or_request(options, app.instance()) else {
+            return;
+        };
+        let info = adapter.get_info();
+        println!("drawing with {}", describe(&info));
+        if let Some(wanted) = self.wanted.as_ref().filter(|&wanted| *wanted != info.name) {
+            eprintln!(
+                "{wanted} was asked for, but wgpu gave {} in its place, as the first of its kind",
+                info.name
+            );
+        }
+    }
+}
+
+/// The GPUs there are to draw with, in the order they're indexed by.
+fn adapters(app: &App) -> Vec<wgpu::AdapterInfo> {
+    (app.instance().enumerate_adapters(wgpu::DEFAULT_BACKENDS))
+        .map(|adapter| adapter.get_info())
+        .collect()
+}
+
+/// A GPU's name, kind and the backend it's driven through.
+fn describe(info: &wgpu::AdapterInfo) -> String {
+    let kind = match info.device_type {
+        wgpu::DeviceType::IntegratedGpu => "integrated",
+        wgpu::DeviceType::DiscreteGpu => "discrete",
+        wgpu::DeviceType::VirtualGpu => "virtual",
+        wgpu::DeviceType::Cpu => "software",
+        wgpu::DeviceType::Other => "other",
+    };
+    format!("{} ({kind}, {:?})", info.name, info.backend)
+}
@@ -0,0 +1,279 @@
+//! Tints the room to match the piece, pushing the frame's dominant colors to Philips Hue and LIFX
+//! bulbs. Bulbs are slow to take commands, so they're updated every few seconds from a thread of
+//! their own, each fading to its new color over the time until the next.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use nannou::color::{Hsv, Srgb};
+use nannou::rand::random;
+use nannou::wgpu;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::export::{Offscreen, Shot};
+use crate::lighting::{DominantColors, SHOT_SIZE};
+use crate::style::Style;
+use crate::transition::TransitionConfig;
+
+/// Longest to wait on a Hue bridge before giving up on an update.
+const HUE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Color temperature LIFX bulbs are told to use for the white in a color, in kelvin.
+const LIFX_KELVIN: u16 = 3500;
+
+/// The `[ambience]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AmbienceConfig {
+    /// Seconds between updates. Hue bridges manage about ten commands a second between all
+    /// their lights.
+    pub interval: f32,
+    /// Multiplier on the bulbs' brightness, from 0 to 1.
+    pub brightness: f32,
+    pub hue: Option<HueConfig>,
+    /// Addresses of LIFX bulbs, usually on port 56700.
+    pub lifx: Vec<SocketAddr>,
+}
+
+impl Default for AmbienceConfig {
+    fn default() -> Self {
+        AmbienceConfig {
+            interval: 2.0,
+            brightness: 1.0,
+            hue: None,
+            lifx: Vec::new(),
+        }
+    }
+}
+
+/// Lights on a Philips Hue bridge.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct HueConfig {
+    /// Address of the bridge, e.g. `192.168.1.2:80`.
+    pub bridge: SocketAddr,
+    /// A user made on the bridge for the piece, by pressing its link button.
+    pub username: String,
+    /// IDs of the lights to set.
+    pub lights: Vec<String>,
+}
+
+/// A bulb to set.
+enum Bulb {
+    Hue { light: String },
+    Lifx { addr: SocketAddr },
+}
+
+/// Samples frames every `interval` and hands their colors to the thread updating the bulbs.
+pub struct Ambience {
+    config: AmbienceConfig,
+    offscreen: Offscreen,
+    dominant: DominantColors,
+    bulbs: usize,
+    /// Wall-clock seconds until the next update.
+    until: f32,
+    updates: SyncSender<Vec<[f32; 3]>>,
+}
+
+impl Ambience {
+    pub fn new(config: AmbienceConfig, transition: TransitionConfig) -> Result<Self, String> {
+        let hue_lights = config.hue.iter().flat_map(|hue| &hue.lights);
+        let bulbs: Vec<Bulb> = hue_lights
+            .map(|light| Bulb::Hue {
+                light: light.clone(),
+            })
+            .chain(config.lifx.iter().map(|&addr| Bulb::Lifx { addr }))
+            .collect();
+        if bulbs.is_empty() {
+            return Err("--ambience needs Hue lights or LIFX bulbs in [ambience]".into());
+        }
+        let count = bulbs.len();
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|err| format!("LIFX: {err}"))?;
+
+        // an update waiting to be sent is replaced by a newer one rather than queued
+        let (updates, pending) = mpsc::sync_channel::<Vec<[f32; 3]>>(1);
+        let sender = Sender {
+            config: config.clone(),
+            socket,
+            source: random(),
+        };
+        thread::spawn(move || {
+            for colors in pending {
+                for (bulb, color) in bulbs.iter().zip(colors.iter().cycle()) {
+                    if let Err(err) = sender.set(bulb, *color) {
+                        eprintln!("failed to update a bulb: {err}");
+                    }
+                }
+            }
+        });
+
+        Ok(Ambience {
+            bulbs: count,
+            config,
+            offscreen: Offscreen::new(transition, Style::default()),
+            dominant: DominantColors::default(),
+            until: 0.0,
+            updates,
+        })
+    }
+
+    /// Count down `wall_seconds`, returning whether the bulbs are due an update.
+    pub fn due(&mut self, wall_seconds: f32) -> bool {
+        self.until -= wall_seconds;
+        if self.until > 0.0 {
+            return false;
+        }
+        self.until = self.config.interval;
+        true
+    }
+
+    /// Render `shot` at `SHOT_SIZE` and send its dominant colors to the bulbs, one each, the most
+    /// dominant to the first bulb.
+    pub fn send(&mut self, device_queue: &Arc<wgpu::DeviceQueuePair>, shot: Shot) {
+        let Some(frame) = self.offscreen.capture(device_queue, SHOT_SIZE, shot) else {
+            return;
+        };
+        let colors = self.dominant.update(&frame.pixels(), self.bulbs);
+        // a failure means the thread is still busy with the last update
+        let _ = self.updates.try_send(colors);
+    }
+}
+
+/// Sets bulbs' colors, on the thread of its own.
+struct Sender {
+    config: AmbienceConfig,
+    socket: UdpSocket,
+    /// Identifies this client to LIFX bulbs.
+    source: u32,
+}
+
+impl Sender {
+    /// Fade `bulb` to `color`, in sRGB, over the interval.
+    fn set(&self, bulb: &Bulb, color: [f32; 3]) -> Result<(), String> {
+        let [r, g, b] = color;
+        let hsv = Hsv::from(Srgb::new(r, g, b));
+        let brightness = (hsv.value * self.config.brightness).clamp(0.0, 1.0);
+        match bulb {
+            Bulb::Hue { light } => {
+                let hue = self.config.hue.as_ref().unwrap();
+                let [x, y] = hue_xy(color);
+                let body = serde_json::json!({
+                    "on": brightness > 0.0,
+                    "xy": [x, y],
+                    "bri": (brightness * 254.0).round() as u8,
+                    // in tenths of a second
+                    "transitiontime": (self.config.interval * 10.0).round() as u32,
+                });
+                let path = format!("/api/{}/lights/{light}/state", hue.username);
+                put(hue.bridge, &path, &body.to_string())
+                    .map_err(|err| format!("Hue light {light}: {err}"))
+            }
+            Bulb::Lifx { addr } => {
+                let packet = lifx_set_color(
+                    self.source,
+                    [
+                        hsv.hue.to_positive_degrees() / 360.0,
+                        hsv.saturation,
+                        brightness,
+                    ],
+                    (self.config.interval * 1000.0) as u32,
+                );
+                self.socket
+                    .send_to(&packet, addr)
+                    .map(|_| ())
+                    .map_err(|err| format!("LIFX bulb {addr}: {err}"))
+            }
+        }
+    }
+}
+
+/// The CIE xy chromaticity of an sRGB color, as Hue lights are set by.
+fn hue_xy(color: [f32; 3]) -> [f32; 2] {
+    let [r, g, b] = color.map(linear);
+    // sRGB to XYZ, with a D65 white point
+    let x = 0.4124 * r + 0.3576 * g + 0.1805 * b;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = 0.0193 * r + 0.1192 * g + 0.9505 * b;
+    let sum = x + y + z;
+    if sum <= 0.0 {
+        // black has no chromaticity, so show white, dimmed to nothing
+        return [0.3127, 0.3290];
+    }
+    [x / sum, y / sum]
+}
+
+/// Decode an sRGB channel to linear.
+fn linear(srgb: f32) -> f32 {
+    if srgb <= 0.04045 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Make an HTTP PUT request of a JSON `body`, failing unless it succeeds.
+fn put(addr: SocketAddr, path: &str, body: &str) -> Result<(), String> {
+    let mut stream =
+        TcpStream::connect_timeout(&addr, HUE_TIMEOUT).map_err(|err| err.to_string())?;
+    stream
+        .set_read_timeout(Some(HUE_TIMEOUT))
+        .map_err(|err| err.to_string())?;
+    let request = format!(
+        "PUT {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|err| err.to_string())?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|err| err.to_string())?;
+    let status = response.lines().next().unwrap_or_default();
+    // the bridge reports a bad request as errors in a 200 response
+    if !status.contains(" 200 ") || response.contains("\"error\"") {
+        return Err(format!("the bridge answered {status:?}: {response}"));
+    }
+    Ok(())
+}
+
+/// A LIFX LAN SetColor message, fading to `hsb`, each from 0 to 1, over `duration_ms`.
+fn lifx_set_color(
+    source: u32,
+    [hue, saturation, brightness]: [f32; 3],
+    duration_ms: u32,
+) -> Vec<u8> {
+    let scale = |value: f32| (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+    let mut payload = vec![0];
+    for value in [
+        scale(hue),
+        scale(saturation),
+        scale(brightness),
+        LIFX_KELVIN,
+    ] {
+        payload.extend(value.to_le_bytes());
+    }
+    payload.extend(duration_ms.to_le_bytes());
+
+    let size = 36 + payload.len() as u16;
+    let mut packet = Vec::with_capacity(size as usize);
+    // frame header: protocol 1024, addressable, and tagged as the target is any bulb
+    packet.extend(size.to_le_bytes());
+    packet.extend((1024u16 | 1 << 12 | 1 << 13).to_le_bytes());
+    packet.extend(source.to_le_bytes());
+    // frame address: any target, no acknowledgement or response wanted
+    packet.extend(0u64.to_le_bytes());
+    packet.extend([0; 6]);
+    packet.extend([0, 0]);
+    // protocol header, of message type SetColor
+    packet.extend(0u64.to_le_bytes());
+    packet.extend(102u16.to_le_bytes());
+    packet.extend(0u16.to_le_bytes());
+    packet.extend(payload);
+    packet
+}
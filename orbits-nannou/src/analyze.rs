@@ -0,0 +1,282 @@
+//! Sums up how a seed's system holds together under a config, for designing presets without
+//! watching each one play out: a short run is simulated headlessly, and the orbits it ends on
+//! sized up as the legend sorts them, the frame a still of it would be covered in trails, and
+//! how far each integrator lets the swarm's energy drift over as many steps. The drift is measured
+//! under the pull of the masses alone, the only part of it with an energy to keep, so that it's
+//! the integrator's error rather than the work done by fields and plugins.
+//!
+//! For debugging the physics, two runs of a seed can be stepped in lockstep, each traced by a run
+//! of a build of its own under a config of its own, to find the first step their particles part
+//! ways at: where a change to the parallelism, the integrator or a refactor altered what the
+//! simulation does, rather than only how.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+use nannou::prelude::*;
+use orbits_core::integrator::Scheme;
+use orbits_core::params::Param;
+use orbits_core::physics::{self, Body, Particle};
+use orbits_core::simulation::Simulation;
+use orbits_core::trails::Occupancy;
+use orbits_core::{plugins, settings};
+
+use crate::batch;
+use crate::compare::FRAME_SECONDS;
+use crate::compute::Look;
+use crate::config::Config;
+use crate::perspective::Perspective;
+use crate::render;
+use crate::viewport::Viewport;
+
+/// The integrators compared, as the `integrator` of `[simulation]` names them.
+const SCHEMES: [(Scheme, &str); 3] = [
+    (Scheme::SemiImplicitEuler, "semi_implicit_euler"),
+    (Scheme::VelocityVerlet, "velocity_verlet"),
+    (Scheme::Rk4, "rk4"),
+];
+
+/// Points across each cell of the grid the coverage is counted on, in a still of the batch's size.
+const COVERAGE_CELL: f32 = 8.0;
+
+/// Simulate `seed` for `seconds` under the config at `path`, or the one in the working directory,
+/// and print what it comes to.
+pub fn explain(seed: u64, seconds: f32, path: Option<&Path>) -> Result<(), String> {
+    let config = load(path)?;
+    let steps = (seconds / FRAME_SECONDS).round() as usize;
+    let mut simulation = Simulation::new(seed);
+    let start = simulation.current().particles.clone();
+    let start_bodies = simulation.bodies();
+    let delta_seconds = FRAME_SECONDS * simulation.params.get(Param::TimeScale);
+    for _ in 0..steps {
+        simulation.step(FRAME_SECONDS);
+    }
+
+    let particles = &simulation.current().particles;
+    let gm: f32 = simulation.bodies().iter().map(|body| body.gm).sum();
+    // sized up as the legend sorts them, as if the masses were one inverse-square point
+    let bound: Vec<f32> = (particles.iter())
+        .filter(|p| p.vel.length_squared() / 2.0 - gm / p.pos.length() < 0.0)
+        .map(|p| physics::eccentricity(p.pos, p.vel, gm))
+        .collect();
+    let escaping = particles.len() - bound.len();
+    println!("seed {seed}, {seconds} seconds simulated in {steps} steps");
+    println!("particles          {}", particles.len());
+    println!(
+        "mean eccentricity  {:.3} of the {} bound",
+        bound.iter().sum::<f32>() / bound.len().max(1) as f32,
+        bound.len()
+    );
+    println!(
+        "escaping           {:.1}%",
+        percent(escaping, particles.len())
+    );
+    println!(
+        "trail coverage     {:.1}% of a still",
+        coverage(&config, &simulation) * 100.0
+    );
+    println!("energy drift over {steps} steps, under the masses alone:");
+    let chosen = settings::get().integrator;
+    for (scheme, name) in SCHEMES {
+        let drift = drift(scheme, &start, &start_bodies, delta_seconds, steps);
+        let note = if scheme == chosen {
+            " (the config's)"
+        } else {
+            ""
+        };
+        println!("  {name:<20} {:.4}%{note}", drift * 100.0);
+    }
+    Ok(())
+}
+
+/// Load the config at `path`, or the one in the working directory, and set up the simulation
+/// under it.
+fn load(path: Option<&Path>) -> Result<Config, String> {
+    let config = match path {
+        Some(path) => Config::load_file(path)?,
+        None => Config::load()?,
+    };
+    settings::set(config.simulation.clone())?;
+    plugins::load(Path::new(plugins::PLUGINS_DIR))?;
+    Ok(config)
+}
+
+/// One of the two runs `diverge` steps in lockstep.
+pub struct Side {
+    /// The build to trace it with, or this one.
+    pub build: Option<PathBuf>,
+    pub config: PathBuf,
+}
+
+/// Simulate `seed` for `seconds` under the config at `path`, or the one in the working directory,
+/// printing the current generation's particles a line a step, from before the first, for
+/// `diverge` to read.
+pub fn trace(seed: u64, seconds: f32, path: Option<&Path>) -> Result<(), String> {
+    load(path)?;
+    let steps = (seconds / FRAME_SECONDS).round() as usize;
+    let mut simulation = Simulation::new(seed);
+    let mut out = io::stdout().lock();
+    for step in 0..=steps {
+        if step > 0 {
+            simulation.step(FRAME_SECONDS);
+        }
+        let line = serde_json::to_string(&simulation.current().particles).unwrap();
+        // `diverge` stops reading once they part ways, so a closed pipe is the end of it
+        if writeln!(out, "{line}").is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Step `seed` for `seconds` under each of `sides` in lockstep, and print the first step any of
+/// their particles are further apart than `tolerance` at, in position or velocity, or that they
+/// never are.
+pub fn diverge(sides: [Side; 2], seed: u64, seconds: f32, tolerance: f32) -> Result<(), String> {
+    let mut runs = sides.map(|side| Run::start(side, seed, seconds));
+    let [a, b] = match &mut runs {
+        [Ok(a), Ok(b)] => [a, b],
+        [Err(err), _] | [_, Err(err)] => return Err(err.clone()),
+    };
+    let steps = (seconds / FRAME_SECONDS).round() as usize;
+    for step in 0..=steps {
+        let (ours, theirs) = (a.next()?, b.next()?);
+        let time = step as f32 * FRAME_SECONDS;
+        if ours.len() != theirs.len() {
+            println!(
+                "diverged at step {step} ({time:.3} wall seconds): {} particles against {}",
+                ours.len(),
+                theirs.len()
+            );
+            return Ok(());
+        }
+        let apart: Vec<(usize, f32, f32)> = (ours.iter().zip(&theirs).enumerate())
+            .map(|(i, (a, b))| (i, a.pos.distance(b.pos), a.vel.distance(b.vel)))
+            .filter(|&(_, pos, vel)| !(pos <= tolerance && vel <= tolerance))
+            .collect();
+        if let Some(&(particle, pos, vel)) = apart.first() {
+            println!(
+                "diverged at step {step} ({time:.3} wall seconds), {} of {} particles beyond {tolerance}",
+                apart.len(),
+                ours.len()
+            );
+            println!("  first is particle {particle}, {pos:e} apart, moving {vel:e} apart");
+            println!("  {:?}", ours[particle]);
+            println!("  {:?}", theirs[particle]);
+            return Ok(());
+        }
+    }
+    println!("seed {seed} stayed within {tolerance} for all {steps} steps");
+    Ok(())
+}
+
+/// A run tracing one side of `diverge`.
+struct Run {
+    child: Child,
+    lines: io::Lines<BufReader<ChildStdout>>,
+    config: PathBuf,
+}
+
+impl Run {
+    fn start(side: Side, seed: u64, seconds: f32) -> Result<Self, String> {
+        // each side traces in a run of its own, as the settings can only be set once a process
+        let exe = match side.build {
+            Some(build) => build,
+            None => std::env::current_exe().map_err(|err| err.to_string())?,
+        };
+        let mut child = Command::new(&exe)
+            .args(["analyze", "trace", "--config"])
+            .arg(&side.config)
+            .args(["--seed", &seed.to_string()])
+            .args(["--seconds", &seconds.to_string()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("{}: {err}", exe.display()))?;
+        let lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        Ok(Run {
+            child,
+            lines,
+            config: side.config,
+        })
+    }
+
+    /// The particles at the next step.
+    fn next(&mut self) -> Result<Vec<Particle>, String> {
+        let failed = || format!("the trace under {} stopped short", self.config.display());
+        let line = self.lines.next().ok_or_else(failed)?;
+        let line = line.map_err(|err| err.to_string())?;
+        serde_json::from_str(&line).map_err(|err| format!("{}: {err}", self.config.display()))
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The share of `part` in `whole`, as a percentage.
+fn percent(part: usize, whole: usize) -> f32 {
+    part as f32 / whole.max(1) as f32 * 100.0
+}
+
+/// How much of a still of the batch's size, framed as it frames the system, the current
+/// generation's trails cover, in the config's trail style.
+fn coverage(config: &Config, simulation: &Simulation) -> f32 {
+    let [width, height] = batch::DEFAULT_SIZE.map(|side| side as f32);
+    let win = Rect::from_w_h(width, height);
+    let viewport = Viewport::whole_system(win);
+    let mut occupancy = Occupancy::new(viewport.shown(win), viewport.to_units(COVERAGE_CELL));
+    let params = &simulation.params;
+    let look = Look {
+        opacity: params.get(Param::TrailOpacity),
+        highlight: params.get(Param::Highlight),
+        extrapolate: 0.0,
+        build_in: 1.0,
+        length: params.get(Param::TrailLength),
+        view: (settings::get().three_d).then(|| Perspective::at(simulation.time)),
+    };
+    let generation = simulation.current();
+    occupancy.add(&render::build(
+        config.render.style,
+        &config.render,
+        generation,
+        look,
+    ));
+    occupancy.overdraw().coverage
+}
+
+/// How far, on average, the energy of each of the `particles` bound to `bodies` drifts from where
+/// it started, as a share of it, over `steps` of `delta_seconds` with `scheme`.
+fn drift(
+    scheme: Scheme,
+    particles: &[Particle],
+    bodies: &[Body],
+    delta_seconds: f32,
+    steps: usize,
+) -> f32 {
+    let law = settings::get().gravity_law;
+    let energy = |p: &Particle| {
+        let potential: f32 = (bodies.iter())
+            .map(|body| law.potential(p.pos.distance(body.pos), body.gm))
+            .sum();
+        p.vel.length_squared() / 2.0 + potential
+    };
+    let acceleration = |pos: Point2, _| physics::acceleration_at(pos, bodies);
+    let integrator = scheme.integrator();
+    let drifts: Vec<f32> = (particles.iter())
+        .filter(|p| energy(p) < 0.0)
+        .map(|p| {
+            let before = energy(p);
+            let mut p = p.clone();
+            for _ in 0..steps {
+                integrator.step(&mut p, delta_seconds, &acceleration);
+            }
+            ((energy(&p) - before) / before).abs()
+        })
+        .filter(|drift| drift.is_finite())
+        .collect();
+    drifts.iter().sum::<f32>() / drifts.len().max(1) as f32
+}
@@ -0,0 +1,130 @@
+//! ASCII art: the frame redrawn as a grid of glyphs, each tile's as dense as the tile is bright
+//! and in its color, like the piece in a terminal.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use nannou::image::{GrayImage, Luma};
+use nannou::text::{self, font};
+use orbits_core::ascii::RAMP;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::assets;
+use crate::gpu::{Filter, Post, Scene, Target};
+
+/// Texels across each glyph of the atlas, which are twice as many down.
+const ATLAS_CELL: u32 = 32;
+/// The most glyphs the atlas can hold.
+const MAX_GLYPHS: usize = 64;
+
+/// The `[ascii]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsciiConfig {
+    /// Pixels across each glyph's tile, which is twice as tall, or 0 for no ASCII art.
+    pub cell: f32,
+    /// Up to 64 glyphs to draw the frame in, from darkest to brightest.
+    pub glyphs: String,
+    /// A font to draw the glyphs in instead of the built-in Noto Sans, for glyphs it hasn't got.
+    pub font: Option<PathBuf>,
+}
+
+impl Default for AsciiConfig {
+    fn default() -> Self {
+        AsciiConfig {
+            cell: 0.0,
+            glyphs: RAMP.to_string(),
+            font: None,
+        }
+    }
+}
+
+/// The ASCII art filter, redrawing whatever is drawn through it.
+#[derive(Clone)]
+pub struct Ascii(Filter);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Uniforms {
+    cell: [f32; 2],
+    glyphs: f32,
+    _padding: f32,
+}
+
+impl Ascii {
+    /// The filter `config` asks for, or none if it's turned off.
+    pub fn load(config: &AsciiConfig) -> Result<Option<Self>, String> {
+        if config.cell <= 0.0 {
+            return Ok(None);
+        }
+        let glyphs: Vec<char> = config.glyphs.chars().collect();
+        if glyphs.is_empty() || glyphs.len() > MAX_GLYPHS {
+            return Err(format!(
+                "ascii art needs from 1 to {MAX_GLYPHS} glyphs, not {}",
+                glyphs.len()
+            ));
+        }
+        let font = match &config.font {
+            Some(path) => font::from_file(assets::resolve(path))
+                .map_err(|err| format!("{}: {err}", path.display()))?,
+            None => font::default_notosans(),
+        };
+        let uniforms = Uniforms {
+            cell: [config.cell, config.cell * 2.0],
+            glyphs: glyphs.len() as f32,
+            _padding: 0.0,
+        };
+        let shader = include_str!("shaders/ascii.wgsl");
+        Ok(Some(Ascii(Filter::new(
+            "ascii",
+            shader,
+            Arc::new(atlas(&font, &glyphs)),
+            &uniforms,
+        ))))
+    }
+}
+
+impl Post for Ascii {
+    fn draw(&self, target: &mut Target, f: &mut dyn FnMut(&mut Target, &mut Scene)) {
+        self.0.draw(target, f)
+    }
+}
+
+/// `glyphs` rasterized side by side in `font`, each centered in a cell `ATLAS_CELL` across and
+/// twice that down, all at the one size that fits the widest.
+fn atlas(font: &text::Font, glyphs: &[char]) -> GrayImage {
+    let (width, height) = (ATLAS_CELL as f32, ATLAS_CELL as f32 * 2.0);
+    let measure = text::Scale::uniform(height);
+    let metrics = font.v_metrics(measure);
+    let widest = glyphs
+        .iter()
+        .map(|&c| font.glyph(c).scaled(measure).h_metrics().advance_width)
+        .fold(0.0, f32::max);
+    let fit = (width / widest.max(1.0)).min(height / (metrics.ascent - metrics.descent));
+    let scale = text::Scale::uniform(height * fit.min(1.0));
+    let metrics = font.v_metrics(scale);
+    // centered down the cell, descenders and all
+    let baseline = (height + metrics.ascent + metrics.descent) / 2.0;
+
+    let mut atlas = GrayImage::new(ATLAS_CELL * glyphs.len() as u32, ATLAS_CELL * 2);
+    for (i, &c) in glyphs.iter().enumerate() {
+        let glyph = font.glyph(c).scaled(scale);
+        let left = i as f32 * width + (width - glyph.h_metrics().advance_width) / 2.0;
+        let glyph = glyph.positioned(text::rt::point(left, baseline));
+        let Some(bounds) = glyph.pixel_bounding_box() else {
+            continue;
+        };
+        let cell = (i as u32 * ATLAS_CELL) as i32..((i as u32 + 1) * ATLAS_CELL) as i32;
+        glyph.draw(|gx, gy, coverage| {
+            let x = bounds.min.x + gx as i32;
+            let y = bounds.min.y + gy as i32;
+            // clipped to its own cell, if it's wider than it should be
+            if cell.contains(&x) && (0..atlas.height() as i32).contains(&y) {
+                let value = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                atlas.put_pixel(x as u32, y as u32, Luma([value]));
+            }
+        });
+    }
+    atlas
+}
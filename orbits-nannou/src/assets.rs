@@ -0,0 +1,53 @@
+//! Finding the files the piece reads, so it runs the same from a checkout, from a folder it was
+//! unpacked into and from an app bundle double-clicked with no working directory to speak of.
+//!
+//! A relative path is looked for in the working directory, then beside the executable, then
+//! wherever the platform's bundle puts resources. What has to be there for the piece to look
+//! right at all is built in instead: the icon here, and the font and grain texture where they're
+//! used.
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+use nannou::image;
+use nannou::winit::window::Icon;
+
+/// The app icon, as a PNG.
+pub const ICON: &[u8] = include_bytes!("../assets/icon.png");
+
+/// Where the file or directory at `path` is, relative paths looked for in each of the places
+/// assets can be in turn, or `path` as it is if it's in none of them.
+pub fn resolve(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    if path.is_absolute() || path.exists() {
+        return path.to_owned();
+    }
+    (search_dirs().into_iter())
+        .map(|dir| dir.join(path))
+        .find(|candidate| candidate.exists())
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// The places besides the working directory assets are looked for, in order.
+fn search_dirs() -> Vec<PathBuf> {
+    let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_owned))
+    else {
+        return Vec::new();
+    };
+    vec![
+        dir.clone(),
+        // a macOS app keeps them in Contents/Resources, the executable in Contents/MacOS
+        dir.join("../Resources"),
+        // an AppImage or a Linux package under usr/share, the executable in usr/bin
+        dir.join("../share/trynannou"),
+    ]
+}
+
+/// The icon as the window wants it, or none if it somehow doesn't decode.
+pub fn icon() -> Option<Icon> {
+    let image = image::load_from_memory(ICON).ok()?.to_rgba8();
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height).ok()
+}
@@ -0,0 +1,454 @@
+//! The piece played along to the room's sound, with `--audio`: the default input's loudness, and
+//! how much of it is bass, mids and highs, as signals nudging the parameters the config maps them
+//! to. Built with the `cpal` feature, the default input is recorded through cpal, the platform's
+//! own audio API. Without it, so a build needs no more system libraries than nannou does, it's
+//! recorded by a recorder program run for the purpose, `arecord` on Linux or `ffmpeg` on macOS,
+//! and there's none on Windows. Whichever records it, or whatever recorder the config names
+//! instead, its samples are split into bands on a thread of their own, and should the input stop,
+//! as a recorder that isn't there or a device unplugged does, the piece says why.
+
+use std::collections::BTreeMap;
+use std::f32::consts::TAU;
+use std::io::{ErrorKind, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use orbits_core::params::{Modulation, Param, Params};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Samples each spectrum is taken over, a power of two.
+const FFT_SIZE: usize = 1024;
+/// Samples between one spectrum and the next.
+const HOP: usize = FFT_SIZE / 2;
+/// Where the bass ends and the mids start, in hertz.
+const BASS_TOP: f32 = 250.0;
+/// Where the mids end and the highs start, in hertz.
+const MIDS_TOP: f32 = 2000.0;
+
+/// The `[audio]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// The recorder run for the input's samples, which it's to write to its output as mono
+    /// 32-bit little-endian floats at `sample_rate`. `arecord` on Linux and `ffmpeg` on macOS
+    /// record the default input if left out.
+    pub command: Option<Vec<String>>,
+    /// Samples a second the recorder records at.
+    pub sample_rate: u32,
+    /// Multiplier on the input before it's measured, so the loudest it gets reads as about 1.
+    pub gain: f32,
+    /// Seconds a signal takes to rise most of the way to a louder level.
+    pub attack: f32,
+    /// Seconds a signal takes to fall most of the way to a quieter level.
+    pub release: f32,
+    /// Which signals move which parameters.
+    pub mappings: Vec<Mapping>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            command: None,
+            sample_rate: 48000,
+            gain: 4.0,
+            attack: 0.02,
+            release: 0.3,
+            mappings: vec![
+                Mapping {
+                    signal: Signal::Bass,
+                    param: Param::Gravity,
+                    amount: 0.5,
+                },
+                Mapping {
+                    signal: Signal::Level,
+                    param: Param::TrailOpacity,
+                    amount: 0.5,
+                },
+                Mapping {
+                    signal: Signal::Highs,
+                    param: Param::ColorJitter,
+                    amount: 0.02,
+                },
+            ],
+        }
+    }
+}
+
+/// A signal moving a parameter.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+    pub signal: Signal,
+    #[schemars(with = "String")]
+    pub param: Param,
+    /// How far the parameter is moved from where it's been put at the signal's loudest, negative
+    /// to move it down. Mappings to the same parameter add up.
+    pub amount: f32,
+}
+
+/// What's measured of the input, each from 0 for silence to about 1 for the loudest it gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Signal {
+    /// The loudness of the whole of it.
+    Level,
+    /// The loudness of what's below 250 Hz.
+    Bass,
+    /// The loudness of what's between 250 Hz and 2 kHz.
+    Mids,
+    /// The loudness of what's above 2 kHz.
+    Highs,
+}
+
+impl Signal {
+    const ALL: [Signal; 4] = [Signal::Level, Signal::Bass, Signal::Mids, Signal::Highs];
+}
+
+/// What the input's recorded by.
+enum Input {
+    /// A recorder program, writing the samples to its output.
+    Recorder(Child),
+    /// The platform's audio API, calling back with the samples for as long as it's held.
+    #[cfg(feature = "cpal")]
+    Stream(#[allow(dead_code)] cpal::Stream),
+}
+
+pub struct Audio {
+    config: AudioConfig,
+    /// Kept to stop the recording once the piece is done with it.
+    input: Input,
+    /// The signals of the latest spectrum, in the order of `Signal::ALL`, unsmoothed.
+    measured: Arc<Mutex<[f32; 4]>>,
+    /// The signals smoothed, in the order of `Signal::ALL`.
+    signals: [f32; 4],
+    modulation: Modulation,
+    /// Why the input stopped, if it has, yet to be told.
+    stopped: Arc<Mutex<Option<String>>>,
+}
+
+impl Audio {
+    /// Start recording the input.
+    pub fn new(config: AudioConfig) -> Result<Self, String> {
+        let measured = Arc::new(Mutex::new([0.0; 4]));
+        let stopped = Arc::new(Mutex::new(None));
+        let input = match config.command.clone() {
+            Some(command) => record(&command, config.sample_rate, &measured, &stopped)?,
+            None => default_input(config.sample_rate, &measured, &stopped)?,
+        };
+        Ok(Audio {
+            config,
+            input,
+            measured,
+            signals: [0.0; 4],
+            modulation: Modulation::default(),
+            stopped,
+        })
+    }
+
+    /// Why the input stopped, the once, if it has since the last call.
+    pub fn stopped(&mut self) -> Option<String> {
+        self.stopped.lock().unwrap().take()
+    }
+
+    /// Smooth the signals over the `wall_seconds` since the last frame, and move the parameters
+    /// they're mapped to. Parameters put somewhere else since, by hand or otherwise, are moved
+    /// from there.
+    pub fn modulate(&mut self, wall_seconds: f32, params: &mut Params) {
+        let measured = *self.measured.lock().unwrap();
+        for (signal, measured) in self.signals.iter_mut().zip(measured) {
+            let measured = (measured * self.config.gain).min(1.0);
+            let seconds = if measured > *signal {
+                self.config.attack
+            } else {
+                self.config.release
+            };
+            let eased = 1.0 - (-wall_seconds / seconds.max(1e-3)).exp();
+            *signal += (measured - *signal) * eased;
+        }
+
+        let mut offsets: BTreeMap<Param, f32> = BTreeMap::new();
+        for mapping in &self.config.mappings {
+            let index = Signal::ALL
+                .iter()
+                .position(|&s| s == mapping.signal)
+                .unwrap();
+            *offsets.entry(mapping.param).or_default() += mapping.amount * self.signals[index];
+        }
+        self.modulation.apply(offsets, params);
+    }
+}
+
+impl Drop for Audio {
+    // without cpal, a recorder's all there is to stop
+    #[allow(irrefutable_let_patterns)]
+    fn drop(&mut self) {
+        if let Input::Recorder(recorder) = &mut self.input {
+            let _ = recorder.kill();
+            let _ = recorder.wait();
+        }
+    }
+}
+
+/// Start `command` recording the input at `sample_rate`, its samples analyzed into `measured`
+/// until it stops, and why into `stopped`.
+fn record(
+    command: &[String],
+    sample_rate: u32,
+    measured: &Arc<Mutex<[f32; 4]>>,
+    stopped: &Arc<Mutex<Option<String>>>,
+) -> Result<Input, String> {
+    let (program, args) = command.split_first().ok_or("the audio command is empty")?;
+    let mut recorder = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("couldn't start the audio recorder {program:?}: {err}"))?;
+    let stdout = recorder.stdout.take().unwrap();
+    spawn_analysis(stdout, sample_rate as f32, measured, stopped);
+    Ok(Input::Recorder(recorder))
+}
+
+/// Analyze the samples read from `input` into `measured` on a thread of its own, until it ends,
+/// then say why in `stopped`.
+fn spawn_analysis(
+    input: impl Read + Send + 'static,
+    sample_rate: f32,
+    measured: &Arc<Mutex<[f32; 4]>>,
+    stopped: &Arc<Mutex<Option<String>>>,
+) {
+    let (measured, stopped) = (measured.clone(), stopped.clone());
+    thread::spawn(move || {
+        if let Err(err) = analyze(input, sample_rate, &measured) {
+            eprintln!("the audio input stopped: {err}");
+            *stopped.lock().unwrap() = Some(format!("the audio input stopped: {err}"));
+        }
+        *measured.lock().unwrap() = [0.0; 4];
+    });
+}
+
+/// The default input recorded through the platform's audio API, at whatever rate it records at.
+#[cfg(feature = "cpal")]
+fn default_input(
+    _sample_rate: u32,
+    measured: &Arc<Mutex<[f32; 4]>>,
+    stopped: &Arc<Mutex<Option<String>>>,
+) -> Result<Input, String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+    use cpal::SampleFormat;
+
+    let device =
+        (cpal::default_host().default_input_device()).ok_or("there's no audio input to record")?;
+    let supported = device
+        .default_input_config()
+        .map_err(|err| format!("couldn't configure the audio input: {err}"))?;
+    let format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let channels = config.channels as usize;
+    // the samples, as the recorders write them, mono 32-bit little-endian floats
+    let (sender, samples) = std::sync::mpsc::channel::<Vec<u8>>();
+    let mono = move |frames: Vec<f32>| {
+        let bytes = (frames.chunks(channels))
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let _ = sender.send(bytes);
+    };
+    let failed = stopped.clone();
+    let error = move |err: cpal::StreamError| {
+        *failed.lock().unwrap() = Some(format!("the audio input stopped: {err}"));
+    };
+    let stream = match format {
+        SampleFormat::F32 => device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &_| mono(data.to_vec()),
+            error,
+            None,
+        ),
+        SampleFormat::I16 => device.build_input_stream(
+            &config,
+            move |data: &[i16], _: &_| {
+                mono(data.iter().map(|&s| s as f32 / i16::MAX as f32).collect())
+            },
+            error,
+            None,
+        ),
+        SampleFormat::U16 => device.build_input_stream(
+            &config,
+            move |data: &[u16], _: &_| {
+                mono(data.iter().map(|&s| s as f32 / 32768.0 - 1.0).collect())
+            },
+            error,
+            None,
+        ),
+        format => return Err(format!("can't record audio in {format:?} samples")),
+    }
+    .map_err(|err| format!("couldn't record the audio input: {err}"))?;
+    stream
+        .play()
+        .map_err(|err| format!("couldn't record the audio input: {err}"))?;
+    let reader = Samples {
+        samples,
+        pending: Vec::new(),
+    };
+    spawn_analysis(reader, config.sample_rate.0 as f32, measured, stopped);
+    Ok(Input::Stream(stream))
+}
+
+/// The samples the platform's audio API calls back with, read as a recorder's output would be.
+#[cfg(feature = "cpal")]
+struct Samples {
+    samples: std::sync::mpsc::Receiver<Vec<u8>>,
+    /// Bytes of the last lot not read yet.
+    pending: Vec<u8>,
+}
+
+#[cfg(feature = "cpal")]
+impl Read for Samples {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pending.is_empty() {
+            match self.samples.recv() {
+                Ok(bytes) => self.pending = bytes,
+                // the stream's gone, ending the input
+                Err(_) => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.pending.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        Ok(len)
+    }
+}
+
+/// The default input recorded by a recorder on this platform, if there's one to be had.
+#[cfg(not(feature = "cpal"))]
+fn default_input(
+    sample_rate: u32,
+    measured: &Arc<Mutex<[f32; 4]>>,
+    stopped: &Arc<Mutex<Option<String>>>,
+) -> Result<Input, String> {
+    record(
+        &default_command(sample_rate)?,
+        sample_rate,
+        measured,
+        stopped,
+    )
+}
+
+/// A recorder of the default input on this platform, if there's one to be had.
+#[cfg(not(feature = "cpal"))]
+fn default_command(sample_rate: u32) -> Result<Vec<String>, String> {
+    let rate = sample_rate.to_string();
+    let command: &[&str] = if cfg!(target_os = "linux") {
+        &[
+            "arecord", "-q", "-t", "raw", "-f", "FLOAT_LE", "-c", "1", "-r", &rate,
+        ]
+    } else if cfg!(target_os = "macos") {
+        &[
+            "ffmpeg",
+            "-loglevel",
+            "quiet",
+            "-f",
+            "avfoundation",
+            "-i",
+            ":0",
+            "-ac",
+            "1",
+            "-ar",
+            &rate,
+            "-f",
+            "f32le",
+            "-",
+        ]
+    } else {
+        return Err(
+            "there's no default audio recorder here, so set [audio] command, or build with \
+             --features cpal"
+                .into(),
+        );
+    };
+    Ok(command.iter().map(|arg| arg.to_string()).collect())
+}
+
+/// Read samples from `input` until it ends, measuring the signals of each spectrum into
+/// `measured`.
+fn analyze(
+    mut input: impl Read,
+    sample_rate: f32,
+    measured: &Mutex<[f32; 4]>,
+) -> Result<(), String> {
+    let window: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+        .collect();
+    // a Hann window keeps three eighths of the power of what it's over, on average
+    let window_power = 0.375;
+    let hertz_per_bin = sample_rate / FFT_SIZE as f32;
+    let mut samples = vec![0.0; FFT_SIZE];
+    let mut bytes = vec![0; HOP * 4];
+    loop {
+        match input.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                return Err("no more samples".into())
+            }
+            Err(err) => return Err(err.to_string()),
+        }
+        samples.drain(..HOP);
+        samples.extend(
+            bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+        );
+
+        let level = (samples.iter().map(|s| s * s).sum::<f32>() / FFT_SIZE as f32).sqrt();
+        let mut spectrum: Vec<(f32, f32)> = (samples.iter().zip(&window))
+            .map(|(s, w)| (s * w, 0.0))
+            .collect();
+        fft(&mut spectrum);
+        // each band's share of the mean square, the positive and negative frequencies both
+        let mut bands = [0.0; 3];
+        for (bin, (re, im)) in spectrum.iter().enumerate().take(FFT_SIZE / 2).skip(1) {
+            let hertz = bin as f32 * hertz_per_bin;
+            let band = if hertz < BASS_TOP {
+                0
+            } else if hertz < MIDS_TOP {
+                1
+            } else {
+                2
+            };
+            bands[band] += 2.0 * (re * re + im * im);
+        }
+        let norm = (FFT_SIZE * FFT_SIZE) as f32 * window_power;
+        let [bass, mids, highs] = bands.map(|power| (power / norm).sqrt());
+        *measured.lock().unwrap() = [level, bass, mids, highs];
+    }
+}
+
+/// Transform `values`, a power of two of them, into their spectrum in place.
+fn fft(values: &mut [(f32, f32)]) {
+    let n = values.len();
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (re, im) = values[start + k + len / 2];
+                let twiddled = (re * cos - im * sin, re * sin + im * cos);
+                let (even_re, even_im) = values[start + k];
+                values[start + k] = (even_re + twiddled.0, even_im + twiddled.1);
+                values[start + k + len / 2] = (even_re - twiddled.0, even_im - twiddled.1);
+            }
+        }
+        len *= 2;
+    }
+}
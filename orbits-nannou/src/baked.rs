@@ -0,0 +1,51 @@
+//! Sculpting a composition in layers: the current generation's trails baked, as they're drawn, into
+//! still ribbons kept under the live ones, and the live trails cleared to go on from there. Bake
+//! after bake builds up, over reseeds too, until the layers are cleared, and exports and stills
+//! show them like the rest of the scene.
+
+use nannou::prelude::*;
+use orbits_core::trails::Mesh;
+
+use crate::mask::Mask;
+
+/// The ribbons baked so far.
+#[derive(Clone, Default)]
+pub struct Baked {
+    /// Every layer's ribbons in the one mesh, drawn in one go, the oldest first and so furthest
+    /// under.
+    mesh: Mesh,
+    /// Layers baked since they were last cleared.
+    pub layers: usize,
+}
+
+impl Baked {
+    /// Add `ribbons`, laid out and layered as they'd be drawn, as a layer on top of the rest.
+    pub fn add(&mut self, ribbons: &Mesh) {
+        let offset = self.mesh.vertices.len();
+        // flat at the back, so whatever's live is drawn over every layer
+        let vertices = ribbons.vertices.iter();
+        (self.mesh.vertices).extend(vertices.map(|&(pos, color)| (pt3(pos.x, pos.y, 0.0), color)));
+        let indices = ribbons.indices.iter();
+        (self.mesh.indices).extend(indices.map(|index| index + offset));
+        self.layers += 1;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Baked::default();
+    }
+
+    /// Draw every layer at `opacity`, faded by `mask` if there is one.
+    pub fn draw(&self, opacity: f32, mask: Option<&Mask>, draw: &Draw) {
+        if self.mesh.indices.is_empty() {
+            return;
+        }
+        let vertices = self.mesh.vertices.iter();
+        draw.mesh().indexed_colored(
+            vertices.map(|&(pos, mut color)| {
+                color.alpha *= opacity * mask.map_or(1.0, |mask| mask.coverage(pos.truncate()));
+                (pos, color)
+            }),
+            self.mesh.indices.iter().copied(),
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Renders a still of each of a list of seeds straight to a texture, without a window or a display,
+//! for generating variations in bulk on a server: each seed is simulated for so many steps and
+//! its scene drawn, as a still of the whole system, into a PNG of its own.
+
+use std::path::{Path, PathBuf};
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use orbits_core::params::Param;
+use orbits_core::simulation::Simulation;
+use orbits_core::{orbital_radius, plugins, settings, trails};
+
+use crate::compare::FRAME_SECONDS;
+use crate::compute::Look;
+use crate::config::Config;
+use crate::export::{self, Offscreen, Shot};
+use crate::layers::Layer;
+use crate::manifest::{self, Manifest};
+use crate::mask::Masks;
+use crate::perspective::Perspective;
+use crate::render::{self, TrailStyle};
+use crate::style::Style;
+use crate::viewport::Viewport;
+
+/// Steps simulated before each still is taken, unless told otherwise, as many as the seconds a
+/// snapshot simulates.
+pub const DEFAULT_STEPS: usize = 600;
+/// Size of each still, in pixels, unless told otherwise.
+pub const DEFAULT_SIZE: [u32; 2] = [3840, 2160];
+
+/// The stills the `batch` subcommand renders.
+#[derive(Debug)]
+pub struct Batch {
+    /// The directory the PNGs go in, one named for each seed.
+    pub out: PathBuf,
+    pub seeds: Vec<u64>,
+    /// Steps of `FRAME_SECONDS` of wall-clock time simulated before each still.
+    pub steps: usize,
+    pub size: [u32; 2],
+    /// Config to load instead of the one in the working directory.
+    pub config: Option<PathBuf>,
+}
+
+impl Batch {
+    /// Render every still, saying as each is written.
+    pub fn run(&self) -> Result<(), String> {
+        let config = match &self.config {
+            Some(path) => Config::load_file(path)?,
+            None => Config::load()?,
+        };
+        settings::set(config.simulation.clone())?;
+        plugins::load(Path::new(plugins::PLUGINS_DIR))?;
+        export::create_dir(&self.out)?;
+
+        let instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
+        let adapters = wgpu::AdapterMap::default();
+        // a server without a GPU may still have a software one
+        let adapter = [false, true]
+            .into_iter()
+            .find_map(|force_fallback_adapter| {
+                let options = wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                };
+                adapters.get_or_request(options, &instance)
+            })
+            .ok_or("there's no GPU adapter to render with")?;
+        let device_queue = adapter.get_or_request_device(wgpu::default_device_descriptor());
+
+        config.layers.check()?;
+        let masks = Masks::load(&config.layers)?;
+        let style = Style::new(&config)?;
+        let smear = style.smear.clone();
+        let offscreen = Offscreen::new(config.transition, style);
+        let [width, height] = self.size;
+        // the whole system in view, as the window shows it before the camera moves
+        let scale = Viewport::whole_system(Rect::from_w_h(width as f32, height as f32)).scale;
+        let mut written = Vec::new();
+        for &seed in &self.seeds {
+            let mut simulation = Simulation::new(seed);
+            for _ in 0..self.steps {
+                simulation.step(FRAME_SECONDS);
+            }
+            if let Some(smear) = &smear {
+                smear.splat(&simulation.generations, |draw| draw.scale(scale), scale);
+            }
+            let draw = Draw::new();
+            draw_scene(&config, &masks, &simulation, scale, &draw);
+            let path = self.out.join(format!("{seed}.png"));
+            let still = offscreen
+                .capture(&device_queue, self.size, Shot::Still(draw))
+                .ok_or_else(|| format!("failed to render seed {seed}"))?;
+            export::write_png(&path, self.size, &still.pixels())
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            println!("{}", path.display());
+            written.push(path);
+        }
+        let manifest = Manifest::new(vec![config.written()])
+            .seeds(self.seeds.clone())
+            .time(self.steps as f32 * FRAME_SECONDS);
+        manifest.write(&manifest::within(&self.out), &written)
+    }
+}
+
+/// Draw the current generation of `simulation` over its background, as `config` styles it and
+/// `masks` mask it, at `scale` pixels to a world unit.
+fn draw_scene(config: &Config, masks: &Masks, simulation: &Simulation, scale: f32, draw: &Draw) {
+    let generation = simulation.current();
+    draw.background().color(generation.background);
+    let draw = &draw.scale(scale);
+    let view = settings::get()
+        .three_d
+        .then(|| Perspective::at(simulation.time));
+    match view {
+        Some(view) => {
+            draw.polygon()
+                .points(view.disc(orbital_radius()))
+                .color(generation.circle_color);
+        }
+        None => {
+            draw.ellipse()
+                .radius(orbital_radius())
+                .color(generation.circle_color);
+        }
+    }
+    let params = &simulation.params;
+    let look = Look {
+        opacity: params.get(Param::TrailOpacity),
+        highlight: params.get(Param::Highlight),
+        extrapolate: 0.0,
+        build_in: 1.0,
+        length: params.get(Param::TrailLength),
+        view,
+    };
+    let style = config.render.style;
+    let mut mesh = render::build(style, &config.render, generation, look);
+    if style == TrailStyle::Ribbons {
+        config.thinning.thin(&mut mesh, scale);
+        if config.render.feather > 0.0 {
+            trails::feather(&mut mesh, config.render.feather / scale);
+        }
+        config.layering.layer(&mut mesh);
+    }
+    let mask = masks.get(Layer::Trails);
+    if style == TrailStyle::Ribbons {
+        let place = |particle, pos| look.place(generation, particle, pos);
+        for mut archived in [
+            trails::archived_ribbons(generation, look.opacity, look.highlight, look.length, place),
+            trails::remnant_ribbons(generation, look.opacity, look.highlight, look.length, place),
+        ] {
+            if let Some(mask) = mask {
+                mask.apply(&mut archived);
+            }
+            draw.mesh()
+                .indexed_colored(archived.vertices, archived.indices);
+        }
+    }
+    if let Some(mask) = mask {
+        mask.apply(&mut mesh);
+    }
+    draw.mesh().indexed_colored(mesh.vertices, mesh.indices);
+    for line in trails::highlights(
+        generation,
+        look.opacity,
+        look.highlight,
+        look.extrapolate,
+        look.length,
+    ) {
+        let mut points = line.points;
+        if let Some(view) = view {
+            view.flatten_trail(generation, line.particle, &mut points);
+        }
+        for (pos, color) in &mut points {
+            color.alpha *= mask.map_or(1.0, |mask| mask.coverage(*pos));
+        }
+        draw.polyline().weight(line.width).points_colored(points);
+    }
+    let layer = Layer::Heads;
+    let (config, mask) = (config.layers.get(layer), masks.get(layer));
+    let mut bodies = simulation.bodies();
+    if let Some(view) = view {
+        view.flatten_bodies(&mut bodies);
+    }
+    render::draw_bodies(&bodies, generation.body_color, config, mask, draw);
+}
@@ -0,0 +1,78 @@
+//! Painting with the mouse: while the brush is on, dragging pushes the particles under it along
+//! the way it's dragged, the most at its middle and less toward its edge, as many times a second
+//! as there are frames. Each push is a change like any other, so it's logged, replayed and synced.
+
+use nannou::prelude::*;
+use orbits_core::session::Change;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::viewport::Viewport;
+
+/// The `[brush]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrushConfig {
+    /// Radius of the brush in points across the window, so it's as big at any zoom.
+    pub radius: f32,
+    /// Fraction of the brush's velocity a particle at its middle is given each second it's
+    /// under it.
+    pub strength: f32,
+}
+
+impl Default for BrushConfig {
+    fn default() -> Self {
+        BrushConfig {
+            radius: 60.0,
+            strength: 3.0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Brush {
+    pub config: BrushConfig,
+    /// Whether dragging paints instead of spawning.
+    pub active: bool,
+    /// Where in the world the brush was at the last frame, while it's being dragged.
+    last: Option<Point2>,
+}
+
+impl Brush {
+    pub fn new(config: BrushConfig) -> Self {
+        Brush {
+            config,
+            ..Brush::default()
+        }
+    }
+
+    /// Whether the brush is being dragged.
+    pub fn is_painting(&self) -> bool {
+        self.last.is_some()
+    }
+
+    /// Start painting with the brush at `pos`, a point of the world.
+    pub fn press(&mut self, pos: Point2) {
+        self.last = Some(pos);
+    }
+
+    pub fn release(&mut self) {
+        self.last = None;
+    }
+
+    /// The push the brush gives as it's dragged to `pos`, a point of the world, over the
+    /// `wall_seconds` since the last frame, if it's being dragged and has moved, as `viewport`
+    /// shows the world.
+    pub fn stroke(&mut self, pos: Point2, viewport: Viewport, wall_seconds: f32) -> Option<Change> {
+        let last = self.last.replace(pos)?;
+        if pos == last || wall_seconds <= 0.0 {
+            return None;
+        }
+        let vel = (pos - last) / wall_seconds;
+        Some(Change::Impulse {
+            pos: pos.to_array(),
+            radius: viewport.to_units(self.config.radius),
+            vel: (vel * (self.config.strength * wall_seconds).min(1.0)).to_array(),
+        })
+    }
+}
@@ -0,0 +1,97 @@
+//! Moving the view about the world by hand: zooming with the mouse wheel, panning by dragging with
+//! the right button, and following a particle about its orbit. The camera is relative to the view
+//! the piece would otherwise show, the whole system or the `--viewport`, which a reset goes back to.
+
+use nannou::prelude::*;
+use nannou::winit::event::MouseScrollDelta;
+
+use crate::viewport::Viewport;
+
+/// Factor a line of mouse wheel scrolling zooms by.
+const ZOOM_STEP: f32 = 1.1;
+/// Points of scrolling on a touchpad taken as a line of a mouse wheel.
+const POINTS_PER_LINE: f32 = 40.0;
+/// Furthest out and in the camera zooms, relative to the view it starts at.
+const ZOOM_RANGE: (f32, f32) = (0.25, 64.0);
+
+#[derive(Debug, Clone, Default)]
+pub struct Camera {
+    /// Zoom on top of the view the piece would otherwise show, or none for 1.
+    zoom: Option<f32>,
+    /// World units the middle of the view is moved from where it would otherwise be.
+    pan: Vec2,
+    /// The particle of the current generation being followed, by index.
+    pub following: Option<usize>,
+    /// Where the mouse grabbed the view to pan it, in window points, and the pan then.
+    grabbed: Option<(Point2, Vec2)>,
+}
+
+impl Camera {
+    /// The view the camera shows, given `fit`, the view the piece would otherwise show, and where
+    /// the particle being followed is, if it's still there.
+    pub fn view(&self, fit: Viewport, followed: Option<Point2>) -> Viewport {
+        Viewport::new(
+            fit.scale * self.zoom.unwrap_or(1.0),
+            followed.unwrap_or(fit.middle) + self.pan,
+        )
+    }
+
+    /// Whether the view is any different from the one the piece would otherwise show.
+    pub fn is_moved(&self) -> bool {
+        self.zoom.is_some() || self.pan != Vec2::ZERO || self.following.is_some()
+    }
+
+    /// Zoom by `delta` of scrolling, keeping the point of the world under `cursor`, a point in the
+    /// window, where it is. `scale` is the camera's points across to a world unit before zooming.
+    pub fn scroll(&mut self, delta: MouseScrollDelta, cursor: Point2, scale: f32) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, lines) => lines,
+            MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / POINTS_PER_LINE,
+        };
+        let zoom = self.zoom.unwrap_or(1.0);
+        let (least, most) = ZOOM_RANGE;
+        let zoomed = (zoom * ZOOM_STEP.powf(lines)).clamp(least, most);
+        let factor = zoomed / zoom;
+        self.pan += cursor / scale * (1.0 - 1.0 / factor);
+        self.zoom = Some(zoomed);
+    }
+
+    /// Start panning the view with the mouse at `cursor`, a point in the window.
+    pub fn grab(&mut self, cursor: Point2) {
+        self.grabbed = Some((cursor, self.pan));
+    }
+
+    /// Move the view along with the mouse, now at `cursor`, if it's been grabbed. `scale` is the
+    /// camera's points across to a world unit. Returns whether it had been.
+    pub fn drag(&mut self, cursor: Point2, scale: f32) -> bool {
+        let Some((start, pan)) = self.grabbed else {
+            return false;
+        };
+        self.pan = pan - (cursor - start) / scale;
+        true
+    }
+
+    pub fn release(&mut self) {
+        self.grabbed = None;
+    }
+
+    /// Follow `particle` of the current generation from now on, centered on it.
+    pub fn follow(&mut self, particle: usize) {
+        self.following = Some(particle);
+        self.pan = Vec2::ZERO;
+    }
+
+    /// Stop following a particle, leaving the view where it is, given where the particle is and
+    /// the middle of the view the piece would otherwise show.
+    pub fn unfollow(&mut self, followed: Option<Point2>, middle: Point2) {
+        if let Some(pos) = followed.filter(|_| self.following.is_some()) {
+            self.pan += pos - middle;
+        }
+        self.following = None;
+    }
+
+    /// Go back to the view the piece would otherwise show.
+    pub fn reset(&mut self) {
+        *self = Camera::default();
+    }
+}
@@ -0,0 +1,152 @@
+//! Views of the same simulation from other sides than the orbits themselves, drawn from the same
+//! trails with each record placed somewhere else. The radius chart plots each particle's distance
+//! from the middle of the system against time, a line scrolling off to the left in its trail's
+//! colors, now at the right and as far back as its trail goes at the left, over the part of the
+//! world the whole system takes up. Phase space plots each one's distance from the middle against
+//! how fast it's moving away from it, its trail tracing the loop its orbit makes there, in a panel
+//! beside the orbits, both of them fitted into the window together. Either way the camera zooms
+//! and pans them as it would the orbits. Switched between with a hotkey, or by a preset.
+
+use nannou::prelude::*;
+use orbits_core::generation::{Generation, Record};
+use orbits_core::orbital_radius;
+use orbits_core::settings;
+use orbits_core::trails::{self, Mesh};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::compute::Look;
+use crate::render::{self, RenderConfig};
+
+/// The radius at the top of the chart and the right of phase space, as a multiple of the average
+/// orbit's, those further out running along it.
+const TOP: f32 = 2.0;
+/// How far right of the middle of the system the middle of phase space is, clear of the orbits
+/// with room around each, in orbital radii.
+const PHASE_MIDDLE: f32 = 2.2;
+
+/// What the piece is shown as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum View {
+    /// The orbits, in the trails' style.
+    #[default]
+    Orbits,
+    /// Each particle's distance from the middle against time.
+    RadiusChart,
+    /// The orbits, with each particle's distance from the middle against how fast it's moving
+    /// away from it beside them.
+    PhaseSpace,
+}
+
+impl View {
+    /// The view after this one, going round.
+    pub fn next(self) -> Self {
+        match self {
+            View::Orbits => View::RadiusChart,
+            View::RadiusChart => View::PhaseSpace,
+            View::PhaseSpace => View::Orbits,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            View::Orbits => "orbits",
+            View::RadiusChart => "radius chart",
+            View::PhaseSpace => "phase space",
+        }
+    }
+
+    /// The part of the world the view takes up, to fit into the window, if it's other than the
+    /// whole system.
+    pub fn region(self) -> Option<Rect> {
+        // the whole system's square with the same beside it
+        let side = PHASE_MIDDLE * orbital_radius();
+        (self == View::PhaseSpace).then(|| Rect::from_x_y_w_h(side / 2.0, 0.0, 2.0 * side, side))
+    }
+}
+
+/// Where the point `back` records before now of a trail, at `pos`, is drawn on the chart.
+pub fn plot(back: usize, pos: Point2) -> Point2 {
+    let along = back as f32 / settings::get().history as f32;
+    let height = (pos.length() / (TOP * orbital_radius())).min(1.0);
+    vec2(1.0 - 2.0 * along, 2.0 * height - 1.0) * orbital_radius()
+}
+
+/// Where `record` is drawn in phase space, `circular` the speed of a circular orbit at the average
+/// orbit's radius, which those falling in or flying out at reach the bottom or top of it.
+pub fn phase(record: &Record, circular: f32) -> Point2 {
+    let radial = record.vel.dot(record.pos.normalize_or_zero());
+    phase_at(record.pos.length(), radial / circular)
+}
+
+/// Where a particle `r` from the middle, moving away from it at `radial` times the speed of a
+/// circular orbit at the average orbit's radius, is drawn in phase space.
+fn phase_at(r: f32, radial: f32) -> Point2 {
+    let across = (r / (TOP * orbital_radius())).min(1.0);
+    let up = radial.clamp(-1.0, 1.0);
+    pt2(
+        (PHASE_MIDDLE + 2.0 * across - 1.0) * orbital_radius(),
+        up * orbital_radius(),
+    )
+}
+
+/// The lines the trails of `generation` make placed by `project` as `trails::projected` places
+/// them, as `look` has them, drawn `config.line_width` wide.
+pub fn build(
+    config: &RenderConfig,
+    generation: &Generation,
+    look: Look,
+    project: impl Fn(usize, &Record) -> Point2,
+) -> Mesh {
+    let length = look.build_in * look.length;
+    let mut mesh = Mesh::default();
+    for particle in 0..generation.particles.len() {
+        let opacity = look.opacity * generation.saliency.alpha(particle, look.highlight);
+        let mut points = trails::projected(generation, particle, opacity, length, &project);
+        for (_, color) in &mut points {
+            color.saturation *= look.build_in;
+        }
+        let thickness = trails::thickness(generation, particle, length);
+        render::line(&mut mesh, &points, &thickness, config.line_width);
+    }
+    mesh
+}
+
+/// Draw the chart's guides in `color`: its axis at the middle of the system, and a line across at
+/// the average orbit's radius.
+pub fn draw_guides(color: LinSrgba, draw: &Draw) {
+    let middle = plot(0, Point2::ZERO).y;
+    let orbit = plot(0, vec2(orbital_radius(), 0.0)).y;
+    let (left, right) = (-orbital_radius(), orbital_radius());
+    draw.line()
+        .start(pt2(left, middle))
+        .end(pt2(right, middle))
+        .weight(2.0)
+        .color(color);
+    let mut faint = color;
+    faint.alpha /= 2.0;
+    draw.line()
+        .start(pt2(left, orbit))
+        .end(pt2(right, orbit))
+        .weight(2.0)
+        .color(faint);
+}
+
+/// Draw phase space's guides in `color`: its axis of standing still, neither falling in nor
+/// flying out, and a line up it at the average orbit's radius.
+pub fn draw_phase_guides(color: LinSrgba, draw: &Draw) {
+    draw.line()
+        .start(phase_at(0.0, 0.0))
+        .end(phase_at(TOP * orbital_radius(), 0.0))
+        .weight(2.0)
+        .color(color);
+    let orbit = phase_at(orbital_radius(), 0.0).x;
+    let mut faint = color;
+    faint.alpha /= 2.0;
+    draw.line()
+        .start(pt2(orbit, -orbital_radius()))
+        .end(pt2(orbit, orbital_radius()))
+        .weight(2.0)
+        .color(faint);
+}
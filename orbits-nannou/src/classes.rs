@@ -0,0 +1,242 @@
+//! Sorts each particle's orbit into a class every so often, from where it is and how fast it's
+//! going, for a legend counting how many fall into each and for ringing the particles in their
+//! class's color, and for leaving the trails of some classes in styles of their own. Orbits are
+//! sized up as the saliency sizes them, as if the central masses were one inverse-square point at
+//! the origin.
+
+use std::collections::BTreeMap;
+
+use nannou::prelude::*;
+use orbits_core::generation::{ClassStyle, Generation};
+use orbits_core::physics::{self, Body, Particle};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::layers::LayerConfig;
+use crate::overlay::Row;
+
+/// Radius of the rings around the particles, in points.
+const RING_RADIUS: f32 = 8.0;
+
+/// What kind of orbit a particle is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrbitClass {
+    /// Bound and near enough round.
+    Circular,
+    /// Bound, and stretched out.
+    Eccentric,
+    /// Going fast enough to leave the system for good.
+    Escaping,
+    /// Bound, but diving in close to the middle of the system on the way round.
+    Plunging,
+    /// Bound, going the other way round from most of the swarm.
+    Retrograde,
+}
+
+impl OrbitClass {
+    pub const ALL: [OrbitClass; 5] = [
+        OrbitClass::Circular,
+        OrbitClass::Eccentric,
+        OrbitClass::Escaping,
+        OrbitClass::Plunging,
+        OrbitClass::Retrograde,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OrbitClass::Circular => "circular",
+            OrbitClass::Eccentric => "eccentric",
+            OrbitClass::Escaping => "escaping",
+            OrbitClass::Plunging => "plunging",
+            OrbitClass::Retrograde => "retrograde",
+        }
+    }
+
+    /// The color the class is ringed and keyed in.
+    pub fn color(self) -> Hsla {
+        let hue = match self {
+            OrbitClass::Circular => 140.0,
+            OrbitClass::Eccentric => 50.0,
+            OrbitClass::Escaping => 0.0,
+            OrbitClass::Plunging => 300.0,
+            OrbitClass::Retrograde => 210.0,
+        };
+        hsla(hue / 360.0, 0.8, 0.6, 1.0)
+    }
+}
+
+/// The `[classes]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassesConfig {
+    /// Simulated seconds between one sorting of the orbits and the next.
+    pub interval: f32,
+    /// Eccentricity below which a bound orbit counts as circular.
+    pub circular: f32,
+    /// Distance from the middle of the system, in world units, that a bound orbit passing any
+    /// closer counts as plunging.
+    pub plunge_radius: f32,
+    /// Whether the legend is shown from the start.
+    pub legend: bool,
+    /// Whether the particles are ringed in their class's color from the start.
+    pub color: bool,
+    /// The style the trails of each class named are left in, while their orbits are of it.
+    pub styles: BTreeMap<OrbitClass, ClassStyle>,
+}
+
+impl Default for ClassesConfig {
+    fn default() -> Self {
+        ClassesConfig {
+            interval: 0.5,
+            circular: 0.1,
+            plunge_radius: 150.0,
+            legend: false,
+            color: false,
+            styles: BTreeMap::new(),
+        }
+    }
+}
+
+impl ClassesConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.interval <= 0.0 {
+            return Err(format!(
+                "the classes' interval has to be positive, not {}",
+                self.interval
+            ));
+        }
+        if self.circular < 0.0 {
+            return Err(format!(
+                "the classes' circular can't be negative, not {}",
+                self.circular
+            ));
+        }
+        if self.plunge_radius < 0.0 {
+            return Err(format!(
+                "the classes' plunge_radius can't be negative, not {}",
+                self.plunge_radius
+            ));
+        }
+        for (class, style) in &self.styles {
+            if style.saturation < 0.0 {
+                return Err(format!(
+                    "the {} style's saturation can't be negative, not {}",
+                    class.label(),
+                    style.saturation
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The class of each of the current generation's particles, as last sorted.
+pub struct Classes {
+    config: ClassesConfig,
+    classes: Vec<OrbitClass>,
+    /// Simulated seconds since the orbits were last sorted.
+    elapsed: f32,
+    pub legend: bool,
+    pub color: bool,
+}
+
+impl Classes {
+    pub fn new(config: ClassesConfig) -> Self {
+        Classes {
+            classes: Vec::new(),
+            elapsed: 0.0,
+            legend: config.legend,
+            color: config.color,
+            config,
+        }
+    }
+
+    /// Sort the orbits of `particles` around `bodies` again if it's been long enough, or if
+    /// there are more or fewer of them than there were, `delta_seconds` having gone by since the
+    /// last update, returning whether they were. Only done while they're shown or styled, and
+    /// forgotten while they're not.
+    pub fn update(&mut self, particles: &[Particle], bodies: &[Body], delta_seconds: f32) -> bool {
+        if !self.legend && !self.color && self.config.styles.is_empty() {
+            self.classes.clear();
+            return false;
+        }
+        self.elapsed += delta_seconds;
+        if self.elapsed < self.config.interval && self.classes.len() == particles.len() {
+            return false;
+        }
+        self.elapsed = 0.0;
+        let gm: f32 = bodies.iter().map(|body| body.gm).sum();
+        // the way round most of the swarm goes, counterclockwise if it's even
+        let spin: f32 = particles.iter().map(|p| p.pos.perp_dot(p.vel)).sum();
+        let way = if spin < 0.0 { -1.0 } else { 1.0 };
+        self.classes = (particles.iter())
+            .map(|particle| classify(particle, gm, way, &self.config))
+            .collect();
+        true
+    }
+
+    /// The style each particle's trail is left in, as its orbit was last sorted.
+    pub fn styles(&self) -> Vec<Option<ClassStyle>> {
+        (self.classes.iter())
+            .map(|class| self.config.styles.get(class).copied())
+            .collect()
+    }
+
+    /// How many particles there are of each class, in the order of `OrbitClass::ALL`.
+    pub fn counts(&self) -> [usize; 5] {
+        OrbitClass::ALL.map(|class| self.classes.iter().filter(|&&c| c == class).count())
+    }
+
+    /// Rows of the legend, keying each class and counting the particles in it.
+    pub fn legend_rows(&self) -> Vec<Row> {
+        let mut rows = vec![Row::Heading("Orbits".into())];
+        rows.extend(
+            (OrbitClass::ALL.into_iter().zip(self.counts())).map(|(class, count)| {
+                Row::Swatch(class.color(), class.label().into(), count.to_string())
+            }),
+        );
+        rows
+    }
+
+    /// Ring each particle of `generation` in its class's color, faded as `layer` is.
+    pub fn draw_rings(
+        &self,
+        generation: &Generation,
+        to_screen: impl Fn(Point2) -> Point2,
+        layer: &LayerConfig,
+        draw: &Draw,
+    ) {
+        for (particle, class) in generation.particles.iter().zip(&self.classes) {
+            draw.ellipse()
+                .xy(to_screen(particle.pos))
+                .radius(RING_RADIUS)
+                .no_fill()
+                .stroke_weight(1.5)
+                .stroke(layer.fade(class.color()));
+        }
+    }
+}
+
+/// The class of the orbit of `particle` around a mass of strength `gm` at the origin, most of the
+/// swarm going the `way` round that's 1 for counterclockwise and -1 for clockwise.
+fn classify(particle: &Particle, gm: f32, way: f32, config: &ClassesConfig) -> OrbitClass {
+    let (r, v) = (particle.pos, particle.vel);
+    let energy = v.length_squared() / 2.0 - gm / r.length();
+    if energy >= 0.0 {
+        return OrbitClass::Escaping;
+    }
+    let eccentricity = physics::eccentricity(r, v, gm);
+    let semi_major = -gm / (2.0 * energy);
+    if semi_major * (1.0 - eccentricity) < config.plunge_radius {
+        return OrbitClass::Plunging;
+    }
+    if r.perp_dot(v) * way < 0.0 {
+        return OrbitClass::Retrograde;
+    }
+    if eccentricity < config.circular {
+        OrbitClass::Circular
+    } else {
+        OrbitClass::Eccentric
+    }
+}
@@ -0,0 +1,691 @@
+use std::net::{SocketAddr, SocketAddrV4};
+use std::path::{Path, PathBuf};
+
+use nannou::geom::Rect;
+use nannou::rand::random;
+
+use crate::adapter::Preference;
+use crate::analyze;
+use crate::assets;
+use crate::batch::{self, Batch};
+use crate::compare::{self, Snapshot};
+use crate::config;
+use crate::entropy;
+use crate::export::Crop;
+use crate::gallery;
+use crate::portraits::Portraits;
+use crate::ratings::{self, MAX_STARS, RATINGS_PATH};
+use crate::series::Series;
+use crate::sync::Role;
+use crate::tier;
+use crate::token::Token;
+
+const USAGE: &str = "usage: trynannou [run] [options]         show the piece in a window
+       trynannou export <dir> [options]    show it, writing every frame to an image sequence
+       trynannou snapshot <out.png> [still options] [--config <file>]
+                                           render a still headlessly
+       trynannou portraits <dir> [still options] [--config <file>] [--sheet]
+                                           render each particle's trail alone, framed to fit,
+                                           to dir, or into a contact sheet
+       trynannou batch <dir> --seeds <list> [--steps <n>] [--size <w>x<h>] [--config <file>]
+                                           render a PNG of each seed, like 1-100 or 3,17,42,
+                                           to dir without a window or a display
+       trynannou compare <a.toml> <b.toml> <out.png> [still options] [--difference]
+                                           render a still under each config, side by side
+       trynannou gallery <out.png> [still options] [--presets <dir>] [--columns <n>]
+                         [--rated] [--min <stars>]
+                                           render every preset, or every rated seed, into a
+                                           contact sheet
+       trynannou series <manifest.toml> <dir>
+                                           render each edition of a series, numbered, to dir
+       trynannou ratings [--min <stars>]   list the rated seeds, the most stars first
+       trynannou analyze explain [--seed <n>] [--seconds <s>] [--config <file>]
+                                           simulate a seed briefly without a window and sum up
+                                           its orbits, trail coverage and each integrator's
+                                           energy drift
+       trynannou analyze diverge <a.toml> <b.toml> [--seed <n>] [--seconds <s>]
+                         [--tolerance <d>] [--build <exe>]
+                                           step a seed under both configs in lockstep, the
+                                           second traced by another build if given, and report
+                                           the first step their particles part ways at
+       trynannou benchmark                 time which performance tier suits this machine, and
+                                           remember it
+       trynannou check <file>              validate a config file
+       trynannou schema                    print a JSON Schema for the config file
+
+options:
+  --seed <n>               seed the scene with n rather than at random, or 1 for gallery, and
+                           every reseed after it from the same
+  --seed text:<words>      seed it from the hash of these words, file:<path> of this file's bytes,
+                           or weather:<lat>,<lon> of the current weather there
+  --token <hash>           make every choice made at random from this 64 hex digit hash, as
+                           platforms minting a piece from a token's hash do
+  --log-session <file>     record every change made while running
+  --log-diagnostics <file> write a CSV row of the diagnostics, temperature and entropy too,
+                           every second
+  --replay <file>          play back a recorded session
+  --automation <file>      load and save automation curves here
+  --cues <file>            step through a JSON cue list
+  --sync leader|follower   keep several instances in lockstep
+  --sync-group <ip:port>   multicast group to sync over
+  --genlock                time synced frames against the leader's clock
+  --viewport <x,y,w,h>     show only this region of the world, centered on x,y
+  --web <ip:port>          serve a control page, e.g. on 0.0.0.0:8080
+  --osc <ip:port>          take OSC messages, e.g. on 0.0.0.0:9000
+  --metrics <ip:port>      serve Prometheus metrics at /metrics
+  --record <dir>           export PNGs to dir at a fixed timestep, for a video
+  --long-exposure <file>   accumulate a long exposure, resumed from and saved to an EXR file
+  --highlights <file>      list the most interesting moments in this file
+  --engagement <dir>       count how visitors play with the piece into a report a day here
+  --tray                   add a tray icon, and close the window to it rather than quitting
+  --leds <ip:port>         stream a low resolution copy to an LED matrix
+  --art-net <ip:port>      drive stage lighting from the frame's colors, e.g. on port 6454
+  --ambience               tint the Hue and LIFX bulbs in [ambience] to match the piece
+  --audio                  move the parameters in [audio] along with the sound of the room
+  --midi                   play the piece from the MIDI controller bound in [midi]
+  --gpu                    step the particles and build the trails in compute shaders
+  --gpu-index <n>          draw with the nth of the GPUs listed on starting with it
+  --prefer-low-power       draw with the GPU drawing the least power, an integrated one if any
+  --prefer-discrete        draw with the best performing GPU, a discrete one if any
+  --load-state <file>      resume the run saved in this file, rather than seeding a new one
+  --save-state <file>      save the run to this file on quitting, and with the save hotkey
+  --setup                  ask how to show the piece and write the answers to trynannou.toml,
+                           as the first run without one does
+  --selftest               run 300 frames of seed 1 hidden and render one, failing if anything
+                           breaks, to check a build
+  --config <file>          use this config instead of trynannou.toml
+
+export options:
+  --crop [world:]<x,y,w,h> export only this region, in pixels or world units
+  --pixel-scale <s>        render exports, stills and screenshots at s pixels to a point, rather
+                           than at the display's scale factor
+  --frames <n>             with export or --record, quit once n frames are written
+
+still options:
+  --seconds <s>            simulate s seconds before taking the still, 10 by default
+  --difference             with compare, show how far apart the stills are instead
+  --presets <dir>          with gallery, render the presets here rather than in presets/
+  --columns <n>            with gallery, lay out n thumbnails a row, 4 by default
+  --rated                  with gallery, render the rated seeds under the configs they were rated
+                           under, the most stars first, rather than the presets
+  --min <stars>            with gallery --rated or ratings, leave out seeds rated fewer stars
+  --pixel-scale <s>        render at s pixels to a point, rather than at the display's scale
+                           factor";
+
+/// Command line arguments.
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Seed the first generation with this, and pick the seeds of the rest from it.
+    pub seed: Option<u64>,
+    /// Where the seed was drawn from, if it wasn't given as a number.
+    pub seed_source: Option<String>,
+    /// Make the seed, the palette and the particle count from this token's hash.
+    pub token: Option<Token>,
+    /// Write every change made while running to this file.
+    pub log_session: Option<PathBuf>,
+    /// Write a row of the diagnostics every second to this CSV file.
+    pub log_diagnostics: Option<PathBuf>,
+    /// Play back the changes recorded in this session log.
+    pub replay: Option<PathBuf>,
+    /// Load and save automation curves here instead of `automation.json`.
+    pub automation: Option<PathBuf>,
+    /// Cue list to step through with the next cue hotkey.
+    pub cues: Option<PathBuf>,
+    pub sync: Option<Role>,
+    pub sync_group: Option<SocketAddrV4>,
+    pub genlock: bool,
+    pub viewport: Option<Rect>,
+    /// Where to serve the web control page.
+    pub web: Option<SocketAddr>,
+    /// Where to listen for OSC messages.
+    pub osc: Option<SocketAddr>,
+    /// Where to serve Prometheus metrics.
+    pub metrics: Option<SocketAddr>,
+    /// Accumulate a long exposure, kept in this OpenEXR file.
+    pub long_exposure: Option<PathBuf>,
+    /// Write every frame to an image sequence in this directory.
+    pub export: Option<PathBuf>,
+    /// Export only this part of the scene.
+    pub crop: Option<Crop>,
+    /// Pixels to a point exports, stills and screenshots are rendered at, instead of the
+    /// window's scale factor.
+    pub pixel_scale: Option<f32>,
+    /// Export PNGs, simulating each frame as the same length of time however long it took.
+    pub record: bool,
+    /// Quit once this many frames are exported.
+    pub frames: Option<u64>,
+    /// List the most interesting moments of the run in this file.
+    pub highlights: Option<PathBuf>,
+    /// Count how the piece is played with into daily reports in this directory.
+    pub engagement: Option<PathBuf>,
+    /// Add a tray icon, and close the window to it.
+    pub tray: bool,
+    /// Stream a copy of the piece to the LED matrix here.
+    pub leds: Option<SocketAddr>,
+    /// Send lighting channels to the Art-Net node here.
+    pub art_net: Option<SocketAddr>,
+    /// Push the piece's colors to the bulbs in the config.
+    pub ambience: bool,
+    /// Listen to the default audio input, moving parameters with it.
+    pub audio: bool,
+    /// Read the MIDI controller, moving parameters and acting on notes as the config binds them.
+    pub midi: bool,
+    /// Step the particles and build the current generation's trails on the GPU, where it can.
+    pub gpu: bool,
+    /// The GPU to draw with, if one was asked for.
+    pub gpu_preference: Option<Preference>,
+    /// Resume the run saved in this file.
+    pub load_state: Option<PathBuf>,
+    /// Save the run to this file on quitting.
+    pub save_state: Option<PathBuf>,
+    /// Ask how to show the piece, as a first run does, even with a config.
+    pub setup: bool,
+    /// Read the config from this file instead of `trynannou.toml`.
+    pub config: Option<PathBuf>,
+    /// Render a still headlessly, then quit.
+    pub snapshot: Option<Snapshot>,
+    /// Run briefly in a hidden window, checking nothing breaks, then quit.
+    pub selftest: bool,
+}
+
+impl Args {
+    /// Parse the arguments to `run` or `export`, or carry out any other subcommand and exit.
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1).peekable();
+        match iter.peek().map(String::as_str) {
+            Some("run") => {
+                iter.next();
+            }
+            Some("export") => {
+                iter.next();
+                args.export = Some(operand(&mut iter, "directory to export to")?.into());
+            }
+            Some("snapshot") => {
+                iter.next();
+                let out = operand(&mut iter, "file to write the still to")?;
+                args.snapshot = Some(Snapshot::new(out.into()));
+            }
+            Some("portraits") => {
+                iter.next();
+                let dir = operand(&mut iter, "directory to write the portraits to")?;
+                let mut snapshot = Snapshot::new(dir.into());
+                snapshot.portraits = Some(Portraits::default());
+                args.snapshot = Some(snapshot);
+            }
+            Some("batch") => {
+                iter.next();
+                render_batch(&mut iter)?;
+                std::process::exit(0);
+            }
+            Some("compare") => {
+                iter.next();
+                compare_configs(&mut iter)?;
+                std::process::exit(0);
+            }
+            Some("gallery") => {
+                iter.next();
+                render_gallery(&mut iter)?;
+                std::process::exit(0);
+            }
+            Some("series") => {
+                iter.next();
+                let manifest = PathBuf::from(operand(&mut iter, "series manifest to render")?);
+                let out = PathBuf::from(operand(&mut iter, "directory to render it to")?);
+                Series::load(&manifest)?.render(&out)?;
+                std::process::exit(0);
+            }
+            Some("ratings") => {
+                iter.next();
+                list_ratings(&mut iter)?;
+                std::process::exit(0);
+            }
+            Some("analyze") => {
+                iter.next();
+                analyze_seed(&mut iter)?;
+                std::process::exit(0);
+            }
+            Some("benchmark") => {
+                let tier = tier::remeasure()?;
+                println!("{}: remembered in {}", tier.name(), tier::TIER_PATH);
+                std::process::exit(0);
+            }
+            Some("check") => {
+                iter.next();
+                let path = PathBuf::from(operand(&mut iter, "config file to check")?);
+                config::check(&path)?;
+                println!("{}: ok", path.display());
+                std::process::exit(0);
+            }
+            Some("schema") => {
+                println!("{}", config::schema());
+                std::process::exit(0);
+            }
+            // running is the default
+            _ => {}
+        }
+
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+            };
+            match arg.as_str() {
+                "--seed" => {
+                    let drawn = entropy::draw(&value()?)?;
+                    args.seed = Some(drawn.seed);
+                    args.seed_source = drawn.source;
+                }
+                "--token" => args.token = Some(Token::parse(&value()?)?),
+                "--log-session" => args.log_session = Some(value()?.into()),
+                "--log-diagnostics" => args.log_diagnostics = Some(value()?.into()),
+                "--replay" => args.replay = Some(value()?.into()),
+                "--automation" => args.automation = Some(value()?.into()),
+                "--cues" => args.cues = Some(value()?.into()),
+                "--sync" => {
+                    args.sync = Some(match value()?.as_str() {
+                        "leader" => Role::Leader,
+                        "follower" => Role::Follower,
+                        other => return Err(format!("unknown sync role {other:?}\n{USAGE}")),
+                    })
+                }
+                "--sync-group" => {
+                    let group = value()?;
+                    let group = group
+                        .parse()
+                        .map_err(|err| format!("bad sync group {group:?}: {err}"))?;
+                    args.sync_group = Some(group);
+                }
+                "--genlock" => args.genlock = true,
+                "--viewport" => args.viewport = Some(parse_rect(&value()?)?),
+                "--web" => args.web = Some(parse_addr(&value()?)?),
+                "--osc" => args.osc = Some(parse_addr(&value()?)?),
+                "--metrics" => args.metrics = Some(parse_addr(&value()?)?),
+                "--long-exposure" => args.long_exposure = Some(value()?.into()),
+                "--crop" if args.export.is_none() => {
+                    return Err(format!("--crop only applies to export\n{USAGE}"))
+                }
+                "--crop" => {
+                    let crop = value()?;
+                    args.crop = Some(match crop.strip_prefix("world:") {
+                        Some(rect) => Crop::World(parse_rect(rect)?),
+                        None => Crop::Pixels(parse_rect(&crop)?),
+                    });
+                }
+                "--pixel-scale" => args.pixel_scale = Some(parse_pixel_scale(&value()?)?),
+                "--record" if args.export.is_some() => {
+                    return Err(format!("--record is an export of its own\n{USAGE}"))
+                }
+                "--record" => {
+                    args.export = Some(value()?.into());
+                    args.record = true;
+                }
+                "--frames" => {
+                    let frames = value()?;
+                    let frames = frames
+                        .parse()
+                        .map_err(|err| format!("bad frame count {frames:?}: {err}"))?;
+                    args.frames = Some(frames);
+                }
+                "--highlights" => args.highlights = Some(value()?.into()),
+                "--engagement" => args.engagement = Some(value()?.into()),
+                "--tray" => args.tray = true,
+                "--leds" => args.leds = Some(parse_addr(&value()?)?),
+                "--art-net" => args.art_net = Some(parse_addr(&value()?)?),
+                "--ambience" => args.ambience = true,
+                "--audio" => args.audio = true,
+                "--midi" => args.midi = true,
+                "--gpu" => args.gpu = true,
+                "--gpu-index" | "--prefer-low-power" | "--prefer-discrete"
+                    if args.gpu_preference.is_some() =>
+                {
+                    return Err(format!(
+                        "only one of --gpu-index, --prefer-low-power and --prefer-discrete \
+                         can be given\n{USAGE}"
+                    ))
+                }
+                "--gpu-index" => {
+                    let index = value()?;
+                    let index = index
+                        .parse()
+                        .map_err(|err| format!("bad gpu index {index:?}: {err}"))?;
+                    args.gpu_preference = Some(Preference::Index(index));
+                }
+                "--prefer-low-power" => args.gpu_preference = Some(Preference::LowPower),
+                "--prefer-discrete" => args.gpu_preference = Some(Preference::Discrete),
+                "--load-state" => args.load_state = Some(value()?.into()),
+                "--save-state" => args.save_state = Some(value()?.into()),
+                "--setup" => args.setup = true,
+                "--selftest" if args.snapshot.is_some() => {
+                    return Err(format!("--selftest doesn't apply to snapshot\n{USAGE}"))
+                }
+                "--selftest" => args.selftest = true,
+                "--sheet" => match args.snapshot.as_mut().and_then(|s| s.portraits.as_mut()) {
+                    Some(portraits) => portraits.sheet = Some(gallery::DEFAULT_COLUMNS),
+                    None => return Err(format!("--sheet only applies to portraits\n{USAGE}")),
+                },
+                "--seconds" if args.snapshot.is_none() => {
+                    return Err(format!("{arg} only applies to snapshot\n{USAGE}"))
+                }
+                "--config" => match &mut args.snapshot {
+                    Some(snapshot) => snapshot.config = Some(value()?.into()),
+                    None => args.config = Some(value()?.into()),
+                },
+                "--seconds" => args.snapshot.as_mut().unwrap().seconds = parse_seconds(&value()?)?,
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("unexpected argument {arg:?}\n{USAGE}")),
+            }
+        }
+        if args.frames.is_some() && args.export.is_none() {
+            return Err(format!(
+                "--frames only applies to export and --record\n{USAGE}"
+            ));
+        }
+        // a loaded state isn't a change a log, replay or follower could start from
+        if args.load_state.is_some()
+            && (args.replay.is_some() || args.log_session.is_some() || args.sync.is_some())
+        {
+            return Err(format!(
+                "--load-state can't be combined with --replay, --log-session or --sync\n{USAGE}"
+            ));
+        }
+        // a token is to be all there is to go on
+        if args.token.is_some() && (args.seed.is_some() || args.load_state.is_some()) {
+            return Err(format!(
+                "--token can't be combined with --seed or --load-state\n{USAGE}"
+            ));
+        }
+        Ok(args)
+    }
+}
+
+/// Carry out `compare`, given the arguments after it.
+fn compare_configs(iter: &mut impl Iterator<Item = String>) -> Result<(), String> {
+    let a = PathBuf::from(operand(iter, "config file to compare")?);
+    let b = PathBuf::from(operand(iter, "config file to compare with")?);
+    let out = PathBuf::from(operand(iter, "file to write the comparison to")?);
+    let mut seed = None;
+    let mut seconds = compare::DEFAULT_SECONDS;
+    let mut pixel_scale = None;
+    let mut difference = false;
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--seed" => seed = Some(draw_seed(&value()?)?),
+            "--seconds" => seconds = parse_seconds(&value()?)?,
+            "--pixel-scale" => pixel_scale = Some(parse_pixel_scale(&value()?)?),
+            "--difference" => difference = true,
+            _ => return Err(format!("unexpected argument {arg:?}\n{USAGE}")),
+        }
+    }
+    // both stills have to share a seed to be comparable
+    let seed = seed.unwrap_or_else(random);
+    let configs: [&Path; 2] = [&a, &b];
+    for config in configs {
+        config::check(config)?;
+    }
+    compare::compare(configs, &out, seed, seconds, pixel_scale, difference)?;
+    println!("{}: seed {seed}", out.display());
+    Ok(())
+}
+
+/// Carry out `analyze`, given the arguments after it.
+fn analyze_seed(iter: &mut impl Iterator<Item = String>) -> Result<(), String> {
+    let kind = operand(iter, "kind of analysis, explain or diverge")?;
+    let mut sides = Vec::new();
+    match kind.as_str() {
+        "explain" | "trace" => {}
+        "diverge" => {
+            sides.push(PathBuf::from(operand(iter, "first config to step")?));
+            sides.push(PathBuf::from(operand(iter, "second config to step")?));
+        }
+        other => {
+            return Err(format!(
+                "there's no analysis {other:?}, only explain and diverge\n{USAGE}"
+            ))
+        }
+    }
+    let mut seed = None;
+    let mut seconds = compare::DEFAULT_SECONDS;
+    let mut config = None;
+    let mut tolerance = 0.0;
+    let mut build = None;
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--seed" => seed = Some(draw_seed(&value()?)?),
+            "--seconds" => seconds = parse_seconds(&value()?)?,
+            "--config" if sides.is_empty() => config = Some(PathBuf::from(value()?)),
+            "--tolerance" if !sides.is_empty() => {
+                let text = value()?;
+                tolerance = text
+                    .parse()
+                    .ok()
+                    .filter(|tolerance: &f32| *tolerance >= 0.0)
+                    .ok_or_else(|| format!("bad --tolerance {text:?}\n{USAGE}"))?;
+            }
+            "--build" if !sides.is_empty() => build = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("unexpected argument {arg:?}\n{USAGE}")),
+        }
+    }
+    for config in config.iter().chain(&sides) {
+        config::check(config)?;
+    }
+    let seed = seed.unwrap_or_else(random);
+    match kind.as_str() {
+        "trace" => analyze::trace(seed, seconds, config.as_deref()),
+        "diverge" => {
+            let [a, b] = [sides.remove(0), sides.remove(0)];
+            let sides = [
+                analyze::Side {
+                    build: None,
+                    config: a,
+                },
+                analyze::Side { build, config: b },
+            ];
+            analyze::diverge(sides, seed, seconds, tolerance)
+        }
+        _ => analyze::explain(seed, seconds, config.as_deref()),
+    }
+}
+
+/// Carry out `batch`, given the arguments after it.
+fn render_batch(iter: &mut impl Iterator<Item = String>) -> Result<(), String> {
+    let mut batch = Batch {
+        out: PathBuf::from(operand(iter, "directory to write the stills to")?),
+        seeds: Vec::new(),
+        steps: batch::DEFAULT_STEPS,
+        size: batch::DEFAULT_SIZE,
+        config: None,
+    };
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--seeds" => batch.seeds = parse_seeds(&value()?)?,
+            "--steps" => {
+                let text = value()?;
+                batch.steps = text
+                    .parse()
+                    .map_err(|_| format!("expected a number of steps, got {text:?}"))?;
+            }
+            "--size" => batch.size = parse_size(&value()?)?,
+            "--config" => batch.config = Some(value()?.into()),
+            _ => return Err(format!("unexpected argument {arg:?}\n{USAGE}")),
+        }
+    }
+    if batch.seeds.is_empty() {
+        return Err(format!("batch needs --seeds to render\n{USAGE}"));
+    }
+    if let Some(config) = &batch.config {
+        config::check(config)?;
+    }
+    batch.run()
+}
+
+/// Carry out `gallery`, given the arguments after it.
+fn render_gallery(iter: &mut impl Iterator<Item = String>) -> Result<(), String> {
+    let out = PathBuf::from(operand(iter, "file to write the gallery to")?);
+    let mut dir = assets::resolve(gallery::PRESETS_DIR);
+    let mut seed = gallery::DEFAULT_SEED;
+    let mut seconds = compare::DEFAULT_SECONDS;
+    let mut columns = gallery::DEFAULT_COLUMNS;
+    let mut pixel_scale = None;
+    let mut rated = false;
+    let mut min_stars = 1;
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--presets" => dir = value()?.into(),
+            "--seed" => seed = draw_seed(&value()?)?,
+            "--seconds" => seconds = parse_seconds(&value()?)?,
+            "--pixel-scale" => pixel_scale = Some(parse_pixel_scale(&value()?)?),
+            "--columns" => {
+                let text = value()?;
+                columns = match text.parse() {
+                    Ok(columns) if columns > 0 => columns,
+                    _ => return Err(format!("expected a number of columns, got {text:?}")),
+                };
+            }
+            "--rated" => rated = true,
+            "--min" => min_stars = parse_stars(&value()?)?,
+            _ => return Err(format!("unexpected argument {arg:?}\n{USAGE}")),
+        }
+    }
+    if rated {
+        gallery::rated(min_stars, &out, seconds, pixel_scale, columns)?;
+    } else {
+        gallery::gallery(&dir, &out, seed, seconds, pixel_scale, columns)?;
+    }
+    println!("{}: ok", out.display());
+    Ok(())
+}
+
+/// Carry out `ratings`, given the arguments after it.
+fn list_ratings(iter: &mut impl Iterator<Item = String>) -> Result<(), String> {
+    let mut min_stars = 1;
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--min" => {
+                let text = iter
+                    .next()
+                    .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))?;
+                min_stars = parse_stars(&text)?;
+            }
+            _ => return Err(format!("unexpected argument {arg:?}\n{USAGE}")),
+        }
+    }
+    let best = ratings::best(ratings::load(Path::new(RATINGS_PATH))?, min_stars);
+    // each with the config it was rated under, indented beneath it
+    for rating in best {
+        println!("{} seed {}", rating.stars(), rating.seed);
+        for line in rating.config.lines().filter(|line| !line.trim().is_empty()) {
+            println!("    {line}");
+        }
+    }
+    Ok(())
+}
+
+/// The next argument, which is the `name` a subcommand needs.
+fn operand(iter: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    iter.next()
+        .ok_or_else(|| format!("expected a {name}\n{USAGE}"))
+}
+
+/// Parse `x,y,w,h` into a rectangle centered on `x,y`.
+fn parse_rect(text: &str) -> Result<Rect, String> {
+    let values: Vec<f32> = text
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|err| format!("bad rectangle {text:?}: {err}"))?;
+    match values[..] {
+        [x, y, w, h] if w > 0.0 && h > 0.0 => Ok(Rect::from_x_y_w_h(x, y, w, h)),
+        _ => Err(format!(
+            "expected x,y,w,h with a positive size, got {text:?}"
+        )),
+    }
+}
+
+/// The seed `text` gives, saying where it was drawn from if it wasn't given as a number.
+fn draw_seed(text: &str) -> Result<u64, String> {
+    let drawn = entropy::draw(text)?;
+    if let Some(source) = drawn.source {
+        println!("seed {} drawn from {source}", drawn.seed);
+    }
+    Ok(drawn.seed)
+}
+
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    seed.parse()
+        .map_err(|err| format!("bad seed {seed:?}: {err}"))
+}
+
+/// Parse a list of seeds and ranges of them, like `3,17,40-49`.
+fn parse_seeds(seeds: &str) -> Result<Vec<u64>, String> {
+    let mut parsed = Vec::new();
+    for part in seeds.split(',').map(str::trim) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse_seed(first)?, parse_seed(last)?);
+                if first > last {
+                    return Err(format!("the seeds {part:?} run backwards"));
+                }
+                parsed.extend(first..=last);
+            }
+            None => parsed.push(parse_seed(part)?),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Parse `<w>x<h>` into a size in pixels.
+fn parse_size(size: &str) -> Result<[u32; 2], String> {
+    let parsed = size
+        .split_once('x')
+        .and_then(|(w, h)| Some([w.trim().parse().ok()?, h.trim().parse().ok()?]));
+    match parsed {
+        Some([width, height]) if width > 0 && height > 0 => Ok([width, height]),
+        _ => Err(format!("expected a size like 3840x2160, got {size:?}")),
+    }
+}
+
+fn parse_seconds(seconds: &str) -> Result<f32, String> {
+    match seconds.parse::<f32>() {
+        Ok(value) if value >= 0.0 => Ok(value),
+        _ => Err(format!("expected a number of seconds, got {seconds:?}")),
+    }
+}
+
+fn parse_stars(stars: &str) -> Result<u8, String> {
+    match stars.parse::<u8>() {
+        Ok(value) if (1..=MAX_STARS).contains(&value) => Ok(value),
+        _ => Err(format!("expected 1 to {MAX_STARS} stars, got {stars:?}")),
+    }
+}
+
+fn parse_pixel_scale(scale: &str) -> Result<f32, String> {
+    match scale.parse::<f32>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        _ => Err(format!("expected a positive pixel scale, got {scale:?}")),
+    }
+}
+
+fn parse_addr(addr: &str) -> Result<SocketAddr, String> {
+    addr.parse()
+        .map_err(|err| format!("bad address {addr:?}: {err}"))
+}
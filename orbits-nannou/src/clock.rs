@@ -0,0 +1,93 @@
+//! The one account of time the frame loop keeps, so everything stepped along with the frames
+//! agrees on how long each stands for: the physics, the cues, presets, sound and feedback moving
+//! the parameters, the layers' drift and the framing, and the diagnostics and highlights read
+//! off them, as well as what's captured of them. Live, a frame stands for the real time it took
+//! to come round; recordings, stills and comparisons step each by the same slice however long it
+//! takes to render, so they play back just as a run that kept up would have; and a run being
+//! followed or a clip being replayed takes each frame's length from the run it follows. What goes
+//! on in the room rather than the piece — the toasts, the panel, the engagement report — reads
+//! the real time of the frame from it instead.
+
+use std::time::Duration;
+
+use nannou::prelude::*;
+
+/// How long each frame stands for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// The real time it took to come round.
+    Realtime,
+    /// `step` seconds however long it took to render, for renders that play back at an even
+    /// pace.
+    Fixed { step: f32 },
+    /// What the run being followed says it does.
+    Replay,
+}
+
+pub struct Clock {
+    mode: Mode,
+    /// Seconds the frame stands for.
+    seconds: f32,
+    /// Seconds the frames so far stand for, this one's included.
+    elapsed: f64,
+    /// Real time since the last frame, and since the run started.
+    real: Duration,
+    real_elapsed: Duration,
+}
+
+impl Clock {
+    pub fn new(mode: Mode) -> Self {
+        Clock {
+            mode,
+            seconds: 0.0,
+            elapsed: 0.0,
+            real: Duration::ZERO,
+            real_elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Clock the frames from the next on by `mode`, returning how they were clocked before.
+    pub fn set_mode(&mut self, mode: Mode) -> Mode {
+        std::mem::replace(&mut self.mode, mode)
+    }
+
+    /// Move on to the frame `update` is for, returning the seconds it stands for, which a replay
+    /// puts at none until it's told otherwise.
+    pub fn tick(&mut self, update: &Update) -> f32 {
+        self.real = update.since_last;
+        self.real_elapsed = update.since_start;
+        self.seconds = match self.mode {
+            Mode::Realtime => self.real.as_secs_f32(),
+            Mode::Fixed { step } => step,
+            Mode::Replay => 0.0,
+        };
+        self.elapsed += self.seconds as f64;
+        self.seconds
+    }
+
+    /// Have the frame stand for `seconds`, as the run being followed says it does.
+    pub fn replay(&mut self, seconds: f32) {
+        self.elapsed += (seconds - self.seconds) as f64;
+        self.seconds = seconds;
+    }
+
+    /// Seconds the frame stands for.
+    pub fn seconds(&self) -> f32 {
+        self.seconds
+    }
+
+    /// Seconds the frames so far stand for.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed as f32
+    }
+
+    /// Real seconds since the last frame, for what goes on in the room rather than the piece.
+    pub fn real_seconds(&self) -> f32 {
+        self.real.as_secs_f32()
+    }
+
+    /// Real time since the run started.
+    pub fn real_elapsed(&self) -> Duration {
+        self.real_elapsed
+    }
+}
@@ -0,0 +1,127 @@
+//! Renders stills of one seed under two configs and sets them side by side, or shows where they
+//! differ, to document what a change of config does to the piece.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use nannou::image::{self, GenericImage, RgbaImage};
+
+use crate::config::Config;
+use crate::manifest::{self, Manifest};
+use crate::portraits::Portraits;
+
+/// Wall-clock seconds each frame of a still is simulated as, however long it took to render.
+pub const FRAME_SECONDS: f32 = 1.0 / 60.0;
+/// Size of a still, in points.
+pub const SIZE: (f32, f32) = (1280.0, 720.0);
+/// Seconds simulated before a still is taken, unless told otherwise.
+pub const DEFAULT_SECONDS: f32 = 10.0;
+
+/// A still being rendered by the `snapshot` subcommand, in a hidden window.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// Where the PNG goes.
+    pub out: PathBuf,
+    /// Config to load instead of the one in the working directory.
+    pub config: Option<PathBuf>,
+    /// Seconds to simulate before taking the still.
+    pub seconds: f32,
+    /// Seconds simulated so far.
+    pub elapsed: f32,
+    /// Portraits of each particle to write to `out`, a directory, instead of the still.
+    pub portraits: Option<Portraits>,
+}
+
+impl Snapshot {
+    pub fn new(out: PathBuf) -> Self {
+        Snapshot {
+            out,
+            config: None,
+            seconds: DEFAULT_SECONDS,
+            elapsed: 0.0,
+            portraits: None,
+        }
+    }
+}
+
+/// Render `seed` after `seconds` under each of `configs`, at `pixel_scale` pixels to a point if
+/// given, and write them to `out`, side by side, or as the difference between them if
+/// `difference`.
+pub fn compare(
+    configs: [&Path; 2],
+    out: &Path,
+    seed: u64,
+    seconds: f32,
+    pixel_scale: Option<f32>,
+    difference: bool,
+) -> Result<(), String> {
+    let [a, b] = [
+        still(configs[0], seed, seconds, pixel_scale)?,
+        still(configs[1], seed, seconds, pixel_scale)?,
+    ];
+    let image = if difference {
+        differences(&a, &b)
+    } else {
+        side_by_side(&a, &b)
+    };
+    image
+        .save(out)
+        .map_err(|err| format!("{}: {err}", out.display()))?;
+    let written = [
+        Config::load_file(configs[0])?,
+        Config::load_file(configs[1])?,
+    ];
+    let manifest = Manifest::new(written.map(|config| config.written()).into())
+        .seeds(vec![seed])
+        .time(seconds);
+    manifest.write_beside(out)
+}
+
+/// Render `seed` after `seconds` under `config`, at `pixel_scale` pixels to a point if given and
+/// otherwise at the display's scale factor, with a run of the `snapshot` subcommand.
+pub fn still(
+    config: &Path,
+    seed: u64,
+    seconds: f32,
+    pixel_scale: Option<f32>,
+) -> Result<RgbaImage, String> {
+    // each still takes a run of its own, as nannou can only run one app in a process
+    let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+    let path = std::env::temp_dir().join(format!("trynannou-still-{}.png", std::process::id()));
+    let pixel_scale = pixel_scale.map(|scale| ["--pixel-scale".to_string(), scale.to_string()]);
+    let status = Command::new(exe)
+        .arg("snapshot")
+        .arg(&path)
+        .arg("--config")
+        .arg(config)
+        .args(["--seed", &seed.to_string()])
+        .args(["--seconds", &seconds.to_string()])
+        .args(pixel_scale.iter().flatten())
+        .status()
+        .map_err(|err| err.to_string())?;
+    if !status.success() {
+        return Err(format!("failed to render {}", config.display()));
+    }
+    let still = image::open(&path).map_err(|err| format!("{}: {err}", path.display()));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(manifest::beside(&path));
+    Ok(still?.to_rgba8())
+}
+
+fn side_by_side(a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+    let mut image = RgbaImage::new(a.width() + b.width(), a.height().max(b.height()));
+    image.copy_from(a, 0, 0).unwrap();
+    image.copy_from(b, a.width(), 0).unwrap();
+    image
+}
+
+/// How far apart each channel of `a` and `b` is, black where they match.
+fn differences(a: &RgbaImage, b: &RgbaImage) -> RgbaImage {
+    let (width, height) = (a.width().min(b.width()), a.height().min(b.height()));
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (a, b) = (a.get_pixel(x, y).0, b.get_pixel(x, y).0);
+        let [r, g, b] = [0, 1, 2].map(|c| a[c].abs_diff(b[c]));
+        image::Rgba([r, g, b, 255])
+    })
+}
@@ -0,0 +1,863 @@
+//! The compute path `--gpu` turns on: the particles stepped through their ticks by a compute
+//! shader, and the current generation's trails kept in GPU buffers, with their ribbons laid out
+//! by another and drawn by a pipeline of their own into a texture the scene draws like any other.
+//! Whatever either can't do is left to the CPU, as it all is without it.
+
+use std::sync::Arc;
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use orbits_core::generation::{Generation, Ticker, TICK_SECONDS};
+use orbits_core::integrator::Scheme;
+use orbits_core::physics::{Body, GravityLaw, Particle};
+use orbits_core::trails::{self, echo_delay};
+use orbits_core::{orbital_radius, settings};
+
+use crate::layers::Blend;
+use crate::perspective::Perspective;
+
+/// Most bodies the step shader has room for; with more, the CPU steps the particles.
+const MAX_BODIES: usize = 8;
+/// Invocations in each of the shaders' workgroups.
+const WORKGROUP_SIZE: u32 = 64;
+/// Most workgroups a dispatch can have along its one dimension.
+const MAX_WORKGROUPS: u32 = 65535;
+/// Storage buffers bound at once by the trails' compute pass, the most any stage binds.
+const STORAGE_BUFFERS: u32 = 4;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Stands in for a record a trail hasn't left.
+const NO_RECORD: Record = Record {
+    pos: [0.0; 2],
+    slot: u32::MAX,
+    thickness: 1.0,
+    color: [0.0; 4],
+};
+
+/// Blends colors premultiplied by their alpha, as the trails' texture holds them.
+const PREMULTIPLIED: wgpu::BlendComponent = wgpu::BlendComponent {
+    src_factor: wgpu::BlendFactor::One,
+    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+    operation: wgpu::BlendOperation::Add,
+};
+
+/// How the trails are drawn, as `draw_history` is given it.
+#[derive(Clone, Copy)]
+pub struct Look {
+    /// Opacity of the trails, with the most interesting picked out by `highlight`.
+    pub opacity: f32,
+    pub highlight: f32,
+    /// Simulated seconds the newest points are pushed along the particles' velocities.
+    pub extrapolate: f32,
+    /// How far the trails are to their full color and length.
+    pub build_in: f32,
+    /// How much of their full length is drawn, from 0 to 1.
+    pub length: f32,
+    /// The camera the trails are seen through in 3D, or nothing in 2D.
+    pub view: Option<Perspective>,
+}
+
+impl Look {
+    /// Where `pos`, a point of the trail of `particle` of `generation`, is seen.
+    pub fn place(&self, generation: &Generation, particle: usize, pos: Point2) -> Point2 {
+        self.view
+            .map_or(pos, |view| view.place(generation, particle, pos))
+    }
+}
+
+pub struct Compute {
+    device_queue: Arc<wgpu::DeviceQueuePair>,
+    step: Step,
+    trails: Trails,
+    /// Whether stepping on the GPU still works, as it stops for good once it fails.
+    stepping: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct StepUniforms {
+    bodies: [[f32; 4]; MAX_BODIES],
+    body_count: u32,
+    count: u32,
+    ticks: u32,
+    scheme: u32,
+    tick_seconds: f32,
+    force_exponent: f32,
+    /// 0 for a power law, 1 for a softened inverse square, 2 for a post-Newtonian one and 3 for
+    /// the legacy pull.
+    law: u32,
+    /// The power law's scale, the average orbit's radius to the exponent less 2, the softening
+    /// squared, the post-Newtonian strength times the average orbit's radius squared, or the
+    /// legacy pull of a mass of strength 1.
+    law_parameter: f32,
+}
+
+struct Step {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    uniforms: wgpu::Buffer,
+    buffers: Option<StepBuffers>,
+}
+
+/// Buffers with room for `capacity` particles.
+struct StepBuffers {
+    capacity: usize,
+    particles: wgpu::Buffer,
+    previous: wgpu::Buffer,
+    /// Where the particles and their previous positions are copied to be read back.
+    staging: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Record {
+    pos: [f32; 2],
+    slot: u32,
+    /// How thick the trail is here, as a multiple of as ever.
+    thickness: f32,
+    /// Hue from 0 to 1, saturation, lightness and alpha.
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Info {
+    vel: [f32; 2],
+    alpha: f32,
+    visible: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Point {
+    _pos: [f32; 2],
+    _padding: [f32; 2],
+    _color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BuildUniforms {
+    count: u32,
+    history: u32,
+    epochs: u32,
+    rows: u32,
+    echo_delay: u32,
+    extrapolate: f32,
+    build_in: f32,
+    fade: f32,
+    span: u32,
+    curve: u32,
+    _padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DrawUniforms {
+    middle: [f32; 2],
+    half: [f32; 2],
+    count: u32,
+    pieces: u32,
+    _padding: [u32; 2],
+}
+
+struct Trails {
+    store: wgpu::ComputePipeline,
+    place: wgpu::ComputePipeline,
+    build_layout: wgpu::BindGroupLayout,
+    render: wgpu::RenderPipeline,
+    render_layout: wgpu::BindGroupLayout,
+    build_uniforms: wgpu::Buffer,
+    draw_uniforms: wgpu::Buffer,
+    buffers: Option<TrailBuffers>,
+    texture: Option<wgpu::Texture>,
+    /// The generation the rings hold the trails of, and how far.
+    synced: Option<Synced>,
+    /// What the texture was last drawn of.
+    drawn: Option<Drawn>,
+}
+
+/// Buffers for the trails of `count` particles, each kept to `history` records.
+struct TrailBuffers {
+    count: usize,
+    history: usize,
+    rings: wgpu::Buffer,
+    incoming: wgpu::Buffer,
+    infos: wgpu::Buffer,
+    build_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Synced {
+    /// Which generation seeded it is, counting from 1.
+    seeded: usize,
+    count: usize,
+    history: usize,
+    respawns: usize,
+    epochs: usize,
+}
+
+/// Which of the records the rings are missing.
+#[derive(Clone, Copy, PartialEq)]
+enum Missing {
+    None,
+    /// Those of each trail just shown.
+    Newest,
+    /// Any of them, as they're of another generation or have fallen behind.
+    All,
+}
+
+#[derive(Clone, Copy)]
+struct Drawn {
+    seeded: usize,
+    opacity: f32,
+    /// The world the texture covers.
+    view: Rect,
+}
+
+impl Compute {
+    /// The compute path on the device of `device_queue`, if it has what the shaders need.
+    pub fn new(device_queue: Arc<wgpu::DeviceQueuePair>) -> Result<Self, String> {
+        let device = device_queue.device();
+        let storage_buffers = device.limits().max_storage_buffers_per_shader_stage;
+        if storage_buffers < STORAGE_BUFFERS {
+            return Err(format!(
+                "the GPU binds only {storage_buffers} storage buffers to a shader, and the compute \
+                 path needs {STORAGE_BUFFERS}"
+            ));
+        }
+        let step = Step::new(device);
+        let trails = Trails::new(device);
+        Ok(Compute {
+            device_queue,
+            step,
+            trails,
+            stepping: true,
+        })
+    }
+
+    /// Bring the trails of `generation`, the `seeded`th, up to date on the GPU, and draw them as
+    /// `look` has them into a texture of `size` pixels covering `view` of the world. Returns
+    /// whether it could, as otherwise they're left for the CPU to draw.
+    pub fn render_trails(
+        &mut self,
+        generation: &Generation,
+        seeded: usize,
+        look: Look,
+        view: Rect,
+        size: [u32; 2],
+    ) -> bool {
+        let device_queue = &self.device_queue;
+        let drawn = self
+            .trails
+            .render(device_queue, generation, seeded, look, view, size);
+        self.trails.drawn = drawn.then_some(Drawn {
+            seeded,
+            opacity: look.opacity,
+            view,
+        });
+        drawn
+    }
+
+    /// Draw the trails of the `seeded`th generation at `opacity`, in world coordinates, blended
+    /// by `blend`, if they were last rendered just so. Returns whether they were.
+    pub fn draw_trails(&self, seeded: usize, opacity: f32, blend: Blend, draw: &Draw) -> bool {
+        let (Some(drawn), Some(texture)) = (self.trails.drawn, &self.trails.texture) else {
+            return false;
+        };
+        if drawn.seeded != seeded || drawn.opacity != opacity {
+            return false;
+        }
+        draw.color_blend(blend.component(true))
+            .alpha_blend(PREMULTIPLIED)
+            .texture(texture)
+            .xy(drawn.view.xy())
+            .wh(drawn.view.wh());
+        true
+    }
+
+    /// Upload the trails afresh next time, as they're of another run once a saved state is loaded
+    /// over the one they were of.
+    pub fn forget(&mut self) {
+        self.trails.synced = None;
+        self.trails.drawn = None;
+    }
+
+    /// Make the buffers and texture afresh once the GPU has failed, uploading the trails again
+    /// from those kept on the CPU, and go back to stepping the particles on it.
+    pub fn rebuild(&mut self) {
+        self.step.buffers = None;
+        self.trails.buffers = None;
+        self.trails.texture = None;
+        self.stepping = true;
+        self.forget();
+    }
+}
+
+impl Ticker for Compute {
+    fn tick(
+        &mut self,
+        particles: &mut [Particle],
+        previous: &mut [Point2],
+        ticks: usize,
+        bodies: &[Body],
+    ) -> bool {
+        if !self.stepping || bodies.len() > MAX_BODIES || particles.is_empty() {
+            return false;
+        }
+        if workgroups(particles.len()) > MAX_WORKGROUPS {
+            return false;
+        }
+        match self
+            .step
+            .tick(&self.device_queue, particles, previous, ticks, bodies)
+        {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("stepping the particles on the GPU failed, so the CPU steps them: {err}");
+                self.stepping = false;
+                false
+            }
+        }
+    }
+}
+
+impl Step {
+    fn new(device: &wgpu::Device) -> Self {
+        let layout = wgpu::BindGroupLayoutBuilder::new()
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+            .storage_buffer(wgpu::ShaderStages::COMPUTE, false, false)
+            .uniform_buffer(wgpu::ShaderStages::COMPUTE, false)
+            .build(device);
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("step uniforms"),
+            size: std::mem::size_of::<StepUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let shader = device.create_shader_module(&wgpu::include_wgsl!("shaders/step.wgsl"));
+        let pipeline = compute_pipeline(device, "step", &layout, &shader, "main");
+        Step {
+            pipeline,
+            layout,
+            uniforms,
+            buffers: None,
+        }
+    }
+
+    fn tick(
+        &mut self,
+        device_queue: &wgpu::DeviceQueuePair,
+        particles: &mut [Particle],
+        previous: &mut [Point2],
+        ticks: usize,
+        bodies: &[Body],
+    ) -> Result<(), String> {
+        let (device, queue) = (device_queue.device(), device_queue.queue());
+        let count = particles.len();
+        if self.buffers.as_ref().is_none_or(|b| b.capacity < count) {
+            self.buffers = Some(StepBuffers::new(
+                device,
+                &self.layout,
+                &self.uniforms,
+                count,
+            ));
+        }
+        let buffers = self.buffers.as_ref().unwrap();
+
+        // every body pulls by the same law, the settings' with the parameter's exponent
+        let law = bodies
+            .first()
+            .map_or(settings::get().gravity_law, |body| body.law);
+        let mut uniforms = StepUniforms {
+            bodies: [[0.0; 4]; MAX_BODIES],
+            body_count: bodies.len() as u32,
+            count: count as u32,
+            ticks: ticks as u32,
+            scheme: match settings::get().integrator {
+                Scheme::SemiImplicitEuler => 0,
+                Scheme::VelocityVerlet => 1,
+                Scheme::Rk4 => 2,
+            },
+            tick_seconds: TICK_SECONDS,
+            force_exponent: law.exponent(),
+            law: 0,
+            law_parameter: 1.0,
+        };
+        match law {
+            GravityLaw::Newtonian => {}
+            GravityLaw::Power { exponent } => {
+                uniforms.law_parameter = orbital_radius().powf(exponent - 2.0);
+            }
+            GravityLaw::Legacy => {
+                uniforms.law = 3;
+                uniforms.law_parameter = law.pull(orbital_radius(), 1.0);
+            }
+            GravityLaw::Softened { softening } => {
+                uniforms.law = 1;
+                uniforms.law_parameter = softening * softening;
+            }
+            GravityLaw::PostNewtonian { strength } => {
+                uniforms.law = 2;
+                uniforms.law_parameter = strength * orbital_radius() * orbital_radius();
+            }
+        }
+        for (slot, body) in uniforms.bodies.iter_mut().zip(bodies) {
+            *slot = [body.pos.x, body.pos.y, body.gm, 0.0];
+        }
+        queue.write_buffer(&self.uniforms, 0, unsafe { wgpu::bytes::from(&uniforms) });
+        let state: Vec<[f32; 4]> = particles
+            .iter()
+            .map(|particle| {
+                [
+                    particle.pos.x,
+                    particle.pos.y,
+                    particle.vel.x,
+                    particle.vel.y,
+                ]
+            })
+            .collect();
+        queue.write_buffer(&buffers.particles, 0, unsafe {
+            wgpu::bytes::from_slice(&state)
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("step"),
+        });
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("step"),
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &buffers.bind_group, &[]);
+        pass.dispatch(workgroups(count), 1, 1);
+        drop(pass);
+        let state_bytes = (count * std::mem::size_of::<[f32; 4]>()) as u64;
+        let previous_bytes = (count * std::mem::size_of::<[f32; 2]>()) as u64;
+        encoder.copy_buffer_to_buffer(&buffers.particles, 0, &buffers.staging, 0, state_bytes);
+        encoder.copy_buffer_to_buffer(
+            &buffers.previous,
+            0,
+            &buffers.staging,
+            state_bytes,
+            previous_bytes,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffers.staging.slice(..state_bytes + previous_bytes);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapping).map_err(|err| err.to_string())?;
+        let bytes = slice.get_mapped_range();
+        let floats = |range: std::ops::Range<usize>| {
+            bytes[range]
+                .chunks_exact(4)
+                .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                .collect::<Vec<f32>>()
+        };
+        let state = floats(0..state_bytes as usize);
+        let before = floats(state_bytes as usize..(state_bytes + previous_bytes) as usize);
+        for (particle, state) in particles.iter_mut().zip(state.chunks_exact(4)) {
+            particle.pos = pt2(state[0], state[1]);
+            particle.vel = vec2(state[2], state[3]);
+        }
+        for (previous, before) in previous.iter_mut().zip(before.chunks_exact(2)) {
+            *previous = pt2(before[0], before[1]);
+        }
+        drop(bytes);
+        buffers.staging.unmap();
+        Ok(())
+    }
+}
+
+impl StepBuffers {
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniforms: &wgpu::Buffer,
+        capacity: usize,
+    ) -> Self {
+        let state_bytes = (capacity * std::mem::size_of::<[f32; 4]>()) as u64;
+        let previous_bytes = (capacity * std::mem::size_of::<[f32; 2]>()) as u64;
+        let particles = storage(
+            device,
+            "step particles",
+            state_bytes,
+            wgpu::BufferUsages::COPY_SRC,
+        );
+        let previous = storage(
+            device,
+            "step previous",
+            previous_bytes,
+            wgpu::BufferUsages::COPY_SRC,
+        );
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("step staging"),
+            size: state_bytes + previous_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .buffer_bytes(&particles, 0, None)
+            .buffer_bytes(&previous, 0, None)
+            .buffer::<StepUniforms>(uniforms, 0..1)
+            .build(device, layout);
+        StepBuffers {
+            capacity,
+            particles,
+            previous,
+            staging,
+            bind_group,
+        }
+    }
+}
+
+impl Trails {
+    fn new(device: &wgpu::Device) -> Self {
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let build_layout = wgpu::BindGroupLayoutBuilder::new()
+            .storage_buffer(compute, false, false)
+            .storage_buffer(compute, false, true)
+            .storage_buffer(compute, false, true)
+            .storage_buffer(compute, false, false)
+            .uniform_buffer(compute, false)
+            .build(device);
+        let vertex = wgpu::ShaderStages::VERTEX;
+        let render_layout = wgpu::BindGroupLayoutBuilder::new()
+            .storage_buffer(vertex, false, true)
+            .storage_buffer(vertex, false, true)
+            .uniform_buffer(vertex, false)
+            .build(device);
+        let uniforms = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let build_uniforms = uniforms("ribbons uniforms", std::mem::size_of::<BuildUniforms>());
+        let draw_uniforms = uniforms("trails uniforms", std::mem::size_of::<DrawUniforms>());
+
+        let ribbons = device.create_shader_module(&wgpu::include_wgsl!("shaders/ribbons.wgsl"));
+        let store = compute_pipeline(device, "ribbons", &build_layout, &ribbons, "store_records");
+        let place = compute_pipeline(device, "ribbons", &build_layout, &ribbons, "place_points");
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("trails"),
+            bind_group_layouts: &[&render_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&wgpu::include_wgsl!("shaders/trails.wgsl"));
+        let render = wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+            .vertex_entry_point("vs_main")
+            .fragment_shader(&shader)
+            .fragment_entry_point("fs_main")
+            .color_format(FORMAT)
+            .color_blend(PREMULTIPLIED)
+            .alpha_blend(PREMULTIPLIED)
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleList)
+            .build(device);
+
+        Trails {
+            store,
+            place,
+            build_layout,
+            render,
+            render_layout,
+            build_uniforms,
+            draw_uniforms,
+            buffers: None,
+            texture: None,
+            synced: None,
+            drawn: None,
+        }
+    }
+
+    fn render(
+        &mut self,
+        device_queue: &wgpu::DeviceQueuePair,
+        generation: &Generation,
+        seeded: usize,
+        look: Look,
+        view: Rect,
+        size: [u32; 2],
+    ) -> bool {
+        let Look {
+            opacity,
+            highlight,
+            extrapolate,
+            build_in,
+            length,
+            ..
+        } = look;
+        let (device, queue) = (device_queue.device(), device_queue.queue());
+        let count = generation.history.len();
+        let history = settings::get().history;
+        let length = build_in * length;
+        let visible: Vec<usize> = (0..count)
+            .map(|particle| trails::visible(generation, particle, length))
+            .collect();
+        let rows = visible.iter().copied().max().unwrap_or(0);
+        // a pair of trails at the least, and no more than a buffer or dispatch can hold
+        let ring_bytes = (count * history * std::mem::size_of::<Record>()) as u64;
+        let most_bytes = device.limits().max_storage_buffer_binding_size as u64;
+        let invocations = (count * history) as u64;
+        if count < 2
+            || history < 2
+            || ring_bytes > most_bytes
+            || invocations > (MAX_WORKGROUPS * WORKGROUP_SIZE) as u64
+        {
+            return false;
+        }
+
+        if self
+            .buffers
+            .as_ref()
+            .is_none_or(|b| b.count != count || b.history != history)
+        {
+            self.buffers = Some(TrailBuffers::new(self, device, count, history));
+            self.synced = None;
+        }
+        let buffers = self.buffers.as_ref().unwrap();
+        if self
+            .texture
+            .as_ref()
+            .is_none_or(|texture| texture.size() != size)
+        {
+            self.texture = Some(
+                wgpu::TextureBuilder::new()
+                    .size(size)
+                    .format(FORMAT)
+                    .usage(
+                        wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::TEXTURE_BINDING,
+                    )
+                    .build(device),
+            );
+        }
+        let texture = self.texture.as_ref().unwrap();
+
+        let synced = Synced {
+            seeded,
+            count,
+            history,
+            respawns: generation.respawns,
+            epochs: generation.epochs,
+        };
+        let missing = match self.synced {
+            Some(last) if last == synced => Missing::None,
+            Some(last)
+                if Synced {
+                    epochs: last.epochs + 1,
+                    ..last
+                } == synced =>
+            {
+                Missing::Newest
+            }
+            _ => Missing::All,
+        };
+        self.synced = Some(synced);
+        // a trail lags the one before it by its echo delay, so the newest record it shows is
+        // that many back from its newest
+        let newest = |particle| record(generation, history, particle, echo_delay(particle));
+        match missing {
+            Missing::None => {}
+            Missing::Newest => {
+                let records: Vec<Record> = (0..count)
+                    .map(|particle| newest(particle).unwrap_or(NO_RECORD))
+                    .collect();
+                queue.write_buffer(&buffers.incoming, 0, unsafe {
+                    wgpu::bytes::from_slice(&records)
+                });
+            }
+            Missing::All => {
+                let mut rings = vec![NO_RECORD; count * history];
+                for particle in 0..count {
+                    let delay = echo_delay(particle);
+                    for back in delay..delay + history {
+                        if let Some(record) = record(generation, history, particle, back) {
+                            rings[particle * history + record.slot as usize] = record;
+                        }
+                    }
+                }
+                queue.write_buffer(&buffers.rings, 0, unsafe {
+                    wgpu::bytes::from_slice(&rings)
+                });
+            }
+        }
+
+        let infos: Vec<Info> = (0..count)
+            .map(|particle| Info {
+                vel: generation.particles[particle].vel.into(),
+                alpha: opacity
+                    * generation.saliency.alpha(particle, highlight)
+                    * generation.presence(particle),
+                visible: visible[particle] as u32,
+            })
+            .collect();
+        queue.write_buffer(&buffers.infos, 0, unsafe {
+            wgpu::bytes::from_slice(&infos)
+        });
+        let build_uniforms = BuildUniforms {
+            count: count as u32,
+            history: history as u32,
+            epochs: generation.epochs as u32,
+            rows: rows as u32,
+            echo_delay: settings::get().echo_delay as u32,
+            extrapolate,
+            build_in,
+            fade: settings::get().trail_fade,
+            span: trails::span(length) as u32,
+            curve: settings::get().fade_curve.index(),
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.build_uniforms, 0, unsafe {
+            wgpu::bytes::from(&build_uniforms)
+        });
+        let pieces = rows.saturating_sub(1);
+        let draw_uniforms = DrawUniforms {
+            middle: view.xy().into(),
+            half: (view.wh() / 2.0).into(),
+            count: count as u32,
+            pieces: pieces as u32,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.draw_uniforms, 0, unsafe {
+            wgpu::bytes::from(&draw_uniforms)
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("trails"),
+        });
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("ribbons"),
+        });
+        pass.set_bind_group(0, &buffers.build_bind_group, &[]);
+        if missing == Missing::Newest {
+            pass.set_pipeline(&self.store);
+            pass.dispatch(workgroups(count), 1, 1);
+        }
+        if rows > 0 {
+            pass.set_pipeline(&self.place);
+            pass.dispatch(workgroups(count * rows), 1, 1);
+        }
+        drop(pass);
+        let view = texture.view().build();
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(&view, |color| {
+                color.load_op(wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT))
+            })
+            .begin(&mut encoder);
+        if pieces > 0 {
+            pass.set_pipeline(&self.render);
+            pass.set_bind_group(0, &buffers.render_bind_group, &[]);
+            pass.draw(0..((count - 1) * pieces * 6) as u32, 0..1);
+        }
+        drop(pass);
+        queue.submit(Some(encoder.finish()));
+        true
+    }
+}
+
+impl TrailBuffers {
+    fn new(trails: &Trails, device: &wgpu::Device, count: usize, history: usize) -> Self {
+        let bytes = |size: usize| (count * size) as u64;
+        let copy = wgpu::BufferUsages::COPY_DST;
+        let records = bytes(history * std::mem::size_of::<Record>());
+        let rings = storage(device, "trail rings", records, copy);
+        let incoming = storage(
+            device,
+            "trail records",
+            bytes(std::mem::size_of::<Record>()),
+            copy,
+        );
+        let infos = storage(
+            device,
+            "trail infos",
+            bytes(std::mem::size_of::<Info>()),
+            copy,
+        );
+        let points = bytes(history * std::mem::size_of::<Point>());
+        let points = storage(device, "trail points", points, wgpu::BufferUsages::empty());
+        let build_bind_group = wgpu::BindGroupBuilder::new()
+            .buffer_bytes(&rings, 0, None)
+            .buffer_bytes(&incoming, 0, None)
+            .buffer_bytes(&infos, 0, None)
+            .buffer_bytes(&points, 0, None)
+            .buffer::<BuildUniforms>(&trails.build_uniforms, 0..1)
+            .build(device, &trails.build_layout);
+        let render_bind_group = wgpu::BindGroupBuilder::new()
+            .buffer_bytes(&infos, 0, None)
+            .buffer_bytes(&points, 0, None)
+            .buffer::<DrawUniforms>(&trails.draw_uniforms, 0..1)
+            .build(device, &trails.render_layout);
+        TrailBuffers {
+            count,
+            history,
+            rings,
+            incoming,
+            infos,
+            build_bind_group,
+            render_bind_group,
+        }
+    }
+}
+
+/// The record `particle` of `generation` left `back` records before its newest, in the slot of
+/// its ring of `history` it goes in, if it's kept one that far back.
+fn record(generation: &Generation, history: usize, particle: usize, back: usize) -> Option<Record> {
+    let record = generation.history[particle].get(back)?;
+    let color = record.color;
+    Some(Record {
+        pos: record.pos.into(),
+        slot: ((generation.epochs - 1 - back) % history) as u32,
+        thickness: record.thickness,
+        color: [
+            color.hue.to_positive_degrees() / 360.0,
+            color.saturation,
+            color.lightness,
+            color.alpha,
+        ],
+    })
+}
+
+/// A storage buffer of `size` bytes, usable in the other ways `usage` adds too.
+fn storage(
+    device: &wgpu::Device,
+    label: &str,
+    size: u64,
+    usage: wgpu::BufferUsages,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | usage,
+        mapped_at_creation: false,
+    })
+}
+
+fn compute_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::BindGroupLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+) -> wgpu::ComputePipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        module: shader,
+        entry_point,
+    })
+}
+
+/// Workgroups enough for an invocation for each of `count` things.
+fn workgroups(count: usize) -> u32 {
+    count.div_ceil(WORKGROUP_SIZE as usize) as u32
+}
@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use nannou::window::Window;
+use orbits_core::settings::Settings;
+use orbits_core::trails::{Layering, Thinning};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use toml::Table;
+
+use crate::ambience::AmbienceConfig;
+use crate::ascii::AsciiConfig;
+use crate::assets;
+use crate::audio::AudioConfig;
+use crate::brush::BrushConfig;
+use crate::classes::ClassesConfig;
+use crate::constellation::ConstellationConfig;
+use crate::dice::DiceConfig;
+use crate::export::ExportConfig;
+use crate::exposure::ExposureConfig;
+use crate::feedback::FeedbackConfig;
+use crate::framing::FramingConfig;
+use crate::grain::GrainConfig;
+use crate::highlights::HighlightsConfig;
+use crate::hints::HintsConfig;
+use crate::hulls::HullsConfig;
+use crate::keys::{Action, Keymap};
+use crate::layers::LayersConfig;
+use crate::layout::LayoutConfig;
+use crate::leds::LedsConfig;
+use crate::lighting::LightingConfig;
+use crate::mask::{self, Masks};
+use crate::midi::MidiConfig;
+use crate::nebula::NebulaConfig;
+use crate::perturbation::PerturbationConfig;
+use crate::power::PowerConfig;
+use crate::presets::PresetsConfig;
+use crate::render::RenderConfig;
+use crate::retro::RetroConfig;
+use crate::rewind::{Rewind, RewindConfig};
+use crate::smear::SmearConfig;
+use crate::span::SpanConfig;
+use crate::stars::StarsConfig;
+use crate::tessellation::TessellationConfig;
+use crate::tier::PerformanceConfig;
+use crate::transition::TransitionConfig;
+use crate::tray::TrayConfig;
+
+/// Where the config is looked for, relative to the working directory or anywhere else assets are.
+pub const CONFIG_PATH: &str = "trynannou.toml";
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How the window is shown.
+    pub window: WindowConfig,
+    /// The windows the piece is spread over, one on each monitor spanned.
+    pub span: SpanConfig,
+    /// Hotkeys to use instead of the defaults, as `action = "Key"`.
+    pub keys: BTreeMap<Action, String>,
+    /// Seconds to simulate before the first frame is shown, so trails are fully formed from the
+    /// start rather than growing in.
+    pub pre_roll: f32,
+    /// The tunables of the simulation itself.
+    pub simulation: Settings,
+    /// How trails are thinned out where they're dense.
+    pub thinning: Thinning,
+    /// How overlapping pieces of trail are stacked.
+    pub layering: Layering,
+    /// How a reseed gives way from one scene to the next.
+    pub transition: TransitionConfig,
+    /// The disturbances that keep a long run from settling.
+    pub perturbation: PerturbationConfig,
+    /// The nebula drawn behind the piece.
+    pub nebula: NebulaConfig,
+    /// The star field drawn behind the piece.
+    pub stars: StarsConfig,
+    /// The smear of each frame along the particles' motion.
+    pub smear: SmearConfig,
+    /// The frame redrawn as ASCII art.
+    pub ascii: AsciiConfig,
+    /// The pixelation, dithering and CRT passes for a retro look.
+    pub retro: RetroConfig,
+    /// The grain laid over the finished frame.
+    pub grain: GrainConfig,
+    /// How a `--long-exposure` is developed.
+    pub exposure: ExposureConfig,
+    /// How frames are written by `export`.
+    pub export: ExportConfig,
+    /// How much of the run is kept to mark and export clips of after the fact.
+    pub rewind: RewindConfig,
+    /// What counts as a highlight with `--highlights`.
+    pub highlights: HighlightsConfig,
+    /// How the particles' orbits are sorted into classes for the legend.
+    pub classes: ClassesConfig,
+    /// The presets switched between with the number keys.
+    pub presets: PresetsConfig,
+    /// The hints given while the piece is left alone.
+    pub hints: HintsConfig,
+    /// What closing the window does with `--tray`.
+    pub tray: TrayConfig,
+    /// The matrix streamed to with `--leds`.
+    pub leds: LedsConfig,
+    /// The channels sent with `--art-net`.
+    pub lighting: LightingConfig,
+    /// The bulbs tinted with `--ambience`.
+    pub ambience: AmbienceConfig,
+    /// The performance tier whose defaults fill in what's left unset.
+    pub performance: PerformanceConfig,
+    /// How the piece is lightened on battery or when hot.
+    pub power: PowerConfig,
+    /// What rolling the dice picks.
+    pub dice: DiceConfig,
+    /// How big and strong the brush is.
+    pub brush: BrushConfig,
+    /// The style the trails are drawn in.
+    pub render: RenderConfig,
+    /// Which layers of the scene are shown, and how they're blended and ordered.
+    pub layers: LayersConfig,
+    /// Which particles the constellation joins, and how its lines are drawn.
+    pub constellation: ConstellationConfig,
+    /// Which diagram the tessellation draws, and how.
+    pub tessellation: TessellationConfig,
+    /// What the hulls outline, and how.
+    pub hulls: HullsConfig,
+    /// The panes the window is partitioned into, each showing the simulation its own way.
+    pub layout: LayoutConfig,
+    /// Whether the view follows the swarm, and how closely.
+    pub framing: FramingConfig,
+    /// The input listened to with `--audio`, and what it moves.
+    pub audio: AudioConfig,
+    /// The readings of the simulation that move the parameters.
+    pub feedback: FeedbackConfig,
+    /// The controller read with `--midi`, and what its knobs and notes are bound to.
+    pub midi: MidiConfig,
+    /// The file as it was written, to tell what it sets from what it leaves to the defaults.
+    #[serde(skip)]
+    written: Table,
+}
+
+impl Config {
+    /// Load the config file, or the defaults if there is none.
+    pub fn load() -> Result<Self, String> {
+        Ok(Config::read(&assets::resolve(CONFIG_PATH))?.unwrap_or_default())
+    }
+
+    /// Load the config file at `path`, which has to exist.
+    pub fn load_file(path: &Path) -> Result<Self, String> {
+        Config::read(path)?.ok_or_else(|| format!("{}: not found", path.display()))
+    }
+
+    /// What the file sets, as TOML, less its comments and layout. Empty for the defaults.
+    pub fn written(&self) -> String {
+        toml::to_string(&self.written).unwrap_or_default()
+    }
+
+    /// Whether the file sets `key` of its `section`, rather than leaving it to the defaults.
+    pub fn sets(&self, section: &str, key: &str) -> bool {
+        (self.written.get(section))
+            .and_then(|section| section.get(key))
+            .is_some()
+    }
+
+    /// Read the config at `path`, or `None` if there's no file there.
+    fn read(path: &Path) -> Result<Option<Self>, String> {
+        let err = |err: &dyn std::fmt::Display| format!("{}: {err}", path.display());
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let mut config: Config = toml::from_str(&text).map_err(|e| err(&e))?;
+                config.written = text.parse().map_err(|e| err(&e))?;
+                Ok(Some(config))
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(err(&e)),
+        }
+    }
+}
+
+/// How the window is shown. Trynannou's `[window]` config section.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    /// Size to show the window at, in points, or whatever size it opens at if left out.
+    pub size: Option<[u32; 2]>,
+    /// Whether to cover the whole display rather than show a window.
+    pub fullscreen: bool,
+}
+
+impl WindowConfig {
+    /// Show `window` this way.
+    pub fn apply(&self, window: &Window) {
+        if let Some([width, height]) = self.size {
+            window.set_inner_size_points(width as f32, height as f32);
+        }
+        window.set_fullscreen(self.fullscreen);
+    }
+}
+
+/// A JSON Schema for the config file, for editors and deployment pipelines.
+pub fn schema() -> String {
+    serde_json::to_string_pretty(&schemars::schema_for!(Config)).unwrap()
+}
+
+/// Validate a config file as thoroughly as launching with it would, without launching.
+pub fn check(path: &Path) -> Result<(), String> {
+    let config = Config::load_file(path)?;
+    let err = |err: String| format!("{}: {err}", path.display());
+    Keymap::new(&config.keys).map_err(err)?;
+    config.simulation.check().map_err(err)?;
+    if let Some(center_path) = &config.simulation.center_path {
+        mask::trace(&mut center_path.path.clone()).map_err(err)?;
+    }
+    config.midi.check().map_err(err)?;
+    config.layers.check().map_err(err)?;
+    config.constellation.check().map_err(err)?;
+    config.tessellation.check().map_err(err)?;
+    config.hulls.check().map_err(err)?;
+    config.classes.check().map_err(err)?;
+    config.span.check().map_err(err)?;
+    config.layout.check().map_err(err)?;
+    config.framing.check().map_err(err)?;
+    config.feedback.check().map_err(err)?;
+    config
+        .presets
+        .check(&config.simulation.palettes)
+        .map_err(err)?;
+    config.hints.check().map_err(err)?;
+    config.performance.check().map_err(err)?;
+    config.power.check().map_err(err)?;
+    config.export.check().map_err(err)?;
+    Masks::load(&config.layers).map_err(err)?;
+    Rewind::new(config.rewind, config.export, config.transition).map_err(err)?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//! Lines strung between the particles near each other, plexus-style, in a layer of their own over
+//! the trails: each particle is joined to those within `distance` of it, or only the `nearest` few
+//! of them, by a line fading out as the two draw apart, in the colors of the two particles' trails
+//! mixed. The particles are hashed into a grid of cells `distance` across, so only those in the
+//! cells around each are looked at. The layer is hidden until `[layers.constellation]` shows it.
+
+use std::collections::HashMap;
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::layers::LayerConfig;
+
+/// The `[constellation]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConstellationConfig {
+    /// How close two particles come to be joined, in world units.
+    pub distance: f32,
+    /// Most particles each is joined to, the nearest of those close enough, or 0 for all of them.
+    pub nearest: usize,
+    /// Width of the lines, in world units.
+    pub line_width: f32,
+    /// Alpha of the line between two particles right on top of each other, those further apart
+    /// fading out to nothing at `distance`.
+    pub alpha: f32,
+}
+
+impl Default for ConstellationConfig {
+    fn default() -> Self {
+        ConstellationConfig {
+            distance: 300.0,
+            nearest: 3,
+            line_width: 2.0,
+            alpha: 0.6,
+        }
+    }
+}
+
+impl ConstellationConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.distance <= 0.0 {
+            return Err(format!(
+                "the constellation's distance has to be positive, not {}",
+                self.distance
+            ));
+        }
+        if self.line_width <= 0.0 {
+            return Err(format!(
+                "the constellation's line_width has to be positive, not {}",
+                self.line_width
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.alpha) {
+            return Err(format!(
+                "the constellation's alpha goes from 0 to 1, not {}",
+                self.alpha
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draw the lines between those of `particles`, where each is seen and the color of its
+    /// trail, near each other, faded as `layer` fades them.
+    pub fn draw(&self, particles: &[(Point2, Hsla)], layer: &LayerConfig, draw: &Draw) {
+        let positions: Vec<Point2> = particles.iter().map(|&(pos, _)| pos).collect();
+        for (i, j, apart) in self.links(&positions) {
+            let [a, b] = [i, j].map(|k| particles[k].1.into_lin_srgba());
+            let mut color = LinSrgba::new(
+                (a.red + b.red) / 2.0,
+                (a.green + b.green) / 2.0,
+                (a.blue + b.blue) / 2.0,
+                self.alpha * (1.0 - apart / self.distance),
+            );
+            color = layer.fade(color);
+            draw.line()
+                .start(positions[i])
+                .end(positions[j])
+                .weight(self.line_width)
+                .color(color);
+        }
+    }
+
+    /// The pairs of `positions` joined, the lower index first, each once, with how far apart
+    /// they are.
+    fn links(&self, positions: &[Point2]) -> Vec<(usize, usize, f32)> {
+        let cell = |pos: Point2| {
+            let cell = (pos / self.distance).floor();
+            (cell.x as i32, cell.y as i32)
+        };
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, &pos) in positions.iter().enumerate() {
+            if pos.is_finite() {
+                grid.entry(cell(pos)).or_default().push(i);
+            }
+        }
+        let mut links = Vec::new();
+        let mut near = Vec::new();
+        for (i, &pos) in positions.iter().enumerate() {
+            if !pos.is_finite() {
+                continue;
+            }
+            let (x, y) = cell(pos);
+            near.clear();
+            for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                let Some(cell) = grid.get(&(x.saturating_add(dx), y.saturating_add(dy))) else {
+                    continue;
+                };
+                for &j in cell {
+                    let apart = pos.distance(positions[j]);
+                    if j != i && apart < self.distance {
+                        near.push((j, apart));
+                    }
+                }
+            }
+            if self.nearest > 0 && near.len() > self.nearest {
+                near.select_nth_unstable_by(self.nearest, |a, b| a.1.total_cmp(&b.1));
+                near.truncate(self.nearest);
+            }
+            links.extend(near.iter().map(|&(j, apart)| (i.min(j), i.max(j), apart)));
+        }
+        links.sort_unstable_by_key(|&(i, j, _)| (i, j));
+        links.dedup_by_key(|&mut (i, j, _)| (i, j));
+        links
+    }
+}
@@ -0,0 +1,68 @@
+//! The diagnostics over a run, with `--log-diagnostics <file.csv>`, for plotting afterwards: a
+//! row a second of how long the frames and the physics took, how many particles there were, and
+//! the current generation's temperature and entropy, as the diagnostics overlay gives them.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use orbits_core::physics::Particle;
+use orbits_core::thermo;
+
+use crate::tier::Quality;
+
+/// Wall-clock seconds between one row and the next.
+const EVERY_SECONDS: f32 = 1.0;
+
+pub struct DiagnosticsLog {
+    out: BufWriter<File>,
+    /// Wall-clock seconds since the last row.
+    since: f32,
+}
+
+impl DiagnosticsLog {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let err = |err: std::io::Error| format!("{}: {err}", path.display());
+        let mut out = BufWriter::new(File::create(path).map_err(err)?);
+        writeln!(
+            out,
+            "seconds,time,particles,frame_ms,physics_ms,temperature,entropy"
+        )
+        .map_err(err)?;
+        Ok(DiagnosticsLog {
+            out,
+            since: EVERY_SECONDS,
+        })
+    }
+
+    /// Add a row for the frame `seconds` after launch, `time` simulated seconds in, if one's due
+    /// `wall_seconds` after the last frame.
+    pub fn update(
+        &mut self,
+        seconds: f32,
+        wall_seconds: f32,
+        time: f32,
+        quality: &Quality,
+        particles: &[Particle],
+    ) {
+        self.since += wall_seconds;
+        if self.since < EVERY_SECONDS {
+            return;
+        }
+        self.since = 0.0;
+        let (frame_seconds, physics_seconds) = quality.timings();
+        let row = writeln!(
+            self.out,
+            "{seconds:.2},{time:.2},{},{:.3},{:.3},{:.2},{:.4}",
+            particles.len(),
+            frame_seconds * 1000.0,
+            physics_seconds * 1000.0,
+            thermo::temperature(particles),
+            thermo::entropy(particles)
+        );
+        // flushed every row so a crash doesn't lose the run
+        if let Err(err) = row.and_then(|_| self.out.flush()) {
+            eprintln!("failed to write a row of diagnostics: {err}");
+        }
+    }
+}
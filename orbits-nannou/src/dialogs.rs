@@ -0,0 +1,226 @@
+//! Native file dialogs, for picking what to load and where to save from the control panel rather
+//! than from paths fixed beforehand. Built with the `rfd` feature, each is the platform's own,
+//! through rfd: the XDG desktop portal on Linux, AppKit on macOS and the common dialogs on
+//! Windows. Without it, so a build needs no more system libraries than nannou does, each is opened
+//! through the desktop's own tool for them: zenity, or kdialog should it be missing, on Linux,
+//! AppleScript on macOS and PowerShell on Windows, and if none is installed, picking a file says
+//! so. Either way the dialog runs on a thread of its own while the piece goes on, and what's picked
+//! is taken up once it closes.
+
+#[cfg(not(feature = "rfd"))]
+use std::io::ErrorKind;
+use std::path::PathBuf;
+#[cfg(not(feature = "rfd"))]
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// What a dialog picks a file for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialog {
+    /// A config to start the piece again under.
+    LoadConfig,
+    /// A palette to color the trails from.
+    LoadPalette,
+    /// Where to save a screenshot.
+    SaveScreenshot,
+    /// Where to save the trails as an SVG.
+    SaveSvg,
+    /// The directory to export the clip marked to.
+    ExportClip,
+}
+
+/// Whether a dialog picks a file to read, a file to write, or a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Open,
+    Save,
+    Directory,
+}
+
+impl Dialog {
+    fn title(self) -> &'static str {
+        match self {
+            Dialog::LoadConfig => "Load a config",
+            Dialog::LoadPalette => "Load a palette",
+            Dialog::SaveScreenshot => "Save a screenshot as",
+            Dialog::SaveSvg => "Save the trails as",
+            Dialog::ExportClip => "Export the clip to",
+        }
+    }
+
+    fn kind(self) -> Kind {
+        match self {
+            Dialog::LoadConfig | Dialog::LoadPalette => Kind::Open,
+            Dialog::SaveScreenshot | Dialog::SaveSvg => Kind::Save,
+            Dialog::ExportClip => Kind::Directory,
+        }
+    }
+
+    /// What the files picked from are called, and their extension, if they're of one kind.
+    fn filter(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Dialog::LoadConfig => Some(("Configs", "toml")),
+            Dialog::SaveScreenshot => Some(("PNG images", "png")),
+            Dialog::SaveSvg => Some(("SVG images", "svg")),
+            // palettes are kept as plain text, under whatever extension
+            Dialog::LoadPalette | Dialog::ExportClip => None,
+        }
+    }
+}
+
+/// The path picked with a dialog, or nothing if it was cancelled.
+type Picked = Result<Option<PathBuf>, String>;
+
+/// The dialog open, if there is one.
+#[derive(Default)]
+pub struct Dialogs {
+    open: Option<(Dialog, Receiver<Picked>)>,
+}
+
+impl Dialogs {
+    /// Open `dialog`, unless one is open already.
+    pub fn open(&mut self, dialog: Dialog) {
+        if self.open.is_some() {
+            return;
+        }
+        let (sender, picked) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(ask(dialog));
+        });
+        self.open = Some((dialog, picked));
+    }
+
+    /// The dialog that's closed since the last poll, and the path picked with it, if one was, or
+    /// why none could be.
+    pub fn poll(&mut self) -> Option<Result<(Dialog, PathBuf), String>> {
+        let (dialog, picked) = self.open.as_ref()?;
+        let dialog = *dialog;
+        let picked = match picked.try_recv() {
+            Err(TryRecvError::Empty) => return None,
+            Ok(picked) => picked,
+            Err(TryRecvError::Disconnected) => Err("the file dialog went away".into()),
+        };
+        self.open = None;
+        picked
+            .map(|path| path.map(|path| (dialog, path)))
+            .transpose()
+    }
+}
+
+/// Show `dialog` and wait for it to close, returning the path picked, or nothing if it was
+/// cancelled.
+#[cfg(feature = "rfd")]
+fn ask(dialog: Dialog) -> Picked {
+    let mut picker = rfd::AsyncFileDialog::new().set_title(dialog.title());
+    if let Some((name, extension)) = dialog.filter() {
+        picker = picker.add_filter(name, &[extension]);
+    }
+    // the asynchronous dialogs can be shown from any thread, the synchronous ones not on macOS
+    let picked = futures::executor::block_on(async move {
+        match dialog.kind() {
+            Kind::Open => picker.pick_file().await,
+            Kind::Save => picker.save_file().await,
+            Kind::Directory => picker.pick_folder().await,
+        }
+    });
+    Ok(picked.map(|file| file.path().to_path_buf()))
+}
+
+/// Show `dialog` and wait for it to close, returning the path picked, or nothing if it was
+/// cancelled, or why there's no dialog to show if none of the tools for it are installed.
+#[cfg(not(feature = "rfd"))]
+fn ask(dialog: Dialog) -> Picked {
+    let commands = commands(dialog);
+    for command in &commands {
+        let (program, args) = command.split_first().unwrap();
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            // on to the next tool, should this one not be installed
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("couldn't open a file dialog with {program}: {err}")),
+        };
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        // the tools all exit unsuccessfully, or say nothing, when the dialog's cancelled
+        return Ok((output.status.success() && !path.is_empty()).then(|| PathBuf::from(path)));
+    }
+    let programs: Vec<&str> = commands.iter().map(|command| command[0].as_str()).collect();
+    Err(format!(
+        "there's no file dialog here, as {} isn't installed",
+        programs.join(" or ")
+    ))
+}
+
+/// The commands that show `dialog`, to be tried in turn until one can be started.
+#[cfg(not(feature = "rfd"))]
+fn commands(dialog: Dialog) -> Vec<Vec<String>> {
+    let title = dialog.title();
+    if cfg!(target_os = "macos") {
+        let script = match dialog.kind() {
+            Kind::Open => format!("POSIX path of (choose file with prompt \"{title}\")"),
+            Kind::Save => format!("POSIX path of (choose file name with prompt \"{title}\")"),
+            Kind::Directory => format!("POSIX path of (choose folder with prompt \"{title}\")"),
+        };
+        vec![vec!["osascript".into(), "-e".into(), script]]
+    } else if cfg!(target_os = "windows") {
+        let (class, picked) = match dialog.kind() {
+            Kind::Open => ("OpenFileDialog", "FileName"),
+            Kind::Save => ("SaveFileDialog", "FileName"),
+            Kind::Directory => ("FolderBrowserDialog", "SelectedPath"),
+        };
+        let mut script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $dialog = New-Object System.Windows.Forms.{class}; "
+        );
+        match (dialog.kind(), dialog.filter()) {
+            (Kind::Directory, _) => script += &format!("$dialog.Description = '{title}'; "),
+            (_, filter) => {
+                script += &format!("$dialog.Title = '{title}'; ");
+                if let Some((name, extension)) = filter {
+                    script += &format!("$dialog.Filter = '{name} (*.{extension})|*.{extension}'; ");
+                }
+            }
+        }
+        script += &format!("if ($dialog.ShowDialog() -eq 'OK') {{ $dialog.{picked} }}");
+        vec![vec![
+            "powershell".into(),
+            "-NoProfile".into(),
+            "-Command".into(),
+            script,
+        ]]
+    } else {
+        let mut zenity = vec![
+            "zenity".into(),
+            "--file-selection".into(),
+            format!("--title={title}"),
+        ];
+        let kdialog_filter = match dialog.filter() {
+            Some((name, extension)) => {
+                zenity.push(format!("--file-filter={name} | *.{extension}"));
+                format!("{name} (*.{extension})")
+            }
+            None => String::new(),
+        };
+        let kdialog = match dialog.kind() {
+            Kind::Open => vec!["--getopenfilename".into(), ".".into(), kdialog_filter],
+            Kind::Save => {
+                zenity.extend(["--save".into(), "--confirm-overwrite".into()]);
+                vec!["--getsavefilename".into(), ".".into(), kdialog_filter]
+            }
+            Kind::Directory => {
+                zenity.push("--directory".into());
+                vec!["--getexistingdirectory".into(), ".".into()]
+            }
+        };
+        let kdialog = ["kdialog".into(), "--title".into(), title.into()]
+            .into_iter()
+            .chain(kdialog)
+            .collect();
+        vec![zenity, kdialog]
+    }
+}
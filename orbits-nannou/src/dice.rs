@@ -0,0 +1,124 @@
+//! Rolling the dice for a new look: the parameters shaping the swarm's pull and how its trails are
+//! drawn each picked at random within a range that keeps the piece looking like itself, along
+//! with a palette, a trail style and a reseed.
+
+use std::collections::BTreeMap;
+
+use nannou::rand::Rng;
+use orbits_core::params::Param;
+use orbits_core::session::Change;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::render::TrailStyle;
+
+/// The parameters a roll picks, and the range each is picked within. The time scale, particle
+/// count and exposure gain are left be, being more about how the piece is watched than how it
+/// looks.
+const CONSTRAINTS: [(Param, [f32; 2]); 8] = [
+    (Param::Gravity, [0.6, 1.8]),
+    (Param::ForceExponent, [1.0, 2.5]),
+    (Param::VaryVelocity, [40.0, 200.0]),
+    (Param::TrailLength, [0.4, 1.0]),
+    (Param::ColorJitter, [0.0, 0.02]),
+    (Param::BackgroundHue, [0.0, 1.0]),
+    (Param::TrailOpacity, [0.6, 1.4]),
+    (Param::Highlight, [0.2, 0.8]),
+];
+
+/// The `[dice]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiceConfig {
+    /// Ranges to pick parameters within instead of the usual ones, adding any parameter not
+    /// usually picked, as `param = [least, most]`.
+    pub ranges: BTreeMap<Param, [f32; 2]>,
+    /// Whether a roll also reseeds the swarm, crossfading to the new generation.
+    pub reseed: bool,
+}
+
+/// What a roll picked: the changes to apply, and the style to draw the trails in, which being
+/// how this window draws them rather than anything simulated isn't a change.
+pub struct Roll {
+    pub changes: Vec<Change>,
+    pub trail_style: TrailStyle,
+}
+
+impl Default for DiceConfig {
+    fn default() -> Self {
+        DiceConfig {
+            ranges: BTreeMap::new(),
+            reseed: true,
+        }
+    }
+}
+
+impl DiceConfig {
+    /// A roll drawn from `rng`, the palette picked from those called `palettes`.
+    pub fn roll(&self, palettes: &[String], rng: &mut impl Rng) -> Roll {
+        let mut ranges: BTreeMap<Param, [f32; 2]> = CONSTRAINTS.into_iter().collect();
+        ranges.extend(&self.ranges);
+        let mut changes: Vec<Change> = ranges
+            .into_iter()
+            .map(|(param, [least, most])| Change::Set {
+                param,
+                value: least + rng.gen::<f32>() * (most - least),
+            })
+            .collect();
+        if self.reseed {
+            changes.push(Change::Reseed { seed: rng.gen() });
+        }
+        // after any reseed, so the generation fading out keeps its colors
+        if !palettes.is_empty() {
+            let name = palettes[rng.gen_range(0..palettes.len())].clone();
+            changes.push(Change::Palette { name });
+        }
+        let trail_style = TrailStyle::ALL[rng.gen_range(0..TrailStyle::ALL.len())];
+        Roll {
+            changes,
+            trail_style,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nannou::rand::rngs::StdRng;
+    use nannou::rand::SeedableRng;
+    use orbits_core::palette;
+
+    use super::*;
+
+    #[test]
+    fn every_roll_picks_something_new() {
+        let palettes = palette::names(&BTreeMap::new());
+        let dice = DiceConfig::default();
+        let mut rng = StdRng::seed_from_u64(0);
+        let rolls: Vec<Roll> = (0..16).map(|_| dice.roll(&palettes, &mut rng)).collect();
+        let first = &rolls[0];
+        for (param, [least, most]) in CONSTRAINTS {
+            let values: Vec<f32> = (rolls.iter())
+                .map(|roll| {
+                    roll.changes.iter().find_map(|change| match change {
+                        Change::Set { param: set, value } if *set == param => Some(*value),
+                        _ => None,
+                    })
+                })
+                .map(|value| value.unwrap_or_else(|| panic!("{param:?} isn't rolled")))
+                .collect();
+            assert!(values.iter().all(|value| (least..=most).contains(value)));
+            assert!(values.iter().any(|value| *value != values[0]), "{param:?}");
+        }
+        let palette = |roll: &Roll| {
+            roll.changes.iter().find_map(|change| match change {
+                Change::Palette { name } => Some(name.clone()),
+                _ => None,
+            })
+        };
+        assert!(palette(first).is_some());
+        assert!(rolls.iter().any(|roll| palette(roll) != palette(first)));
+        assert!(rolls
+            .iter()
+            .any(|roll| roll.trail_style != first.trail_style));
+    }
+}
@@ -0,0 +1,52 @@
+//! What a file dropped onto the window is taken as, going by its extension: a config to start the
+//! piece again under, a palette to color the trails from, or an image whose colors, from its left
+//! edge to its right, make a palette of their own.
+
+use std::path::{Path, PathBuf};
+
+use nannou::image;
+use orbits_core::palette;
+
+/// How many stops a palette taken from an image has, each the mean of a strip of it.
+const IMAGE_STOPS: u32 = 8;
+
+/// A file dropped onto the window, as it's to be taken.
+pub enum Dropped {
+    Config(PathBuf),
+    Palette { name: String, stops: Vec<String> },
+}
+
+/// `path`, as it's to be taken, or why it can't be.
+pub fn open(path: &Path) -> Result<Dropped, String> {
+    let extension = (path.extension())
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "toml" => Ok(Dropped::Config(path.to_path_buf())),
+        "hex" | "txt" => {
+            let (name, stops) = palette::load(path)?;
+            Ok(Dropped::Palette { name, stops })
+        }
+        "png" | "jpg" | "jpeg" => {
+            let (name, stops) = image_palette(path)?;
+            Ok(Dropped::Palette { name, stops })
+        }
+        _ => Err(format!(
+            "{} isn't a config, a palette or an image",
+            path.display()
+        )),
+    }
+}
+
+/// The palette of the image at `path`, the mean colors of strips of it from left to right, named
+/// after the file.
+fn image_palette(path: &Path) -> Result<(String, Vec<String>), String> {
+    let image = image::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let strips = image.thumbnail_exact(IMAGE_STOPS, 1).to_rgb8();
+    let stops = (strips.pixels())
+        .map(|pixel| format!("#{:02x}{:02x}{:02x}", pixel[0], pixel[1], pixel[2]))
+        .collect();
+    let name =
+        (path.file_stem()).map_or("image".into(), |stem| stem.to_string_lossy().into_owned());
+    Ok((name, stops))
+}
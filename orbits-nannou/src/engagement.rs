@@ -0,0 +1,256 @@
+//! How visitors play with an installation, with `--engagement <dir>`, for exhibitors to see how
+//! the piece is taken to: every touch, key and scroll is counted, along with each change to the
+//! piece made here and where on the window it was touched, in each hour of the day. Visits are
+//! told apart by the piece being left alone in between them for as long as the hints wait, each
+//! lasting from its first touch to its last. A day's counts are kept in `engagement-<date>.json`,
+//! rewritten every minute and on quitting and picked up again if the piece is restarted that day,
+//! and a row for each visit is added to `engagement-<date>.csv` as it ends. Days and hours are
+//! in UTC.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nannou::prelude::*;
+use orbits_core::session::Change;
+use serde::{Deserialize, Serialize};
+
+use crate::export;
+
+/// Wall-clock seconds between one writing of the day's counts and the next.
+const WRITE_SECONDS: f32 = 60.0;
+/// Cells across and down the grid of where the window's touched.
+const HEAT_CELLS: usize = 8;
+
+/// A day's counts, as written to its JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Day {
+    date: String,
+    touches: u32,
+    keys: u32,
+    scrolls: u32,
+    /// Changes made to the piece, by kind.
+    changes: BTreeMap<String, u32>,
+    /// Parameters set by hand, by name.
+    params: BTreeMap<String, u32>,
+    visits: u32,
+    /// Seconds all the visits lasted, between them.
+    dwell_seconds: f32,
+    longest_visit_seconds: f32,
+    /// Touches in each hour of the day.
+    hours: [u32; 24],
+    /// Touches on each cell of a grid over the window, a row at a time from the top.
+    heat: Vec<Vec<u32>>,
+}
+
+/// The visit going on.
+struct Visit {
+    /// When it started, in seconds since the epoch.
+    started: u64,
+    /// Wall-clock seconds since it started.
+    seconds: f32,
+    touches: u32,
+    changes: u32,
+}
+
+/// The counts of the day so far, and of the visit going on.
+pub struct Engagement {
+    dir: PathBuf,
+    /// Wall-clock seconds the piece is left alone for a visit to be over.
+    gap: f32,
+    day: Day,
+    visit: Option<Visit>,
+    /// Wall-clock seconds since the piece was last played with.
+    idle: f32,
+    /// Wall-clock seconds since the day's counts were last written.
+    unwritten: f32,
+}
+
+impl Engagement {
+    /// Count how the piece is played with into reports in `dir`, a visit being over once it's
+    /// been left alone for `gap` seconds, carrying on with today's counts if there are any.
+    pub fn create(dir: &Path, gap: f32) -> Result<Self, String> {
+        export::create_dir(dir)?;
+        let mut engagement = Engagement {
+            dir: dir.to_owned(),
+            gap,
+            day: Day::default(),
+            visit: None,
+            idle: 0.0,
+            unwritten: 0.0,
+        };
+        engagement.day = engagement.load(&today().0)?;
+        Ok(engagement)
+    }
+
+    /// The counts of `date` written before, or none if there aren't any.
+    fn load(&self, date: &str) -> Result<Day, String> {
+        let path = self.path(date, "json");
+        let day = match fs::read_to_string(&path) {
+            Ok(text) => {
+                serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?
+            }
+            Err(_) => Day {
+                date: date.into(),
+                heat: vec![vec![0; HEAT_CELLS]; HEAT_CELLS],
+                ..Day::default()
+            },
+        };
+        Ok(day)
+    }
+
+    fn path(&self, date: &str, extension: &str) -> PathBuf {
+        self.dir.join(format!("engagement-{date}.{extension}"))
+    }
+
+    /// Note the piece being played with, starting a visit if it's not already being visited.
+    pub fn interacted(&mut self) {
+        self.idle = 0.0;
+        self.visit.get_or_insert_with(|| Visit {
+            started: now(),
+            seconds: 0.0,
+            touches: 0,
+            changes: 0,
+        });
+    }
+
+    /// Count a touch or click at `pos` of `win`.
+    pub fn touch(&mut self, pos: Point2, win: Rect) {
+        self.interacted();
+        self.day.touches += 1;
+        self.day.hours[today().1] += 1;
+        let cell = |fraction: f32| ((fraction * HEAT_CELLS as f32) as usize).min(HEAT_CELLS - 1);
+        let x = cell((pos.x - win.left()) / win.w());
+        let y = cell((win.top() - pos.y) / win.h());
+        self.day.heat[y][x] += 1;
+        if let Some(visit) = &mut self.visit {
+            visit.touches += 1;
+        }
+    }
+
+    pub fn key(&mut self) {
+        self.interacted();
+        self.day.keys += 1;
+    }
+
+    pub fn scroll(&mut self) {
+        self.interacted();
+        self.day.scrolls += 1;
+    }
+
+    /// Count `change`, made to the piece here.
+    pub fn change(&mut self, change: &Change) {
+        // as a session log names them
+        let name = |value: serde_json::Value| value.as_str().unwrap_or_default().to_owned();
+        let kind = name(serde_json::to_value(change).unwrap()["change"].take());
+        *self.day.changes.entry(kind).or_default() += 1;
+        if let Change::Set { param, .. } = change {
+            let param = name(serde_json::to_value(param).unwrap());
+            *self.day.params.entry(param).or_default() += 1;
+        }
+        if let Some(visit) = &mut self.visit {
+            visit.changes += 1;
+        }
+    }
+
+    /// Count `wall_seconds` more going by, ending the visit if the piece has been left alone long
+    /// enough, starting a new day's counts at midnight, and writing the day's counts if it's
+    /// time to.
+    pub fn update(&mut self, wall_seconds: f32) -> Result<(), String> {
+        self.idle += wall_seconds;
+        self.unwritten += wall_seconds;
+        if let Some(visit) = &mut self.visit {
+            visit.seconds += wall_seconds;
+        }
+        if self.idle >= self.gap {
+            self.end_visit()?;
+        }
+        let date = today().0;
+        if date != self.day.date {
+            self.end_visit()?;
+            self.write()?;
+            self.day = self.load(&date)?;
+        }
+        if self.unwritten >= WRITE_SECONDS {
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// End the visit going on, if one is, counting it and adding its row.
+    fn end_visit(&mut self) -> Result<(), String> {
+        let Some(visit) = self.visit.take() else {
+            return Ok(());
+        };
+        // it lasted until it was last played with
+        let seconds = (visit.seconds - self.idle).max(0.0);
+        self.day.visits += 1;
+        self.day.dwell_seconds += seconds;
+        self.day.longest_visit_seconds = self.day.longest_visit_seconds.max(seconds);
+        let path = self.path(&self.day.date, "csv");
+        let err = |err: std::io::Error| format!("{}: {err}", path.display());
+        let new = !path.exists();
+        let mut file = (OpenOptions::new().create(true).append(true))
+            .open(&path)
+            .map_err(err)?;
+        if new {
+            writeln!(file, "started,seconds,touches,changes").map_err(err)?;
+        }
+        let started = visit.started % 86_400;
+        writeln!(
+            file,
+            "{:02}:{:02}:{:02},{seconds:.1},{},{}",
+            started / 3600,
+            started / 60 % 60,
+            started % 60,
+            visit.touches,
+            visit.changes
+        )
+        .map_err(err)
+    }
+
+    /// Write the day's counts.
+    fn write(&mut self) -> Result<(), String> {
+        self.unwritten = 0.0;
+        let path = self.path(&self.day.date, "json");
+        let text = serde_json::to_string_pretty(&self.day).unwrap();
+        fs::write(&path, text).map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    /// End the visit going on and write the day's counts, on quitting.
+    pub fn finish(&mut self) -> Result<(), String> {
+        self.end_visit()?;
+        self.write()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Today's date, as `yyyy-mm-dd`, and the hour it is.
+fn today() -> (String, usize) {
+    let seconds = now();
+    // the civil date of a count of days since the epoch, after Howard Hinnant's `civil_from_days`
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    let hour = (seconds % 86_400 / 3600) as usize;
+    (format!("{year:04}-{month:02}-{day:02}"), hour)
+}
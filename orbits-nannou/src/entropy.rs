@@ -0,0 +1,108 @@
+//! Seeds drawn from outside the piece rather than picked at random, as generative work is often
+//! seeded from a phrase, a file or the world around it: `--seed text:<words>` hashes the words,
+//! `--seed file:<path>` the bytes of the file, and `--seed weather:<lat>,<lon>` the current weather
+//! there, as Open-Meteo reports it. The same words or bytes always give the same seed. The weather
+//! changes, so what it was when the seed was drawn is said along with the seed, to be looked up
+//! again by anyone wanting to know where it came from.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const WEATHER_HOST: &str = "api.open-meteo.com";
+const WEATHER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A seed, and where it was drawn from if it wasn't given as a number.
+pub struct Drawn {
+    pub seed: u64,
+    pub source: Option<String>,
+}
+
+/// The seed `text` gives, as a number or drawn from a source, or why it can't be drawn.
+pub fn draw(text: &str) -> Result<Drawn, String> {
+    let (bytes, source) = match text.split_once(':') {
+        Some(("text", words)) => (words.as_bytes().to_vec(), format!("the text {words:?}")),
+        Some(("file", path)) => {
+            let bytes = fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+            (bytes, format!("the file {path}"))
+        }
+        Some(("weather", place)) => {
+            let weather = weather(place)?;
+            (weather.clone().into_bytes(), weather)
+        }
+        Some((kind, _)) => {
+            return Err(format!(
+                "there's no seed source {kind:?}, only text, file and weather"
+            ))
+        }
+        None => {
+            let seed = text
+                .parse()
+                .map_err(|err| format!("bad seed {text:?}: {err}"))?;
+            return Ok(Drawn { seed, source: None });
+        }
+    };
+    Ok(Drawn {
+        seed: hash(&bytes),
+        source: Some(source),
+    })
+}
+
+/// The current weather at `place`, given as `<lat>,<lon>`, in words to be hashed.
+fn weather(place: &str) -> Result<String, String> {
+    let (lat, lon) = place
+        .split_once(',')
+        .and_then(|(lat, lon)| {
+            Some((
+                lat.trim().parse::<f64>().ok()?,
+                lon.trim().parse::<f64>().ok()?,
+            ))
+        })
+        .ok_or_else(|| format!("expected weather:<lat>,<lon>, got weather:{place}"))?;
+    let addr = (WEATHER_HOST, 80)
+        .to_socket_addrs()
+        .map_err(|err| format!("couldn't look up {WEATHER_HOST}: {err}"))?
+        .next()
+        .ok_or_else(|| format!("{WEATHER_HOST} has no address"))?;
+    let fetch = || -> std::io::Result<String> {
+        let mut stream = TcpStream::connect_timeout(&addr, WEATHER_TIMEOUT)?;
+        stream.set_read_timeout(Some(WEATHER_TIMEOUT))?;
+        // HTTP/1.0, so the answer isn't chunked
+        write!(
+            stream,
+            "GET /v1/forecast?latitude={lat}&longitude={lon}&current_weather=true HTTP/1.0\r\n\
+             Host: {WEATHER_HOST}\r\n\r\n"
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    };
+    let response = fetch().map_err(|err| format!("couldn't ask {WEATHER_HOST}: {err}"))?;
+    let body = response.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+    let json: serde_json::Value = serde_json::from_str(body)
+        .map_err(|err| format!("{WEATHER_HOST} didn't answer with the weather: {err}"))?;
+    let current = &json["current_weather"];
+    let reading = |name: &str| current[name].to_string();
+    if current.is_null() {
+        return Err(format!(
+            "{WEATHER_HOST} answered without the weather: {body}"
+        ));
+    }
+    Ok(format!(
+        "the weather at {lat},{lon} at {}: {}°C, wind {} km/h from {}°, code {}",
+        current["time"].as_str().unwrap_or_default(),
+        reading("temperature"),
+        reading("windspeed"),
+        reading("winddirection"),
+        reading("weathercode"),
+    ))
+}
+
+/// The 64-bit FNV-1a hash of `bytes`, which unlike the standard library's stays the same from one
+/// build to the next.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
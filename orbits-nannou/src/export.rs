@@ -0,0 +1,787 @@
+//! Writes every frame of the piece to a numbered image sequence, rendered offscreen without any of
+//! the overlays. Frames of the sequence are read back a few frames behind, through staging buffers
+//! the GPU copies into while the render loop goes on, and handed still mapped to a bounded queue,
+//! which a pool of worker threads encodes and writes from, so the render loop only ever waits on
+//! the GPU once every staging buffer is in flight, and on the workers once the queue is full. A
+//! sequence can be deflickered on its way to the queue, each pixel's luminance held to within a
+//! step of what it was the frame before, which has to be done a frame at a time in order, and so
+//! on the render loop.
+
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use exr::math::Vec2 as Pixel;
+use exr::prelude::{
+    f16, AnyChannel, AnyChannels, Encoding, FlatSamples, Image, LayerAttributes, SmallVec,
+    SpecificChannels, WritableImage,
+};
+use nannou::image;
+use nannou::prelude::*;
+use nannou::wgpu;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::gpu::{self, Mapped, Readback, Scene, Target};
+use crate::manifest::{self, Manifest};
+use crate::passes::{Pass, Passes};
+use crate::smear::Smear;
+use crate::style::Style;
+use crate::transition::{Transition, TransitionConfig};
+
+/// How much precision each channel of an EXR is stored with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    /// 16 bit floats, which is all the precision the scene is rendered with.
+    Half,
+    /// 32 bit floats.
+    #[default]
+    Full,
+}
+
+/// How an EXR is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    None,
+    /// Lossless zip compression.
+    #[default]
+    Zip,
+}
+
+/// The kind of image each frame is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Linear float OpenEXR.
+    #[default]
+    Exr,
+    /// 8 bit sRGB PNG.
+    Png,
+}
+
+/// How transparent pixels' colors are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Alpha {
+    /// Colors are multiplied by their alpha, as compositors expect of OpenEXR.
+    #[default]
+    Premultiplied,
+    /// Colors are kept at full strength, as most tools expect of PNG.
+    Straight,
+}
+
+/// What recording a frame does when the queue of those waiting to be written is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    /// Wait for a worker to catch up, holding the render loop up, so no frame is lost.
+    #[default]
+    Wait,
+    /// Leave the frame out of the sequence, keeping the render loop smooth, as for a run
+    /// recorded live.
+    Drop,
+}
+
+/// The `[export]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    pub format: Format,
+    /// Draw the trails over nothing, instead of over the background.
+    pub transparent: bool,
+    pub alpha: Alpha,
+    /// Only for OpenEXR.
+    pub precision: Precision,
+    /// Only for OpenEXR.
+    pub compression: Compression,
+    /// Frames to each simulated second of a `--record`, however fast they're rendered.
+    pub fps: f32,
+    /// Frames that can be waiting to be written before the queue is full.
+    pub queue: usize,
+    /// Threads encoding and writing frames at once.
+    pub workers: usize,
+    pub backpressure: Backpressure,
+    /// Frames of the sequence being read back from the GPU at once, each in a staging buffer of
+    /// its own, before the oldest is waited on: 1 to wait on each as it's rendered, 2 or 3 for
+    /// double or triple buffering.
+    pub readback: usize,
+    /// The auxiliary passes written along with each frame of a sequence, as channels of its
+    /// OpenEXR of their own.
+    pub passes: Passes,
+    /// Most each pixel's luminance can change by from one frame of a sequence to the next, in
+    /// the scene's linear units, to steady the flicker the trails' jittered colors can leave in
+    /// encoded video, or 0 for none. The lower it is the steadier, but the more anything moving
+    /// fast drags.
+    pub deflicker: f32,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            format: Format::default(),
+            transparent: false,
+            alpha: Alpha::default(),
+            precision: Precision::default(),
+            compression: Compression::default(),
+            fps: 60.0,
+            queue: 8,
+            workers: 2,
+            backpressure: Backpressure::default(),
+            readback: 3,
+            passes: Passes::default(),
+            deflicker: 0.0,
+        }
+    }
+}
+
+impl ExportConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.queue == 0 {
+            return Err("the export's queue has to have room for a frame".into());
+        }
+        if self.workers == 0 {
+            return Err("the export needs at least one worker".into());
+        }
+        if self.readback == 0 {
+            return Err("the export has to read back at least one frame at a time".into());
+        }
+        if !self.passes.on().is_empty() && self.format != Format::Exr {
+            return Err("the export's passes are only written to OpenEXR".into());
+        }
+        if self.deflicker < 0.0 {
+            return Err(format!(
+                "the export's deflicker can't be negative, not {}",
+                self.deflicker
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The part of the scene to export, as a rectangle centered on its middle.
+#[derive(Debug, Clone, Copy)]
+pub enum Crop {
+    /// In pixels from the middle of the window.
+    Pixels(Rect),
+    /// In world units.
+    World(Rect),
+}
+
+/// What a frame shows: one scene, or one scene giving way to the next. The drawings are in
+/// pixels, centered on the frame.
+pub enum Shot {
+    Still(Draw),
+    Transition {
+        outgoing: Draw,
+        incoming: Draw,
+        progress: f32,
+    },
+}
+
+/// A frame downloaded from the GPU.
+#[derive(Clone)]
+pub struct Captured {
+    size: [u32; 2],
+    texels: Texels,
+    /// The auxiliary passes of the frame.
+    passes: Vec<(Pass, Captured)>,
+}
+
+/// Rows of `Frame::TEXTURE_FORMAT` texels, top first.
+#[derive(Clone)]
+enum Texels {
+    Copied(Arc<[u8]>),
+    /// Still in the staging buffer they were read back into.
+    Mapped(Arc<Mapped>),
+    /// Decoded into linear, premultiplied pixels already, as a deflickered frame is.
+    Pixels(Arc<[[f32; 4]]>),
+}
+
+impl Captured {
+    fn mapped(mapped: Mapped) -> Self {
+        Captured {
+            size: mapped.size(),
+            texels: Texels::Mapped(Arc::new(mapped)),
+            passes: Vec::new(),
+        }
+    }
+
+    /// The frame's linear, premultiplied pixels, in rows from the top.
+    pub fn pixels(&self) -> Vec<[f32; 4]> {
+        let [width, height] = self.size;
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        let mut push = |row: &[u8]| {
+            pixels.extend(row.chunks_exact(8).map(|texel| {
+                [0, 2, 4, 6]
+                    .map(|c| f16::from_bits(u16::from_ne_bytes([texel[c], texel[c + 1]])).to_f32())
+            }))
+        };
+        match &self.texels {
+            Texels::Copied(texels) => push(texels),
+            Texels::Mapped(mapped) => mapped.rows(push),
+            Texels::Pixels(decoded) => pixels.extend_from_slice(decoded),
+        }
+        pixels
+    }
+}
+
+/// Renders shots offscreen and reads them back, keeping the texture it renders to between shots
+/// of the same size.
+pub struct Offscreen {
+    scene: RefCell<Option<Scene>>,
+    transition: Transition,
+    style: Style,
+}
+
+impl Offscreen {
+    /// Render shots giving way to each other as `transition` asks, each drawn through `style`.
+    pub fn new(transition: TransitionConfig, style: Style) -> Self {
+        Offscreen {
+            scene: RefCell::new(None),
+            transition: Transition::new(transition),
+            style,
+        }
+    }
+
+    /// Render `shot` at `size` pixels and read it back.
+    pub fn capture(
+        &self,
+        device_queue: &Arc<wgpu::DeviceQueuePair>,
+        size: [u32; 2],
+        shot: Shot,
+    ) -> Option<Captured> {
+        let scene = self.render(device_queue, size, shot);
+        match gpu::download(device_queue, &scene.texture) {
+            Ok(texels) => Some(Captured {
+                size,
+                texels: Texels::Copied(texels.into()),
+                passes: Vec::new(),
+            }),
+            Err(err) => {
+                eprintln!("failed to read back a frame: {err}");
+                None
+            }
+        }
+    }
+
+    /// Render `shot` at `size` pixels, returning the scene it's rendered to.
+    fn render(
+        &self,
+        device_queue: &Arc<wgpu::DeviceQueuePair>,
+        size: [u32; 2],
+        shot: Shot,
+    ) -> RefMut<'_, Scene> {
+        let device = device_queue.device();
+        let mut scene = self.scene.borrow_mut();
+        if scene
+            .as_ref()
+            .is_none_or(|scene| scene.texture.size() != size || !scene.is_current())
+        {
+            *scene = Some(Scene::new(device, size));
+        }
+        let mut scene = RefMut::map(scene, |scene| scene.as_mut().unwrap());
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("offscreen"),
+        });
+        let view = scene.texture.view().build();
+        let mut target = Target {
+            device_queue,
+            encoder: &mut encoder,
+            view: &view,
+            size,
+            msaa_samples: 1,
+        };
+        let mut draw_shot = |target: &mut Target, scene: &mut Scene| match &shot {
+            Shot::Still(draw) => scene.render(target.device_queue.device(), target.encoder, draw),
+            Shot::Transition {
+                outgoing,
+                incoming,
+                progress,
+            } => self.transition.draw(target, outgoing, incoming, *progress),
+        };
+        let posts = self.style.posts();
+        if posts.is_empty() {
+            draw_shot(&mut target, &mut scene);
+        } else {
+            gpu::draw_posted(&posts, &mut target, &mut draw_shot);
+        }
+        device_queue.queue().submit(Some(encoder.finish()));
+        scene
+    }
+}
+
+/// A frame waiting to be written.
+struct Job {
+    path: PathBuf,
+    frame: Captured,
+    /// Written beside the frame once it is, for a still.
+    manifest: Option<Manifest>,
+}
+
+/// Renders frames offscreen and writes them out as numbered images, either every frame to `dir`
+/// or just the ones asked for.
+pub struct Export {
+    dir: Option<PathBuf>,
+    crop: Option<Crop>,
+    config: ExportConfig,
+    frames: Cell<u64>,
+    /// Frames of the sequence left out for the queue being full.
+    dropped: Cell<u64>,
+    /// The luminance of each pixel of the last frame of the sequence written, as it was
+    /// deflickered, for the next to be held to.
+    luminance: RefCell<Vec<f32>>,
+    /// The frames queued to each directory, for the manifest written in it once they're done.
+    written: RefCell<BTreeMap<PathBuf, Vec<PathBuf>>>,
+    offscreen: Offscreen,
+    /// Renders the auxiliary passes, through none of the post passes.
+    pass_offscreen: Offscreen,
+    /// The auxiliary passes of each frame still being read back, oldest first.
+    pending_passes: RefCell<VecDeque<Vec<(Pass, Captured)>>>,
+    /// Reads back the frames of the sequence.
+    readback: Readback,
+    writer: SyncSender<Job>,
+    /// Frames queued and not yet written, those being written included.
+    queued: Arc<AtomicUsize>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Export {
+    pub fn new(
+        dir: Option<PathBuf>,
+        crop: Option<Crop>,
+        config: ExportConfig,
+        transition: TransitionConfig,
+        style: Style,
+    ) -> Result<Self, String> {
+        config.check()?;
+        if let Some(dir) = &dir {
+            create_dir(dir)?;
+        }
+        let (writer, jobs) = mpsc::sync_channel::<Job>(config.queue);
+        let jobs = Arc::new(Mutex::new(jobs));
+        let queued = Arc::new(AtomicUsize::new(0));
+        let workers = (0..config.workers)
+            .map(|_| {
+                let (jobs, queued) = (Arc::clone(&jobs), Arc::clone(&queued));
+                thread::spawn(move || loop {
+                    // the lock is only held waiting for the next frame, the rest writing meanwhile
+                    let job = jobs.lock().unwrap().recv();
+                    let Ok(job) = job else {
+                        break;
+                    };
+                    match write(&job, config) {
+                        Ok(()) => {
+                            let written = job
+                                .manifest
+                                .as_ref()
+                                .map(|manifest| manifest.write_beside(&job.path));
+                            if let Some(Err(err)) = written {
+                                eprintln!("failed to write a manifest: {err}");
+                            }
+                        }
+                        Err(err) => eprintln!("failed to export a frame: {err}"),
+                    }
+                    queued.fetch_sub(1, Ordering::Relaxed);
+                })
+            })
+            .collect();
+        Ok(Export {
+            dir,
+            crop,
+            config,
+            frames: Cell::new(0),
+            dropped: Cell::new(0),
+            luminance: RefCell::new(Vec::new()),
+            written: RefCell::new(BTreeMap::new()),
+            offscreen: Offscreen::new(transition, style),
+            pass_offscreen: Offscreen::new(TransitionConfig::default(), Style::default()),
+            pending_passes: RefCell::new(VecDeque::new()),
+            readback: Readback::new(config.readback),
+            writer,
+            queued,
+            workers,
+        })
+    }
+
+    pub fn crop(&self) -> Option<Crop> {
+        self.crop
+    }
+
+    /// Whether shots should leave out the background.
+    pub fn transparent(&self) -> bool {
+        self.config.transparent
+    }
+
+    /// The auxiliary passes written along with each frame of the sequence.
+    pub fn passes(&self) -> Vec<Pass> {
+        self.config.passes.on()
+    }
+
+    /// The smear frames are drawn through, to splat the velocities of each onto.
+    pub fn smear(&self) -> Option<&Smear> {
+        self.offscreen.style.smear.as_ref()
+    }
+
+    /// Render `shot` at `size` pixels and read it back.
+    pub fn capture(
+        &self,
+        device_queue: &Arc<wgpu::DeviceQueuePair>,
+        size: [u32; 2],
+        shot: Shot,
+    ) -> Option<Captured> {
+        self.offscreen.capture(device_queue, size, shot)
+    }
+
+    /// Render `shot` at `size` pixels and start reading it back, along with its auxiliary
+    /// `passes`, returning the frames started before it that have been read back since, in order.
+    /// The passes are read back as they're rendered.
+    pub fn stream(
+        &self,
+        device_queue: &Arc<wgpu::DeviceQueuePair>,
+        size: [u32; 2],
+        shot: Shot,
+        passes: Vec<(Pass, Draw)>,
+    ) -> Vec<Captured> {
+        let passes = (passes.into_iter())
+            .filter_map(|(pass, draw)| {
+                let captured = self
+                    .pass_offscreen
+                    .capture(device_queue, size, Shot::Still(draw));
+                Some((pass, captured?))
+            })
+            .collect();
+        self.pending_passes.borrow_mut().push_back(passes);
+        let scene = self.offscreen.render(device_queue, size, shot);
+        let settled = self.readback.start(device_queue, &scene.texture);
+        self.settle_all(settled)
+    }
+
+    /// The frames read back as `settled`, each with its auxiliary passes, leaving out those that failed.
+    fn settle_all(&self, settled: Vec<Result<Mapped, String>>) -> Vec<Captured> {
+        let mut pending = self.pending_passes.borrow_mut();
+        (settled.into_iter())
+            .filter_map(|mapped| {
+                let passes = pending.pop_front().unwrap_or_default();
+                let mut frame = settle(mapped)?;
+                frame.passes = passes;
+                Some(frame)
+            })
+            .collect()
+    }
+
+    /// How many frames streamed are still being read back.
+    pub fn reading(&self) -> usize {
+        self.readback.in_flight()
+    }
+
+    /// How many frames of the sequence have been queued so far.
+    pub fn frames(&self) -> u64 {
+        self.frames.get()
+    }
+
+    /// Whether every frame is recorded, as a sequence, rather than just the ones saved.
+    pub fn is_sequence(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// How many frames are waiting to be written or being written, and how many can be before
+    /// the queue is full.
+    pub fn queued(&self) -> (usize, usize) {
+        let queued = self.queued.load(Ordering::Relaxed);
+        (queued, self.config.queue + self.config.workers)
+    }
+
+    /// How many frames of the sequence were left out for the queue being full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    /// Queue `frame` to be written as the next of the sequence in `dir`, if there is one, unless
+    /// the queue is full and it's to be dropped then, deflickered first if the config asks.
+    pub fn record(&self, frame: &Captured) {
+        if let Some(dir) = &self.dir {
+            let index = self.frames.get();
+            let path = self.path(&dir.join(format!("frame-{index:06}")));
+            let (frame, luminance) = match self.config.deflicker > 0.0 {
+                true => {
+                    let mut pixels = frame.pixels();
+                    let luminance = deflicker(
+                        &mut pixels,
+                        &self.luminance.borrow(),
+                        self.config.deflicker,
+                        self.config.transparent,
+                    );
+                    let frame = Captured {
+                        size: frame.size,
+                        texels: Texels::Pixels(pixels.into()),
+                        passes: frame.passes.clone(),
+                    };
+                    (frame, Some(luminance))
+                }
+                false => (frame.clone(), None),
+            };
+            if self.send(path.clone(), frame, self.config.backpressure, None) {
+                self.note(dir, path);
+                self.frames.set(index + 1);
+                if let Some(luminance) = luminance {
+                    *self.luminance.borrow_mut() = luminance;
+                }
+            } else {
+                self.dropped.set(self.dropped.get() + 1);
+            }
+        }
+    }
+
+    /// Queue `frame` to be written as frame `index` of a sequence in `dir`.
+    pub fn save(&self, dir: &Path, index: u64, frame: Captured) {
+        let path = self.path(&dir.join(format!("frame-{index:06}")));
+        if self.send(path.clone(), frame, Backpressure::Wait, None) {
+            self.note(dir, path);
+        }
+    }
+
+    /// Queue `frame` to be written to `path`, with the extension of the format added, waiting
+    /// for room in the queue if need be, and `manifest` beside it once it is.
+    pub fn save_as(&self, path: &Path, frame: Captured, manifest: Option<Manifest>) {
+        self.send(self.path(path), frame, Backpressure::Wait, manifest);
+    }
+
+    /// Note the frame queued to `path`, in `dir`, for the manifest written in it.
+    fn note(&self, dir: &Path, path: PathBuf) {
+        let mut written = self.written.borrow_mut();
+        written.entry(dir.to_path_buf()).or_default().push(path);
+    }
+
+    /// `path` with the extension of the format added.
+    fn path(&self, path: &Path) -> PathBuf {
+        let extension = match self.config.format {
+            Format::Exr => "exr",
+            Format::Png => "png",
+        };
+        path.with_extension(extension)
+    }
+
+    /// Queue `frame` to be written to `path`, as `backpressure` has it should the queue be full,
+    /// returning whether it was.
+    fn send(
+        &self,
+        path: PathBuf,
+        frame: Captured,
+        backpressure: Backpressure,
+        manifest: Option<Manifest>,
+    ) -> bool {
+        let job = Job {
+            path,
+            frame,
+            manifest,
+        };
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        // only fails otherwise once the workers have given up, which they don't
+        let sent = match backpressure {
+            Backpressure::Wait => self.writer.send(job).is_ok(),
+            Backpressure::Drop => !matches!(self.writer.try_send(job), Err(TrySendError::Full(_))),
+        };
+        if !sent {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+        }
+        sent
+    }
+
+    /// Wait for every frame streamed to be read back and recorded, and every captured frame to be
+    /// written, then write `manifest`, if there is one, in each directory frames were written to.
+    pub fn finish(self, manifest: Option<&Manifest>) {
+        let settled = self.readback.finish();
+        for frame in self.settle_all(settled) {
+            self.record(&frame);
+        }
+        drop(self.writer);
+        for worker in self.workers {
+            let _ = worker.join();
+        }
+        let Some(manifest) = manifest else {
+            return;
+        };
+        for (dir, frames) in self.written.into_inner() {
+            if let Err(err) = manifest.write(&manifest::within(&dir), &frames) {
+                eprintln!("failed to write a manifest: {err}");
+            }
+        }
+    }
+}
+
+/// The frame a read back settled as, if it didn't fail.
+fn settle(mapped: Result<Mapped, String>) -> Option<Captured> {
+    mapped
+        .map(Captured::mapped)
+        .map_err(|err| eprintln!("failed to read back a frame: {err}"))
+        .ok()
+}
+
+/// Hold the luminance of each of `pixels` to within `limit` of what it was in `last`, scaling its
+/// color to match, returning the luminance of each as it's held for the next frame to be held to.
+/// A frame of another size than the last is left as it is. A `transparent` frame's pixels are
+/// scaled alpha and all, as if more or less of each were covered, the color staying the same.
+fn deflicker(pixels: &mut [[f32; 4]], last: &[f32], limit: f32, transparent: bool) -> Vec<f32> {
+    let luminance = |[r, g, b, _]: [f32; 4]| 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    if pixels.len() != last.len() {
+        return pixels.iter().map(|&pixel| luminance(pixel)).collect();
+    }
+    (pixels.iter_mut().zip(last))
+        .map(|(pixel, &was)| {
+            let now = luminance(*pixel);
+            // black can't be brightened by scaling, so it's left to come up as it will
+            if now > 0.0 && (now - was).abs() > limit {
+                let mut factor = now.clamp(was - limit, was + limit) / now;
+                let channels = match transparent {
+                    true => {
+                        factor = factor.min(1.0 / pixel[3].max(f32::EPSILON));
+                        &mut pixel[..]
+                    }
+                    false => &mut pixel[..3],
+                };
+                for c in channels {
+                    *c *= factor;
+                }
+            }
+            luminance(*pixel)
+        })
+        .collect()
+}
+
+pub fn create_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| format!("{}: {err}", dir.display()))
+}
+
+fn write(job: &Job, config: ExportConfig) -> Result<(), String> {
+    let mut pixels = job.frame.pixels();
+    // the scene is drawn premultiplied
+    if config.alpha == Alpha::Straight {
+        for [r, g, b, a] in &mut pixels {
+            if *a > 0.0 {
+                for c in [r, g, b] {
+                    *c /= *a;
+                }
+            }
+        }
+    }
+    let result = match config.format {
+        Format::Exr if !job.frame.passes.is_empty() => {
+            write_exr_passes(&job.path, &job.frame, &pixels, config)
+        }
+        Format::Exr => write_exr(&job.path, job.frame.size, &pixels, config),
+        Format::Png => write_png(&job.path, job.frame.size, &pixels),
+    };
+    result.map_err(|err| format!("{}: {err}", job.path.display()))
+}
+
+fn write_exr(
+    path: &Path,
+    [width, _]: [u32; 2],
+    pixels: &[[f32; 4]],
+    config: ExportConfig,
+) -> Result<(), String> {
+    let size = (width as usize, pixels.len() / width as usize);
+    let pixel = |x: usize, y: usize| pixels[y * size.0 + x];
+    let encoding = encoding(config);
+    let result = match config.precision {
+        Precision::Half => {
+            let channels = SpecificChannels::rgba(|Pixel(x, y)| {
+                let [r, g, b, a] = pixel(x, y).map(f16::from_f32);
+                (r, g, b, a)
+            });
+            let image = Image::from_encoded_channels(size, encoding, channels);
+            image.write().to_file(path)
+        }
+        Precision::Full => {
+            let channels = SpecificChannels::rgba(|Pixel(x, y)| {
+                let [r, g, b, a] = pixel(x, y);
+                (r, g, b, a)
+            });
+            let image = Image::from_encoded_channels(size, encoding, channels);
+            image.write().to_file(path)
+        }
+    };
+    result.map_err(|err| err.to_string())
+}
+
+/// Write `frame`'s `pixels` along with its auxiliary passes, each pass to channels of its own.
+fn write_exr_passes(
+    path: &Path,
+    frame: &Captured,
+    pixels: &[[f32; 4]],
+    config: ExportConfig,
+) -> Result<(), String> {
+    let channel = |c: usize| pixels.iter().map(move |pixel| pixel[c]);
+    let mut channels: SmallVec<[AnyChannel<FlatSamples>; 4]> = (["R", "G", "B", "A"].iter())
+        .enumerate()
+        .map(|(c, name)| {
+            let samples = match config.precision {
+                Precision::Half => FlatSamples::F16(channel(c).map(f16::from_f32).collect()),
+                Precision::Full => FlatSamples::F32(channel(c).collect()),
+            };
+            AnyChannel::new(*name, samples)
+        })
+        .collect();
+    for (pass, captured) in &frame.passes {
+        let values: Vec<[f32; 2]> = (captured.pixels().into_iter())
+            .map(|texel| pass.decode(texel))
+            .collect();
+        for (c, name) in pass.channels().iter().enumerate() {
+            let samples = values.iter().map(|value| value[c]).collect();
+            channels.push(AnyChannel::new(*name, FlatSamples::F32(samples)));
+        }
+    }
+    let [width, height] = frame.size;
+    let layer = exr::prelude::Layer::new(
+        (width as usize, height as usize),
+        LayerAttributes::default(),
+        encoding(config),
+        AnyChannels::sort(channels),
+    );
+    (Image::from_layer(layer).write().to_file(path)).map_err(|err| err.to_string())
+}
+
+/// How an EXR is encoded, as `config` has it.
+fn encoding(config: ExportConfig) -> Encoding {
+    Encoding {
+        compression: match config.compression {
+            Compression::None => exr::compression::Compression::Uncompressed,
+            Compression::Zip => exr::compression::Compression::ZIP16,
+        },
+        ..Encoding::default()
+    }
+}
+
+/// Write 8 bit sRGB, the way the window shows the scene.
+pub fn write_png(path: &Path, size: [u32; 2], pixels: &[[f32; 4]]) -> Result<(), String> {
+    let image = to_image(size, pixels);
+    image.save(path).map_err(|err| err.to_string())
+}
+
+/// Linear `pixels` as an 8 bit sRGB image.
+pub fn to_image([width, height]: [u32; 2], pixels: &[[f32; 4]]) -> image::RgbaImage {
+    let bytes = pixels
+        .iter()
+        .flat_map(|&[r, g, b, a]| {
+            [srgb(r), srgb(g), srgb(b), a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect();
+    image::RgbaImage::from_raw(width, height, bytes).unwrap()
+}
+
+/// Encode a linear channel with the sRGB transfer function.
+pub fn srgb(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
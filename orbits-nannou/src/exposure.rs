@@ -0,0 +1,469 @@
+//! Long exposure: the mean of every frame drawn, developed onto the background like film. The
+//! exposure can be saved to an OpenEXR file and picked up again later, so one can run for days
+//! across restarts.
+
+use std::cell::RefCell;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use nannou::wgpu;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::gpu::{self, quad, Scene, Target, QUAD_LAYOUT, QUAD_VERTICES};
+
+/// How often the exposure is saved while running.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+/// Bytes per texel of `FORMAT`.
+const TEXEL_BYTES: u32 = 16;
+/// How often the histogram is retaken for `Scale::Equalize`.
+const EQUALIZE_INTERVAL: Duration = Duration::from_secs(1);
+/// Bins of log luminance the histogram is taken over, as in `develop.wgsl`.
+const BINS: usize = 256;
+/// Decades of luminance the bins span, up to 1, as in `develop.wgsl`.
+const DECADES: f32 = 6.0;
+
+/// How the exposure's brightness is mapped onto the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    /// Like film, brightening quickly at first and then saturating.
+    #[default]
+    Film,
+    /// Logarithmic, over six decades.
+    Log,
+    /// Histogram equalized, so every brightness covers as much of the frame as every other.
+    Equalize,
+}
+
+/// Colors to map the exposure's brightness onto, instead of lighting the trails' own colors over
+/// the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Colormap {
+    #[default]
+    None,
+    Viridis,
+    Magma,
+    Inferno,
+}
+
+/// The `[exposure]` section of the config.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExposureConfig {
+    pub scale: Scale,
+    pub colormap: Colormap,
+}
+
+/// A long exposure, kept in a float texture on the GPU and saved to `path`.
+pub struct LongExposure {
+    path: PathBuf,
+    config: ExposureConfig,
+    gpu: RefCell<Option<Gpu>>,
+    saved_at: RefCell<Instant>,
+    /// The latest equalization, and when it was taken.
+    equalized: RefCell<Option<([f32; BINS], Instant)>>,
+}
+
+struct Gpu {
+    device_queue: Arc<wgpu::DeviceQueuePair>,
+    size: [u32; 2],
+    scene: Scene,
+    /// The exposure so far, ping-ponged between as each frame is added.
+    exposures: [wgpu::Texture; 2],
+    /// Which of `exposures` is current.
+    current: usize,
+    frames: f32,
+    accumulate: wgpu::RenderPipeline,
+    /// Bind group `i` adds the scene to exposure `i`.
+    accumulate_groups: [wgpu::BindGroup; 2],
+    accumulate_uniforms: wgpu::Buffer,
+    develop: wgpu::RenderPipeline,
+    /// Bind group `i` develops exposure `i`.
+    develop_groups: [wgpu::BindGroup; 2],
+    develop_uniforms: wgpu::Buffer,
+    quad: wgpu::Buffer,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AccumulateUniforms {
+    frames: f32,
+    _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DevelopUniforms {
+    background: [f32; 3],
+    gain: f32,
+    scale: u32,
+    colormap: u32,
+    _padding: [u32; 2],
+    equalized: [f32; BINS],
+}
+
+impl LongExposure {
+    /// Start a long exposure saved to `path`, picking up the one already there if there is one.
+    pub fn new(path: PathBuf, config: ExposureConfig) -> Self {
+        LongExposure {
+            path,
+            config,
+            gpu: RefCell::new(None),
+            saved_at: RefCell::new(Instant::now()),
+            equalized: RefCell::new(None),
+        }
+    }
+
+    /// Add `draw`, a scene in pixels on a transparent background, to the exposure and develop
+    /// the exposure onto the target with `background` behind it, brightened by `gain`.
+    pub fn draw(&self, target: &mut Target, draw: &Draw, background: Hsl, gain: f32) {
+        let size = target.size;
+        let mut gpu = self.gpu.borrow_mut();
+        let resized = gpu.as_ref().is_some_and(|gpu| gpu.size != size);
+        if resized || gpu.as_ref().is_none_or(|gpu| !gpu.scene.is_current()) {
+            if resized {
+                eprintln!("the window changed size, so the long exposure starts over");
+            }
+            let mut fresh = Gpu::new(target.device_queue, size, target.msaa_samples);
+            // taken up again as it was last saved, at the start or once the GPU has failed
+            if !resized && self.path.exists() {
+                if let Err(err) = fresh.load(&self.path) {
+                    eprintln!("starting a new long exposure: {err}");
+                }
+            }
+            *gpu = Some(fresh);
+        }
+        let gpu = gpu.as_mut().unwrap();
+        if self.config.scale == Scale::Equalize {
+            self.equalize(gpu);
+        }
+        let device = gpu.device_queue.device();
+        let queue = gpu.device_queue.queue();
+
+        gpu.frames += 1.0;
+        let accumulate = AccumulateUniforms {
+            frames: gpu.frames,
+            _padding: [0.0; 3],
+        };
+        let background = background.into_lin_srgba();
+        let develop = DevelopUniforms {
+            background: [background.red, background.green, background.blue],
+            gain,
+            scale: self.config.scale as u32,
+            colormap: self.config.colormap as u32,
+            _padding: [0; 2],
+            equalized: self
+                .equalized
+                .borrow()
+                .map_or([0.0; BINS], |(equalized, _)| equalized),
+        };
+        queue.write_buffer(&gpu.accumulate_uniforms, 0, unsafe {
+            wgpu::bytes::from(&accumulate)
+        });
+        queue.write_buffer(&gpu.develop_uniforms, 0, unsafe {
+            wgpu::bytes::from(&develop)
+        });
+
+        let encoder = &mut *target.encoder;
+        gpu.scene.render(device, encoder, draw);
+
+        let next = 1 - gpu.current;
+        let next_view = gpu.exposures[next].view().build();
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(&next_view, |color| color)
+            .begin(encoder);
+        pass.set_pipeline(&gpu.accumulate);
+        pass.set_vertex_buffer(0, gpu.quad.slice(..));
+        pass.set_bind_group(0, &gpu.accumulate_groups[gpu.current], &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+        drop(pass);
+        gpu.current = next;
+
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(target.view, |color| color)
+            .begin(encoder);
+        pass.set_pipeline(&gpu.develop);
+        pass.set_vertex_buffer(0, gpu.quad.slice(..));
+        pass.set_bind_group(0, &gpu.develop_groups[gpu.current], &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+    }
+
+    /// Retake the histogram the exposure is equalized by, if it's due.
+    fn equalize(&self, gpu: &Gpu) {
+        let mut equalized = self.equalized.borrow_mut();
+        if equalized.is_some_and(|(_, taken)| taken.elapsed() < EQUALIZE_INTERVAL) {
+            return;
+        }
+        match gpu::download(&gpu.device_queue, &gpu.exposures[gpu.current]) {
+            Ok(bytes) => *equalized = Some((histogram_equalize(&bytes), Instant::now())),
+            Err(err) => eprintln!("failed to read back the long exposure to equalize: {err}"),
+        }
+    }
+
+    /// Where the exposure is saved.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save the exposure in the background if it hasn't been saved for a while.
+    pub fn autosave(&self) {
+        if self.saved_at.borrow().elapsed() < AUTOSAVE_INTERVAL {
+            return;
+        }
+        *self.saved_at.borrow_mut() = Instant::now();
+        if let Some((size, texels)) = self.download() {
+            let path = self.path.clone();
+            thread::spawn(move || {
+                if let Err(err) = write(&path, size, &texels) {
+                    eprintln!("failed to save the long exposure: {err}");
+                }
+            });
+        }
+    }
+
+    /// Save the exposure, waiting until it's written.
+    pub fn save(&self) -> Result<(), String> {
+        match self.download() {
+            Some((size, texels)) => write(&self.path, size, &texels),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy the current exposure back from the GPU.
+    fn download(&self) -> Option<([u32; 2], Vec<f32>)> {
+        let gpu = self.gpu.borrow();
+        let gpu = gpu.as_ref()?;
+        match gpu::download(&gpu.device_queue, &gpu.exposures[gpu.current]) {
+            Ok(bytes) => {
+                let texels = bytes
+                    .chunks(4)
+                    .map(|value| f32::from_ne_bytes(value.try_into().unwrap()))
+                    .collect();
+                Some((gpu.size, texels))
+            }
+            Err(err) => {
+                eprintln!("failed to read back the long exposure: {err}");
+                None
+            }
+        }
+    }
+}
+
+impl Gpu {
+    fn new(device_queue: &Arc<wgpu::DeviceQueuePair>, size: [u32; 2], msaa_samples: u32) -> Self {
+        let device = device_queue.device();
+        let scene = Scene::new(device, size);
+        let exposures = [(), ()].map(|_| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .format(FORMAT)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC
+                        | wgpu::TextureUsages::COPY_DST,
+                )
+                .build(device)
+        });
+        let unfilterable = wgpu::TextureSampleType::Float { filterable: false };
+        let uniform_buffer = |label, size: usize| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        let accumulate_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                unfilterable,
+            )
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                unfilterable,
+            )
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let accumulate_uniforms = uniform_buffer(
+            "long exposure accumulate",
+            std::mem::size_of::<AccumulateUniforms>(),
+        );
+        let scene_view = scene.texture.view().build();
+        let accumulate_groups = [0, 1].map(|i| {
+            wgpu::BindGroupBuilder::new()
+                .texture_view(&exposures[i].view().build())
+                .texture_view(&scene_view)
+                .buffer::<AccumulateUniforms>(&accumulate_uniforms, 0..1)
+                .build(device, &accumulate_layout)
+        });
+        let accumulate = pipeline(
+            device,
+            &accumulate_layout,
+            &wgpu::include_wgsl!("shaders/accumulate.wgsl"),
+            FORMAT,
+            1,
+        );
+
+        let develop_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                unfilterable,
+            )
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let develop_uniforms = uniform_buffer(
+            "long exposure develop",
+            std::mem::size_of::<DevelopUniforms>(),
+        );
+        let develop_groups = [0, 1].map(|i| {
+            wgpu::BindGroupBuilder::new()
+                .texture_view(&exposures[i].view().build())
+                .buffer::<DevelopUniforms>(&develop_uniforms, 0..1)
+                .build(device, &develop_layout)
+        });
+        let develop = pipeline(
+            device,
+            &develop_layout,
+            &wgpu::include_wgsl!("shaders/develop.wgsl"),
+            Frame::TEXTURE_FORMAT,
+            msaa_samples,
+        );
+
+        Gpu {
+            device_queue: device_queue.clone(),
+            size,
+            scene,
+            exposures,
+            current: 0,
+            frames: 0.0,
+            accumulate,
+            accumulate_groups,
+            accumulate_uniforms,
+            develop,
+            develop_groups,
+            develop_uniforms,
+            quad: quad(device),
+        }
+    }
+
+    /// Replace the exposure with the one saved at `path`.
+    fn load(&mut self, path: &Path) -> Result<(), String> {
+        let (size, texels) = read(path)?;
+        if size != self.size {
+            return Err(format!(
+                "{} is {}x{}, but the window is {}x{}",
+                path.display(),
+                size[0],
+                size[1],
+                self.size[0],
+                self.size[1]
+            ));
+        }
+        let exposure = &self.exposures[self.current];
+        self.device_queue.queue().write_texture(
+            exposure.as_image_copy(),
+            unsafe { wgpu::bytes::from_slice(&texels) },
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(size[0] * TEXEL_BYTES),
+                rows_per_image: NonZeroU32::new(size[1]),
+            },
+            exposure.extent(),
+        );
+        // every texel's alpha holds the frame count
+        self.frames = texels.get(3).copied().unwrap_or(0.0);
+        Ok(())
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    shader: &wgpu::ShaderModuleDescriptor,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("long exposure"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(shader);
+    wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+        .vertex_entry_point("vs_main")
+        .fragment_shader(&shader)
+        .fragment_entry_point("fs_main")
+        // 32 bit float targets can't be blended, and nothing here needs blending anyway
+        .color_state(wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        })
+        .add_vertex_buffer_layout(QUAD_LAYOUT)
+        .primitive_topology(wgpu::PrimitiveTopology::TriangleStrip)
+        .sample_count(sample_count)
+        .build(device)
+}
+
+/// The brightness to give each bin of log luminance such that the texels, given as RGBA `f32`s,
+/// spread evenly from black to white. Texels with no light at all are left out.
+fn histogram_equalize(bytes: &[u8]) -> [f32; BINS] {
+    let mut counts = [0u32; BINS];
+    for texel in bytes.chunks_exact(TEXEL_BYTES as usize) {
+        let [r, g, b] = [0, 4, 8].map(|i| f32::from_ne_bytes(texel[i..i + 4].try_into().unwrap()));
+        let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        if luminance >= 10.0.powf(-DECADES) {
+            let bin = (luminance.log10() / DECADES + 1.0) * BINS as f32;
+            counts[(bin as usize).min(BINS - 1)] += 1;
+        }
+    }
+    let total = counts.iter().sum::<u32>().max(1) as f32;
+    let mut below = 0;
+    counts.map(|count| {
+        below += count;
+        below as f32 / total
+    })
+}
+
+/// Write RGBA texels, top row first, to an OpenEXR file.
+fn write(path: &Path, [width, height]: [u32; 2], texels: &[f32]) -> Result<(), String> {
+    let width = width as usize;
+    exr::prelude::write_rgba_file(path, width, height as usize, |x, y| {
+        let i = (y * width + x) * 4;
+        (texels[i], texels[i + 1], texels[i + 2], texels[i + 3])
+    })
+    .map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Read the RGBA texels of an OpenEXR file, top row first.
+fn read(path: &Path) -> Result<([u32; 2], Vec<f32>), String> {
+    let image = exr::prelude::read_first_rgba_layer_from_file(
+        path,
+        |resolution, _| {
+            let size = [resolution.width() as u32, resolution.height() as u32];
+            (size, vec![0.0; resolution.area() * 4])
+        },
+        |(size, texels), position, (r, g, b, a): (f32, f32, f32, f32)| {
+            let i = (position.y() * size[0] as usize + position.x()) * 4;
+            texels[i..i + 4].copy_from_slice(&[r, g, b, a]);
+        },
+    )
+    .map_err(|err| format!("{}: {err}", path.display()))?;
+    Ok(image.layer_data.channel_data.pixels)
+}
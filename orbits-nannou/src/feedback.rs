@@ -0,0 +1,187 @@
+//! The simulation's own readings as signals moving the parameters, as `[feedback]` maps them, so
+//! the look of the piece follows what the physics is doing: how hot the swarm is, how evenly it's
+//! spread, how far its energy has drifted since it was seeded, and how much of it is escaping.
+//! Each reading is taken of the current generation every frame, smoothed, and put from 0 to 1
+//! over the range its mapping gives, so a background turning with the temperature turns smoothly
+//! rather than jittering as the particles swing round.
+
+use std::collections::BTreeMap;
+
+use orbits_core::generation::Generation;
+use orbits_core::params::{Modulation, Param, Params};
+use orbits_core::physics::{self, Particle};
+use orbits_core::{orbital_radius, settings, thermo};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// The `[feedback]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedbackConfig {
+    /// Seconds a reading takes to follow most of the way to where it's gone.
+    pub smoothing: f32,
+    /// Which readings move which parameters, none by default.
+    pub mappings: Vec<Mapping>,
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        FeedbackConfig {
+            smoothing: 1.0,
+            mappings: Vec::new(),
+        }
+    }
+}
+
+impl FeedbackConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.smoothing < 0.0 {
+            return Err(format!(
+                "feedback's smoothing can't be negative, not {}",
+                self.smoothing
+            ));
+        }
+        for mapping in &self.mappings {
+            let [low, high] = mapping.range;
+            if low == high {
+                return Err(format!(
+                    "the range of feedback from {} can't start and end at {low}",
+                    mapping.reading.label()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A reading moving a parameter.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+    pub reading: Reading,
+    #[schemars(with = "String")]
+    pub param: Param,
+    /// How far the parameter is moved from where it's been put at the top of the range, negative
+    /// to move it down. Mappings to the same parameter add up.
+    pub amount: f32,
+    /// The readings the parameter is moved none of the way at, and all of it, those past either
+    /// end moving it no further.
+    #[serde(default = "whole")]
+    pub range: [f32; 2],
+}
+
+fn whole() -> [f32; 2] {
+    [0.0, 1.0]
+}
+
+/// What's read of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Reading {
+    /// How widely the particles' velocities are spread, as a multiple of the speed of a circular
+    /// orbit on the average orbit, about 1 for a swarm going round.
+    Temperature,
+    /// How evenly the particles are spread over the system, from 0 to 1.
+    Entropy,
+    /// How far the swarm's mean energy has drifted since it was seeded, as a share of it.
+    EnergyDrift,
+    /// The share of the particles escaping, from 0 to 1.
+    Escaping,
+}
+
+impl Reading {
+    const ALL: [Reading; 4] = [
+        Reading::Temperature,
+        Reading::Entropy,
+        Reading::EnergyDrift,
+        Reading::Escaping,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Reading::Temperature => "temperature",
+            Reading::Entropy => "entropy",
+            Reading::EnergyDrift => "energy drift",
+            Reading::Escaping => "escaping",
+        }
+    }
+}
+
+pub struct Feedback {
+    config: FeedbackConfig,
+    /// The readings smoothed, in the order of `Reading::ALL`, or none before the first.
+    readings: Option<[f32; 4]>,
+    /// The seed of the generation read, and its mean energy when it was first read.
+    start: Option<(u64, f32)>,
+    modulation: Modulation,
+}
+
+impl Feedback {
+    /// The readings `config` maps, if it maps any.
+    pub fn new(config: FeedbackConfig) -> Option<Self> {
+        (!config.mappings.is_empty()).then(|| Feedback {
+            config,
+            readings: None,
+            start: None,
+            modulation: Modulation::default(),
+        })
+    }
+
+    /// Read `generation`, around masses of combined strength `gm`, smoothed over the
+    /// `wall_seconds` since the last frame, and move the parameters the readings are mapped to.
+    pub fn modulate(
+        &mut self,
+        wall_seconds: f32,
+        generation: &Generation,
+        gm: f32,
+        params: &mut Params,
+    ) {
+        let particles = &generation.particles;
+        let energy = mean_energy(particles, gm);
+        let start = match self.start {
+            // a new generation drifts from its own start
+            Some((seed, start)) if seed == generation.seed => start,
+            _ => energy,
+        };
+        self.start = Some((generation.seed, start));
+        let law = settings::get().gravity_law;
+        let escaping = (particles.iter())
+            .filter(|p| {
+                p.vel.length_squared() / 2.0 + law.potential(p.pos.length().max(1.0), gm) >= 0.0
+            })
+            .count();
+        let circular = physics::circular_speed(orbital_radius(), gm).max(1e-3);
+        let read = [
+            thermo::temperature(particles) / circular,
+            thermo::entropy(particles),
+            ((energy - start) / start.abs().max(1e-3)).abs(),
+            escaping as f32 / particles.len().max(1) as f32,
+        ];
+        let eased = 1.0 - (-wall_seconds / self.config.smoothing.max(1e-3)).exp();
+        let readings = self.readings.get_or_insert(read);
+        for (reading, read) in readings.iter_mut().zip(read) {
+            *reading += (read - *reading) * eased;
+        }
+
+        let mut offsets: BTreeMap<Param, f32> = BTreeMap::new();
+        for mapping in &self.config.mappings {
+            let index = (Reading::ALL.iter())
+                .position(|&r| r == mapping.reading)
+                .unwrap();
+            let [low, high] = mapping.range;
+            let along = ((readings[index] - low) / (high - low)).clamp(0.0, 1.0);
+            *offsets.entry(mapping.param).or_default() += mapping.amount * along;
+        }
+        self.modulation.apply(offsets, params);
+    }
+}
+
+/// The mean energy of `particles` around masses of combined strength `gm` taken as one at the
+/// middle.
+fn mean_energy(particles: &[Particle], gm: f32) -> f32 {
+    let law = settings::get().gravity_law;
+    let total: f32 = (particles.iter())
+        .map(|p| p.vel.length_squared() / 2.0 + law.potential(p.pos.length().max(1.0), gm))
+        .sum();
+    total / particles.len().max(1) as f32
+}
@@ -0,0 +1,140 @@
+//! Framing the swarm as it is rather than the average orbit, with `auto` set in `[framing]`: the
+//! view the piece would otherwise show follows the box holding the `percentile` of the current
+//! generation's particles nearest the middle of it along each axis, easing towards it over
+//! `damping` seconds and zooming and panning no faster than the most it's let, so escapers stay
+//! in view as the swarm spreads and it fills the window again as it draws in. The camera still
+//! moves the view from there.
+
+use nannou::prelude::*;
+use orbits_core::orbital_radius;
+use orbits_core::physics::Particle;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// How much room is left around the particles framed.
+const MARGIN: f32 = 1.2;
+/// Least width and height of the world framed, as a multiple of the average orbit's radius, so a
+/// swarm drawn in tight isn't blown up without end.
+const LEAST_SIDE: f32 = 0.5;
+
+/// The `[framing]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct FramingConfig {
+    /// Whether the view follows the swarm, rather than staying on the whole system.
+    pub auto: bool,
+    /// The share of the particles kept in view along each axis, from 0 to 1, the furthest out
+    /// either side left out of it.
+    pub percentile: f32,
+    /// Wall-clock seconds the view takes to ease most of the way to the frame it's after.
+    pub damping: f32,
+    /// Most the view zooms in or out by a second, as a factor.
+    pub max_zoom_rate: f32,
+    /// Most the view pans by a second, as a share of how wide it is.
+    pub max_pan_rate: f32,
+}
+
+impl Default for FramingConfig {
+    fn default() -> Self {
+        FramingConfig {
+            auto: false,
+            percentile: 0.9,
+            damping: 2.0,
+            max_zoom_rate: 1.5,
+            max_pan_rate: 0.5,
+        }
+    }
+}
+
+impl FramingConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.percentile > 0.0 && self.percentile <= 1.0) {
+            return Err(format!(
+                "the framing's percentile has to be over 0 and at most 1, not {}",
+                self.percentile
+            ));
+        }
+        if self.damping < 0.0 {
+            return Err(format!(
+                "the framing's damping can't be negative, not {}",
+                self.damping
+            ));
+        }
+        if self.max_zoom_rate < 1.0 {
+            return Err(format!(
+                "the framing's max_zoom_rate has to be at least 1, not {}",
+                self.max_zoom_rate
+            ));
+        }
+        if self.max_pan_rate < 0.0 {
+            return Err(format!(
+                "the framing's max_pan_rate can't be negative, not {}",
+                self.max_pan_rate
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The part of the world framed, as it eases after the swarm.
+pub struct Framing {
+    config: FramingConfig,
+    middle: Point2,
+    size: Vec2,
+}
+
+impl Framing {
+    /// The framing `config` asks for, starting on the whole system, if it asks for any.
+    pub fn new(config: FramingConfig) -> Option<Self> {
+        let side = 2.0 * orbital_radius() * MARGIN;
+        config.auto.then(|| Framing {
+            config,
+            middle: Point2::ZERO,
+            size: Vec2::splat(side),
+        })
+    }
+
+    /// The part of the world to fit into the window.
+    pub fn region(&self) -> Rect {
+        Rect::from_xy_wh(self.middle, self.size)
+    }
+
+    /// Ease the frame over the `wall_seconds` since the last frame towards the one `particles`
+    /// call for.
+    pub fn update(&mut self, wall_seconds: f32, particles: &[Particle]) {
+        let Some(target) = self.target(particles) else {
+            return;
+        };
+        let eased = 1.0 - (-wall_seconds / self.config.damping.max(1e-3)).exp();
+        // zoomed evenly in and out, as a factor
+        let most = self.config.max_zoom_rate.ln() * wall_seconds;
+        let zoom =
+            |size: f32, target: f32| size * ((target / size).ln() * eased).clamp(-most, most).exp();
+        self.size = vec2(zoom(self.size.x, target.w()), zoom(self.size.y, target.h()));
+        let pan = (target.xy() - self.middle) * eased;
+        let most = self.config.max_pan_rate * self.size.max_element() * wall_seconds;
+        self.middle += pan.clamp_length_max(most);
+    }
+
+    /// The frame holding the share of `particles` asked for along each axis, with room around it.
+    fn target(&self, particles: &[Particle]) -> Option<Rect> {
+        if particles.is_empty() {
+            return None;
+        }
+        let left_out = (1.0 - self.config.percentile) / 2.0;
+        let span = |along: fn(&Particle) -> f32| {
+            let mut values: Vec<f32> = particles.iter().map(along).collect();
+            values.sort_by(f32::total_cmp);
+            let last = (values.len() - 1) as f32;
+            let low = values[(left_out * last).floor() as usize];
+            let high = values[((1.0 - left_out) * last).ceil() as usize];
+            (low, high)
+        };
+        let (left, right) = span(|p| p.pos.x);
+        let (bottom, top) = span(|p| p.pos.y);
+        let least = LEAST_SIDE * orbital_radius();
+        let size = (vec2(right - left, top - bottom) * MARGIN).max(Vec2::splat(least));
+        let middle = pt2(left + right, bottom + top) / 2.0;
+        (middle.is_finite() && size.is_finite()).then(|| Rect::from_xy_wh(middle, size))
+    }
+}
@@ -0,0 +1,190 @@
+//! Renders a still of every preset in a directory into one labelled contact sheet, so the preset
+//! library can be browsed at a glance, or of every favorite seed, best first.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nannou::image::imageops::{self, FilterType};
+use nannou::image::{GenericImage, Rgba, RgbaImage};
+use nannou::text::{self, font};
+
+use crate::compare;
+use crate::config::Config;
+use crate::manifest::Manifest;
+use crate::ratings::{self, RATINGS_PATH};
+
+/// Where presets are looked for, relative to the working directory or anywhere else assets are.
+pub const PRESETS_DIR: &str = "presets";
+/// Seed every thumbnail is rendered from, unless told otherwise, so a gallery only changes when
+/// the presets do.
+pub const DEFAULT_SEED: u64 = 1;
+/// Thumbnails across each row, unless told otherwise.
+pub const DEFAULT_COLUMNS: u32 = 4;
+/// Size of each thumbnail, in pixels.
+const THUMBNAIL: [u32; 2] = [320, 180];
+/// Height of the strip under each thumbnail its label is written in, in pixels.
+const LABEL_HEIGHT: u32 = 28;
+const LABEL_SIZE: f32 = 18.0;
+const SHEET_BACKGROUND: Rgba<u8> = Rgba([24, 24, 24, 255]);
+const LABEL_COLOR: [u8; 3] = [230, 230, 230];
+
+/// A still to render into the sheet.
+struct Thumbnail {
+    config: PathBuf,
+    seed: u64,
+    label: String,
+}
+
+/// Render every `.toml` preset in `dir` after `seconds` of `seed`, at `pixel_scale` pixels to a
+/// point if given, into a sheet of `columns` thumbnails across, each labelled with its file name,
+/// and write it to `out`.
+pub fn gallery(
+    dir: &Path,
+    out: &Path,
+    seed: u64,
+    seconds: f32,
+    pixel_scale: Option<f32>,
+    columns: u32,
+) -> Result<(), String> {
+    let presets = presets(dir)?;
+    if presets.is_empty() {
+        return Err(format!("{}: no presets to render", dir.display()));
+    }
+    let thumbnails: Vec<_> = (presets.into_iter())
+        .map(|preset| Thumbnail {
+            label: preset
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into(),
+            config: preset,
+            seed,
+        })
+        .collect();
+    sheet(&thumbnails, out, seconds, pixel_scale, columns)
+}
+
+/// Render every seed rated at least `min_stars` under the config it was rated under, the most
+/// stars first, each labelled with its stars and seed, as `gallery` renders presets.
+pub fn rated(
+    min_stars: u8,
+    out: &Path,
+    seconds: f32,
+    pixel_scale: Option<f32>,
+    columns: u32,
+) -> Result<(), String> {
+    let best = ratings::best(ratings::load(Path::new(RATINGS_PATH))?, min_stars);
+    if best.is_empty() {
+        return Err(format!("no seeds rated {min_stars} stars or more"));
+    }
+    let dir = std::env::temp_dir();
+    let thumbnails: Vec<_> = (best.iter().enumerate())
+        .map(|(i, rating)| Thumbnail {
+            config: dir.join(format!("trynannou-rated-{}-{i}.toml", std::process::id())),
+            seed: rating.seed,
+            label: format!("{} {}", rating.stars(), rating.seed),
+        })
+        .collect();
+    let written = (best.iter().zip(&thumbnails))
+        .try_for_each(|(rating, thumbnail)| {
+            fs::write(&thumbnail.config, &rating.config)
+                .map_err(|err| format!("{}: {err}", thumbnail.config.display()))
+        })
+        .and_then(|()| sheet(&thumbnails, out, seconds, pixel_scale, columns));
+    for thumbnail in &thumbnails {
+        let _ = fs::remove_file(&thumbnail.config);
+    }
+    written
+}
+
+/// Render each of `thumbnails` after `seconds`, at `pixel_scale` pixels to a point if given, into
+/// a sheet of `columns` across, and write it to `out`, with its manifest beside it.
+fn sheet(
+    thumbnails: &[Thumbnail],
+    out: &Path,
+    seconds: f32,
+    pixel_scale: Option<f32>,
+    columns: u32,
+) -> Result<(), String> {
+    let stills = (thumbnails.iter())
+        .map(|thumbnail| {
+            println!("rendering {}", thumbnail.label);
+            let still = compare::still(&thumbnail.config, thumbnail.seed, seconds, pixel_scale)?;
+            Ok((still, thumbnail.label.clone()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    contact_sheet(&stills, columns)
+        .save(out)
+        .map_err(|err| format!("{}: {err}", out.display()))?;
+    let configs = (thumbnails.iter())
+        .map(|thumbnail| Config::load_file(&thumbnail.config).map(|config| config.written()))
+        .collect::<Result<_, String>>()?;
+    let seeds = thumbnails.iter().map(|thumbnail| thumbnail.seed).collect();
+    let manifest = Manifest::new(configs).seeds(seeds).time(seconds);
+    manifest.write_beside(out)
+}
+
+/// `stills` shrunk to thumbnails `columns` across a sheet, each labelled.
+pub fn contact_sheet(stills: &[(RgbaImage, String)], columns: u32) -> RgbaImage {
+    let columns = columns.clamp(1, stills.len().max(1) as u32);
+    let rows = (stills.len() as u32).div_ceil(columns);
+    let [width, height] = THUMBNAIL;
+    let cell_height = height + LABEL_HEIGHT;
+    let mut sheet = RgbaImage::from_pixel(width * columns, cell_height * rows, SHEET_BACKGROUND);
+    let font = font::default_notosans();
+    for (i, (still, name)) in stills.iter().enumerate() {
+        let resized = imageops::resize(still, width, height, FilterType::Triangle);
+        let (x, y) = (i as u32 % columns * width, i as u32 / columns * cell_height);
+        sheet.copy_from(&resized, x, y).unwrap();
+        label(&mut sheet, &font, name, x, y + height);
+    }
+    sheet
+}
+
+/// The `.toml` files in `dir`, in order of name.
+fn presets(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let err = |err: std::io::Error| format!("{}: {err}", dir.display());
+    let mut presets = Vec::new();
+    for entry in fs::read_dir(dir).map_err(err)? {
+        let path = entry.map_err(err)?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            presets.push(path);
+        }
+    }
+    presets.sort();
+    Ok(presets)
+}
+
+/// Write `name` into the label strip whose top left corner is at `x`, `y`, clipped to the width
+/// of a thumbnail.
+fn label(sheet: &mut RgbaImage, font: &text::Font, name: &str, x: u32, y: u32) {
+    let scale = text::Scale::uniform(LABEL_SIZE);
+    let ascent = font.v_metrics(scale).ascent;
+    // centered on the strip, ignoring descenders
+    let baseline = y as f32 + (LABEL_HEIGHT as f32 + ascent) / 2.0;
+    let start = text::rt::point(x as f32 + 8.0, baseline);
+    for glyph in font.layout(name, scale, start) {
+        let Some(bounds) = glyph.pixel_bounding_box() else {
+            continue;
+        };
+        glyph.draw(|gx, gy, coverage| {
+            let px = bounds.min.x + gx as i32;
+            let py = bounds.min.y + gy as i32;
+            let inside = px >= x as i32
+                && px < (x + THUMBNAIL[0]) as i32
+                && py >= y as i32
+                && py < (y + LABEL_HEIGHT) as i32;
+            if !inside {
+                return;
+            }
+            let pixel = sheet.get_pixel_mut(px as u32, py as u32);
+            for (channel, &color) in pixel.0.iter_mut().zip(&LABEL_COLOR) {
+                let blended = *channel as f32 + (color as f32 - *channel as f32) * coverage;
+                *channel = blended.round() as u8;
+            }
+        });
+    }
+}
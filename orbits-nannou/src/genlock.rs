@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// How often a follower measures its clock against the leader's.
+const PROBE_INTERVAL: Duration = Duration::from_millis(500);
+/// Number of recent measurements to pick the best from.
+const SAMPLES: usize = 16;
+/// How long the leader waits to listen again after its socket fails, rather than spinning on it.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Seconds since the unix epoch on this machine's clock.
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+}
+
+/// One round trip of the NTP-style clock exchange, in seconds on each side's own clock.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Probe {
+    /// When the follower sent the probe.
+    sent: f64,
+    /// When the leader received it.
+    received: f64,
+    /// When the leader replied.
+    replied: f64,
+}
+
+/// Answer clock probes from followers on `port`, on a thread of its own so replies don't wait for
+/// a frame to finish.
+pub fn serve(port: u16) -> Result<(), String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))
+        .map_err(|err| format!("genlock on port {port}: {err}"))?;
+    thread::spawn(move || {
+        let mut buf = [0; 256];
+        // whether the socket's failing, so it's said the once rather than every retry
+        let mut failing = false;
+        loop {
+            let (len, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                // a reply undelivered, as Windows reports it, or a signal, is no failure
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::ConnectionReset
+                    ) =>
+                {
+                    continue
+                }
+                Err(err) => {
+                    if !failing {
+                        eprintln!("genlock: couldn't receive a clock probe: {err}");
+                        failing = true;
+                    }
+                    thread::sleep(RETRY_INTERVAL);
+                    continue;
+                }
+            };
+            if failing {
+                eprintln!("genlock: receiving clock probes again");
+                failing = false;
+            }
+            let received = now();
+            let Ok(mut probe) = serde_json::from_slice::<Probe>(&buf[..len]) else {
+                continue;
+            };
+            probe.received = received;
+            probe.replied = now();
+            let _ = socket.send_to(&serde_json::to_vec(&probe).unwrap(), from);
+        }
+    });
+    Ok(())
+}
+
+/// A follower's running estimate of how far the leader's clock is ahead of its own.
+pub struct ClockEstimate {
+    socket: UdpSocket,
+    port: u16,
+    last_probe: Option<Instant>,
+    /// Recent measurements as `(round trip delay, offset)`.
+    samples: VecDeque<(f64, f64)>,
+    offset: f64,
+}
+
+impl ClockEstimate {
+    /// Measure against a leader answering probes on `port`.
+    pub fn new(port: u16) -> Result<Self, String> {
+        let err = |err: std::io::Error| format!("genlock: {err}");
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(err)?;
+        socket.set_nonblocking(true).map_err(err)?;
+        Ok(ClockEstimate {
+            socket,
+            port,
+            last_probe: None,
+            samples: VecDeque::new(),
+            offset: 0.0,
+        })
+    }
+
+    /// Take in any replies and probe the leader at `leader` again if it's time to.
+    pub fn update(&mut self, leader: Ipv4Addr) {
+        let mut buf = [0; 256];
+        loop {
+            match self.socket.recv(&mut buf) {
+                Ok(len) => {
+                    let arrived = now();
+                    if let Ok(probe) = serde_json::from_slice::<Probe>(&buf[..len]) {
+                        self.add_sample(probe, arrived);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    eprintln!("genlock: {err}");
+                    break;
+                }
+            }
+        }
+
+        if self
+            .last_probe
+            .is_none_or(|t| t.elapsed() >= PROBE_INTERVAL)
+        {
+            self.last_probe = Some(Instant::now());
+            let probe = Probe {
+                sent: now(),
+                ..Probe::default()
+            };
+            let to = SocketAddr::from((leader, self.port));
+            if let Err(err) = self
+                .socket
+                .send_to(&serde_json::to_vec(&probe).unwrap(), to)
+            {
+                eprintln!("genlock: failed to probe {to}: {err}");
+            }
+        }
+    }
+
+    fn add_sample(&mut self, probe: Probe, arrived: f64) {
+        let delay = (arrived - probe.sent) - (probe.replied - probe.received);
+        let offset = ((probe.received - probe.sent) + (probe.replied - arrived)) / 2.0;
+        self.samples.push_back((delay, offset));
+        if self.samples.len() > SAMPLES {
+            self.samples.pop_front();
+        }
+        // the quickest round trip is the one least distorted by queueing on the way
+        let (_, best) = self
+            .samples
+            .iter()
+            .copied()
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .unwrap();
+        self.offset = best;
+    }
+
+    /// The current time on the leader's clock.
+    pub fn leader_now(&self) -> f64 {
+        now() + self.offset
+    }
+}
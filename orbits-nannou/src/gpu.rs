@@ -0,0 +1,580 @@
+//! Pieces shared by the passes that draw through textures of their own, and the watch kept on the
+//! GPU for them to be built again should it fail.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::num::NonZeroU32;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use futures::FutureExt;
+
+use nannou::image::GrayImage;
+use nannou::prelude::*;
+use nannou::wgpu::{self, util::DeviceExt};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 2],
+}
+
+/// A quad covering the whole target, as a triangle strip.
+const VERTICES: [Vertex; 4] = [
+    Vertex {
+        position: [-1.0, 1.0],
+    },
+    Vertex {
+        position: [-1.0, -1.0],
+    },
+    Vertex {
+        position: [1.0, 1.0],
+    },
+    Vertex {
+        position: [1.0, -1.0],
+    },
+];
+
+/// Errors the GPU has raised, as `watch` catches them.
+static ERRORS: AtomicU64 = AtomicU64::new(0);
+/// How many times what's kept on the GPU has been let go of to be built afresh: whatever was built
+/// in an earlier epoch is built again the next time it's used.
+static EPOCH: AtomicU64 = AtomicU64::new(0);
+/// How many of the GPU's errors are printed.
+const REPORTED: u64 = 16;
+
+/// Count the errors `device` raises, rather than letting wgpu panic on the first of them, for the
+/// piece to build what it keeps on the GPU again.
+pub fn watch(device: &wgpu::Device) {
+    device.on_uncaptured_error(|err| {
+        // a device lost raises one for everything done with it, so only the first few are said
+        if ERRORS.fetch_add(1, Ordering::Relaxed) < REPORTED {
+            eprintln!("GPU error: {err}");
+        }
+    });
+}
+
+/// Errors the GPU has raised since the piece started.
+pub fn errors() -> u64 {
+    ERRORS.load(Ordering::Relaxed)
+}
+
+pub fn epoch() -> u64 {
+    EPOCH.load(Ordering::Relaxed)
+}
+
+/// Let go of everything kept on the GPU, for it to be built afresh as it's next used.
+pub fn rebuild() {
+    EPOCH.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A vertex buffer holding a quad that covers the whole target, to be drawn as a triangle strip of
+/// `QUAD_VERTICES` vertices.
+pub fn quad(device: &wgpu::Device) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::BufferInitDescriptor {
+        label: Some("quad"),
+        contents: unsafe { wgpu::bytes::from_slice(&VERTICES[..]) },
+        usage: wgpu::BufferUsages::VERTEX,
+    })
+}
+
+pub const QUAD_VERTICES: u32 = VERTICES.len() as u32;
+
+/// The layout of `quad`'s vertices, for building pipelines that draw it.
+pub const QUAD_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+};
+
+/// Somewhere a pass can draw to: the window's frame, or a texture of its own.
+pub struct Target<'a> {
+    pub device_queue: &'a Arc<wgpu::DeviceQueuePair>,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub view: &'a wgpu::TextureView,
+    pub size: [u32; 2],
+    pub msaa_samples: u32,
+}
+
+impl Target<'_> {
+    pub fn device(&self) -> &wgpu::Device {
+        self.device_queue.device()
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        self.device_queue.queue()
+    }
+}
+
+/// Draw to the frame through `f`, as a `Target`.
+pub fn with_frame<R>(frame: &Frame, f: impl FnOnce(&mut Target) -> R) -> R {
+    let mut encoder = frame.command_encoder();
+    let mut target = Target {
+        device_queue: frame.device_queue_pair(),
+        encoder: &mut encoder,
+        view: frame.texture_view(),
+        size: frame.texture_size(),
+        msaa_samples: frame.texture_msaa_samples(),
+    };
+    f(&mut target)
+}
+
+/// A texture in the frame's format, along with a renderer for drawing into it.
+pub struct Scene {
+    pub texture: wgpu::Texture,
+    renderer: nannou::draw::Renderer,
+    epoch: u64,
+}
+
+impl Scene {
+    pub fn new(device: &wgpu::Device, size: [u32; 2]) -> Self {
+        let texture = wgpu::TextureBuilder::new()
+            .size(size)
+            .format(Frame::TEXTURE_FORMAT)
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            )
+            .build(device);
+        let renderer = nannou::draw::RendererBuilder::new()
+            .build_from_texture_descriptor(device, texture.descriptor());
+        Scene {
+            texture,
+            renderer,
+            epoch: epoch(),
+        }
+    }
+
+    /// Whether it was built since what's kept on the GPU was last let go of.
+    pub fn is_current(&self) -> bool {
+        self.epoch == epoch()
+    }
+
+    /// Render `draw`, in pixels centered on the texture.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        draw: &Draw,
+    ) {
+        self.renderer
+            .render_to_texture(device, encoder, draw, &self.texture);
+    }
+}
+
+/// Copy a texture back from the GPU, waiting for it, as rows of texels from the top.
+pub fn download(
+    device_queue: &wgpu::DeviceQueuePair,
+    texture: &wgpu::Texture,
+) -> Result<Vec<u8>, String> {
+    let device = device_queue.device();
+    let [width, height] = texture.size();
+    // rows of a copy must be aligned
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let row_bytes = width * wgpu::texture_format_size_bytes(texture.format());
+    let padded_row_bytes = row_bytes.div_ceil(align) * align;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("download"),
+        size: (padded_row_bytes * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("download"),
+    });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_row_bytes),
+                rows_per_image: NonZeroU32::new(height),
+            },
+        },
+        texture.extent(),
+    );
+    device_queue.queue().submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let mapping = slice.map_async(wgpu::MapMode::Read);
+    device.poll(wgpu::Maintain::Wait);
+    futures::executor::block_on(mapping).map_err(|err| err.to_string())?;
+    let bytes = slice.get_mapped_range();
+    Ok(bytes
+        .chunks(padded_row_bytes as usize)
+        .flat_map(|row| &row[..row_bytes as usize])
+        .copied()
+        .collect())
+}
+
+/// Staging buffers let go of, with their sizes in bytes and the epoch they were made in, to be
+/// reused.
+type Pool = Arc<Mutex<Vec<(u64, u64, wgpu::Buffer)>>>;
+
+/// The mapping of a staging buffer, settled once the device is polled after the GPU has done
+/// copying into it.
+type Mapping = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+/// A texture read back from the GPU into a staging buffer, left mapped there for its texels to be
+/// read straight out of it. The buffer is unmapped and goes back to be reused once it's dropped.
+pub struct Mapped {
+    buffer: Option<wgpu::Buffer>,
+    bytes: u64,
+    /// The epoch the buffer was made in.
+    made: u64,
+    size: [u32; 2],
+    row_bytes: usize,
+    padded_row_bytes: usize,
+    pool: Pool,
+}
+
+impl Mapped {
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Call `f` with each row of texels, from the top.
+    pub fn rows(&self, mut f: impl FnMut(&[u8])) {
+        let view = self.buffer.as_ref().unwrap().slice(..).get_mapped_range();
+        for row in view.chunks(self.padded_row_bytes) {
+            f(&row[..self.row_bytes]);
+        }
+    }
+}
+
+impl Drop for Mapped {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            buffer.unmap();
+            (self.pool.lock().unwrap()).push((self.bytes, self.made, buffer));
+        }
+    }
+}
+
+/// A copy into a staging buffer that the GPU hasn't been waited on for yet.
+struct InFlight {
+    device_queue: Arc<wgpu::DeviceQueuePair>,
+    mapping: Mapping,
+    mapped: Mapped,
+}
+
+/// Reads textures back from the GPU without holding the render loop up for them. Each is copied
+/// into a staging buffer of its own and mapped, the mapping settling once the GPU has got through
+/// the copy, so it stands in for a fence. Up to `depth` are left in flight before the oldest is
+/// waited on, and each is handed back mapped as it settles, in the order they were started.
+pub struct Readback {
+    depth: usize,
+    in_flight: RefCell<VecDeque<InFlight>>,
+    pool: Pool,
+}
+
+impl Readback {
+    /// Read back with up to `depth` copies in flight: 1 to wait on each as it's started, 2 or 3
+    /// for the GPU to run that many frames behind.
+    pub fn new(depth: usize) -> Self {
+        Readback {
+            depth: depth.max(1),
+            in_flight: RefCell::new(VecDeque::new()),
+            pool: Pool::default(),
+        }
+    }
+
+    /// How many copies haven't been handed back yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.borrow().len()
+    }
+
+    /// Start reading `texture` back, handing back those started before it whose copies have
+    /// settled since, and waiting on the oldest should `depth` be in flight.
+    pub fn start(
+        &self,
+        device_queue: &Arc<wgpu::DeviceQueuePair>,
+        texture: &wgpu::Texture,
+    ) -> Vec<Result<Mapped, String>> {
+        let device = device_queue.device();
+        let [width, height] = texture.size();
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let row_bytes = width * wgpu::texture_format_size_bytes(texture.format());
+        let padded_row_bytes = row_bytes.div_ceil(align) * align;
+        let bytes = (padded_row_bytes * height) as u64;
+        let buffer = {
+            let mut pool = self.pool.lock().unwrap();
+            // buffers of another size were for frames of another size
+            pool.retain(|&(size, made, _)| size == bytes && made == epoch());
+            pool.pop().map(|(_, made, buffer)| (made, buffer))
+        };
+        let (made, buffer) = buffer.unwrap_or_else(|| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("readback"),
+                size: bytes,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            (epoch(), buffer)
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_row_bytes),
+                    rows_per_image: NonZeroU32::new(height),
+                },
+            },
+            texture.extent(),
+        );
+        device_queue.queue().submit(Some(encoder.finish()));
+        let mapping = Box::pin(buffer.slice(..).map_async(wgpu::MapMode::Read));
+        self.in_flight.borrow_mut().push_back(InFlight {
+            device_queue: Arc::clone(device_queue),
+            mapping,
+            mapped: Mapped {
+                buffer: Some(buffer),
+                bytes,
+                made,
+                size: [width, height],
+                row_bytes: row_bytes as usize,
+                padded_row_bytes: padded_row_bytes as usize,
+                pool: Arc::clone(&self.pool),
+            },
+        });
+
+        device.poll(wgpu::Maintain::Poll);
+        let mut settled = Vec::new();
+        while let Some(mapped) = self.next(self.in_flight() >= self.depth) {
+            settled.push(mapped);
+        }
+        settled
+    }
+
+    /// Wait for every copy still in flight, handing them all back.
+    pub fn finish(&self) -> Vec<Result<Mapped, String>> {
+        let mut settled = Vec::new();
+        while let Some(mapped) = self.next(true) {
+            settled.push(mapped);
+        }
+        settled
+    }
+
+    /// The oldest copy in flight, if it's settled, or once it has if `wait`.
+    fn next(&self, wait: bool) -> Option<Result<Mapped, String>> {
+        let mut in_flight = self.in_flight.borrow_mut();
+        let oldest = in_flight.front_mut()?;
+        let settled = if wait {
+            oldest.device_queue.device().poll(wgpu::Maintain::Wait);
+            Some(futures::executor::block_on(oldest.mapping.as_mut()))
+        } else {
+            oldest.mapping.as_mut().now_or_never()
+        }?;
+        let InFlight { mut mapped, .. } = in_flight.pop_front().unwrap();
+        Some(match settled {
+            Ok(()) => Ok(mapped),
+            Err(err) => {
+                // never mapped, so not to be unmapped or reused
+                mapped.buffer.take();
+                Err(err.to_string())
+            }
+        })
+    }
+}
+
+/// A pass over the finished scene, such as grain laid over it.
+pub trait Post {
+    /// Draw through `f` onto a texture the size of `target`, then that onto `target` with the
+    /// pass applied. `f` is given both the texture as a target, for passes of its own, and its
+    /// scene, for drawings.
+    fn draw(&self, target: &mut Target, f: &mut dyn FnMut(&mut Target, &mut Scene));
+}
+
+/// Draw through `f` onto `target`, through each of `posts` in turn, the first innermost. There has
+/// to be at least one.
+pub fn draw_posted(
+    posts: &[&dyn Post],
+    target: &mut Target,
+    f: &mut dyn FnMut(&mut Target, &mut Scene),
+) {
+    let (outermost, inner) = posts.split_last().expect("no passes to draw through");
+    outermost.draw(target, &mut |target, scene| {
+        if inner.is_empty() {
+            f(target, scene)
+        } else {
+            draw_posted(inner, target, f)
+        }
+    });
+}
+
+/// A post pass made by a single fragment shader, `fs_main`, reading the scene drawn through it
+/// with `textureLoad`, along with a grayscale texture, sampled repeating, and uniforms of its own.
+pub struct Filter {
+    label: &'static str,
+    /// WGSL with the vertex shader `vs_main` taking `QUAD_LAYOUT` vertices, and `fs_main`.
+    shader: &'static str,
+    texture: Arc<GrayImage>,
+    uniforms: Vec<u8>,
+    gpu: RefCell<Option<FilterGpu>>,
+}
+
+struct FilterGpu {
+    size: [u32; 2],
+    msaa_samples: u32,
+    scene: Scene,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    quad: wgpu::Buffer,
+}
+
+impl Filter {
+    pub fn new<U: Copy>(
+        label: &'static str,
+        shader: &'static str,
+        texture: Arc<GrayImage>,
+        uniforms: &U,
+    ) -> Self {
+        Filter {
+            label,
+            shader,
+            texture,
+            uniforms: unsafe { wgpu::bytes::from(uniforms) }.to_vec(),
+            gpu: RefCell::new(None),
+        }
+    }
+}
+
+/// The same filter with GPU resources of its own, for drawing through at another size.
+impl Clone for Filter {
+    fn clone(&self) -> Self {
+        Filter {
+            label: self.label,
+            shader: self.shader,
+            texture: self.texture.clone(),
+            uniforms: self.uniforms.clone(),
+            gpu: RefCell::new(None),
+        }
+    }
+}
+
+impl Post for Filter {
+    fn draw(&self, target: &mut Target, f: &mut dyn FnMut(&mut Target, &mut Scene)) {
+        let (size, msaa_samples) = (target.size, target.msaa_samples);
+        let mut gpu = self.gpu.borrow_mut();
+        if gpu.as_ref().is_none_or(|gpu| {
+            gpu.size != size || gpu.msaa_samples != msaa_samples || !gpu.scene.is_current()
+        }) {
+            *gpu = Some(FilterGpu::new(
+                self,
+                target.device_queue,
+                size,
+                msaa_samples,
+            ));
+        }
+        let gpu = gpu.as_mut().unwrap();
+
+        let view = gpu.scene.texture.view().build();
+        let mut inner = Target {
+            device_queue: target.device_queue,
+            encoder: &mut *target.encoder,
+            view: &view,
+            size,
+            msaa_samples: 1,
+        };
+        f(&mut inner, &mut gpu.scene);
+
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(target.view, |color| color)
+            .begin(target.encoder);
+        pass.set_pipeline(&gpu.pipeline);
+        pass.set_vertex_buffer(0, gpu.quad.slice(..));
+        pass.set_bind_group(0, &gpu.bind_group, &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+    }
+}
+
+impl FilterGpu {
+    fn new(
+        filter: &Filter,
+        device_queue: &wgpu::DeviceQueuePair,
+        size: [u32; 2],
+        msaa_samples: u32,
+    ) -> Self {
+        let device = device_queue.device();
+        let scene = Scene::new(device, size);
+        let texture = wgpu::Texture::load_from_image_buffer(
+            device,
+            device_queue.queue(),
+            wgpu::TextureUsages::TEXTURE_BINDING,
+            &*filter.texture,
+        );
+
+        let sampler_desc = wgpu::SamplerBuilder::new()
+            .address_mode(wgpu::AddressMode::Repeat)
+            .into_descriptor();
+        let filtering = wgpu::sampler_filtering(&sampler_desc);
+        let sampler = device.create_sampler(&sampler_desc);
+        let sample_type = wgpu::TextureSampleType::Float { filterable: true };
+        let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                sample_type,
+            )
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                sample_type,
+            )
+            .sampler(wgpu::ShaderStages::FRAGMENT, filtering)
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let uniforms = device.create_buffer_init(&wgpu::BufferInitDescriptor {
+            label: Some(filter.label),
+            contents: &filter.uniforms,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .texture_view(&scene.texture.view().build())
+            .texture_view(&texture.view().build())
+            .sampler(&sampler)
+            .buffer_bytes(&uniforms, 0, None)
+            .build(device, &bind_group_layout);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(filter.label),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some(filter.label),
+            source: wgpu::ShaderSource::Wgsl(filter.shader.into()),
+        });
+        let pipeline = wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+            .vertex_entry_point("vs_main")
+            .fragment_shader(&shader)
+            .fragment_entry_point("fs_main")
+            .color_format(Frame::TEXTURE_FORMAT)
+            .color_blend(wgpu::BlendComponent::REPLACE)
+            .alpha_blend(wgpu::BlendComponent::REPLACE)
+            .add_vertex_buffer_layout(QUAD_LAYOUT)
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleStrip)
+            .sample_count(msaa_samples)
+            .build(device);
+
+        FilterGpu {
+            size,
+            msaa_samples,
+            scene,
+            pipeline,
+            bind_group,
+            quad: quad(device),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! A grain or paper texture laid over the finished frame, for the look of something printed.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use nannou::image::{self, GrayImage, Luma};
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::assets;
+use crate::gpu::{Filter, Post, Scene, Target};
+
+/// Texels along each side of the built-in grain.
+const BUILT_IN_SIZE: u32 = 256;
+/// Cells of the built-in grain's coarse fibres along each side.
+const FIBRES: usize = 16;
+
+/// How the grain is blended over the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Blend {
+    /// Darkening the dark parts and lightening the light ones, leaving mid gray grain unseen.
+    #[default]
+    Overlay,
+    /// Only ever lightening, like light through the grain.
+    Screen,
+}
+
+/// The `[grain]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrainConfig {
+    /// How strongly the grain shows, from 0, for not at all, to 1.
+    pub strength: f32,
+    pub blend: Blend,
+    /// A tileable image to use instead of the built-in grain, taken as grayscale.
+    pub texture: Option<PathBuf>,
+    /// Pixels each texel of the texture covers.
+    pub scale: f32,
+}
+
+impl Default for GrainConfig {
+    fn default() -> Self {
+        GrainConfig {
+            strength: 0.0,
+            blend: Blend::default(),
+            texture: None,
+            scale: 1.0,
+        }
+    }
+}
+
+/// The grain, laid over whatever is drawn through it.
+#[derive(Clone)]
+pub struct Grain(Filter);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Uniforms {
+    blend: u32,
+    strength: f32,
+    scale: f32,
+    _padding: f32,
+}
+
+impl Grain {
+    /// The grain `config` asks for, or none if it's turned off.
+    pub fn load(config: &GrainConfig) -> Result<Option<Self>, String> {
+        if config.strength <= 0.0 {
+            return Ok(None);
+        }
+        let image = match &config.texture {
+            Some(path) => image::open(assets::resolve(path))
+                .map_err(|err| format!("{}: {err}", path.display()))?
+                .to_luma8(),
+            None => built_in(),
+        };
+        let uniforms = Uniforms {
+            blend: config.blend as u32,
+            strength: config.strength.min(1.0),
+            scale: config.scale.max(0.01),
+            _padding: 0.0,
+        };
+        let shader = include_str!("shaders/grain.wgsl");
+        Ok(Some(Grain(Filter::new(
+            "grain",
+            shader,
+            Arc::new(image),
+            &uniforms,
+        ))))
+    }
+}
+
+impl Post for Grain {
+    fn draw(&self, target: &mut Target, f: &mut dyn FnMut(&mut Target, &mut Scene)) {
+        self.0.draw(target, f)
+    }
+}
+
+/// Tileable grain: fine speckle over faint, coarser fibres, around mid gray.
+fn built_in() -> GrayImage {
+    let mut rng = StdRng::seed_from_u64(0);
+    let fibres: Vec<f32> = (0..FIBRES * FIBRES).map(|_| rng.gen()).collect();
+    let cell = BUILT_IN_SIZE as f32 / FIBRES as f32;
+    GrayImage::from_fn(BUILT_IN_SIZE, BUILT_IN_SIZE, |x, y| {
+        // value noise, smoothly interpolated between cells that wrap around the edges
+        let (fx, fy) = (x as f32 / cell, y as f32 / cell);
+        let (cx, cy) = (fx as usize, fy as usize);
+        let lattice = |i: usize, j: usize| fibres[(j % FIBRES) * FIBRES + i % FIBRES];
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (u, v) = (smooth(fx.fract()), smooth(fy.fract()));
+        let top = lattice(cx, cy) + (lattice(cx + 1, cy) - lattice(cx, cy)) * u;
+        let bottom = lattice(cx, cy + 1) + (lattice(cx + 1, cy + 1) - lattice(cx, cy + 1)) * u;
+        let coarse = top + (bottom - top) * v;
+        let fine: f32 = rng.gen();
+        let value = 0.5 + 0.6 * (fine - 0.5) + 0.3 * (coarse - 0.5);
+        Luma([(value.clamp(0.0, 1.0) * 255.0).round() as u8])
+    })
+}
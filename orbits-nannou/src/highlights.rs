@@ -0,0 +1,307 @@
+//! Watches a long run for its most interesting moments, so hours of output can be curated from a
+//! short list. Each frame is scored for close encounters, tightly bending trails and shifts of
+//! palette, and a moment is noted each time the score rises past a threshold and falls back.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use nannou::prelude::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::export::{self, Captured, Export};
+use orbits_core::generation::Generation;
+use orbits_core::orbital_radius;
+use orbits_core::physics::{acceleration_at, Body};
+use orbits_core::saliency::ENCOUNTER_RADIUS;
+
+/// Seconds over which the encounter and bend scores are smoothed.
+const SMOOTHING: f32 = 0.5;
+/// Seconds for the score of a palette shift to fade to a third.
+const PALETTE_MEMORY: f32 = 2.0;
+/// How deep into encounters the particles must be between them to score 1.
+const ENCOUNTERS_FOR_ONE: f32 = 2.0;
+/// How far past circular the trails must bend on average to score 1.
+const BEND_FOR_ONE: f32 = 2.0;
+/// Most a single trail can add to the bend, so a particle skimming a body can't score alone.
+const MAX_BEND: f32 = 10.0;
+
+/// The `[highlights]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HighlightsConfig {
+    /// How interesting a moment must score to be noted. Several particles deep in close
+    /// encounters, or a reseed to a very different palette, each score about 1.
+    pub threshold: f32,
+    /// Seconds after one moment before another is looked for.
+    pub spacing: f32,
+    /// Write a clip of each moment to its own directory in here.
+    pub clips: Option<PathBuf>,
+    /// Seconds of each clip from before the moment.
+    pub pre_roll: f32,
+    /// Seconds of each clip from after the moment.
+    pub post_roll: f32,
+}
+
+impl Default for HighlightsConfig {
+    fn default() -> Self {
+        HighlightsConfig {
+            threshold: 1.0,
+            spacing: 30.0,
+            clips: None,
+            pre_roll: 1.0,
+            post_roll: 2.0,
+        }
+    }
+}
+
+/// A moment, as written to the list.
+#[derive(Debug, Serialize)]
+struct Highlight<'a> {
+    /// Seconds since launch when the moment peaked.
+    seconds: f32,
+    /// Frame of an `export` sequence the moment peaked on.
+    frame: u64,
+    score: f32,
+    reasons: Vec<&'static str>,
+    clip: Option<&'a Path>,
+}
+
+/// How interesting a frame is, by each measure.
+#[derive(Debug, Clone, Copy, Default)]
+struct Score {
+    encounters: f32,
+    bends: f32,
+    palette: f32,
+}
+
+impl Score {
+    fn total(&self) -> f32 {
+        self.encounters + self.bends + self.palette
+    }
+}
+
+/// The moment the score is currently above the threshold for.
+struct Moment {
+    seconds: f32,
+    frame: u64,
+    peak: Score,
+    clip: Option<PathBuf>,
+}
+
+/// A clip being written.
+struct Clip {
+    dir: PathBuf,
+    frames: u64,
+    /// When the clip ends, once its moment has.
+    until: Option<f32>,
+}
+
+/// Scores frames and appends every moment that stands out to a file, one JSON object per line.
+pub struct Highlights {
+    config: HighlightsConfig,
+    out: BufWriter<File>,
+    /// Seconds since launch at the latest frame.
+    seconds: f32,
+    frames: u64,
+    score: Score,
+    background: Option<Hsl>,
+    moment: Option<Moment>,
+    /// When the latest moment ended.
+    ended: Option<f32>,
+    /// The latest `pre_roll` seconds of frames, ready to start a clip with.
+    recent: VecDeque<(f32, Captured)>,
+    clips: usize,
+    clip: Option<Clip>,
+}
+
+impl Highlights {
+    pub fn create(path: &Path, config: HighlightsConfig) -> Result<Self, String> {
+        let file = File::create(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        if let Some(dir) = &config.clips {
+            export::create_dir(dir)?;
+        }
+        Ok(Highlights {
+            config,
+            out: BufWriter::new(file),
+            seconds: 0.0,
+            frames: 0,
+            score: Score::default(),
+            background: None,
+            moment: None,
+            ended: None,
+            recent: VecDeque::new(),
+            clips: 0,
+            clip: None,
+        })
+    }
+
+    /// Whether clips are wanted, and so every frame needs capturing.
+    pub fn wants_frames(&self) -> bool {
+        self.config.clips.is_some()
+    }
+
+    /// Score the frame shown `seconds` after launch, along with `frames`, those captured since
+    /// the last update if clips are wanted, the read back running a few frames behind.
+    pub fn update(
+        &mut self,
+        seconds: f32,
+        generation: &Generation,
+        bodies: &[Body],
+        frames: Vec<Captured>,
+        export: Option<&Export>,
+    ) {
+        let delta_seconds = seconds - self.seconds;
+        self.seconds = seconds;
+        let index = self.frames;
+        self.frames += 1;
+        self.rescore(delta_seconds, generation, bodies);
+
+        let score = self.score.total();
+        let spaced = self
+            .ended
+            .is_none_or(|ended| seconds - ended >= self.config.spacing);
+        match &mut self.moment {
+            None if score >= self.config.threshold && spaced => {
+                let clip = export.and_then(|export| self.start_clip(export));
+                self.moment = Some(Moment {
+                    seconds,
+                    frame: index,
+                    peak: self.score,
+                    clip,
+                });
+            }
+            Some(_) if score < self.config.threshold => {
+                let moment = self.moment.take().unwrap();
+                self.write(&moment);
+                self.ended = Some(seconds);
+                if let Some(clip) = &mut self.clip {
+                    clip.until = Some(seconds + self.config.post_roll);
+                }
+            }
+            Some(moment) if score > moment.peak.total() => {
+                (moment.seconds, moment.frame, moment.peak) = (seconds, index, self.score);
+            }
+            _ => {}
+        }
+
+        let Some(export) = export else {
+            return;
+        };
+        for frame in frames {
+            self.keep(seconds, frame, export);
+        }
+    }
+
+    /// Save `frame`, captured around `seconds` after launch, to the clip being exported, or keep
+    /// it to start the next one with.
+    fn keep(&mut self, seconds: f32, frame: Captured, export: &Export) {
+        match &mut self.clip {
+            Some(clip) => {
+                export.save(&clip.dir, clip.frames, frame);
+                clip.frames += 1;
+                if clip.until.is_some_and(|until| seconds >= until) {
+                    self.clip = None;
+                }
+            }
+            None if self.config.clips.is_some() => {
+                self.recent.push_back((seconds, frame));
+                while let Some(&(shown, _)) = self.recent.front() {
+                    if seconds - shown <= self.config.pre_roll {
+                        break;
+                    }
+                    self.recent.pop_front();
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn rescore(&mut self, delta_seconds: f32, generation: &Generation, bodies: &[Body]) {
+        let particles = &generation.particles;
+        let encounters: f32 = particles
+            .iter()
+            .map(|particle| {
+                bodies
+                    .iter()
+                    .map(|body| 1.0 - particle.pos.distance(body.pos) / ENCOUNTER_RADIUS)
+                    .fold(0.0, f32::max)
+            })
+            .sum();
+        // a circular orbit of the usual size bends by 1
+        let bend = particles
+            .iter()
+            .map(|particle| {
+                let v = particle.vel;
+                let a = acceleration_at(particle.pos, bodies);
+                let curvature = v.perp_dot(a).abs() / v.length().powi(3);
+                (curvature * orbital_radius()).min(MAX_BEND)
+            })
+            .sum::<f32>()
+            / particles.len().max(1) as f32;
+
+        let smoothing = 1.0 - (-delta_seconds / SMOOTHING).exp();
+        let score = &mut self.score;
+        score.encounters += (encounters / ENCOUNTERS_FOR_ONE - score.encounters) * smoothing;
+        score.bends += ((bend - 1.0).max(0.0) / BEND_FOR_ONE - score.bends) * smoothing;
+
+        // a reseed to the opposite side of the color wheel scores 1
+        score.palette *= (-delta_seconds / PALETTE_MEMORY).exp();
+        let background = generation.background;
+        if let Some(previous) = self.background.replace(background) {
+            if previous != background {
+                let turn = (background.hue - previous.hue).to_degrees().abs() / 180.0;
+                score.palette = score.palette.max(turn);
+            }
+        }
+    }
+
+    /// Start a clip with the frames leading up to now.
+    fn start_clip(&mut self, export: &Export) -> Option<PathBuf> {
+        let clips = self.config.clips.as_ref()?;
+        self.clips += 1;
+        let dir = clips.join(format!("highlight-{:04}", self.clips));
+        if let Err(err) = export::create_dir(&dir) {
+            eprintln!("failed to start a clip: {err}");
+            return None;
+        }
+        let frames = self.recent.len() as u64;
+        for (index, (_, frame)) in self.recent.drain(..).enumerate() {
+            export.save(&dir, index as u64, frame);
+        }
+        self.clip = Some(Clip {
+            dir: dir.clone(),
+            frames,
+            until: None,
+        });
+        Some(dir)
+    }
+
+    fn write(&mut self, moment: &Moment) {
+        let peak = moment.peak;
+        let reasons = [
+            (peak.encounters, "close encounters"),
+            (peak.bends, "tight turns"),
+            (peak.palette, "palette shift"),
+        ]
+        .into_iter()
+        // anything that made up a good part of the score
+        .filter(|&(score, _)| score >= peak.total() / 3.0)
+        .map(|(_, reason)| reason)
+        .collect();
+        let highlight = Highlight {
+            seconds: moment.seconds,
+            frame: moment.frame,
+            score: peak.total(),
+            reasons,
+            clip: moment.clip.as_deref(),
+        };
+        let line = serde_json::to_string(&highlight).unwrap();
+        // flush every moment so a crash doesn't lose the list
+        if let Err(err) = writeln!(self.out, "{line}").and_then(|_| self.out.flush()) {
+            eprintln!("failed to write a highlight: {err}");
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Hints at how to play with the piece, for an installation visitors walk up to: once no one has
+//! touched it for a while, short hints such as "touch to add a star" fade in over it one at a time,
+//! every so often, and each fades out again on its own. Any touch, click, key or scroll puts them
+//! away until the piece has been left alone again. The hints are in English unless the config
+//! gives them in the language it picks.
+
+use std::collections::BTreeMap;
+
+use nannou::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::layers::LayerConfig;
+
+/// The hints given in English, unless the config gives others, and the one given as well when
+/// there's an attractor to drag.
+const ENGLISH: [&str; 2] = ["touch to add a star", "swipe to fling one into orbit"];
+const ENGLISH_DRAG: &str = "drag a glowing mass to pull the stars";
+/// Wall-clock seconds a hint takes to fade in, and out.
+const FADE_SECONDS: f32 = 1.0;
+const FONT_SIZE: u32 = 32;
+
+/// The `[hints]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HintsConfig {
+    /// Whether hints are given at all.
+    pub on: bool,
+    /// Wall-clock seconds the piece is left alone before the first hint.
+    pub idle: f32,
+    /// Wall-clock seconds from one hint to the next.
+    pub every: f32,
+    /// Wall-clock seconds each hint is shown for, fading in and out included.
+    pub shown: f32,
+    /// The language the hints are given in, among `languages` or `en`.
+    pub language: String,
+    /// The hints given in each language, by its code, in the order they're given.
+    pub languages: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for HintsConfig {
+    fn default() -> Self {
+        HintsConfig {
+            on: false,
+            idle: 30.0,
+            every: 12.0,
+            shown: 5.0,
+            language: "en".into(),
+            languages: BTreeMap::new(),
+        }
+    }
+}
+
+impl HintsConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.idle < 0.0 {
+            return Err(format!(
+                "the hints' idle can't be negative, not {}",
+                self.idle
+            ));
+        }
+        if !(self.shown > 0.0 && self.shown <= self.every) {
+            return Err(format!(
+                "each hint has to be shown for a while, and no longer than the {} seconds from one \
+                 to the next, not {}",
+                self.every, self.shown
+            ));
+        }
+        match self.languages.get(&self.language) {
+            Some(hints) if hints.is_empty() => {
+                Err(format!("the hints in {:?} are empty", self.language))
+            }
+            None if self.language != "en" => Err(format!(
+                "there are no hints in {:?} in the hints' languages",
+                self.language
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The hints in the language picked, hinting at dragging an attractor in English if
+    /// `draggable`.
+    fn hints(&self, draggable: bool) -> Vec<String> {
+        if let Some(hints) = self.languages.get(&self.language) {
+            return hints.clone();
+        }
+        let drag = draggable.then_some(ENGLISH_DRAG);
+        ENGLISH.into_iter().chain(drag).map(String::from).collect()
+    }
+}
+
+/// How long the piece has been left alone, and so which hint is shown.
+pub struct Hints {
+    config: HintsConfig,
+    hints: Vec<String>,
+    /// Wall-clock seconds since the piece was last touched.
+    idle: f32,
+}
+
+impl Hints {
+    /// Hints as `config` gives them, whether or not there's an attractor that's `draggable`.
+    pub fn new(config: HintsConfig, draggable: bool) -> Self {
+        Hints {
+            hints: config.hints(draggable),
+            config,
+            idle: 0.0,
+        }
+    }
+
+    /// Put the hints away, as someone's touched the piece.
+    pub fn touched(&mut self) {
+        self.idle = 0.0;
+    }
+
+    /// Count `wall_seconds` more of the piece being left alone.
+    pub fn update(&mut self, wall_seconds: f32) {
+        self.idle += wall_seconds;
+    }
+
+    /// The hint shown now, if one is, and how far it's faded in.
+    fn shown(&self) -> Option<(&str, f32)> {
+        let waited = self.idle - self.config.idle;
+        if !self.config.on || waited < 0.0 {
+            return None;
+        }
+        let into = waited % self.config.every;
+        if into >= self.config.shown {
+            return None;
+        }
+        let hint = &self.hints[(waited / self.config.every) as usize % self.hints.len()];
+        let fade = (into.min(self.config.shown - into) / FADE_SECONDS).min(1.0);
+        Some((hint, fade))
+    }
+
+    /// Draw the hint shown, if one is, across the lower part of `win`, faded as `layer` is.
+    pub fn draw(&self, win: Rect, layer: &LayerConfig, draw: &Draw) {
+        let Some((hint, fade)) = self.shown() else {
+            return;
+        };
+        draw.text(hint)
+            .x_y(win.x(), win.bottom() + win.h() / 4.0)
+            .w(win.w())
+            .font_size(FONT_SIZE)
+            .center_justify()
+            .color(layer.fade(rgba(1.0, 1.0, 1.0, 0.85 * fade)));
+    }
+}
@@ -0,0 +1,124 @@
+//! The outline of the swarm each frame, in a layer of its own over the trails: its convex hull,
+//! grown by `padding` all round into a soft blob, filled faintly and edged a little less so, in
+//! the colors of the particles within it mixed. With `clusters` on, the swarm is split into the
+//! clumps packed closely enough together first, DBSCAN style, each drawn as a blob of its own and
+//! the stragglers between them left out. The layer is hidden until `[layers.hulls]` shows it.
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use orbits_core::geometry;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::layers::LayerConfig;
+
+/// Pieces each rounded corner of a blob is drawn in.
+const CORNER_SEGMENTS: usize = 8;
+
+/// The `[hulls]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct HullsConfig {
+    /// Whether each clump of the swarm is outlined on its own, rather than the whole of it.
+    pub clusters: bool,
+    /// How close particles come to be in the same clump, in world units.
+    pub radius: f32,
+    /// Fewest others within `radius` of a particle for a clump to grow out from it.
+    pub least: usize,
+    /// How far each blob reaches past the particles on its edge, in world units.
+    pub padding: f32,
+    /// Alpha the blobs are filled in at.
+    pub fill: f32,
+    /// Alpha of the blobs' outlines, 0 for none.
+    pub outline: f32,
+    /// Width of the outlines, in world units.
+    pub line_width: f32,
+}
+
+impl Default for HullsConfig {
+    fn default() -> Self {
+        HullsConfig {
+            clusters: false,
+            radius: 250.0,
+            least: 3,
+            padding: 60.0,
+            fill: 0.12,
+            outline: 0.3,
+            line_width: 2.0,
+        }
+    }
+}
+
+impl HullsConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.radius <= 0.0 {
+            return Err(format!(
+                "the hulls' radius has to be positive, not {}",
+                self.radius
+            ));
+        }
+        if self.padding < 0.0 {
+            return Err(format!(
+                "the hulls' padding can't be negative, not {}",
+                self.padding
+            ));
+        }
+        for (name, alpha) in [("fill", self.fill), ("outline", self.outline)] {
+            if !(0.0..=1.0).contains(&alpha) {
+                return Err(format!("the hulls' {name} goes from 0 to 1, not {alpha}"));
+            }
+        }
+        if self.line_width <= 0.0 {
+            return Err(format!(
+                "the hulls' line_width has to be positive, not {}",
+                self.line_width
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draw the blobs around `particles`, where each is seen and the color of its trail, faded as
+    /// `layer` fades them.
+    pub fn draw(&self, particles: &[(Point2, Hsla)], layer: &LayerConfig, draw: &Draw) {
+        let positions: Vec<Point2> = particles.iter().map(|&(pos, _)| pos).collect();
+        let groups: Vec<Vec<usize>> = if self.clusters {
+            let labels = geometry::clusters(&positions, self.radius, self.least);
+            let count = labels.iter().flatten().max().map_or(0, |&most| most + 1);
+            let mut groups = vec![Vec::new(); count];
+            for (i, label) in labels.into_iter().enumerate() {
+                if let Some(label) = label {
+                    groups[label].push(i);
+                }
+            }
+            groups
+        } else {
+            vec![(0..particles.len()).collect()]
+        };
+        for group in groups {
+            let points: Vec<Point2> = group.iter().map(|&i| positions[i]).collect();
+            let hull = geometry::convex_hull(&points);
+            if hull.is_empty() {
+                continue;
+            }
+            let blob = geometry::inflate(&hull, self.padding, CORNER_SEGMENTS);
+            let sum = (group.iter())
+                .map(|&i| particles[i].1.into_lin_srgba())
+                .fold([0.0; 3], |[r, g, b], color| {
+                    [r + color.red, g + color.green, b + color.blue]
+                });
+            let [r, g, b] = sum.map(|channel| channel / group.len() as f32);
+            let color = |alpha: f32| layer.fade(LinSrgba::new(r, g, b, alpha));
+            if self.fill > 0.0 && blob.len() >= 3 {
+                draw.polygon()
+                    .points(blob.iter().copied())
+                    .color(color(self.fill));
+            }
+            if self.outline > 0.0 {
+                draw.polyline()
+                    .weight(self.line_width)
+                    .points_closed(blob)
+                    .color(color(self.outline));
+            }
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{BTreeMap, HashMap};
+
+use nannou::prelude::Key;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Something a hotkey can trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Reseed,
+    Help,
+    Panel,
+    NextCue,
+    Minimap,
+    Labels,
+    Velocities,
+    Stats,
+    Diagnostics,
+    Legend,
+    ClassColors,
+    TrailStyle,
+    View,
+    Palette,
+    Pause,
+    Step,
+    Faster,
+    Slower,
+    SaveState,
+    LoadState,
+    Follow,
+    ResetCamera,
+    KeyCamera,
+    Dice,
+    Brush,
+    Bake,
+    Unbake,
+    MarkIn,
+    MarkOut,
+    ExportClip,
+    Svg,
+    Preset1,
+    Preset2,
+    Preset3,
+    Preset4,
+    Preset5,
+    Preset6,
+    Preset7,
+    Preset8,
+    Preset9,
+    Rate1,
+    Rate2,
+    Rate3,
+    Rate4,
+    Rate5,
+}
+
+impl Action {
+    pub const ALL: [Action; 45] = [
+        Action::Reseed,
+        Action::Help,
+        Action::Panel,
+        Action::NextCue,
+        Action::Minimap,
+        Action::Labels,
+        Action::Velocities,
+        Action::Stats,
+        Action::Diagnostics,
+        Action::Legend,
+        Action::ClassColors,
+        Action::TrailStyle,
+        Action::View,
+        Action::Palette,
+        Action::Pause,
+        Action::Step,
+        Action::Faster,
+        Action::Slower,
+        Action::SaveState,
+        Action::LoadState,
+        Action::Follow,
+        Action::ResetCamera,
+        Action::KeyCamera,
+        Action::Dice,
+        Action::Brush,
+        Action::Bake,
+        Action::Unbake,
+        Action::MarkIn,
+        Action::MarkOut,
+        Action::ExportClip,
+        Action::Svg,
+        Action::Preset1,
+        Action::Preset2,
+        Action::Preset3,
+        Action::Preset4,
+        Action::Preset5,
+        Action::Preset6,
+        Action::Preset7,
+        Action::Preset8,
+        Action::Preset9,
+        Action::Rate1,
+        Action::Rate2,
+        Action::Rate3,
+        Action::Rate4,
+        Action::Rate5,
+    ];
+
+    fn default_key(self) -> Key {
+        match self {
+            Action::Reseed => Key::R,
+            // the key that makes `?` when shifted, on most layouts
+            Action::Help => Key::Slash,
+            Action::Panel => Key::Tab,
+            Action::NextCue => Key::PageDown,
+            Action::Minimap => Key::M,
+            Action::Labels => Key::I,
+            Action::Velocities => Key::V,
+            Action::Stats => Key::F3,
+            Action::Diagnostics => Key::F1,
+            Action::Legend => Key::O,
+            Action::ClassColors => Key::C,
+            Action::TrailStyle => Key::T,
+            Action::View => Key::G,
+            Action::Palette => Key::P,
+            Action::Pause => Key::Space,
+            Action::Step => Key::Period,
+            // the key that makes `+` when shifted, on most layouts
+            Action::Faster => Key::Equals,
+            Action::Slower => Key::Minus,
+            Action::SaveState => Key::F5,
+            Action::LoadState => Key::F9,
+            Action::Follow => Key::F,
+            Action::ResetCamera => Key::Key0,
+            Action::KeyCamera => Key::J,
+            Action::Dice => Key::D,
+            Action::Brush => Key::B,
+            Action::Bake => Key::K,
+            Action::Unbake => Key::Back,
+            Action::MarkIn => Key::LBracket,
+            Action::MarkOut => Key::RBracket,
+            Action::ExportClip => Key::Backslash,
+            Action::Svg => Key::S,
+            Action::Preset1 => Key::Key1,
+            Action::Preset2 => Key::Key2,
+            Action::Preset3 => Key::Key3,
+            Action::Preset4 => Key::Key4,
+            Action::Preset5 => Key::Key5,
+            Action::Preset6 => Key::Key6,
+            Action::Preset7 => Key::Key7,
+            Action::Preset8 => Key::Key8,
+            Action::Preset9 => Key::Key9,
+            // the same numbers as the presets, with Shift held
+            Action::Rate1 => Key::Key1,
+            Action::Rate2 => Key::Key2,
+            Action::Rate3 => Key::Key3,
+            Action::Rate4 => Key::Key4,
+            Action::Rate5 => Key::Key5,
+        }
+    }
+
+    /// The key the action is bound to unless the config binds another.
+    fn default_chord(self) -> Chord {
+        Chord {
+            key: self.default_key(),
+            shift: self.stars().is_some(),
+        }
+    }
+
+    /// The preset the action switches to, by its index, if it's one of the preset actions.
+    pub fn preset(self) -> Option<usize> {
+        match self {
+            Action::Preset1 => Some(0),
+            Action::Preset2 => Some(1),
+            Action::Preset3 => Some(2),
+            Action::Preset4 => Some(3),
+            Action::Preset5 => Some(4),
+            Action::Preset6 => Some(5),
+            Action::Preset7 => Some(6),
+            Action::Preset8 => Some(7),
+            Action::Preset9 => Some(8),
+            _ => None,
+        }
+    }
+
+    /// The stars the action rates the seed, if it's one of the rating actions.
+    pub fn stars(self) -> Option<u8> {
+        match self {
+            Action::Rate1 => Some(1),
+            Action::Rate2 => Some(2),
+            Action::Rate3 => Some(3),
+            Action::Rate4 => Some(4),
+            Action::Rate5 => Some(5),
+            _ => None,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Reseed => "reseed the swarm",
+            Action::Help => "show or hide this help",
+            Action::Panel => "show or hide the control panel",
+            Action::NextCue => "go to the next cue",
+            Action::Minimap => "show or hide the minimap when zoomed in",
+            Action::Labels => "show or hide each particle's index",
+            Action::Velocities => "show or hide each particle's velocity",
+            Action::Stats => "show or hide the frame rate and overdraw",
+            Action::Diagnostics => {
+                "show or hide the frame and physics times, vertex count, temperature and entropy"
+            }
+            Action::Legend => "show or hide the legend of orbit classes",
+            Action::ClassColors => "ring the particles in their orbit's class color",
+            Action::TrailStyle => "draw the trails in the next style",
+            Action::View => "show the next view: the orbits, the radius chart or phase space",
+            Action::Palette => "color the trails from the next palette",
+            Action::Pause => "pause or resume the simulation",
+            Action::Step => "pause, and step the simulation on one tick",
+            Action::Faster => "speed the simulation up",
+            Action::Slower => "slow the simulation down",
+            Action::SaveState => "save the state of the simulation",
+            Action::LoadState => "go back to the state last saved",
+            Action::Follow => "follow the particle nearest the mouse, or stop following it",
+            Action::ResetCamera => "undo any zooming, panning and following",
+            Action::KeyCamera => "key the camera's path to the view, while recording automation",
+            Action::Dice => "roll the dice for a new look",
+            Action::Brush => "turn the brush on or off, to paint pushes with the mouse",
+            Action::Bake => "bake the trails into a still layer and clear them",
+            Action::Unbake => "clear away the layers baked",
+            Action::MarkIn => "mark where a clip of the run goes in",
+            Action::MarkOut => "mark where a clip of the run goes out",
+            Action::ExportClip => "export the clip marked, played back from the rewind",
+            Action::Svg => "export the trails as an SVG",
+            Action::Preset1 => "switch to the 1st preset",
+            Action::Preset2 => "switch to the 2nd preset",
+            Action::Preset3 => "switch to the 3rd preset",
+            Action::Preset4 => "switch to the 4th preset",
+            Action::Preset5 => "switch to the 5th preset",
+            Action::Preset6 => "switch to the 6th preset",
+            Action::Preset7 => "switch to the 7th preset",
+            Action::Preset8 => "switch to the 8th preset",
+            Action::Preset9 => "switch to the 9th preset",
+            Action::Rate1 => "rate the seed 1 star",
+            Action::Rate2 => "rate the seed 2 stars",
+            Action::Rate3 => "rate the seed 3 stars",
+            Action::Rate4 => "rate the seed 4 stars",
+            Action::Rate5 => "rate the seed 5 stars",
+        }
+    }
+}
+
+/// A key, pressed with Shift held or without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Chord {
+    pub key: Key,
+    pub shift: bool,
+}
+
+/// Which key triggers which action.
+pub struct Keymap {
+    actions: HashMap<Chord, Action>,
+}
+
+impl Keymap {
+    /// Bind every action to its default key, unless `overrides` names another key for it.
+    pub fn new(overrides: &BTreeMap<Action, String>) -> Result<Self, String> {
+        let mut actions = HashMap::new();
+        for action in Action::ALL {
+            let chord = match overrides.get(&action) {
+                Some(name) => parse_chord(name)
+                    .ok_or_else(|| format!("unknown key {name:?} for {action:?}"))?,
+                None => action.default_chord(),
+            };
+            if let Some(other) = actions.insert(chord, action) {
+                return Err(format!(
+                    "{:?} is bound to both {other:?} and {action:?}",
+                    chord_name(chord)
+                ));
+            }
+        }
+        Ok(Keymap { actions })
+    }
+
+    /// The action `key` triggers, pressed with Shift held if `shift`. A key with nothing bound to
+    /// it shifted triggers what it does unshifted, so `?` triggers what `/` does.
+    pub fn action(&self, key: Key, shift: bool) -> Option<Action> {
+        let action = |shift| self.actions.get(&Chord { key, shift }).copied();
+        action(shift).or_else(|| action(false))
+    }
+
+    /// Every action along with the key bound to it, in the order of `Action::ALL`.
+    pub fn bindings(&self) -> Vec<(Action, Chord)> {
+        let mut bindings: Vec<_> = self.actions.iter().map(|(k, a)| (*a, *k)).collect();
+        bindings.sort();
+        bindings
+    }
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        const KEY_NAMES: &[(&str, Key)] = &[$((stringify!($key), Key::$key)),*];
+    };
+}
+
+key_names! {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Escape, Tab, Space, Return, Back, Delete, Insert, Home, End, PageUp, PageDown,
+    Left, Right, Up, Down,
+    Apostrophe, Backslash, Comma, Equals, Grave, LBracket, Minus, Period, Plus, RBracket,
+    Semicolon, Slash,
+}
+
+/// Look up a key by the name of its `Key` variant, e.g. `"R"`, `"F5"` or `"PageDown"`, with
+/// Shift held for a name starting `"Shift+"`.
+fn parse_chord(name: &str) -> Option<Chord> {
+    let prefix = "shift+";
+    // sliced with `get`, so a name with a character straddling where the prefix ends is no panic
+    let shifted = name.len() > prefix.len()
+        && (name.get(..prefix.len())).is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+    let name = if shifted { &name[prefix.len()..] } else { name };
+    parse_key(name).map(|key| Chord {
+        key,
+        shift: shifted,
+    })
+}
+
+/// Look up a key by the name of its `Key` variant.
+fn parse_key(name: &str) -> Option<Key> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+pub fn key_name(key: Key) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map_or("?", |(name, _)| name)
+}
+
+/// The name of `chord`, as the config would give it.
+pub fn chord_name(chord: Chord) -> String {
+    let name = key_name(chord.key);
+    if chord.shift {
+        format!("Shift+{name}")
+    } else {
+        name.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords_parse_whatever_they_are_called() {
+        let shift_a = Chord {
+            key: Key::A,
+            shift: true,
+        };
+        assert_eq!(parse_chord("Shift+A"), Some(shift_a));
+        assert_eq!(parse_chord("shift+a"), Some(shift_a));
+        assert_eq!(parse_chord("F5").map(|chord| chord.shift), Some(false));
+        assert_eq!(parse_chord("Shift+"), None);
+        assert_eq!(parse_chord("Shift→A"), None);
+        assert_eq!(parse_chord("Shif→"), None);
+        assert_eq!(parse_chord("→"), None);
+    }
+}
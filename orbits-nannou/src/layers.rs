@@ -0,0 +1,376 @@
+//! The layers the scene is drawn in, from the background up to the overlays, each shown or hidden,
+//! faded and blended over those under it on its own, from the config and then the panel. The
+//! background is always at the bottom and the overlays on top, and those between are drawn in
+//! whatever order the config or the panel puts them in.
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::mask::MaskConfig;
+
+/// One of the layers the scene is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    /// The background, the nebula and the stars.
+    Background,
+    /// The circle of the average orbit.
+    Guides,
+    /// The trails baked into still layers.
+    Baked,
+    /// The live trails, with their highlights and ghosts.
+    Trails,
+    /// Lines between the particles near each other, as `[constellation]` strings them.
+    Constellation,
+    /// The Delaunay triangles or Voronoi cells of the particles, as `[tessellation]` draws them.
+    Tessellation,
+    /// Soft blobs around the swarm or the clumps within it, as `[hulls]` outlines them.
+    Hulls,
+    /// The masses, and a dot at the head of each trail if `head_size` is set.
+    Heads,
+    /// The labels, arrows, rings, minimap and panels drawn over the window.
+    Overlays,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 9] = [
+        Layer::Background,
+        Layer::Guides,
+        Layer::Baked,
+        Layer::Trails,
+        Layer::Constellation,
+        Layer::Tessellation,
+        Layer::Hulls,
+        Layer::Heads,
+        Layer::Overlays,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Layer::Background => "background",
+            Layer::Guides => "guides",
+            Layer::Baked => "baked",
+            Layer::Trails => "trails",
+            Layer::Constellation => "constellation",
+            Layer::Tessellation => "tessellation",
+            Layer::Hulls => "hulls",
+            Layer::Heads => "heads",
+            Layer::Overlays => "overlays",
+        }
+    }
+
+    /// Whether the layer can be drawn anywhere but at the bottom or on top.
+    pub fn is_movable(self) -> bool {
+        !matches!(self, Layer::Background | Layer::Overlays)
+    }
+
+    /// Whether the layer can be left out of the order, as those added since orders were first
+    /// written can, to be drawn just over the trails.
+    pub fn is_optional(self) -> bool {
+        matches!(
+            self,
+            Layer::Constellation | Layer::Tessellation | Layer::Hulls
+        )
+    }
+
+    /// Whether the layer can be masked, as only the trails and what's drawn along them can.
+    pub fn is_maskable(self) -> bool {
+        matches!(self, Layer::Baked | Layer::Trails | Layer::Heads)
+    }
+}
+
+/// How a layer is combined with what's under it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Blend {
+    /// Drawn over it, as far as it's opaque.
+    #[default]
+    Normal,
+    /// Added to it, brightening it.
+    Add,
+    /// Taken away from it, darkening it.
+    Subtract,
+}
+
+impl Blend {
+    pub const ALL: [Blend; 3] = [Blend::Normal, Blend::Add, Blend::Subtract];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Blend::Normal => "normal",
+            Blend::Add => "add",
+            Blend::Subtract => "subtract",
+        }
+    }
+
+    /// How colors are blended, as drawn with or, if `premultiplied`, already multiplied by their
+    /// alpha.
+    pub fn component(self, premultiplied: bool) -> wgpu::BlendComponent {
+        let src_factor = if premultiplied {
+            wgpu::BlendFactor::One
+        } else {
+            wgpu::BlendFactor::SrcAlpha
+        };
+        let (dst_factor, operation) = match self {
+            Blend::Normal => (
+                wgpu::BlendFactor::OneMinusSrcAlpha,
+                wgpu::BlendOperation::Add,
+            ),
+            Blend::Add => (wgpu::BlendFactor::One, wgpu::BlendOperation::Add),
+            Blend::Subtract => (
+                wgpu::BlendFactor::One,
+                wgpu::BlendOperation::ReverseSubtract,
+            ),
+        };
+        wgpu::BlendComponent {
+            src_factor,
+            dst_factor,
+            operation,
+        }
+    }
+}
+
+/// How one layer is drawn.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayerConfig {
+    pub visible: bool,
+    /// From 0 for none of the layer showing to 1 for all of it.
+    pub opacity: f32,
+    /// How the layer is blended over those under it. The background is under nothing, so this
+    /// does nothing for it.
+    pub blend: Blend,
+    /// The shape the layer is confined to, or kept out of.
+    pub mask: Option<MaskConfig>,
+    /// How fast the layer turns about the middle of the system, in degrees a second,
+    /// counterclockwise. The overlays are drawn over the window, so they don't.
+    pub spin: f32,
+    /// Most the layer's size strays either way as it breathes in and out, as a fraction of it.
+    pub breathe: f32,
+    /// Seconds a breath in and out takes.
+    pub breathe_seconds: f32,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        LayerConfig {
+            visible: true,
+            opacity: 1.0,
+            blend: Blend::Normal,
+            mask: None,
+            spin: 0.0,
+            breathe: 0.0,
+            breathe_seconds: 30.0,
+        }
+    }
+}
+
+impl LayerConfig {
+    /// A layer hidden until it's given a section of its own.
+    fn hidden() -> Self {
+        LayerConfig {
+            visible: false,
+            ..LayerConfig::default()
+        }
+    }
+
+    /// `draw` blending as the layer does, or nothing if the layer doesn't show.
+    pub fn draw(&self, draw: &Draw) -> Option<Draw> {
+        self.shows()
+            .then(|| draw.color_blend(self.blend.component(false)))
+    }
+
+    /// `draw` turned and scaled about the middle of the world as far as the layer has drifted in
+    /// `drift` seconds.
+    pub fn drift(&self, drift: f32, draw: &Draw) -> Draw {
+        let breath = (drift / self.breathe_seconds * TAU).sin();
+        draw.rotate((self.spin * drift).to_radians())
+            .scale(1.0 + self.breathe * breath)
+    }
+
+    pub fn drifts(&self) -> bool {
+        self.spin != 0.0 || self.breathe != 0.0
+    }
+
+    pub fn shows(&self) -> bool {
+        self.visible && self.opacity > 0.0
+    }
+
+    /// `color` faded by the layer's opacity.
+    pub fn fade(&self, color: impl IntoLinSrgba<f32>) -> LinSrgba {
+        let mut color = color.into_lin_srgba();
+        color.alpha *= self.opacity;
+        color
+    }
+
+    /// Fade everything drawn on `draw` so far as far as the layer's opacity has it, or away
+    /// altogether once it's hidden, for the layer at the bottom, which there's nothing under to
+    /// blend with. `extent` is how far across the world it goes.
+    pub fn veil(&self, extent: f32, draw: &Draw) {
+        let opacity = if self.visible { self.opacity } else { 0.0 };
+        if opacity >= 1.0 {
+            return;
+        }
+        let fade = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Zero,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+        draw.color_blend(fade)
+            .alpha_blend(fade)
+            .rect()
+            .w_h(2.0 * extent, 2.0 * extent)
+            .color(rgba(0.0, 0.0, 0.0, 1.0 - opacity));
+    }
+}
+
+/// The `[layers]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayersConfig {
+    pub background: LayerConfig,
+    pub guides: LayerConfig,
+    pub baked: LayerConfig,
+    pub trails: LayerConfig,
+    /// Hidden unless it's given a section of its own, as are the tessellation and the hulls.
+    pub constellation: LayerConfig,
+    pub tessellation: LayerConfig,
+    pub hulls: LayerConfig,
+    pub heads: LayerConfig,
+    pub overlays: LayerConfig,
+    /// The order the layers between the background and the overlays are drawn in, bottom first,
+    /// each of them once, though the constellation, the tessellation and the hulls can be left
+    /// out for them to go over the trails.
+    pub order: Vec<Layer>,
+    /// Size of the dot at the head of each trail, in world units, or 0 for none.
+    pub head_size: f32,
+}
+
+impl Default for LayersConfig {
+    fn default() -> Self {
+        LayersConfig {
+            background: LayerConfig::default(),
+            guides: LayerConfig::default(),
+            baked: LayerConfig::default(),
+            trails: LayerConfig::default(),
+            constellation: LayerConfig::hidden(),
+            tessellation: LayerConfig::hidden(),
+            hulls: LayerConfig::hidden(),
+            heads: LayerConfig::default(),
+            overlays: LayerConfig::default(),
+            order: vec![
+                Layer::Guides,
+                Layer::Baked,
+                Layer::Trails,
+                Layer::Constellation,
+                Layer::Tessellation,
+                Layer::Hulls,
+                Layer::Heads,
+            ],
+            head_size: 0.0,
+        }
+    }
+}
+
+impl LayersConfig {
+    /// Whether the order has each of the layers it can have once, only the layers that can be
+    /// masked are, and every layer breathes as it can.
+    pub fn check(&self) -> Result<(), String> {
+        for layer in Layer::ALL {
+            let config = self.get(layer);
+            if config.breathe_seconds <= 0.0 {
+                return Err(format!(
+                    "{}'s breathe_seconds has to be positive, not {}",
+                    layer.label(),
+                    config.breathe_seconds
+                ));
+            }
+            if !(0.0..1.0).contains(&config.breathe) {
+                return Err(format!(
+                    "{}'s breathe has to be from 0 up to 1, not {}",
+                    layer.label(),
+                    config.breathe
+                ));
+            }
+        }
+        let masked = |layer: &&Layer| self.get(**layer).mask.is_some();
+        if let Some(layer) = Layer::ALL.iter().filter(masked).find(|l| !l.is_maskable()) {
+            return Err(format!("{} can't be masked", layer.label()));
+        }
+        for layer in Layer::ALL.iter().filter(|layer| layer.is_movable()) {
+            let count = self.order.iter().filter(|l| *l == layer).count();
+            if count != 1 && !(layer.is_optional() && count == 0) {
+                return Err(format!(
+                    "the layer order needs {} in it once",
+                    layer.label()
+                ));
+            }
+        }
+        match self.order.iter().find(|layer| !layer.is_movable()) {
+            Some(layer) => Err(format!(
+                "{} is always drawn where it is, so it can't be in the layer order",
+                layer.label()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get(&self, layer: Layer) -> &LayerConfig {
+        match layer {
+            Layer::Background => &self.background,
+            Layer::Guides => &self.guides,
+            Layer::Baked => &self.baked,
+            Layer::Trails => &self.trails,
+            Layer::Constellation => &self.constellation,
+            Layer::Tessellation => &self.tessellation,
+            Layer::Hulls => &self.hulls,
+            Layer::Heads => &self.heads,
+            Layer::Overlays => &self.overlays,
+        }
+    }
+
+    pub fn get_mut(&mut self, layer: Layer) -> &mut LayerConfig {
+        match layer {
+            Layer::Background => &mut self.background,
+            Layer::Guides => &mut self.guides,
+            Layer::Baked => &mut self.baked,
+            Layer::Trails => &mut self.trails,
+            Layer::Constellation => &mut self.constellation,
+            Layer::Tessellation => &mut self.tessellation,
+            Layer::Hulls => &mut self.hulls,
+            Layer::Heads => &mut self.heads,
+            Layer::Overlays => &mut self.overlays,
+        }
+    }
+
+    /// The layers between the background and the overlays, bottom first, as they're drawn, any
+    /// the order leaves out that can be just over the trails.
+    pub fn order(&self) -> Vec<Layer> {
+        let mut order = self.order.clone();
+        let left_out: Vec<Layer> = (Layer::ALL.into_iter())
+            .filter(|layer| layer.is_optional() && !order.contains(layer))
+            .collect();
+        let trails = order.iter().position(|&layer| layer == Layer::Trails);
+        let at = trails.map_or(0, |i| i + 1);
+        order.splice(at..at, left_out);
+        order
+    }
+
+    /// Every layer, bottom first, as they're drawn.
+    pub fn stack(&self) -> Vec<Layer> {
+        let mut stack = vec![Layer::Background];
+        stack.extend(self.order());
+        stack.push(Layer::Overlays);
+        stack
+    }
+
+    /// Swap the layer `i` places up the order with the one above it.
+    pub fn raise(&mut self, i: usize) {
+        self.order = self.order();
+        if i + 1 < self.order.len() {
+            self.order.swap(i, i + 1);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! A dashboard of the one simulation, the window partitioned into the `[[layout.panes]]`, each
+//! showing it its own way: the orbits, phase space, the radius chart, or the stats. The first pane
+//! is the piece's own view, the one the camera moves, the mouse plays with, and the hotkeys and
+//! presets switch the view of. The rest each look on through a camera of their own, fixed on the
+//! whole of what they show unless they're given a `middle` and a `zoom`.
+
+use nannou::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::chart::View;
+use crate::viewport::Viewport;
+
+/// What a pane shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Show {
+    Orbits,
+    RadiusChart,
+    /// The orbits, with phase space beside them.
+    PhaseSpace,
+    /// The frame and physics times, the particle count and the legend of orbit classes.
+    Stats,
+}
+
+impl Show {
+    /// The view of the simulation it shows, unless it's the stats.
+    pub fn view(self) -> Option<View> {
+        match self {
+            Show::Orbits => Some(View::Orbits),
+            Show::RadiusChart => Some(View::RadiusChart),
+            Show::PhaseSpace => Some(View::PhaseSpace),
+            Show::Stats => None,
+        }
+    }
+}
+
+/// One of the `[[layout.panes]]`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Pane {
+    pub show: Show,
+    /// The part of the window it takes up, as `[left, top, width, height]` in fractions of the
+    /// window from its top left corner.
+    pub rect: [f32; 4],
+    /// How far its camera is zoomed in on what it shows, for all but the first pane.
+    #[serde(default = "unzoomed")]
+    pub zoom: f32,
+    /// The point of the world its camera looks at, for all but the first pane, or left out for
+    /// the middle of what it shows.
+    pub middle: Option<[f32; 2]>,
+}
+
+fn unzoomed() -> f32 {
+    1.0
+}
+
+/// The `[layout]` section of the config.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// The panes the window is partitioned into, the first the piece's own view, or left out for
+    /// the one view filling the window.
+    pub panes: Vec<Pane>,
+}
+
+impl LayoutConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self
+            .panes
+            .first()
+            .is_some_and(|pane| pane.show == Show::Stats)
+        {
+            return Err("the layout's first pane has to show the simulation, not the stats".into());
+        }
+        for (i, pane) in self.panes.iter().enumerate() {
+            let [left, top, width, height] = pane.rect;
+            if width <= 0.0 || height <= 0.0 {
+                return Err(format!(
+                    "the layout's pane {i} has to have a positive size, not {width} by {height}"
+                ));
+            }
+            if left < 0.0 || top < 0.0 || left + width > 1.0 || top + height > 1.0 {
+                return Err(format!(
+                    "the layout's pane {i} has to be within the window, not {:?}",
+                    pane.rect
+                ));
+            }
+            if pane.zoom <= 0.0 {
+                return Err(format!(
+                    "the zoom of the layout's pane {i} has to be positive, not {}",
+                    pane.zoom
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The parts of `win` the panes showing the stats take up.
+    pub fn stats(&self, win: Rect) -> Vec<Rect> {
+        (self.panes.iter())
+            .filter(|pane| pane.show == Show::Stats)
+            .map(|pane| pane.rect(win))
+            .collect()
+    }
+}
+
+impl Pane {
+    /// The part of `win` it takes up.
+    pub fn rect(&self, win: Rect) -> Rect {
+        let [left, top, width, height] = self.rect;
+        let corner =
+            |across: f32, down: f32| pt2(win.left() + across * win.w(), win.top() - down * win.h());
+        Rect::from_corners(corner(left, top), corner(left + width, top + height))
+    }
+
+    /// The view of the world through its own camera, `view` fitted into it as it is in `win`, as
+    /// a view of the whole window.
+    pub fn viewport(&self, view: View, win: Rect) -> Viewport {
+        let rect = self.rect(win);
+        let fit = fit(view, Rect::from_wh(rect.wh()));
+        let middle = self.middle.map_or(fit.middle, Point2::from);
+        Viewport::new(fit.scale * self.zoom, middle).within(rect)
+    }
+}
+
+/// The view of the world fitting the whole of what `view` shows into `win`.
+pub fn fit(view: View, win: Rect) -> Viewport {
+    match view.region() {
+        Some(region) => Viewport::fitting(region, win),
+        None => Viewport::whole_system(win),
+    }
+}
@@ -0,0 +1,240 @@
+//! Streams a low resolution copy of the piece to an LED matrix over UDP, so it can run on LED
+//! hardware. Frames are sent as DDP, which WLED speaks natively, or as E1.31 (sACN), which most
+//! other controllers do.
+
+use std::cell::Cell;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+
+use nannou::rand::random;
+use nannou::wgpu;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::export::{self, Offscreen, Shot};
+use crate::style::Style;
+use crate::transition::TransitionConfig;
+
+/// Pixels rendered across each LED, in each direction, before they're averaged down to it.
+pub const SUPERSAMPLE: u32 = 4;
+/// Most pixel data a DDP packet carries, 480 pixels of RGB.
+const DDP_DATA: usize = 1440;
+/// DMX channels used of each E1.31 universe, 170 pixels of RGB.
+const E131_CHANNELS: usize = 510;
+
+/// How frames are sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    /// Distributed Display Protocol, usually on port 4048.
+    #[default]
+    Ddp,
+    /// Streaming DMX over ACN, usually on port 5568.
+    E131,
+}
+
+/// The `[leds]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LedsConfig {
+    pub protocol: Protocol,
+    /// LEDs across the matrix.
+    pub width: u32,
+    /// LEDs down the matrix.
+    pub height: u32,
+    /// Whether every other row runs right to left, as in matrices wired in a zigzag.
+    pub serpentine: bool,
+    /// Exponent applied to each channel after encoding it as sRGB. 1 sends the colors the window
+    /// shows; around 2.2 suits LEDs that don't correct for gamma themselves.
+    pub gamma: f32,
+    /// Multiplier on every channel, as LED matrices are bright.
+    pub brightness: f32,
+    /// E1.31 universe of the first LEDs. Each universe takes 170, the rest spilling into the next.
+    pub universe: u16,
+}
+
+impl Default for LedsConfig {
+    fn default() -> Self {
+        LedsConfig {
+            protocol: Protocol::Ddp,
+            width: 32,
+            height: 16,
+            serpentine: false,
+            gamma: 1.0,
+            brightness: 0.5,
+            universe: 1,
+        }
+    }
+}
+
+/// An LED matrix being streamed to.
+pub struct Leds {
+    config: LedsConfig,
+    addr: SocketAddr,
+    socket: UdpSocket,
+    offscreen: Offscreen,
+    /// Counts packets for the receiver to spot ones lost or reordered.
+    sequence: Cell<u8>,
+    /// Identifies this sender to E1.31 receivers.
+    cid: [u8; 16],
+}
+
+impl Leds {
+    pub fn new(
+        addr: SocketAddr,
+        config: LedsConfig,
+        transition: TransitionConfig,
+    ) -> Result<Self, String> {
+        if config.width == 0 || config.height == 0 {
+            return Err("an LED matrix needs at least one LED".into());
+        }
+        let bind: SocketAddr = if addr.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+        let socket = UdpSocket::bind(bind).map_err(|err| format!("{bind}: {err}"))?;
+        Ok(Leds {
+            config,
+            addr,
+            socket,
+            offscreen: Offscreen::new(transition, Style::default()),
+            sequence: Cell::new(0),
+            cid: random(),
+        })
+    }
+
+    /// Size of the shots `send` takes, in pixels.
+    pub fn shot_size(&self) -> [u32; 2] {
+        [self.config.width, self.config.height].map(|side| side * SUPERSAMPLE)
+    }
+
+    /// Render `shot` at `shot_size`, average it down to one color per LED and send it.
+    pub fn send(&self, device_queue: &Arc<wgpu::DeviceQueuePair>, shot: Shot) {
+        let Some(frame) = self.offscreen.capture(device_queue, self.shot_size(), shot) else {
+            return;
+        };
+        let rgb = self.downsample(&frame.pixels());
+        let sent = match self.config.protocol {
+            Protocol::Ddp => self.send_ddp(&rgb),
+            Protocol::E131 => self.send_e131(&rgb),
+        };
+        if let Err(err) = sent {
+            eprintln!("failed to send to the LEDs at {}: {err}", self.addr);
+        }
+    }
+
+    /// The color of each LED, in the order they're wired, as 8 bit RGB.
+    fn downsample(&self, pixels: &[[f32; 4]]) -> Vec<u8> {
+        let LedsConfig {
+            width,
+            height,
+            gamma,
+            brightness,
+            ..
+        } = self.config;
+        let stride = (width * SUPERSAMPLE) as usize;
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let x = if self.config.serpentine && y % 2 == 1 {
+                    width - 1 - x
+                } else {
+                    x
+                };
+                let mut sum = [0.0; 3];
+                for dy in 0..SUPERSAMPLE {
+                    let row = (y * SUPERSAMPLE + dy) as usize * stride;
+                    for dx in 0..SUPERSAMPLE {
+                        let pixel = pixels[row + (x * SUPERSAMPLE + dx) as usize];
+                        for c in 0..3 {
+                            sum[c] += pixel[c];
+                        }
+                    }
+                }
+                for c in sum {
+                    let linear = c / (SUPERSAMPLE * SUPERSAMPLE) as f32;
+                    let value = export::srgb(linear).clamp(0.0, 1.0).powf(gamma) * brightness;
+                    rgb.push((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+                }
+            }
+        }
+        rgb
+    }
+
+    fn send_ddp(&self, rgb: &[u8]) -> std::io::Result<()> {
+        let chunks = rgb.chunks(DDP_DATA).count();
+        for (i, chunk) in rgb.chunks(DDP_DATA).enumerate() {
+            let offset = (i * DDP_DATA) as u32;
+            // version 1, with the push flag on the last packet to show the frame
+            let flags = if i == chunks - 1 { 0x41 } else { 0x40 };
+            let mut packet = vec![
+                flags,
+                // 1 to 15, as 0 tells the receiver not to check
+                self.next_sequence() % 15 + 1,
+                // RGB, 8 bits per channel
+                0x0b,
+                // the default output device
+                0x01,
+            ];
+            packet.extend(offset.to_be_bytes());
+            packet.extend((chunk.len() as u16).to_be_bytes());
+            packet.extend(chunk);
+            self.socket.send_to(&packet, self.addr)?;
+        }
+        Ok(())
+    }
+
+    fn send_e131(&self, rgb: &[u8]) -> std::io::Result<()> {
+        for (i, channels) in rgb.chunks(E131_CHANNELS).enumerate() {
+            let universe = self.config.universe.wrapping_add(i as u16);
+            let sequence = self.next_sequence();
+            let packet = e131_packet(&self.cid, universe, sequence, channels);
+            self.socket.send_to(&packet, self.addr)?;
+        }
+        Ok(())
+    }
+
+    fn next_sequence(&self) -> u8 {
+        let sequence = self.sequence.get().wrapping_add(1);
+        self.sequence.set(sequence);
+        sequence
+    }
+}
+
+/// An E1.31 data packet carrying `channels` to `universe`.
+fn e131_packet(cid: &[u8; 16], universe: u16, sequence: u8, channels: &[u8]) -> Vec<u8> {
+    let length = 126 + channels.len();
+    // each layer starts with its length from there on, flagged as in ANSI E1.17
+    let pdu_length = |start: usize| (0x7000 | (length - start) as u16).to_be_bytes();
+    let mut source_name = [0; 64];
+    let name = env!("CARGO_PKG_NAME").as_bytes();
+    source_name[..name.len()].copy_from_slice(name);
+
+    let mut packet = Vec::with_capacity(length);
+    // root layer
+    packet.extend(0x0010u16.to_be_bytes());
+    packet.extend(0x0000u16.to_be_bytes());
+    packet.extend(b"ASC-E1.17\0\0\0");
+    packet.extend(pdu_length(16));
+    packet.extend(0x0000_0004u32.to_be_bytes());
+    packet.extend(cid);
+    // framing layer
+    packet.extend(pdu_length(38));
+    packet.extend(0x0000_0002u32.to_be_bytes());
+    packet.extend(source_name);
+    packet.push(100);
+    packet.extend(0u16.to_be_bytes());
+    packet.push(sequence);
+    packet.push(0);
+    packet.extend(universe.to_be_bytes());
+    // DMP layer, starting from address 0 with the null start code
+    packet.extend(pdu_length(115));
+    packet.extend([0x02, 0xa1]);
+    packet.extend(0u16.to_be_bytes());
+    packet.extend(1u16.to_be_bytes());
+    packet.extend((channels.len() as u16 + 1).to_be_bytes());
+    packet.push(0);
+    packet.extend(channels);
+    packet
+}
@@ -0,0 +1,213 @@
+//! Drives stage lighting from the piece over Art-Net, so the room can follow what's on screen. A
+//! few dominant colors of the frame and the brightness of its center are worked out every frame
+//! and sent as DMX channels.
+
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::Arc;
+
+use nannou::wgpu;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::export::{self, Offscreen, Shot};
+use crate::style::Style;
+use crate::transition::TransitionConfig;
+
+/// Size of the frame the colors are picked from, small as only its broad strokes matter.
+pub const SHOT_SIZE: [u32; 2] = [64, 64];
+/// Rounds of k-means each frame. Starting from the last frame's colors, a few are plenty.
+const ROUNDS: usize = 4;
+/// Channels in a DMX universe.
+const UNIVERSE_CHANNELS: usize = 512;
+
+/// The `[lighting]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightingConfig {
+    /// Art-Net port address of the universe to send, its net, sub-net and universe packed into
+    /// 15 bits.
+    pub universe: u16,
+    /// DMX address of the first channel. Each color takes three, red, green and blue, most
+    /// dominant first, followed by one for the brightness of the center of the frame.
+    pub address: u16,
+    /// How many of the frame's dominant colors to send.
+    pub colors: usize,
+    /// Seconds for the channels to cover most of the way to a new value, so lights fade rather
+    /// than flicker.
+    pub smoothing: f32,
+}
+
+impl Default for LightingConfig {
+    fn default() -> Self {
+        LightingConfig {
+            universe: 0,
+            address: 1,
+            colors: 4,
+            smoothing: 0.5,
+        }
+    }
+}
+
+/// Sends channels derived from each frame to an Art-Net node.
+pub struct Lighting {
+    config: LightingConfig,
+    addr: SocketAddr,
+    socket: UdpSocket,
+    offscreen: Offscreen,
+    dominant: DominantColors,
+    /// Smoothed channel values, from 0 to 1.
+    channels: Vec<f32>,
+    sequence: u8,
+}
+
+impl Lighting {
+    pub fn new(
+        addr: SocketAddr,
+        config: LightingConfig,
+        transition: TransitionConfig,
+    ) -> Result<Self, String> {
+        let channels = config.colors * 3 + 1;
+        if config.address == 0 || config.address as usize + channels - 1 > UNIVERSE_CHANNELS {
+            return Err(format!(
+                "{channels} lighting channels don't fit in a universe from address {}",
+                config.address
+            ));
+        }
+        let bind: SocketAddr = if addr.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+        let socket = UdpSocket::bind(bind).map_err(|err| format!("{bind}: {err}"))?;
+        Ok(Lighting {
+            config,
+            addr,
+            socket,
+            offscreen: Offscreen::new(transition, Style::default()),
+            dominant: DominantColors::default(),
+            channels: vec![0.0; channels],
+            sequence: 0,
+        })
+    }
+
+    /// Render `shot` at `SHOT_SIZE`, derive the channels from it, and send them. `wall_seconds`
+    /// have passed since the last frame.
+    pub fn send(
+        &mut self,
+        device_queue: &Arc<wgpu::DeviceQueuePair>,
+        shot: Shot,
+        wall_seconds: f32,
+    ) {
+        let Some(frame) = self.offscreen.capture(device_queue, SHOT_SIZE, shot) else {
+            return;
+        };
+        let pixels = frame.pixels();
+        let dominant = self.dominant.update(&pixels, self.config.colors);
+        let mut targets: Vec<f32> = dominant.into_iter().flatten().collect();
+        targets.push(center_brightness(&pixels));
+
+        let blend = 1.0 - (-wall_seconds / self.config.smoothing.max(1e-3)).exp();
+        for (channel, target) in self.channels.iter_mut().zip(targets) {
+            *channel += (target - *channel) * blend;
+        }
+
+        let start = self.config.address as usize - 1;
+        // Art-Net wants an even number of channels
+        let mut dmx = vec![0; (start + self.channels.len()).next_multiple_of(2)];
+        for (slot, channel) in dmx[start..].iter_mut().zip(&self.channels) {
+            *slot = (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        // 1 to 255, as 0 tells the node not to check the order
+        self.sequence = self.sequence % 255 + 1;
+        let packet = art_dmx(self.config.universe, self.sequence, &dmx);
+        if let Err(err) = self.socket.send_to(&packet, self.addr) {
+            eprintln!("failed to send lighting to {}: {err}", self.addr);
+        }
+    }
+}
+
+/// Finds the most common colors in a frame by k-means, carrying the colors found over to start
+/// the next frame's search from, so they only drift as the frame does.
+#[derive(Default)]
+pub struct DominantColors {
+    centroids: Vec<[f32; 3]>,
+}
+
+impl DominantColors {
+    /// The `k` most common colors of a frame of linear `pixels`, in sRGB, most dominant first.
+    pub fn update(&mut self, pixels: &[[f32; 4]], k: usize) -> Vec<[f32; 3]> {
+        // clustered in sRGB, so colors are told apart about as the eye would
+        let colors: Vec<[f32; 3]> = pixels
+            .iter()
+            .map(|&[r, g, b, _]| [r, g, b].map(export::srgb))
+            .collect();
+        if k == 0 {
+            return Vec::new();
+        }
+        if self.centroids.len() != k {
+            self.centroids = (0..k).map(|i| colors[i * colors.len() / k]).collect();
+        }
+
+        let mut counts = vec![0; k];
+        for _ in 0..ROUNDS {
+            let mut sums = vec![[0.0; 3]; k];
+            counts.fill(0);
+            for color in &colors {
+                let nearest = nearest(&self.centroids, color);
+                for c in 0..3 {
+                    sums[nearest][c] += color[c];
+                }
+                counts[nearest] += 1;
+            }
+            // a color no pixel is nearest to stays where it was
+            for ((centroid, sum), &count) in self.centroids.iter_mut().zip(&sums).zip(&counts) {
+                if count > 0 {
+                    *centroid = sum.map(|c| c / count as f32);
+                }
+            }
+        }
+
+        let mut ranked: Vec<_> = self.centroids.iter().copied().zip(counts).collect();
+        ranked.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        ranked.into_iter().map(|(color, _)| color).collect()
+    }
+}
+
+/// Index of the centroid closest to `color`.
+fn nearest(centroids: &[[f32; 3]], color: &[f32; 3]) -> usize {
+    let distance =
+        |centroid: &[f32; 3]| -> f32 { (0..3).map(|c| (centroid[c] - color[c]).powi(2)).sum() };
+    (0..centroids.len())
+        .min_by(|&a, &b| distance(&centroids[a]).total_cmp(&distance(&centroids[b])))
+        .unwrap()
+}
+
+/// Average luminance of the middle of the frame, a quarter of its area, encoded as sRGB.
+fn center_brightness(pixels: &[[f32; 4]]) -> f32 {
+    let [width, height] = SHOT_SIZE.map(|side| side as usize);
+    let mut sum = 0.0;
+    let mut count = 0;
+    for y in height / 4..height * 3 / 4 {
+        for x in width / 4..width * 3 / 4 {
+            let [r, g, b, _] = pixels[y * width + x];
+            sum += 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            count += 1;
+        }
+    }
+    export::srgb(sum / count as f32)
+}
+
+/// An ArtDmx packet carrying `channels` to `universe`.
+fn art_dmx(universe: u16, sequence: u8, channels: &[u8]) -> Vec<u8> {
+    let mut packet = b"Art-Net\0".to_vec();
+    // the opcode is little endian, unlike the rest
+    packet.extend(0x5000u16.to_le_bytes());
+    packet.extend(14u16.to_be_bytes());
+    packet.push(sequence);
+    // physical input port, for information only
+    packet.push(0);
+    packet.extend((universe & 0x7fff).to_le_bytes());
+    packet.extend((channels.len() as u16).to_be_bytes());
+    packet.extend(channels);
+    packet
+}
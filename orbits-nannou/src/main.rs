@@ -0,0 +1,3318 @@
+use std::cell::{Ref, RefCell};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use nannou::image::RgbaImage;
+use nannou::prelude::*;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+use nannou_egui::Egui;
+use orbits_core::automation::{Automation, CameraKey, Easing};
+use orbits_core::cues::CueList;
+use orbits_core::forces::Attractor;
+use orbits_core::generation::{self, Generation, Ticker, TICK_SECONDS};
+use orbits_core::init;
+use orbits_core::orbital_radius;
+use orbits_core::palette;
+use orbits_core::params::{Param, Params};
+use orbits_core::physics::{self, Body, Center, Particle};
+use orbits_core::plugins;
+use orbits_core::session::{Change, GroupOp, Replay, SessionLog};
+use orbits_core::settings;
+use orbits_core::state;
+use orbits_core::time_scale::{self, TimeScale};
+use orbits_core::trails::{self, Layering, Occupancy, Overdraw, Ribbons, Thinning};
+
+use adapter::GpuRequest;
+use ambience::Ambience;
+use audio::Audio;
+use baked::Baked;
+use brush::Brush;
+use camera::Camera;
+use chart::View;
+use classes::Classes;
+use cli::Args;
+use clock::{Clock, Mode};
+use compare::Snapshot;
+use compute::{Compute, Look};
+use config::Config;
+use constellation::ConstellationConfig;
+use diagnostics::DiagnosticsLog;
+use dialogs::{Dialog, Dialogs};
+use dice::DiceConfig;
+use dropped::Dropped;
+use engagement::Engagement;
+use export::{Captured, Crop, Export, ExportConfig, Shot};
+use exposure::LongExposure;
+use feedback::Feedback;
+use framing::Framing;
+use highlights::Highlights;
+use hints::Hints;
+use hulls::HullsConfig;
+use keys::{Action, Keymap};
+use layers::{Layer, LayersConfig};
+use layout::{LayoutConfig, Show};
+use leds::Leds;
+use lighting::Lighting;
+use manifest::Manifest;
+use mask::{Mask, Masks};
+use midi::{Midi, NoteAction, Target};
+use nebula::Nebula;
+use osc::Osc;
+use overlay::Corner;
+use passes::Pass;
+use perspective::Perspective;
+use perturbation::Perturbations;
+use power::{Power, Strain};
+use presets::Presets;
+use ratings::{Rating, RATINGS_PATH};
+use recovery::{Recover, Recovery, RECOVERY_PATH};
+use render::{RenderConfig, TrailStyle};
+use rewind::{Playback, Rewind};
+use selection::Selection;
+use selftest::SelfTest;
+use setup::Setup;
+use span::Span;
+use splash::Splash;
+use stars::StarField;
+use style::Style;
+use sync::{Message, Role, Sync};
+use tessellation::TessellationConfig;
+use tier::Quality;
+use toasts::Toasts;
+use token::Token;
+use transition::Transition;
+use tray::{OnClose, TrayIcon};
+use ui::PanelState;
+use viewport::Viewport;
+use web::metrics::Metrics;
+use web::{Request, WebControl};
+
+mod adapter;
+mod ambience;
+mod analyze;
+mod ascii;
+mod assets;
+mod audio;
+mod baked;
+mod batch;
+mod brush;
+mod camera;
+mod chart;
+mod classes;
+mod cli;
+mod clock;
+mod compare;
+mod compute;
+mod config;
+mod constellation;
+mod diagnostics;
+mod dialogs;
+mod dice;
+mod dropped;
+mod engagement;
+mod entropy;
+mod export;
+mod exposure;
+mod feedback;
+mod framing;
+mod gallery;
+mod genlock;
+mod gpu;
+mod grain;
+mod highlights;
+mod hints;
+mod hulls;
+mod keys;
+mod layers;
+mod layout;
+mod leds;
+mod lighting;
+mod manifest;
+mod mask;
+mod midi;
+mod nebula;
+mod osc;
+mod overlay;
+mod passes;
+mod perspective;
+mod perturbation;
+mod portraits;
+mod power;
+mod presets;
+mod ratings;
+mod recovery;
+mod render;
+mod retro;
+mod rewind;
+mod selection;
+mod selftest;
+mod series;
+mod setup;
+mod smear;
+mod span;
+mod splash;
+mod stars;
+mod style;
+mod svg;
+mod sync;
+mod tessellation;
+mod tier;
+mod toasts;
+mod token;
+mod transition;
+mod tray;
+mod ui;
+mod viewport;
+mod web;
+
+/// Most simulated seconds a genlocked follower draws ahead of its last tick, so a stalled leader
+/// doesn't send the swarm flying off.
+const MAX_EXTRAPOLATE: f32 = 0.1;
+/// Simulated seconds a particle spawned by dragging takes to cover the drag at its first speed.
+const DRAG_SECONDS: f32 = 1.0;
+/// Points the mouse can move between press and release and still spawn a particle by clicking.
+const CLICK_RADIUS: f32 = 4.0;
+/// Points from an attractor within which pressing the mouse grabs it, if it can be dragged, rather
+/// than spawning a particle.
+const GRAB_RADIUS: f32 = 16.0;
+/// Width and height of the minimap shown when zoomed in, in points.
+const MINIMAP_SIZE: f32 = 200.0;
+/// How far across the world the background is faded, past as far out as the view goes, in
+/// orbital radii.
+const VEIL_EXTENT: f32 = 1e4;
+/// Factor each press of the faster or slower hotkey changes the time scale by, so two double or
+/// halve it.
+const SPEED_STEP: f32 = std::f32::consts::SQRT_2;
+/// Slowest the slower hotkey takes the time scale.
+const SLOWEST: f32 = 1.0 / 16.0;
+/// Where the save and load hotkeys keep the state of the run, without `--save-state` or
+/// `--load-state`, relative to the working directory.
+const STATE_PATH: &str = "trynannou.state.json";
+
+/// Parsed before nannou starts, so `check` and `--help` work without a display, then taken by
+/// `model`.
+static ARGS: Mutex<Option<Args>> = Mutex::new(None);
+
+fn main() {
+    let args = Args::parse().unwrap_or_else(|err| exit_with_error(&err));
+    *ARGS.lock().unwrap() = Some(args);
+    nannou::app(model).update(update).exit(exit).run();
+}
+
+struct Model {
+    /// Generations in the order they were seeded. The last one is current, any others are fading
+    /// out after a reseed.
+    generations: Vec<Generation>,
+    center: Center,
+    /// The attractors besides the central mass, wherever they've been dragged to.
+    attractors: Vec<Attractor>,
+    thinning: Thinning,
+    layering: Layering,
+    /// Counts the trails drawn in the frame being shown, while the stats are.
+    occupancy: RefCell<Option<Occupancy>>,
+    /// The current generation's ribbons, kept from one frame to the next.
+    ribbons: RefCell<Ribbons>,
+    perturbations: Option<Perturbations>,
+    time_scale: TimeScale,
+    transition: Transition,
+    nebula: Nebula,
+    stars: StarField,
+    /// Steps the particles and builds the current generation's trails on the GPU, with `--gpu`.
+    compute: Option<Compute>,
+    /// The post passes the scene is drawn through in the window.
+    style: Style,
+    params: Params,
+    automation: Automation,
+    automation_path: PathBuf,
+    cues: Option<CueList>,
+    presets: Presets,
+    egui: Egui,
+    panel: PanelState,
+    /// What rolling the dice picks.
+    dice: DiceConfig,
+    /// The palettes named in the settings, and those loaded from files since.
+    palettes: BTreeMap<String, Vec<String>>,
+    dialogs: Dialogs,
+    toasts: Toasts,
+    /// The hints at how to play with the piece given while it's left alone.
+    hints: Hints,
+    recovery: Recovery,
+    /// Watches for the machine running on battery or hot, to lighten the piece while it is.
+    power: Option<Power>,
+    /// The particle count to go back to once the machine is no longer strained.
+    unstrained_particles: Option<f32>,
+    /// The config as written, for the seeds rated to be rendered under again.
+    config_text: String,
+    /// What's recorded of how the run was made, for the manifests written alongside exports.
+    manifest: Manifest,
+    /// The questions asked on a first run, until they're answered.
+    setup: Option<Setup>,
+    keymap: Keymap,
+    show_help: bool,
+    show_minimap: bool,
+    show_labels: bool,
+    show_velocities: bool,
+    show_stats: bool,
+    show_diagnostics: bool,
+    /// How long frames take, and how much of the trails' length is drawn for it.
+    quality: Quality,
+    /// The class of each particle's orbit, for the legend and the rings.
+    classes: Classes,
+    /// Where in the world the particle being spawned by the mouse was pressed.
+    spawning: Option<Point2>,
+    /// Which of the attractors is being dragged by the mouse.
+    dragging: Option<usize>,
+    /// The particles of the current generation picked out to be done things to together.
+    selection: Selection,
+    /// Pushes the particles under the mouse along as it's dragged, while it's on.
+    brush: Brush,
+    /// The trails baked into still layers under the live ones.
+    baked: Baked,
+    /// The style the trails are drawn in, and how.
+    trail_style: TrailStyle,
+    /// Whether the orbits are shown, the radius chart, or phase space beside the orbits, in the
+    /// window or the first of its panes.
+    view: View,
+    /// The panes the window is partitioned into, if it is.
+    layout: LayoutConfig,
+    render: RenderConfig,
+    /// Which layers of the scene are shown, and how they're blended and ordered.
+    layers: LayersConfig,
+    /// Which particles the constellation joins, and how its lines are drawn.
+    constellation: ConstellationConfig,
+    /// Which diagram the tessellation draws, and how.
+    tessellation: TessellationConfig,
+    /// What the hulls outline, and how.
+    hulls: HullsConfig,
+    /// The masks the layers have, loaded.
+    masks: Masks,
+    /// The last stretch of the run, kept to export clips of.
+    rewind: Rewind,
+    /// The clip being exported, if one is.
+    playback: Option<Playback>,
+    /// How many generations have been seeded so far.
+    seeded: usize,
+    /// Where the first generation's seed was drawn from, if it wasn't given as a number.
+    seed_source: Option<String>,
+    /// The token the piece was made from, if it was.
+    token: Option<Token>,
+    /// Picks the seeds of the generations after the first, so a run from the same seed reseeds
+    /// the same way.
+    rng: StdRng,
+    session_log: Option<SessionLog>,
+    replay: Option<Replay>,
+    sync: Option<Sync>,
+    web: Option<WebControl>,
+    osc: Option<Osc>,
+    metrics: Option<Metrics>,
+    exposure: Option<LongExposure>,
+    export: Option<Export>,
+    highlights: Option<Highlights>,
+    /// Where a row of the diagnostics is written every second, with `--log-diagnostics`.
+    diagnostics_log: Option<DiagnosticsLog>,
+    /// How visitors play with the piece, counted into daily reports.
+    engagement: Option<Engagement>,
+    tray: Option<TrayIcon>,
+    /// Written to from the tray icon's menu.
+    screenshots: Export,
+    leds: Option<Leds>,
+    lighting: Option<Lighting>,
+    ambience: Option<Ambience>,
+    audio: Option<Audio>,
+    /// The readings of the simulation moving the parameters, if `[feedback]` maps any.
+    feedback: Option<Feedback>,
+    /// The part of the world the view follows the swarm to, with `[framing]` on.
+    framing: Option<Framing>,
+    midi: Option<Midi>,
+    /// The still being rendered by `snapshot`, if that's what the run is for.
+    snapshot: Option<Snapshot>,
+    /// The self test `--selftest` is running, if that's what the run is for.
+    selftest: Option<SelfTest>,
+    /// How long each frame stands for, live, for stills and recordings, or as a run followed
+    /// says.
+    clock: Clock,
+    /// Frames to export before quitting.
+    frame_limit: Option<u64>,
+    /// Pixels to a point exports, stills and screenshots are rendered at, instead of the
+    /// window's scale factor.
+    pixel_scale: Option<f32>,
+    /// The window the piece is shown in, with the overlays and the panel, whichever window has
+    /// the focus.
+    window: WindowId,
+    /// The windows the piece is spread over, if it's spread over several.
+    span: Option<Span>,
+    /// Whether the window is closed to the tray.
+    hidden: bool,
+    /// Size of the window in points, to open it at again after it's closed to the tray.
+    window_size: (f32, f32),
+    /// How the windows ask for the GPU they're drawn with.
+    gpu_request: GpuRequest,
+    /// Simulated seconds to push the newest positions forward by when drawing, so a genlocked
+    /// follower shows the moment its leader's clock says it is rather than its last tick.
+    extrapolate: f32,
+    /// The leader's clock at its latest tick and the rate simulated time was running at.
+    genlocked: Option<(f64, f32)>,
+    /// Region of the world to show, instead of fitting the whole system in the window.
+    viewport: Option<Rect>,
+    /// Where the view has been zoomed and panned to from there.
+    camera: Camera,
+    /// Simulated seconds since the start of the run.
+    time: f32,
+    /// Simulated seconds the layers have drifted through, sped up and slowed down by the layer
+    /// drift parameter.
+    drift: f32,
+    /// Whether simulated time stands still, but for ticks stepped through one at a time.
+    paused: bool,
+    /// Whether a tick has been asked for while paused, to be stepped through next update.
+    step_tick: bool,
+    /// Where the save and load hotkeys keep the state of the run.
+    state_path: PathBuf,
+    /// Whether the state is saved there on quitting, as `--save-state` asks.
+    save_state_on_exit: bool,
+    /// The loader shown while the pre-roll runs, or running once it's done.
+    splash: Splash,
+}
+
+impl Model {
+    fn current(&self) -> &Generation {
+        self.generations.last().unwrap()
+    }
+
+    /// The central bodies and the attractors, as they are now.
+    fn bodies(&self) -> Vec<Body> {
+        let mut bodies = self.center.bodies(self.time);
+        bodies.extend(
+            self.attractors
+                .iter()
+                .map(|attractor| attractor.body(self.time)),
+        );
+        bodies
+    }
+
+    /// Label and value pairs summarizing what is being simulated, for the help overlay.
+    fn describe_scene(&self) -> Vec<(String, String)> {
+        let mut scene = physics::describe();
+        scene.push(("generation".into(), self.seeded.to_string()));
+        scene.push(("seed".into(), self.current().seed.to_string()));
+        if let Some(token) = &self.token {
+            scene.push(("token".into(), token.hex()));
+        }
+        if let Some(source) = self.seed_source.as_ref().filter(|_| self.seeded == 1) {
+            scene.push(("drawn from".into(), source.clone()));
+        }
+        scene.push(("trails".into(), self.trail_style.label().into()));
+        scene.push(("view".into(), self.view.label().into()));
+        scene.push(("palette".into(), self.current().palette.clone()));
+        if self.baked.layers > 0 {
+            scene.push(("baked layers".into(), self.baked.layers.to_string()));
+        }
+        if let Some(cues) = &self.cues {
+            scene.push(("cue".into(), cues.describe()));
+        }
+        if let Some(preset) = self.presets.describe() {
+            scene.push(("preset".into(), preset));
+        }
+        let time_scale = self
+            .time_scale
+            .scale(1.0, self.params.get(Param::TimeScale));
+        scene.push((
+            "time scale".into(),
+            if self.paused {
+                "paused".into()
+            } else {
+                format!("{time_scale:.2}x")
+            },
+        ));
+        scene
+    }
+
+    /// Multiply the time scale by `factor`, from no slower than `SLOWEST`.
+    fn scale_speed(&mut self, factor: f32) {
+        let value = (self.params.get(Param::TimeScale).max(SLOWEST) * factor).max(SLOWEST);
+        self.apply(Change::Set {
+            param: Param::TimeScale,
+            value,
+        });
+    }
+
+    /// The state of the run as it is now. The seeds of later generations are picked afresh here
+    /// and in the state alike, so the run goes on the same whether it's resumed or not.
+    fn state(&mut self) -> state::Snapshot {
+        let reseeds = self.rng.gen();
+        self.rng = StdRng::seed_from_u64(reseeds);
+        state::Snapshot {
+            time: self.time,
+            generations: self.generations.clone(),
+            seeded: self.seeded,
+            reseeds,
+            center: self.center.clone(),
+            attractors: self.attractors.clone(),
+            time_scale: self.time_scale.clone(),
+            params: self.params.clone(),
+        }
+    }
+
+    /// What's recorded of how the run was made, as of now.
+    fn manifest(&self) -> Manifest {
+        self.manifest.at(self.time, &self.automation)
+    }
+
+    /// Go on from `state` rather than from where the run is.
+    fn resume(&mut self, state: state::Snapshot) {
+        self.unfollow();
+        self.selection.reset();
+        self.time = state.time;
+        self.generations = state.generations;
+        self.seeded = state.seeded;
+        self.rng = StdRng::seed_from_u64(state.reseeds);
+        self.center = state.center;
+        self.attractors = state.attractors;
+        self.time_scale = state.time_scale;
+        self.params = state.params;
+        if let Some(compute) = &mut self.compute {
+            compute.forget();
+        }
+    }
+
+    fn save_state(&mut self) {
+        let path = self.state_path.clone();
+        let saved = (self.state().save(&path)).and_then(|()| self.manifest().write_beside(&path));
+        match saved {
+            Ok(()) => self
+                .toasts
+                .info(format!("saved the state to {}", path.display())),
+            Err(err) => self
+                .toasts
+                .error(format!("failed to save the state: {err}")),
+        }
+    }
+
+    /// Go back to the state last saved, unless the run is logged, replayed or synced, none of
+    /// which could follow the jump.
+    fn load_state(&mut self) {
+        if self.session_log.is_some() || self.replay.is_some() || self.sync.is_some() {
+            self.toasts
+                .error("a logged, replayed or synced run can't load a saved state");
+            return;
+        }
+        match state::Snapshot::load(&self.state_path) {
+            Ok(state) => {
+                self.resume(state);
+                self.manifest.resume(self.time, &self.generations);
+                self.rewind.forget();
+                let path = self.state_path.display();
+                self.toasts.info(format!("loaded the state from {path}"));
+            }
+            Err(err) => self
+                .toasts
+                .error(format!("failed to load the state: {err}")),
+        }
+    }
+
+    /// Where the particle the camera follows is, if it's following one still there.
+    fn followed(&self) -> Option<Point2> {
+        let particle = self.camera.following?;
+        let current = self.generations.last()?;
+        current.particles.get(particle).map(|particle| particle.pos)
+    }
+
+    /// Where the camera's path has the camera now, if it has a path and it's being played back
+    /// rather than recorded.
+    fn camera_path(&self) -> Option<CameraKey> {
+        (!self.automation.recording)
+            .then(|| self.automation.camera(self.time))
+            .flatten()
+    }
+
+    /// The view the camera shows, given the view the piece would otherwise show, as `Camera::view`
+    /// has it, or along its path while that's played back.
+    fn camera_view(&self, fit: Viewport) -> Viewport {
+        match self.camera_path() {
+            Some(key) => Viewport::new(fit.scale * key.zoom, Point2::from(key.middle)),
+            None => self.camera.view(fit, self.followed()),
+        }
+    }
+
+    /// The camera the scene is seen through with `three_d` on, turned as the camera's path has it
+    /// if its keyframes say.
+    fn perspective(&self) -> Option<Perspective> {
+        let turned = self.camera_path().and_then(|key| key.orientation);
+        settings::get().three_d.then(|| match turned {
+            Some([turns, tilt]) => Perspective::oriented(turns, tilt),
+            None => Perspective::at(self.time),
+        })
+    }
+
+    /// Stop the camera following a particle, leaving the view where it is.
+    fn unfollow(&mut self) {
+        let followed = self.followed();
+        let middle = fit_middle(self);
+        self.camera.unfollow(followed, middle);
+    }
+
+    /// Have the camera follow the particle of the current generation nearest `pos`, a point of
+    /// the world, or stop following one if it already is.
+    fn toggle_follow(&mut self, pos: Point2) {
+        if self.camera.following.is_some() {
+            self.unfollow();
+            return;
+        }
+        let nearest = (self.current().particles.iter().enumerate())
+            .min_by(|(_, a), (_, b)| a.pos.distance(pos).total_cmp(&b.pos.distance(pos)))
+            .map(|(particle, _)| particle);
+        if let Some(particle) = nearest {
+            self.camera.follow(particle);
+        }
+    }
+
+    /// Keep following the same particle as `deleted` are taken out of the current generation
+    /// ahead of it, or stop following it if it's one of them.
+    fn delete_followed(&mut self, deleted: &[usize]) {
+        let Some(following) = self.camera.following else {
+            return;
+        };
+        if deleted.contains(&following) {
+            self.unfollow();
+        } else {
+            let deleted: BTreeSet<usize> = deleted.iter().copied().collect();
+            let ahead = deleted.range(..following).count();
+            self.camera.following = Some(following - ahead);
+        }
+    }
+
+    /// Catch up with the particles of the current generation `merged` away as it was stepped, by
+    /// where they were, as a delete of them would have.
+    fn merged(&mut self, merged: &[usize]) {
+        self.delete_followed(merged);
+        self.selection.delete(merged);
+        let count = self.current().staying() as f32;
+        self.params.set(Param::Particles, count);
+        if let Some(compute) = &mut self.compute {
+            compute.forget();
+        }
+    }
+
+    /// Catch up with the halves split off particles of the current generation, by where they
+    /// went in, each a place after the particle it's the other half of.
+    fn split(&mut self, split: &[usize]) {
+        if let Some(following) = &mut self.camera.following {
+            for &half in split {
+                if half <= *following {
+                    *following += 1;
+                }
+            }
+        }
+        self.selection.insert(split);
+        let count = self.current().staying() as f32;
+        self.params.set(Param::Particles, count);
+        if let Some(compute) = &mut self.compute {
+            compute.forget();
+        }
+    }
+
+    /// Switch to preset `index`, easing into its parameters and taking on its palette, trail
+    /// style and view, if there is one.
+    fn preset(&mut self, index: usize) {
+        let Some(preset) = self.presets.start(index, &self.params) else {
+            return;
+        };
+        let (palette, trail_style, view) =
+            (preset.palette.clone(), preset.trail_style, preset.view);
+        if let Some(name) = palette {
+            self.apply(Change::Palette { name });
+        }
+        if let Some(trail_style) = trail_style {
+            self.trail_style = trail_style;
+        }
+        if let Some(view) = view {
+            self.view = view;
+        }
+    }
+
+    /// Rate the current generation's seed `stars` out of five, under the config it's shown under.
+    fn rate(&self, stars: u8) {
+        let seed = self.current().seed;
+        let rating = Rating::new(seed, stars, self.config_text.clone());
+        let stars = rating.stars();
+        match ratings::rate(Path::new(RATINGS_PATH), rating) {
+            Ok(()) => self.toasts.info(format!("rated seed {seed} {stars}")),
+            Err(err) => self.toasts.error(format!("failed to rate the seed: {err}")),
+        }
+    }
+
+    /// Bake the current generation's trails, as they're drawn now, into a layer under the live
+    /// ones, and clear them.
+    fn bake(&mut self) {
+        let current = self.current();
+        let look = look(self, current, 1.0);
+        let mut mesh = render::build(self.trail_style, &self.render, current, look);
+        if self.trail_style == TrailStyle::Ribbons {
+            self.layering.layer(&mut mesh);
+        }
+        self.baked.add(&mesh);
+        self.generations.last_mut().unwrap().clear_trails();
+        // the trails kept on the GPU are of the history just cleared
+        if let Some(compute) = &mut self.compute {
+            compute.forget();
+        }
+    }
+
+    /// Spawn `count` particles about the average orbit at once.
+    fn burst(&mut self, count: usize) {
+        let gravity = self.params.get(Param::Gravity);
+        let gm = gravity * self.bodies().iter().map(|body| body.gm).sum::<f32>();
+        let vary = self.params.get(Param::VaryVelocity);
+        for _ in 0..count {
+            let particle = init::spawn(gm, vary, &mut self.rng);
+            self.apply(Change::Spawn {
+                pos: particle.pos.to_array(),
+                vel: particle.vel.to_array(),
+            });
+        }
+    }
+
+    /// Mark where the clip of the run goes in, or out, as now.
+    fn mark(&mut self, out: bool) {
+        if !self.rewind.is_on() {
+            self.toasts
+                .error("the rewind is off, so there's nothing to mark a clip in");
+            return;
+        }
+        self.rewind.marks[out as usize] = Some(self.time);
+        self.rewind.cursor = self.time;
+        let which = if out { "out" } else { "in" };
+        self.toasts
+            .info(format!("marked the clip {which} at {:.1}s", self.time));
+    }
+
+    /// Play the clip marked on the rewind timeline back from the state kept before it, exporting
+    /// each frame of it as it goes, then go back to the run as it was. Not for a logged, replayed
+    /// or synced run, none of which could follow the jumps.
+    fn export_clip(&mut self, dir: Option<PathBuf>) {
+        if self.session_log.is_some() || self.replay.is_some() || self.sync.is_some() {
+            self.toasts
+                .error("a logged, replayed or synced run can't export a clip");
+            return;
+        }
+        if self.playback.is_some() {
+            return;
+        }
+        let clip = match self.rewind.clip(self.style.clone(), dir) {
+            Ok(clip) => clip,
+            Err(err) => {
+                self.toasts.error(format!("failed to export a clip: {err}"));
+                return;
+            }
+        };
+        println!(
+            "exporting the clip from {:.1}s to {:.1}s to {}",
+            clip.from,
+            clip.to,
+            clip.dir.display()
+        );
+        let live = self.state();
+        let start = clip.start.clone();
+        let clocked = self.clock.set_mode(Mode::Fixed {
+            step: 1.0 / clip.fps,
+        });
+        self.playback = Some(Playback {
+            clip,
+            live,
+            baked: self.baked.clone(),
+            clocked,
+        });
+        self.resume(start);
+    }
+
+    /// Roll the dice for a new look, from the same random stream reseeds are picked from.
+    fn roll_dice(&mut self) {
+        let palettes = palette::names(&self.palettes);
+        let roll = self.dice.roll(&palettes, &mut self.rng);
+        for change in roll.changes {
+            self.apply(change);
+        }
+        self.trail_style = roll.trail_style;
+    }
+
+    fn is_follower(&self) -> bool {
+        matches!(&self.sync, Some(sync) if sync.role == Role::Follower)
+    }
+
+    fn next_cue(&mut self) {
+        let Some(cues) = &self.cues else {
+            return;
+        };
+        let Some(index) = cues.next() else {
+            return;
+        };
+        // the reseed is a change of its own, so followers and replays see it without the cue list
+        let seed = cues.cues[index].seed;
+        self.apply(Change::Cue { index });
+        if let Some(seed) = seed {
+            self.apply(Change::Reseed { seed });
+        }
+    }
+
+    /// Make a change to the running piece, recording it in the session log and passing it on to
+    /// any followers.
+    fn apply(&mut self, change: Change) {
+        // a clip's changes were noted the first time round
+        if self.playback.is_none() {
+            self.rewind.note(&change);
+            self.manifest.note(self.time, &change);
+        }
+        if let Some(log) = &mut self.session_log {
+            log.record(self.time, &change);
+        }
+        // only what's done here counts as played with
+        let made_here = self.replay.is_none() && self.playback.is_none() && !self.is_follower();
+        if let Some(engagement) = self.engagement.as_mut().filter(|_| made_here) {
+            engagement.change(&change);
+        }
+        if let Some(sync) = &self.sync {
+            if sync.role == Role::Leader {
+                sync.send(&Message::Change {
+                    change: change.clone(),
+                });
+            }
+        }
+        match change {
+            Change::Start { seed } => {
+                self.seeded = 1;
+                self.unfollow();
+                self.selection.reset();
+                let vary_velocity = self.params.get(Param::VaryVelocity);
+                self.generations = vec![Generation::new(seed, self.time, 1.0, vary_velocity)];
+            }
+            Change::Reseed { seed } => {
+                self.seeded += 1;
+                // the particle followed, and those picked, are of the generation about to fade out
+                self.unfollow();
+                self.selection.reset();
+                let palette = self.current().palette.clone();
+                let vary_velocity = self.params.get(Param::VaryVelocity);
+                let mut generation = Generation::new(seed, self.time, 0.0, vary_velocity);
+                // a palette picked by hand lasts past the generation it was picked for
+                if generation.palette != palette {
+                    generation.repaint(&palette, &self.palettes).unwrap();
+                }
+                self.generations.push(generation);
+            }
+            Change::Set { param, value } => {
+                self.params.set(param, value);
+                self.automation.record(param, self.time, value);
+            }
+            Change::Cue { index } => {
+                if let Some(cues) = &mut self.cues {
+                    cues.start(index, &self.params);
+                }
+            }
+            Change::Spawn { pos, vel } => {
+                let particle = Particle {
+                    pos: pos.into(),
+                    vel: vel.into(),
+                };
+                let current = self.generations.last_mut().unwrap();
+                current.add(particle);
+                // or the swarm would be cut back to the count asked for before
+                let count = current.particles.len() as f32;
+                self.params.set(Param::Particles, count);
+            }
+            Change::Move { attractor, pos } => {
+                if let Some(attractor) = self.attractors.get_mut(attractor) {
+                    attractor.pos = pos;
+                }
+            }
+            Change::Bake => self.bake(),
+            Change::Unbake => self.baked.clear(),
+            Change::LoadPalette { name, stops } => {
+                self.palettes.insert(name.clone(), stops);
+                self.apply(Change::Palette { name });
+            }
+            Change::Palette { name } => {
+                let current = self.generations.last_mut().unwrap();
+                if let Err(err) = current.repaint(&name, &self.palettes) {
+                    self.toasts.error(err);
+                }
+                // the trails kept on the GPU are in the colors they were left in
+                if let Some(compute) = &mut self.compute {
+                    compute.forget();
+                }
+            }
+            Change::Weigh { attractor, mass } => {
+                if let Some(attractor) = self.attractors.get_mut(attractor) {
+                    attractor.mass = mass;
+                }
+            }
+            Change::Impulse { pos, radius, vel } => {
+                let current = self.generations.last_mut().unwrap();
+                current.impulse(pos.into(), radius, vel.into());
+            }
+            Change::Group { particles, op } => {
+                if op == GroupOp::Delete {
+                    self.delete_followed(&particles);
+                    self.selection.delete(&particles);
+                }
+                let current = self.generations.last_mut().unwrap();
+                current.apply_to(&particles, op);
+                if op == GroupOp::Delete {
+                    // or the swarm would be grown back to the count asked for before
+                    let count = current.staying() as f32;
+                    self.params.set(Param::Particles, count);
+                }
+                // the trails kept on the GPU are of the particles as they were
+                if let Some(compute) = &mut self.compute {
+                    compute.forget();
+                }
+            }
+        }
+    }
+}
+
+fn model(app: &App) -> Model {
+    let args = ARGS.lock().unwrap().take().unwrap();
+    let config_path = (args.snapshot.as_ref())
+        .and_then(|s| s.config.as_deref())
+        .or(args.config.as_deref());
+    let mut config = match config_path {
+        Some(path) => Config::load_file(path),
+        None => Config::load(),
+    }
+    .unwrap_or_else(|err| exit_with_error(&err));
+    let headless = args.snapshot.is_some() || args.selftest;
+    // stills are of the config alone, synced instances have to agree on a tier to stay in step,
+    // and a token's piece has to be the same on every machine
+    let unsettled = args.sync.is_none() && args.token.is_none();
+    if !headless && (unsettled || config.performance.tier.is_some()) {
+        tier::pick(&config).apply(&mut config);
+    }
+    if let Some(token) = &args.token {
+        token.apply(&mut config.simulation);
+        println!("token {}", token.hex());
+    }
+    let keymap = Keymap::new(&config.keys).unwrap_or_else(|err| exit_with_error(&err));
+    let config_text = config.written();
+    if let Some(center_path) = &mut config.simulation.center_path {
+        mask::trace(&mut center_path.path).unwrap_or_else(|err| exit_with_error(&err));
+    }
+    settings::set(config.simulation.clone()).unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .layers
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    let masks = Masks::load(&config.layers).unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .constellation
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .tessellation
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .hulls
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .classes
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .span
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .layout
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .feedback
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .framing
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .presets
+        .check(&config.simulation.palettes)
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .hints
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .performance
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    config
+        .power
+        .check()
+        .unwrap_or_else(|err| exit_with_error(&err));
+    let mut classes = Classes::new(config.classes.clone());
+    // the stats panes count the orbits of each class from the start
+    classes.legend |= (config.layout.panes.iter()).any(|pane| pane.show == Show::Stats);
+    let rewind = Rewind::new(config.rewind.clone(), config.export, config.transition)
+        .unwrap_or_else(|err| exit_with_error(&err));
+    plugins::load(Path::new(plugins::PLUGINS_DIR)).unwrap_or_else(|err| exit_with_error(&err));
+    let style = Style::new(&config).unwrap_or_else(|err| exit_with_error(&err));
+    let session_log = args
+        .log_session
+        .map(|path| SessionLog::create(&path).unwrap_or_else(|err| exit_with_error(&err)));
+    let replay = args
+        .replay
+        .map(|path| Replay::load(&path).unwrap_or_else(|err| exit_with_error(&err)));
+    let automation_path = args
+        .automation
+        .unwrap_or_else(|| PathBuf::from("automation.json"));
+    let automation = Automation::load(&automation_path).unwrap_or_else(|err| exit_with_error(&err));
+    let mut manifest = Manifest::new(vec![config_text.clone()]);
+    if let Some(token) = &args.token {
+        manifest = manifest.token(token.hex());
+    }
+    if let Some(source) = &args.seed_source {
+        manifest = manifest.seed_source(source.clone());
+    }
+    if let Some(path) = &args.cues {
+        manifest = manifest
+            .cues(path)
+            .unwrap_or_else(|err| exit_with_error(&err));
+    }
+    let cues = args
+        .cues
+        .map(|path| CueList::load(&path).unwrap_or_else(|err| exit_with_error(&err)));
+    let sync = args.sync.map(|role| {
+        let group = args.sync_group.unwrap_or(sync::DEFAULT_GROUP);
+        Sync::new(role, group, args.genlock).unwrap_or_else(|err| exit_with_error(&err))
+    });
+    // a follower's frames are as long as the leader's ticks say
+    let clocked = if matches!(&sync, Some(sync) if sync.role == Role::Follower) {
+        Mode::Replay
+    } else if headless {
+        Mode::Fixed {
+            step: compare::FRAME_SECONDS,
+        }
+    } else if args.record {
+        Mode::Fixed {
+            step: 1.0 / config.export.fps.max(1.0),
+        }
+    } else {
+        Mode::Realtime
+    };
+    let web = args
+        .web
+        .map(|addr| WebControl::serve(addr).unwrap_or_else(|err| exit_with_error(&err)));
+    let osc = args
+        .osc
+        .map(|addr| Osc::listen(addr).unwrap_or_else(|err| exit_with_error(&err)));
+    let metrics = args
+        .metrics
+        .map(|addr| Metrics::serve(addr).unwrap_or_else(|err| exit_with_error(&err)));
+    let engagement = args.engagement.map(|dir| {
+        Engagement::create(&dir, config.hints.idle).unwrap_or_else(|err| exit_with_error(&err))
+    });
+    let diagnostics_log = args
+        .log_diagnostics
+        .map(|path| DiagnosticsLog::create(&path).unwrap_or_else(|err| exit_with_error(&err)));
+    let highlights = args.highlights.map(|path| {
+        Highlights::create(&path, config.highlights).unwrap_or_else(|err| exit_with_error(&err))
+    });
+    // clips of highlights are exported even without a sequence of every frame
+    let export = (args.export.is_some()
+        || highlights.as_ref().is_some_and(Highlights::wants_frames))
+    .then(|| {
+        Export::new(
+            args.export,
+            args.crop,
+            // a recording is for assembling into a video
+            if args.record {
+                ExportConfig {
+                    format: export::Format::Png,
+                    ..config.export
+                }
+            } else {
+                config.export
+            },
+            config.transition,
+            style.clone(),
+        )
+        .unwrap_or_else(|err| exit_with_error(&err))
+    });
+    let seed = args
+        .seed
+        .or(args.token.map(|token| token.seed()))
+        .or_else(|| replay.as_ref().and_then(Replay::start_seed))
+        .or(args.selftest.then_some(selftest::SEED))
+        .unwrap_or_else(random);
+    let state = args.load_state.as_ref().map(|path| {
+        let state = state::Snapshot::load(path).unwrap_or_else(|err| exit_with_error(&err));
+        println!("resuming {}", path.display());
+        state
+    });
+    // a still's seed is given by whatever asked for it
+    if args.snapshot.is_none() && !args.selftest && state.is_none() {
+        match &args.seed_source {
+            Some(source) => println!("seed {seed} drawn from {source}"),
+            None => println!("seed {seed}"),
+        }
+    }
+
+    let tray = args
+        .tray
+        .then(|| TrayIcon::spawn(config.tray).unwrap_or_else(|err| exit_with_error(&err)));
+    let screenshots = {
+        let png = ExportConfig {
+            format: export::Format::Png,
+            alpha: export::Alpha::Straight,
+            ..ExportConfig::default()
+        };
+        // a screenshot is of the one frame, with nothing to smear it over
+        let style = Style {
+            smear: None,
+            ..style.clone()
+        };
+        Export::new(None, None, png, config.transition, style)
+            .unwrap_or_else(|err| exit_with_error(&err))
+    };
+    let leds = args.leds.map(|addr| {
+        Leds::new(addr, config.leds, config.transition).unwrap_or_else(|err| exit_with_error(&err))
+    });
+    let ambience = args.ambience.then(|| {
+        Ambience::new(config.ambience.clone(), config.transition)
+            .unwrap_or_else(|err| exit_with_error(&err))
+    });
+    // a follower's parameters are its leader's, moved by the leader's audio
+    let audio = (args.audio && args.sync != Some(Role::Follower))
+        .then(|| Audio::new(config.audio.clone()).unwrap_or_else(|err| exit_with_error(&err)));
+    let feedback = (args.sync != Some(Role::Follower))
+        .then(|| Feedback::new(config.feedback.clone()))
+        .flatten();
+    let midi = (args.midi && args.sync != Some(Role::Follower))
+        .then(|| Midi::new(config.midi.clone()).unwrap_or_else(|err| exit_with_error(&err)));
+    let lighting = args.art_net.map(|addr| {
+        Lighting::new(addr, config.lighting, config.transition)
+            .unwrap_or_else(|err| exit_with_error(&err))
+    });
+
+    let gpu_request =
+        GpuRequest::new(app, args.gpu_preference).unwrap_or_else(|err| exit_with_error(&err));
+    let window = open_window(app, headless.then_some(compare::SIZE), &gpu_request);
+    gpu_request.report(app);
+    if headless {
+        // nothing is shown, so simulate as fast as frames can be drawn
+        app.set_loop_mode(LoopMode::Rate {
+            update_interval: Duration::ZERO,
+        });
+    }
+    let span = (!headless && config.span.is_on()).then(|| {
+        let open = |fullscreen| {
+            app.new_window()
+                .power_preference(gpu_request.power_preference)
+                .force_fallback_adapter(gpu_request.fallback)
+                .view(view_pane)
+                .key_pressed(key_pressed)
+                .fullscreen_with(Some(fullscreen))
+                .build()
+                .unwrap()
+        };
+        Span::new(app, &config.span, window, open).unwrap_or_else(|err| exit_with_error(&err))
+    });
+    if !headless && span.is_none() {
+        config.window.apply(&app.window(window).unwrap());
+    }
+    // a first run for someone to watch asks how it's to be shown
+    let first_run = !assets::resolve(config::CONFIG_PATH).exists()
+        && !headless
+        && export.is_none()
+        && args.sync.is_none()
+        && replay.is_none();
+    let setup = (args.setup || first_run).then(Setup::new);
+    // what every frame counts for is pre-rolled before the first, the rest behind the loader
+    let splashed = !headless
+        && export.is_none()
+        && replay.is_none()
+        && args.sync.is_none()
+        && args.frames.is_none();
+    let egui = Egui::from_window(&app.window(window).unwrap());
+    let window_size = app.window(window).unwrap().inner_size_points();
+    let compute = args.gpu.then(|| {
+        let device_queue = app.window(window).unwrap().device_queue_pair().clone();
+        Compute::new(device_queue)
+            .map_err(|err| eprintln!("{err}, so the CPU does it all"))
+            .ok()
+    });
+
+    let mut model = Model {
+        generations: Vec::new(),
+        center: Center::default(),
+        attractors: config.simulation.attractors.clone(),
+        thinning: config.thinning,
+        layering: config.layering,
+        occupancy: RefCell::new(None),
+        ribbons: RefCell::default(),
+        perturbations: Perturbations::new(config.perturbation.clone()),
+        time_scale: TimeScale::default(),
+        transition: Transition::new(config.transition),
+        nebula: Nebula::new(config.nebula),
+        stars: StarField::new(config.stars),
+        compute: compute.flatten(),
+        style,
+        params: Params::default(),
+        automation,
+        automation_path,
+        cues,
+        presets: Presets::new(config.presets.clone()),
+        egui,
+        panel: PanelState::default(),
+        dice: config.dice.clone(),
+        palettes: config.simulation.palettes.clone(),
+        dialogs: Dialogs::default(),
+        toasts: Toasts::default(),
+        hints: Hints::new(
+            config.hints.clone(),
+            config
+                .simulation
+                .attractors
+                .iter()
+                .any(|attractor| attractor.draggable),
+        ),
+        recovery: Recovery::default(),
+        // exports and stills are of every frame, in full
+        power: (config.power.adapt && !headless && export.is_none())
+            .then(|| Power::new(config.power)),
+        unstrained_particles: None,
+        config_text,
+        manifest,
+        setup,
+        keymap,
+        show_help: false,
+        // a follower's viewport is its tile of a video wall, not a zoom
+        show_minimap: args.sync != Some(Role::Follower),
+        show_labels: false,
+        show_velocities: false,
+        show_stats: false,
+        show_diagnostics: false,
+        quality: Quality::new(config.performance),
+        classes,
+        spawning: None,
+        dragging: None,
+        selection: Selection::default(),
+        brush: Brush::new(config.brush.clone()),
+        baked: Baked::default(),
+        trail_style: config.render.style,
+        // the first pane starts off showing its own view
+        view: (config.layout.panes.first())
+            .and_then(|pane| pane.show.view())
+            .unwrap_or(config.render.view),
+        // each of the windows spread over shows the one view
+        layout: if config.span.is_on() {
+            LayoutConfig::default()
+        } else {
+            config.layout.clone()
+        },
+        render: config.render,
+        layers: config.layers.clone(),
+        constellation: config.constellation.clone(),
+        tessellation: config.tessellation.clone(),
+        hulls: config.hulls.clone(),
+        masks,
+        rewind,
+        playback: None,
+        seeded: 0,
+        seed_source: args.seed_source,
+        token: args.token,
+        rng: StdRng::seed_from_u64(seed),
+        session_log,
+        replay,
+        sync,
+        web,
+        osc,
+        metrics,
+        exposure: args
+            .long_exposure
+            .map(|path| LongExposure::new(path, config.exposure)),
+        export,
+        highlights,
+        diagnostics_log,
+        engagement,
+        tray,
+        screenshots,
+        leds,
+        lighting,
+        ambience,
+        audio,
+        feedback,
+        framing: Framing::new(config.framing),
+        midi,
+        clock: Clock::new(clocked),
+        frame_limit: args.frames,
+        pixel_scale: args.pixel_scale,
+        snapshot: args.snapshot,
+        selftest: args.selftest.then(SelfTest::default),
+        window,
+        span,
+        hidden: false,
+        window_size,
+        gpu_request,
+        extrapolate: 0.0,
+        genlocked: None,
+        viewport: args.viewport,
+        camera: Camera::default(),
+        time: 0.0,
+        drift: 0.0,
+        paused: false,
+        step_tick: false,
+        state_path: (args.save_state.as_ref())
+            .or(args.load_state.as_ref())
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(STATE_PATH)),
+        save_state_on_exit: args.save_state.is_some(),
+        splash: Splash::Running,
+    };
+    // a config loaded from the panel or dropped on the window starts the piece again under it
+    if let (Some(path), false) = (&args.config, headless) {
+        model
+            .toasts
+            .info(format!("running under {}", path.display()));
+    }
+    match state {
+        // it was pre-rolled, if at all, before it was saved
+        Some(state) => {
+            model.resume(state);
+            model.manifest.resume(model.time, &model.generations);
+        }
+        None => {
+            model.apply(Change::Start { seed });
+            // a follower is brought up to date by its leader
+            let frames = pre_roll_frames(config.pre_roll);
+            if splashed {
+                model.splash = Splash::new(frames);
+            } else if !model.is_follower() {
+                for _ in 0..frames {
+                    pre_roll(&mut model);
+                }
+            }
+        }
+    }
+    model
+}
+
+/// How many frames at the full frame rate a pre-roll of `seconds` takes.
+fn pre_roll_frames(seconds: f32) -> usize {
+    (seconds / compare::FRAME_SECONDS).round() as usize
+}
+
+/// Silently simulate a frame of the pre-roll.
+fn pre_roll(model: &mut Model) {
+    let delta_seconds = scale_time(model, compare::FRAME_SECONDS);
+    step(model, compare::FRAME_SECONDS, delta_seconds);
+}
+
+fn exit(_app: &App, mut model: Model) {
+    if model.save_state_on_exit {
+        model.save_state();
+    }
+    if let Some(exposure) = &model.exposure {
+        let saved = (exposure.save()).and_then(|()| model.manifest().write_beside(exposure.path()));
+        if let Err(err) = saved {
+            eprintln!("failed to save the long exposure: {err}");
+        }
+    }
+    let manifest = model.manifest();
+    if let Some(export) = model.export {
+        export.finish(Some(&manifest));
+    }
+    if let Some(Err(err)) = model.engagement.as_mut().map(Engagement::finish) {
+        eprintln!("failed to write the engagement report: {err}");
+    }
+    model.screenshots.finish(None);
+}
+
+fn exit_with_error(err: &str) -> ! {
+    eprintln!("error: {err}");
+    std::process::exit(1);
+}
+
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    if model.splash.is_loading() {
+        return;
+    }
+    model.hints.touched();
+    if let Some(engagement) = &mut model.engagement {
+        engagement.key();
+    }
+    let action = model.keymap.action(key, app.keys.mods.shift());
+    // followers take every change from their leader
+    if model.is_follower()
+        && !matches!(
+            action,
+            Some(
+                Action::Help
+                    | Action::Panel
+                    | Action::Minimap
+                    | Action::Labels
+                    | Action::Velocities
+                    | Action::Stats
+                    | Action::Diagnostics
+                    | Action::TrailStyle
+                    | Action::View
+                    | Action::SaveState
+                    | Action::Rate1
+                    | Action::Rate2
+                    | Action::Rate3
+                    | Action::Rate4
+                    | Action::Rate5
+            )
+        )
+    {
+        return;
+    }
+    match action {
+        Some(Action::Reseed) => {
+            let seed = model.rng.gen();
+            model.apply(Change::Reseed { seed })
+        }
+        Some(Action::Help) => model.show_help = !model.show_help,
+        Some(Action::Minimap) => model.show_minimap = !model.show_minimap,
+        Some(Action::Labels) => model.show_labels = !model.show_labels,
+        Some(Action::Velocities) => model.show_velocities = !model.show_velocities,
+        Some(Action::Stats) => model.show_stats = !model.show_stats,
+        Some(Action::Diagnostics) => model.show_diagnostics = !model.show_diagnostics,
+        Some(Action::Legend) => model.classes.legend = !model.classes.legend,
+        Some(Action::ClassColors) => model.classes.color = !model.classes.color,
+        Some(Action::Panel) => model.panel.visible = !model.panel.visible,
+        Some(Action::NextCue) => model.next_cue(),
+        Some(Action::Pause) => model.paused = !model.paused,
+        Some(Action::Step) => {
+            model.paused = true;
+            model.step_tick = true;
+        }
+        Some(Action::Faster) => model.scale_speed(SPEED_STEP),
+        Some(Action::Slower) => model.scale_speed(1.0 / SPEED_STEP),
+        Some(Action::SaveState) => model.save_state(),
+        Some(Action::LoadState) => model.load_state(),
+        Some(Action::Follow) => {
+            let pos = to_world(app, model, app.mouse.position());
+            model.toggle_follow(pos);
+        }
+        Some(Action::ResetCamera) => model.camera.reset(),
+        Some(Action::KeyCamera) => key_camera(app, model),
+        Some(Action::Dice) => model.roll_dice(),
+        Some(Action::TrailStyle) => model.trail_style = model.trail_style.next(),
+        Some(Action::View) => model.view = model.view.next(),
+        Some(Action::Palette) => {
+            let names = palette::names(&model.palettes);
+            let current = names
+                .iter()
+                .position(|name| *name == model.current().palette);
+            let next = current.map_or(0, |i| (i + 1) % names.len());
+            model.apply(Change::Palette {
+                name: names[next].clone(),
+            });
+        }
+        Some(Action::Bake) => model.apply(Change::Bake),
+        Some(Action::Unbake) => model.apply(Change::Unbake),
+        Some(Action::MarkIn) => model.mark(false),
+        Some(Action::MarkOut) => model.mark(true),
+        Some(Action::ExportClip) => model.export_clip(None),
+        Some(Action::Svg) => export_svg(app, model, None),
+        Some(Action::Brush) => {
+            model.brush.active = !model.brush.active;
+            model.brush.release();
+        }
+        Some(
+            action
+            @ (Action::Rate1 | Action::Rate2 | Action::Rate3 | Action::Rate4 | Action::Rate5),
+        ) => model.rate(action.stars().unwrap()),
+        Some(
+            action @ (Action::Preset1
+            | Action::Preset2
+            | Action::Preset3
+            | Action::Preset4
+            | Action::Preset5
+            | Action::Preset6
+            | Action::Preset7
+            | Action::Preset8
+            | Action::Preset9),
+        ) => model.preset(action.preset().unwrap()),
+        None => {}
+    }
+}
+
+/// Grab the attractor under the mouse if it can be dragged, or else start spawning a particle
+/// where the window is clicked, or start a box picking out particles with shift held, or start
+/// painting if the brush is on. The right button grabs the view to pan it.
+fn mouse_pressed(app: &App, model: &mut Model, button: MouseButton) {
+    if model.splash.is_loading() {
+        return;
+    }
+    model.hints.touched();
+    let win = main_window(app, model).rect();
+    if let Some(engagement) = &mut model.engagement {
+        engagement.touch(app.mouse.position(), win);
+    }
+    // followers take every change from their leader, and show their tile of it
+    if model.is_follower() || model.egui.ctx().wants_pointer_input() {
+        return;
+    }
+    if button == MouseButton::Right {
+        model.camera.grab(app.mouse.position());
+    }
+    if button != MouseButton::Left {
+        return;
+    }
+    let pos = to_world(app, model, app.mouse.position());
+    if app.keys.mods.shift() {
+        model.selection.boxing = Some(pos);
+        return;
+    }
+    if model.brush.active {
+        model.brush.press(pos);
+        return;
+    }
+    let viewport = viewport(model, main_window(app, model).rect());
+    let grabbed = model.attractors.iter().position(|attractor| {
+        let distance = Vec2::from(attractor.pos).distance(pos);
+        attractor.draggable && viewport.to_points(distance) <= GRAB_RADIUS
+    });
+    match grabbed {
+        Some(attractor) => model.dragging = Some(attractor),
+        None => model.spawning = Some(pos),
+    }
+}
+
+/// Move the attractor being dragged to under the mouse, or the view if it's been grabbed.
+fn mouse_moved(app: &App, model: &mut Model, pos: Point2) {
+    model.hints.touched();
+    if let Some(engagement) = &mut model.engagement {
+        engagement.interacted();
+    }
+    let scale = viewport(model, main_window(app, model).rect()).scale;
+    if model.camera.drag(pos, scale) {
+        return;
+    }
+    if let Some(attractor) = model.dragging {
+        let pos = to_world(app, model, pos).to_array();
+        model.apply(Change::Move { attractor, pos });
+    }
+}
+
+/// Spawn a particle where the window was clicked, going as fast and as far as it was dragged in
+/// `DRAG_SECONDS`, or on a circular orbit if it was only clicked, or pick out the particles in the
+/// box dragged out.
+fn mouse_released(app: &App, model: &mut Model, button: MouseButton) {
+    if button == MouseButton::Right {
+        model.camera.release();
+    }
+    if button != MouseButton::Left || model.dragging.take().is_some() {
+        return;
+    }
+    model.brush.release();
+    if let Some(start) = model.selection.boxing.take() {
+        let end = to_world(app, model, app.mouse.position());
+        let current = model.generations.last().unwrap();
+        model
+            .selection
+            .pick_box(current, Rect::from_corners(start, end));
+        return;
+    }
+    let Some(pos) = model.spawning.take() else {
+        return;
+    };
+    let end = to_world(app, model, app.mouse.position());
+    let viewport = viewport(model, main_window(app, model).rect());
+    let vel = if viewport.to_points(pos.distance(end)) > CLICK_RADIUS {
+        (end - pos) / DRAG_SECONDS
+    } else {
+        let gm = model
+            .center
+            .bodies(model.time)
+            .iter()
+            .map(|body| body.gm)
+            .sum();
+        let speed = physics::circular_speed(pos.length().max(1.0), gm);
+        speed * vec2(pos.y, -pos.x).normalize_or_zero()
+    };
+    model.apply(Change::Spawn {
+        pos: pos.to_array(),
+        vel: vel.to_array(),
+    });
+}
+
+/// Zoom the view in or out about the point under the mouse.
+fn mouse_wheel(app: &App, model: &mut Model, delta: MouseScrollDelta, _phase: TouchPhase) {
+    model.hints.touched();
+    if let Some(engagement) = &mut model.engagement {
+        engagement.scroll();
+    }
+    if model.is_follower() || model.egui.ctx().wants_pointer_input() {
+        return;
+    }
+    let scale = viewport(model, main_window(app, model).rect()).scale;
+    model.camera.scroll(delta, app.mouse.position(), scale);
+}
+
+/// The point of the world under `pos`, a point in the window.
+fn to_world(app: &App, model: &Model, pos: Point2) -> Point2 {
+    viewport(model, main_window(app, model).rect()).to_world(pos)
+}
+
+/// Add a keyframe to the camera's path of the view as it is now, while automation's recorded.
+fn key_camera(app: &App, model: &mut Model) {
+    if !model.automation.recording {
+        let err = "the camera is only keyed while automation's being recorded";
+        model.toasts.error(err);
+        return;
+    }
+    let win = match &model.span {
+        Some(span) => span.canvas(),
+        None => main_window(app, model).rect(),
+    };
+    let fit = fit(model, win);
+    let Viewport { scale, middle } = model.camera.view(fit, model.followed());
+    let orientation = settings::get()
+        .three_d
+        .then(|| Perspective::orientation_at(model.time));
+    model.automation.key_camera(CameraKey {
+        time: model.time,
+        middle: middle.into(),
+        zoom: scale / fit.scale,
+        orientation,
+        easing: Easing::Smooth,
+    });
+    let time = model.time;
+    model.toasts.info(format!("keyed the camera at {time:.2}s"));
+}
+
+/// The window the piece is shown in, whichever has the focus.
+fn main_window<'a>(app: &'a App, model: &Model) -> Ref<'a, Window> {
+    app.window(model.window).unwrap()
+}
+
+/// Open the window the piece is shown in, or a hidden one of `hidden_size` in its place, drawn
+/// with the GPU `gpu_request` asks for.
+fn open_window(app: &App, hidden_size: Option<(f32, f32)>, gpu_request: &GpuRequest) -> WindowId {
+    let mut builder = app
+        .new_window()
+        .power_preference(gpu_request.power_preference)
+        .force_fallback_adapter(gpu_request.fallback)
+        .view(view)
+        .key_pressed(key_pressed)
+        .mouse_pressed(mouse_pressed)
+        .mouse_moved(mouse_moved)
+        .mouse_released(mouse_released)
+        .mouse_wheel(mouse_wheel)
+        .raw_event(raw_window_event)
+        .dropped_file(dropped_file)
+        .window_icon(assets::icon());
+    if let Some((width, height)) = hidden_size {
+        builder = builder.size(width as u32, height as u32).visible(false);
+    }
+    let window = builder.build().unwrap();
+    gpu::watch(app.window(window).unwrap().device());
+    window
+}
+
+fn raw_window_event(app: &App, model: &mut Model, event: &nannou::winit::event::WindowEvent) {
+    if model.tray.is_some() && *event == nannou::winit::event::WindowEvent::CloseRequested {
+        // nannou has already dropped the window, so open a hidden one in its place, which also
+        // keeps the app from quitting for want of windows
+        let window = open_window(app, Some(model.window_size), &model.gpu_request);
+        model.egui = Egui::from_window(&app.window(window).unwrap());
+        model.window = window;
+        model.hidden = true;
+        // a hidden window never asks to be redrawn, which would otherwise drive updates
+        app.set_loop_mode(LoopMode::rate_fps(60.0));
+        return;
+    }
+    model.egui.handle_raw_event(event);
+}
+
+/// Take up the file dropped onto the window at `path`, as `dropped::open` has it, toasting what
+/// was made of it.
+fn dropped_file(app: &App, model: &mut Model, path: PathBuf) {
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let done = dropped::open(&path).and_then(|dropped| match dropped {
+        Dropped::Config(path) => {
+            load_config(app, &path).map(|()| format!("starting again under {name}"))
+        }
+        // followers take their palette from the leader
+        Dropped::Palette { .. } if model.is_follower() => {
+            Err("a follower takes its palette from its leader".into())
+        }
+        Dropped::Palette { .. } if model.playback.is_some() => {
+            Err("a clip is being exported".into())
+        }
+        Dropped::Palette {
+            name: palette,
+            stops,
+        } => {
+            model.apply(Change::LoadPalette {
+                name: palette,
+                stops,
+            });
+            Ok(format!("coloring the trails from {name}"))
+        }
+    });
+    match done {
+        Ok(done) => model.toasts.info(done),
+        Err(err) => model.toasts.error(format!("couldn't load {name}: {err}")),
+    }
+}
+
+/// Lighten the piece while the machine is strained as `strain` says, or draw it in full again once
+/// it's not.
+fn relieve(app: &App, model: &mut Model, strain: Option<Strain>) {
+    let config = model.power.as_ref().unwrap().config;
+    // a logged, replayed or synced run takes its particle count from its log or its leader
+    let own = model.session_log.is_none() && model.replay.is_none() && model.sync.is_none();
+    let particles = match strain {
+        Some(_) if own && model.unstrained_particles.is_none() && config.particles < 1.0 => {
+            let count = model.params.get(Param::Particles);
+            model.unstrained_particles = Some(count);
+            Some((count * config.particles).round().max(2.0))
+        }
+        Some(_) => None,
+        None => model.unstrained_particles.take(),
+    };
+    if let Some(value) = particles {
+        model.apply(Change::Set {
+            param: Param::Particles,
+            value,
+        });
+    }
+    if !model.hidden {
+        app.set_loop_mode(loop_mode(model));
+    }
+    match strain {
+        Some(strain) => model
+            .toasts
+            .info(format!("{}, so the piece is lightened", strain.label())),
+        None => model
+            .toasts
+            .info("no longer strained, so the piece is drawn in full again"),
+    }
+}
+
+/// How often the window is drawn, capped as the power section has it while the machine is
+/// strained.
+fn loop_mode(model: &Model) -> LoopMode {
+    match &model.power {
+        Some(power) if power.strain().is_some() && power.config.fps > 0.0 => {
+            LoopMode::rate_fps(power.config.fps as f64)
+        }
+        _ => LoopMode::default(),
+    }
+}
+
+/// Start the piece again in a process of its own, as the GPU is lost for good, from the state it
+/// was in unless the run is logged, replayed or synced, none of which could follow the jump.
+fn restart(app: &App, model: &mut Model) {
+    eprintln!("the GPU is lost, so the piece starts again");
+    let mut state = None;
+    if model.session_log.is_none() && model.replay.is_none() && model.sync.is_none() {
+        let path = Path::new(RECOVERY_PATH);
+        match model.state().save(path) {
+            Ok(()) => state = Some(path),
+            Err(err) => eprintln!("failed to save the state: {err}"),
+        }
+    }
+    match setup::relaunch(None, state) {
+        Ok(()) => app.quit(),
+        Err(err) => model.toasts.error(format!("error: {err}")),
+    }
+}
+
+/// Start the piece again under the config at `path`, if it's a good one.
+fn load_config(app: &App, path: &Path) -> Result<(), String> {
+    config::check(path).and_then(|()| setup::relaunch(Some(path), None))?;
+    app.quit();
+    Ok(())
+}
+
+/// Act on whatever was picked from the tray icon's menu.
+fn handle_tray(app: &App, model: &mut Model) {
+    let requests = model.tray.as_ref().map(TrayIcon::requests);
+    for request in requests.into_iter().flatten() {
+        match request {
+            tray::Request::Show => {
+                main_window(app, model).set_visible(true);
+                app.set_loop_mode(loop_mode(model));
+                model.hidden = false;
+            }
+            tray::Request::Screenshot => {
+                let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                let path = PathBuf::from(format!("screenshot-{}", since_epoch.as_secs()));
+                save_screenshot(app, model, &path);
+            }
+            tray::Request::Reseed if !model.is_follower() => {
+                let seed = model.rng.gen();
+                model.apply(Change::Reseed { seed })
+            }
+            tray::Request::Reseed => {}
+            tray::Request::Quit => app.quit(),
+        }
+    }
+}
+
+/// Capture the window as it is, and queue it to be written as a screenshot to `path`.
+fn save_screenshot(app: &App, model: &Model, path: &Path) {
+    let path = path.with_extension("png");
+    match capture(app, model, &model.screenshots) {
+        Some(frame) => {
+            model
+                .screenshots
+                .save_as(&path, frame, Some(model.manifest()));
+            model
+                .toasts
+                .info(format!("saved a screenshot to {}", path.display()));
+        }
+        None => model
+            .toasts
+            .error(format!("failed to capture {}", path.display())),
+    }
+}
+
+/// Write the current generation's trails, as the window shows them, to an SVG named for when it's
+/// written.
+fn export_svg(app: &App, model: &Model, path: Option<PathBuf>) {
+    let generation = model.current();
+    let win = main_window(app, model).rect();
+    let viewport = viewport(model, win);
+    let svg = svg::document(
+        generation,
+        look(model, generation, 1.0),
+        model.trail_style,
+        &model.render,
+        model.masks.get(Layer::Trails),
+        viewport.shown(win),
+        viewport.scale,
+    );
+    let path = path.unwrap_or_else(|| {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        PathBuf::from(format!("trails-{}.svg", since_epoch.as_secs()))
+    });
+    let written = (fs::write(&path, svg).map_err(|err| format!("{}: {err}", path.display())))
+        .and_then(|()| model.manifest().write_beside(&path));
+    match written {
+        Ok(()) => model
+            .toasts
+            .info(format!("wrote the trails to {}", path.display())),
+        Err(err) => model.toasts.error(err),
+    }
+}
+
+/// Write the still `snapshot` was run for, or its portraits, and quit.
+fn take_snapshot(app: &App, model: &mut Model) {
+    let snapshot = model.snapshot.take().unwrap();
+    if let Some(portraits) = &snapshot.portraits {
+        let written =
+            (portraits.write(&snapshot.out, take_portraits(app, model))).and_then(|paths| {
+                model
+                    .manifest()
+                    .write(&manifest::within(&snapshot.out), &paths)
+            });
+        if let Err(err) = written {
+            exit_with_error(&err);
+        }
+        return app.quit();
+    }
+    let screenshots = &model.screenshots;
+    match capture(app, model, screenshots) {
+        Some(frame) => screenshots.save_as(&snapshot.out, frame, Some(model.manifest())),
+        None => eprintln!("failed to capture {}", snapshot.out.display()),
+    }
+    app.quit();
+}
+
+/// Render the trail of each particle of the current generation alone on its background, framed
+/// to fill the window, each along with which particle it's of.
+fn take_portraits(app: &App, model: &Model) -> Vec<(usize, RgbaImage)> {
+    let window = main_window(app, model);
+    let win = window.rect();
+    let scale_factor = model.pixel_scale.unwrap_or(window.scale_factor());
+    let size = [win.w(), win.h()].map(|side| (side * scale_factor).round() as u32);
+    let pixels = Viewport::new(scale_factor, Point2::ZERO);
+    let generation = model.current();
+    let mut portraits = Vec::new();
+    for particle in 0..generation.particles.len() {
+        let alone = generation.isolated(particle);
+        let framed = Viewport::fitting(portraits::framing(&alone), win);
+        let draw = Draw::new();
+        draw.background().color(alone.background);
+        let projected = framed.project(&pixels.project(&draw));
+        draw_trails(model, &alone, 1.0, false, View::Orbits, &projected);
+        let shot = Shot::Still(draw);
+        match (model.screenshots).capture(window.device_queue_pair(), size, shot) {
+            Some(frame) => portraits.push((particle, export::to_image(size, &frame.pixels()))),
+            None => eprintln!("failed to capture the portrait of particle {particle}"),
+        }
+    }
+    portraits
+}
+
+/// Check the run `--selftest` asked for is still sound, and once it has run long enough, that it
+/// renders, then quit. Exits with an error at the first thing found wrong.
+fn self_test(app: &App, model: &mut Model) {
+    let result = selftest::check_particles(&model.generations).and_then(|()| {
+        let test = model.selftest.as_mut().unwrap();
+        test.steps += 1;
+        if test.steps < selftest::STEPS {
+            return Ok(false);
+        }
+        let screenshots = &model.screenshots;
+        selftest::check_frame(capture(app, model, screenshots).as_ref()).map(|()| true)
+    });
+    match result {
+        Ok(false) => {}
+        Ok(true) => {
+            println!("selftest: ok");
+            app.quit();
+        }
+        Err(err) => exit_with_error(&format!("selftest failed: {err}")),
+    }
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.clock.tick(&update);
+    let real_seconds = model.clock.real_seconds();
+    if model.splash.is_loading() {
+        let mut splash = mem::replace(&mut model.splash, Splash::Running);
+        splash.load(real_seconds, || pre_roll(model));
+        model.splash = splash;
+        return;
+    }
+    handle_tray(app, model);
+    model.toasts.update(real_seconds);
+    model.hints.update(real_seconds);
+    if let Some(Err(err)) =
+        (model.engagement.as_mut()).map(|engagement| engagement.update(real_seconds))
+    {
+        model
+            .toasts
+            .error(format!("failed to write the engagement report: {err}"));
+    }
+    match model.recovery.check() {
+        None => {}
+        Some(Recover::Rebuild) => {
+            if let Some(compute) = &mut model.compute {
+                compute.rebuild();
+            }
+            model
+                .toasts
+                .error("the GPU failed, so what's kept on it is built again");
+        }
+        Some(Recover::Restart) => restart(app, model),
+    }
+    if let Some(strain) = model.power.as_mut().and_then(Power::poll) {
+        relieve(app, model, strain);
+    }
+    if model.hidden {
+        if model.tray.as_ref().unwrap().config.on_close == OnClose::Pause {
+            return;
+        }
+    } else {
+        model.window_size = main_window(app, model).inner_size_points();
+    }
+    model.egui.set_elapsed_time(model.clock.real_elapsed());
+    let ctx = model.egui.begin_frame();
+    let mut output = ui::panel(
+        &ctx,
+        &mut model.panel,
+        &model.params,
+        &mut model.automation,
+        &mut model.selection,
+        &mut model.layers,
+        model.time,
+    );
+    ui::rewind(&ctx, &model.panel, &mut model.rewind, &mut output);
+    let setup = (model.setup.as_mut()).map(|setup| setup.show(&ctx, real_seconds));
+    drop(ctx);
+    if let Some(setup) = setup {
+        output.changes.extend(setup.changes);
+        if let Some(window) = setup.window {
+            window.apply(&main_window(app, model));
+        }
+        if setup.restart {
+            match setup::relaunch(None, None) {
+                Ok(()) => app.quit(),
+                Err(err) => model.toasts.error(format!("error: {err}")),
+            }
+        }
+    }
+
+    if let Some(dialog) = output.dialog.take() {
+        model.dialogs.open(dialog);
+    }
+    match model.dialogs.poll() {
+        None => {}
+        Some(Err(err)) => model.toasts.error(err),
+        Some(Ok((dialog, path))) => match dialog {
+            Dialog::LoadConfig => {
+                if let Err(err) = load_config(app, &path) {
+                    model.toasts.error(format!("error: {err}"));
+                }
+            }
+            Dialog::LoadPalette => match palette::load(&path) {
+                Ok((name, stops)) => {
+                    model.toasts.info(format!("loaded the palette {name}"));
+                    output.changes.push(Change::LoadPalette { name, stops });
+                }
+                Err(err) => model.toasts.error(err),
+            },
+            Dialog::SaveScreenshot => save_screenshot(app, model, &path),
+            Dialog::SaveSvg => export_svg(app, model, Some(path)),
+            Dialog::ExportClip => model.export_clip(Some(path)),
+        },
+    }
+
+    if let Some(metrics) = &model.metrics {
+        let particles = model.generations.iter().map(|g| g.particles.len()).sum();
+        metrics.frame(real_seconds, particles);
+    }
+
+    let (wall_seconds, delta_seconds) = if model.playback.is_some() {
+        play(model)
+    } else if model.is_follower() {
+        match follow(model) {
+            Some(step) => step,
+            // nothing to do until the leader moves on
+            None => return,
+        }
+    } else {
+        let wall_seconds = model.clock.seconds();
+        if model.brush.is_painting() {
+            let pos = to_world(app, model, app.mouse.position());
+            let viewport = viewport(model, main_window(app, model).rect());
+            output
+                .changes
+                .extend(model.brush.stroke(pos, viewport, wall_seconds));
+        }
+        let export_clip = output.export_clip;
+        let step = lead(model, output, wall_seconds);
+        if export_clip {
+            model.export_clip(None);
+        }
+        step
+    };
+
+    if model.playback.is_none() {
+        if model.rewind.wants_state(model.time) {
+            let state = model.state();
+            model.rewind.keep_state(state);
+        }
+        model.rewind.keep(model.time, &model.params);
+    }
+    let stepping = Instant::now();
+    step(model, wall_seconds, delta_seconds);
+    // a sequence exported is kept whole, and a video wall's instances have to agree on it
+    let adapt = model.export.is_none() && model.sync.is_none();
+    (model.quality).update(wall_seconds, stepping.elapsed().as_secs_f32(), adapt);
+    let window = main_window(app, model);
+    let device_queue = window.device_queue_pair();
+    model
+        .nebula
+        .render(device_queue, model.time, &model.generations);
+    drop(window);
+    render_gpu_trails(app, model);
+    if let Some(snapshot) = &mut model.snapshot {
+        snapshot.elapsed += wall_seconds;
+        if snapshot.elapsed >= snapshot.seconds {
+            take_snapshot(app, model);
+            return;
+        }
+    }
+    if model.selftest.is_some() {
+        self_test(app, model);
+        return;
+    }
+    if model.playback.is_some() {
+        record_clip(app, model, delta_seconds);
+        return;
+    }
+
+    if let Some(web) = &model.web {
+        web.publish(&model.params);
+    }
+    if let Some(exposure) = &model.exposure {
+        exposure.autosave();
+    }
+    // read back a few frames behind, so the render loop needn't wait on the GPU
+    let frames = model.export.as_ref().map_or_else(Vec::new, |export| {
+        let (size, shot, passes) = export_shot(app, model, export);
+        export.stream(
+            main_window(app, model).device_queue_pair(),
+            size,
+            shot,
+            passes,
+        )
+    });
+    if let Some(export) = &model.export {
+        for frame in &frames {
+            export.record(frame);
+        }
+        if model
+            .frame_limit
+            .is_some_and(|limit| export.frames() + export.reading() as u64 >= limit)
+        {
+            // the frames still being read back and queued are written on the way out
+            app.quit();
+        }
+    }
+    if let Some(leds) = &model.leds {
+        let shot = filled_shot(app, model, leds.shot_size());
+        leds.send(main_window(app, model).device_queue_pair(), shot);
+    }
+    let lighting_shot = model
+        .lighting
+        .as_ref()
+        .map(|_| filled_shot(app, model, lighting::SHOT_SIZE));
+    if let (Some(lighting), Some(shot)) = (&mut model.lighting, lighting_shot) {
+        lighting.send(
+            app.window(model.window).unwrap().device_queue_pair(),
+            shot,
+            real_seconds,
+        );
+    }
+    let ambience_shot = model
+        .ambience
+        .as_mut()
+        .is_some_and(|ambience| ambience.due(real_seconds))
+        .then(|| filled_shot(app, model, lighting::SHOT_SIZE));
+    if let (Some(ambience), Some(shot)) = (&mut model.ambience, ambience_shot) {
+        ambience.send(app.window(model.window).unwrap().device_queue_pair(), shot);
+    }
+    let bodies = model.bodies();
+    if let Some(log) = &mut model.diagnostics_log {
+        log.update(
+            model.clock.elapsed(),
+            wall_seconds,
+            model.time,
+            &model.quality,
+            &model.generations.last().unwrap().particles,
+        );
+    }
+    if let Some(highlights) = &mut model.highlights {
+        highlights.update(
+            model.clock.elapsed(),
+            model.generations.last().unwrap(),
+            &bodies,
+            frames,
+            model.export.as_ref(),
+        );
+    }
+
+    if let Some(sync) = &model.sync {
+        if sync.role == Role::Leader {
+            sync.send(&Message::Tick {
+                time: model.time,
+                wall_seconds,
+                params: model.params.iter().collect(),
+                clock: sync.genlock.then(genlock::now),
+            });
+        }
+    }
+}
+
+/// Gather every change made locally and decide how far to step, returning the wall-clock and
+/// simulated seconds to advance by.
+fn lead(model: &mut Model, output: ui::PanelOutput, wall_seconds: f32) -> (f32, f32) {
+    for change in output.changes {
+        model.apply(change);
+    }
+    if output.roll_dice {
+        model.roll_dice();
+    }
+    if let Some(filter) = output.pick {
+        let gm = model.bodies().iter().map(|body| body.gm).sum::<f32>()
+            * model.params.get(Param::Gravity);
+        let current = model.generations.last().unwrap();
+        model.selection.pick_filter(current, filter, gm);
+    }
+    let requests = (model.web.as_ref().map(WebControl::requests))
+        .into_iter()
+        .chain(model.osc.as_ref().map(Osc::requests));
+    for request in requests.flatten().collect::<Vec<_>>() {
+        match request {
+            Request::Set { param, value } => model.apply(Change::Set { param, value }),
+            Request::Reseed => {
+                let seed = model.rng.gen();
+                model.apply(Change::Reseed { seed })
+            }
+            Request::NextCue => model.next_cue(),
+        }
+    }
+    let (played, burst) = match &model.midi {
+        Some(midi) => (midi.requests(), midi.burst()),
+        None => (Vec::new(), 0),
+    };
+    for request in played {
+        match request {
+            midi::Request::Set {
+                target: Target::Param(param),
+                value,
+            } => model.apply(Change::Set { param, value }),
+            midi::Request::Set {
+                target: Target::Attractor(attractor),
+                value,
+            } => model.apply(Change::Weigh {
+                attractor,
+                mass: value,
+            }),
+            midi::Request::Act(NoteAction::Burst) => model.burst(burst),
+            midi::Request::Act(NoteAction::Reseed) => {
+                let seed = model.rng.gen();
+                model.apply(Change::Reseed { seed })
+            }
+            midi::Request::Act(NoteAction::Dice) => model.roll_dice(),
+            midi::Request::Act(NoteAction::NextCue) => model.next_cue(),
+        }
+    }
+    if output.save_automation {
+        let path = model.automation_path.display().to_string();
+        match model.automation.save(&model.automation_path) {
+            Ok(()) => model.toasts.info(format!("saved the automation to {path}")),
+            Err(err) => model
+                .toasts
+                .error(format!("couldn't save the automation: {err}")),
+        }
+    }
+    if !model.automation.recording {
+        for param in Param::ALL {
+            if let Some(value) = model.automation.value(param, model.time) {
+                model.params.set(param, value);
+            }
+        }
+    }
+
+    if let Some(cues) = &mut model.cues {
+        cues.update(wall_seconds, &mut model.params);
+    }
+    if let Some(next) = model.presets.update(wall_seconds, &mut model.params) {
+        model.preset(next);
+    }
+    if let Some(audio) = &mut model.audio {
+        audio.modulate(wall_seconds, &mut model.params);
+        if let Some(err) = audio.stopped() {
+            model.toasts.error(err);
+        }
+    }
+    let gm =
+        model.bodies().iter().map(|body| body.gm).sum::<f32>() * model.params.get(Param::Gravity);
+    if let Some(feedback) = &mut model.feedback {
+        let generation = model.generations.last().unwrap();
+        feedback.modulate(wall_seconds, generation, gm, &mut model.params);
+    }
+    if let Some(framing) = &mut model.framing {
+        let generation = model.generations.last().unwrap();
+        framing.update(wall_seconds, &generation.particles);
+    }
+
+    while let Some(change) = model
+        .replay
+        .as_mut()
+        .and_then(|replay| replay.next_due(model.time))
+    {
+        model.apply(change);
+    }
+
+    let delta_seconds = if model.paused {
+        // a tick at a time, when asked for
+        if std::mem::take(&mut model.step_tick) {
+            TICK_SECONDS
+        } else {
+            0.0
+        }
+    } else {
+        scale_time(model, wall_seconds)
+    };
+    (wall_seconds, delta_seconds)
+}
+
+/// Play the clip being exported on by a frame, making the changes and taking the parameters the
+/// run had by now the first time round, returning how far to step.
+fn play(model: &mut Model) -> (f32, f32) {
+    let clip = &mut model.playback.as_mut().unwrap().clip;
+    let (changes, params) = clip.due(model.time);
+    let wall_seconds = model.clock.seconds();
+    for change in changes {
+        model.apply(change);
+    }
+    if let Some(params) = params {
+        model.params = params;
+    }
+    (wall_seconds, scale_time(model, wall_seconds))
+}
+
+/// Export the frame just stepped to if it's in the clip being exported, going back to the run as
+/// it was once the clip is over.
+fn record_clip(app: &App, model: &mut Model, delta_seconds: f32) {
+    let clip = &model.playback.as_ref().unwrap().clip;
+    if clip.shows(model.time) {
+        if let Some(frame) = capture(app, model, &clip.export) {
+            clip.export.record(&frame);
+        }
+    }
+    let time = model.time;
+    let playback = model.playback.as_mut().unwrap();
+    if !playback.clip.is_done(time, delta_seconds) {
+        return;
+    }
+    let Playback {
+        clip,
+        live,
+        baked,
+        clocked,
+    } = model.playback.take().unwrap();
+    let frames = clip.export.frames();
+    clip.export.finish(Some(&model.manifest()));
+    model.resume(live);
+    model.baked = baked;
+    model.clock.set_mode(clocked);
+    model.toasts.info(format!(
+        "wrote {frames} frames of the clip to {}",
+        clip.dir.display()
+    ));
+}
+
+/// Simulated seconds to advance by over `wall_seconds`, in bullet time if the swarm is diving.
+fn scale_time(model: &mut Model, wall_seconds: f32) -> f32 {
+    let bodies = model.bodies();
+    let peak_acceleration =
+        time_scale::peak_acceleration(&model.current().particles, &bodies, model.time);
+    model.time_scale.update(wall_seconds, peak_acceleration);
+    model
+        .time_scale
+        .scale(wall_seconds, model.params.get(Param::TimeScale))
+}
+
+/// Mirror whatever the leader did since the last frame, returning how far to step to catch up
+/// with it, if at all.
+fn follow(model: &mut Model) -> Option<(f32, f32)> {
+    let messages = model.sync.as_mut()?.receive();
+    let mut caught_up = None;
+    let mut wall_seconds = 0.0;
+    for message in messages {
+        match message {
+            Message::Change { change } => model.apply(change),
+            Message::Tick {
+                time,
+                wall_seconds: leader_wall_seconds,
+                params,
+                clock,
+            } => {
+                for (param, value) in params {
+                    model.params.set(param, value);
+                }
+                wall_seconds += leader_wall_seconds;
+                // how fast simulated time ran over the last tick
+                let rate = if leader_wall_seconds > 0.0 {
+                    (time - caught_up.map_or(model.time, |(t, _, _)| t)) / leader_wall_seconds
+                } else {
+                    0.0
+                };
+                caught_up = Some((time, clock, rate));
+            }
+        }
+    }
+
+    let sync = model.sync.as_ref()?;
+    if let Some((_, Some(clock), rate)) = caught_up {
+        model.genlocked = Some((clock, rate));
+    }
+    // even without a new tick, the leader's clock moves on
+    model.extrapolate = match model.genlocked {
+        Some((clock, rate)) if sync.genlock => {
+            ((sync.leader_now() - clock) as f32 * rate).clamp(0.0, MAX_EXTRAPOLATE)
+        }
+        _ => 0.0,
+    };
+    let (time, _, _) = caught_up?;
+    model.clock.replay(wall_seconds);
+    Some((wall_seconds, time - model.time))
+}
+
+/// Advance the simulation by `delta_seconds`, and fade generations in and out over
+/// `wall_seconds` of real time.
+fn step(model: &mut Model, wall_seconds: f32, delta_seconds: f32) {
+    model.center.advance(model.time, delta_seconds);
+    model.time += delta_seconds;
+    model.drift += delta_seconds * model.params.get(Param::LayerDrift);
+    let bodies = model.bodies();
+    if let Some(perturbations) = &model.perturbations {
+        let split = perturbations.apply(&mut model.generations, &bodies, model.time, delta_seconds);
+        if !split.is_empty() {
+            model.split(&split);
+        }
+    }
+    let merged = generation::step(
+        &mut model.generations,
+        wall_seconds,
+        delta_seconds,
+        &bodies,
+        &model.params,
+        model
+            .compute
+            .as_mut()
+            .map(|compute| compute as &mut dyn Ticker),
+    );
+    if !merged.is_empty() {
+        model.merged(&merged);
+    }
+    let current = model.generations.last_mut().unwrap();
+    if (model.classes).update(&current.particles, &bodies, delta_seconds) {
+        current.restyle(model.classes.styles());
+    }
+}
+
+/// Draw the trails of a generation as `look` has them, as ribbons between neighboring particles
+/// updated in `ribbons` then thinned out, looked over and stacked by `thin`, along with the lines
+/// over the most interesting, all faded by `mask` if there is one.
+fn draw_history(
+    generation: &Generation,
+    look: Look,
+    ribbons: &mut Ribbons,
+    thin: impl FnOnce(&mut trails::Mesh),
+    mask: Option<&Mask>,
+    draw: &Draw,
+) {
+    if generation.history.iter().all(VecDeque::is_empty) {
+        return;
+    }
+    let Look {
+        opacity,
+        highlight,
+        extrapolate,
+        build_in,
+        length,
+        ..
+    } = look;
+    let ribbons = ribbons.update_placed(
+        generation,
+        opacity,
+        highlight,
+        extrapolate,
+        build_in * length,
+        |particle, pos| look.place(generation, particle, pos),
+    );
+    thin(ribbons);
+    if let Some(mask) = mask {
+        mask.apply(ribbons);
+    }
+    let vertices = ribbons.vertices.iter().map(|&(pos, mut color)| {
+        color.saturation *= build_in;
+        (pos, color)
+    });
+    draw.mesh()
+        .indexed_colored(vertices, ribbons.indices.iter().copied());
+    draw_highlights(generation, look, mask, draw);
+}
+
+/// Draw the ribbons of the archives of `generation`'s trails, and of the remnants of those merged
+/// away, as `look` has them, faded by `mask` if there is one, under its trails.
+fn draw_archived(
+    model: &Model,
+    generation: &Generation,
+    look: Look,
+    mask: Option<&Mask>,
+    draw: &Draw,
+) {
+    let length = look.build_in * look.length;
+    let place = |particle, pos| look.place(generation, particle, pos);
+    for mut mesh in [
+        trails::archived_ribbons(generation, look.opacity, look.highlight, length, place),
+        trails::remnant_ribbons(generation, look.opacity, look.highlight, length, place),
+    ] {
+        if mesh.indices.is_empty() {
+            continue;
+        }
+        if let Some(mask) = mask {
+            mask.apply(&mut mesh);
+        }
+        count_vertices(model, mesh.vertices.len());
+        let vertices = mesh.vertices.into_iter().map(|(pos, mut color)| {
+            color.saturation *= look.build_in;
+            (pos, color)
+        });
+        draw.mesh().indexed_colored(vertices, mesh.indices);
+    }
+}
+
+/// Draw lines over the most interesting trails of a generation, as `look` has them, faded by
+/// `mask` if there is one.
+fn draw_highlights(generation: &Generation, look: Look, mask: Option<&Mask>, draw: &Draw) {
+    let Look {
+        opacity,
+        highlight,
+        extrapolate,
+        build_in,
+        length,
+        view,
+    } = look;
+    let length = build_in * length;
+    for mut line in trails::highlights(generation, opacity, highlight, extrapolate, length) {
+        if let Some(view) = view {
+            view.flatten_trail(generation, line.particle, &mut line.points);
+        }
+        for (pos, color) in &mut line.points {
+            color.saturation *= build_in;
+            color.alpha *= mask.map_or(1.0, |mask| mask.coverage(*pos));
+        }
+        draw.polyline()
+            .weight(line.width)
+            .points_colored(line.points);
+    }
+}
+
+/// Draw onion-skin frames of where the swarm was, each older frame fainter than the last, and
+/// faded by `mask` if there is one, seen through `view` in 3D.
+fn draw_ghosts(
+    generation: &Generation,
+    view: Option<Perspective>,
+    mask: Option<&Mask>,
+    draw: &Draw,
+) {
+    for (age, positions) in generation.ghosts.iter().enumerate() {
+        let fade = 1.0 - age as f32 / settings::get().ghost_count as f32;
+        for (particle, (pos, color)) in positions.iter().zip(&generation.colors).enumerate() {
+            let pos = &view.map_or(*pos, |view| view.place(generation, particle, *pos));
+            let mut color = *color;
+            color.alpha *= fade * generation.opacity;
+            color.alpha *= mask.map_or(1.0, |mask| mask.coverage(*pos));
+            draw.ellipse().xy(*pos).radius(12.0).color(color);
+        }
+    }
+}
+
+fn view(app: &App, model: &Model, frame: Frame) {
+    let win = main_window(app, model).rect();
+    let screen = app.draw();
+    if model.splash.is_loading() {
+        let current = model.current();
+        (model.splash).draw(win, current.background, current.circle_color, &screen);
+        return screen.to_frame(app, &frame).unwrap();
+    }
+
+    let (incoming, outgoing) = model.generations.split_last().unwrap();
+    model.quality.vertices.set(0);
+    if model.show_stats {
+        // a grid of cells 8 points across over the part of the world in the window
+        let viewport = viewport(model, win);
+        let view = viewport.shown(win);
+        *model.occupancy.borrow_mut() = Some(Occupancy::new(view, viewport.to_units(8.0)));
+    }
+    if let Some(smear) = &model.style.smear {
+        let scale_factor = main_window(app, model).scale_factor();
+        let project = |draw: &Draw| camera(model, win, &draw.scale(scale_factor));
+        let pixels_per_unit = viewport(model, win).scale * scale_factor;
+        smear.splat(&model.generations, project, pixels_per_unit);
+    }
+    // the overlays are left clear of the stylization
+    let strained = model.power.as_ref().is_some_and(Power::drops_effects);
+    let posts = if strained {
+        Vec::new()
+    } else {
+        model.style.posts()
+    };
+    if !posts.is_empty() {
+        gpu::with_frame(&frame, |target| {
+            gpu::draw_posted(&posts, target, &mut |target, scene| {
+                draw_through(app, model, target, scene)
+            })
+        });
+    } else if let Some(exposure) = &model.exposure {
+        gpu::with_frame(&frame, |target| expose(app, model, exposure, target));
+    } else if outgoing.is_empty() {
+        screen.background().color(incoming.background);
+        let points = Viewport::new(1.0, Point2::ZERO);
+        draw_panes(model, slice::from_ref(incoming), true, win, points, &screen);
+    } else if let Shot::Transition {
+        outgoing,
+        incoming,
+        progress,
+    } = window_shot(app, model)
+    {
+        gpu::with_frame(&frame, |target| {
+            model
+                .transition
+                .draw(target, &outgoing, &incoming, progress)
+        });
+    }
+
+    // taken before the minimap adds its own trails
+    let overdraw = model.occupancy.borrow_mut().take().map(|o| o.overdraw());
+    let vertices = model.quality.vertices.take();
+
+    if let Some(screen) = model.layers.overlays.draw(&screen) {
+        draw_overlays(app, model, incoming, overdraw, vertices, &screen);
+    }
+
+    screen.to_frame(app, &frame).unwrap();
+    model.egui.draw_to_frame(&frame).unwrap();
+}
+
+/// Draw the part of the scene a window the piece is spread over shows, other than its own window:
+/// all but the overlays and the stylization, with the trails built on the CPU.
+fn view_pane(app: &App, model: &Model, frame: Frame) {
+    let span = model.span.as_ref().unwrap();
+    let Some(pane) = span.pane(frame.window_id()) else {
+        return;
+    };
+    let window = app.window(frame.window_id()).unwrap();
+    let viewport = pane_viewport(model, span, pane, window.rect());
+    let camera = |draw: &Draw| viewport.project(draw);
+    let (incoming, outgoing) = model.generations.split_last().unwrap();
+    if outgoing.is_empty() {
+        let draw = Draw::new();
+        draw.background().color(incoming.background);
+        draw_scene(
+            model,
+            slice::from_ref(incoming),
+            false,
+            model.view,
+            &camera(&draw),
+        );
+        draw.to_frame(app, &frame).unwrap();
+    } else {
+        let scale_factor = window.scale_factor();
+        let scene = |generations: &[Generation], draw: &Draw| {
+            let draw = camera(&draw.scale(scale_factor));
+            draw_scene(model, generations, false, model.view, &draw);
+        };
+        if let Shot::Transition {
+            outgoing,
+            incoming,
+            progress,
+        } = shot(model, scene, true)
+        {
+            gpu::with_frame(&frame, |target| {
+                model
+                    .transition
+                    .draw(target, &outgoing, &incoming, progress)
+            });
+        }
+    }
+}
+
+/// Draw the overlays of `generation`, those over the window rather than the world, onto
+/// `screen`. `overdraw` is how much the trails drawn this frame overdrew, if it was counted, and
+/// `vertices` how many vertices they were built of.
+fn draw_overlays(
+    app: &App,
+    model: &Model,
+    generation: &Generation,
+    overdraw: Option<Overdraw>,
+    vertices: usize,
+    screen: &Draw,
+) {
+    let win = main_window(app, model).rect();
+    let layer = &model.layers.overlays;
+    let viewport = viewport(model, win);
+    if model.show_labels || model.show_velocities {
+        overlay::draw_particles(
+            generation,
+            |pos| viewport.to_screen(pos),
+            model.show_labels,
+            model.show_velocities,
+            layer,
+            screen,
+        );
+    }
+    if model.classes.color {
+        (model.classes).draw_rings(generation, |pos| viewport.to_screen(pos), layer, screen);
+    }
+    if !model.selection.particles.is_empty() {
+        overlay::draw_picked(
+            generation,
+            &model.selection.particles,
+            |pos| viewport.to_screen(pos),
+            layer,
+            screen,
+        );
+    }
+    if model.brush.active {
+        screen
+            .ellipse()
+            .xy(app.mouse.position())
+            .radius(model.brush.config.radius)
+            .no_fill()
+            .stroke_weight(1.0)
+            .stroke(layer.fade(rgba(1.0, 1.0, 1.0, 0.6)));
+    }
+    if let Some(start) = model.selection.boxing {
+        let start = viewport.to_screen(start);
+        let rect = Rect::from_corners(start, app.mouse.position());
+        screen
+            .rect()
+            .xy(rect.xy())
+            .wh(rect.wh())
+            .no_fill()
+            .stroke_weight(1.0)
+            .stroke(layer.fade(WHITE));
+    }
+    if let Some(start) = model.spawning {
+        screen
+            .arrow()
+            .start(viewport.to_screen(start))
+            .end(app.mouse.position())
+            .weight(1.5)
+            .color(layer.fade(WHITE));
+    }
+    let moved = model.camera.is_moved() || model.camera_path().is_some();
+    if (model.viewport.is_some() || moved) && model.show_minimap {
+        let shown = viewport.shown(win);
+        draw_minimap(model, generation, shown, win, screen);
+    }
+    if model.show_help {
+        let rows = overlay::help_rows(&model.keymap, model.describe_scene());
+        overlay::draw_panel(&rows, Corner::TopLeft, win, layer, screen);
+    }
+    // the diagnostics and the stats share the corner, one over the other
+    let mut rows = Vec::new();
+    if model.show_diagnostics {
+        rows.extend(overlay::diagnostics_rows(
+            &model.quality,
+            &generation.particles,
+            vertices,
+        ));
+    }
+    if let Some(overdraw) = overdraw {
+        rows.extend(overlay::stats_rows(app.fps(), overdraw));
+    }
+    if !rows.is_empty() {
+        overlay::draw_panel(&rows, Corner::TopRight, win, layer, screen);
+    }
+    // the stats panes show the diagnostics, and the legend in place of its corner
+    let stats = model.layout.stats(win);
+    for &pane in &stats {
+        let mut rows = overlay::diagnostics_rows(&model.quality, &generation.particles, vertices);
+        if model.classes.legend {
+            rows.extend(model.classes.legend_rows());
+        }
+        overlay::draw_panel(&rows, Corner::TopLeft, pane, layer, screen);
+    }
+    if model.classes.legend && stats.is_empty() {
+        let rows = model.classes.legend_rows();
+        overlay::draw_panel(&rows, Corner::BottomLeft, win, layer, screen);
+    }
+    let exporting = match &model.playback {
+        Some(playback) => Some(&playback.clip.export),
+        None => model.export.as_ref().filter(|export| export.is_sequence()),
+    };
+    if let Some(export) = exporting {
+        let rows = overlay::export_rows(export);
+        overlay::draw_panel(&rows, Corner::BottomRight, win, layer, screen);
+    }
+    model.hints.draw(win, layer, screen);
+    model.toasts.draw(win, layer, screen);
+}
+
+/// Add the trails to the long exposure, and develop it onto `target`.
+fn expose(app: &App, model: &Model, exposure: &LongExposure, target: &mut gpu::Target) {
+    // only the trails are exposed, over the current generation's background
+    let draw = Draw::new();
+    draw.background().color(rgba(0.0, 0.0, 0.0, 0.0));
+    let scale_factor = main_window(app, model).scale_factor();
+    let camera = camera(
+        model,
+        main_window(app, model).rect(),
+        &draw.scale(scale_factor),
+    );
+    let camera = model.layers.trails.drift(model.drift, &camera);
+    for generation in &model.generations {
+        draw_trails(
+            model,
+            generation,
+            generation.opacity,
+            true,
+            model.view,
+            &camera,
+        );
+    }
+    let gain = model.params.get(Param::ExposureGain);
+    let background = model.generations.last().unwrap().background;
+    exposure.draw(target, &draw, background, gain);
+}
+
+/// Draw the scene, all but the overlays, onto `target`, a texture that `scene` draws onto too.
+fn draw_through(app: &App, model: &Model, target: &mut gpu::Target, scene: &mut gpu::Scene) {
+    if let Some(exposure) = &model.exposure {
+        return expose(app, model, exposure, target);
+    }
+    match window_shot(app, model) {
+        Shot::Still(draw) => scene.render(target.device_queue.device(), target.encoder, &draw),
+        Shot::Transition {
+            outgoing,
+            incoming,
+            progress,
+        } => model
+            .transition
+            .draw(target, &outgoing, &incoming, progress),
+    }
+}
+
+/// The scene as the window shows it, in pixels.
+fn window_shot(app: &App, model: &Model) -> Shot {
+    let win = main_window(app, model).rect();
+    let scale_factor = main_window(app, model).scale_factor();
+    let pixels = Viewport::new(scale_factor, Point2::ZERO);
+    shot(
+        model,
+        |generations, draw| draw_panes(model, generations, true, win, pixels, draw),
+        true,
+    )
+}
+
+/// Render the scene for `export`, cropped if it asks to be.
+fn capture(app: &App, model: &Model, export: &Export) -> Option<Captured> {
+    let (size, shot, _) = export_shot(app, model, export);
+    export.capture(main_window(app, model).device_queue_pair(), size, shot)
+}
+
+/// The shot `export` takes of the scene, its size in pixels, and its auxiliary passes drawn.
+fn export_shot(app: &App, model: &Model, export: &Export) -> ([u32; 2], Shot, Vec<(Pass, Draw)>) {
+    let window = main_window(app, model);
+    let win = window.rect();
+    let scale_factor = model.pixel_scale.unwrap_or(window.scale_factor());
+    let [width, height] = [win.w(), win.h()].map(|side| (side * scale_factor).round() as u32);
+    let background = !export.transparent();
+    let pixels = |wh: Vec2| [wh.x, wh.y].map(|side| side.round().max(1.0) as u32);
+    let window_scale = viewport(model, win).scale * scale_factor;
+    type Project<'a> = Box<dyn Fn(&Draw) -> Draw + 'a>;
+    // how points of the window map onto the shot, as it's of the window's panes at all
+    let (size, pixels_per_unit, screen, project): (_, _, _, Project) = match export.crop() {
+        None => (
+            [width, height],
+            window_scale,
+            Some(Viewport::new(scale_factor, Point2::ZERO)),
+            Box::new(|draw| camera(model, win, &draw.scale(scale_factor))),
+        ),
+        // a piece of the window, as shown
+        Some(Crop::Pixels(rect)) => (
+            pixels(rect.wh()),
+            window_scale,
+            Some(Viewport::new(scale_factor, rect.xy() / scale_factor)),
+            Box::new(move |draw| {
+                let draw = draw.translate(-rect.xy().extend(0.0));
+                camera(model, win, &draw.scale(scale_factor))
+            }),
+        ),
+        // a piece of the world, magnified to fill as much as the window would
+        Some(Crop::World(rect)) => {
+            let scale = (width as f32 / rect.w()).min(height as f32 / rect.h());
+            (
+                pixels(rect.wh() * scale),
+                scale,
+                None,
+                Box::new(move |draw| draw.scale(scale).translate(-rect.xy().extend(0.0))),
+            )
+        }
+    };
+    if let Some(smear) = export.smear() {
+        smear.splat(&model.generations, &project, pixels_per_unit);
+    }
+    let look = look(model, model.current(), 1.0);
+    let passes = (export.passes().into_iter())
+        .map(|pass| {
+            let draw = Draw::new();
+            let weight = model.render.line_width;
+            pass.draw(
+                model.current(),
+                &look,
+                weight,
+                pixels_per_unit,
+                &project(&draw),
+            );
+            (pass, draw)
+        })
+        .collect();
+    let scene = |generations: &[Generation], draw: &Draw| match screen {
+        Some(screen) => draw_panes(model, generations, false, win, screen, draw),
+        None => draw_scene(model, generations, false, model.view, &project(draw)),
+    };
+    (size, shot(model, scene, background), passes)
+}
+
+/// The window's view over its background, scaled to fill `size` pixels and cropped to its shape.
+fn filled_shot(app: &App, model: &Model, size: [u32; 2]) -> Shot {
+    let win = main_window(app, model).rect();
+    let [width, height] = size.map(|side| side as f32);
+    let scale = (width / win.w()).max(height / win.h());
+    let pixels = Viewport::new(scale, Point2::ZERO);
+    shot(
+        model,
+        |generations, draw| draw_panes(model, generations, false, win, pixels, draw),
+        true,
+    )
+}
+
+/// Draw the scene as it stands, all but the overlays, over either its background or nothing.
+/// `draw_scene` draws the generations it's given onto a drawing in pixels, centered on the frame.
+fn shot(model: &Model, draw_scene: impl Fn(&[Generation], &Draw), background: bool) -> Shot {
+    let scene = |generations: &[Generation]| {
+        let draw = Draw::new();
+        let color = if background {
+            generations.last().unwrap().background.into()
+        } else {
+            rgba(0.0, 0.0, 0.0, 0.0)
+        };
+        draw.background().color(color);
+        draw_scene(generations, &draw);
+        draw
+    };
+    let (incoming, outgoing) = model.generations.split_last().unwrap();
+    if outgoing.is_empty() {
+        Shot::Still(scene(slice::from_ref(incoming)))
+    } else {
+        Shot::Transition {
+            outgoing: scene(outgoing),
+            incoming: scene(slice::from_ref(incoming)),
+            progress: incoming.opacity,
+        }
+    }
+}
+
+/// Map world coordinates onto `screen`, a drawing the size of the window.
+fn camera(model: &Model, win: Rect, screen: &Draw) -> Draw {
+    viewport(model, win).project(screen)
+}
+
+/// Draw a scene made of `generations` as `draw_scene` does, in each of the panes the window `win`
+/// is partitioned into that shows the simulation, or filling it if it isn't. `screen` maps points
+/// of the window onto `target`, the drawing it's drawn on, and `gpu` is whether it's as the window
+/// shows it, so trails built on the GPU can be drawn in the piece's own view.
+fn draw_panes(
+    model: &Model,
+    generations: &[Generation],
+    gpu: bool,
+    win: Rect,
+    screen: Viewport,
+    target: &Draw,
+) {
+    let projected = screen.project(target);
+    if model.layout.panes.is_empty() {
+        let draw = camera(model, win, &projected);
+        return draw_scene(model, generations, gpu, model.view, &draw);
+    }
+    for (i, pane) in model.layout.panes.iter().enumerate() {
+        let Some(shown) = pane.show.view() else {
+            continue;
+        };
+        let rect = pane.rect(win);
+        let scissor = Rect::from_corners(
+            screen.to_screen(rect.bottom_left()),
+            screen.to_screen(rect.top_right()),
+        );
+        let screen = screen.project(&target.scissor(scissor));
+        // the piece's own view, the first, is as the camera has it and switched by the hotkeys
+        let (shown, viewport) = match i {
+            0 => (model.view, viewport(model, win)),
+            _ => (shown, pane.viewport(shown, win)),
+        };
+        draw_scene(
+            model,
+            generations,
+            gpu && i == 0,
+            shown,
+            &viewport.project(&screen),
+        );
+    }
+}
+
+/// The view of the world the window `win` shows, as the camera has moved it.
+fn viewport(model: &Model, win: Rect) -> Viewport {
+    match (&model.span, model.layout.panes.first()) {
+        // the piece's own window is the first of those it's spread over
+        (Some(span), _) => pane_viewport(model, span, 0, win),
+        // and its own view the first of the panes it's partitioned into
+        (None, Some(pane)) => {
+            let rect = pane.rect(win);
+            model
+                .camera_view(fit(model, Rect::from_wh(rect.wh())))
+                .within(rect)
+        }
+        (None, None) => model.camera_view(fit(model, win)),
+    }
+}
+
+/// `viewport` for the window of `pane` of `span`, `win` in points, the camera moving the view of
+/// the whole canvas.
+fn pane_viewport(model: &Model, span: &Span, pane: usize, win: Rect) -> Viewport {
+    let whole = model.camera_view(fit(model, span.canvas()));
+    span.view(pane, whole, win)
+}
+
+/// The view of the world before the camera moves it, as `viewport` has it.
+fn fit(model: &Model, win: Rect) -> Viewport {
+    match model.viewport {
+        // fill the window with just the given region
+        Some(region) => Viewport::fitting(region, win),
+        None => match &model.framing {
+            // the orbits as the swarm has them, the other views being of the whole system
+            Some(framing) if model.view == View::Orbits => Viewport::fitting(framing.region(), win),
+            _ => layout::fit(model.view, win),
+        },
+    }
+}
+
+/// The point of the world in the middle of the window before the camera moves it.
+fn fit_middle(model: &Model) -> Point2 {
+    // phase space is fitted in beside the orbits
+    (model.viewport.or(model.view.region())).map_or(Point2::ZERO, |viewport| viewport.xy())
+}
+
+/// Draw a scene made of `generations`, all but its background, in world coordinates, as `shown`
+/// has it. The last generation is drawn fully opaque, and its flat colors are used for the whole
+/// scene. Its trails are drawn as built on the GPU if `gpu`, for scenes shown as the window shows
+/// them.
+fn draw_scene(model: &Model, generations: &[Generation], gpu: bool, shown: View, draw: &Draw) {
+    let top = generations.last().unwrap();
+    // a follower's viewport is its tile of a video wall, all seen by the one camera
+    let middle = match model.viewport {
+        Some(viewport) if !model.is_follower() => viewport.xy(),
+        _ => Point2::ZERO,
+    };
+    let layers = &model.layers;
+    if let Some(draw) = layers.background.draw(draw) {
+        let draw = layers.background.drift(model.drift, &draw);
+        model.nebula.draw(&draw);
+        model.stars.draw(model.time, middle, &draw);
+    }
+    layers.background.veil(VEIL_EXTENT * orbital_radius(), draw);
+    let view = model.perspective();
+    let opacity = |i: usize, generation: &Generation| {
+        if i == generations.len() - 1 {
+            1.0
+        } else {
+            generation.opacity
+        }
+    };
+    for layer in layers.order() {
+        let config = layers.get(layer);
+        let Some(draw) = config.draw(draw) else {
+            continue;
+        };
+        let draw = config.drift(model.drift, &draw);
+        let chart = shown == View::RadiusChart;
+        match layer {
+            Layer::Guides if chart => chart::draw_guides(config.fade(top.circle_color), &draw),
+            Layer::Guides if shown == View::PhaseSpace && view.is_none() => {
+                chart::draw_phase_guides(config.fade(top.circle_color), &draw);
+                draw.ellipse()
+                    .radius(orbital_radius())
+                    .color(config.fade(top.circle_color));
+            }
+            // draw the average orbit, a circle
+            Layer::Guides => match view {
+                Some(view) => {
+                    draw.polygon()
+                        .points(view.disc(orbital_radius()))
+                        .color(config.fade(top.circle_color));
+                }
+                None => {
+                    draw.ellipse()
+                        .radius(orbital_radius())
+                        .color(config.fade(top.circle_color));
+                }
+            },
+            // the bakes, the shapes the particles make and the masses are of the orbits, not the
+            // chart
+            Layer::Baked | Layer::Constellation | Layer::Tessellation | Layer::Hulls if chart => {}
+            Layer::Heads if chart => {
+                if layers.head_size > 0.0 {
+                    for (i, generation) in generations.iter().enumerate() {
+                        draw_heads(model, generation, opacity(i, generation), shown, &draw);
+                    }
+                }
+            }
+            Layer::Baked => (model.baked).draw(config.opacity, model.masks.get(layer), &draw),
+            Layer::Constellation => {
+                let particles = seen_particles(model, top);
+                model.constellation.draw(&particles, config, &draw);
+            }
+            Layer::Tessellation => {
+                let particles = seen_particles(model, top);
+                model.tessellation.draw(&particles, config, &draw);
+            }
+            Layer::Hulls => {
+                let particles = seen_particles(model, top);
+                model.hulls.draw(&particles, config, &draw);
+            }
+            Layer::Trails => {
+                for (i, generation) in generations.iter().enumerate() {
+                    draw_trails(model, generation, opacity(i, generation), gpu, shown, &draw);
+                }
+            }
+            Layer::Heads => {
+                let mut bodies = model.bodies();
+                if let Some(view) = view {
+                    view.flatten_bodies(&mut bodies);
+                }
+                let mask = model.masks.get(layer);
+                render::draw_bodies(&bodies, top.body_color, config, mask, &draw);
+                if layers.head_size > 0.0 {
+                    for (i, generation) in generations.iter().enumerate() {
+                        draw_heads(model, generation, opacity(i, generation), shown, &draw);
+                    }
+                }
+            }
+            Layer::Background | Layer::Overlays => {}
+        }
+    }
+}
+
+/// Draw a dot at the head of each of the trails of a generation at `opacity`, in world
+/// coordinates, as `shown` has them.
+fn draw_heads(model: &Model, generation: &Generation, opacity: f32, shown: View, draw: &Draw) {
+    let look = look(model, generation, 1.0);
+    let opacity = opacity * model.layers.heads.opacity;
+    let circular = circular_speed(model);
+    for particle in 0..generation.particles.len() {
+        let trail = trails::trail(generation, particle, opacity, look.extrapolate, look.length);
+        let Some(&(pos, color)) = trail.first() else {
+            continue;
+        };
+        let mut heads = vec![match shown {
+            View::Orbits | View::PhaseSpace => look.place(generation, particle, pos),
+            View::RadiusChart => chart::plot(0, pos),
+        }];
+        if shown == View::PhaseSpace {
+            // the record the trail's drawn from the newest of, as there is one
+            let newest = &generation.history[particle][trails::echo_delay(particle)];
+            heads.push(chart::phase(newest, circular));
+        }
+        for pos in heads {
+            let mut color = color;
+            color.alpha *= (model.masks.get(Layer::Heads)).map_or(1.0, |mask| mask.coverage(pos));
+            draw.ellipse()
+                .xy(pos)
+                .radius(model.layers.head_size / 2.0)
+                .color(color);
+        }
+    }
+}
+
+/// Where each of the particles of `generation` is seen, in world coordinates, with the color of
+/// the newest record of its trail.
+fn seen_particles(model: &Model, generation: &Generation) -> Vec<(Point2, Hsla)> {
+    let look = look(model, generation, 1.0);
+    (generation.particles.iter().enumerate())
+        .filter_map(|(i, particle)| {
+            let newest = generation.history[i].front()?;
+            Some((look.place(generation, i, particle.pos), newest.color))
+        })
+        .collect()
+}
+
+/// The speed of a circular orbit at the average orbit's radius around the masses as they are,
+/// which phase space is scaled to.
+fn circular_speed(model: &Model) -> f32 {
+    let gm = model.bodies().iter().map(|body| body.gm).sum();
+    physics::circular_speed(orbital_radius(), gm)
+}
+
+/// Draw the trails and ghosts of a generation at `opacity`, in world coordinates, the current
+/// generation's ribbons as last built on the GPU if `gpu` and they were built just so, with its
+/// lines in phase space beside them if that's `shown`, or its lines of the radius chart alone if
+/// that is.
+fn draw_trails(
+    model: &Model,
+    generation: &Generation,
+    opacity: f32,
+    gpu: bool,
+    shown: View,
+    draw: &Draw,
+) {
+    let layer = &model.layers.trails;
+    let Some(draw) = layer.draw(draw) else {
+        return;
+    };
+    let draw = &draw;
+    let look = look(model, generation, opacity);
+    let mask = model.masks.get(Layer::Trails);
+    let charted = match shown {
+        View::Orbits => None,
+        View::RadiusChart => Some(chart::build(
+            &model.render,
+            generation,
+            look,
+            |back, record| chart::plot(back, record.pos),
+        )),
+        View::PhaseSpace => {
+            let circular = circular_speed(model);
+            Some(chart::build(
+                &model.render,
+                generation,
+                look,
+                |_, record| chart::phase(record, circular),
+            ))
+        }
+    };
+    if let Some(mut mesh) = charted {
+        if let Some(mask) = mask {
+            mask.apply(&mut mesh);
+        }
+        count_vertices(model, mesh.vertices.len());
+        draw.mesh().indexed_colored(mesh.vertices, mesh.indices);
+        // phase space is beside the orbits, the chart in place of them
+        if shown == View::RadiusChart {
+            return;
+        }
+    }
+    if model.trail_style != TrailStyle::Ribbons {
+        let mut mesh = render::build(model.trail_style, &model.render, generation, look);
+        if let Some(mask) = mask {
+            mask.apply(&mut mesh);
+        }
+        count_vertices(model, mesh.vertices.len());
+        draw.mesh().indexed_colored(mesh.vertices, mesh.indices);
+        draw_highlights(generation, look, mask, draw);
+        draw_ghosts(generation, look.view, mask, draw);
+        return;
+    }
+    draw_archived(model, generation, look, mask, draw);
+    // the GPU's ribbons are built for the window as it is, not masked, drifting, paired,
+    // feathered or in 3D, so those that are are built here
+    let built = gpu
+        && mask.is_none()
+        && !layer.drifts()
+        && !settings::get().paired_ribbons()
+        && model.render.feather == 0.0
+        && look.view.is_none()
+        && std::ptr::eq(generation, model.current())
+        && (model.compute.as_ref()).is_some_and(|compute| {
+            compute.draw_trails(model.seeded, look.opacity, layer.blend, draw)
+        });
+    if built {
+        // a vertex for each point of each trail, as they would be built here
+        count_vertices(model, generation.history.iter().map(VecDeque::len).sum());
+        draw_highlights(generation, look, None, draw);
+        draw_ghosts(generation, None, None, draw);
+        return;
+    }
+    // as dense as the window shows them, however they're drawn
+    let (width, height) = model.window_size;
+    let pixels_per_unit = viewport(model, Rect::from_w_h(width, height)).scale;
+    // a generation fading out is only drawn for a moment, so its ribbons are built afresh
+    let mut fading = Ribbons::default();
+    let mut current = model.ribbons.borrow_mut();
+    let ribbons = if std::ptr::eq(generation, model.current()) {
+        &mut *current
+    } else {
+        &mut fading
+    };
+    draw_history(
+        generation,
+        look,
+        ribbons,
+        |ribbons| {
+            model.thinning.thin(ribbons, pixels_per_unit);
+            if model.render.feather > 0.0 {
+                trails::feather(ribbons, model.render.feather / pixels_per_unit);
+            }
+            count_vertices(model, ribbons.vertices.len());
+            if let Some(occupancy) = &mut *model.occupancy.borrow_mut() {
+                occupancy.add(ribbons);
+            }
+            model.layering.layer(ribbons);
+        },
+        mask,
+        draw,
+    );
+    draw_ghosts(generation, look.view, mask, draw);
+}
+
+/// Count `vertices` more built for the trails shown.
+fn count_vertices(model: &Model, vertices: usize) {
+    let counted = &model.quality.vertices;
+    counted.set(counted.get() + vertices);
+}
+
+/// How the trails of `generation` are drawn at `opacity`.
+fn look(model: &Model, generation: &Generation, opacity: f32) -> Look {
+    Look {
+        opacity: opacity * model.params.get(Param::TrailOpacity) * model.layers.trails.opacity,
+        highlight: model.params.get(Param::Highlight),
+        extrapolate: model.extrapolate,
+        build_in: model.transition.build_in(generation.age),
+        length: model.params.get(Param::TrailLength) * model.quality.length(),
+        view: model.perspective(),
+    }
+}
+
+/// Build the current generation's trails on the GPU as the window is about to show them, for
+/// `draw_trails` to draw in place of those the CPU would.
+fn render_gpu_trails(app: &App, model: &mut Model) {
+    // only the flat ribbons, sharing their trails with hard edges, are built there
+    let settings = settings::get();
+    if model.trail_style != TrailStyle::Ribbons
+        || model.view == View::RadiusChart
+        || settings.three_d
+        || settings.paired_ribbons()
+        || model.render.feather > 0.0
+    {
+        return;
+    }
+    let generation = model.current();
+    // the long exposure takes in the generation as it fades, while the scene shows it whole
+    let opacity = if model.exposure.is_some() {
+        generation.opacity
+    } else {
+        1.0
+    };
+    let look = look(model, generation, opacity);
+    let win = main_window(app, model).rect();
+    let view = viewport(model, win).shown(win);
+    let (width, height) = main_window(app, model).inner_size_pixels();
+    let Some(compute) = &mut model.compute else {
+        return;
+    };
+    let generation = model.generations.last().unwrap();
+    compute.render_trails(generation, model.seeded, look, view, [width, height]);
+}
+
+/// Draw the whole system into a corner of the window, with the region `shown` in the window
+/// outlined.
+fn draw_minimap(model: &Model, generation: &Generation, shown: Rect, win: Rect, screen: &Draw) {
+    let inset = Rect::from_w_h(MINIMAP_SIZE, MINIMAP_SIZE).bottom_right_of(win.pad(20.0));
+    let clipped = screen.scissor(inset);
+    clipped
+        .rect()
+        .xy(inset.xy())
+        .wh(inset.wh())
+        .color(model.layers.overlays.fade(generation.background));
+
+    let map = Viewport::whole_system(Rect::from_wh(inset.wh()));
+    let draw = map.project(&clipped.translate(inset.xy().extend(0.0)));
+    draw_scene(
+        model,
+        slice::from_ref(generation),
+        false,
+        View::Orbits,
+        &draw,
+    );
+    draw.rect()
+        .xy(shown.xy())
+        .wh(shown.wh())
+        .no_fill()
+        .stroke(model.layers.overlays.fade(WHITE))
+        .stroke_weight(map.to_units(1.5));
+
+    screen
+        .rect()
+        .xy(inset.xy())
+        .wh(inset.wh())
+        .no_fill()
+        .stroke(model.layers.overlays.fade(rgba(1.0, 1.0, 1.0, 0.5)))
+        .stroke_weight(1.0);
+}
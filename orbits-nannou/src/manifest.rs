@@ -0,0 +1,190 @@
+//! Manifests written alongside every export, recording how it was made so it can be made again
+//! and checked against what it claims to be: the version and git commit of the build, the command
+//! line, the seeds and where the first was drawn from, the whole of each config rendered under, the changes made along the way,
+//! the automation and cues moving it, and the size and BLAKE3 checksum of every file written. A
+//! still's manifest goes beside it as `<name>.manifest.json`, and a directory's, of a sequence or
+//! a batch, in it as `manifest.json`. Each is written to a file of its own first and renamed into
+//! place, so none is ever seen half written.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use orbits_core::automation::Automation;
+use orbits_core::generation::Generation;
+use orbits_core::session::{Change, Entry};
+use serde::Serialize;
+use serde_json::Value;
+
+/// What's recorded of how an export was made.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    /// The version of the build it was made with.
+    version: &'static str,
+    /// The git commit the build was made from, `-dirty` if the tree had changes, if it was built
+    /// from a checkout.
+    commit: Option<&'static str>,
+    /// The command line the run was started with.
+    command: Vec<String>,
+    /// The config files rendered under, each as it was written, the rest of it being the
+    /// defaults of the version: the one for most exports, one for each side of a comparison or
+    /// edition of a series.
+    configs: Vec<String>,
+    /// The token every choice made at random was made from, if there was one.
+    token: Option<String>,
+    /// Where the first seed was drawn from, like the text or the weather reading it was hashed
+    /// from, if it wasn't given as a number.
+    seed_source: Option<String>,
+    /// Every seed a generation was seeded with, in order.
+    seeds: Vec<u64>,
+    /// Simulated seconds into the run it was taken at, or those it ran for, for a sequence.
+    time: Option<f32>,
+    /// Every change made to the run until then, at the simulated time it was made.
+    timeline: Vec<Entry>,
+    /// The simulated times the run went on from a saved state at, what went on before each being
+    /// in the state rather than the timeline.
+    resumed: Vec<f32>,
+    /// The automation curves and the camera's path, if there were any.
+    automation: Option<Automation>,
+    /// The cue list stepped through, as its file wrote it, if there was one.
+    cues: Option<Value>,
+    /// The files written, relative to the manifest.
+    outputs: Vec<Output>,
+}
+
+/// A file an export wrote.
+#[derive(Debug, Clone, Serialize)]
+struct Output {
+    path: String,
+    bytes: u64,
+    blake3: String,
+}
+
+impl Manifest {
+    /// What's recorded of a run under `configs`, as this build was started.
+    pub fn new(configs: Vec<String>) -> Self {
+        Manifest {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: option_env!("TRYNANNOU_COMMIT"),
+            command: std::env::args().collect(),
+            configs,
+            token: None,
+            seed_source: None,
+            seeds: Vec::new(),
+            time: None,
+            timeline: Vec::new(),
+            resumed: Vec::new(),
+            automation: None,
+            cues: None,
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn token(mut self, hex: String) -> Self {
+        self.token = Some(hex);
+        self
+    }
+
+    pub fn seed_source(mut self, source: String) -> Self {
+        self.seed_source = Some(source);
+        self
+    }
+
+    pub fn seeds(mut self, seeds: Vec<u64>) -> Self {
+        self.seeds = seeds;
+        self
+    }
+
+    pub fn time(mut self, seconds: f32) -> Self {
+        self.time = Some(seconds);
+        self
+    }
+
+    /// Record the cue list at `path`, as it's written there.
+    pub fn cues(mut self, path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let cues =
+            serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display()))?;
+        self.cues = Some(cues);
+        Ok(self)
+    }
+
+    /// Note `change`, made at simulated time `time`, in the timeline, and the seed it seeds a
+    /// generation with, if it does.
+    pub fn note(&mut self, time: f32, change: &Change) {
+        if let Change::Start { seed } | Change::Reseed { seed } = *change {
+            self.seeds.push(seed);
+        }
+        self.timeline.push(Entry {
+            time,
+            change: change.clone(),
+        });
+    }
+
+    /// Note the run going on from a saved state, at simulated time `time`, with `generations`
+    /// seeded before it was saved.
+    pub fn resume(&mut self, time: f32, generations: &[Generation]) {
+        self.seeds
+            .extend(generations.iter().map(|generation| generation.seed));
+        self.resumed.push(time);
+    }
+
+    /// The manifest as of simulated time `time`, with the automation curves as they are then, if
+    /// there are any.
+    pub fn at(&self, time: f32, automation: &Automation) -> Self {
+        let curves = !automation.lanes.is_empty() || !automation.camera.is_empty();
+        Manifest {
+            time: Some(time),
+            automation: curves.then(|| automation.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Write the manifest of the still at `path` beside it.
+    pub fn write_beside(&self, path: &Path) -> Result<(), String> {
+        self.write(&beside(path), &[path.to_path_buf()])
+    }
+
+    /// Write the manifest of `outputs`, each hashed as it is now, to `path`, through a file of
+    /// its own renamed into place. Any that weren't written, as a frame that failed to be, are
+    /// left out.
+    pub fn write(&self, path: &Path, outputs: &[PathBuf]) -> Result<(), String> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let outputs = (outputs.iter())
+            .filter_map(|output| {
+                // streamed through the hasher, so a long video isn't read into memory whole
+                let mut hasher = blake3::Hasher::new();
+                fs::File::open(output)
+                    .and_then(|file| hasher.update_reader(file).map(|_| ()))
+                    .map_err(|err| eprintln!("{}: {err}", output.display()))
+                    .ok()?;
+                let relative = output.strip_prefix(dir).unwrap_or(output);
+                Some(Output {
+                    path: relative.display().to_string(),
+                    bytes: hasher.count(),
+                    blake3: hasher.finalize().to_hex().to_string(),
+                })
+            })
+            .collect();
+        let manifest = Manifest {
+            outputs,
+            ..self.clone()
+        };
+        let text = serde_json::to_string_pretty(&manifest).map_err(|err| err.to_string())?;
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let err = |err: std::io::Error| format!("{}: {err}", path.display());
+        fs::write(&partial, text).map_err(err)?;
+        fs::rename(&partial, path).map_err(err)
+    }
+}
+
+/// Where the manifest of the still at `path` goes.
+pub fn beside(path: &Path) -> PathBuf {
+    path.with_extension("manifest.json")
+}
+
+/// Where the manifest of the files in `dir` goes.
+pub fn within(dir: &Path) -> PathBuf {
+    dir.join("manifest.json")
+}
@@ -0,0 +1,495 @@
+//! Masks confining a layer of the scene to within a shape, or to outside it: a circle, the filled
+//! shapes of an SVG, or where an image is opaque, like a logo whose silhouette the orbits are
+//! drawn within. A mask fades each point of the layer by how far it's covered, so the ribbons' edge
+//! along one is as fine as their points are close.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use nannou::image::{self, GrayImage};
+use nannou::prelude::*;
+use orbits_core::geometry::Path;
+use orbits_core::orbital_radius;
+use orbits_core::trails::Mesh;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::assets;
+use crate::layers::{Layer, LayersConfig};
+
+/// Straight pieces each curve of an SVG is flattened into.
+const CURVE_PIECES: usize = 16;
+
+/// What a mask is the shape of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskShape {
+    #[default]
+    Circle,
+    /// The filled shapes of an SVG: its paths, polygons, rectangles and circles, with any
+    /// transforms left out.
+    Svg,
+    /// Where an image is opaque, or bright if it's opaque all over.
+    Image,
+}
+
+/// The mask of a layer, in its section of `[layers]`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaskConfig {
+    pub shape: MaskShape,
+    /// The SVG or image, for those shapes.
+    pub path: Option<PathBuf>,
+    /// The middle of the mask, in world units.
+    pub center: [f32; 2],
+    /// Radius of the circle, or half the longer side of the SVG or image, in world units.
+    pub radius: f32,
+    /// Keep the layer outside the shape rather than within it.
+    pub outside: bool,
+}
+
+impl Default for MaskConfig {
+    fn default() -> Self {
+        MaskConfig {
+            shape: MaskShape::Circle,
+            path: None,
+            center: [0.0, 0.0],
+            radius: orbital_radius(),
+            outside: false,
+        }
+    }
+}
+
+/// What a loaded mask covers.
+#[derive(Debug)]
+enum Cover {
+    Circle,
+    /// Outlines in the world, filled by the even-odd rule so holes stay holes.
+    Outlines {
+        outlines: Vec<Vec<Point2>>,
+        bounds: Rect,
+    },
+    /// How far each pixel is covered, laid over the square the mask spans.
+    Image(GrayImage),
+}
+
+/// A mask, loaded.
+#[derive(Debug)]
+pub struct Mask {
+    center: Point2,
+    radius: f32,
+    outside: bool,
+    cover: Cover,
+}
+
+impl Mask {
+    pub fn load(config: &MaskConfig) -> Result<Self, String> {
+        if config.radius <= 0.0 {
+            return Err(format!(
+                "a mask's radius has to be positive, not {}",
+                config.radius
+            ));
+        }
+        let center = Point2::from(config.center);
+        let path = || {
+            let path = config
+                .path
+                .as_ref()
+                .ok_or("an SVG or image mask needs a path")?;
+            Ok::<_, String>((assets::resolve(path), path.display().to_string()))
+        };
+        let cover = match config.shape {
+            MaskShape::Circle => Cover::Circle,
+            MaskShape::Svg => {
+                let (path, name) = path()?;
+                let svg = fs::read_to_string(path).map_err(|err| format!("{name}: {err}"))?;
+                let outlines = outlines(&svg, center, config.radius);
+                if outlines.is_empty() {
+                    return Err(format!("{name}: there are no shapes to mask with"));
+                }
+                let bounds = bounds(outlines.iter().flatten().copied()).unwrap();
+                Cover::Outlines { outlines, bounds }
+            }
+            MaskShape::Image => {
+                let (path, name) = path()?;
+                let image = image::open(path).map_err(|err| format!("{name}: {err}"))?;
+                let rgba = image.to_rgba8();
+                let opaque = rgba.pixels().all(|pixel| pixel.0[3] == u8::MAX);
+                let coverage = if opaque {
+                    image.to_luma8()
+                } else {
+                    GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+                        image::Luma([rgba.get_pixel(x, y).0[3]])
+                    })
+                };
+                Cover::Image(coverage)
+            }
+        };
+        Ok(Mask {
+            center,
+            radius: config.radius,
+            outside: config.outside,
+            cover,
+        })
+    }
+
+    /// How far the layer shows at `pos`, a point of the world, from 0 to 1.
+    pub fn coverage(&self, pos: Point2) -> f32 {
+        let within = match &self.cover {
+            Cover::Circle => (pos.distance(self.center) <= self.radius) as u8 as f32,
+            Cover::Outlines { outlines, bounds } => {
+                let inside = bounds.contains(pos)
+                    && outlines.iter().filter(|o| crosses(o, pos)).count() % 2 == 1;
+                inside as u8 as f32
+            }
+            Cover::Image(image) => sample(image, (pos - self.center) / self.radius),
+        };
+        if self.outside {
+            1.0 - within
+        } else {
+            within
+        }
+    }
+
+    /// Fade each vertex of `mesh` by how far it's covered.
+    pub fn apply(&self, mesh: &mut Mesh) {
+        for (pos, color) in &mut mesh.vertices {
+            color.alpha *= self.coverage(pos.truncate());
+        }
+    }
+}
+
+/// The masks of the layers that have one.
+#[derive(Debug, Default)]
+pub struct Masks(BTreeMap<Layer, Mask>);
+
+impl Masks {
+    /// Load the masks `layers` asks for.
+    pub fn load(layers: &LayersConfig) -> Result<Self, String> {
+        let mut masks = BTreeMap::new();
+        for layer in Layer::ALL {
+            if let Some(config) = &layers.get(layer).mask {
+                let mask = Mask::load(config).map_err(|err| format!("{}: {err}", layer.label()))?;
+                masks.insert(layer, mask);
+            }
+        }
+        Ok(Masks(masks))
+    }
+
+    pub fn get(&self, layer: Layer) -> Option<&Mask> {
+        self.0.get(&layer)
+    }
+}
+
+/// Whether a ray from `pos` to the right crosses `outline` an odd number of times.
+fn crosses(outline: &[Point2], pos: Point2) -> bool {
+    let mut inside = false;
+    for (i, &a) in outline.iter().enumerate() {
+        let b = outline[(i + 1) % outline.len()];
+        if (a.y > pos.y) != (b.y > pos.y) {
+            let x = a.x + (pos.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if pos.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// How far `image` covers `local`, a point of the square from -1 to 1 its longer side spans,
+/// blending the nearest pixels.
+fn sample(image: &GrayImage, local: Vec2) -> f32 {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let half = width.max(height) / 2.0;
+    // in pixels from the top left, the middle of the first pixel at 0
+    let x = width / 2.0 + local.x * half - 0.5;
+    let y = height / 2.0 - local.y * half - 0.5;
+    if x < -0.5 || y < -0.5 || x > width - 0.5 || y > height - 0.5 {
+        return 0.0;
+    }
+    let pixel = |x: f32, y: f32| {
+        let x = x.clamp(0.0, width - 1.0) as u32;
+        let y = y.clamp(0.0, height - 1.0) as u32;
+        image.get_pixel(x, y).0[0] as f32 / u8::MAX as f32
+    };
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let top = pixel(x0, y0) * (1.0 - fx) + pixel(x0 + 1.0, y0) * fx;
+    let bottom = pixel(x0, y0 + 1.0) * (1.0 - fx) + pixel(x0 + 1.0, y0 + 1.0) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Trace the points of `path`, if it's a spline with an `svg`, from the longest outline of the
+/// filled shapes in that file, its longer side `radius` either side of the middle.
+pub fn trace(path: &mut Path) -> Result<(), String> {
+    let Path::Spline {
+        points,
+        svg: Some(svg),
+        radius,
+    } = path
+    else {
+        return Ok(());
+    };
+    let name = svg.display().to_string();
+    let text = fs::read_to_string(assets::resolve(svg)).map_err(|err| format!("{name}: {err}"))?;
+    let perimeter = |outline: &Vec<Point2>| -> f32 {
+        let sides = outline.iter().zip(outline.iter().cycle().skip(1));
+        sides.map(|(a, b)| a.distance(*b)).sum()
+    };
+    let longest = outlines(&text, Point2::ZERO, *radius)
+        .into_iter()
+        .filter(|outline| outline.len() >= 3)
+        .max_by(|a, b| perimeter(a).total_cmp(&perimeter(b)))
+        .ok_or_else(|| format!("{name}: there are no shapes to trace a path from"))?;
+    *points = longest.into_iter().map(|pos| pos.to_array()).collect();
+    Ok(())
+}
+
+/// The outlines of the filled shapes of `svg`, laid out in the world with its view centered on
+/// `center` and its longer side `2 * radius` long.
+fn outlines(svg: &str, center: Point2, radius: f32) -> Vec<Vec<Point2>> {
+    let mut outlines = Vec::new();
+    let mut view = None;
+    for (tag, attributes) in elements(svg) {
+        let number = |name: &str| attribute(attributes, name).and_then(|v| v.parse::<f32>().ok());
+        match tag {
+            "svg" => {
+                let numbers = attribute(attributes, "viewBox").map(numbers);
+                if let Some(&[x, y, w, h]) = numbers.as_deref() {
+                    view = Some(Rect::from_corners(pt2(x, y), pt2(x + w, y + h)));
+                }
+            }
+            "path" => {
+                if let Some(data) = attribute(attributes, "d") {
+                    outlines.extend(path_outlines(data));
+                }
+            }
+            "polygon" | "polyline" => {
+                let points = attribute(attributes, "points")
+                    .map(numbers)
+                    .unwrap_or_default();
+                let outline: Vec<_> = points.chunks_exact(2).map(|p| pt2(p[0], p[1])).collect();
+                outlines.push(outline);
+            }
+            "rect" => {
+                let (x, y) = (number("x").unwrap_or(0.0), number("y").unwrap_or(0.0));
+                if let (Some(w), Some(h)) = (number("width"), number("height")) {
+                    outlines.push(vec![
+                        pt2(x, y),
+                        pt2(x + w, y),
+                        pt2(x + w, y + h),
+                        pt2(x, y + h),
+                    ]);
+                }
+            }
+            "circle" | "ellipse" => {
+                let (cx, cy) = (number("cx").unwrap_or(0.0), number("cy").unwrap_or(0.0));
+                let (rx, ry) = match number("r") {
+                    Some(r) => (r, r),
+                    None => (number("rx").unwrap_or(0.0), number("ry").unwrap_or(0.0)),
+                };
+                let pieces = 4 * CURVE_PIECES;
+                outlines.push(
+                    (0..pieces)
+                        .map(|i| {
+                            let angle = i as f32 / pieces as f32 * TAU;
+                            pt2(cx + rx * angle.cos(), cy + ry * angle.sin())
+                        })
+                        .collect(),
+                );
+            }
+            _ => {}
+        }
+    }
+    outlines.retain(|outline| outline.len() >= 3);
+    let points = outlines.iter().flatten().copied();
+    let Some(view) = view.or_else(|| bounds(points)) else {
+        return Vec::new();
+    };
+    let scale = radius / (view.w().max(view.h()) / 2.0);
+    // an SVG's y runs down the page
+    let place = |p: Point2| center + vec2(p.x - view.x(), view.y() - p.y) * scale;
+    (outlines.into_iter())
+        .map(|outline| outline.into_iter().map(place).collect())
+        .collect()
+}
+
+/// The smallest rectangle around `points`, if there are any.
+fn bounds(points: impl Iterator<Item = Point2>) -> Option<Rect> {
+    points.fold(None, |bounds: Option<Rect>, p| {
+        Some(bounds.map_or(Rect::from_xy_wh(p, Vec2::ZERO), |b| b.stretch_to(p)))
+    })
+}
+
+/// The name and the text of the attributes of each element of `svg`.
+fn elements(svg: &str) -> impl Iterator<Item = (&str, &str)> {
+    svg.split('<').skip(1).filter_map(|element| {
+        let element = &element[..element.find('>')?];
+        let element = element.trim_end_matches('/');
+        let (tag, attributes) = element
+            .split_once(char::is_whitespace)
+            .unwrap_or((element, ""));
+        Some((tag, attributes))
+    })
+}
+
+/// The value of the attribute `name` among `attributes`.
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attributes;
+    while let Some(at) = rest.find(name) {
+        let before = rest[..at].chars().next_back();
+        let after = rest[at + name.len()..].trim_start();
+        rest = &rest[at + name.len()..];
+        if !before.is_none_or(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        let value = &value[quote.len_utf8()..];
+        return Some(&value[..value.find(quote)?]);
+    }
+    None
+}
+
+/// The numbers in `text`, however they're separated, even by nothing but the sign or point
+/// starting the next one.
+fn numbers(text: &str) -> Vec<f32> {
+    let mut numbers = Vec::new();
+    let mut number = String::new();
+    let mut flush = |number: &mut String| {
+        if let Ok(value) = number.parse() {
+            numbers.push(value);
+        }
+        number.clear();
+    };
+    for c in text.chars() {
+        let starts = match c {
+            '-' | '+' => !number.ends_with(['e', 'E']),
+            '.' => number.contains('.') && !number.contains(['e', 'E']),
+            _ => false,
+        };
+        if starts {
+            flush(&mut number);
+        }
+        if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+            number.push(c);
+        } else {
+            flush(&mut number);
+        }
+    }
+    flush(&mut number);
+    numbers
+}
+
+/// The outlines the path data `data` traces, its curves flattened and each subpath closed.
+fn path_outlines(data: &str) -> Vec<Vec<Point2>> {
+    // each command with the numbers after it
+    let mut commands = Vec::new();
+    let mut start = None;
+    for (i, c) in data.char_indices() {
+        if c.is_ascii_alphabetic() && !matches!(c, 'e' | 'E') {
+            if let Some((command, from)) = start {
+                commands.push((command, numbers(&data[from..i])));
+            }
+            start = Some((c, i + 1));
+        }
+    }
+    if let Some((command, from)) = start {
+        commands.push((command, numbers(&data[from..])));
+    }
+
+    let mut outlines = Vec::new();
+    let mut outline: Vec<Point2> = Vec::new();
+    let mut at = Point2::ZERO;
+    let mut first = Point2::ZERO;
+    for (command, args) in commands {
+        let relative = command.is_ascii_lowercase();
+        let origin = |at: Point2| if relative { at } else { Point2::ZERO };
+        match command.to_ascii_uppercase() {
+            'M' => {
+                if outline.len() >= 3 {
+                    outlines.push(std::mem::take(&mut outline));
+                }
+                outline.clear();
+                for (i, p) in args.chunks_exact(2).enumerate() {
+                    at = origin(at) + vec2(p[0], p[1]);
+                    if i == 0 {
+                        first = at;
+                    }
+                    outline.push(at);
+                }
+            }
+            'L' | 'T' => {
+                for p in args.chunks_exact(2) {
+                    at = origin(at) + vec2(p[0], p[1]);
+                    outline.push(at);
+                }
+            }
+            'H' => {
+                for &x in &args {
+                    at.x = if relative { at.x + x } else { x };
+                    outline.push(at);
+                }
+            }
+            'V' => {
+                for &y in &args {
+                    at.y = if relative { at.y + y } else { y };
+                    outline.push(at);
+                }
+            }
+            'C' | 'S' | 'Q' => {
+                let step = match command.to_ascii_uppercase() {
+                    'C' => 6,
+                    _ => 4,
+                };
+                for args in args.chunks_exact(step) {
+                    let base = origin(at);
+                    let points: Vec<Point2> = (args.chunks_exact(2))
+                        .map(|p| base + vec2(p[0], p[1]))
+                        .collect();
+                    let mut controls = vec![at];
+                    controls.extend(&points);
+                    for piece in 1..=CURVE_PIECES {
+                        outline.push(bezier(&controls, piece as f32 / CURVE_PIECES as f32));
+                    }
+                    at = *points.last().unwrap();
+                }
+            }
+            'A' => {
+                // arcs are taken as straight to where they end
+                for args in args.chunks_exact(7) {
+                    at = origin(at) + vec2(args[5], args[6]);
+                    outline.push(at);
+                }
+            }
+            'Z' => {
+                if outline.len() >= 3 {
+                    outlines.push(std::mem::take(&mut outline));
+                }
+                outline.clear();
+                at = first;
+            }
+            _ => {}
+        }
+    }
+    if outline.len() >= 3 {
+        outlines.push(outline);
+    }
+    outlines
+}
+
+/// The point `t` of the way along the Bézier curve with `controls`.
+fn bezier(controls: &[Point2], t: f32) -> Point2 {
+    let mut points = controls.to_vec();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| pair[0].lerp(pair[1], t))
+            .collect();
+    }
+    points[0]
+}
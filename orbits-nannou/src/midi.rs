@@ -0,0 +1,383 @@
+//! Playing the piece from a MIDI controller, with `--midi`: knobs and faders bound by the config to
+//! parameters or to the masses of attractors, and pads or keys bound to bursts of particles, reseeds
+//! for a new palette, rolls of the dice and the next cue. Built with the `midir` feature, the
+//! controller is read from a port of the platform's own MIDI API, through midir. Without it the
+//! controller is read as raw MIDI, from its device file on Linux or from the output of whatever
+//! command the config names, and on macOS and Windows there's nothing to read without a command.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use orbits_core::params::Param;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Where Linux keeps the raw MIDI devices.
+const DEVICES_DIR: &str = "/dev/snd";
+
+/// The `[midi]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiConfig {
+    /// The raw MIDI device read, like `/dev/snd/midiC1D0`, on Linux. If neither this nor
+    /// `command` is set, the first port there is, or the first device without midir, is read.
+    pub device: Option<PathBuf>,
+    /// Built with midir, the port read in place of the first, by its name or any part of it.
+    pub port: Option<String>,
+    /// A command writing the controller's raw MIDI to its output, read in place of a device, like
+    /// `["amidi", "-p", "hw:1", "-r", "/dev/stdout"]`.
+    pub command: Option<Vec<String>>,
+    /// The channel listened to, from 1 to 16, or every channel if left out.
+    pub channel: Option<u8>,
+    /// Which controls move what.
+    pub knobs: Vec<Knob>,
+    /// Which notes do what.
+    pub notes: Vec<Note>,
+    /// Particles a burst spawns.
+    pub burst: usize,
+}
+
+impl Default for MidiConfig {
+    fn default() -> Self {
+        MidiConfig {
+            device: None,
+            port: None,
+            command: None,
+            channel: None,
+            knobs: Vec::new(),
+            notes: Vec::new(),
+            burst: 20,
+        }
+    }
+}
+
+impl MidiConfig {
+    /// Whether every knob is bound to something to move, and the channel is one there is.
+    pub fn check(&self) -> Result<(), String> {
+        for knob in &self.knobs {
+            knob.target()?;
+        }
+        match self.channel.filter(|channel| !(1..=16).contains(channel)) {
+            Some(channel) => Err(format!("MIDI channel {channel} isn't from 1 to 16")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A knob or fader moving a parameter, or the mass of an attractor, across a range.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Knob {
+    /// The number of the control it sends changes of.
+    pub control: u8,
+    /// The parameter it moves.
+    #[schemars(with = "Option<String>")]
+    pub param: Option<Param>,
+    /// The attractor whose mass it moves, by its place among those in the config, if it moves
+    /// one instead of a parameter.
+    pub attractor: Option<usize>,
+    /// What the knob turned all the way down and all the way up stand for, the parameter's whole
+    /// range, or a mass from none to as much as the central mass's, if left out.
+    pub range: Option<[f32; 2]>,
+}
+
+/// What a knob moves.
+#[derive(Debug, Clone, Copy)]
+pub enum Target {
+    Param(Param),
+    Attractor(usize),
+}
+
+impl Knob {
+    fn target(&self) -> Result<Target, String> {
+        match (self.param, self.attractor) {
+            (Some(param), None) => Ok(Target::Param(param)),
+            (None, Some(attractor)) => Ok(Target::Attractor(attractor)),
+            _ => Err(format!(
+                "knob {} needs either a param or an attractor to move",
+                self.control
+            )),
+        }
+    }
+
+    /// Where the knob puts its target at `value`, from 0 to 127.
+    fn position(&self, target: Target, value: u8) -> f32 {
+        let [least, most] = self.range.unwrap_or(match target {
+            Target::Param(param) => [*param.range().start(), *param.range().end()],
+            Target::Attractor(_) => [0.0, 1.0],
+        });
+        least + (most - least) * value as f32 / 127.0
+    }
+}
+
+/// A note, as a pad or key sends, doing something on being struck.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Note {
+    /// The number of the note.
+    pub note: u8,
+    pub action: NoteAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteAction {
+    /// Spawn `burst` particles at once.
+    Burst,
+    /// Reseed the swarm, with a new palette.
+    Reseed,
+    /// Roll the dice.
+    Dice,
+    /// Go to the next cue.
+    NextCue,
+}
+
+/// What the controller asks for.
+#[derive(Debug, Clone, Copy)]
+pub enum Request {
+    /// Put `target` at `value`.
+    Set {
+        target: Target,
+        value: f32,
+    },
+    Act(NoteAction),
+}
+
+/// A message read from the controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Message {
+    Control { channel: u8, control: u8, value: u8 },
+    NoteOn { channel: u8, note: u8 },
+}
+
+pub struct Midi {
+    config: MidiConfig,
+    messages: Receiver<Message>,
+    /// The command read from, if it's a command rather than a device.
+    reader: Option<Child>,
+    /// The port read from, for as long as it's held, if it's read through midir.
+    #[cfg(feature = "midir")]
+    #[allow(dead_code)]
+    connection: Option<midir::MidiInputConnection<(Parser, Sender<Message>)>>,
+}
+
+impl Midi {
+    /// Start reading the controller.
+    pub fn new(config: MidiConfig) -> Result<Self, String> {
+        config.check()?;
+        let (sender, messages) = mpsc::channel();
+        #[cfg(feature = "midir")]
+        if config.command.is_none() && config.device.is_none() {
+            let connection = connect(config.port.as_deref(), sender)?;
+            return Ok(Midi {
+                config,
+                messages,
+                reader: None,
+                connection: Some(connection),
+            });
+        }
+        let reader = match (&config.command, &config.device) {
+            (Some(command), _) => {
+                let (program, args) = command.split_first().ok_or("the MIDI command is empty")?;
+                let mut reader = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .map_err(|err| format!("couldn't start the MIDI reader {program:?}: {err}"))?;
+                let stdout = reader.stdout.take().unwrap();
+                listen(stdout, sender);
+                Some(reader)
+            }
+            (None, device) => {
+                let device = match device {
+                    Some(device) => device.clone(),
+                    None => default_device()?,
+                };
+                let file =
+                    File::open(&device).map_err(|err| format!("{}: {err}", device.display()))?;
+                println!("reading MIDI from {}", device.display());
+                listen(file, sender);
+                None
+            }
+        };
+        Ok(Midi {
+            config,
+            messages,
+            reader,
+            #[cfg(feature = "midir")]
+            connection: None,
+        })
+    }
+
+    /// Particles a burst spawns.
+    pub fn burst(&self) -> usize {
+        self.config.burst
+    }
+
+    /// What the controller has asked for since the last call.
+    pub fn requests(&self) -> Vec<Request> {
+        let listened = |channel: u8| self.config.channel.is_none_or(|c| c == channel + 1);
+        let mut requests = Vec::new();
+        for message in self.messages.try_iter() {
+            match message {
+                Message::Control {
+                    channel,
+                    control,
+                    value,
+                } if listened(channel) => {
+                    for knob in self.config.knobs.iter().filter(|k| k.control == control) {
+                        let target = knob.target().unwrap();
+                        let value = knob.position(target, value);
+                        requests.push(Request::Set { target, value });
+                    }
+                }
+                Message::NoteOn { channel, note } if listened(channel) => {
+                    requests.extend(
+                        (self.config.notes.iter())
+                            .filter(|n| n.note == note)
+                            .map(|n| Request::Act(n.action)),
+                    );
+                }
+                _ => {}
+            }
+        }
+        requests
+    }
+}
+
+impl Drop for Midi {
+    fn drop(&mut self) {
+        if let Some(reader) = &mut self.reader {
+            let _ = reader.kill();
+            let _ = reader.wait();
+        }
+    }
+}
+
+/// Read the port named `name`, or the first there is, through midir, sending on its messages.
+#[cfg(feature = "midir")]
+fn connect(
+    name: Option<&str>,
+    sender: Sender<Message>,
+) -> Result<midir::MidiInputConnection<(Parser, Sender<Message>)>, String> {
+    let input = midir::MidiInput::new("trynannou")
+        .map_err(|err| format!("couldn't open MIDI input: {err}"))?;
+    let port = (input.ports().into_iter())
+        .map(|port| (input.port_name(&port).unwrap_or_default(), port))
+        .find(|(port_name, _)| name.is_none_or(|name| port_name.contains(name)));
+    let Some((port_name, port)) = port else {
+        return Err(match name {
+            Some(name) => format!("there's no MIDI port named {name:?}"),
+            None => "there's no MIDI port to read, so set [midi] device or command".to_string(),
+        });
+    };
+    println!("reading MIDI from {port_name}");
+    input
+        .connect(
+            &port,
+            "trynannou",
+            |_, bytes, (parser, sender)| {
+                for message in bytes.iter().filter_map(|&b| parser.push(b)) {
+                    let _ = sender.send(message);
+                }
+            },
+            (Parser::default(), sender),
+        )
+        .map_err(|err| format!("couldn't read MIDI from {port_name}: {err}"))
+}
+
+/// The first raw MIDI device there is, on Linux.
+fn default_device() -> Result<PathBuf, String> {
+    let none = || "there's no MIDI device to read, so set [midi] device or command".to_string();
+    if !cfg!(target_os = "linux") {
+        return Err(none());
+    }
+    let mut devices: Vec<PathBuf> = fs::read_dir(DEVICES_DIR)
+        .map_err(|_| none())?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            (path.file_name().and_then(|name| name.to_str()))
+                .is_some_and(|name| name.starts_with("midi"))
+        })
+        .collect();
+    devices.sort();
+    devices.into_iter().next().ok_or_else(none)
+}
+
+/// Read messages from `input` on a thread of its own until it ends, sending them on.
+fn listen(mut input: impl Read + Send + 'static, sender: Sender<Message>) {
+    thread::spawn(move || {
+        let mut parser = Parser::default();
+        let mut bytes = [0; 256];
+        loop {
+            match input.read(&mut bytes) {
+                Ok(0) => break,
+                Ok(len) => {
+                    for message in bytes[..len].iter().filter_map(|&b| parser.push(b)) {
+                        if sender.send(message).is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => {
+                    eprintln!("the MIDI input stopped: {err}");
+                    return;
+                }
+            }
+        }
+        eprintln!("the MIDI input stopped");
+    });
+}
+
+/// Splits a stream of raw MIDI into messages, keeping to the running status a controller may send
+/// a run of messages under.
+#[derive(Debug, Default)]
+struct Parser {
+    /// The status byte of the message being read, if it's one of those two bytes of data long.
+    status: Option<u8>,
+    /// The first byte of its data, once it's been read.
+    first: Option<u8>,
+}
+
+impl Parser {
+    /// Take in `byte`, returning the message it finishes, if it finishes one that's understood.
+    fn push(&mut self, byte: u8) -> Option<Message> {
+        if byte >= 0xf8 {
+            // real time messages, like the clock, can come between any two bytes
+            return None;
+        }
+        if byte & 0x80 != 0 {
+            // note off, note on, aftertouch, control change and pitch bend take two bytes of
+            // data, and anything else is skipped until the next status byte
+            self.status = matches!(byte & 0xf0, 0x80 | 0x90 | 0xa0 | 0xb0 | 0xe0).then_some(byte);
+            self.first = None;
+            return None;
+        }
+        let status = self.status?;
+        let Some(first) = self.first.take() else {
+            self.first = Some(byte);
+            return None;
+        };
+        let channel = status & 0x0f;
+        match status & 0xf0 {
+            0xb0 => Some(Message::Control {
+                channel,
+                control: first,
+                value: byte,
+            }),
+            // a note on of velocity 0 is how many controllers send a note off
+            0x90 if byte > 0 => Some(Message::NoteOn {
+                channel,
+                note: first,
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! A nebula behind the piece: clouds of domain-warped noise, rendered by a shader into a texture
+//! that the scene draws like any other, tinted from the palette and slowly swirling.
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use nannou::wgpu;
+use orbits_core::generation::Generation;
+use orbits_core::orbital_radius;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::gpu::{self, quad, QUAD_LAYOUT, QUAD_VERTICES};
+
+/// Half the width of the square of world the nebula covers, enough to fill a wide window zoomed
+/// out to the whole system, in orbital radii.
+const EXTENT: f32 = 3.0;
+/// Texels along each side of the nebula's texture; it's soft enough not to need more.
+const TEXTURE_SIZE: u32 = 512;
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// The `[nebula]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct NebulaConfig {
+    /// Opacity of the thickest clouds over the background, from 0, for none, to 1.
+    pub opacity: f32,
+    /// World units across the largest clouds.
+    pub scale: f32,
+    /// How much the clouds swirl into each other, from 0, for plain noise.
+    pub warp: f32,
+    /// How fast the clouds evolve, relative to simulated time.
+    pub speed: f32,
+}
+
+impl Default for NebulaConfig {
+    fn default() -> Self {
+        NebulaConfig {
+            opacity: 0.0,
+            scale: orbital_radius(),
+            warp: 1.5,
+            speed: 0.05,
+        }
+    }
+}
+
+pub struct Nebula {
+    config: NebulaConfig,
+    gpu: Option<Gpu>,
+}
+
+struct Gpu {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniforms: wgpu::Buffer,
+    quad: wgpu::Buffer,
+    epoch: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Uniforms {
+    near: [f32; 4],
+    far: [f32; 4],
+    time: f32,
+    scale: f32,
+    warp: f32,
+    extent: f32,
+    opacity: f32,
+    _padding: [f32; 3],
+}
+
+impl Nebula {
+    pub fn new(config: NebulaConfig) -> Self {
+        Nebula { config, gpu: None }
+    }
+
+    /// Render the nebula as it is at simulated time `t`, tinted from the palettes of
+    /// `generations` as they fade into each other.
+    pub fn render(
+        &mut self,
+        device_queue: &wgpu::DeviceQueuePair,
+        t: f32,
+        generations: &[Generation],
+    ) {
+        if self.config.opacity <= 0.0 {
+            return;
+        }
+        let device = device_queue.device();
+        if self
+            .gpu
+            .as_ref()
+            .is_some_and(|gpu| gpu.epoch != gpu::epoch())
+        {
+            self.gpu = None;
+        }
+        let gpu = self.gpu.get_or_insert_with(|| Gpu::new(device));
+
+        let (mut near, mut far) = ([0.0; 4], [0.0; 4]);
+        for (i, generation) in generations.iter().enumerate() {
+            // each palette fades in over the ones before it, as its generation does
+            let weight = if i == 0 { 1.0 } else { generation.opacity };
+            let ends = [generation.colors.first(), generation.colors.last()];
+            for (tint, color) in [&mut near, &mut far].into_iter().zip(ends) {
+                let Some(color) = color else { continue };
+                let color = color.color.into_lin_srgba();
+                let color = [color.red, color.green, color.blue, 1.0];
+                for (channel, target) in tint.iter_mut().zip(color) {
+                    *channel += (target - *channel) * weight;
+                }
+            }
+        }
+        let uniforms = Uniforms {
+            near,
+            far,
+            time: t * self.config.speed,
+            scale: self.config.scale.max(1.0),
+            warp: self.config.warp,
+            extent: EXTENT * orbital_radius(),
+            opacity: self.config.opacity.min(1.0),
+            _padding: [0.0; 3],
+        };
+        device_queue
+            .queue()
+            .write_buffer(&gpu.uniforms, 0, unsafe { wgpu::bytes::from(&uniforms) });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("nebula"),
+        });
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(&gpu.view, |color| color)
+            .begin(&mut encoder);
+        pass.set_pipeline(&gpu.pipeline);
+        pass.set_vertex_buffer(0, gpu.quad.slice(..));
+        pass.set_bind_group(0, &gpu.bind_group, &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+        drop(pass);
+        device_queue.queue().submit(Some(encoder.finish()));
+    }
+
+    /// Draw the nebula as last rendered, in world coordinates.
+    pub fn draw(&self, draw: &Draw) {
+        if let Some(gpu) = &self.gpu {
+            let side = 2.0 * EXTENT * orbital_radius();
+            draw.texture(&gpu.texture).w_h(side, side);
+        }
+    }
+}
+
+impl Gpu {
+    fn new(device: &wgpu::Device) -> Self {
+        let texture = wgpu::TextureBuilder::new()
+            .size([TEXTURE_SIZE; 2])
+            .format(FORMAT)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .build(device);
+        let view = texture.view().build();
+
+        let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("nebula uniforms"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .buffer::<Uniforms>(&uniforms, 0..1)
+            .build(device, &bind_group_layout);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("nebula"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&wgpu::include_wgsl!("shaders/nebula.wgsl"));
+        let pipeline = wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+            .vertex_entry_point("vs_main")
+            .fragment_shader(&shader)
+            .fragment_entry_point("fs_main")
+            .color_format(FORMAT)
+            .color_blend(wgpu::BlendComponent::REPLACE)
+            .alpha_blend(wgpu::BlendComponent::REPLACE)
+            .add_vertex_buffer_layout(QUAD_LAYOUT)
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleStrip)
+            .build(device);
+
+        Gpu {
+            texture,
+            view,
+            pipeline,
+            bind_group,
+            uniforms,
+            quad: quad(device),
+            epoch: gpu::epoch(),
+        }
+    }
+}
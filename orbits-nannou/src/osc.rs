@@ -0,0 +1,191 @@
+//! Remote control over OSC, with `--osc`, for TouchOSC, a lighting desk or anything else that
+//! speaks it: messages sent to the port set parameters, reseed and step through cues, just as the
+//! web control page does. These are understood:
+//!
+//! - `/particles/count <n>`, `/gravity/gm <x>` and `/palette/shift <x>`, setting the particle
+//!   count, the strength of gravity and how far round the background's hue is turned
+//! - `/param/<name> <x>`, setting any parameter by its name in the config, like `trail_opacity`
+//! - `/reset` and `/cue/next`, reseeding and going to the next cue, unless given a 0, as a button
+//!   sends on being let go
+//!
+//! Packets are decoded by `rosc`, bundles and all. A message's first argument that's a number,
+//! an int, float, long, double or boolean, is its value, whatever comes before it.
+
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use orbits_core::params::Param;
+use rosc::{OscMessage, OscPacket, OscType};
+
+use crate::web::Request;
+
+/// Largest packet read, more than any control surface sends.
+const MAX_PACKET: usize = 4096;
+
+/// A listener for OSC messages on a UDP port.
+pub struct Osc {
+    requests: Receiver<Request>,
+}
+
+impl Osc {
+    pub fn listen(addr: SocketAddr) -> Result<Self, String> {
+        let socket = UdpSocket::bind(addr).map_err(|err| format!("OSC on {addr}: {err}"))?;
+        println!("listening for OSC on {addr}");
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            let mut packet = [0; MAX_PACKET];
+            loop {
+                match socket.recv_from(&mut packet) {
+                    Ok((len, _)) => receive(&packet[..len], &sender),
+                    Err(err) => {
+                        eprintln!("OSC: {err}");
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Osc { requests })
+    }
+
+    /// Requests made since the last call.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+}
+
+/// Pass on whatever `packet`, a message or a bundle of them, asks for, ignoring what isn't
+/// understood.
+fn receive(packet: &[u8], sender: &Sender<Request>) {
+    match rosc::decoder::decode_udp(packet) {
+        Ok((_, packet)) => dispatch(packet, sender),
+        Err(err) => eprintln!("OSC: {err:?}"),
+    }
+}
+
+/// Pass on what `packet` and every message bundled in it ask for, in the order they came.
+fn dispatch(packet: OscPacket, sender: &Sender<Request>) {
+    match packet {
+        OscPacket::Message(message) => {
+            if let Some(request) = request(&message.addr, value(&message)) {
+                let _ = sender.send(request);
+            }
+        }
+        // the time tag is ignored, everything being done as soon as it comes
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                dispatch(packet, sender);
+            }
+        }
+    }
+}
+
+/// What a message to `address` with `arg` as its first number asks for, if it's understood.
+fn request(address: &str, arg: Option<f32>) -> Option<Request> {
+    let set = |param| Some(Request::Set { param, value: arg? });
+    let pressed = arg.is_none_or(|value| value != 0.0);
+    match address {
+        "/particles/count" => set(Param::Particles),
+        "/gravity/gm" => set(Param::Gravity),
+        "/palette/shift" => set(Param::BackgroundHue),
+        "/reset" => pressed.then_some(Request::Reseed),
+        "/cue/next" => pressed.then_some(Request::NextCue),
+        _ => {
+            let name = address.strip_prefix("/param/")?;
+            set(serde_json::from_value(name.into()).ok()?)
+        }
+    }
+}
+
+/// The first argument of `message` that's a number, if it has one.
+fn value(message: &OscMessage) -> Option<f32> {
+    message.args.iter().find_map(|arg| match *arg {
+        OscType::Int(value) => Some(value as f32),
+        OscType::Float(value) => Some(value),
+        OscType::Long(value) => Some(value as f32),
+        OscType::Double(value) => Some(value as f32),
+        OscType::Bool(value) => Some(if value { 1.0 } else { 0.0 }),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rosc::{OscBundle, OscTime};
+
+    use super::*;
+
+    fn message(addr: &str, args: Vec<OscType>) -> OscPacket {
+        OscPacket::Message(OscMessage {
+            addr: addr.into(),
+            args,
+        })
+    }
+
+    fn requests(packet: &OscPacket) -> Vec<Request> {
+        let (sender, requests) = mpsc::channel();
+        receive(&rosc::encoder::encode(packet).unwrap(), &sender);
+        requests.try_iter().collect()
+    }
+
+    #[test]
+    fn any_number_sets_a_param() {
+        let args = [
+            OscType::Int(3),
+            OscType::Long(3),
+            OscType::Double(3.0),
+            OscType::Float(3.0),
+        ];
+        for arg in args {
+            let packet = message("/param/trail_opacity", vec!["label".into(), arg]);
+            let requests = requests(&packet);
+            assert!(
+                matches!(
+                    requests[..],
+                    [Request::Set {
+                        param: Param::TrailOpacity,
+                        value: 3.0
+                    }]
+                ),
+                "{requests:?}"
+            );
+        }
+        let released = message("/reset", vec![OscType::Bool(false)]);
+        assert!(requests(&released).is_empty());
+        let pressed = message("/reset", vec![OscType::Bool(true)]);
+        assert!(matches!(requests(&pressed)[..], [Request::Reseed]));
+        let unknown = message("/param/nothing", vec![OscType::Float(1.0)]);
+        assert!(requests(&unknown).is_empty());
+    }
+
+    #[test]
+    fn bundles_are_unpacked_in_order() {
+        let nested = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::from((0, 1)),
+            content: vec![message("/cue/next", vec![])],
+        });
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime::from((0, 1)),
+            content: vec![
+                message("/gravity/gm", vec![OscType::Float(1.5)]),
+                nested,
+                message("/reset", vec![OscType::Int(1)]),
+            ],
+        });
+        let requests = requests(&bundle);
+        assert!(
+            matches!(
+                requests[..],
+                [
+                    Request::Set {
+                        param: Param::Gravity,
+                        value: 1.5
+                    },
+                    Request::NextCue,
+                    Request::Reseed
+                ]
+            ),
+            "{requests:?}"
+        );
+    }
+}
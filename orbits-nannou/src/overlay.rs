@@ -0,0 +1,247 @@
+use std::collections::BTreeSet;
+
+use nannou::prelude::*;
+
+use crate::export::Export;
+use crate::keys::{chord_name, Keymap};
+use crate::layers::LayerConfig;
+use crate::tier::Quality;
+use orbits_core::generation::Generation;
+use orbits_core::physics::Particle;
+use orbits_core::thermo;
+use orbits_core::trails::Overdraw;
+
+const LINE_HEIGHT: f32 = 20.0;
+const FONT_SIZE: u32 = 14;
+const PANEL_WIDTH: f32 = 360.0;
+const LABEL_WIDTH: f32 = 110.0;
+/// Font size of the particle indices, in points.
+const INDEX_FONT_SIZE: u32 = 12;
+/// Simulated seconds of travel the velocity arrows are as long as.
+const ARROW_SECONDS: f32 = 0.5;
+/// Radius of the rings around the particles picked out, in points.
+const PICKED_RADIUS: f32 = 6.0;
+
+/// The corner of the window an overlay panel is drawn in.
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// A line of text in an overlay panel.
+pub enum Row {
+    Heading(String),
+    /// A label in the left column with its value in the right one.
+    Entry(String, String),
+    /// An entry keyed by a dot of the color it goes with.
+    Swatch(Hsla, String, String),
+}
+
+/// Ring each of the particles of `generation` at `picked`, those frozen in place in white, faded
+/// as `layer` is.
+pub fn draw_picked(
+    generation: &Generation,
+    picked: &BTreeSet<usize>,
+    to_screen: impl Fn(Point2) -> Point2,
+    layer: &LayerConfig,
+    draw: &Draw,
+) {
+    for &i in picked {
+        let (Some(particle), Some(&color)) =
+            (generation.particles.get(i), generation.colors.get(i))
+        else {
+            continue;
+        };
+        let ring = draw
+            .ellipse()
+            .xy(to_screen(particle.pos))
+            .radius(PICKED_RADIUS)
+            .no_fill()
+            .stroke_weight(1.5);
+        if generation.frozen[i] {
+            ring.stroke(layer.fade(WHITE));
+        } else {
+            ring.stroke(layer.fade(color));
+        }
+    }
+}
+
+/// Rows describing the hotkeys and the scene currently running.
+pub fn help_rows(keymap: &Keymap, scene: Vec<(String, String)>) -> Vec<Row> {
+    let mut rows = vec![Row::Heading("Hotkeys".into())];
+    rows.extend(
+        keymap
+            .bindings()
+            .into_iter()
+            .map(|(action, chord)| Row::Entry(chord_name(chord), action.description().into())),
+    );
+    rows.push(Row::Heading("Scene".into()));
+    rows.extend(
+        scene
+            .into_iter()
+            .map(|(label, value)| Row::Entry(label, value)),
+    );
+    rows
+}
+
+/// Rows giving how long frames take, how long stepping `particles` takes of it, how many vertices
+/// the trails are built of, for `quality`, and the temperature and entropy of the particles.
+pub fn diagnostics_rows(quality: &Quality, particles: &[Particle], vertices: usize) -> Vec<Row> {
+    let (frame_seconds, physics_seconds) = quality.timings();
+    let mut rows = vec![
+        Row::Heading("Diagnostics".into()),
+        Row::Entry(
+            "frame time".into(),
+            format!("{:.2} ms", frame_seconds * 1000.0),
+        ),
+        Row::Entry(
+            "physics time".into(),
+            format!("{:.2} ms", physics_seconds * 1000.0),
+        ),
+        Row::Entry("particles".into(), particles.len().to_string()),
+        Row::Entry("trail vertices".into(), vertices.to_string()),
+        Row::Entry(
+            "temperature".into(),
+            format!("{:.1} units/s", thermo::temperature(particles)),
+        ),
+        Row::Entry(
+            "entropy".into(),
+            format!("{:.2}", thermo::entropy(particles)),
+        ),
+    ];
+    if quality.is_adaptive() {
+        rows.push(Row::Entry(
+            "trail length".into(),
+            format!("{:.0}%", quality.length() * 100.0),
+        ));
+    }
+    rows
+}
+
+/// Rows giving the frame rate and how much the trails drawn in the last frame overdrew.
+pub fn stats_rows(fps: f32, overdraw: Overdraw) -> Vec<Row> {
+    vec![
+        Row::Heading("Performance".into()),
+        Row::Entry("frame rate".into(), format!("{fps:.1} fps")),
+        Row::Entry(
+            "frame time".into(),
+            format!("{:.1} ms", 1000.0 / fps.max(1e-3)),
+        ),
+        Row::Entry("triangles".into(), overdraw.triangles.to_string()),
+        Row::Entry(
+            "coverage".into(),
+            format!("{:.0}% of the window", overdraw.coverage * 100.0),
+        ),
+        Row::Entry(
+            "overdraw".into(),
+            format!(
+                "{:.1}x on average, {}x at most",
+                overdraw.mean, overdraw.peak
+            ),
+        ),
+    ]
+}
+
+/// Rows of the panel on `export`, saying how full its queue of frames waiting to be written is.
+pub fn export_rows(export: &Export) -> Vec<Row> {
+    let (queued, capacity) = export.queued();
+    vec![
+        Row::Heading("Export".into()),
+        Row::Entry("frames".into(), export.frames().to_string()),
+        Row::Entry("queue".into(), format!("{queued} of {capacity}")),
+        Row::Entry("dropped".into(), export.dropped().to_string()),
+    ]
+}
+
+/// Draw `rows` on a translucent panel in a corner of the window, faded as `layer` is.
+pub fn draw_panel(rows: &[Row], corner: Corner, win: Rect, layer: &LayerConfig, draw: &Draw) {
+    let panel = Rect::from_w_h(PANEL_WIDTH, LINE_HEIGHT * (rows.len() as f32 + 1.0));
+    let panel = match corner {
+        Corner::TopLeft => panel.top_left_of(win.pad(20.0)),
+        Corner::TopRight => panel.top_right_of(win.pad(20.0)),
+        Corner::BottomLeft => panel.bottom_left_of(win.pad(20.0)),
+        Corner::BottomRight => panel.bottom_right_of(win.pad(20.0)),
+    };
+    draw.rect()
+        .xy(panel.xy())
+        .wh(panel.wh())
+        .color(layer.fade(rgba(0.0, 0.0, 0.0, 0.6)));
+
+    let inner = panel.pad(LINE_HEIGHT / 2.0);
+    for (i, row) in rows.iter().enumerate() {
+        let top = inner.top() - i as f32 * LINE_HEIGHT;
+        let line = Rect::from_corners(
+            pt2(inner.left(), top),
+            pt2(inner.right(), top - LINE_HEIGHT),
+        );
+        match row {
+            Row::Heading(text) => draw_text(text, line, layer.fade(rgb(1.0, 0.85, 0.5)), draw),
+            Row::Entry(label, value) => draw_entry(label, value, line, layer, draw),
+            Row::Swatch(color, label, value) => {
+                let dot = LINE_HEIGHT / 2.0;
+                draw.ellipse()
+                    .xy(pt2(line.left() + dot / 2.0, line.y()))
+                    .radius(dot / 3.0)
+                    .color(layer.fade(*color));
+                let line = Rect::from_corners(
+                    pt2(line.left() + dot * 1.5, line.top()),
+                    line.bottom_right(),
+                );
+                draw_entry(label, value, line, layer, draw);
+            }
+        }
+    }
+}
+
+/// Draw `label` in the left column of `line` and `value` in the right one.
+fn draw_entry(label: &str, value: &str, line: Rect, layer: &LayerConfig, draw: &Draw) {
+    let split = line.left() + LABEL_WIDTH;
+    let label_rect = Rect::from_corners(line.top_left(), pt2(split, line.bottom()));
+    let value_rect = Rect::from_corners(pt2(split, line.top()), line.bottom_right());
+    draw_text(label, label_rect, layer.fade(rgb(1.0, 1.0, 1.0)), draw);
+    draw_text(value, value_rect, layer.fade(rgb(0.8, 0.8, 0.8)), draw);
+}
+
+fn draw_text(text: &str, rect: Rect, color: LinSrgba, draw: &Draw) {
+    draw.text(text)
+        .xy(rect.xy())
+        .wh(rect.wh())
+        .font_size(FONT_SIZE)
+        .no_line_wrap()
+        .left_justify()
+        .color(color);
+}
+
+/// Label each particle of `generation` with its index, in the color its trail is drawn with, and
+/// optionally draw its velocity as an arrow, faded as `layer` is. `to_screen` maps world
+/// coordinates onto `draw`.
+pub fn draw_particles(
+    generation: &Generation,
+    to_screen: impl Fn(Point2) -> Point2,
+    labels: bool,
+    velocities: bool,
+    layer: &LayerConfig,
+    draw: &Draw,
+) {
+    for (i, (particle, &color)) in generation
+        .particles
+        .iter()
+        .zip(&generation.colors)
+        .enumerate()
+    {
+        let pos = to_screen(particle.pos);
+        let color = layer.fade(color);
+        if velocities {
+            let end = to_screen(particle.pos + particle.vel * ARROW_SECONDS);
+            draw.arrow().start(pos).end(end).weight(1.5).color(color);
+        }
+        if labels {
+            draw.text(&i.to_string())
+                .xy(pos + vec2(10.0, 10.0))
+                .font_size(INDEX_FONT_SIZE)
+                .color(color);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Auxiliary passes written along with each frame of an OpenEXR sequence, for grading the trails
+//! apart from each other and blurring them along their motion in a compositor: which particle
+//! left each pixel's trail, how far it is from the camera, how fast it was going there, and the
+//! motion vectors Nuke's vector blur reads, where it moves to by the next frame and from. Each
+//! pass draws the current generation's trails as lines, whatever their style, encoding its value
+//! in their colors rather than blending them, the nearest trail covering the rest.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use orbits_core::generation::Generation;
+use orbits_core::trails;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::compute::Look;
+
+/// Particle indices are split across two channels, each exact in the frame's half floats.
+const ID_SPLIT: f32 = 1024.0;
+
+/// An auxiliary pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    /// The index of the particle whose trail covers each pixel, or -1 where none does.
+    Id,
+    /// How far the trail is from the camera, in world units, with `three_d` on, 1 where there's a
+    /// trail otherwise, and 0 where there's none.
+    Depth,
+    /// Pixels across and down the image the particle moved in the frame it left the trail in.
+    Velocity,
+    /// Nuke's forward motion vectors: pixels right and up the image each point of the trail moves
+    /// to by the next frame, where the point newer than it is now.
+    Forward,
+    /// Nuke's backward motion vectors: pixels right and up the image each point of the trail was
+    /// at the frame before, where the point older than it is now.
+    Backward,
+}
+
+/// The `[export.passes]` section of the config: which auxiliary passes are written.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Passes {
+    pub id: bool,
+    pub depth: bool,
+    pub velocity: bool,
+    /// Nuke's `forward` and `backward` motion vectors, for vector motion blur.
+    pub motion: bool,
+}
+
+impl Passes {
+    /// The passes turned on.
+    pub fn on(&self) -> Vec<Pass> {
+        [
+            (self.id, Pass::Id),
+            (self.depth, Pass::Depth),
+            (self.velocity, Pass::Velocity),
+            (self.motion, Pass::Forward),
+            (self.motion, Pass::Backward),
+        ]
+        .into_iter()
+        .filter_map(|(on, pass)| on.then_some(pass))
+        .collect()
+    }
+}
+
+impl Pass {
+    /// The names of the EXR channels the pass is written to.
+    pub fn channels(self) -> &'static [&'static str] {
+        match self {
+            Pass::Id => &["particle.id"],
+            Pass::Depth => &["depth.Z"],
+            Pass::Velocity => &["velocity.u", "velocity.v"],
+            Pass::Forward => &["forward.u", "forward.v"],
+            Pass::Backward => &["backward.u", "backward.v"],
+        }
+    }
+
+    /// The values of the pass's channels a linear texel it was drawn as encodes.
+    pub fn decode(self, [r, g, _, a]: [f32; 4]) -> [f32; 2] {
+        let covered = a > 0.0;
+        match self {
+            Pass::Id if covered => [r + g * ID_SPLIT, 0.0],
+            Pass::Id => [-1.0, 0.0],
+            _ if covered => [r, g],
+            _ => [0.0, 0.0],
+        }
+    }
+
+    /// Draw the pass of the trails of `generation`, as `look` has them, onto `draw` in world
+    /// coordinates, each a line `weight` world units wide, `pixels_per_unit` of the frame to each.
+    pub fn draw(
+        self,
+        generation: &Generation,
+        look: &Look,
+        weight: f32,
+        pixels_per_unit: f32,
+        draw: &Draw,
+    ) {
+        draw.background().color(rgba(0.0, 0.0, 0.0, 0.0));
+        let draw = draw
+            .color_blend(wgpu::BlendComponent::REPLACE)
+            .alpha_blend(wgpu::BlendComponent::REPLACE);
+        let mut lines = Vec::new();
+        for particle in 0..generation.particles.len() {
+            let trail = trails::trail(generation, particle, 1.0, look.extrapolate, look.length);
+            if trail.is_empty() {
+                continue;
+            }
+            let points: Vec<(Point2, f32)> = (trail.iter())
+                .map(|&(pos, _)| match look.view {
+                    Some(view) => {
+                        let depth = view.depth(generation.lift(particle, pos));
+                        (view.place(generation, particle, pos), depth)
+                    }
+                    None => (pos, 1.0),
+                })
+                .collect();
+            let last = points.len() - 1;
+            let value = |i: usize| -> [f32; 2] {
+                match self {
+                    Pass::Id => [
+                        particle as f32 % ID_SPLIT,
+                        (particle as f32 / ID_SPLIT).floor(),
+                    ],
+                    Pass::Depth => [points[i].1, 0.0],
+                    Pass::Velocity => {
+                        // newest first, so the point before is the one left after it
+                        let (newer, older) = match i {
+                            0 => (0, 1.min(last)),
+                            _ => (i - 1, i),
+                        };
+                        let moved = (points[newer].0 - points[older].0) * pixels_per_unit;
+                        [moved.x, -moved.y]
+                    }
+                    // the head goes on the way it's going, and the tail came the way it's going
+                    Pass::Forward => {
+                        let moved = match i {
+                            0 => points[0].0 - points[1.min(last)].0,
+                            _ => points[i - 1].0 - points[i].0,
+                        };
+                        (moved * pixels_per_unit).to_array()
+                    }
+                    Pass::Backward => {
+                        let moved = if i == last {
+                            points[i].0 - points[i.saturating_sub(1)].0
+                        } else {
+                            points[i + 1].0 - points[i].0
+                        };
+                        (moved * pixels_per_unit).to_array()
+                    }
+                }
+            };
+            // a run of the trail for each stretch of it that's drawn, dashed trails' gaps left out
+            let mut run: Vec<(Point2, LinSrgba)> = Vec::new();
+            let depth = points.iter().map(|&(_, depth)| depth).sum::<f32>() / points.len() as f32;
+            for (i, &(_, color)) in trail.iter().enumerate() {
+                if color.alpha > 0.0 {
+                    let [a, b] = value(i);
+                    run.push((points[i].0, lin_srgba(a, b, 0.0, 1.0)));
+                } else if !run.is_empty() {
+                    lines.push((depth, std::mem::take(&mut run)));
+                }
+            }
+            lines.push((depth, run));
+        }
+        // the farthest first, so the nearest are drawn over them
+        lines.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, points) in lines.into_iter().filter(|(_, points)| points.len() >= 2) {
+            draw.polyline().weight(weight).points_colored(points);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! The camera the scene is seen through with `three_d` on, the particles orbiting in planes of
+//! their own: looking down at the middle of the system at a slant, through a perspective, as it
+//! slowly turns round. The trails are built as ever, each point in its particle's plane, then
+//! lifted into 3D and projected back down to where the camera sees it, so all that's drawn after,
+//! thinning and masks and the window's own camera, goes on as it would in 2D.
+
+use nannou::prelude::*;
+use orbits_core::generation::Generation;
+use orbits_core::orbital_radius;
+use orbits_core::physics::Body;
+
+/// Simulated seconds the camera takes to turn all the way round.
+const TURN_SECONDS: f32 = 240.0;
+/// How far the camera looks down from straight overhead.
+const TILT: f32 = PI / 3.0;
+/// How far the camera is from the middle of the system, in orbital radii. The plane through the
+/// middle facing it is drawn at the size it would be in 2D.
+const DISTANCE: f32 = 3.0;
+/// Points round the circle of the average orbit, as it's seen slanting away.
+const CIRCLE_POINTS: usize = 128;
+/// Nearest a point is taken to be to the camera, as a fraction of `DISTANCE`, so those passing
+/// behind it aren't flung across the view.
+const NEAREST: f32 = 0.1;
+
+/// Where the camera is.
+#[derive(Debug, Clone, Copy)]
+pub struct Perspective {
+    /// Rotation of the world in front of the camera.
+    turn: Quat,
+}
+
+impl Perspective {
+    /// The camera `t` simulated seconds into the run.
+    pub fn at(t: f32) -> Self {
+        let [turns, tilt] = Perspective::orientation_at(t);
+        Perspective::oriented(turns, tilt)
+    }
+
+    /// How far round the system has turned, in turns, and how far down from overhead the camera
+    /// looks, in radians, `t` simulated seconds into the run.
+    pub fn orientation_at(t: f32) -> [f32; 2] {
+        [t / TURN_SECONDS, TILT]
+    }
+
+    /// The camera with the system turned `turns` of the way round, looking down `tilt` radians
+    /// from overhead.
+    pub fn oriented(turns: f32, tilt: f32) -> Self {
+        let around = Quat::from_rotation_z(turns * TAU);
+        Perspective {
+            turn: Quat::from_rotation_x(-tilt) * around,
+        }
+    }
+
+    /// Where the camera sees `pos`, as a point of the flat world.
+    pub fn project(&self, pos: Point3) -> Point2 {
+        let pos = self.turn * pos;
+        let distance = DISTANCE * orbital_radius();
+        let depth = (distance - pos.z).max(NEAREST * distance);
+        pos.truncate() * distance / depth
+    }
+
+    /// How far in front of the camera `pos` is.
+    pub fn depth(&self, pos: Point3) -> f32 {
+        DISTANCE * orbital_radius() - (self.turn * pos).z
+    }
+
+    /// Where the camera sees `pos`, a point in the orbital plane of `particle` of `generation`.
+    pub fn place(&self, generation: &Generation, particle: usize, pos: Point2) -> Point2 {
+        self.project(generation.lift(particle, pos))
+    }
+
+    /// Where the camera sees the disc of `radius` round the middle of the flat plane.
+    pub fn disc(&self, radius: f32) -> impl Iterator<Item = Point2> + '_ {
+        (0..CIRCLE_POINTS).map(move |i| {
+            let angle = i as f32 / CIRCLE_POINTS as f32 * TAU;
+            self.project(pt3(angle.cos(), angle.sin(), 0.0) * radius)
+        })
+    }
+
+    /// Where the camera sees `bodies`, in the flat plane.
+    pub fn flatten_bodies(&self, bodies: &mut [Body]) {
+        for body in bodies {
+            body.pos = self.project(body.pos.extend(0.0));
+        }
+    }
+
+    /// Project the points of the trail of `particle` of `generation`.
+    pub fn flatten_trail<C>(
+        &self,
+        generation: &Generation,
+        particle: usize,
+        points: &mut [(Point2, C)],
+    ) {
+        for (pos, _) in points {
+            *pos = self.place(generation, particle, *pos);
+        }
+    }
+}
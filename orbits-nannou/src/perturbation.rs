@@ -0,0 +1,201 @@
+//! Perturbations: brief disturbances of every particle now and then, so a long-running
+//! installation keeps changing rather than settling into one look, or now and then a few particles
+//! split in two, their trails branching. They're timed by simulated time alone, so followers and
+//! replays see the same ones.
+
+use nannou::prelude::*;
+use orbits_core::generation::Generation;
+use orbits_core::orbital_radius;
+use orbits_core::physics::{acceleration_at, circular_speed, gm, reference_acceleration, Body};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Simulated seconds each perturbation lasts.
+const DURATION: f32 = 0.5;
+/// Particles each split perturbation splits, at most.
+const SPLITS: usize = 3;
+/// Mixed into each perturbation's number before it's hashed, so its draws don't line up with
+/// anything else's.
+const SALT: u64 = 0x5045_5254_5552_4221;
+
+/// A way of disturbing the particles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// The whole swarm shoved one way.
+    Impulse,
+    /// The central pull briefly strengthened.
+    Gravity,
+    /// Each particle kicked along a smooth noise field, scattering the swarm.
+    Noise,
+    /// A few particles of the current generation, picked at random, each split in two as it
+    /// starts, the halves kicked apart across the way it was going.
+    Split,
+}
+
+/// When perturbations happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Timing {
+    /// Once every `period`, on the dot, going through `kinds` in turn.
+    #[default]
+    Scheduled,
+    /// Once at a random moment of every `period`, of a kind picked at random.
+    Random,
+}
+
+/// The `[perturbation]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PerturbationConfig {
+    /// How hard each perturbation kicks, as a fraction of the speed of the average orbit, or 0
+    /// for none.
+    pub strength: f32,
+    /// Simulated seconds between perturbations.
+    pub period: f32,
+    pub timing: Timing,
+    pub kinds: Vec<Kind>,
+}
+
+impl Default for PerturbationConfig {
+    fn default() -> Self {
+        PerturbationConfig {
+            strength: 0.0,
+            period: 60.0,
+            timing: Timing::default(),
+            kinds: vec![Kind::Impulse, Kind::Gravity, Kind::Noise],
+        }
+    }
+}
+
+/// The perturbations of a run.
+pub struct Perturbations {
+    config: PerturbationConfig,
+}
+
+/// One perturbation, along with the random bits it draws its direction or field from.
+struct Event {
+    kind: Kind,
+    bits: u64,
+    /// Simulated time it starts at.
+    start: f32,
+}
+
+impl Perturbations {
+    /// The perturbations `config` asks for, or none if they're turned off.
+    pub fn new(config: PerturbationConfig) -> Option<Self> {
+        (config.strength > 0.0 && config.period > 0.0 && !config.kinds.is_empty())
+            .then_some(Perturbations { config })
+    }
+
+    /// Disturb the particles of `generations` for the `delta_seconds` up to simulated time `t`,
+    /// if a perturbation is under way, with the central `bodies` as they are at `t`. Returns where
+    /// the halves split off particles of the current generation went in, in order, by where they
+    /// are now, for the count it's asked to have to be brought up to match.
+    pub fn apply(
+        &self,
+        generations: &mut [Generation],
+        bodies: &[Body],
+        t: f32,
+        delta_seconds: f32,
+    ) -> Vec<usize> {
+        let Some(event) = self.event_at(t) else {
+            return Vec::new();
+        };
+        let speed = circular_speed(orbital_radius(), gm(t));
+        if event.kind == Kind::Split {
+            let starting = (t - delta_seconds..t).contains(&event.start);
+            return match (starting, generations.last_mut()) {
+                (true, Some(current)) => self.split(current, &event, speed),
+                _ => Vec::new(),
+            };
+        }
+        // spread over the perturbation, so each adds up to `strength` of the average orbit's speed
+        let kick = self.config.strength * speed / DURATION * delta_seconds;
+        let angle = unit(event.bits) * TAU;
+        let particles = generations
+            .iter_mut()
+            .flat_map(|generation| &mut generation.particles);
+        for particle in particles {
+            particle.vel += match event.kind {
+                Kind::Impulse => vec2(angle.cos(), angle.sin()) * kick,
+                // as strong, on the average orbit, as the impulse
+                Kind::Gravity => {
+                    acceleration_at(particle.pos, bodies) / reference_acceleration(t) * kick
+                }
+                Kind::Noise => {
+                    let angle = field(particle.pos, event.bits);
+                    vec2(angle.cos(), angle.sin()) * kick
+                }
+                Kind::Split => unreachable!("splits kick as they split"),
+            };
+        }
+        Vec::new()
+    }
+
+    /// Split the particles of `generation` that `event` picks, the halves kicked apart by half of
+    /// `strength` of `speed` each, returning where the halves split off went in.
+    fn split(&self, generation: &mut Generation, event: &Event, speed: f32) -> Vec<usize> {
+        let count = generation.staying();
+        let mut picked: Vec<usize> = (0..SPLITS as u64)
+            .map(|k| (hash(event.bits ^ k) % count as u64) as usize)
+            .filter(|&particle| !generation.frozen[particle])
+            .collect();
+        picked.sort_unstable();
+        picked.dedup();
+        // from the last, so those before each are where they were picked
+        for &particle in picked.iter().rev() {
+            let vel = generation.particles[particle].vel;
+            let kick = vel.perp().normalize_or_zero() * self.config.strength * speed / 2.0;
+            generation.split(particle, kick);
+        }
+        // each half goes in after its particle, and after the halves of those before it
+        (picked.iter().enumerate())
+            .map(|(before, &particle)| particle + 1 + before)
+            .collect()
+    }
+
+    /// The perturbation under way at simulated time `t`, if there is one. The `k`th starts in the
+    /// `k + 1`th period, after none in the first.
+    fn event_at(&self, t: f32) -> Option<Event> {
+        let period = self.config.period;
+        let latest = (t / period).floor() as i64 - 1;
+        // a perturbation can run on past the end of its period
+        let earliest = latest - (DURATION / period).ceil() as i64;
+        (earliest.max(0)..=latest).rev().find_map(|k| {
+            let bits = hash(k as u64);
+            let (start, kind) = match self.config.timing {
+                Timing::Scheduled => (0.0, k as usize),
+                Timing::Random => (unit(bits >> 32), (bits >> 8) as usize),
+            };
+            let start = (k as f32 + 1.0 + start) * period;
+            let kinds = &self.config.kinds;
+            (start..start + DURATION).contains(&t).then(|| Event {
+                kind: kinds[kind % kinds.len()],
+                bits,
+                start,
+            })
+        })
+    }
+}
+
+/// The angle a noise perturbation drawn from `bits` pushes particles at `pos`, varying smoothly
+/// over about the size of an orbit.
+fn field(pos: Point2, bits: u64) -> f32 {
+    let [a, b, c] = [0, 16, 32].map(|shift| unit(bits >> shift) * TAU);
+    let p = pos / orbital_radius() * TAU;
+    PI * ((p.x + a).sin() + (p.y * 1.3 + b).sin() + ((p.x + p.y) * 0.7 + c).sin())
+}
+
+/// The low 16 bits of `bits` as a fraction from 0 to 1.
+fn unit(bits: u64) -> f32 {
+    (bits & 0xffff) as f32 / 65536.0
+}
+
+/// SplitMix64, so each perturbation's draws come from its number alone.
+fn hash(k: u64) -> u64 {
+    let mut z = (k ^ SALT).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,77 @@
+//! Portraits of the orbits of a run, with the `portraits` subcommand, to browse and pick out
+//! single orbit shapes as works of their own: once the run has been simulated as long as a still
+//! would be, each particle's trail is rendered alone on the background, framed to fill the image,
+//! and written to a numbered PNG of its own, or laid out with the rest in a contact sheet.
+
+use std::path::{Path, PathBuf};
+
+use nannou::image::RgbaImage;
+use nannou::prelude::*;
+use orbits_core::generation::Generation;
+
+use crate::export;
+use crate::gallery;
+
+/// How much further than its trail reaches a portrait is framed, on each side.
+const MARGIN: f32 = 1.1;
+/// Least width and height of the world a portrait is framed on, in world units, so a particle
+/// that's hardly moved isn't blown up to fill it.
+const LEAST_SIDE: f32 = 100.0;
+
+/// What the `portraits` subcommand writes, to the directory its still would go to.
+#[derive(Debug, Default)]
+pub struct Portraits {
+    /// Write one contact sheet of them all, `columns` across, rather than each on its own.
+    pub sheet: Option<u32>,
+}
+
+impl Portraits {
+    /// Write `portraits` to `dir`, one by one or as the sheet, each labelled with the particle
+    /// it's of, returning the files written.
+    pub fn write(
+        &self,
+        dir: &Path,
+        portraits: Vec<(usize, RgbaImage)>,
+    ) -> Result<Vec<PathBuf>, String> {
+        export::create_dir(dir)?;
+        let save = |image: &RgbaImage, path: &Path| {
+            (image.save(path)).map_err(|err| format!("{}: {err}", path.display()))
+        };
+        let mut written = Vec::new();
+        match self.sheet {
+            Some(columns) => {
+                let stills: Vec<_> = (portraits.into_iter())
+                    .map(|(particle, image)| (image, format!("particle {particle}")))
+                    .collect();
+                let path = dir.join("sheet.png");
+                save(&gallery::contact_sheet(&stills, columns), &path)?;
+                println!("{}: {} portraits", path.display(), stills.len());
+                written.push(path);
+            }
+            None => {
+                for (particle, image) in &portraits {
+                    let path = dir.join(format!("particle-{particle:04}.png"));
+                    save(image, &path)?;
+                    written.push(path);
+                }
+                println!("{}: {} portraits", dir.display(), portraits.len());
+            }
+        }
+        Ok(written)
+    }
+}
+
+/// The part of the world the portrait of the one particle of `alone` frames: all of its trail,
+/// archived and all, with room around it.
+pub fn framing(alone: &Generation) -> Rect {
+    let archived = alone.archives.iter().flat_map(|archive| archive.records());
+    let points = (alone.history.iter().flatten().map(|record| record.pos))
+        .chain(archived.map(|record| record.pos))
+        .chain(alone.particles.iter().map(|particle| particle.pos));
+    let (least, most) = points.fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(least, most), pos| (least.min(pos), most.max(pos)),
+    );
+    let extent = ((most - least) * MARGIN).max(Vec2::splat(LEAST_SIDE));
+    Rect::from_xy_wh((least + most) / 2.0, extent)
+}
@@ -0,0 +1,198 @@
+//! Lightens the piece while a laptop runs on its battery or runs hot, so a demo left going doesn't
+//! drain it: fewer particles, the post effects left out and the frame rate capped, each as the
+//! `[power]` section has it, and all put back once it's plugged in and cool again. Whether it's on
+//! battery is read from `/sys/class/power_supply` on Linux, `pmset` on macOS and the battery's
+//! status through PowerShell on Windows. How hot it is is read from the thermal zones on Linux,
+//! and taken from whether macOS is holding the CPU back on a Mac.
+
+use std::fs;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// The `[power]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Whether the piece is lightened while on battery or hot.
+    pub adapt: bool,
+    /// Wall-clock seconds between one reading of the power state and the next.
+    pub interval: f32,
+    /// Degrees Celsius the hottest thermal zone has to be over for the machine to count as hot,
+    /// or left out for the heat to be left be.
+    pub hot: Option<f32>,
+    /// How many of the particles are kept while lightened, as a fraction of those there were.
+    pub particles: f32,
+    /// Whether the post effects are still drawn while lightened.
+    pub effects: bool,
+    /// Most frames a second while lightened, or 0 to leave the frame rate be.
+    pub fps: f32,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        PowerConfig {
+            adapt: true,
+            interval: 10.0,
+            hot: Some(90.0),
+            particles: 0.5,
+            effects: false,
+            fps: 30.0,
+        }
+    }
+}
+
+impl PowerConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.interval <= 0.0 {
+            return Err(format!(
+                "the power's interval has to be positive, not {}",
+                self.interval
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.particles) {
+            return Err(format!(
+                "the power's particles go from 0 to 1, not {}",
+                self.particles
+            ));
+        }
+        if self.fps < 0.0 {
+            return Err(format!(
+                "the power's fps can't be negative, not {}",
+                self.fps
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Why the piece is lightened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strain {
+    Battery,
+    Hot,
+}
+
+impl Strain {
+    pub fn label(self) -> &'static str {
+        match self {
+            Strain::Battery => "on battery",
+            Strain::Hot => "running hot",
+        }
+    }
+}
+
+/// The power state as last read, read again every so often on a thread of its own.
+pub struct Power {
+    pub config: PowerConfig,
+    readings: Receiver<Option<Strain>>,
+    strain: Option<Strain>,
+}
+
+impl Power {
+    pub fn new(config: PowerConfig) -> Self {
+        let (sender, readings) = mpsc::channel();
+        let interval = Duration::from_secs_f32(config.interval);
+        thread::spawn(move || {
+            let mut last = None;
+            loop {
+                let strain = read(config.hot);
+                // only changes are sent, and the thread ends once nothing's listening
+                if strain != last && sender.send(strain).is_err() {
+                    return;
+                }
+                last = strain;
+                thread::sleep(interval);
+            }
+        });
+        Power {
+            config,
+            readings,
+            strain: None,
+        }
+    }
+
+    /// Why the piece is to be lightened, if it is.
+    pub fn strain(&self) -> Option<Strain> {
+        self.strain
+    }
+
+    /// Whether the post effects are left out, as the machine is strained.
+    pub fn drops_effects(&self) -> bool {
+        self.strain.is_some() && !self.config.effects
+    }
+
+    /// The strain read since the last poll, if it's changed.
+    pub fn poll(&mut self) -> Option<Option<Strain>> {
+        let strain = self.readings.try_iter().last()?;
+        self.strain = strain;
+        Some(strain)
+    }
+}
+
+/// Why the machine is strained, if it is, it counting as hot over `hot` degrees Celsius.
+fn read(hot: Option<f32>) -> Option<Strain> {
+    if on_battery() {
+        return Some(Strain::Battery);
+    }
+    hot.filter(|&hot| running_hot(hot)).map(|_| Strain::Hot)
+}
+
+fn on_battery() -> bool {
+    if cfg!(target_os = "macos") {
+        output("pmset", &["-g", "batt"]).is_some_and(|out| out.contains("'Battery Power'"))
+    } else if cfg!(target_os = "windows") {
+        // a status of 1 is discharging
+        let status = "(Get-CimInstance Win32_Battery).BatteryStatus";
+        output("powershell", &["-NoProfile", "-Command", status])
+            .is_some_and(|out| out.lines().any(|line| line.trim() == "1"))
+    } else {
+        let Ok(supplies) = fs::read_dir("/sys/class/power_supply") else {
+            return false;
+        };
+        supplies.flatten().any(|supply| {
+            let read = |name| fs::read_to_string(supply.path().join(name)).unwrap_or_default();
+            read("type").trim() == "Battery" && read("status").trim() == "Discharging"
+        })
+    }
+}
+
+fn running_hot(hot: f32) -> bool {
+    if cfg!(target_os = "macos") {
+        // macOS says how far it's holding the CPU back by, from 100 for not at all
+        output("pmset", &["-g", "therm"]).is_some_and(|out| {
+            out.lines()
+                .filter_map(|line| line.trim().strip_prefix("CPU_Speed_Limit"))
+                .filter_map(|limit| limit.trim_start_matches([' ', '=']).parse::<u32>().ok())
+                .any(|limit| limit < 100)
+        })
+    } else if cfg!(target_os = "windows") {
+        false
+    } else {
+        let Ok(zones) = fs::read_dir("/sys/class/thermal") else {
+            return false;
+        };
+        zones.flatten().any(|zone| {
+            // in thousandths of a degree
+            fs::read_to_string(zone.path().join("temp"))
+                .ok()
+                .and_then(|temp| temp.trim().parse::<f32>().ok())
+                .is_some_and(|temp| temp / 1000.0 > hot)
+        })
+    }
+}
+
+/// What `program` run with `args` prints, if it can be run.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
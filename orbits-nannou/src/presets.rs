@@ -0,0 +1,147 @@
+//! Presets of the whole look of the piece, kept in the config and switched between with the
+//! number keys, or in turn every so often on their own for a piece left running unattended. Each
+//! eases the parameters it sets over the transition rather than cutting to them, and recolors
+//! the trails and switches the trail style and view as it starts.
+
+use std::collections::BTreeMap;
+
+use orbits_core::cues::Transition;
+use orbits_core::params::{Param, Params};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::chart::View;
+use crate::render::TrailStyle;
+
+/// One of the `[[presets.list]]`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    #[serde(default)]
+    pub name: String,
+    /// Parameters to ease to, such as the particle count and gravity, leaving the others as they
+    /// are.
+    #[serde(default)]
+    #[schemars(with = "BTreeMap<String, f32>")]
+    pub params: BTreeMap<Param, f32>,
+    /// The palette to color the trails from, by name.
+    pub palette: Option<String>,
+    /// The style to draw the trails in.
+    pub trail_style: Option<TrailStyle>,
+    /// What to show the piece as, the orbits, the radius chart or phase space.
+    pub view: Option<View>,
+}
+
+/// The `[presets]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PresetsConfig {
+    /// Wall-clock seconds the parameters take to ease to a preset's.
+    pub transition: f32,
+    /// Wall-clock seconds before moving on to the next preset on its own, going round, or 0 to
+    /// stay on each until another is picked.
+    pub cycle: f32,
+    pub list: Vec<Preset>,
+}
+
+impl Default for PresetsConfig {
+    fn default() -> Self {
+        PresetsConfig {
+            transition: 4.0,
+            cycle: 0.0,
+            list: Vec::new(),
+        }
+    }
+}
+
+impl PresetsConfig {
+    /// Check the presets, their palettes among the built-in ones and `palettes`.
+    pub fn check(&self, palettes: &BTreeMap<String, Vec<String>>) -> Result<(), String> {
+        if self.transition < 0.0 {
+            return Err(format!(
+                "the presets' transition can't be negative, not {}",
+                self.transition
+            ));
+        }
+        if self.cycle < 0.0 {
+            return Err(format!(
+                "the presets' cycle can't be negative, not {}",
+                self.cycle
+            ));
+        }
+        for preset in &self.list {
+            if let Some(palette) = &preset.palette {
+                orbits_core::palette::Palette::named(palette, palettes)
+                    .map_err(|err| format!("the preset {:?}: {err}", preset.name))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The presets, and which the piece is on.
+pub struct Presets {
+    config: PresetsConfig,
+    /// Index of the preset most recently started.
+    current: Option<usize>,
+    transition: Option<Transition>,
+    /// Wall-clock seconds since the current preset was started.
+    since: f32,
+}
+
+impl Presets {
+    pub fn new(config: PresetsConfig) -> Self {
+        Presets {
+            config,
+            current: None,
+            transition: None,
+            since: 0.0,
+        }
+    }
+
+    /// Start easing `params` towards those of preset `index`, returning it if there is one for
+    /// the rest of it to be taken on.
+    pub fn start(&mut self, index: usize, params: &Params) -> Option<&Preset> {
+        let preset = self.config.list.get(index)?;
+        self.current = Some(index);
+        self.since = 0.0;
+        self.transition = Some(Transition::new(
+            &preset.params,
+            params,
+            self.config.transition,
+        ));
+        Some(preset)
+    }
+
+    /// Continue easing `params` into the current preset, `wall_seconds` on, returning the next
+    /// preset to start if it's time to move on to it.
+    pub fn update(&mut self, wall_seconds: f32, params: &mut Params) -> Option<usize> {
+        if let Some(transition) = &mut self.transition {
+            if transition.update(wall_seconds, params) {
+                self.transition = None;
+            }
+        }
+        self.since += wall_seconds;
+        if self.config.cycle <= 0.0 || self.config.list.is_empty() || self.since < self.config.cycle
+        {
+            return None;
+        }
+        Some(self.current.map_or(0, |i| (i + 1) % self.config.list.len()))
+    }
+
+    /// Which preset the piece is on, if any are kept, e.g. `"2/4 calm"`.
+    pub fn describe(&self) -> Option<String> {
+        if self.config.list.is_empty() {
+            return None;
+        }
+        Some(match self.current {
+            None => format!("0/{}", self.config.list.len()),
+            Some(i) => format!(
+                "{}/{} {}",
+                i + 1,
+                self.config.list.len(),
+                self.config.list[i].name
+            ),
+        })
+    }
+}
@@ -0,0 +1,73 @@
+//! Favorite seeds, rated from one to five stars with the number keys as they're shown. Each rating
+//! keeps the config the seed was shown under along with it, so `ratings` can list the favorites
+//! and `gallery --rated` render them again however the config file's changed since.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Where ratings are kept, relative to the working directory.
+pub const RATINGS_PATH: &str = "trynannou.ratings.json";
+/// Most stars a seed can be given.
+pub const MAX_STARS: u8 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    pub seed: u64,
+    /// From 1 to `MAX_STARS`.
+    pub stars: u8,
+    /// The config the seed was shown under, as TOML, empty for the defaults.
+    pub config: String,
+    /// When it was last rated, in seconds since the Unix epoch.
+    pub rated: u64,
+}
+
+impl Rating {
+    pub fn new(seed: u64, stars: u8, config: String) -> Self {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        Rating {
+            seed,
+            stars: stars.clamp(1, MAX_STARS),
+            config,
+            rated: since_epoch.as_secs(),
+        }
+    }
+
+    /// The stars, as many filled in as it was given.
+    pub fn stars(&self) -> String {
+        (1..=MAX_STARS)
+            .map(|star| if star <= self.stars { '*' } else { '.' })
+            .collect()
+    }
+}
+
+/// Every rating in the file at `path`, or none if there's no file yet.
+pub fn load(path: &Path) -> Result<Vec<Rating>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(|err| format!("{}: {err}", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(format!("{}: {err}", path.display())),
+    }
+}
+
+/// Add `rating` to the file at `path`, in place of any earlier rating of the same seed under the
+/// same config.
+pub fn rate(path: &Path, rating: Rating) -> Result<(), String> {
+    let mut ratings = load(path)?;
+    ratings.retain(|other| (other.seed, &other.config) != (rating.seed, &rating.config));
+    ratings.push(rating);
+    let text = serde_json::to_string_pretty(&ratings).unwrap();
+    fs::write(path, text).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// The ratings of at least `min_stars`, the most stars first, and the most recently rated first
+/// of those with as many.
+pub fn best(mut ratings: Vec<Rating>, min_stars: u8) -> Vec<Rating> {
+    ratings.retain(|rating| rating.stars >= min_stars);
+    ratings.sort_by_key(|rating| Reverse((rating.stars, rating.rated)));
+    ratings
+}
@@ -0,0 +1,52 @@
+//! Keeps a long-running piece going through the GPU failing, as it can on waking from sleep or
+//! when the driver is reset. nannou configures a window's surface again itself once it's lost;
+//! for everything else, as the GPU raises errors, the piece lets go of what it keeps there and
+//! builds it again, from the trails and particles kept on the CPU and the long exposure as last
+//! saved. Should the errors keep coming, the device is lost for good, and only a process of its
+//! own gets a new one, so the piece starts again from its state as it was.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::gpu;
+
+/// Where the state is saved for the piece to start again from, should the GPU be lost.
+pub const RECOVERY_PATH: &str = "trynannou.recovery.json";
+/// How many times what's kept on the GPU is built again within `WINDOW` before the device is
+/// taken to be lost for good.
+const MOST_REBUILDS: usize = 3;
+const WINDOW: Duration = Duration::from_secs(10);
+
+/// What to do about the GPU having failed.
+pub enum Recover {
+    /// Build what's kept on the GPU again.
+    Rebuild,
+    /// Start the piece again in a process of its own.
+    Restart,
+}
+
+/// The GPU's errors seen, and when what's kept on it was last built again.
+#[derive(Default)]
+pub struct Recovery {
+    seen: u64,
+    rebuilt: VecDeque<Instant>,
+}
+
+impl Recovery {
+    /// What to do about any errors the GPU has raised since the last check, if it has.
+    pub fn check(&mut self) -> Option<Recover> {
+        let errors = gpu::errors();
+        if errors == self.seen {
+            return None;
+        }
+        self.seen = errors;
+        let now = Instant::now();
+        self.rebuilt.retain(|&at| now - at < WINDOW);
+        if self.rebuilt.len() >= MOST_REBUILDS {
+            return Some(Recover::Restart);
+        }
+        self.rebuilt.push_back(now);
+        gpu::rebuild();
+        Some(Recover::Rebuild)
+    }
+}
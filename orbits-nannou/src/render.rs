@@ -0,0 +1,235 @@
+//! The styles the trails can be drawn in, switched between with a hotkey as suits how many
+//! particles there are: the ribbons between neighboring particles' trails, a line along each
+//! particle's own trail, a dot at each point of it shrinking with age, or a fan filled in from the
+//! middle of the system out to it. Every style but the ribbons is built here, as one mesh drawn in
+//! one go; the ribbons are built as they've always been, on the GPU too with `--gpu`. The masses
+//! the trails are drawn around are drawn here too.
+
+use nannou::prelude::*;
+use orbits_core::generation::Generation;
+use orbits_core::physics::Body;
+use orbits_core::trails::{self, Mesh, Ribbons};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::chart::View;
+use crate::compute::Look;
+use crate::layers::LayerConfig;
+use crate::mask::Mask;
+
+/// How the trails are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailStyle {
+    /// Ribbons filled in between each particle's trail and the next one's.
+    #[default]
+    Ribbons,
+    /// A line along each particle's trail.
+    Lines,
+    /// A dot at each point of each trail, smaller the older it is.
+    Points,
+    /// Each trail filled in with a fan from the middle of the system, fading toward it.
+    Fans,
+}
+
+impl TrailStyle {
+    pub const ALL: [TrailStyle; 4] = [
+        TrailStyle::Ribbons,
+        TrailStyle::Lines,
+        TrailStyle::Points,
+        TrailStyle::Fans,
+    ];
+
+    /// The style after this one, going round.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&style| style == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TrailStyle::Ribbons => "ribbons",
+            TrailStyle::Lines => "lines",
+            TrailStyle::Points => "points",
+            TrailStyle::Fans => "fans",
+        }
+    }
+}
+
+/// The `[render]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RenderConfig {
+    /// The style the trails start off drawn in.
+    pub style: TrailStyle,
+    /// What the piece starts off shown as, the orbits, the radius chart or phase space.
+    pub view: View,
+    /// Width of the lines, in world units.
+    pub line_width: f32,
+    /// Width of the newest dots, in world units, the oldest shrinking to nothing.
+    pub point_size: f32,
+    /// Opacity of the fans relative to the trails, as they overlap a great deal.
+    pub fan_opacity: f32,
+    /// Points across the soft edge faded out along each side of the ribbons, so they look smooth
+    /// without heavy multisampling, or 0 for hard edges.
+    pub feather: f32,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            style: TrailStyle::Ribbons,
+            view: View::Orbits,
+            line_width: 2.0,
+            point_size: 6.0,
+            fan_opacity: 0.2,
+            feather: 0.0,
+        }
+    }
+}
+
+/// The trails of `generation` as `look` has them, in `style`. Ribbons drawn frame after frame are
+/// better kept in a `trails::Ribbons`, which builds them afresh only as they change shape.
+pub fn build(
+    style: TrailStyle,
+    config: &RenderConfig,
+    generation: &Generation,
+    look: Look,
+) -> Mesh {
+    let length = look.build_in * look.length;
+    if style == TrailStyle::Ribbons {
+        let mut ribbons = Ribbons::default();
+        let ribbons = ribbons.update_placed(
+            generation,
+            look.opacity,
+            look.highlight,
+            look.extrapolate,
+            length,
+            |particle, pos| look.place(generation, particle, pos),
+        );
+        for (_, color) in &mut ribbons.vertices {
+            color.saturation *= look.build_in;
+        }
+        return std::mem::take(ribbons);
+    }
+    let mut mesh = Mesh::default();
+    for particle in 0..generation.particles.len() {
+        let opacity = look.opacity * generation.saliency.alpha(particle, look.highlight);
+        let mut points = trails::trail(generation, particle, opacity, look.extrapolate, length);
+        for (_, color) in &mut points {
+            color.saturation *= look.build_in;
+        }
+        if let Some(view) = look.view {
+            view.flatten_trail(generation, particle, &mut points);
+        }
+        let thickness = trails::thickness(generation, particle, length);
+        match style {
+            TrailStyle::Ribbons => {}
+            TrailStyle::Lines => line(&mut mesh, &points, &thickness, config.line_width),
+            TrailStyle::Points => dots(&mut mesh, &points, &thickness, config.point_size),
+            TrailStyle::Fans => fan(&mut mesh, &points, config.fan_opacity),
+        }
+    }
+    mesh
+}
+
+/// Draw the masses, sized by their share of the total mass and faded as `layer` and its `mask`
+/// are. A lone body at the origin is left implicit, as the orbit guide already marks it.
+pub fn draw_bodies(
+    bodies: &[Body],
+    color: Hsl,
+    layer: &LayerConfig,
+    mask: Option<&Mask>,
+    draw: &Draw,
+) {
+    if bodies.len() < 2 {
+        return;
+    }
+    let total: f32 = bodies.iter().map(|body| body.gm).sum();
+    for body in bodies {
+        let radius = 40.0 * (body.gm / total).sqrt();
+        let coverage = mask.map_or(1.0, |mask| mask.coverage(body.pos));
+        let fade = |color| {
+            let mut color = layer.fade(color);
+            color.alpha *= coverage;
+            color
+        };
+        draw.ellipse()
+            .xy(body.pos)
+            .radius(radius * 1.4)
+            .color(fade(hsla(
+                color.hue.to_radians() / TAU,
+                color.saturation,
+                color.lightness,
+                0.2,
+            )));
+        draw.ellipse()
+            .xy(body.pos)
+            .radius(radius)
+            .color(fade(color.into()));
+    }
+}
+
+/// Add a line of `width` through `points` to `mesh`, as a quad along each piece of it, each point
+/// as many times as thick as its `thickness`.
+pub fn line(mesh: &mut Mesh, points: &[(Point2, Hsla)], thickness: &[f32], width: f32) {
+    for (i, piece) in points.windows(2).enumerate() {
+        let [(start, start_color), (end, end_color)] = [piece[0], piece[1]];
+        let across = (end - start).perp().normalize_or_zero() * width / 2.0;
+        let [start_across, end_across] = [thickness[i], thickness[i + 1]].map(|t| across * t);
+        quad(
+            mesh,
+            [
+                (start - start_across, start_color),
+                (start + start_across, start_color),
+                (end + end_across, end_color),
+                (end - end_across, end_color),
+            ],
+        );
+    }
+}
+
+/// Add a square dot to `mesh` at each of `points`, the newest first, `size` across times its
+/// `thickness` and the older ones smaller.
+fn dots(mesh: &mut Mesh, points: &[(Point2, Hsla)], thickness: &[f32], size: f32) {
+    for (age, (&(pos, color), thickness)) in points.iter().zip(thickness).enumerate() {
+        let half = size / 2.0 * thickness * (1.0 - age as f32 / points.len() as f32);
+        quad(
+            mesh,
+            [
+                (pos + vec2(-half, -half), color),
+                (pos + vec2(half, -half), color),
+                (pos + vec2(half, half), color),
+                (pos + vec2(-half, half), color),
+            ],
+        );
+    }
+}
+
+/// Add a fan from the origin through `points` to `mesh`, at `opacity` out at the trail and
+/// fading to nothing at the origin.
+fn fan(mesh: &mut Mesh, points: &[(Point2, Hsla)], opacity: f32) {
+    if points.len() < 2 {
+        return;
+    }
+    let first = mesh.vertices.len();
+    let mut middle = points[0].1;
+    middle.alpha = 0.0;
+    mesh.vertices.push((Point3::ZERO, middle));
+    mesh.vertices.extend(points.iter().map(|&(pos, mut color)| {
+        color.alpha *= opacity;
+        (pos.extend(0.0), color)
+    }));
+    for i in 1..points.len() {
+        mesh.indices.extend([first, first + i, first + i + 1]);
+    }
+}
+
+/// Add the quad with `corners`, going round it, to `mesh`.
+fn quad(mesh: &mut Mesh, corners: [(Point2, Hsla); 4]) {
+    let first = mesh.vertices.len();
+    mesh.vertices
+        .extend(corners.iter().map(|&(pos, color)| (pos.extend(0.0), color)));
+    mesh.indices
+        .extend([0, 1, 2, 0, 2, 3].map(|corner| first + corner));
+}
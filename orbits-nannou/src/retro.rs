@@ -0,0 +1,193 @@
+//! Retro output: pixelating the frame into blocks, dithering it to a few colors, and bending it
+//! onto the curved, scanlined glass of a CRT, each a pass of its own, in that order.
+
+use std::sync::Arc;
+
+use nannou::image::{GrayImage, Luma};
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::gpu::{Filter, Post};
+
+/// The most colors a palette can have, as in `dither.wgsl`.
+const MAX_PALETTE: usize = 16;
+/// Texels along each side of the blue noise.
+const BLUE_NOISE_SIZE: usize = 64;
+
+/// The pattern dithering follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Dither {
+    #[default]
+    None,
+    /// An 8x8 Bayer matrix, in the crosshatch of old games.
+    Ordered,
+    /// Blue noise, which looks like fine, even grain.
+    BlueNoise,
+}
+
+/// The `[retro]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetroConfig {
+    /// Pixels along each side of the blocks the frame is pixelated into, or 1 for none.
+    pub pixelate: u32,
+    pub dither: Dither,
+    /// Up to 16 colors to dither to, as hex like `"#1a1c2c"`. With none, each channel is dithered
+    /// to `levels` levels instead.
+    pub palette: Vec<String>,
+    pub levels: u32,
+    /// How much the frame bulges, as on the glass of a CRT, from 0, for flat.
+    pub curvature: f32,
+    /// How dark the gaps between scanlines are, from 0, for none, to 1.
+    pub scanlines: f32,
+}
+
+impl Default for RetroConfig {
+    fn default() -> Self {
+        RetroConfig {
+            pixelate: 1,
+            dither: Dither::default(),
+            palette: Vec::new(),
+            levels: 4,
+            curvature: 0.0,
+            scanlines: 0.0,
+        }
+    }
+}
+
+/// The retro passes the config turns on.
+#[derive(Clone, Default)]
+pub struct Retro {
+    passes: Vec<Filter>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PixelateUniforms {
+    block: f32,
+    _padding: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DitherUniforms {
+    pattern: u32,
+    levels: f32,
+    palette_len: u32,
+    block: f32,
+    palette: [[f32; 4]; MAX_PALETTE],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CrtUniforms {
+    curvature: f32,
+    scanlines: f32,
+    _padding: [f32; 2],
+}
+
+impl Retro {
+    pub fn new(config: &RetroConfig) -> Result<Self, String> {
+        // the passes that don't sample a texture are given a blank one
+        let blank = Arc::new(GrayImage::new(1, 1));
+        let block = config.pixelate.max(1) as f32;
+        let mut passes = Vec::new();
+        if config.pixelate > 1 {
+            let uniforms = PixelateUniforms {
+                block,
+                _padding: [0.0; 3],
+            };
+            let shader = include_str!("shaders/pixelate.wgsl");
+            passes.push(Filter::new("pixelate", shader, blank.clone(), &uniforms));
+        }
+        if config.dither != Dither::None {
+            if config.palette.len() > MAX_PALETTE {
+                return Err(format!(
+                    "a retro palette can have at most {MAX_PALETTE} colors, not {}",
+                    config.palette.len()
+                ));
+            }
+            let mut palette = [[0.0; 4]; MAX_PALETTE];
+            for (color, hex) in palette.iter_mut().zip(&config.palette) {
+                let [r, g, b] = parse_hex(hex)?;
+                *color = [r, g, b, 1.0];
+            }
+            let uniforms = DitherUniforms {
+                pattern: (config.dither == Dither::BlueNoise) as u32,
+                levels: config.levels.max(2) as f32,
+                palette_len: config.palette.len() as u32,
+                block,
+                palette,
+            };
+            let noise = match config.dither {
+                Dither::BlueNoise => Arc::new(blue_noise()),
+                _ => blank.clone(),
+            };
+            let shader = include_str!("shaders/dither.wgsl");
+            passes.push(Filter::new("dither", shader, noise, &uniforms));
+        }
+        if config.curvature > 0.0 || config.scanlines > 0.0 {
+            let uniforms = CrtUniforms {
+                curvature: config.curvature,
+                scanlines: config.scanlines.clamp(0.0, 1.0),
+                _padding: [0.0; 2],
+            };
+            let shader = include_str!("shaders/crt.wgsl");
+            passes.push(Filter::new("crt", shader, blank, &uniforms));
+        }
+        Ok(Retro { passes })
+    }
+
+    /// The passes, in the order they're drawn through.
+    pub fn passes(&self) -> impl Iterator<Item = &dyn Post> {
+        self.passes.iter().map(|pass| pass as &dyn Post)
+    }
+}
+
+/// A color given as hex like `"#1a1c2c"`, as sRGB channels from 0 to 1.
+fn parse_hex(hex: &str) -> Result<[f32; 3], String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let channel = |i: usize| {
+        digits
+            .get(i..i + 2)
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+    };
+    match (digits.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b].map(|c| c as f32 / 255.0)),
+        _ => Err(format!("{hex:?} isn't a color like \"#1a1c2c\"")),
+    }
+}
+
+/// Tileable blue noise: every texel ranked by when it was picked, each pick the texel farthest
+/// from all those before it, so thresholds at any level spread out evenly.
+fn blue_noise() -> GrayImage {
+    const N: usize = BLUE_NOISE_SIZE;
+    const RADIUS: isize = 6;
+    const SIGMA: f32 = 1.5;
+    let mut rng = StdRng::seed_from_u64(0);
+    // a little noise to break the ties between texels no pick is near yet
+    let mut energy: Vec<f32> = (0..N * N).map(|_| rng.gen_range(0.0..1e-3)).collect();
+    let mut rank = vec![None; N * N];
+    for picked in 0..N * N {
+        let void = (0..N * N)
+            .filter(|&i| rank[i].is_none())
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap();
+        rank[void] = Some(picked);
+        let (x, y) = ((void % N) as isize, (void / N) as isize);
+        for dy in -RADIUS..=RADIUS {
+            for dx in -RADIUS..=RADIUS {
+                let i = (y + dy).rem_euclid(N as isize) as usize * N
+                    + (x + dx).rem_euclid(N as isize) as usize;
+                energy[i] += (-((dx * dx + dy * dy) as f32) / (2.0 * SIGMA * SIGMA)).exp();
+            }
+        }
+    }
+    GrayImage::from_fn(N as u32, N as u32, |x, y| {
+        let rank = rank[y as usize * N + x as usize].unwrap();
+        Luma([(rank * 256 / (N * N)) as u8])
+    })
+}
@@ -0,0 +1,290 @@
+//! Keeps the last stretch of the run, so a slice of it can be marked on the rewind timeline and
+//! exported as a clip after the fact: the whole state every so often, and every change and the
+//! parameters in between, for the slice to be played back from the state before it and rendered
+//! frame by frame at a frame rate of its own, without the overlays, as `--record` would have.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use orbits_core::params::Params;
+use orbits_core::session::Change;
+use orbits_core::state::Snapshot;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::baked::Baked;
+use crate::clock::Mode;
+use crate::export::{self, Export, ExportConfig};
+use crate::style::Style;
+use crate::transition::TransitionConfig;
+
+/// Frames in a row simulated time can stand still for before a clip is given up on, as it would
+/// otherwise never reach its out point.
+const STALLED_FRAMES: usize = 120;
+
+/// The `[rewind]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct RewindConfig {
+    /// Simulated seconds of the run kept to mark a clip in, or 0 to keep none.
+    pub seconds: f32,
+    /// Simulated seconds between the states kept, each a place a clip can be played back from.
+    /// The fewer there are the less memory they take, and the longer a clip takes to get to its
+    /// in point.
+    pub interval: f32,
+    /// The directory each clip gets a directory of frames in.
+    pub clips: PathBuf,
+    /// Frames to each simulated second of a clip, unless the panel picks another rate.
+    pub fps: f32,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        RewindConfig {
+            seconds: 60.0,
+            interval: 2.0,
+            clips: PathBuf::from("clips"),
+            fps: 60.0,
+        }
+    }
+}
+
+/// What happened over one frame of the run.
+#[derive(Clone)]
+struct Moment {
+    /// Simulated seconds since the start of the run, at the start of the frame.
+    time: f32,
+    /// The changes made before the frame was stepped.
+    changes: Vec<Change>,
+    params: Params,
+}
+
+/// The stretch of the run kept, and the slice of it marked.
+pub struct Rewind {
+    config: RewindConfig,
+    /// How the clips' frames are rendered and written, as PNGs whatever the export's format.
+    export: ExportConfig,
+    transition: TransitionConfig,
+    /// States kept, oldest first, each with a moment for every frame after it.
+    states: VecDeque<Snapshot>,
+    moments: VecDeque<Moment>,
+    /// Changes made since the last moment was kept.
+    changes: Vec<Change>,
+    /// Where on the timeline the cursor is, in simulated seconds since the start of the run.
+    pub cursor: f32,
+    /// Where the slice marked starts and ends, if they've been marked.
+    pub marks: [Option<f32>; 2],
+    /// Frames to each simulated second of the clips exported.
+    pub fps: f32,
+}
+
+impl Rewind {
+    pub fn new(
+        config: RewindConfig,
+        export: ExportConfig,
+        transition: TransitionConfig,
+    ) -> Result<Self, String> {
+        if config.interval <= 0.0 {
+            return Err(format!(
+                "the rewind's interval has to be positive, not {}",
+                config.interval
+            ));
+        }
+        if config.fps <= 0.0 {
+            return Err(format!(
+                "the rewind's fps has to be positive, not {}",
+                config.fps
+            ));
+        }
+        Ok(Rewind {
+            fps: config.fps,
+            config,
+            // a clip is played back for the export alone, so is never hurried by dropping frames
+            export: ExportConfig {
+                format: export::Format::Png,
+                backpressure: export::Backpressure::Wait,
+                ..export
+            },
+            transition,
+            states: VecDeque::new(),
+            moments: VecDeque::new(),
+            changes: Vec::new(),
+            cursor: 0.0,
+            marks: [None, None],
+        })
+    }
+
+    /// Whether anything is kept at all.
+    pub fn is_on(&self) -> bool {
+        self.config.seconds > 0.0
+    }
+
+    /// The first and last moments on the timeline, once there are any.
+    pub fn span(&self) -> Option<(f32, f32)> {
+        let first = self.states.front()?.time;
+        let last = self.moments.back().map_or(first, |moment| moment.time);
+        Some((first, last))
+    }
+
+    /// Note `change`, made to the run, for the moment it's made in.
+    pub fn note(&mut self, change: &Change) {
+        if self.is_on() {
+            self.changes.push(change.clone());
+        }
+    }
+
+    /// Whether a state is due to be kept at `time`.
+    pub fn wants_state(&self, time: f32) -> bool {
+        self.is_on()
+            && self
+                .states
+                .back()
+                .is_none_or(|state| time - state.time >= self.config.interval)
+    }
+
+    /// Keep `state`, the run as it is now, to play clips back from, before the frame it's taken
+    /// in is kept.
+    pub fn keep_state(&mut self, state: Snapshot) {
+        self.states.push_back(state);
+    }
+
+    /// Keep the frame about to be stepped from `time` at `params`, letting go of what's fallen
+    /// off the end of the timeline.
+    pub fn keep(&mut self, time: f32, params: &Params) {
+        if !self.is_on() || self.states.is_empty() {
+            return;
+        }
+        self.moments.push_back(Moment {
+            time,
+            changes: std::mem::take(&mut self.changes),
+            params: params.clone(),
+        });
+        // the oldest state is let go of once the next is far enough back on its own
+        while self
+            .states
+            .get(1)
+            .is_some_and(|next| time - next.time >= self.config.seconds)
+        {
+            self.states.pop_front();
+        }
+        let first = self.states.front().unwrap().time;
+        while self
+            .moments
+            .front()
+            .is_some_and(|moment| moment.time <= first)
+        {
+            self.moments.pop_front();
+        }
+        let (first, last) = self.span().unwrap();
+        self.cursor = self.cursor.clamp(first, last);
+        for mark in &mut self.marks {
+            *mark = mark.filter(|&mark| mark >= first);
+        }
+    }
+
+    /// Let go of everything kept, as the run has jumped somewhere else.
+    pub fn forget(&mut self) {
+        self.states.clear();
+        self.moments.clear();
+        self.changes.clear();
+        self.marks = [None, None];
+    }
+
+    /// The marked slice, to be played back from the last state kept before it and rendered in
+    /// `style` into `dir`, or a directory of its own among the clips.
+    pub fn clip(&self, style: Style, dir: Option<PathBuf>) -> Result<Clip, String> {
+        let [Some(from), Some(to)] = self.marks else {
+            return Err("mark where the clip goes in and out first".into());
+        };
+        if to <= from {
+            return Err(format!(
+                "the clip goes out at {to:.1}s, before it goes in at {from:.1}s"
+            ));
+        }
+        let start = (self.states.iter().rev())
+            .find(|state| state.time <= from)
+            .ok_or("the clip goes in before the start of the timeline")?;
+        let moments = (self.moments.iter())
+            .filter(|moment| moment.time > start.time)
+            .take_while(|moment| moment.time <= to)
+            .cloned()
+            .collect();
+        let dir = dir.unwrap_or_else(|| {
+            (0..)
+                .map(|i| self.config.clips.join(format!("clip-{i:03}")))
+                .find(|dir| !dir.exists())
+                .unwrap()
+        });
+        let export = Export::new(Some(dir.clone()), None, self.export, self.transition, style)?;
+        Ok(Clip {
+            start: start.clone(),
+            moments,
+            from,
+            to,
+            dir,
+            export,
+            fps: self.fps,
+            stalled: 0,
+        })
+    }
+}
+
+/// A slice of the run being played back and exported.
+pub struct Clip {
+    /// The state the slice is played back from, at or before its in point.
+    pub start: Snapshot,
+    moments: VecDeque<Moment>,
+    pub from: f32,
+    pub to: f32,
+    /// The directory its frames go in.
+    pub dir: PathBuf,
+    pub export: Export,
+    pub fps: f32,
+    /// Frames in a row simulated time has stood still for.
+    stalled: usize,
+}
+
+impl Clip {
+    /// The changes made by `time`, and the parameters the run had then, if any moments were
+    /// reached.
+    pub fn due(&mut self, time: f32) -> (Vec<Change>, Option<Params>) {
+        let mut changes = Vec::new();
+        let mut params = None;
+        while let Some(moment) = self.moments.front() {
+            if moment.time > time {
+                break;
+            }
+            let moment = self.moments.pop_front().unwrap();
+            changes.extend(moment.changes);
+            params = Some(moment.params);
+        }
+        (changes, params)
+    }
+
+    /// Whether the frame at `time` is in the slice.
+    pub fn shows(&self, time: f32) -> bool {
+        (self.from..=self.to).contains(&time)
+    }
+
+    /// Whether the slice has been played back as far as it goes, `delta_seconds` having been
+    /// simulated over the last frame.
+    pub fn is_done(&mut self, time: f32, delta_seconds: f32) -> bool {
+        self.stalled = if delta_seconds > 0.0 {
+            0
+        } else {
+            self.stalled + 1
+        };
+        time >= self.to || self.stalled >= STALLED_FRAMES
+    }
+}
+
+/// A clip being exported, and the run to go back to once it's done.
+pub struct Playback {
+    pub clip: Clip,
+    /// The run as it was when the clip was started.
+    pub live: Snapshot,
+    /// The layers baked then, which the run's state leaves out.
+    pub baked: Baked,
+    /// How the run's frames were being clocked then.
+    pub clocked: Mode,
+}
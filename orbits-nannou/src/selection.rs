@@ -0,0 +1,159 @@
+//! Picking out particles of the current generation to do things to together: those in a box
+//! dragged across the window with shift held, or those a filter lets through, like every particle
+//! on an orbit more eccentric than 0.5. A pick can be tagged with a name to be picked again later.
+//! Recoloring, freezing, boosting and deleting what's picked are changes like any other, so
+//! they're logged, replayed and synced.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use nannou::prelude::*;
+use orbits_core::generation::Generation;
+use orbits_core::physics::{self, Particle};
+
+/// What a filter picks particles by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    /// Of the orbit the particle is on, as if the pull were of one mass at the origin.
+    Eccentricity,
+    /// World units a second.
+    Speed,
+    /// World units from the origin.
+    Distance,
+}
+
+impl Property {
+    pub const ALL: [Property; 3] = [Property::Eccentricity, Property::Speed, Property::Distance];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Property::Eccentricity => "eccentricity",
+            Property::Speed => "speed",
+            Property::Distance => "distance",
+        }
+    }
+
+    /// The property of `particle`, pulled on by a total of `gm`.
+    fn of(self, particle: &Particle, gm: f32) -> f32 {
+        match self {
+            Property::Eccentricity => physics::eccentricity(particle.pos, particle.vel, gm),
+            Property::Speed => particle.vel.length(),
+            Property::Distance => particle.pos.length(),
+        }
+    }
+}
+
+/// Picks the particles whose property is above, or below, a threshold.
+#[derive(Debug, Clone, Copy)]
+pub struct Filter {
+    pub property: Property,
+    pub above: bool,
+    pub threshold: f32,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            property: Property::Eccentricity,
+            above: true,
+            threshold: 0.5,
+        }
+    }
+}
+
+impl Filter {
+    fn matches(&self, particle: &Particle, gm: f32) -> bool {
+        let value = self.property.of(particle, gm);
+        if self.above {
+            value > self.threshold
+        } else {
+            value < self.threshold
+        }
+    }
+}
+
+/// The particles picked, and the picks tagged, all by index into the current generation.
+#[derive(Debug, Default)]
+pub struct Selection {
+    pub particles: BTreeSet<usize>,
+    pub tags: BTreeMap<String, BTreeSet<usize>>,
+    /// Where in the world the box being dragged out was started.
+    pub boxing: Option<Point2>,
+}
+
+impl Selection {
+    /// The particles picked, in order.
+    pub fn indices(&self) -> Vec<usize> {
+        self.particles.iter().copied().collect()
+    }
+
+    /// Pick the particles of `generation` inside `rect`, a region of the world.
+    pub fn pick_box(&mut self, generation: &Generation, rect: Rect) {
+        self.pick(generation, |particle| rect.contains(particle.pos));
+    }
+
+    /// Pick the particles of `generation` that `filter` lets through, pulled on by a total of `gm`.
+    pub fn pick_filter(&mut self, generation: &Generation, filter: Filter, gm: f32) {
+        self.pick(generation, |particle| filter.matches(particle, gm));
+    }
+
+    fn pick(&mut self, generation: &Generation, picked: impl Fn(&Particle) -> bool) {
+        self.particles = (generation.particles.iter().enumerate())
+            .filter(|(_, particle)| picked(particle))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Tag what's picked as `name`, in place of whatever was tagged so before.
+    pub fn tag(&mut self, name: &str) {
+        self.tags.insert(name.into(), self.particles.clone());
+    }
+
+    /// Pick what was tagged `name` again.
+    pub fn recall(&mut self, name: &str) {
+        if let Some(particles) = self.tags.get(name) {
+            self.particles = particles.clone();
+        }
+    }
+
+    /// Forget the particles at `deleted`, moving those after each a place forward as the
+    /// generation does.
+    pub fn delete(&mut self, deleted: &[usize]) {
+        let deleted: BTreeSet<usize> = deleted.iter().copied().collect();
+        let shift = |particles: &BTreeSet<usize>| -> BTreeSet<usize> {
+            (particles.difference(&deleted))
+                .map(|&i| i - deleted.range(..i).count())
+                .collect()
+        };
+        self.particles = shift(&self.particles);
+        for particles in self.tags.values_mut() {
+            *particles = shift(particles);
+        }
+    }
+
+    /// Make room for particles gone in at `inserted`, in order, by where they are now, moving
+    /// those at or after each a place back as the generation does.
+    pub fn insert(&mut self, inserted: &[usize]) {
+        let shift = |particles: &BTreeSet<usize>| -> BTreeSet<usize> {
+            (particles.iter())
+                .map(|&i| {
+                    let mut at = i;
+                    for &new in inserted {
+                        if new <= at {
+                            at += 1;
+                        }
+                    }
+                    at
+                })
+                .collect()
+        };
+        self.particles = shift(&self.particles);
+        for particles in self.tags.values_mut() {
+            *particles = shift(particles);
+        }
+    }
+
+    /// Forget everything picked and tagged, the particles they were of being gone.
+    pub fn reset(&mut self) {
+        *self = Selection::default();
+    }
+}
@@ -0,0 +1,58 @@
+//! `--selftest`: the whole piece run briefly in a hidden window, to check that a build works on a
+//! machine, or that a refactor didn't break it, without having to watch it.
+
+use orbits_core::generation::Generation;
+
+use crate::export::Captured;
+
+/// Seed a self test runs with, unless given another.
+pub const SEED: u64 = 1;
+/// Updates a self test runs before rendering its frame.
+pub const STEPS: usize = 300;
+
+/// A self test under way.
+#[derive(Debug, Default)]
+pub struct SelfTest {
+    /// Updates run so far.
+    pub steps: usize,
+}
+
+/// Check that every particle of `generations` is still somewhere, going somewhere, and never had
+/// to be respawned for going nowhere.
+pub fn check_particles(generations: &[Generation]) -> Result<(), String> {
+    for (g, generation) in generations.iter().enumerate() {
+        if generation.respawns > 0 {
+            return Err(format!(
+                "{} particles of generation {g} were respawned",
+                generation.respawns
+            ));
+        }
+        for (i, particle) in generation.particles.iter().enumerate() {
+            if !(particle.pos.is_finite() && particle.vel.is_finite()) {
+                return Err(format!(
+                    "particle {i} of generation {g} is at {} going {}",
+                    particle.pos, particle.vel
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that a frame was rendered, and to pixels that are all numbers.
+pub fn check_frame(frame: Option<&Captured>) -> Result<(), String> {
+    let pixels = frame.ok_or("the frame couldn't be rendered")?.pixels();
+    if pixels.is_empty() {
+        return Err("the frame rendered empty".into());
+    }
+    match pixels
+        .iter()
+        .position(|pixel| pixel.iter().any(|c| !c.is_finite()))
+    {
+        Some(i) => Err(format!(
+            "pixel {i} of the frame rendered as {:?}",
+            pixels[i]
+        )),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,215 @@
+//! Renders a numbered series of editions, for a run of prints: a manifest lists the seeds or
+//! tokens picked out for it, each edition overriding what it needs to of the config, and every
+//! edition is rendered the same way, at the same size after the same number of steps, into files
+//! named for the series and numbered in the manifest's order. The series written has a manifest
+//! of its own, `manifest.json` in the directory it's written to, recording how each edition was
+//! made.
+//!
+//! ```toml
+//! name = "orbits"
+//! config = "trynannou.toml"
+//! size = [3840, 2160]
+//! steps = 600
+//!
+//! [[editions]]
+//! seed = 42
+//!
+//! [[editions]]
+//! token = "0x3f5a…"
+//! overrides = { simulation = { palette = "magma" } }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use orbits_core::settings::Settings;
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::assets;
+use crate::batch;
+use crate::compare::FRAME_SECONDS;
+use crate::config::{self, CONFIG_PATH};
+use crate::export;
+use crate::manifest::{self, Manifest};
+use crate::token::Token;
+
+/// A series' manifest.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Series {
+    /// What every edition's file is named for, as `<name>-001.png` and on.
+    pub name: String,
+    /// The config every edition is rendered under, relative to the manifest, or the one in the
+    /// working directory if left out.
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+    /// Size of every edition, in pixels.
+    #[serde(default = "default_size")]
+    pub size: [u32; 2],
+    /// Steps simulated before every edition is taken.
+    #[serde(default = "default_steps")]
+    pub steps: usize,
+    pub editions: Vec<Edition>,
+}
+
+/// One of a series' editions.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Edition {
+    /// The seed it's rendered from, if it's not from a token.
+    pub seed: Option<u64>,
+    /// The token whose hash it's made from, as `--token` makes the piece from it.
+    pub token: Option<String>,
+    /// What it changes of the config, as sections of the config file.
+    #[serde(default)]
+    pub overrides: Table,
+}
+
+fn default_size() -> [u32; 2] {
+    batch::DEFAULT_SIZE
+}
+
+fn default_steps() -> usize {
+    batch::DEFAULT_STEPS
+}
+
+impl Series {
+    /// The series in the manifest at `path`, its config found relative to it.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let err = |err: &dyn std::fmt::Display| format!("{}: {err}", path.display());
+        let text = fs::read_to_string(path).map_err(|e| err(&e))?;
+        let mut series: Series = toml::from_str(&text).map_err(|e| err(&e))?;
+        if series.editions.is_empty() {
+            return Err(err(&"a series needs at least one edition"));
+        }
+        for (i, edition) in series.editions.iter().enumerate() {
+            match (edition.seed, &edition.token) {
+                (Some(_), None) => {}
+                (None, Some(token)) => {
+                    Token::parse(token).map_err(|e| err(&format!("edition {}: {e}", i + 1)))?;
+                }
+                _ => {
+                    return Err(err(&format!(
+                        "edition {} needs either a seed or a token",
+                        i + 1
+                    )))
+                }
+            }
+        }
+        let dir = path.parent().unwrap_or(Path::new(""));
+        series.config = series.config.map(|config| dir.join(config));
+        Ok(series)
+    }
+
+    /// Render every edition into `out`, saying as each is written.
+    pub fn render(&self, out: &Path) -> Result<(), String> {
+        let config_path = (self.config.clone()).unwrap_or_else(|| assets::resolve(CONFIG_PATH));
+        let base = match fs::read_to_string(&config_path) {
+            Ok(text) => text
+                .parse::<Table>()
+                .map_err(|err| format!("{}: {err}", config_path.display()))?,
+            Err(_) if self.config.is_none() => Table::new(),
+            Err(err) => return Err(format!("{}: {err}", config_path.display())),
+        };
+        export::create_dir(out)?;
+        let digits = self.editions.len().to_string().len().max(3);
+        let scratch = std::env::temp_dir().join(format!("trynannou-series-{}", std::process::id()));
+        let (mut configs, mut seeds, mut written) = (Vec::new(), Vec::new(), Vec::new());
+        let result: Result<(), String> =
+            self.editions
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, edition)| {
+                    let number = i + 1;
+                    let err = |err: String| format!("edition {number}: {err}");
+                    let (config, seed) = edition.config(&base).map_err(err)?;
+                    let path = out.join(format!("{}-{number:0digits$}.png", self.name));
+                    self.render_edition(&config, seed, &scratch, &path)
+                        .map_err(err)?;
+                    println!("{}", path.display());
+                    configs.push(toml::to_string(&config).map_err(|err| err.to_string())?);
+                    seeds.push(seed);
+                    written.push(path);
+                    Ok(())
+                });
+        let _ = fs::remove_dir_all(&scratch);
+        result?;
+        let manifest = Manifest::new(configs)
+            .seeds(seeds)
+            .time(self.steps as f32 * FRAME_SECONDS);
+        manifest.write(&manifest::within(out), &written)
+    }
+
+    /// Render `seed` under `config` to `path`, with a run of the `batch` subcommand in `scratch`.
+    fn render_edition(
+        &self,
+        config: &Table,
+        seed: u64,
+        scratch: &Path,
+        path: &Path,
+    ) -> Result<(), String> {
+        export::create_dir(scratch)?;
+        let config_path = scratch.join("config.toml");
+        let text = toml::to_string(config).map_err(|err| err.to_string())?;
+        fs::write(&config_path, text).map_err(|err| format!("{}: {err}", config_path.display()))?;
+        config::check(&config_path)?;
+        // each edition takes a run of its own, as the settings can only be set once a process
+        let exe = std::env::current_exe().map_err(|err| err.to_string())?;
+        let [width, height] = self.size;
+        let status = Command::new(exe)
+            .arg("batch")
+            .arg(scratch)
+            .args(["--seeds", &seed.to_string()])
+            .args(["--steps", &self.steps.to_string()])
+            .args(["--size", &format!("{width}x{height}")])
+            .arg("--config")
+            .arg(&config_path)
+            .status()
+            .map_err(|err| err.to_string())?;
+        if !status.success() {
+            return Err(format!("failed to render seed {seed}"));
+        }
+        let rendered = scratch.join(format!("{seed}.png"));
+        fs::copy(&rendered, path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let _ = fs::remove_file(&rendered);
+        Ok(())
+    }
+}
+
+impl Edition {
+    /// The config it's rendered under, `base` with its overrides and its token's choices, and the
+    /// seed it's rendered from.
+    fn config(&self, base: &Table) -> Result<(Table, u64), String> {
+        let mut config = base.clone();
+        merge(&mut config, &self.overrides);
+        let Some(token) = &self.token else {
+            return Ok((config, self.seed.unwrap()));
+        };
+        let token = Token::parse(token)?;
+        let simulation = (config.entry("simulation"))
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .ok_or("the simulation section has to be a table")?;
+        let mut settings: Settings = Value::Table(simulation.clone())
+            .try_into()
+            .map_err(|err| format!("the simulation section: {err}"))?;
+        token.apply(&mut settings);
+        simulation.insert("palette".into(), Value::from(settings.palette));
+        simulation.insert("particles".into(), Value::from(settings.particles as i64));
+        Ok((config, token.seed()))
+    }
+}
+
+/// Lay `overrides` over `config`, table by table, its values replacing those in `config`.
+fn merge(config: &mut Table, overrides: &Table) {
+    for (key, value) in overrides {
+        match (config.get_mut(key), value) {
+            (Some(Value::Table(table)), Value::Table(overrides)) => merge(table, overrides),
+            _ => {
+                config.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
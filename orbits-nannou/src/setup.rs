@@ -0,0 +1,345 @@
+//! The setup shown the first time the piece is run, before there's a config: a window over the
+//! piece asking how to show it, how many particles to draw and in what colors, whose answers are
+//! written to the config file for the piece to start again with. How many particles the machine
+//! draws smoothly is measured while it's shown, by drawing more of them until frames run late.
+
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+use nannou_egui::egui::{self, Color32, CtxRef};
+use orbits_core::params::Param;
+use orbits_core::session::Change;
+use toml::{Table, Value};
+
+use crate::assets;
+use crate::config::{WindowConfig, CONFIG_PATH};
+
+/// Particles drawn in turn while measuring, the most drawn smoothly being suggested.
+const TIERS: [usize; 5] = [16, 64, 128, 256, 512];
+/// Seconds each tier is drawn for while measuring, the first half to settle in and the second
+/// timed.
+const TIER_SECONDS: f32 = 2.0;
+/// Longest frames can take on average for a tier to count as drawn smoothly, in seconds.
+const SMOOTH_FRAME: f32 = 1.0 / 50.0;
+/// Sizes the window can be shown at, in points.
+const SIZES: [[u32; 2]; 4] = [[1024, 768], [1280, 720], [1600, 900], [1920, 1080]];
+
+/// Colors for the trails and background, as the `[simulation]` settings of the same names have
+/// them.
+struct Palette {
+    name: &'static str,
+    hue_run: [f32; 2],
+    trail_saturation: f32,
+    trail_lightness: f32,
+    background_saturation: f32,
+    background_lightness: f32,
+}
+
+const PALETTES: [Palette; 4] = [
+    Palette {
+        name: "dusk",
+        hue_run: [0.2, 0.4],
+        trail_saturation: 0.5,
+        trail_lightness: 0.5,
+        background_saturation: 0.38,
+        background_lightness: 0.33,
+    },
+    Palette {
+        name: "vivid",
+        hue_run: [0.4, 0.8],
+        trail_saturation: 0.9,
+        trail_lightness: 0.55,
+        background_saturation: 0.5,
+        background_lightness: 0.15,
+    },
+    Palette {
+        name: "pastel",
+        hue_run: [0.15, 0.3],
+        trail_saturation: 0.4,
+        trail_lightness: 0.75,
+        background_saturation: 0.25,
+        background_lightness: 0.6,
+    },
+    Palette {
+        name: "silver",
+        hue_run: [0.0, 0.05],
+        trail_saturation: 0.08,
+        trail_lightness: 0.8,
+        background_saturation: 0.1,
+        background_lightness: 0.08,
+    },
+];
+
+/// The answers given so far, and how far the measuring has got.
+pub struct Setup {
+    size: usize,
+    fullscreen: bool,
+    /// Of `TIERS`.
+    particles: usize,
+    /// Of `PALETTES`.
+    palette: usize,
+    measuring: Measuring,
+    /// Why the config couldn't be written, when it couldn't.
+    error: Option<String>,
+}
+
+/// Timing of the tier being drawn.
+struct Measuring {
+    /// Of `TIERS`, past the last when done.
+    tier: usize,
+    /// Whether the tier's particles have been asked for yet.
+    asked: bool,
+    elapsed: f32,
+    timed: f32,
+    frames: u32,
+}
+
+impl Measuring {
+    /// The `tier`th of `TIERS`, before it's drawn.
+    fn of(tier: usize) -> Self {
+        Measuring {
+            tier,
+            asked: false,
+            elapsed: 0.0,
+            timed: 0.0,
+            frames: 0,
+        }
+    }
+}
+
+/// What the setup asks of the rest of the app this frame.
+#[derive(Default)]
+pub struct SetupOutput {
+    pub changes: Vec<Change>,
+    /// How the window's to be shown, when that's just been picked.
+    pub window: Option<WindowConfig>,
+    /// Whether the config has been written, for the piece to start again with it.
+    pub restart: bool,
+}
+
+impl Setup {
+    /// The setup as it starts, measuring from the fewest particles up.
+    pub fn new() -> Self {
+        Setup {
+            size: 1,
+            fullscreen: false,
+            particles: 0,
+            palette: 0,
+            measuring: Measuring::of(0),
+            error: None,
+        }
+    }
+
+    /// Show the setup, the last frame having taken `frame_seconds`.
+    pub fn show(&mut self, ctx: &CtxRef, frame_seconds: f32) -> SetupOutput {
+        let mut output = SetupOutput::default();
+        self.measure(frame_seconds, &mut output);
+        let measuring = self.measuring.tier < TIERS.len();
+        let (size, fullscreen, particles) = (self.size, self.fullscreen, self.particles);
+        egui::Window::new("welcome to trynannou")
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label("A few questions before the piece starts for real.");
+                ui.separator();
+                ui.checkbox(&mut self.fullscreen, "fill the whole display");
+                ui.add_enabled_ui(!self.fullscreen, |ui| {
+                    egui::ComboBox::from_label("window size")
+                        .selected_text(size_label(SIZES[self.size]))
+                        .show_ui(ui, |ui| {
+                            for (i, &size) in SIZES.iter().enumerate() {
+                                ui.selectable_value(&mut self.size, i, size_label(size));
+                            }
+                        });
+                });
+                if measuring {
+                    let progress = (self.measuring.tier as f32
+                        + self.measuring.elapsed / TIER_SECONDS)
+                        / TIERS.len() as f32;
+                    ui.add(
+                        egui::ProgressBar::new(progress)
+                            .text(format!("trying {} particles", TIERS[self.measuring.tier])),
+                    );
+                } else {
+                    egui::ComboBox::from_label("particles")
+                        .selected_text(TIERS[self.particles].to_string())
+                        .show_ui(ui, |ui| {
+                            for (i, tier) in TIERS.iter().enumerate() {
+                                ui.selectable_value(&mut self.particles, i, tier.to_string());
+                            }
+                        });
+                    ui.label("as many as this machine drew smoothly");
+                }
+                egui::ComboBox::from_label("palette")
+                    .selected_text(PALETTES[self.palette].name)
+                    .show_ui(ui, |ui| {
+                        for (i, palette) in PALETTES.iter().enumerate() {
+                            ui.selectable_value(&mut self.palette, i, palette.name);
+                        }
+                    });
+                ui.label("colors take effect once the piece starts");
+                ui.separator();
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, error);
+                }
+                let path = config_path();
+                if ui
+                    .add_enabled(!measuring, egui::Button::new("save and start"))
+                    .on_hover_text(format!("writes {}", path.display()))
+                    .clicked()
+                {
+                    match self.save(&path) {
+                        Ok(()) => output.restart = true,
+                        Err(err) => self.error = Some(err),
+                    }
+                }
+            });
+        if (self.size, self.fullscreen) != (size, fullscreen) {
+            output.window = Some(self.window());
+        }
+        if !measuring && self.particles != particles {
+            output.changes.push(set_particles(TIERS[self.particles]));
+        }
+        output
+    }
+
+    /// Time the frame just drawn, moving on to the next tier once this one's been timed, and
+    /// settling on the most drawn smoothly once one isn't.
+    fn measure(&mut self, frame_seconds: f32, output: &mut SetupOutput) {
+        let measuring = &mut self.measuring;
+        if measuring.tier >= TIERS.len() {
+            return;
+        }
+        if !measuring.asked {
+            output.changes.push(set_particles(TIERS[measuring.tier]));
+            measuring.asked = true;
+        }
+        measuring.elapsed += frame_seconds;
+        if measuring.elapsed > TIER_SECONDS / 2.0 {
+            measuring.timed += frame_seconds;
+            measuring.frames += 1;
+        }
+        if measuring.elapsed < TIER_SECONDS {
+            return;
+        }
+        let smooth = measuring.timed / measuring.frames.max(1) as f32 <= SMOOTH_FRAME;
+        if smooth {
+            self.particles = measuring.tier;
+        }
+        let tier = if smooth {
+            measuring.tier + 1
+        } else {
+            TIERS.len()
+        };
+        *measuring = Measuring::of(tier);
+        if tier >= TIERS.len() {
+            output.changes.push(set_particles(TIERS[self.particles]));
+        }
+    }
+
+    fn window(&self) -> WindowConfig {
+        WindowConfig {
+            size: Some(SIZES[self.size]),
+            fullscreen: self.fullscreen,
+        }
+    }
+
+    /// Write the answers into the config file at `path`, keeping whatever else it already has.
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let err = |err: &dyn std::fmt::Display| format!("{}: {err}", path.display());
+        let mut config: Table = match fs::read_to_string(path) {
+            Ok(text) => text.parse().map_err(|e| err(&e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Table::new(),
+            Err(e) => return Err(err(&e)),
+        };
+        let WindowConfig { size, fullscreen } = self.window();
+        let [width, height] = size.unwrap();
+        let window = section(&mut config, "window");
+        window.insert("size".into(), Value::from(vec![width, height]));
+        window.insert("fullscreen".into(), Value::from(fullscreen));
+
+        let palette = &PALETTES[self.palette];
+        let simulation = section(&mut config, "simulation");
+        simulation.insert(
+            "particles".into(),
+            Value::from(TIERS[self.particles] as i64),
+        );
+        let float = |value: f32| Value::from(value as f64);
+        let [least, most] = palette.hue_run;
+        simulation.insert(
+            "hue_run".into(),
+            Value::from(vec![float(least), float(most)]),
+        );
+        for (key, value) in [
+            ("trail_saturation", palette.trail_saturation),
+            ("trail_lightness", palette.trail_lightness),
+            ("background_saturation", palette.background_saturation),
+            ("background_lightness", palette.background_lightness),
+        ] {
+            simulation.insert(key.into(), float(value));
+        }
+        let text = toml::to_string(&config).map_err(|e| err(&e))?;
+        fs::write(path, text).map_err(|e| err(&e))
+    }
+}
+
+/// Where the config is written: over the one already in use, if there is one.
+fn config_path() -> PathBuf {
+    assets::resolve(CONFIG_PATH)
+}
+
+/// The table of `config` named `name`, added if it isn't there.
+fn section<'a>(config: &'a mut Table, name: &str) -> &'a mut Table {
+    let value = config
+        .entry(name)
+        .or_insert_with(|| Value::Table(Table::new()));
+    if !value.is_table() {
+        *value = Value::Table(Table::new());
+    }
+    value.as_table_mut().unwrap()
+}
+
+fn size_label([width, height]: [u32; 2]) -> String {
+    format!("{width} × {height}")
+}
+
+fn set_particles(particles: usize) -> Change {
+    Change::Set {
+        param: Param::Particles,
+        value: particles as f32,
+    }
+}
+
+/// Start the piece again as it was started, but for `--setup`, so it picks up the config just
+/// written, or under `config` instead of the config it was started under, if given, and from the
+/// state saved at `state`, if given.
+pub fn relaunch(config: Option<&Path>, state: Option<&Path>) -> Result<(), String> {
+    let exe = env::current_exe().map_err(|err| format!("couldn't start again: {err}"))?;
+    let mut args: Vec<OsString> = Vec::new();
+    let mut given = env::args().skip(1);
+    while let Some(arg) = given.next() {
+        match arg.as_str() {
+            "--setup" => {}
+            "--config" if config.is_some() => {
+                given.next();
+            }
+            "--load-state" if state.is_some() => {
+                given.next();
+            }
+            _ => args.push(arg.into()),
+        }
+    }
+    if let Some(config) = config {
+        args.extend(["--config".into(), config.as_os_str().to_owned()]);
+    }
+    if let Some(state) = state {
+        args.extend(["--load-state".into(), state.as_os_str().to_owned()]);
+    }
+    Command::new(exe)
+        .args(args)
+        .spawn()
+        .map(drop)
+        .map_err(|err| format!("couldn't start again: {err}"))
+}
@@ -0,0 +1,26 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    /// Frames in the exposure, this one included.
+    frames: f32;
+};
+
+[[group(0), binding(0)]]
+var exposure: texture_2d<f32>;
+[[group(0), binding(1)]]
+var scene: texture_2d<f32>;
+[[group(0), binding(2)]]
+var<uniform> uniforms: Uniforms;
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let texel = vec2<i32>(pos.xy);
+    let mean = textureLoad(exposure, texel, 0).rgb;
+    let frame = textureLoad(scene, texel, 0).rgb;
+    // a running mean, rather than a sum, so precision holds up over days
+    return vec4<f32>(mean + (frame - mean) / uniforms.frames, uniforms.frames);
+}
@@ -0,0 +1,57 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    /// Pixels across and down each glyph's tile.
+    cell: vec2<f32>;
+    /// How many glyphs the atlas has, side by side, darkest first.
+    glyphs: f32;
+};
+
+[[group(0), binding(0)]]
+var scene: texture_2d<f32>;
+[[group(0), binding(1)]]
+var atlas: texture_2d<f32>;
+[[group(0), binding(2)]]
+var atlas_sampler: sampler;
+[[group(0), binding(3)]]
+var<uniform> uniforms: Uniforms;
+
+fn encode(linear: f32) -> f32 {
+    if (linear <= 0.0031308) {
+        return linear * 12.92;
+    }
+    return 1.055 * pow(linear, 1.0 / 2.4) - 0.055;
+}
+
+fn decode(srgb: f32) -> f32 {
+    if (srgb <= 0.04045) {
+        return srgb / 12.92;
+    }
+    return pow((srgb + 0.055) / 1.055, 2.4);
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    // each tile takes the color in its middle, as a terminal cell has one
+    let size = textureDimensions(scene);
+    let tile = floor(pos.xy / uniforms.cell);
+    let middle = (tile + 0.5) * uniforms.cell;
+    let texel = textureLoad(scene, min(vec2<i32>(middle), size - vec2<i32>(1)), 0);
+    let straight = clamp(texel.rgb / max(texel.a, 0.0001), vec3<f32>(0.0), vec3<f32>(1.0));
+    let shown = vec3<f32>(encode(straight.r), encode(straight.g), encode(straight.b));
+
+    // as bright as the tile looks over black, the denser the glyph, as in orbits_core::ascii
+    let luminance = dot(shown, vec3<f32>(0.2126, 0.7152, 0.0722)) * texel.a;
+    let glyph = round(clamp(luminance, 0.0, 1.0) * (uniforms.glyphs - 1.0));
+    let local = pos.xy / uniforms.cell - tile;
+    let ink = textureSample(atlas, atlas_sampler, vec2<f32>((glyph + local.x) / uniforms.glyphs, local.y)).r;
+
+    // drawn in the tile's hue at full brightness, the glyph's density standing for how bright
+    let hue = shown / max(max(shown.r, max(shown.g, shown.b)), 0.0001);
+    let color = vec3<f32>(decode(hue.r), decode(hue.g), decode(hue.b));
+    return vec4<f32>(color * ink * texel.a, texel.a);
+}
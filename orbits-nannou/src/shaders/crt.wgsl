@@ -0,0 +1,36 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    curvature: f32;
+    scanlines: f32;
+};
+
+[[group(0), binding(0)]]
+var scene: texture_2d<f32>;
+[[group(0), binding(2)]]
+var scene_sampler: sampler;
+[[group(0), binding(3)]]
+var<uniform> uniforms: Uniforms;
+
+let TAU: f32 = 6.283185307179586;
+/// Pixels from one scanline to the next.
+let SCANLINE_PITCH: f32 = 3.0;
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let size = vec2<f32>(textureDimensions(scene));
+    // bulging like the glass of a tube, which pushes the corners off the edge
+    let centered = pos.xy / size * 2.0 - 1.0;
+    let bent = centered * (1.0 + uniforms.curvature * dot(centered, centered));
+    let uv = bent * 0.5 + 0.5;
+    let color = textureSample(scene, scene_sampler, uv);
+
+    let line = 0.5 + 0.5 * cos(uv.y * size.y / SCANLINE_PITCH * TAU);
+    let shade = 1.0 - uniforms.scanlines * line;
+    let inside = uv.x >= 0.0 && uv.x <= 1.0 && uv.y >= 0.0 && uv.y <= 1.0;
+    return select(vec4<f32>(0.0), vec4<f32>(color.rgb * shade, color.a), inside);
+}
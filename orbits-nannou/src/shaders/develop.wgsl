@@ -0,0 +1,104 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    background: vec3<f32>;
+    gain: f32;
+    /// 0 for film, 1 for log, 2 for equalized.
+    scale: u32;
+    /// 0 for none, then viridis, magma and inferno.
+    colormap: u32;
+    /// Equalized brightness for each of `BINS` bins of log luminance, four to an element.
+    equalized: array<vec4<f32>, 64>;
+};
+
+[[group(0), binding(0)]]
+var exposure: texture_2d<f32>;
+[[group(0), binding(1)]]
+var<uniform> uniforms: Uniforms;
+
+/// Luminance below this counts as nothing at all.
+let FAINTEST: f32 = 0.000001;
+/// Decades of luminance the log scale and the equalization bins span.
+let DECADES: f32 = 6.0;
+let BINS: f32 = 256.0;
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+/// Brightness from 0 to 1 for luminance `l`.
+fn brightness(l: f32) -> f32 {
+    if (l < FAINTEST) {
+        return 0.0;
+    }
+    let decade = log(l * uniforms.gain) / log(10.0);
+    if (uniforms.scale == 1u) {
+        return clamp(decade / DECADES + 1.0, 0.0, 1.0);
+    }
+    if (uniforms.scale == 2u) {
+        let bin = i32(clamp((log(l) / log(10.0) / DECADES + 1.0) * BINS, 0.0, BINS - 1.0));
+        return uniforms.equalized[bin / 4][bin % 4];
+    }
+    return 1.0 - exp(-l * uniforms.gain);
+}
+
+/// Matt Zucker's polynomial fits of the matplotlib colormaps.
+fn colormap(t: f32) -> vec3<f32> {
+    var c: array<vec3<f32>, 7>;
+    if (uniforms.colormap == 1u) {
+        c = array<vec3<f32>, 7>(
+            vec3<f32>(0.2777273272234177, 0.005407344544966578, 0.3340998053353061),
+            vec3<f32>(0.1050930431085774, 1.404613529898575, 1.384590162594685),
+            vec3<f32>(-0.3308618287255563, 0.214847559468213, 0.09509516302823659),
+            vec3<f32>(-4.634230498983486, -5.799100973351585, -19.33244095627987),
+            vec3<f32>(6.228269936347081, 14.17993336680509, 56.69055260068105),
+            vec3<f32>(4.776384997670288, -13.74514537774601, -65.35303263337234),
+            vec3<f32>(-5.435455855934631, 4.645852612178535, 26.3124352495832),
+        );
+    } elseif (uniforms.colormap == 2u) {
+        c = array<vec3<f32>, 7>(
+            vec3<f32>(-0.002136485053939582, -0.000749655052795221, -0.005386127855323933),
+            vec3<f32>(0.2516605407371642, 0.6775232436837668, 2.494026599312351),
+            vec3<f32>(8.353717279216625, -3.577719514958484, 0.3144679030132573),
+            vec3<f32>(-27.66873308576866, 14.26473078096533, -13.64921318813922),
+            vec3<f32>(52.17613981234068, -27.94360607168351, 12.94416944238394),
+            vec3<f32>(-50.76852536473588, 29.04658282127291, 4.23415299384598),
+            vec3<f32>(18.65570506591883, -11.48977351997711, -5.601961508734096),
+        );
+    } else {
+        c = array<vec3<f32>, 7>(
+            vec3<f32>(0.0002189403691192265, 0.001651004631001012, -0.01948089843709184),
+            vec3<f32>(0.1065134194856116, 0.5639564367884091, 3.932712388889277),
+            vec3<f32>(11.60249308247187, -3.972853965665698, -15.9423941062914),
+            vec3<f32>(-41.70399613139459, 17.43639888205313, 44.35414519872813),
+            vec3<f32>(77.162935699427, -33.40235894210092, -81.80730925738993),
+            vec3<f32>(-71.31942824499214, 32.62606426397723, 73.20951985803202),
+            vec3<f32>(25.13112622477341, -12.24266895238567, -23.07032500287172),
+        );
+    }
+    let srgb = c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * (c[4] + t * (c[5] + t * c[6])))));
+    // the fits are of sRGB encoded colors, and the frame is linear
+    return pow(clamp(srgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(2.2));
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let mean = textureLoad(exposure, vec2<i32>(pos.xy), 0).rgb;
+    let l = luminance(mean);
+    if (uniforms.colormap != 0u) {
+        return vec4<f32>(colormap(brightness(l)), 1.0);
+    }
+    var light: vec3<f32>;
+    if (uniforms.scale == 0u) {
+        // like film, brightening quickly at first and then saturating
+        light = vec3<f32>(1.0) - exp(-mean * uniforms.gain);
+    } else {
+        // rescaled as a whole, so the trails keep their hue
+        light = min(mean * brightness(l) / max(l, FAINTEST), vec3<f32>(1.0));
+    }
+    return vec4<f32>(uniforms.background * (vec3<f32>(1.0) - light) + light, 1.0);
+}
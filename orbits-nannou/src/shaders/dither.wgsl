@@ -0,0 +1,104 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    /// 0 for an ordered Bayer pattern, 1 for blue noise.
+    pattern: u32;
+    /// Levels each channel is dithered to when there's no palette.
+    levels: f32;
+    palette_len: u32;
+    /// Pixels along each side of the blocks the pattern is laid over.
+    block: f32;
+    /// sRGB colors dithered to, the first `palette_len` of them.
+    palette: array<vec4<f32>, 16>;
+};
+
+[[group(0), binding(0)]]
+var scene: texture_2d<f32>;
+[[group(0), binding(1)]]
+var noise: texture_2d<f32>;
+[[group(0), binding(3)]]
+var<uniform> uniforms: Uniforms;
+
+fn encode(linear: f32) -> f32 {
+    if (linear <= 0.0031308) {
+        return linear * 12.92;
+    }
+    return 1.055 * pow(linear, 1.0 / 2.4) - 0.055;
+}
+
+fn decode(srgb: f32) -> f32 {
+    if (srgb <= 0.04045) {
+        return srgb / 12.92;
+    }
+    return pow((srgb + 0.055) / 1.055, 2.4);
+}
+
+/// Where `cell` falls in an 8x8 Bayer matrix, from 0 to 1.
+fn bayer(cell: vec2<u32>) -> f32 {
+    var rank: u32 = 0u;
+    var bit: u32 = 0u;
+    loop {
+        if (bit >= 3u) {
+            break;
+        }
+        let x = (cell.x >> bit) & 1u;
+        let y = (cell.y >> bit) & 1u;
+        // the lowest bits vary fastest across neighbors, so they matter most
+        rank = rank | ((((x ^ y) << 1u) | y) << (2u * (2u - bit)));
+        continuing {
+            bit = bit + 1u;
+        }
+    }
+    return (f32(rank) + 0.5) / 64.0;
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let texel = textureLoad(scene, vec2<i32>(pos.xy), 0);
+    if (texel.a <= 0.0) {
+        return texel;
+    }
+    let cell = vec2<u32>(pos.xy / uniforms.block);
+    var threshold: f32;
+    if (uniforms.pattern == 0u) {
+        threshold = bayer(cell);
+    } else {
+        let size = vec2<u32>(textureDimensions(noise));
+        threshold = textureLoad(noise, vec2<i32>(cell % size), 0).r;
+    }
+
+    // dithered as shown, from the scene's straight colors
+    let straight = clamp(texel.rgb / texel.a, vec3<f32>(0.0), vec3<f32>(1.0));
+    let shown = vec3<f32>(encode(straight.r), encode(straight.g), encode(straight.b));
+    var dithered: vec3<f32>;
+    if (uniforms.palette_len == 0u) {
+        let steps = max(uniforms.levels - 1.0, 1.0);
+        dithered = clamp(floor(shown * steps + threshold) / steps, vec3<f32>(0.0), vec3<f32>(1.0));
+    } else {
+        // nudged by about as far as palette colors are apart, then the nearest one
+        let spread = 1.0 / pow(f32(uniforms.palette_len), 1.0 / 3.0);
+        let nudged = shown + (threshold - 0.5) * spread;
+        var nearest: f32 = 1000.0;
+        var i: u32 = 0u;
+        loop {
+            if (i >= uniforms.palette_len) {
+                break;
+            }
+            let color = uniforms.palette[i].rgb;
+            let distance = dot(color - nudged, color - nudged);
+            if (distance < nearest) {
+                nearest = distance;
+                dithered = color;
+            }
+            continuing {
+                i = i + 1u;
+            }
+        }
+    }
+    let linear = vec3<f32>(decode(dithered.r), decode(dithered.g), decode(dithered.b));
+    return vec4<f32>(linear * texel.a, texel.a);
+}
@@ -0,0 +1,68 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    blend: u32;
+    strength: f32;
+    /// Pixels each texel of the grain covers.
+    scale: f32;
+};
+
+[[group(0), binding(0)]]
+var scene: texture_2d<f32>;
+[[group(0), binding(1)]]
+var grain: texture_2d<f32>;
+[[group(0), binding(2)]]
+var grain_sampler: sampler;
+[[group(0), binding(3)]]
+var<uniform> uniforms: Uniforms;
+
+fn encode(linear: f32) -> f32 {
+    if (linear <= 0.0031308) {
+        return linear * 12.92;
+    }
+    return 1.055 * pow(linear, 1.0 / 2.4) - 0.055;
+}
+
+fn decode(srgb: f32) -> f32 {
+    if (srgb <= 0.04045) {
+        return srgb / 12.92;
+    }
+    return pow((srgb + 0.055) / 1.055, 2.4);
+}
+
+/// `base` with the grain `g` blended over it, both as they'd be shown.
+fn blend(base: f32, g: f32) -> f32 {
+    if (uniforms.blend == 1u) {
+        return 1.0 - (1.0 - base) * (1.0 - g);
+    }
+    if (base < 0.5) {
+        return 2.0 * base * g;
+    }
+    return 1.0 - 2.0 * (1.0 - base) * (1.0 - g);
+}
+
+/// A linear channel with the grain `g` blended over it as strongly as asked.
+fn grade(linear: f32, g: f32) -> f32 {
+    let base = encode(linear);
+    return decode(mix(base, blend(base, g), uniforms.strength));
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    // sampled before anything branches, as sampling has to be
+    let size = vec2<f32>(textureDimensions(grain));
+    let g = textureSample(grain, grain_sampler, pos.xy / (size * uniforms.scale)).r;
+    let texel = textureLoad(scene, vec2<i32>(pos.xy), 0);
+    if (texel.a <= 0.0) {
+        return texel;
+    }
+
+    // blended as shown, over the scene's straight colors
+    let straight = clamp(texel.rgb / texel.a, vec3<f32>(0.0), vec3<f32>(1.0));
+    let out = vec3<f32>(grade(straight.r, g), grade(straight.g, g), grade(straight.b, g));
+    return vec4<f32>(out * texel.a, texel.a);
+}
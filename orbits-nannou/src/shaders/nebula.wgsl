@@ -0,0 +1,87 @@
+struct VertexOutput {
+    [[location(0)]] uv: vec2<f32>;
+    [[builtin(position)]] pos: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> VertexOutput {
+    let uv = vec2<f32>(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+    return VertexOutput(uv, vec4<f32>(pos, 0.0, 1.0));
+}
+
+[[block]]
+struct Uniforms {
+    /// Linear RGB of the palette's two ends, which the clouds are tinted between.
+    near: vec4<f32>;
+    far: vec4<f32>;
+    time: f32;
+    /// World units across the largest clouds.
+    scale: f32;
+    warp: f32;
+    /// Half the width of the square of world the texture covers.
+    extent: f32;
+    opacity: f32;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+/// Value noise, smoothly interpolated between random values at the integer lattice.
+fn noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = hash(i);
+    let b = hash(i + vec2<f32>(1.0, 0.0));
+    let c = hash(i + vec2<f32>(0.0, 1.0));
+    let d = hash(i + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+/// Five octaves of noise, each twice as fine and half as strong as the last, from 0 to about 1.
+fn fbm(p: vec2<f32>) -> f32 {
+    var value: f32 = 0.0;
+    var amplitude: f32 = 0.5;
+    var q: vec2<f32> = p;
+    var octave: i32 = 0;
+    loop {
+        if (octave >= 5) {
+            break;
+        }
+        value = value + amplitude * noise(q);
+        q = q * 2.0 + vec2<f32>(1.7, 9.2);
+        amplitude = amplitude * 0.5;
+        continuing {
+            octave = octave + 1;
+        }
+    }
+    return value;
+}
+
+[[stage(fragment)]]
+fn fs_main([[location(0)]] uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let world = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0) * uniforms.extent;
+    let p = world / uniforms.scale;
+    let t = uniforms.time;
+
+    // noise looked up at noise, twice over, swirls the clouds as time moves the lookups along
+    let q = vec2<f32>(
+        fbm(p + vec2<f32>(0.0, 0.1 * t)),
+        fbm(p + vec2<f32>(5.2, 1.3) - vec2<f32>(0.1 * t, 0.0)),
+    );
+    let r = vec2<f32>(
+        fbm(p + uniforms.warp * q + vec2<f32>(1.7, 9.2) + vec2<f32>(0.15 * t, 0.0)),
+        fbm(p + uniforms.warp * q + vec2<f32>(8.3, 2.8) + vec2<f32>(0.0, 0.126 * t)),
+    );
+    let density = fbm(p + uniforms.warp * r);
+
+    let color = mix(uniforms.near.rgb, uniforms.far.rgb, clamp(length(r) * 0.6, 0.0, 1.0));
+    // smoothstepped, thin where the noise is low and thickening into solid cloud
+    let thickness = clamp((density - 0.3) / 0.5, 0.0, 1.0);
+    let alpha = uniforms.opacity * thickness * thickness * (3.0 - 2.0 * thickness);
+    return vec4<f32>(color, alpha);
+}
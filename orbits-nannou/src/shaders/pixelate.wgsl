@@ -0,0 +1,24 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    /// Pixels along each side of a block.
+    block: f32;
+};
+
+[[group(0), binding(0)]]
+var scene: texture_2d<f32>;
+[[group(0), binding(3)]]
+var<uniform> uniforms: Uniforms;
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    // every pixel of a block takes the one in its middle, as if rendered small and scaled up
+    let size = textureDimensions(scene);
+    let middle = (floor(pos.xy / uniforms.block) + 0.5) * uniforms.block;
+    let texel = min(vec2<i32>(middle), size - vec2<i32>(1));
+    return textureLoad(scene, texel, 0);
+}
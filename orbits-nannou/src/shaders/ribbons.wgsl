@@ -0,0 +1,133 @@
+struct Record {
+    pos: vec2<f32>;
+    /// Where along each trail's ring of records the record goes, or none when it's `0xffffffff`.
+    slot: u32;
+    /// How thick the trail is here, as a multiple of as ever. The ribbons built here share their
+    /// trails, so are only built while it's 1 all along.
+    thickness: f32;
+    /// Hue from 0 to 1, saturation, lightness and alpha.
+    color: vec4<f32>;
+};
+
+struct Info {
+    vel: vec2<f32>;
+    alpha: f32;
+    /// Epochs of the trail there are to draw.
+    visible: u32;
+};
+
+struct Point {
+    pos: vec2<f32>;
+    /// Linear and premultiplied by alpha.
+    color: vec4<f32>;
+};
+
+/// Each trail's records, `history` of them in a ring, by the epoch each was left at.
+[[block]]
+struct Rings {
+    records: array<Record>;
+};
+
+/// The record each trail has just drawn in to the part of it that's shown.
+[[block]]
+struct Incoming {
+    records: array<Record>;
+};
+
+[[block]]
+struct Infos {
+    infos: array<Info>;
+};
+
+/// The points the ribbons run between, epoch by epoch, each a row of every trail's point.
+[[block]]
+struct Points {
+    points: array<Point>;
+};
+
+[[block]]
+struct Uniforms {
+    count: u32;
+    history: u32;
+    /// Records each trail has left since the generation was seeded.
+    epochs: u32;
+    /// Epochs of the longest trail shown.
+    rows: u32;
+    /// Epochs each trail lags the one before it.
+    echo_delay: u32;
+    /// Simulated seconds to push the newest points on along their particles' velocities.
+    extrapolate: f32;
+    /// How far the trails are towards their full color.
+    build_in: f32;
+    /// How much of its alpha the oldest point of a trail shown keeps.
+    fade: f32;
+    /// Epochs of a full trail shown, along which each fades.
+    span: u32;
+    /// How the trails fade along them: 0 linearly, 1 exponentially.
+    curve: u32;
+};
+
+[[group(0), binding(0)]]
+var<storage, read_write> rings: Rings;
+[[group(0), binding(1)]]
+var<storage, read> incoming: Incoming;
+[[group(0), binding(2)]]
+var<storage, read> infos: Infos;
+[[group(0), binding(3)]]
+var<storage, read_write> points: Points;
+[[group(0), binding(4)]]
+var<uniform> uniforms: Uniforms;
+
+[[stage(compute), workgroup_size(64)]]
+fn store_records([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let particle = id.x;
+    if (particle >= uniforms.count) {
+        return;
+    }
+    let record = incoming.records[particle];
+    if (record.slot != 0xffffffffu) {
+        rings.records[particle * uniforms.history + record.slot] = record;
+    }
+}
+
+fn to_linear(c: f32) -> f32 {
+    if (c <= 0.04045) {
+        return c / 12.92;
+    }
+    return pow((c + 0.055) / 1.055, 2.4);
+}
+
+fn channel(hsl: vec3<f32>, n: f32) -> f32 {
+    let k = (n + hsl.x * 12.0) % 12.0;
+    let a = hsl.y * min(hsl.z, 1.0 - hsl.z);
+    return to_linear(hsl.z - a * clamp(min(k - 3.0, 9.0 - k), -1.0, 1.0));
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn place_points([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let particle = id.x % uniforms.count;
+    let epoch = id.x / uniforms.count;
+    let info = infos.infos[particle];
+    if (epoch >= uniforms.rows || epoch >= info.visible) {
+        return;
+    }
+    // records back from the newest, as the trail lags those before it
+    let back = particle * uniforms.echo_delay + epoch;
+    let slot = (uniforms.epochs - 1u - back) % uniforms.history;
+    let record = rings.records[particle * uniforms.history + slot];
+    var pos = record.pos;
+    if (back == 0u) {
+        pos = pos + info.vel * uniforms.extrapolate;
+    }
+    let hue = fract(record.color.x);
+    let hsl = vec3<f32>(hue, record.color.y * uniforms.build_in, record.color.z);
+    let age = min(f32(epoch) / f32(max(uniforms.span, 2u) - 1u), 1.0);
+    var faded = age;
+    if (uniforms.curve == 1u) {
+        // as sharply as the CPU's exponential fade falls off
+        faded = (1.0 - exp(-4.0 * age)) / (1.0 - exp(-4.0));
+    }
+    let alpha = record.color.w * info.alpha * (1.0 - (1.0 - uniforms.fade) * faded);
+    let rgb = vec3<f32>(channel(hsl, 0.0), channel(hsl, 8.0), channel(hsl, 4.0));
+    points.points[epoch * uniforms.count + particle] = Point(pos, vec4<f32>(rgb * alpha, alpha));
+}
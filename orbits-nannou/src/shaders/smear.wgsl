@@ -0,0 +1,36 @@
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> [[builtin(position)]] vec4<f32> {
+    return vec4<f32>(pos, 0.0, 1.0);
+}
+
+[[block]]
+struct Uniforms {
+    /// How much of the last frame is carried along where things are moving, from 0 to 1.
+    strength: f32;
+};
+
+[[group(0), binding(0)]]
+var previous: texture_2d<f32>;
+[[group(0), binding(1)]]
+var scene: texture_2d<f32>;
+/// Summed velocities splatted around each particle, in pixels, over how many were summed.
+[[group(0), binding(2)]]
+var motion: texture_2d<f32>;
+[[group(0), binding(3)]]
+var previous_sampler: sampler;
+[[group(0), binding(4)]]
+var<uniform> uniforms: Uniforms;
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let texel = vec2<i32>(pos.xy);
+    let splat = textureLoad(motion, texel, 0);
+    let coverage = clamp(splat.b, 0.0, 1.0);
+    let velocity = splat.rg / max(splat.b, 0.0001);
+
+    // the last frame is looked up where whatever is here came from
+    let size = vec2<f32>(textureDimensions(previous));
+    let back = textureSample(previous, previous_sampler, (pos.xy - velocity) / size);
+    let here = textureLoad(scene, texel, 0);
+    return mix(here, back, uniforms.strength * coverage);
+}
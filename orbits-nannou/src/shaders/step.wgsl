@@ -0,0 +1,115 @@
+struct Particle {
+    pos: vec2<f32>;
+    vel: vec2<f32>;
+};
+
+[[block]]
+struct Particles {
+    particles: array<Particle>;
+};
+
+[[block]]
+struct Previous {
+    previous: array<vec2<f32>>;
+};
+
+[[block]]
+struct Uniforms {
+    /// Each body's position in `xy` and strength in `z`.
+    bodies: array<vec4<f32>, 8>;
+    body_count: u32;
+    count: u32;
+    ticks: u32;
+    /// 0 for semi-implicit Euler, 1 for velocity Verlet and 2 for RK4.
+    scheme: u32;
+    tick_seconds: f32;
+    force_exponent: f32;
+    /// 0 for a power law, 1 for a softened inverse square, 2 for a post-Newtonian one and 3 for
+    /// the legacy pull.
+    law: u32;
+    /// The power law's scale, the average orbit's radius to the exponent less 2, the softening
+    /// squared, the post-Newtonian strength times the average orbit's radius squared, or the
+    /// legacy pull of a mass of strength 1.
+    law_parameter: f32;
+};
+
+[[group(0), binding(0)]]
+var<storage, read_write> particles: Particles;
+/// Where each particle was before the last tick.
+[[group(0), binding(1)]]
+var<storage, read_write> previous: Previous;
+[[group(0), binding(2)]]
+var<uniform> uniforms: Uniforms;
+
+/// Least distance from a mass its pull is reckoned at, as `orbits_core::physics::LEAST_DISTANCE`,
+/// so a particle landing right on one is flung off rather than turning to NaN.
+let LEAST_DISTANCE: f32 = 0.001;
+
+fn acceleration(pos: vec2<f32>) -> vec2<f32> {
+    var total = vec2<f32>(0.0, 0.0);
+    for (var i = 0u; i < uniforms.body_count; i = i + 1u) {
+        let body = uniforms.bodies[i];
+        let offset = pos - body.xy;
+        let distance = length(offset);
+        // along the x axis right on the mass, where every way is as much towards it as any other
+        var unit = vec2<f32>(1.0, 0.0);
+        if (distance > 0.0) {
+            unit = offset / distance;
+        }
+        let r = max(distance, LEAST_DISTANCE);
+        var pull = body.z / pow(r, uniforms.force_exponent);
+        if (uniforms.law == 0u) {
+            pull = pull * uniforms.law_parameter;
+        } elseif (uniforms.law == 1u) {
+            let r2 = r * r + uniforms.law_parameter;
+            pull = body.z * r / (r2 * sqrt(r2));
+        } elseif (uniforms.law == 2u) {
+            pull = pull * (1.0 + uniforms.law_parameter / (r * r));
+        } elseif (uniforms.law == 3u) {
+            pull = body.z * uniforms.law_parameter;
+            total = total + vec2<f32>(0.5 / r, 0.5 / r);
+        }
+        total = total - unit * pull;
+    }
+    return total;
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let i = id.x;
+    if (i >= uniforms.count) {
+        return;
+    }
+    let dt = uniforms.tick_seconds;
+    var pos = particles.particles[i].pos;
+    var vel = particles.particles[i].vel;
+    var before_last = pos;
+    for (var tick = 0u; tick < uniforms.ticks; tick = tick + 1u) {
+        before_last = pos;
+        if (uniforms.scheme == 0u) {
+            pos = pos + vel * dt;
+            vel = vel + acceleration(pos) * dt;
+        } else {
+            if (uniforms.scheme == 1u) {
+                let before = acceleration(pos);
+                pos = pos + vel * dt + before * (0.5 * dt * dt);
+                let after = acceleration(pos);
+                vel = vel + (before + after) * (0.5 * dt);
+            } else {
+                let p1 = vel;
+                let v1 = acceleration(pos);
+                let p2 = vel + v1 * (0.5 * dt);
+                let v2 = acceleration(pos + p1 * (0.5 * dt));
+                let p3 = vel + v2 * (0.5 * dt);
+                let v3 = acceleration(pos + p2 * (0.5 * dt));
+                let p4 = vel + v3 * dt;
+                let v4 = acceleration(pos + p3 * dt);
+                pos = pos + (p1 + 2.0 * p2 + 2.0 * p3 + p4) * (dt / 6.0);
+                vel = vel + (v1 + 2.0 * v2 + 2.0 * v3 + v4) * (dt / 6.0);
+            }
+        }
+    }
+    particles.particles[i].pos = pos;
+    particles.particles[i].vel = vel;
+    previous.previous[i] = before_last;
+}
@@ -0,0 +1,74 @@
+struct Info {
+    vel: vec2<f32>;
+    alpha: f32;
+    /// Epochs of the trail there are to draw.
+    visible: u32;
+};
+
+struct Point {
+    pos: vec2<f32>;
+    /// Linear and premultiplied by alpha.
+    color: vec4<f32>;
+};
+
+[[block]]
+struct Infos {
+    infos: array<Info>;
+};
+
+/// The points the ribbons run between, epoch by epoch, each a row of every trail's point.
+[[block]]
+struct Points {
+    points: array<Point>;
+};
+
+[[block]]
+struct Uniforms {
+    /// The point of the world in the middle of the target.
+    middle: vec2<f32>;
+    /// Half the world's width and height the target covers.
+    half: vec2<f32>;
+    count: u32;
+    /// Pieces along the longest ribbon.
+    pieces: u32;
+};
+
+[[group(0), binding(0)]]
+var<storage, read> infos: Infos;
+[[group(0), binding(1)]]
+var<storage, read> points: Points;
+[[group(0), binding(2)]]
+var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] color: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    // each piece is a quad of two triangles between two neighboring trails, the oldest pieces of
+    // every ribbon first so the newest are drawn over them
+    let ribbons = uniforms.count - 1u;
+    let piece = index / 6u;
+    let corner = index % 6u;
+    let ribbon = piece % ribbons;
+    let start = uniforms.pieces - 1u - piece / ribbons;
+    let second = corner == 2u || corner == 4u || corner == 5u;
+    let later = corner == 1u || corner == 2u || corner == 4u;
+    let particle = ribbon + u32(second);
+    let epoch = start + u32(later);
+    // a ribbon runs only as far as the shorter of the trails it joins
+    let visible = min(infos.infos[ribbon].visible, infos.infos[ribbon + 1u].visible);
+    if (start + 1u >= visible) {
+        return VertexOutput(vec4<f32>(0.0, 0.0, 0.0, 1.0), vec4<f32>(0.0));
+    }
+    let point = points.points[epoch * uniforms.count + particle];
+    let position = (point.pos - uniforms.middle) / uniforms.half;
+    return VertexOutput(vec4<f32>(position, 0.0, 1.0), point.color);
+}
+
+[[stage(fragment)]]
+fn fs_main([[location(0)]] color: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    return color;
+}
@@ -0,0 +1,55 @@
+struct VertexOutput {
+    [[location(0)]] uv: vec2<f32>;
+    [[builtin(position)]] pos: vec4<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] pos: vec2<f32>) -> VertexOutput {
+    let uv = vec2<f32>(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+    return VertexOutput(uv, vec4<f32>(pos, 0.0, 1.0));
+}
+
+[[block]]
+struct Uniforms {
+    progress: f32;
+    effect: u32;
+    softness: f32;
+    aspect: f32;
+};
+
+[[group(0), binding(0)]]
+var outgoing: texture_2d<f32>;
+[[group(0), binding(1)]]
+var incoming: texture_2d<f32>;
+[[group(0), binding(2)]]
+var scene_sampler: sampler;
+[[group(0), binding(3)]]
+var<uniform> uniforms: Uniforms;
+
+let TAU: f32 = 6.283185307179586;
+
+[[stage(fragment)]]
+fn fs_main([[location(0)]] uv: vec2<f32>) -> [[location(0)]] vec4<f32> {
+    let a = textureSample(outgoing, scene_sampler, uv);
+    let b = textureSample(incoming, scene_sampler, uv);
+
+    // how far into the transition each pixel switches over, from 0 to 1
+    var edge: f32 = 0.0;
+    if (uniforms.effect == 1u) {
+        // the brightest parts of the incoming scene come through first
+        edge = 1.0 - dot(b.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    } else {
+        if (uniforms.effect == 2u) {
+            // a clock hand sweeping clockwise from twelve
+            let p = vec2<f32>((uv.x - 0.5) * uniforms.aspect, 0.5 - uv.y);
+            edge = fract(atan2(p.x, p.y) / TAU + 1.0);
+        }
+    }
+
+    var t: f32 = uniforms.progress;
+    if (uniforms.effect != 0u) {
+        let soft = max(uniforms.softness, 0.001);
+        t = clamp((uniforms.progress * (1.0 + soft) - edge) / soft, 0.0, 1.0);
+    }
+    return mix(a, b, t);
+}
@@ -0,0 +1,326 @@
+//! A painterly smear: each frame carries some of the last one along the way the particles near
+//! each pixel are moving, streaking the scene in a way the geometric trails don't.
+
+use std::cell::RefCell;
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use orbits_core::generation::Generation;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::gpu::{quad, Post, Scene, Target, QUAD_LAYOUT, QUAD_VERTICES};
+
+const FORMAT: wgpu::TextureFormat = Frame::TEXTURE_FORMAT;
+
+/// The `[smear]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmearConfig {
+    /// How much of the last frame is carried along where particles are moving, from 0, for none,
+    /// to 1.
+    pub strength: f32,
+    /// Simulated seconds of motion each frame is smeared back along.
+    pub length: f32,
+    /// Pixels around each particle its motion is smeared over.
+    pub radius: f32,
+}
+
+impl Default for SmearConfig {
+    fn default() -> Self {
+        SmearConfig {
+            strength: 0.0,
+            length: 0.05,
+            radius: 40.0,
+        }
+    }
+}
+
+/// The smear, kept as the last frame drawn through it.
+pub struct Smear {
+    config: SmearConfig,
+    /// The velocities splatted for the next frame, in pixels.
+    motion: RefCell<Option<Draw>>,
+    gpu: RefCell<Option<Gpu>>,
+}
+
+struct Gpu {
+    size: [u32; 2],
+    msaa_samples: u32,
+    /// This frame, before it's smeared.
+    scene: Scene,
+    /// The particles' velocities, splatted.
+    motion: Scene,
+    /// The smeared frames, ping-ponged between: each smears the one before.
+    smeared: [wgpu::Texture; 2],
+    /// Which of `smeared` is the last frame, or none before the first.
+    current: Option<usize>,
+    smear: wgpu::RenderPipeline,
+    /// Bind group `i` smears `smeared[i]` into the other.
+    smear_groups: [wgpu::BindGroup; 2],
+    uniforms: wgpu::Buffer,
+    present: wgpu::RenderPipeline,
+    /// Bind group `i` presents `smeared[i]`.
+    present_groups: [wgpu::BindGroup; 2],
+    quad: wgpu::Buffer,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Uniforms {
+    strength: f32,
+    _padding: [f32; 3],
+}
+
+impl Smear {
+    /// The smear `config` asks for, or none if it's turned off.
+    pub fn new(config: SmearConfig) -> Option<Self> {
+        (config.strength > 0.0).then(|| Smear {
+            config,
+            motion: RefCell::new(None),
+            gpu: RefCell::new(None),
+        })
+    }
+
+    /// Splat the velocities of `generations`' particles for the next frame to be smeared along.
+    /// `project` maps world coordinates onto a drawing in pixels, `pixels_per_unit` to each world
+    /// unit.
+    pub fn splat(
+        &self,
+        generations: &[Generation],
+        project: impl Fn(&Draw) -> Draw,
+        pixels_per_unit: f32,
+    ) {
+        let motion = blank();
+        // the velocities are summed, along with how many were, to be averaged in the shader
+        let draw = project(&motion).color_blend(wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        });
+        let radius = self.config.radius / pixels_per_unit;
+        for generation in generations {
+            for particle in &generation.particles {
+                // in pixels, down the texture
+                let velocity = particle.vel * self.config.length * pixels_per_unit;
+                let weight = generation.opacity;
+                draw.ellipse()
+                    .xy(particle.pos)
+                    .radius(radius)
+                    .color(lin_srgba(
+                        velocity.x * weight,
+                        -velocity.y * weight,
+                        weight,
+                        0.0,
+                    ));
+            }
+        }
+        *self.motion.borrow_mut() = Some(motion);
+    }
+}
+
+/// The same smear with a history of its own, for drawing through at another size.
+impl Clone for Smear {
+    fn clone(&self) -> Self {
+        Smear {
+            config: self.config,
+            motion: RefCell::new(None),
+            gpu: RefCell::new(None),
+        }
+    }
+}
+
+/// Smears the frame drawn through it along the velocities last splatted, or not at all if none
+/// were.
+impl Post for Smear {
+    fn draw(&self, target: &mut Target, f: &mut dyn FnMut(&mut Target, &mut Scene)) {
+        let (size, msaa_samples) = (target.size, target.msaa_samples);
+        let mut gpu = self.gpu.borrow_mut();
+        if gpu.as_ref().is_none_or(|gpu| {
+            gpu.size != size || gpu.msaa_samples != msaa_samples || !gpu.scene.is_current()
+        }) {
+            *gpu = Some(Gpu::new(target.device(), size, msaa_samples));
+        }
+        let gpu = gpu.as_mut().unwrap();
+        let device = target.device_queue.device();
+
+        let view = gpu.scene.texture.view().build();
+        let mut inner = Target {
+            device_queue: target.device_queue,
+            encoder: &mut *target.encoder,
+            view: &view,
+            size,
+            msaa_samples: 1,
+        };
+        f(&mut inner, &mut gpu.scene);
+        let motion = self.motion.take().unwrap_or_else(blank);
+        gpu.motion.render(device, target.encoder, &motion);
+
+        // nothing to smear the first frame along
+        let (previous, strength) = match gpu.current {
+            Some(current) => (current, self.config.strength.min(1.0)),
+            None => (0, 0.0),
+        };
+        let uniforms = Uniforms {
+            strength,
+            _padding: [0.0; 3],
+        };
+        target
+            .queue()
+            .write_buffer(&gpu.uniforms, 0, unsafe { wgpu::bytes::from(&uniforms) });
+
+        let next = 1 - previous;
+        let next_view = gpu.smeared[next].view().build();
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(&next_view, |color| color)
+            .begin(target.encoder);
+        pass.set_pipeline(&gpu.smear);
+        pass.set_vertex_buffer(0, gpu.quad.slice(..));
+        pass.set_bind_group(0, &gpu.smear_groups[previous], &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+        drop(pass);
+        gpu.current = Some(next);
+
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(target.view, |color| color)
+            .begin(target.encoder);
+        pass.set_pipeline(&gpu.present);
+        pass.set_vertex_buffer(0, gpu.quad.slice(..));
+        pass.set_bind_group(0, &gpu.present_groups[next], &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+    }
+}
+
+/// A drawing with nothing moving in it.
+fn blank() -> Draw {
+    let draw = Draw::new();
+    draw.background().color(rgba(0.0, 0.0, 0.0, 0.0));
+    draw
+}
+
+impl Gpu {
+    fn new(device: &wgpu::Device, size: [u32; 2], msaa_samples: u32) -> Self {
+        let scene = Scene::new(device, size);
+        let motion = Scene::new(device, size);
+        let smeared = [(), ()].map(|_| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .format(FORMAT)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                )
+                .build(device)
+        });
+        let filterable = wgpu::TextureSampleType::Float { filterable: true };
+        let unfilterable = wgpu::TextureSampleType::Float { filterable: false };
+
+        let sampler_desc = wgpu::SamplerBuilder::new().into_descriptor();
+        let filtering = wgpu::sampler_filtering(&sampler_desc);
+        let sampler = device.create_sampler(&sampler_desc);
+        let smear_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                filterable,
+            )
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                unfilterable,
+            )
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                unfilterable,
+            )
+            .sampler(wgpu::ShaderStages::FRAGMENT, filtering)
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("smear uniforms"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let scene_view = scene.texture.view().build();
+        let motion_view = motion.texture.view().build();
+        let smear_groups = [0, 1].map(|i| {
+            wgpu::BindGroupBuilder::new()
+                .texture_view(&smeared[i].view().build())
+                .texture_view(&scene_view)
+                .texture_view(&motion_view)
+                .sampler(&sampler)
+                .buffer::<Uniforms>(&uniforms, 0..1)
+                .build(device, &smear_layout)
+        });
+        let smear = pipeline(
+            device,
+            &smear_layout,
+            &wgpu::include_wgsl!("shaders/smear.wgsl"),
+            1,
+        );
+
+        let present_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                unfilterable,
+            )
+            .build(device);
+        let present_groups = [0, 1].map(|i| {
+            wgpu::BindGroupBuilder::new()
+                .texture_view(&smeared[i].view().build())
+                .build(device, &present_layout)
+        });
+        let present = pipeline(
+            device,
+            &present_layout,
+            &wgpu::include_wgsl!("shaders/present.wgsl"),
+            msaa_samples,
+        );
+
+        Gpu {
+            size,
+            msaa_samples,
+            scene,
+            motion,
+            smeared,
+            current: None,
+            smear,
+            smear_groups,
+            uniforms,
+            present,
+            present_groups,
+            quad: quad(device),
+        }
+    }
+}
+
+fn pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    shader: &wgpu::ShaderModuleDescriptor,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("smear"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(shader);
+    wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+        .vertex_entry_point("vs_main")
+        .fragment_shader(&shader)
+        .fragment_entry_point("fs_main")
+        .color_format(FORMAT)
+        .color_blend(wgpu::BlendComponent::REPLACE)
+        .alpha_blend(wgpu::BlendComponent::REPLACE)
+        .add_vertex_buffer_layout(QUAD_LAYOUT)
+        .primitive_topology(wgpu::PrimitiveTopology::TriangleStrip)
+        .sample_count(sample_count)
+        .build(device)
+}
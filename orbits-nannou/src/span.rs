@@ -0,0 +1,197 @@
+//! Spreads the piece over several windows, each fullscreen on a monitor of its own, for showing it
+//! across projectors. The windows' parts of the desktop are laid out as the monitors are, and the
+//! world is fitted to the rectangle round them all, the canvas, as it would be to one window that
+//! size. Each window then shows its own part of the canvas through a view of its own, so the
+//! scene carries on from one display onto the next as if they were one.
+
+use nannou::prelude::*;
+use nannou::winit::monitor::MonitorHandle;
+use nannou::winit::window::Fullscreen;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::viewport::Viewport;
+
+/// The `[span]` section of the config.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpanConfig {
+    /// Whether a window is opened on every monitor, each showing the part of the desktop it
+    /// covers.
+    pub all: bool,
+    /// The windows opened, the first of them the piece's own window, with the hotkeys' overlays
+    /// and the panel. Left out for the one window, or for every monitor with `all`.
+    pub windows: Vec<PaneConfig>,
+}
+
+/// One window of the `[[span.windows]]` the piece is spread over.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PaneConfig {
+    /// The monitor it's fullscreen on, by its number in the list of them printed at startup.
+    pub monitor: usize,
+    /// The part of the desktop it shows, as `[x, y, width, height]` in pixels from the desktop's
+    /// top left corner, as the monitors' positions are given. Left out for the part the monitor
+    /// covers, set to line projectors' overlapping edges up.
+    pub crop: Option<[f32; 4]>,
+}
+
+impl SpanConfig {
+    /// Whether the piece is spread over windows at all.
+    pub fn is_on(&self) -> bool {
+        self.all || !self.windows.is_empty()
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if self.all && !self.windows.is_empty() {
+            return Err("the span can't have both all and windows".into());
+        }
+        for pane in &self.windows {
+            if let Some([_, _, width, height]) = pane.crop {
+                if width <= 0.0 || height <= 0.0 {
+                    return Err(format!(
+                        "the crop of the span's window on monitor {} has to have a positive \
+                         size, not {width} by {height}",
+                        pane.monitor
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The monitor and the part of the desktop of each window spanned, there being `monitors`.
+    fn panes(&self, monitors: &[MonitorHandle]) -> Result<Vec<(MonitorHandle, Rect)>, String> {
+        if self.all {
+            return Ok((monitors.iter())
+                .map(|monitor| (monitor.clone(), covered(monitor)))
+                .collect());
+        }
+        (self.windows.iter())
+            .map(|pane| {
+                let monitor = monitors.get(pane.monitor).ok_or_else(|| {
+                    format!(
+                        "the span has a window on monitor {}, and there are only {}",
+                        pane.monitor,
+                        monitors.len()
+                    )
+                })?;
+                let part = match pane.crop {
+                    Some([x, y, width, height]) => desktop_rect(x, y, width, height),
+                    None => covered(monitor),
+                };
+                Ok((monitor.clone(), part))
+            })
+            .collect()
+    }
+}
+
+/// The part of the desktop `monitor` covers.
+fn covered(monitor: &MonitorHandle) -> Rect {
+    let (pos, size) = (monitor.position(), monitor.size());
+    desktop_rect(
+        pos.x as f32,
+        pos.y as f32,
+        size.width as f32,
+        size.height as f32,
+    )
+}
+
+/// The rectangle of the desktop `width` by `height` pixels from `x` across and `y` down from its
+/// top left corner, up being positive as it is in the world.
+fn desktop_rect(x: f32, y: f32, width: f32, height: f32) -> Rect {
+    Rect::from_corners(pt2(x, -y), pt2(x + width, -(y + height)))
+}
+
+/// A window the piece is spread over.
+struct Pane {
+    window: WindowId,
+    /// Its part of the canvas, in pixels from the canvas's middle.
+    part: Rect,
+    /// Pixels to a point on its monitor.
+    scale_factor: f32,
+}
+
+/// The windows the piece is spread over, and each one's part of the canvas.
+pub struct Span {
+    panes: Vec<Pane>,
+    /// The size of the canvas, in pixels.
+    canvas: Vec2,
+}
+
+impl Span {
+    /// Spread the piece over the windows `config` asks for, making `main` the first of them and
+    /// opening the rest with `open`, saying which monitor is which.
+    pub fn new(
+        app: &App,
+        config: &SpanConfig,
+        main: WindowId,
+        open: impl Fn(Fullscreen) -> WindowId,
+    ) -> Result<Self, String> {
+        let monitors = app.available_monitors();
+        for (i, monitor) in monitors.iter().enumerate() {
+            let (pos, size) = (monitor.position(), monitor.size());
+            println!(
+                "monitor {i}: {} {}x{} at {},{}",
+                monitor.name().unwrap_or_default(),
+                size.width,
+                size.height,
+                pos.x,
+                pos.y
+            );
+        }
+        let parts = config.panes(&monitors)?;
+        if parts.is_empty() {
+            return Err("there are no monitors to span".into());
+        }
+        let canvas = (parts.iter())
+            .map(|(_, part)| *part)
+            .reduce(Rect::max)
+            .unwrap();
+        let panes = (parts.into_iter().enumerate())
+            .map(|(i, (monitor, part))| {
+                let fullscreen = Fullscreen::Borderless(Some(monitor.clone()));
+                let window = if i == 0 {
+                    app.window(main)
+                        .unwrap()
+                        .set_fullscreen_with(Some(fullscreen));
+                    main
+                } else {
+                    open(fullscreen)
+                };
+                Pane {
+                    window,
+                    part: part.shift(-canvas.xy()),
+                    scale_factor: monitor.scale_factor() as f32,
+                }
+            })
+            .collect();
+        Ok(Span {
+            panes,
+            canvas: canvas.wh(),
+        })
+    }
+
+    /// The canvas, in pixels, centered on the middle of the world's view.
+    pub fn canvas(&self) -> Rect {
+        Rect::from_wh(self.canvas)
+    }
+
+    /// Which of the windows spanned `window` is, if it's one of them.
+    pub fn pane(&self, window: WindowId) -> Option<usize> {
+        self.panes.iter().position(|pane| pane.window == window)
+    }
+
+    /// The view of the window of `pane`, `win` in points, the canvas seen as `whole` has it in
+    /// pixels. Its part of the canvas is fitted to the window, should they differ in size.
+    pub fn view(&self, pane: usize, whole: Viewport, win: Rect) -> Viewport {
+        let Pane {
+            part, scale_factor, ..
+        } = self.panes[pane];
+        let zoom = (win.w() * scale_factor / part.w()).min(win.h() * scale_factor / part.h());
+        Viewport::new(
+            whole.scale * zoom / scale_factor,
+            whole.middle + part.xy() / whole.scale,
+        )
+    }
+}
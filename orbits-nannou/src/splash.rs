@@ -0,0 +1,107 @@
+//! What the window shows while the piece gets going, rather than a frame frozen until it has: a
+//! mass with a particle orbiting it, trailing a tail, over a bar filling up as the loading goes.
+//! The shaders and assets are loaded before the window's first frame, and the pre-roll is run a
+//! slice at a time between frames, so the loader keeps turning through it, from `Loading` to
+//! `Running` once it's done. Exports, replays, snapshots, self-tests, synced runs and frame-limited
+//! runs pre-roll before their first frame as ever, as every frame they show counts.
+
+use std::time::{Duration, Instant};
+
+use nannou::prelude::*;
+
+/// How long each frame of loading runs the pre-roll for, leaving the rest of it to drawing.
+const SLICE: Duration = Duration::from_millis(12);
+/// Wall-clock seconds the loader's particle takes to go round.
+const ORBIT_SECONDS: f32 = 1.2;
+/// Dots in the loader's particle's tail, the particle at their head.
+const TAIL: usize = 12;
+/// Points across the loader's orbit and its bar.
+const RADIUS: f32 = 28.0;
+const BAR_WIDTH: f32 = 160.0;
+
+/// How far the piece has got going.
+pub enum Splash {
+    /// Pre-rolling, `left` of the `total` frames to go.
+    Loading {
+        left: usize,
+        total: usize,
+        /// Wall-clock seconds since the loader was first shown.
+        seconds: f32,
+    },
+    Running,
+}
+
+impl Splash {
+    /// The loader for a pre-roll of `frames`, or running already if there aren't any.
+    pub fn new(frames: usize) -> Self {
+        match frames {
+            0 => Splash::Running,
+            _ => Splash::Loading {
+                left: frames,
+                total: frames,
+                seconds: 0.0,
+            },
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(self, Splash::Loading { .. })
+    }
+
+    /// Run as many frames of the pre-roll as fit in a slice of a frame with `frame`, `wall_seconds`
+    /// since the last, going on to running once they're all done.
+    pub fn load(&mut self, wall_seconds: f32, mut frame: impl FnMut()) {
+        let Splash::Loading { left, seconds, .. } = self else {
+            return;
+        };
+        *seconds += wall_seconds;
+        let started = Instant::now();
+        while *left > 0 && started.elapsed() < SLICE {
+            frame();
+            *left -= 1;
+        }
+        if *left == 0 {
+            *self = Splash::Running;
+        }
+    }
+
+    /// Draw the loader in the middle of `win` in `color`, over `background`.
+    pub fn draw(&self, win: Rect, background: Hsl, color: Hsl, draw: &Draw) {
+        let Splash::Loading {
+            left,
+            total,
+            seconds,
+        } = *self
+        else {
+            return;
+        };
+        draw.background().color(background);
+        let shade = |alpha: f32| Hsla::new(color.hue, color.saturation, color.lightness, alpha);
+        let middle = win.xy() + vec2(0.0, RADIUS);
+        draw.ellipse()
+            .xy(middle)
+            .radius(RADIUS / 5.0)
+            .color(shade(0.8));
+        let turned = seconds / ORBIT_SECONDS;
+        for dot in 0..TAIL {
+            let behind = dot as f32 / TAIL as f32;
+            let angle = (turned - behind * 0.4) * TAU;
+            let fade = 1.0 - behind;
+            draw.ellipse()
+                .xy(middle + vec2(angle.cos(), angle.sin()) * RADIUS)
+                .radius(3.0 * fade + 0.5)
+                .color(shade(fade));
+        }
+        let done = 1.0 - left as f32 / total as f32;
+        let bar = Rect::from_x_y_w_h(win.x(), win.y() - RADIUS, BAR_WIDTH, 3.0);
+        draw.rect().xy(bar.xy()).wh(bar.wh()).color(shade(0.2));
+        let filled = Rect::from_corners(
+            bar.bottom_left(),
+            pt2(bar.left() + bar.w() * done, bar.top()),
+        );
+        draw.rect()
+            .xy(filled.xy())
+            .wh(filled.wh())
+            .color(shade(0.8));
+    }
+}
@@ -0,0 +1,104 @@
+//! A decorative field of stars behind the piece, twinkling and drifting slowly, each at a depth
+//! that sets how far it moves as the view pans and zooms.
+
+use nannou::prelude::*;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::{Rng, SeedableRng};
+use orbits_core::orbital_radius;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Half the width of the square of sky the stars are laid out over and wrap around in, in orbital
+/// radii, enough to cover a zoomed out window at the farthest depth.
+const FIELD: f32 = 4.0;
+/// Depth of the farthest stars, which move this fraction as much as the piece does.
+const FARTHEST: f32 = 0.2;
+
+/// The `[stars]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct StarsConfig {
+    /// Stars in each square the size of the average orbit's diameter, or 0 for none.
+    pub density: f32,
+    /// Opacity of the brightest stars, from 0 to 1.
+    pub brightness: f32,
+    /// How much of its brightness a star loses at the bottom of a twinkle, from 0 to 1.
+    pub twinkle: f32,
+    /// How fast the nearest stars drift across the sky, in world units per simulated second.
+    pub drift: f32,
+}
+
+impl Default for StarsConfig {
+    fn default() -> Self {
+        StarsConfig {
+            density: 0.0,
+            brightness: 0.6,
+            twinkle: 0.5,
+            drift: 5.0,
+        }
+    }
+}
+
+struct Star {
+    pos: Point2,
+    /// From `FARTHEST` to 1, nearest.
+    depth: f32,
+    /// Radians per simulated second of twinkling.
+    rate: f32,
+    phase: f32,
+}
+
+pub struct StarField {
+    config: StarsConfig,
+    stars: Vec<Star>,
+}
+
+impl StarField {
+    pub fn new(config: StarsConfig) -> Self {
+        // the same sky every run, whatever the seed
+        let mut rng = StdRng::seed_from_u64(0);
+        let squares = FIELD.powi(2);
+        let field = FIELD * orbital_radius();
+        let count = (config.density.max(0.0) * squares).round() as usize;
+        let stars = (0..count)
+            .map(|_| Star {
+                pos: pt2(rng.gen_range(-field..field), rng.gen_range(-field..field)),
+                depth: rng.gen_range(FARTHEST..1.0),
+                rate: rng.gen_range(0.5..3.0),
+                phase: rng.gen_range(0.0..TAU),
+            })
+            .collect();
+        StarField { config, stars }
+    }
+
+    /// Draw the stars at simulated time `t`, in world coordinates, as seen from a camera centered
+    /// on `middle`.
+    pub fn draw(&self, t: f32, middle: Point2, draw: &Draw) {
+        let StarsConfig {
+            brightness,
+            twinkle,
+            drift,
+            ..
+        } = self.config;
+        for star in &self.stars {
+            // nearer stars drift faster, all of them wrapping around the field
+            let drifted = star.pos + vec2(drift * star.depth * t, 0.0);
+            let wrapped = pt2(wrap(drifted.x - middle.x), wrap(drifted.y - middle.y));
+            // a star at depth d moves d times as far across the window as the piece does
+            let pos = middle + wrapped * star.depth;
+            let shimmer = 0.5 + 0.5 * (star.rate * t + star.phase).sin();
+            let alpha = brightness * star.depth * (1.0 - twinkle * shimmer);
+            draw.ellipse()
+                .xy(pos)
+                .radius(2.0 + 2.0 * star.depth)
+                .resolution(6.0)
+                .color(rgba(1.0, 1.0, 1.0, alpha));
+        }
+    }
+}
+
+/// `x` wrapped into the field.
+fn wrap(x: f32) -> f32 {
+    let field = FIELD * orbital_radius();
+    (x + field).rem_euclid(2.0 * field) - field
+}
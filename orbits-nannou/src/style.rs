@@ -0,0 +1,41 @@
+//! The post passes that restyle the finished scene, in the order it's drawn through them.
+
+use crate::ascii::Ascii;
+use crate::config::Config;
+use crate::gpu::Post;
+use crate::grain::Grain;
+use crate::retro::Retro;
+use crate::smear::Smear;
+
+/// Every pass the config turns on.
+#[derive(Clone, Default)]
+pub struct Style {
+    pub smear: Option<Smear>,
+    pub ascii: Option<Ascii>,
+    pub retro: Retro,
+    pub grain: Option<Grain>,
+}
+
+impl Style {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        Ok(Style {
+            smear: Smear::new(config.smear),
+            ascii: Ascii::load(&config.ascii)?,
+            retro: Retro::new(&config.retro)?,
+            grain: Grain::load(&config.grain)?,
+        })
+    }
+
+    /// The passes, innermost first: the smear is of the scene as drawn, the ASCII art can be bent
+    /// onto a CRT, and the grain lies over everything.
+    pub fn posts(&self) -> Vec<&dyn Post> {
+        let smear = self.smear.iter().map(|smear| smear as &dyn Post);
+        let ascii = self.ascii.iter().map(|ascii| ascii as &dyn Post);
+        let grain = self.grain.iter().map(|grain| grain as &dyn Post);
+        smear
+            .chain(ascii)
+            .chain(self.retro.passes())
+            .chain(grain)
+            .collect()
+    }
+}
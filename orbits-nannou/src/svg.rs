@@ -0,0 +1,201 @@
+//! Writes the trails of a generation as they stand to an SVG, for plotting on a pen plotter or
+//! editing in a vector editor: the ribbons filled in between neighboring particles' trails, or a
+//! polyline along each particle's own, from the same history the window draws them from. An SVG
+//! shape has the one color, so the colors changing along a trail are had by giving each triangle
+//! of a ribbon a fill of its own, and each stretch of a line a stroke of its own, the stretches
+//! running on for as long as their color stays the same.
+
+use std::fmt::Write;
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use orbits_core::generation::Generation;
+use orbits_core::trails;
+
+use crate::compute::Look;
+use crate::export;
+use crate::mask::Mask;
+use crate::render::{self, RenderConfig, TrailStyle};
+
+/// Opacity below which a shape is left out, as it wouldn't be seen.
+const UNSEEN: f32 = 0.005;
+
+/// The trails of `generation` as `look` has them, in `style`, faded by `mask` if there is one,
+/// as an SVG of the part of the world in `view` at `scale` points to a world unit. Every style
+/// but the ribbons is drawn as lines.
+pub fn document(
+    generation: &Generation,
+    look: Look,
+    style: TrailStyle,
+    config: &RenderConfig,
+    mask: Option<&Mask>,
+    view: Rect,
+    scale: f32,
+) -> String {
+    let mut svg = String::new();
+    // the world's y goes up, an SVG's down
+    let flip = |pos: Point2| pt2(pos.x, -pos.y);
+    let top_left = flip(view.top_left());
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.2} {:.2} {:.2} {:.2}" width="{:.0}" height="{:.0}">"#,
+        top_left.x,
+        top_left.y,
+        view.w(),
+        view.h(),
+        view.w() * scale,
+        view.h() * scale,
+    );
+    let (background, _) = paint(generation.background.into_lin_srgba());
+    let _ = writeln!(
+        svg,
+        r#"<rect id="background" x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{background}"/>"#,
+        top_left.x,
+        top_left.y,
+        view.w(),
+        view.h(),
+    );
+
+    let coverage = |pos: Point2| mask.map_or(1.0, |mask| mask.coverage(pos));
+    if style == TrailStyle::Ribbons {
+        let mut mesh = render::build(style, config, generation, look);
+        if let Some(mask) = mask {
+            mask.apply(&mut mesh);
+        }
+        svg.push_str("<g id=\"trails\">\n");
+        for triangle in mesh.indices.chunks(3) {
+            let corners = triangle.iter().map(|&i| mesh.vertices[i]);
+            let (fill, opacity) = paint(mean(corners.clone().map(|(_, color)| color)));
+            if opacity < UNSEEN {
+                continue;
+            }
+            let points = corners
+                .map(|(pos, _)| point(flip(pos.truncate())))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{points}" fill="{fill}" fill-opacity="{opacity:.2}"/>"#
+            );
+        }
+        svg.push_str("</g>\n");
+    } else {
+        svg.push_str("<g id=\"trails\" fill=\"none\" stroke-linecap=\"round\">\n");
+        let length = look.build_in * look.length;
+        for particle in 0..generation.particles.len() {
+            let opacity = look.opacity * generation.saliency.alpha(particle, look.highlight);
+            let mut points = trails::trail(generation, particle, opacity, look.extrapolate, length);
+            if let Some(view) = look.view {
+                view.flatten_trail(generation, particle, &mut points);
+            }
+            for (pos, color) in &mut points {
+                color.saturation *= look.build_in;
+                color.alpha *= coverage(*pos);
+            }
+            let widths: Vec<f32> = (trails::thickness(generation, particle, length).iter())
+                .map(|thickness| thickness * config.line_width)
+                .collect();
+            polylines(&mut svg, &points, &widths, flip);
+        }
+        svg.push_str("</g>\n");
+    }
+
+    svg.push_str("<g id=\"highlights\" fill=\"none\" stroke-linecap=\"round\">\n");
+    let length = look.build_in * look.length;
+    let lines = trails::highlights(
+        generation,
+        look.opacity,
+        look.highlight,
+        look.extrapolate,
+        length,
+    );
+    for mut line in lines {
+        if let Some(view) = look.view {
+            view.flatten_trail(generation, line.particle, &mut line.points);
+        }
+        for (pos, color) in &mut line.points {
+            color.saturation *= look.build_in;
+            color.alpha *= coverage(*pos);
+        }
+        let widths = vec![line.width; line.points.len()];
+        polylines(&mut svg, &line.points, &widths, flip);
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// Write `points` to `svg` as polylines as wide across as `widths` has each point, each stretch
+/// between two points stroked in the mean of their colors and widths, and those in a row of the
+/// same stroke run on into one line.
+fn polylines(
+    svg: &mut String,
+    points: &[(Point2, Hsla)],
+    widths: &[f32],
+    flip: impl Fn(Point2) -> Point2,
+) {
+    // the line running on, and the stroke it's in
+    let mut run: Vec<Point2> = Vec::new();
+    let mut stroke = (String::new(), 0.0, 0.0);
+    let end = |svg: &mut String,
+               run: &mut Vec<Point2>,
+               (color, opacity, width): &(String, f32, f32)| {
+        if run.len() >= 2 {
+            let points = run.iter().map(|&pos| point(flip(pos))).collect::<Vec<_>>();
+            let _ = writeln!(
+                svg,
+                r#"<polyline points="{}" stroke="{color}" stroke-opacity="{opacity:.2}" stroke-width="{width:.2}"/>"#,
+                points.join(" "),
+            );
+        }
+        run.clear();
+    };
+    for (i, pair) in points.windows(2).enumerate() {
+        let [(from, from_color), (to, to_color)] = [pair[0], pair[1]];
+        let (color, opacity) = paint(mean([from_color, to_color].into_iter()));
+        let opacity = (opacity * 100.0).round() / 100.0;
+        let width = ((widths[i] + widths[i + 1]) / 2.0 * 100.0).round() / 100.0;
+        if opacity < UNSEEN {
+            end(svg, &mut run, &stroke);
+            continue;
+        }
+        if run.is_empty() || (&color, opacity, width) != (&stroke.0, stroke.1, stroke.2) {
+            end(svg, &mut run, &stroke);
+            run.push(from);
+            stroke = (color, opacity, width);
+        }
+        run.push(to);
+    }
+    end(svg, &mut run, &stroke);
+}
+
+/// The mean of `colors`, mixed as light is.
+fn mean(colors: impl Iterator<Item = Hsla>) -> LinSrgba {
+    let (mut sum, mut count) = ([0.0; 4], 0.0);
+    for color in colors {
+        let color = color.into_lin_srgba();
+        for (sum, channel) in sum
+            .iter_mut()
+            .zip([color.red, color.green, color.blue, color.alpha])
+        {
+            *sum += channel;
+        }
+        count += 1.0;
+    }
+    let [red, green, blue, alpha] = sum.map(|sum| sum / count.max(1.0));
+    lin_srgba(red, green, blue, alpha)
+}
+
+/// `color` as an SVG's `#rrggbb`, and its opacity.
+fn paint(color: LinSrgba) -> (String, f32) {
+    let [red, green, blue] = [color.red, color.green, color.blue]
+        .map(|linear| (export::srgb(linear.clamp(0.0, 1.0)) * 255.0).round());
+    (
+        format!("#{:02x}{:02x}{:02x}", red as u8, green as u8, blue as u8),
+        color.alpha,
+    )
+}
+
+/// `pos` as a point of an SVG's list of them.
+fn point(pos: Point2) -> String {
+    format!("{:.2},{:.2}", pos.x, pos.y)
+}
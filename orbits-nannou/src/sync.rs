@@ -0,0 +1,135 @@
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+
+use serde::{Deserialize, Serialize};
+
+use crate::genlock::{self, ClockEstimate};
+use orbits_core::params::Param;
+use orbits_core::session::Change;
+
+/// Multicast group used when none is given.
+pub const DEFAULT_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 77, 77), 7777);
+/// Comfortably larger than any message, which are all a few hundred bytes.
+const MAX_DATAGRAM: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Runs the simulation and broadcasts everything needed to mirror it.
+    Leader,
+    /// Mirrors the leader's simulation, ignoring its own clock.
+    Follower,
+}
+
+/// What the leader broadcasts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "message", rename_all = "snake_case")]
+pub enum Message {
+    /// The leader advanced its simulation to `time`, taking `wall_seconds` of real time to do so.
+    Tick {
+        time: f32,
+        wall_seconds: f32,
+        params: Vec<(Param, f32)>,
+        /// When the leader reached `time`, on its own clock, if genlocked.
+        clock: Option<f64>,
+    },
+    /// The leader made a change. Changes are sent before the tick that follows them.
+    Change { change: Change },
+}
+
+/// Keeps several instances in lockstep over UDP multicast, so each can render its own crop of one
+/// shared simulation. Followers only stay identical to the leader if they are started first and
+/// no datagrams are lost, which a quiet local network provides.
+pub struct Sync {
+    pub role: Role,
+    socket: UdpSocket,
+    group: SocketAddrV4,
+    /// Whether frames are timed against the leader's clock.
+    pub genlock: bool,
+    /// A follower's estimate of the leader's clock, once it knows where the leader is.
+    clock: Option<ClockEstimate>,
+    leader: Option<Ipv4Addr>,
+}
+
+impl Sync {
+    /// Join `group`. With `genlock` the leader answers clock probes one port above the group's,
+    /// and followers measure their clocks against it.
+    pub fn new(role: Role, group: SocketAddrV4, genlock: bool) -> Result<Self, String> {
+        let err = |err: std::io::Error| format!("sync on {group}: {err}");
+        let socket = match role {
+            Role::Leader => {
+                let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(err)?;
+                // let followers on the same machine hear the leader too
+                socket.set_multicast_loop_v4(true).map_err(err)?;
+                socket
+            }
+            Role::Follower => {
+                let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, group.port())).map_err(err)?;
+                socket
+                    .join_multicast_v4(group.ip(), &Ipv4Addr::UNSPECIFIED)
+                    .map_err(err)?;
+                socket.set_nonblocking(true).map_err(err)?;
+                socket
+            }
+        };
+        let clock_port = group.port().wrapping_add(1);
+        let clock = match (genlock, role) {
+            (false, _) => None,
+            (true, Role::Leader) => {
+                genlock::serve(clock_port)?;
+                None
+            }
+            (true, Role::Follower) => Some(ClockEstimate::new(clock_port)?),
+        };
+        Ok(Sync {
+            role,
+            socket,
+            group,
+            genlock,
+            clock,
+            leader: None,
+        })
+    }
+
+    /// The current time on the leader's clock, as best a follower knows it.
+    pub fn leader_now(&self) -> f64 {
+        match &self.clock {
+            Some(clock) => clock.leader_now(),
+            None => genlock::now(),
+        }
+    }
+
+    pub fn send(&self, message: &Message) {
+        let bytes = serde_json::to_vec(message).unwrap();
+        if let Err(err) = self.socket.send_to(&bytes, self.group) {
+            eprintln!("failed to broadcast to {}: {err}", self.group);
+        }
+    }
+
+    /// Every message that arrived since the last call, in order.
+    pub fn receive(&mut self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    if let SocketAddr::V4(from) = from {
+                        self.leader = Some(*from.ip());
+                    }
+                    match serde_json::from_slice(&buf[..len]) {
+                        Ok(message) => messages.push(message),
+                        Err(err) => eprintln!("ignoring malformed sync message: {err}"),
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    eprintln!("failed to receive from {}: {err}", self.group);
+                    break;
+                }
+            }
+        }
+        if let (Some(clock), Some(leader)) = (&mut self.clock, self.leader) {
+            clock.update(leader);
+        }
+        messages
+    }
+}
@@ -0,0 +1,300 @@
+//! The cellular structure of the swarm as it is each frame, in a layer of its own over the trails:
+//! the Delaunay triangulation of where the particles are seen, or the Voronoi diagram it's the dual
+//! of, the lines between the circumcenters of neighboring triangles bounding the cells around
+//! each particle. Lines are colored halfway between the trails of the particles either side of
+//! them, and the triangles or the cells can be filled faintly in their particles' colors too. The
+//! triangulation is built by inserting the particles one by one, Bowyer-Watson style, each
+//! carving out the triangles whose circumcircles it falls in and filling the hole with triangles
+//! of its own, which is plenty quick for the odd thousand particles. The layer is hidden until
+//! `[layers.tessellation]` shows it.
+
+use std::collections::{BTreeMap, HashSet};
+
+use nannou::color::IntoLinSrgba;
+use nannou::prelude::*;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::layers::LayerConfig;
+
+/// How far out the triangle holding every particle reaches, as a multiple of how far they spread.
+const SUPER_REACH: f64 = 20.0;
+
+/// Which diagram the tessellation draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Diagram {
+    /// The triangles joining each particle to its natural neighbors.
+    #[default]
+    Delaunay,
+    /// The cells of the world nearer each particle than any other.
+    Voronoi,
+}
+
+/// The `[tessellation]` section of the config.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TessellationConfig {
+    pub diagram: Diagram,
+    /// Width of the lines, in world units.
+    pub line_width: f32,
+    /// Alpha of the lines.
+    pub alpha: f32,
+    /// Alpha the triangles or cells are filled in at, 0 for them to be left empty. The cells of
+    /// the particles on the edge of the swarm go on for ever, so they're left empty whatever it is.
+    pub fill: f32,
+    /// Longest line drawn, in world units, so the odd escaper isn't joined to the swarm by
+    /// triangles across the whole window, or 0 for every line however long.
+    pub longest: f32,
+}
+
+impl Default for TessellationConfig {
+    fn default() -> Self {
+        TessellationConfig {
+            diagram: Diagram::default(),
+            line_width: 2.0,
+            alpha: 0.4,
+            fill: 0.0,
+            longest: 0.0,
+        }
+    }
+}
+
+impl TessellationConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.line_width <= 0.0 {
+            return Err(format!(
+                "the tessellation's line_width has to be positive, not {}",
+                self.line_width
+            ));
+        }
+        for (name, alpha) in [("alpha", self.alpha), ("fill", self.fill)] {
+            if !(0.0..=1.0).contains(&alpha) {
+                return Err(format!(
+                    "the tessellation's {name} goes from 0 to 1, not {alpha}"
+                ));
+            }
+        }
+        if self.longest < 0.0 {
+            return Err(format!(
+                "the tessellation's longest can't be negative, not {}",
+                self.longest
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draw the diagram of `particles`, where each is seen and the color of its trail, faded as
+    /// `layer` fades it.
+    pub fn draw(&self, particles: &[(Point2, Hsla)], layer: &LayerConfig, draw: &Draw) {
+        let positions: Vec<Point2> = particles.iter().map(|&(pos, _)| pos).collect();
+        let triangles = triangulate(&positions);
+        let color = |corners: &[usize], alpha: f32| {
+            let colors = corners.iter().map(|&i| particles[i].1.into_lin_srgba());
+            let sum = colors.fold([0.0; 3], |[r, g, b], color| {
+                [r + color.red, g + color.green, b + color.blue]
+            });
+            let [r, g, b] = sum.map(|channel| channel / corners.len() as f32);
+            layer.fade(LinSrgba::new(r, g, b, alpha))
+        };
+        let short = |a: Point2, b: Point2| self.longest <= 0.0 || a.distance(b) <= self.longest;
+        let line = |a: Point2, b: Point2, color: LinSrgba| {
+            draw.line()
+                .start(a)
+                .end(b)
+                .weight(self.line_width)
+                .color(color);
+        };
+        match self.diagram {
+            Diagram::Delaunay => {
+                let triangles = triangles.iter().filter(|triangle| {
+                    let [a, b, c] = triangle.map(|i| positions[i]);
+                    short(a, b) && short(b, c) && short(c, a)
+                });
+                let mut drawn = HashSet::new();
+                for triangle in triangles {
+                    if self.fill > 0.0 {
+                        let corners = triangle.map(|i| positions[i]);
+                        draw.tri()
+                            .points(corners[0], corners[1], corners[2])
+                            .color(color(triangle, self.fill));
+                    }
+                    for k in 0..3 {
+                        let (i, j) = (triangle[k], triangle[(k + 1) % 3]);
+                        if drawn.insert((i.min(j), i.max(j))) {
+                            line(positions[i], positions[j], color(&[i, j], self.alpha));
+                        }
+                    }
+                }
+            }
+            Diagram::Voronoi => {
+                let centers: Vec<Point2> = (triangles.iter())
+                    .map(|&triangle| circumcenter(triangle.map(|i| positions[i])))
+                    .collect();
+                if self.fill > 0.0 {
+                    for (i, cell) in cells(&positions, &triangles, &centers) {
+                        if cell.windows(2).all(|side| short(side[0], side[1])) {
+                            draw.polygon().points(cell).color(color(&[i], self.fill));
+                        }
+                    }
+                }
+                // each edge between two particles is crossed by the side of a cell between the
+                // circumcenters of the triangles either side of it
+                let mut sides: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+                for (t, triangle) in triangles.iter().enumerate() {
+                    for k in 0..3 {
+                        let (i, j) = (triangle[k], triangle[(k + 1) % 3]);
+                        sides.entry((i.min(j), i.max(j))).or_default().push(t);
+                    }
+                }
+                for ((i, j), either) in sides {
+                    if let [a, b] = either[..] {
+                        let (a, b) = (centers[a], centers[b]);
+                        if a.is_finite() && b.is_finite() && short(a, b) {
+                            line(a, b, color(&[i, j], self.alpha));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The triangles of the Delaunay triangulation of `points`, each as the indices of its corners,
+/// those that aren't numbers, and any after the first at the same point, left out.
+fn triangulate(points: &[Point2]) -> Vec<[usize; 3]> {
+    let mut seen = HashSet::new();
+    let inserted: Vec<usize> = (0..points.len())
+        .filter(|&i| {
+            let pos = points[i];
+            pos.is_finite() && seen.insert((pos.x.to_bits(), pos.y.to_bits()))
+        })
+        .collect();
+    if inserted.len() < 3 {
+        return Vec::new();
+    }
+    let mut corners: Vec<DVec2> = points.iter().map(|pos| pos.as_f64()).collect();
+    let (low, high) = inserted.iter().fold(
+        (DVec2::splat(f64::MAX), DVec2::splat(f64::MIN)),
+        |(low, high), &i| (low.min(corners[i]), high.max(corners[i])),
+    );
+    let middle = (low + high) / 2.0;
+    let reach = (high - low).max_element().max(1.0) * SUPER_REACH;
+    // a triangle holding every point, taken away again at the end
+    let outer = corners.len();
+    corners.extend([
+        middle + dvec2(-reach, -reach),
+        middle + dvec2(0.0, reach),
+        middle + dvec2(reach, -reach),
+    ]);
+    let mut triangles = vec![Triangle::new([outer, outer + 1, outer + 2], &corners)];
+    for &i in &inserted {
+        let pos = corners[i];
+        let (carved, kept): (Vec<Triangle>, Vec<Triangle>) = triangles
+            .into_iter()
+            .partition(|triangle| triangle.holds(pos));
+        triangles = kept;
+        // the edges around the hole, those of one carved triangle alone
+        let mut edges: BTreeMap<(usize, usize), (usize, usize)> = BTreeMap::new();
+        let mut shared = HashSet::new();
+        for triangle in &carved {
+            for k in 0..3 {
+                let (a, b) = (triangle.corners[k], triangle.corners[(k + 1) % 3]);
+                let key = (a.min(b), a.max(b));
+                if edges.insert(key, (a, b)).is_some() {
+                    shared.insert(key);
+                }
+            }
+        }
+        for (key, (a, b)) in edges {
+            if !shared.contains(&key) {
+                triangles.push(Triangle::new([a, b, i], &corners));
+            }
+        }
+    }
+    (triangles.into_iter())
+        .map(|triangle| triangle.corners)
+        .filter(|corners| corners.iter().all(|&i| i < outer))
+        .collect()
+}
+
+/// The cells of those of `points` off the edge of the swarm, each as the circumcenters of the
+/// `triangles` around it, at `centers`, in order round it.
+fn cells(
+    points: &[Point2],
+    triangles: &[[usize; 3]],
+    centers: &[Point2],
+) -> Vec<(usize, Vec<Point2>)> {
+    let mut around: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    let mut edges: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for (t, triangle) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            around.entry(triangle[k]).or_default().push(t);
+            let (i, j) = (triangle[k], triangle[(k + 1) % 3]);
+            *edges.entry((i.min(j), i.max(j))).or_default() += 1;
+        }
+    }
+    // those on an edge of one triangle alone are on the edge of the swarm
+    let edge: HashSet<usize> = (edges.into_iter())
+        .filter(|&(_, count)| count == 1)
+        .flat_map(|((i, j), _)| [i, j])
+        .collect();
+    (around.into_iter())
+        .filter(|(i, _)| !edge.contains(i))
+        .filter_map(|(i, around)| {
+            let middle = points[i];
+            let mut cell: Vec<Point2> = around.iter().map(|&t| centers[t]).collect();
+            if !cell.iter().all(|corner| corner.is_finite()) {
+                return None;
+            }
+            cell.sort_by(|a, b| {
+                let angle = |pos: &Point2| (pos.y - middle.y).atan2(pos.x - middle.x);
+                angle(a).total_cmp(&angle(b))
+            });
+            Some((i, cell))
+        })
+        .collect()
+}
+
+/// The point as far from each of `corners`, not being a number if they're in a line.
+fn circumcenter(corners: [Point2; 3]) -> Point2 {
+    let [a, b, c] = corners.map(|corner| corner.as_f64());
+    let (center, _) = circumcircle(a, b, c);
+    center.as_f32()
+}
+
+/// The middle of the circle through `a`, `b` and `c`, and its radius squared.
+fn circumcircle(a: DVec2, b: DVec2, c: DVec2) -> (DVec2, f64) {
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    let [a2, b2, c2] = [a, b, c].map(|corner| corner.length_squared());
+    let center = dvec2(
+        a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y),
+        a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x),
+    ) / d;
+    (center, center.distance_squared(a))
+}
+
+/// A triangle of the triangulation as it's built, with its circumcircle.
+struct Triangle {
+    corners: [usize; 3],
+    center: DVec2,
+    radius2: f64,
+}
+
+impl Triangle {
+    fn new(corners: [usize; 3], points: &[DVec2]) -> Self {
+        let [a, b, c] = corners.map(|i| points[i]);
+        let (center, radius2) = circumcircle(a, b, c);
+        Triangle {
+            corners,
+            center,
+            radius2,
+        }
+    }
+
+    /// Whether `pos` is within the circumcircle, as it is of one whose corners are in a line, so
+    /// those are carved out as soon as they can be.
+    fn holds(&self, pos: DVec2) -> bool {
+        !self.radius2.is_finite() || pos.distance_squared(self.center) < self.radius2
+    }
+}
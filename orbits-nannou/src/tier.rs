@@ -0,0 +1,295 @@
+//! Performance tiers, each a set of defaults for how many particles there are, how long their
+//! trails are and which of the background effects are drawn, heavier each than the last. The
+//! first time the piece is shown on a machine, the heaviest it draws smoothly is found by timing
+//! hidden renders of each in turn, and remembered from then on. Whatever the config sets itself
+//! is kept over the tier's defaults. As the piece runs, the trails can be cut shorter still should
+//! frames take too long after all, and grown back once they don't.
+
+use std::cell::Cell;
+use std::fs;
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::compare;
+use crate::config::Config;
+
+/// Where the tier measured for this machine is remembered, relative to the working directory.
+pub const TIER_PATH: &str = "trynannou.tier";
+/// Seconds of frames timed for each tier, after its trails have grown in.
+const TIMED_SECONDS: f32 = 2.0;
+/// Longest a tier's frames can take on average to count as drawn smoothly, leaving room for what
+/// the hidden renders leave out.
+const SMOOTH_FRAME: f32 = 1.0 / 75.0;
+
+/// How much of their length the trails lose each wall-clock second frames run long.
+const SHORTEN_RATE: f32 = 0.25;
+/// How much of their length the trails grow back each wall-clock second frames are quick.
+const LENGTHEN_RATE: f32 = 0.05;
+/// The share of the target frame time frames have to be under for the trails to grow back.
+const QUICK: f32 = 0.8;
+/// How much of the smoothed timings each frame's own makes up.
+const SMOOTHING: f32 = 0.1;
+
+/// The `[performance]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PerformanceConfig {
+    /// The tier whose defaults are used, or the one measured for this machine if left out.
+    pub tier: Option<Tier>,
+    /// Whether the trails are drawn shorter while frames take longer than `target`, and grown
+    /// back once they're quick again.
+    pub adaptive: bool,
+    /// Milliseconds a frame should take at most, a little over the display's refresh interval as
+    /// frames are never quicker than that.
+    pub target: f32,
+    /// The shortest the trails are cut to, as a fraction of the length they'd be drawn at.
+    pub min_length: f32,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        PerformanceConfig {
+            tier: None,
+            adaptive: false,
+            target: 18.0,
+            min_length: 0.25,
+        }
+    }
+}
+
+impl PerformanceConfig {
+    pub fn check(&self) -> Result<(), String> {
+        if self.target <= 0.0 {
+            return Err(format!(
+                "the performance's target has to be positive, not {}",
+                self.target
+            ));
+        }
+        if self.min_length <= 0.0 || self.min_length > 1.0 {
+            return Err(format!(
+                "the performance's min_length has to be above 0 and at most 1, not {}",
+                self.min_length
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    /// Fewer particles with shorter trails than usual, for integrated graphics and old laptops.
+    Low,
+    /// The usual defaults.
+    Medium,
+    /// Three times the particles with longer trails, over a star field.
+    High,
+    /// Six times the particles with longer trails still, over a star field and nebula.
+    Ultra,
+}
+
+impl Tier {
+    pub const ALL: [Tier; 4] = [Tier::Low, Tier::Medium, Tier::High, Tier::Ultra];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tier::Low => "low",
+            Tier::Medium => "medium",
+            Tier::High => "high",
+            Tier::Ultra => "ultra",
+        }
+    }
+
+    fn particles(self) -> usize {
+        match self {
+            Tier::Low => 12,
+            Tier::Medium => 16,
+            Tier::High => 48,
+            Tier::Ultra => 96,
+        }
+    }
+
+    fn history(self) -> usize {
+        match self {
+            Tier::Low => 120,
+            Tier::Medium => 200,
+            Tier::High => 300,
+            Tier::Ultra => 400,
+        }
+    }
+
+    /// Stars in each square the size of the average orbit's diameter.
+    fn star_density(self) -> f32 {
+        match self {
+            Tier::Low | Tier::Medium => 0.0,
+            Tier::High | Tier::Ultra => 60.0,
+        }
+    }
+
+    fn nebula_opacity(self) -> f32 {
+        match self {
+            Tier::Low | Tier::Medium | Tier::High => 0.0,
+            Tier::Ultra => 0.35,
+        }
+    }
+
+    /// Fill in whatever of the tier's defaults `config` doesn't set itself.
+    pub fn apply(self, config: &mut Config) {
+        if !config.sets("simulation", "particles") {
+            config.simulation.particles = self.particles();
+        }
+        if !config.sets("simulation", "history") {
+            config.simulation.history = self.history();
+        }
+        if !config.sets("stars", "density") {
+            config.stars.density = self.star_density();
+        }
+        if !config.sets("nebula", "opacity") {
+            config.nebula.opacity = self.nebula_opacity();
+        }
+    }
+
+    /// A config of just the tier's defaults, with as long a pre-roll as the trails take to grow.
+    fn config(self) -> String {
+        let pre_roll = self.history() as f32 * compare::FRAME_SECONDS;
+        format!(
+            "pre_roll = {pre_roll:?}\n\n\
+             [simulation]\nparticles = {}\nhistory = {}\n\n\
+             [stars]\ndensity = {:?}\n\n\
+             [nebula]\nopacity = {:?}\n",
+            self.particles(),
+            self.history(),
+            self.star_density(),
+            self.nebula_opacity(),
+        )
+    }
+
+    /// Average seconds a frame of the tier takes to simulate and render, found by timing a still
+    /// of it with and without `TIMED_SECONDS` of frames before it, so what it takes to start up
+    /// and write the still cancels out.
+    fn time_frames(self) -> Result<f32, String> {
+        let config = std::env::temp_dir().join(format!(
+            "trynannou-tier-{}-{}.toml",
+            self.name(),
+            std::process::id()
+        ));
+        fs::write(&config, self.config()).map_err(|err| format!("{}: {err}", config.display()))?;
+        let start = Instant::now();
+        let timed = compare::still(&config, 1, 0.0, Some(1.0))
+            .map(|_| start.elapsed())
+            .and_then(|untimed| {
+                let start = Instant::now();
+                compare::still(&config, 1, TIMED_SECONDS, Some(1.0))?;
+                Ok(start.elapsed().saturating_sub(untimed))
+            });
+        let _ = fs::remove_file(&config);
+        let frames = TIMED_SECONDS / compare::FRAME_SECONDS;
+        Ok(timed?.as_secs_f32() / frames)
+    }
+}
+
+/// Time each tier in turn, the lightest first, and pick the heaviest drawn smoothly, or the
+/// lightest if none is.
+fn measure() -> Result<Tier, String> {
+    let mut smooth = Tier::Low;
+    for tier in Tier::ALL {
+        let seconds = tier.time_frames()?;
+        println!("{}: {:.1}ms a frame", tier.name(), seconds * 1000.0);
+        if seconds > SMOOTH_FRAME {
+            break;
+        }
+        smooth = tier;
+    }
+    Ok(smooth)
+}
+
+/// Measure which tier suits this machine, and remember it.
+pub fn remeasure() -> Result<Tier, String> {
+    let tier = measure()?;
+    fs::write(TIER_PATH, format!("{}\n", tier.name()))
+        .map_err(|err| format!("{TIER_PATH}: {err}"))?;
+    Ok(tier)
+}
+
+/// The tier `config` asks for, or else the one remembered for this machine, measuring it first if
+/// it hasn't been yet. The usual defaults stand in should that fail.
+pub fn pick(config: &Config) -> Tier {
+    if let Some(tier) = config.performance.tier {
+        return tier;
+    }
+    let remembered = fs::read_to_string(TIER_PATH)
+        .ok()
+        .and_then(|text| (Tier::ALL.into_iter()).find(|tier| tier.name() == text.trim()));
+    if let Some(tier) = remembered {
+        return tier;
+    }
+    println!("timing this machine to pick how heavy a piece to show, which takes a moment");
+    remeasure().unwrap_or_else(|err| {
+        eprintln!("couldn't time this machine, so the usual defaults are used: {err}");
+        Tier::Medium
+    })
+}
+
+/// How long frames have been taking lately, and how much of their length the trails are drawn at
+/// for it.
+pub struct Quality {
+    config: PerformanceConfig,
+    /// Wall-clock seconds each frame has taken, smoothed.
+    frame_seconds: f32,
+    /// Wall-clock seconds stepping the simulation has taken each frame, smoothed.
+    physics_seconds: f32,
+    /// The fraction of their length the trails are drawn at, 1 for all of it.
+    length: f32,
+    /// Vertices of the trails built for the window, counted as they're drawn.
+    pub vertices: Cell<usize>,
+}
+
+impl Quality {
+    pub fn new(config: PerformanceConfig) -> Self {
+        Quality {
+            config,
+            frame_seconds: 0.0,
+            physics_seconds: 0.0,
+            length: 1.0,
+            vertices: Cell::new(0),
+        }
+    }
+
+    /// Take in a frame that took `frame_seconds`, `physics_seconds` of it stepping the simulation,
+    /// and cut the trails shorter or grow them back for it if `adapt`.
+    pub fn update(&mut self, frame_seconds: f32, physics_seconds: f32, adapt: bool) {
+        let smooth = |smoothed: f32, seconds: f32| match smoothed {
+            0.0 => seconds,
+            _ => smoothed + (seconds - smoothed) * SMOOTHING,
+        };
+        self.frame_seconds = smooth(self.frame_seconds, frame_seconds);
+        self.physics_seconds = smooth(self.physics_seconds, physics_seconds);
+        if !self.config.adaptive || !adapt {
+            self.length = 1.0;
+            return;
+        }
+        let target = self.config.target / 1000.0;
+        if self.frame_seconds > target {
+            self.length -= SHORTEN_RATE * frame_seconds;
+        } else if self.frame_seconds < target * QUICK {
+            self.length += LENGTHEN_RATE * frame_seconds;
+        }
+        self.length = self.length.clamp(self.config.min_length, 1.0);
+    }
+
+    /// The fraction of their length the trails are drawn at.
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Smoothed wall-clock seconds each frame takes, and stepping the simulation takes of it.
+    pub fn timings(&self) -> (f32, f32) {
+        (self.frame_seconds, self.physics_seconds)
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        self.config.adaptive
+    }
+}
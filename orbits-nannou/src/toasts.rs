@@ -0,0 +1,93 @@
+//! Short notes shown along the bottom of the window for a few seconds, so what's done from a
+//! hotkey, the panel or the tray has something to show for it: a screenshot or a clip written, a
+//! config or a palette loaded, or why one couldn't be. The newest is at the bottom, and each fades
+//! out as its time runs out. Each is said on the terminal as well, errors on stderr.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use nannou::prelude::*;
+
+use crate::layers::LayerConfig;
+
+/// Wall-clock seconds a toast is shown for, errors for longer.
+const SHOWN_SECONDS: f32 = 4.0;
+const ERROR_SECONDS: f32 = 8.0;
+/// Wall-clock seconds at the end of those it takes to fade out.
+const FADE_SECONDS: f32 = 0.5;
+/// The most shown at once, the oldest going first.
+const MOST: usize = 4;
+const LINE_HEIGHT: f32 = 28.0;
+const FONT_SIZE: u32 = 14;
+
+struct Toast {
+    text: String,
+    error: bool,
+    /// Wall-clock seconds it has left.
+    left: f32,
+}
+
+/// The toasts shown. They can be pushed from wherever the model is borrowed, drawing included.
+#[derive(Default)]
+pub struct Toasts {
+    shown: RefCell<VecDeque<Toast>>,
+}
+
+impl Toasts {
+    /// Toast that `text` was done.
+    pub fn info(&self, text: impl Into<String>) {
+        let text = text.into();
+        println!("{text}");
+        self.push(text, false, SHOWN_SECONDS);
+    }
+
+    /// Toast that something failed, as `text` says.
+    pub fn error(&self, text: impl Into<String>) {
+        let text = text.into();
+        eprintln!("{text}");
+        self.push(text, true, ERROR_SECONDS);
+    }
+
+    fn push(&self, text: String, error: bool, left: f32) {
+        let mut shown = self.shown.borrow_mut();
+        if shown.len() == MOST {
+            shown.pop_front();
+        }
+        shown.push_back(Toast { text, error, left });
+    }
+
+    /// Run the toasts' time down by `wall_seconds`, letting go of any that have run out.
+    pub fn update(&mut self, wall_seconds: f32) {
+        let shown = self.shown.get_mut();
+        for toast in shown.iter_mut() {
+            toast.left -= wall_seconds;
+        }
+        shown.retain(|toast| toast.left > 0.0);
+    }
+
+    /// Draw the toasts centered along the bottom of `win`, faded as `layer` is.
+    pub fn draw(&self, win: Rect, layer: &LayerConfig, draw: &Draw) {
+        let bottom = win.bottom() + 20.0;
+        for (i, toast) in self.shown.borrow().iter().rev().enumerate() {
+            let alpha = (toast.left / FADE_SECONDS).min(1.0);
+            let width =
+                (toast.text.len() as f32 * FONT_SIZE as f32 * 0.6 + LINE_HEIGHT).min(win.w());
+            let y = bottom + (i as f32 + 0.5) * (LINE_HEIGHT + 6.0);
+            let background = if toast.error {
+                rgba(0.45, 0.05, 0.05, 0.8 * alpha)
+            } else {
+                rgba(0.0, 0.0, 0.0, 0.7 * alpha)
+            };
+            draw.rect()
+                .x_y(win.x(), y)
+                .w_h(width, LINE_HEIGHT)
+                .color(layer.fade(background));
+            draw.text(&toast.text)
+                .x_y(win.x(), y)
+                .w_h(width, LINE_HEIGHT)
+                .font_size(FONT_SIZE)
+                .no_line_wrap()
+                .color(layer.fade(rgba(1.0, 1.0, 1.0, alpha)));
+        }
+    }
+}
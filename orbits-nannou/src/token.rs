@@ -0,0 +1,56 @@
+//! The piece as the platforms for generative work that mint a piece from a hash show it: given
+//! only a token, `--token` and the 64 hex digits of its hash, every choice made at random is made
+//! from the hash instead. Its first eight bytes are the seed, the next eight pick the palette and
+//! the eight after those how many particles there are, between a quarter of the config's and all
+//! of them. The same token under the same config is always the same piece.
+
+use orbits_core::palette;
+use orbits_core::settings::Settings;
+
+/// The share of the config's particles a token can ask for the fewest of.
+const FEWEST_PARTICLES: f32 = 0.25;
+
+/// A token's hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token([u8; 32]);
+
+impl Token {
+    /// The token whose hash is the 64 hex digits of `text`, with or without `0x` in front.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let digits = text.strip_prefix("0x").unwrap_or(text);
+        if digits.len() != 64 || !digits.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+            return Err(format!("a token's hash is 64 hex digits, not {text:?}"));
+        }
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        Ok(Token(bytes))
+    }
+
+    /// The hash, as the 64 hex digits after `0x` it's written as.
+    pub fn hex(&self) -> String {
+        let digits: String = self.0.iter().map(|byte| format!("{byte:02x}")).collect();
+        format!("0x{digits}")
+    }
+
+    /// The eight bytes of the hash from `at`, as a number.
+    fn word(&self, at: usize) -> u64 {
+        u64::from_be_bytes(self.0[at..at + 8].try_into().unwrap())
+    }
+
+    /// The seed of the first generation.
+    pub fn seed(&self) -> u64 {
+        self.word(0)
+    }
+
+    /// Make the choices the token makes of `settings`: the palette, and how many particles there
+    /// are.
+    pub fn apply(&self, settings: &mut Settings) {
+        let palettes = palette::names(&settings.palettes);
+        settings.palette = palettes[(self.word(8) % palettes.len() as u64) as usize].clone();
+        let most = settings.particles.max(2) as u64;
+        let fewest = ((most as f32 * FEWEST_PARTICLES) as u64).max(2);
+        settings.particles = (fewest + self.word(16) % (most - fewest + 1)) as usize;
+    }
+}
@@ -0,0 +1,221 @@
+use std::cell::RefCell;
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::gpu::{quad, Scene, Target, QUAD_LAYOUT, QUAD_VERTICES};
+
+/// How one scene gives way to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    #[default]
+    Crossfade,
+    /// The brightest parts of the incoming scene show through first.
+    LumaWipe,
+    /// A clock hand sweeps the incoming scene in.
+    RadialWipe,
+}
+
+/// How a build-in speeds up and slows down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    /// Quick at first, settling gently into place.
+    #[default]
+    EaseOut,
+    /// Gentle at both ends.
+    EaseInOut,
+}
+
+impl Easing {
+    fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// The `[transition]` section of the config.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransitionConfig {
+    pub effect: Effect,
+    /// How gradual the edge of a wipe is, as a fraction of the transition.
+    pub softness: f32,
+    /// Seconds over which a new generation's trails grow from nothing to full length and its
+    /// palette fades in from gray, or 0 to let them appear as they're laid down.
+    pub build_in: f32,
+    pub easing: Easing,
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        TransitionConfig {
+            effect: Effect::Crossfade,
+            softness: 0.1,
+            build_in: 0.0,
+            easing: Easing::EaseOut,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Uniforms {
+    progress: f32,
+    effect: u32,
+    softness: f32,
+    aspect: f32,
+}
+
+/// Renders the outgoing and incoming scenes to textures and blends them onto the frame.
+pub struct Transition {
+    config: TransitionConfig,
+    gpu: RefCell<Option<Gpu>>,
+}
+
+/// Everything on the GPU, made on first use and remade when the target changes size.
+struct Gpu {
+    size: [u32; 2],
+    msaa_samples: u32,
+    outgoing: Scene,
+    incoming: Scene,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniforms: wgpu::Buffer,
+    vertices: wgpu::Buffer,
+}
+
+impl Transition {
+    pub fn new(config: TransitionConfig) -> Self {
+        Transition {
+            config,
+            gpu: RefCell::new(None),
+        }
+    }
+
+    /// How far through its build-in a generation `age` wall-clock seconds old is, eased, from 0
+    /// to 1.
+    pub fn build_in(&self, age: f32) -> f32 {
+        if self.config.build_in <= 0.0 {
+            return 1.0;
+        }
+        self.config.easing.apply(age / self.config.build_in)
+    }
+
+    /// Draw `outgoing` giving way to `incoming`, `progress` of the way through. Both drawings are
+    /// in pixels, centered on the target.
+    pub fn draw(&self, target: &mut Target, outgoing: &Draw, incoming: &Draw, progress: f32) {
+        let (size, msaa_samples) = (target.size, target.msaa_samples);
+        let mut gpu = self.gpu.borrow_mut();
+        if gpu.as_ref().is_none_or(|gpu| {
+            gpu.size != size || gpu.msaa_samples != msaa_samples || !gpu.outgoing.is_current()
+        }) {
+            *gpu = Some(Gpu::new(target.device(), size, msaa_samples));
+        }
+        let gpu = gpu.as_mut().unwrap();
+
+        let uniforms = Uniforms {
+            progress: progress.clamp(0.0, 1.0),
+            effect: self.config.effect as u32,
+            softness: self.config.softness,
+            aspect: size[0] as f32 / size[1] as f32,
+        };
+        target
+            .queue()
+            .write_buffer(&gpu.uniforms, 0, unsafe { wgpu::bytes::from(&uniforms) });
+
+        let device = target.device_queue.device();
+        let scenes = [(&mut gpu.outgoing, outgoing), (&mut gpu.incoming, incoming)];
+        for (scene, draw) in scenes {
+            scene.render(device, target.encoder, draw);
+        }
+
+        let mut pass = wgpu::RenderPassBuilder::new()
+            .color_attachment(target.view, |color| color)
+            .begin(target.encoder);
+        pass.set_pipeline(&gpu.pipeline);
+        pass.set_vertex_buffer(0, gpu.vertices.slice(..));
+        pass.set_bind_group(0, &gpu.bind_group, &[]);
+        pass.draw(0..QUAD_VERTICES, 0..1);
+    }
+}
+
+impl Gpu {
+    fn new(device: &wgpu::Device, size: [u32; 2], msaa_samples: u32) -> Self {
+        let outgoing = Scene::new(device, size);
+        let incoming = Scene::new(device, size);
+
+        let sampler_desc = wgpu::SamplerBuilder::new().into_descriptor();
+        let filtering = wgpu::sampler_filtering(&sampler_desc);
+        let sampler = device.create_sampler(&sampler_desc);
+        let sample_type = wgpu::TextureSampleType::Float { filterable: true };
+        let bind_group_layout = wgpu::BindGroupLayoutBuilder::new()
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                sample_type,
+            )
+            .texture(
+                wgpu::ShaderStages::FRAGMENT,
+                false,
+                wgpu::TextureViewDimension::D2,
+                sample_type,
+            )
+            .sampler(wgpu::ShaderStages::FRAGMENT, filtering)
+            .uniform_buffer(wgpu::ShaderStages::FRAGMENT, false)
+            .build(device);
+
+        let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("transition uniforms"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = wgpu::BindGroupBuilder::new()
+            .texture_view(&outgoing.texture.view().build())
+            .texture_view(&incoming.texture.view().build())
+            .sampler(&sampler)
+            .buffer::<Uniforms>(&uniforms, 0..1)
+            .build(device, &bind_group_layout);
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("transition"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(&wgpu::include_wgsl!("shaders/transition.wgsl"));
+        let pipeline = wgpu::RenderPipelineBuilder::from_layout(&layout, &shader)
+            .vertex_entry_point("vs_main")
+            .fragment_shader(&shader)
+            .fragment_entry_point("fs_main")
+            .color_format(Frame::TEXTURE_FORMAT)
+            .color_blend(wgpu::BlendComponent::REPLACE)
+            .alpha_blend(wgpu::BlendComponent::REPLACE)
+            .add_vertex_buffer_layout(QUAD_LAYOUT)
+            .primitive_topology(wgpu::PrimitiveTopology::TriangleStrip)
+            .sample_count(msaa_samples)
+            .build(device);
+
+        let vertices = quad(device);
+
+        Gpu {
+            size,
+            msaa_samples,
+            outgoing,
+            incoming,
+            pipeline,
+            bind_group,
+            uniforms,
+            vertices,
+        }
+    }
+}
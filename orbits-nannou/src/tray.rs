@@ -0,0 +1,112 @@
+//! An icon in the system tray, so the window can be closed while the piece carries on. Only on
+//! Linux for now, where it's a freedesktop StatusNotifierItem.
+
+use std::sync::mpsc::{self, Receiver};
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Something picked from the tray icon's menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    Show,
+    Screenshot,
+    Reseed,
+    Quit,
+}
+
+/// What happens to the piece while its window is closed to the tray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnClose {
+    #[default]
+    Run,
+    Pause,
+}
+
+/// The `[tray]` section of the config.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrayConfig {
+    pub on_close: OnClose,
+}
+
+/// The tray icon, served from a thread of its own.
+pub struct TrayIcon {
+    pub config: TrayConfig,
+    requests: Receiver<Request>,
+    #[cfg(target_os = "linux")]
+    _handle: ksni::blocking::Handle<Menu>,
+}
+
+impl TrayIcon {
+    #[cfg(target_os = "linux")]
+    pub fn spawn(config: TrayConfig) -> Result<Self, String> {
+        use ksni::blocking::TrayMethods;
+
+        let (sender, requests) = mpsc::channel();
+        let handle = Menu { sender }
+            .spawn()
+            .map_err(|err| format!("couldn't add a tray icon: {err}"))?;
+        Ok(TrayIcon {
+            config,
+            requests,
+            _handle: handle,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn spawn(_config: TrayConfig) -> Result<Self, String> {
+        Err("a tray icon is only supported on Linux".into())
+    }
+
+    /// Requests made since the last call.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct Menu {
+    sender: mpsc::Sender<Request>,
+}
+
+#[cfg(target_os = "linux")]
+impl ksni::Tray for Menu {
+    fn id(&self) -> String {
+        env!("CARGO_PKG_NAME").into()
+    }
+
+    fn title(&self) -> String {
+        env!("CARGO_PKG_NAME").into()
+    }
+
+    fn icon_name(&self) -> String {
+        "applications-graphics".into()
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        let _ = self.sender.send(Request::Show);
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
+        let item = |label: &str, request: Request| {
+            ksni::menu::StandardItem {
+                label: label.into(),
+                activate: Box::new(move |menu: &mut Self| {
+                    // only fails once the app is quitting
+                    let _ = menu.sender.send(request);
+                }),
+                ..Default::default()
+            }
+            .into()
+        };
+        vec![
+            item("Show", Request::Show),
+            item("Screenshot", Request::Screenshot),
+            item("Reseed", Request::Reseed),
+            ksni::MenuItem::Separator,
+            item("Quit", Request::Quit),
+        ]
+    }
+}
@@ -0,0 +1,419 @@
+use nannou_egui::egui::{
+    self, emath::RectTransform, pos2, vec2, Color32, CtxRef, Pos2, Rect, Sense, Stroke,
+};
+
+use orbits_core::automation::{self, Automation, Easing, Keyframe};
+use orbits_core::params::{Param, Params};
+use orbits_core::session::{Change, GroupOp};
+
+use crate::dialogs::Dialog;
+use crate::layers::{Blend, Layer, LayersConfig};
+use crate::rewind::Rewind;
+use crate::selection::{Filter, Property, Selection};
+
+/// Pixel radius within which a click picks a keyframe.
+const PICK_RADIUS: f32 = 6.0;
+/// Range the particles picked out can be sped up or slowed by at once.
+const BOOST_RANGE: std::ops::RangeInclusive<f32> = 0.25..=4.0;
+
+/// State of the panel that lives between frames.
+pub struct PanelState {
+    pub visible: bool,
+    lane: Param,
+    /// Index of the selected keyframe in the current lane.
+    selected: Option<usize>,
+    /// What the particles are picked out by.
+    filter: Filter,
+    /// Hue the particles picked out are recolored to.
+    hue: f32,
+    /// Factor the particles picked out are boosted by.
+    boost: f32,
+    /// Name the particles picked out are tagged as.
+    tag: String,
+}
+
+impl Default for PanelState {
+    fn default() -> Self {
+        PanelState {
+            visible: false,
+            lane: Param::ALL[0],
+            selected: None,
+            filter: Filter::default(),
+            hue: 0.0,
+            boost: 1.5,
+            tag: String::new(),
+        }
+    }
+}
+
+/// What the panel asks of the rest of the app this frame.
+#[derive(Default)]
+pub struct PanelOutput {
+    pub changes: Vec<Change>,
+    pub save_automation: bool,
+    pub roll_dice: bool,
+    /// A filter to pick out the particles of the current generation by.
+    pub pick: Option<Filter>,
+    /// Whether to export the clip marked on the rewind timeline.
+    pub export_clip: bool,
+    /// A file dialog to open.
+    pub dialog: Option<Dialog>,
+}
+
+pub fn panel(
+    ctx: &CtxRef,
+    state: &mut PanelState,
+    params: &Params,
+    automation: &mut Automation,
+    selection: &mut Selection,
+    layers: &mut LayersConfig,
+    time: f32,
+) -> PanelOutput {
+    let mut output = PanelOutput::default();
+    if !state.visible {
+        return output;
+    }
+    egui::Window::new("trynannou").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.heading("Parameters");
+            if ui.button("roll the dice").clicked() {
+                output.roll_dice = true;
+            }
+        });
+        for param in Param::ALL {
+            let mut value = params.get(param);
+            if ui
+                .add(egui::Slider::new(&mut value, param.range()).text(param.label()))
+                .changed()
+            {
+                output.changes.push(Change::Set { param, value });
+            }
+        }
+
+        ui.separator();
+        ui.heading("Automation");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut automation.recording, "record");
+            if ui.button("save").clicked() {
+                output.save_automation = true;
+            }
+            if ui.button("clear lane").clicked() {
+                automation.lanes.remove(&state.lane);
+                state.selected = None;
+            }
+            if ui.button("clear camera").clicked() {
+                automation.camera.clear();
+            }
+        });
+        egui::ComboBox::from_label("lane")
+            .selected_text(state.lane.label())
+            .show_ui(ui, |ui| {
+                for param in Param::ALL {
+                    if ui
+                        .selectable_value(&mut state.lane, param, param.label())
+                        .clicked()
+                    {
+                        state.selected = None;
+                    }
+                }
+            });
+        let keys = automation.lanes.entry(state.lane).or_default();
+        lane_editor(ui, state, keys, time);
+        if let Some(key) = state.selected.and_then(|i| keys.get_mut(i)) {
+            ui.horizontal(|ui| {
+                ui.label(format!("{:.2}s = {:.3}", key.time, key.value));
+                egui::ComboBox::from_label("easing")
+                    .selected_text(format!("{:?}", key.easing))
+                    .show_ui(ui, |ui| {
+                        for easing in Easing::ALL {
+                            ui.selectable_value(&mut key.easing, easing, format!("{easing:?}"));
+                        }
+                    });
+            });
+        }
+
+        ui.separator();
+        ui.heading("Selection");
+        selection_editor(ui, state, selection, &mut output);
+
+        ui.separator();
+        ui.heading("Layers");
+        layer_editor(ui, layers);
+
+        ui.separator();
+        ui.heading("Files");
+        ui.horizontal(|ui| {
+            let dialogs = [
+                ("load config", Dialog::LoadConfig),
+                ("load palette", Dialog::LoadPalette),
+                ("save screenshot", Dialog::SaveScreenshot),
+                ("save svg", Dialog::SaveSvg),
+            ];
+            for (label, dialog) in dialogs {
+                if ui.button(label).clicked() {
+                    output.dialog = Some(dialog);
+                }
+            }
+        });
+    });
+    output
+}
+
+/// Picking out particles, by a filter or a tag, and doing things to those picked. Boxes are
+/// dragged out in the window itself.
+fn selection_editor(
+    ui: &mut egui::Ui,
+    state: &mut PanelState,
+    selection: &mut Selection,
+    output: &mut PanelOutput,
+) {
+    ui.label(format!(
+        "{} picked, shift-drag in the window to pick in a box",
+        selection.particles.len()
+    ));
+    ui.horizontal(|ui| {
+        let filter = &mut state.filter;
+        egui::ComboBox::from_id_source("property")
+            .selected_text(filter.property.label())
+            .show_ui(ui, |ui| {
+                for property in Property::ALL {
+                    ui.selectable_value(&mut filter.property, property, property.label());
+                }
+            });
+        let above = if filter.above { ">" } else { "<" };
+        if ui.button(above).clicked() {
+            filter.above = !filter.above;
+        }
+        ui.add(egui::DragValue::new(&mut filter.threshold).speed(0.01));
+        if ui.button("pick").clicked() {
+            output.pick = Some(*filter);
+        }
+        if ui.button("clear").clicked() {
+            selection.particles.clear();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut state.tag);
+        let name = state.tag.trim();
+        if ui
+            .add_enabled(!name.is_empty(), egui::Button::new("tag"))
+            .clicked()
+        {
+            selection.tag(name);
+        }
+    });
+    ui.horizontal_wrapped(|ui| {
+        let mut recalled = None;
+        for name in selection.tags.keys() {
+            if ui.button(name).clicked() {
+                recalled = Some(name.clone());
+            }
+        }
+        if let Some(name) = recalled {
+            selection.recall(&name);
+        }
+    });
+
+    let picked = !selection.particles.is_empty();
+    let mut op = None;
+    ui.horizontal(|ui| {
+        ui.add(egui::Slider::new(&mut state.hue, 0.0..=1.0).text("hue"));
+        if ui
+            .add_enabled(picked, egui::Button::new("recolor"))
+            .clicked()
+        {
+            op = Some(GroupOp::Recolor { hue: state.hue });
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.add(egui::Slider::new(&mut state.boost, BOOST_RANGE).text("times"));
+        if ui.add_enabled(picked, egui::Button::new("boost")).clicked() {
+            op = Some(GroupOp::Boost {
+                factor: state.boost,
+            });
+        }
+    });
+    ui.horizontal(|ui| {
+        for (label, group_op) in [
+            ("freeze", GroupOp::Freeze),
+            ("thaw", GroupOp::Thaw),
+            ("delete", GroupOp::Delete),
+        ] {
+            if ui.add_enabled(picked, egui::Button::new(label)).clicked() {
+                op = Some(group_op);
+            }
+        }
+    });
+    if let Some(op) = op {
+        output.changes.push(Change::Group {
+            particles: selection.indices(),
+            op,
+        });
+    }
+}
+
+/// Showing, fading and blending each layer of the scene, the top one first, and moving those that
+/// can be moved up and down.
+fn layer_editor(ui: &mut egui::Ui, layers: &mut LayersConfig) {
+    let stack = layers.stack();
+    let movable = layers.order().len();
+    let mut raised = None;
+    for (i, &layer) in stack.iter().enumerate().rev() {
+        ui.horizontal(|ui| {
+            let config = layers.get_mut(layer);
+            ui.checkbox(&mut config.visible, layer.label());
+            ui.add(egui::Slider::new(&mut config.opacity, 0.0..=1.0));
+            if layer != Layer::Background {
+                egui::ComboBox::from_id_source(("blend", layer.label()))
+                    .selected_text(config.blend.label())
+                    .show_ui(ui, |ui| {
+                        for blend in Blend::ALL {
+                            ui.selectable_value(&mut config.blend, blend, blend.label());
+                        }
+                    });
+            }
+            if layer != Layer::Overlays {
+                let spin = egui::DragValue::new(&mut config.spin).speed(0.1);
+                ui.add(spin.suffix("°/s"));
+            }
+            if layer.is_movable() {
+                // its place in the order, the background being under it
+                let place = i - 1;
+                let up = ui.add_enabled(place + 1 < movable, egui::Button::new("up"));
+                if up.clicked() {
+                    raised = Some(place);
+                }
+                let down = ui.add_enabled(place > 0, egui::Button::new("down"));
+                if down.clicked() {
+                    raised = Some(place - 1);
+                }
+            }
+        });
+    }
+    if let Some(place) = raised {
+        layers.raise(place);
+    }
+}
+
+/// A window alongside the panel scrubbing along the stretch of the run kept, to mark a clip of it
+/// to export, once anything's been kept.
+pub fn rewind(ctx: &CtxRef, state: &PanelState, rewind: &mut Rewind, output: &mut PanelOutput) {
+    let Some((first, last)) = rewind.span().filter(|_| state.visible) else {
+        return;
+    };
+    egui::Window::new("rewind").show(ctx, |ui| rewind_editor(ui, rewind, (first, last), output));
+}
+
+/// Scrubbing along the stretch of the run kept, `span`, to mark a clip of it to export.
+fn rewind_editor(
+    ui: &mut egui::Ui,
+    rewind: &mut Rewind,
+    span: (f32, f32),
+    output: &mut PanelOutput,
+) {
+    let (first, last) = span;
+    ui.add(egui::Slider::new(&mut rewind.cursor, first..=last).suffix("s"));
+    ui.horizontal(|ui| {
+        if ui.button("mark in").clicked() {
+            rewind.marks[0] = Some(rewind.cursor);
+        }
+        if ui.button("mark out").clicked() {
+            rewind.marks[1] = Some(rewind.cursor);
+        }
+        let fps = egui::DragValue::new(&mut rewind.fps).clamp_range(1.0..=240.0);
+        ui.add(fps.suffix(" fps"));
+        let marked = rewind.marks.iter().all(Option::is_some);
+        if ui
+            .add_enabled(marked, egui::Button::new("export clip"))
+            .clicked()
+        {
+            output.export_clip = true;
+        }
+        if ui
+            .add_enabled(marked, egui::Button::new("export clip to"))
+            .clicked()
+        {
+            output.dialog = Some(Dialog::ExportClip);
+        }
+    });
+    let mark = |mark: Option<f32>| mark.map_or("-".to_string(), |mark| format!("{mark:.1}s"));
+    ui.label(format!(
+        "in at {}, out at {}",
+        mark(rewind.marks[0]),
+        mark(rewind.marks[1])
+    ));
+}
+
+/// A plot of the selected lane. Click to add a keyframe, drag one to move it, and right click one
+/// to delete it.
+fn lane_editor(ui: &mut egui::Ui, state: &mut PanelState, keys: &mut Vec<Keyframe>, time: f32) {
+    let span = keys.last().map_or(0.0, |key| key.time).max(time) * 1.1 + 5.0;
+    let range = state.lane.range();
+
+    let (response, painter) = ui.allocate_painter(vec2(360.0, 120.0), Sense::click_and_drag());
+    let to_screen = RectTransform::from_to(
+        Rect::from_min_max(pos2(0.0, *range.end()), pos2(span, *range.start())),
+        response.rect,
+    );
+    let from_screen = to_screen.inverse();
+    let key_pos = |key: &Keyframe| to_screen.transform_pos(pos2(key.time, key.value));
+    let pick = |pointer: Pos2, keys: &[Keyframe]| {
+        keys.iter()
+            .position(|key| key_pos(key).distance(pointer) < PICK_RADIUS)
+    };
+
+    if let Some(pointer) = response.interact_pointer_pos() {
+        let world = from_screen.transform_pos_clamped(pointer);
+        if response.drag_started() || response.clicked() || response.secondary_clicked() {
+            state.selected = pick(pointer, keys);
+        }
+        if response.secondary_clicked() {
+            if let Some(i) = state.selected.take() {
+                keys.remove(i);
+            }
+        } else if response.clicked() && state.selected.is_none() {
+            keys.push(Keyframe {
+                time: world.x.max(0.0),
+                value: world.y,
+                easing: Easing::Linear,
+            });
+        } else if response.dragged() {
+            if let Some(key) = state.selected.and_then(|i| keys.get_mut(i)) {
+                key.time = world.x.max(0.0);
+                key.value = world.y;
+            }
+        }
+        // keep the selection on the same keyframe as the lane is re-sorted
+        let selected = state.selected.map(|i| keys[i]);
+        automation::sort(keys);
+        state.selected = selected.and_then(|key| keys.iter().position(|k| *k == key));
+    }
+
+    painter.rect_filled(response.rect, 2.0, Color32::from_gray(24));
+    let curve: Vec<Pos2> = (0..=100)
+        .filter_map(|i| {
+            let t = span * i as f32 / 100.0;
+            automation::sample(keys, t).map(|v| to_screen.transform_pos(pos2(t, v)))
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        curve,
+        Stroke::new(1.5, Color32::from_rgb(120, 180, 255)),
+    ));
+    for (i, key) in keys.iter().enumerate() {
+        let color = if Some(i) == state.selected {
+            Color32::YELLOW
+        } else {
+            Color32::WHITE
+        };
+        painter.circle_filled(key_pos(key), 4.0, color);
+    }
+    let playhead = to_screen.transform_pos(pos2(time, *range.start())).x;
+    painter.line_segment(
+        [
+            pos2(playhead, response.rect.top()),
+            pos2(playhead, response.rect.bottom()),
+        ],
+        Stroke::new(1.0, Color32::from_rgb(255, 120, 120)),
+    );
+}
@@ -0,0 +1,148 @@
+//! How the world is mapped onto a window: so many points across the window to a world unit, about
+//! a point of the world in its middle. Picking, dragging attractors, the brush, the minimap and
+//! the guides all go between the world and the window through one, so every one of them agrees
+//! with what's drawn.
+
+use nannou::prelude::*;
+use orbits_core::orbital_radius;
+
+/// How much room is left around the average orbit by the view of the whole system.
+const MARGIN: f32 = 1.1;
+
+/// A view of the world, as points across the window to a world unit and the point of the world in
+/// the middle of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Points across the window a world unit takes up.
+    pub scale: f32,
+    /// The point of the world in the middle of the window.
+    pub middle: Point2,
+}
+
+impl Viewport {
+    pub fn new(scale: f32, middle: Point2) -> Self {
+        Viewport { scale, middle }
+    }
+
+    /// The view fitting as much of `region` of the world into `win` as fills it, the rest of `win`
+    /// showing more either side of it.
+    pub fn fitting(region: Rect, win: Rect) -> Self {
+        let scale = (win.w() / region.w()).min(win.h() / region.h());
+        Viewport::new(scale, region.xy())
+    }
+
+    /// The view of the whole system in `win`, as the piece starts, with room left around it.
+    pub fn whole_system(win: Rect) -> Self {
+        let side = 2.0 * orbital_radius() * MARGIN;
+        Viewport::fitting(Rect::from_w_h(side, side), win)
+    }
+
+    /// Where `pos`, a point of the world, is in the window, in points from its middle.
+    pub fn to_screen(self, pos: Point2) -> Point2 {
+        (pos - self.middle) * self.scale
+    }
+
+    /// The point of the world under `pos`, in points from the middle of the window.
+    pub fn to_world(self, pos: Point2) -> Point2 {
+        pos / self.scale + self.middle
+    }
+
+    /// Points across the window `units` world units take up.
+    pub fn to_points(self, units: f32) -> f32 {
+        units * self.scale
+    }
+
+    /// World units `points` across the window take up.
+    pub fn to_units(self, points: f32) -> f32 {
+        points / self.scale
+    }
+
+    /// The part of the world shown in `win`.
+    pub fn shown(self, win: Rect) -> Rect {
+        Rect::from_xy_wh(self.middle, win.wh() / self.scale)
+    }
+
+    /// This view shown in `pane` of a window rather than filling it, as a view of the whole window.
+    pub fn within(self, pane: Rect) -> Self {
+        Viewport::new(self.scale, self.middle - pane.xy() / self.scale)
+    }
+
+    /// Map world coordinates onto `screen`, a drawing of the window in points from its middle.
+    pub fn project(self, screen: &Draw) -> Draw {
+        screen.scale(self.scale).translate(-self.middle.extend(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Point2, b: Point2) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let viewport = Viewport::new(0.37, pt2(120.0, -45.0));
+        for pos in [pt2(0.0, 0.0), pt2(300.0, 200.0), pt2(-1000.0, 640.0)] {
+            assert!(close(viewport.to_world(viewport.to_screen(pos)), pos));
+            assert!(close(viewport.to_screen(viewport.to_world(pos)), pos));
+        }
+        assert!((viewport.to_units(viewport.to_points(12.5)) - 12.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn middle_of_the_world_is_in_the_middle_of_the_window() {
+        let viewport = Viewport::new(2.0, pt2(50.0, 50.0));
+        assert_eq!(viewport.to_screen(pt2(50.0, 50.0)), Point2::ZERO);
+        assert_eq!(viewport.to_screen(pt2(60.0, 40.0)), pt2(20.0, -20.0));
+    }
+
+    #[test]
+    fn fitting_fills_the_tighter_side() {
+        let win = Rect::from_w_h(1600.0, 900.0);
+        let viewport = Viewport::fitting(Rect::from_x_y_w_h(10.0, 20.0, 200.0, 200.0), win);
+        assert_eq!(viewport.scale, 4.5);
+        assert_eq!(viewport.middle, pt2(10.0, 20.0));
+        let shown = viewport.shown(win);
+        assert!((shown.h() - 200.0).abs() < 1e-3);
+        assert!(shown.w() > 200.0);
+        assert!(close(shown.xy(), pt2(10.0, 20.0)));
+    }
+
+    #[test]
+    fn the_whole_system_fits_with_room_around_it() {
+        let win = Rect::from_w_h(1280.0, 720.0);
+        let viewport = Viewport::whole_system(win);
+        assert_eq!(viewport.middle, Point2::ZERO);
+        let shown = viewport.shown(win);
+        assert!(shown.h() > 2.0 * orbital_radius());
+        assert!(shown.w() > shown.h());
+    }
+
+    #[test]
+    fn a_view_within_a_pane_shows_its_middle_in_the_pane() {
+        let pane = Rect::from_x_y_w_h(300.0, -100.0, 400.0, 200.0);
+        let viewport = Viewport::new(1.5, pt2(20.0, 30.0));
+        let within = viewport.within(pane);
+        assert!(close(within.to_screen(pt2(20.0, 30.0)), pane.xy()));
+        let pos = pt2(-40.0, 70.0);
+        assert!(close(
+            within.to_screen(pos),
+            pane.xy() + viewport.to_screen(pos)
+        ));
+        assert_eq!(within.scale, viewport.scale);
+    }
+
+    #[test]
+    fn the_window_shows_what_its_corners_are_over() {
+        let win = Rect::from_w_h(800.0, 600.0);
+        let viewport = Viewport::new(0.5, pt2(-100.0, 300.0));
+        let shown = viewport.shown(win);
+        assert!(close(viewport.to_world(win.top_right()), shown.top_right()));
+        assert!(close(
+            viewport.to_world(win.bottom_left()),
+            shown.bottom_left()
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Read;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::respond;
+
+/// How many recent frames the frame-time percentiles are taken over.
+const FRAME_WINDOW: usize = 600;
+/// How long a connection has to send its request, so one that never does, like a load
+/// balancer's health check, doesn't hold up the scrapes behind it.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+/// Where launches are counted, so restarts by a supervisor show up in the metrics.
+const STARTS_PATH: &str = "trynannou.starts";
+
+struct Snapshot {
+    /// The most recent frames' times, for the percentiles and the frame rate.
+    frame_times: VecDeque<f32>,
+    /// Frames since the start, and the seconds all of them took, as a summary counts them.
+    frames: u64,
+    frame_seconds: f64,
+    particles: usize,
+}
+
+/// A Prometheus `/metrics` endpoint for keeping an eye on long-running installations.
+pub struct Metrics {
+    snapshot: Arc<Mutex<Snapshot>>,
+}
+
+impl Metrics {
+    pub fn serve(addr: SocketAddr) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|err| format!("metrics on {addr}: {err}"))?;
+        let restarts = count_start();
+        let started = Instant::now();
+        let snapshot = Arc::new(Mutex::new(Snapshot {
+            frame_times: VecDeque::with_capacity(FRAME_WINDOW),
+            frames: 0,
+            frame_seconds: 0.0,
+            particles: 0,
+        }));
+        let shared = snapshot.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let body = render(&shared.lock().unwrap(), started, restarts);
+                if let Err(err) = answer(stream, &body) {
+                    eprintln!("metrics: {err}");
+                }
+            }
+        });
+        Ok(Metrics { snapshot })
+    }
+
+    /// Note that a frame took `seconds` and drew `particles` particles.
+    pub fn frame(&self, seconds: f32, particles: usize) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if snapshot.frame_times.len() == FRAME_WINDOW {
+            snapshot.frame_times.pop_front();
+        }
+        snapshot.frame_times.push_back(seconds);
+        snapshot.frames += 1;
+        snapshot.frame_seconds += seconds as f64;
+        snapshot.particles = particles;
+    }
+}
+
+/// Bump the launch count on disk, returning how many launches came before this one.
+fn count_start() -> u64 {
+    let previous = fs::read_to_string(STARTS_PATH)
+        .ok()
+        .and_then(|starts| starts.trim().parse().ok())
+        .unwrap_or(0);
+    if let Err(err) = fs::write(STARTS_PATH, format!("{}\n", previous + 1)) {
+        eprintln!("failed to count this start in {STARTS_PATH}: {err}");
+    }
+    previous
+}
+
+fn answer(mut stream: TcpStream, body: &str) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(READ_TIMEOUT))
+        .map_err(|err| err.to_string())?;
+    let mut request = [0; 1024];
+    let len = stream.read(&mut request).map_err(|err| err.to_string())?;
+    if request[..len].starts_with(b"GET /metrics ") {
+        respond(stream, "200 OK", "text/plain; version=0.0.4", body)
+    } else {
+        respond(stream, "404 Not Found", "text/plain", "")
+    }
+}
+
+fn render(snapshot: &Snapshot, started: Instant, restarts: u64) -> String {
+    let mut sorted: Vec<f32> = snapshot.frame_times.iter().copied().collect();
+    sorted.sort_by(f32::total_cmp);
+    let total: f32 = sorted.iter().sum();
+    let fps = if total > 0.0 {
+        sorted.len() as f32 / total
+    } else {
+        0.0
+    };
+
+    let mut out = String::new();
+    let mut gauge = |name: &str, help: &str, value: String| {
+        let _ = writeln!(
+            out,
+            "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}"
+        );
+    };
+    gauge(
+        "trynannou_fps",
+        "Frames per second over recent frames.",
+        fps.to_string(),
+    );
+    gauge(
+        "trynannou_particles",
+        "Particles being simulated.",
+        snapshot.particles.to_string(),
+    );
+    gauge(
+        "trynannou_uptime_seconds",
+        "Seconds since this instance started.",
+        started.elapsed().as_secs_f64().to_string(),
+    );
+    gauge(
+        "trynannou_restarts",
+        "Launches before this one, counted in the working directory.",
+        restarts.to_string(),
+    );
+
+    let name = "trynannou_frame_seconds";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Frame time, its quantiles over recent frames."
+    );
+    let _ = writeln!(out, "# TYPE {name} summary");
+    for quantile in [0.5, 0.9, 0.99] {
+        let value = sorted
+            .get(((sorted.len() as f32 * quantile) as usize).min(sorted.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or(0.0);
+        let _ = writeln!(out, "{name}{{quantile=\"{quantile}\"}} {value}");
+    }
+    let _ = writeln!(out, "{name}_sum {}", snapshot.frame_seconds);
+    let _ = writeln!(out, "{name}_count {}", snapshot.frames);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_summary_counts_every_frame() {
+        let metrics = Metrics {
+            snapshot: Arc::new(Mutex::new(Snapshot {
+                frame_times: VecDeque::new(),
+                frames: 0,
+                frame_seconds: 0.0,
+                particles: 0,
+            })),
+        };
+        for _ in 0..2 * FRAME_WINDOW {
+            metrics.frame(0.5, 16);
+        }
+        let snapshot = metrics.snapshot.lock().unwrap();
+        assert_eq!(snapshot.frame_times.len(), FRAME_WINDOW);
+        let out = render(&snapshot, Instant::now(), 0);
+        assert!(out.contains(&format!(
+            "trynannou_frame_seconds_count {}",
+            2 * FRAME_WINDOW
+        )));
+        assert!(out.contains(&format!("trynannou_frame_seconds_sum {}", FRAME_WINDOW)));
+    }
+}
@@ -0,0 +1,170 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tungstenite::Message;
+
+use orbits_core::params::{Param, Params};
+
+pub mod metrics;
+
+const CONTROL_PAGE: &str = include_str!("control.html");
+/// How often each connected page is checked for new requests and sent fresh values.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Something a control page asks the piece to do.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    Set { param: Param, value: f32 },
+    Reseed,
+    NextCue,
+}
+
+/// What control pages are told about each parameter.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct ParamState {
+    param: Param,
+    label: &'static str,
+    min: f32,
+    max: f32,
+    value: f32,
+}
+
+/// A tiny HTTP server offering a page of sliders, bound to the parameters over a WebSocket, so
+/// the piece can be adjusted from a phone on the same network.
+pub struct WebControl {
+    requests: Receiver<Request>,
+    params: Arc<Mutex<Vec<ParamState>>>,
+}
+
+impl WebControl {
+    pub fn serve(addr: SocketAddr) -> Result<Self, String> {
+        let listener =
+            TcpListener::bind(addr).map_err(|err| format!("web control on {addr}: {err}"))?;
+        println!("control panel at http://{addr}/");
+        let (sender, requests) = mpsc::channel();
+        let params = Arc::new(Mutex::new(Vec::new()));
+        let shared = params.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let sender = sender.clone();
+                let params = shared.clone();
+                thread::spawn(move || {
+                    if let Err(err) = handle(stream, sender, params) {
+                        eprintln!("web control: {err}");
+                    }
+                });
+            }
+        });
+        Ok(WebControl { requests, params })
+    }
+
+    /// Requests made since the last call.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.try_iter().collect()
+    }
+
+    /// Publish the current parameter values to every connected page.
+    pub fn publish(&self, params: &Params) {
+        let states = params
+            .iter()
+            .map(|(param, value)| ParamState {
+                param,
+                label: param.label(),
+                min: *param.range().start(),
+                max: *param.range().end(),
+                value,
+            })
+            .collect();
+        *self.params.lock().unwrap() = states;
+    }
+}
+
+fn handle(
+    stream: TcpStream,
+    sender: Sender<Request>,
+    params: Arc<Mutex<Vec<ParamState>>>,
+) -> Result<(), String> {
+    // peek, so the WebSocket handshake can read the request for itself
+    let mut head = [0; 1024];
+    let len = stream.peek(&mut head).map_err(|err| err.to_string())?;
+    let head = String::from_utf8_lossy(&head[..len]);
+    if head.starts_with("GET /socket") {
+        socket(stream, sender, params)
+    } else {
+        page(stream, &head)
+    }
+}
+
+fn page(mut stream: TcpStream, head: &str) -> Result<(), String> {
+    let mut request = vec![0; 1024];
+    let _ = stream.read(&mut request);
+    if head.starts_with("GET / ") {
+        respond(stream, "200 OK", "text/html; charset=utf-8", CONTROL_PAGE)
+    } else {
+        respond(stream, "404 Not Found", "text/plain", "")
+    }
+}
+
+/// Write a complete HTTP response and close the connection.
+fn respond(
+    mut stream: TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> Result<(), String> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|err| err.to_string())
+}
+
+fn socket(
+    stream: TcpStream,
+    sender: Sender<Request>,
+    params: Arc<Mutex<Vec<ParamState>>>,
+) -> Result<(), String> {
+    let mut socket = tungstenite::accept(stream).map_err(|err| err.to_string())?;
+    socket
+        .get_mut()
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|err| err.to_string())?;
+    let mut sent = Vec::new();
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(request) => {
+                    if sender.send(request).is_err() {
+                        // the app is shutting down
+                        return Ok(());
+                    }
+                }
+                Err(err) => eprintln!("web control: ignoring {text:?}: {err}"),
+            },
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(err) => return Err(err.to_string()),
+        }
+
+        let current = params.lock().unwrap().clone();
+        if current != sent {
+            let text = serde_json::json!({ "params": current }).to_string();
+            socket
+                .send(Message::Text(text))
+                .map_err(|err| err.to_string())?;
+            sent = current;
+        }
+    }
+}
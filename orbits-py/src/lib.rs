@@ -0,0 +1,185 @@
+//! Python bindings for the simulation behind trynannou, so the same dynamics can be driven from a
+//! notebook and compared against what the piece renders. Built by maturin as the `orbits` module.
+
+use std::collections::HashMap;
+
+use nannou_core::rand::random;
+use orbits_core::params::Param;
+use orbits_core::settings::{self, Settings};
+use orbits_core::{orbital_radius, simulation, trails};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+
+/// A run of the piece, stepped by hand. Positions are in world units, with the average orbit
+/// `orbital_radius` from the origin, and times in seconds.
+#[pyclass]
+struct Simulation(simulation::Simulation);
+
+#[pymethods]
+impl Simulation {
+    /// Seed a run with `seed`, or at random, and set any of the `params`, by name, to start with.
+    /// `config` sets the simulation settings for the rest of the process, as a dict of the keys
+    /// of trynannou's `[simulation]` section or the text of a trynannou config, whose
+    /// `[simulation]` section is used. It can only be given to the first simulation made.
+    #[new]
+    #[pyo3(signature = (seed=None, params=None, config=None))]
+    fn new(
+        seed: Option<u64>,
+        params: Option<HashMap<String, f32>>,
+        config: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        if let Some(config) = config {
+            settings::set(parse_config(config)?).map_err(|err| {
+                PyValueError::new_err(format!(
+                    "{err}: config can only be given to the first Simulation of a process"
+                ))
+            })?;
+        }
+        let mut sim = simulation::Simulation::new(seed.unwrap_or_else(random));
+        for (name, value) in params.unwrap_or_default() {
+            sim.params.set(param(&name)?, value);
+        }
+        Ok(Simulation(sim))
+    }
+
+    /// Advance by `dt` seconds of wall-clock time, slowed by bullet time.
+    fn step(&mut self, dt: f32) -> PyResult<()> {
+        if !dt.is_finite() || dt < 0.0 {
+            return Err(PyValueError::new_err(format!("can't step by {dt} seconds")));
+        }
+        self.0.step(dt);
+        Ok(())
+    }
+
+    /// Start a new generation, seeded with `seed` or at random, fading in over the last.
+    #[pyo3(signature = (seed=None))]
+    fn reseed(&mut self, seed: Option<u64>) {
+        self.0.reseed(seed.unwrap_or_else(random));
+    }
+
+    /// Simulated seconds since the start.
+    #[getter]
+    fn time(&self) -> f32 {
+        self.0.time
+    }
+
+    /// How fast simulated time is running relative to the wall clock.
+    #[getter]
+    fn time_scale(&self) -> f32 {
+        let multiplier = self.0.params.get(Param::TimeScale);
+        self.0.time_scale.scale(1.0, multiplier)
+    }
+
+    /// The radius of the average orbit, in world units, as the config set it.
+    #[getter]
+    fn orbital_radius(&self) -> f32 {
+        orbital_radius()
+    }
+
+    /// The current generation's particles, as `(x, y)`.
+    fn positions(&self) -> Vec<(f32, f32)> {
+        let particles = &self.0.current().particles;
+        particles.iter().map(|p| (p.pos.x, p.pos.y)).collect()
+    }
+
+    /// The current generation's velocities, as `(x, y)`.
+    fn velocities(&self) -> Vec<(f32, f32)> {
+        let particles = &self.0.current().particles;
+        particles.iter().map(|p| (p.vel.x, p.vel.y)).collect()
+    }
+
+    /// The current generation's colors, as `(hue, saturation, lightness, alpha)`, each from 0 to 1.
+    fn colors(&self) -> Vec<(f32, f32, f32, f32)> {
+        let colors = &self.0.current().colors;
+        colors
+            .iter()
+            .map(|c| {
+                let hue = c.hue.to_positive_degrees() / 360.0;
+                (hue, c.saturation, c.lightness, c.alpha)
+            })
+            .collect()
+    }
+
+    /// The trail left by `particle` of the current generation, as `(x, y)`, newest first.
+    fn trail(&self, particle: usize) -> PyResult<Vec<(f32, f32)>> {
+        let particles = self.0.current().particles.len();
+        if particle >= particles {
+            return Err(PyValueError::new_err(format!(
+                "there are only {particles} particles"
+            )));
+        }
+        let trail = trails::trail(self.0.current(), particle, 1.0, 0.0, 1.0);
+        Ok(trail.into_iter().map(|(pos, _)| (pos.x, pos.y)).collect())
+    }
+
+    /// The central bodies, as `(x, y, gm)`.
+    fn bodies(&self) -> Vec<(f32, f32, f32)> {
+        let bodies = self.0.bodies();
+        bodies.iter().map(|b| (b.pos.x, b.pos.y, b.gm)).collect()
+    }
+
+    fn get_param(&self, name: &str) -> PyResult<f32> {
+        Ok(self.0.params.get(param(name)?))
+    }
+
+    /// Set parameter `name`, clamped to its range.
+    fn set_param(&mut self, name: &str, value: f32) -> PyResult<()> {
+        self.0.params.set(param(name)?, value);
+        Ok(())
+    }
+}
+
+/// The settings `config` gives, a dict of the `[simulation]` section's keys or the text of a
+/// trynannou config.
+fn parse_config(config: &Bound<'_, PyAny>) -> PyResult<Settings> {
+    let invalid = |err: String| PyValueError::new_err(format!("invalid config: {err}"));
+    if let Ok(text) = config.downcast::<PyString>() {
+        let mut table: toml::Table =
+            toml::from_str(text.to_str()?).map_err(|err| invalid(err.to_string()))?;
+        let simulation = table
+            .remove("simulation")
+            .unwrap_or(toml::Table::new().into());
+        return simulation
+            .try_into()
+            .map_err(|err| invalid(err.to_string()));
+    }
+    if config.downcast::<PyDict>().is_ok() {
+        // through JSON, which a dict of the section's keys turns into as TOML's would
+        let json = config
+            .py()
+            .import("json")?
+            .call_method1("dumps", (config,))?;
+        let json: String = json.extract()?;
+        return serde_json::from_str(&json).map_err(|err| invalid(err.to_string()));
+    }
+    Err(PyValueError::new_err(
+        "config is a dict of the [simulation] section's keys or the text of a config",
+    ))
+}
+
+/// The parameter called `name`, as in session logs and the web control page.
+fn param(name: &str) -> PyResult<Param> {
+    serde_json::from_value(name.into())
+        .map_err(|_| PyValueError::new_err(format!("unknown parameter {name:?}")))
+}
+
+#[pymodule]
+fn orbits(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<Simulation>()?;
+    // the defaults', so importing the module leaves the settings to be set by a config
+    module.add("ORBITAL_RADIUS", Settings::default().orbital_radius)?;
+    module.add("PARTICLES", Settings::default().particles)?;
+    let names: Vec<String> = Param::ALL
+        .iter()
+        .map(|param| {
+            serde_json::to_value(param)
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .into()
+        })
+        .collect();
+    module.add("PARAMS", names)?;
+    Ok(())
+}
@@ -0,0 +1,232 @@
+//! The piece drawn in a terminal, in braille dots or ASCII art, for showing it over SSH or checking
+//! in CI that the simulation runs without a GPU or a display.
+
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use nannou_core::color::{Hsl, Hsla, Srgb};
+use nannou_core::rand::random;
+use orbits_core::ascii;
+use orbits_core::params::Param;
+use orbits_core::plugins;
+use orbits_core::simulation::Simulation;
+use orbits_core::{orbital_radius, trails};
+use ratatui::backend::{Backend, TestBackend};
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::style::Color;
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::{Canvas, Circle, Line};
+use ratatui::{Frame, Terminal};
+
+const USAGE: &str =
+    "usage: trynannou-tui               show the piece in the terminal, r to reseed, a to switch
+                                   between braille and ASCII art, q to quit
+       trynannou-tui --frames <n>  simulate n frames and print the last, for smoke tests";
+
+/// Wall-clock seconds between frames.
+const FRAME_SECONDS: f32 = 1.0 / 30.0;
+/// Size of the terminal the last frame is printed at by `--frames`.
+const HEADLESS_SIZE: (u16, u16) = (80, 24);
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let result = plugins::load(Path::new(plugins::PLUGINS_DIR)).and_then(|_| {
+        match (args.next().as_deref(), args.next(), args.next()) {
+            (None, _, _) => run().map_err(|err| err.to_string()),
+            (Some("--frames"), Some(frames), None) => frames
+                .parse()
+                .map_err(|err| format!("bad frame count {frames:?}: {err}"))
+                .map(headless),
+            (Some("-h" | "--help"), None, _) => {
+                println!("{USAGE}");
+                Ok(())
+            }
+            _ => Err(USAGE.to_string()),
+        }
+    });
+    if let Err(err) = result {
+        eprintln!("error: {err}");
+        std::process::exit(1);
+    }
+}
+
+/// Show the piece in the terminal until q is pressed.
+fn run() -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_in(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+fn run_in(terminal: &mut Terminal<impl Backend>) -> io::Result<()> {
+    let mut sim = Simulation::new(random());
+    let mut last = Instant::now();
+    let mut ascii = false;
+    loop {
+        terminal.draw(|frame| render(&sim, ascii, frame))?;
+
+        let deadline = last + Duration::from_secs_f32(FRAME_SECONDS);
+        while event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('r') => sim.reseed(random()),
+                    KeyCode::Char('a') => ascii = !ascii,
+                    _ => {}
+                }
+            }
+        }
+
+        let now = Instant::now();
+        sim.step((now - last).as_secs_f32());
+        last = now;
+    }
+}
+
+/// Simulate `frames` frames at the full frame rate, as fast as possible, and print the last.
+fn headless(frames: usize) {
+    let mut sim = Simulation::new(random());
+    for _ in 0..frames {
+        sim.step(FRAME_SECONDS);
+    }
+
+    let (width, height) = HEADLESS_SIZE;
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    let frame = terminal.draw(|frame| render(&sim, false, frame)).unwrap();
+    for row in frame.buffer.content.chunks(width as usize) {
+        let line: String = row.iter().map(|cell| cell.symbol()).collect();
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Draw every generation's trails, each particle's as a line, the current generation on top, in
+/// braille dots or, with `ascii`, in the glyphs of ASCII art.
+fn render(sim: &Simulation, ascii: bool, frame: &mut Frame) {
+    let area = frame.area();
+    let top = sim.current();
+    let background = top.background;
+
+    // braille dots are about square, two across and four down each cell
+    let aspect = (area.width as f32 * 2.0) / (area.height as f32 * 4.0).max(1.0);
+    let half = orbital_radius() * 1.1;
+    let (x, y) = if aspect >= 1.0 {
+        (half * aspect, half)
+    } else {
+        (half, half / aspect)
+    };
+
+    let canvas = Canvas::default()
+        .marker(Marker::Braille)
+        .background_color(rgb(background))
+        .x_bounds([-x as f64, x as f64])
+        .y_bounds([-y as f64, y as f64])
+        .paint(|ctx| {
+            ctx.draw(&Circle {
+                x: 0.0,
+                y: 0.0,
+                radius: orbital_radius() as f64,
+                color: rgb(top.circle_color),
+            });
+
+            let highlight = sim.params.get(Param::Highlight);
+            let length = sim.params.get(Param::TrailLength);
+            let current = sim.generations.len() - 1;
+            for (i, generation) in sim.generations.iter().enumerate() {
+                let opacity = if i == current {
+                    1.0
+                } else {
+                    generation.opacity
+                };
+                let opacity = opacity * sim.params.get(Param::TrailOpacity);
+                for particle in 0..generation.particles.len() {
+                    let opacity = opacity * generation.saliency.alpha(particle, highlight);
+                    let trail = trails::trail(generation, particle, opacity, 0.0, length);
+                    // older segments first, so the newest take the cells they share
+                    for segment in trail.windows(2).rev() {
+                        let [(a, color), (b, _)] = segment else {
+                            unreachable!()
+                        };
+                        ctx.draw(&Line {
+                            x1: a.x as f64,
+                            y1: a.y as f64,
+                            x2: b.x as f64,
+                            y2: b.y as f64,
+                            color: over(*color, background),
+                        });
+                    }
+                }
+            }
+
+            // as in trynannou, a lone central body is left undrawn
+            let bodies = sim.bodies();
+            if bodies.len() >= 2 {
+                let total: f32 = bodies.iter().map(|body| body.gm).sum();
+                for body in &bodies {
+                    ctx.draw(&Circle {
+                        x: body.pos.x as f64,
+                        y: body.pos.y as f64,
+                        radius: 40.0 * (body.gm / total).sqrt() as f64,
+                        color: rgb(top.body_color),
+                    });
+                }
+            }
+        });
+    frame.render_widget(canvas, area);
+    if ascii {
+        to_glyphs(frame.buffer_mut());
+    }
+}
+
+/// Redraw each cell of braille dots in `buffer` as the glyph of `ascii::RAMP` as bright as the
+/// cell looks, as trynannou's ASCII art does with its tiles.
+fn to_glyphs(buffer: &mut Buffer) {
+    let ramp: Vec<char> = ascii::RAMP.chars().collect();
+    let luminance = |color: Color| match color {
+        Color::Rgb(r, g, b) => Some(ascii::luminance(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+        )),
+        _ => None,
+    };
+    for cell in &mut buffer.content {
+        let Some(dots @ '\u{2800}'..='\u{28ff}') = cell.symbol().chars().next() else {
+            continue;
+        };
+        let (Some(fg), Some(bg)) = (luminance(cell.fg), luminance(cell.bg)) else {
+            continue;
+        };
+        // of the eight dots a cell has, how many are lit
+        let coverage = (dots as u32 - 0x2800).count_ones() as f32 / 8.0;
+        let glyph = ascii::glyph(&ramp, bg + (fg - bg) * coverage);
+        cell.set_char(glyph);
+    }
+}
+
+/// `color` blended over `background`, there being no transparency in a terminal.
+fn over(color: Hsla, background: Hsl) -> Color {
+    let alpha = color.alpha.clamp(0.0, 1.0);
+    let color = Srgb::from(color.color);
+    let background = Srgb::from(background);
+    let mix = |c: f32, b: f32| ((b + (c - b) * alpha) * 255.0).round() as u8;
+    Color::Rgb(
+        mix(color.red, background.red),
+        mix(color.green, background.green),
+        mix(color.blue, background.blue),
+    )
+}
+
+fn rgb(color: Hsl) -> Color {
+    let color = Srgb::from(color);
+    let channel = |c: f32| (c * 255.0).round() as u8;
+    Color::Rgb(
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue),
+    )
+}